@@ -33,3 +33,55 @@ impl Decoder for BytesCodec {
         }
     }
 }
+
+/// Frozen bytes codec.
+///
+/// Same as `BytesCodec`, but decodes into `Bytes`. Decoded frames reference
+/// read buffer memory, no data get copied.
+#[derive(Debug, Copy, Clone)]
+pub struct FrozenBytesCodec;
+
+impl Encoder for FrozenBytesCodec {
+    type Item = Bytes;
+    type Error = io::Error;
+
+    #[inline]
+    fn encode(&self, item: Bytes, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        dst.extend_from_slice(&item[..]);
+        Ok(())
+    }
+}
+
+impl Decoder for FrozenBytesCodec {
+    type Item = Bytes;
+    type Error = io::Error;
+
+    fn decode(&self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        if src.is_empty() {
+            Ok(None)
+        } else {
+            let len = src.len();
+            Ok(Some(src.split_to(len).freeze()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frozen_bytes() {
+        let data = [b'x'; 128];
+        let mut buf = BytesMut::from(&data[..]);
+        let ptr = buf.as_ptr();
+        let item = FrozenBytesCodec.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(&item[..], &data[..]);
+        assert_eq!(item.as_ptr(), ptr);
+        assert!(FrozenBytesCodec.decode(&mut buf).unwrap().is_none());
+
+        let mut dst = BytesMut::new();
+        FrozenBytesCodec.encode(item, &mut dst).unwrap();
+        assert_eq!(&dst[..], &data[..]);
+    }
+}
@@ -0,0 +1,250 @@
+//! Adapters between tokio and futures io traits.
+//!
+//! ntex-codec uses tokio's `AsyncRead`/`AsyncWrite`, so tokio transports
+//! could be used directly. `Compat` wraps futures io object and implements
+//! tokio traits for it, or wraps tokio io object and implements futures traits.
+use std::{io, pin::Pin, task::Context, task::Poll};
+
+use futures_io::{AsyncRead as FuturesRead, AsyncWrite as FuturesWrite};
+
+use super::{AsyncRead, AsyncWrite, ReadBuf};
+
+pin_project_lite::pin_project! {
+    /// Compatibility adapter for tokio and futures io objects
+    #[derive(Debug)]
+    pub struct Compat<T> {
+        #[pin]
+        io: T,
+    }
+}
+
+impl<T> Compat<T> {
+    /// Wrap io object
+    pub fn new(io: T) -> Self {
+        Compat { io }
+    }
+
+    /// Get reference to inner io object
+    pub fn get_ref(&self) -> &T {
+        &self.io
+    }
+
+    /// Get mut reference to inner io object
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.io
+    }
+
+    /// Consume adapter, returns inner io object
+    pub fn into_inner(self) -> T {
+        self.io
+    }
+}
+
+/// Convert futures reader to tokio reader
+pub trait FuturesReadCompatExt: FuturesRead + Sized {
+    /// Wrap futures reader, result implements tokio io traits
+    ///
+    /// Result implements `AsyncWrite` as well, if reader is writable.
+    fn tokio_compat(self) -> Compat<Self> {
+        Compat::new(self)
+    }
+}
+
+impl<T: FuturesRead> FuturesReadCompatExt for T {}
+
+/// Convert futures writer to tokio writer
+pub trait FuturesWriteCompatExt: FuturesWrite + Sized {
+    /// Wrap futures writer, result implements tokio io traits
+    fn tokio_compat_write(self) -> Compat<Self> {
+        Compat::new(self)
+    }
+}
+
+impl<T: FuturesWrite> FuturesWriteCompatExt for T {}
+
+/// Convert tokio reader to futures reader
+pub trait TokioReadCompatExt: AsyncRead + Sized {
+    /// Wrap tokio reader, result implements futures io traits
+    ///
+    /// Result implements futures `AsyncWrite` as well, if reader is writable.
+    fn futures_compat(self) -> Compat<Self> {
+        Compat::new(self)
+    }
+}
+
+impl<T: AsyncRead> TokioReadCompatExt for T {}
+
+/// Convert tokio writer to futures writer
+pub trait TokioWriteCompatExt: AsyncWrite + Sized {
+    /// Wrap tokio writer, result implements futures io traits
+    fn futures_compat_write(self) -> Compat<Self> {
+        Compat::new(self)
+    }
+}
+
+impl<T: AsyncWrite> TokioWriteCompatExt for T {}
+
+impl<T: FuturesRead> AsyncRead for Compat<T> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let n = match self.project().io.poll_read(cx, buf.initialize_unfilled()) {
+            Poll::Ready(Ok(n)) => n,
+            Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+            Poll::Pending => return Poll::Pending,
+        };
+        buf.advance(n);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<T: FuturesWrite> AsyncWrite for Compat<T> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.project().io.poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        self.project().io.poll_write_vectored(cx, bufs)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.project().io.poll_flush(cx)
+    }
+
+    fn poll_shutdown(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        self.project().io.poll_close(cx)
+    }
+}
+
+impl<T: AsyncRead> FuturesRead for Compat<T> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut buf = ReadBuf::new(buf);
+        match self.project().io.poll_read(cx, &mut buf) {
+            Poll::Ready(Ok(())) => Poll::Ready(Ok(buf.filled().len())),
+            Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<T: AsyncWrite> FuturesWrite for Compat<T> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.project().io.poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        self.project().io.poll_write_vectored(cx, bufs)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.project().io.poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.project().io.poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::io::{AsyncReadExt, AsyncWriteExt, Cursor};
+    use futures::{future::poll_fn, SinkExt, StreamExt};
+    use ntex::testing::Io;
+    use ntex_bytes::{Bytes, BytesMut};
+
+    use super::*;
+    use crate::{BytesCodec, Framed};
+
+    #[ntex::test]
+    async fn test_futures_io() {
+        let io = Cursor::new(b"data".to_vec()).tokio_compat();
+        let mut server = Framed::new(io, BytesCodec);
+
+        let item = server.next().await.unwrap().unwrap();
+        assert_eq!(item, BytesMut::from(&b"data"[..]));
+        server.send(Bytes::from_static(b"-test")).await.unwrap();
+        assert_eq!(server.get_ref().get_ref().get_ref(), b"data-test");
+
+        let mut io = Cursor::new(Vec::new()).tokio_compat();
+        let mut buf = BytesMut::with_capacity(16);
+        let n = poll_fn(|cx| crate::poll_read_buf(Pin::new(&mut io), cx, &mut buf))
+            .await
+            .unwrap();
+        assert_eq!(n, 0);
+    }
+
+    #[ntex::test]
+    async fn test_tokio_io() {
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(1024);
+        client.write(b"data");
+
+        let mut server = server.futures_compat();
+        let mut buf = [0; 16];
+        let n = server.read(&mut buf).await.unwrap();
+        assert_eq!(&buf[..n], b"data");
+
+        server.write_all(b"test").await.unwrap();
+        server.flush().await.unwrap();
+        assert_eq!(client.read().await.unwrap(), Bytes::from_static(b"test"));
+
+        server.close().await.unwrap();
+        let _ = server.into_inner();
+    }
+
+    #[ntex::test]
+    async fn test_one_direction() {
+        // futures reader only
+        let mut io = futures::io::repeat(b'a').take(4).tokio_compat();
+        let mut buf = BytesMut::with_capacity(16);
+        let n = poll_fn(|cx| crate::poll_read_buf(Pin::new(&mut io), cx, &mut buf))
+            .await
+            .unwrap();
+        assert_eq!(n, 4);
+        assert_eq!(&buf[..], b"aaaa");
+
+        // futures writer only
+        let mut io = futures::io::sink().tokio_compat_write();
+        let n = poll_fn(|cx| AsyncWrite::poll_write(Pin::new(&mut io), cx, b"data"))
+            .await
+            .unwrap();
+        assert_eq!(n, 4);
+
+        // tokio reader only
+        let mut io = (&b"data"[..]).futures_compat();
+        let mut buf = [0; 16];
+        let n = io.read(&mut buf).await.unwrap();
+        assert_eq!(&buf[..n], b"data");
+
+        // tokio writer only
+        let mut io = Vec::new().futures_compat_write();
+        io.write_all(b"test").await.unwrap();
+        io.flush().await.unwrap();
+        assert_eq!(io.into_inner(), b"test");
+    }
+}
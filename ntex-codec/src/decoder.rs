@@ -19,16 +19,25 @@ pub trait Decoder {
     /// A default method available to be called when there are no more bytes
     /// available to be read from the underlying I/O.
     ///
-    /// This method defaults to calling `decode` and returns an error if
-    /// `Ok(None)` is returned while there is unconsumed data in `buf`.
-    /// Typically this doesn't need to be implemented unless the framing
-    /// protocol differs near the end of the stream.
+    /// This method defaults to calling `decode`. Codec could override it
+    /// to emit a final frame from unconsumed data in `buf` or to report
+    /// a truncated frame error. Typically this doesn't need to be implemented
+    /// unless the framing protocol differs near the end of the stream.
     fn decode_eof(&self, buf: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
         match self.decode(buf)? {
             Some(frame) => Ok(Some(frame)),
             None => Ok(None),
         }
     }
+
+    /// Minimum number of additional bytes required to decode next frame.
+    ///
+    /// Called after `decode` returned `Ok(None)`. Codec with large frames
+    /// could use it to avoid tiny reads and repeated decode attempts.
+    /// Default implementation returns `0`, which means unknown.
+    fn decode_hint(&self, _src: &BytesMut) -> usize {
+        0
+    }
 }
 
 impl<T> Decoder for Rc<T>
@@ -45,4 +54,8 @@ where
     fn decode_eof(&self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
         (**self).decode_eof(src)
     }
+
+    fn decode_hint(&self, src: &BytesMut) -> usize {
+        (**self).decode_hint(src)
+    }
 }
@@ -1,5 +1,8 @@
-use ntex_bytes::BytesMut;
-use std::rc::Rc;
+use ntex_bytes::{Bytes, BytesMut};
+use std::{collections::VecDeque, rc::Rc};
+
+/// Chunks smaller than this size are copied to write buffer
+const MIN_CHUNK_SIZE: usize = 1024;
 
 /// Trait of helper objects to write out messages as bytes.
 pub trait Encoder {
@@ -11,6 +14,18 @@ pub trait Encoder {
 
     /// Encodes a frame into the buffer provided.
     fn encode(&self, item: Self::Item, dst: &mut BytesMut) -> Result<(), Self::Error>;
+
+    /// Encodes a frame into the vectored buffer.
+    ///
+    /// Codec could pass large payloads as separate chunks without copying
+    /// them into write buffer. By default frame is encoded with `encode()`.
+    fn encode_vectored(
+        &self,
+        item: Self::Item,
+        dst: &mut VectoredBuf<'_>,
+    ) -> Result<(), Self::Error> {
+        self.encode(item, dst.buf())
+    }
 }
 
 impl<T> Encoder for Rc<T>
@@ -23,4 +38,80 @@ where
     fn encode(&self, item: Self::Item, dst: &mut BytesMut) -> Result<(), Self::Error> {
         (**self).encode(item, dst)
     }
+
+    fn encode_vectored(
+        &self,
+        item: Self::Item,
+        dst: &mut VectoredBuf<'_>,
+    ) -> Result<(), Self::Error> {
+        (**self).encode_vectored(item, dst)
+    }
+}
+
+/// Write buffer with separate data chunks.
+///
+/// Queued chunks precede write buffer content.
+pub struct VectoredBuf<'a> {
+    buf: &'a mut BytesMut,
+    chunks: &'a mut VecDeque<Bytes>,
+}
+
+impl<'a> VectoredBuf<'a> {
+    /// Create vectored buffer
+    pub fn new(buf: &'a mut BytesMut, chunks: &'a mut VecDeque<Bytes>) -> Self {
+        VectoredBuf { buf, chunks }
+    }
+
+    /// Get mut access to write buffer
+    pub fn buf(&mut self) -> &mut BytesMut {
+        self.buf
+    }
+
+    /// Add data chunk
+    ///
+    /// Small chunks are copied to write buffer.
+    pub fn put_chunk(&mut self, data: Bytes) {
+        if data.len() < MIN_CHUNK_SIZE {
+            self.buf.extend_from_slice(&data);
+        } else {
+            if !self.buf.is_empty() {
+                self.chunks.push_back(self.buf.split().freeze());
+            }
+            self.chunks.push_back(data);
+        }
+    }
+
+    /// Total size of buffered data
+    pub fn len(&self) -> usize {
+        self.buf.len() + self.chunks.iter().map(|c| c.len()).sum::<usize>()
+    }
+
+    /// Check if there is no buffered data
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty() && self.chunks.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vectored_buf() {
+        let mut buf = BytesMut::new();
+        let mut chunks = VecDeque::new();
+        let mut dst = VectoredBuf::new(&mut buf, &mut chunks);
+        assert!(dst.is_empty());
+
+        dst.buf().extend_from_slice(b"head");
+        dst.put_chunk(Bytes::from_static(b"small"));
+        dst.put_chunk(Bytes::from(vec![b'x'; MIN_CHUNK_SIZE]));
+        dst.buf().extend_from_slice(b"tail");
+        assert_eq!(dst.len(), MIN_CHUNK_SIZE + 13);
+
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0], Bytes::from_static(b"headsmall"));
+        assert_eq!(chunks[1].len(), MIN_CHUNK_SIZE);
+        assert_eq!(&buf[..], b"tail");
+    }
 }
@@ -3,7 +3,7 @@ use std::task::{Context, Poll};
 use std::{fmt, io};
 
 use ntex_bytes::{Buf, BytesMut};
-use ntex_util::{future::Either, ready, Sink, Stream};
+use ntex_util::{future::poll_fn, future::Either, ready, Sink, Stream};
 
 use crate::{AsyncRead, AsyncWrite, Decoder, Encoder};
 
@@ -61,10 +61,16 @@ impl<T, U> Framed<T, U> {
     #[inline]
     /// Construct `Framed` object `parts`.
     pub fn from_parts(parts: FramedParts<T, U>) -> Framed<T, U> {
+        // decode buffered data before reading from io
+        let mut flags = parts.flags;
+        if !parts.read_buf.is_empty() {
+            flags.insert(Flags::READABLE);
+        }
+
         Framed {
             io: parts.io,
             codec: parts.codec,
-            flags: parts.flags,
+            flags,
             write_buf: parts.write_buf,
             read_buf: parts.read_buf,
             err: parts.err,
@@ -226,6 +232,70 @@ where
         self.write_buf.len() < HW
     }
 
+    /// Flush write buffer until framed is able to write more data.
+    pub fn poll_write_ready(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), io::Error>> {
+        if self.is_write_ready() {
+            return Poll::Ready(Ok(()));
+        }
+        match self.flush(cx)? {
+            Poll::Ready(()) => Poll::Ready(Ok(())),
+            Poll::Pending if self.is_write_ready() => Poll::Ready(Ok(())),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+
+    /// Serialize item to the write buffer without flushing.
+    ///
+    /// Waits until write buffer size drops below high watermark.
+    pub async fn feed(
+        &mut self,
+        item: <U as Encoder>::Item,
+    ) -> Result<(), Either<U::Error, io::Error>> {
+        poll_fn(|cx| self.poll_write_ready(cx))
+            .await
+            .map_err(Either::Right)?;
+        self.write(item).map_err(Either::Left)
+    }
+
+    /// Serialize all items from the stream and flush write buffer.
+    ///
+    /// Buffered data is flushed while stream is not ready.
+    pub async fn send_all<S>(
+        &mut self,
+        stream: &mut S,
+    ) -> Result<(), Either<U::Error, io::Error>>
+    where
+        S: Stream<Item = <U as Encoder>::Item> + Unpin,
+    {
+        // stream must not be polled after it is terminated
+        let mut done = false;
+
+        poll_fn(|cx| loop {
+            if done {
+                return self.flush(cx).map_err(Either::Right);
+            }
+            ready!(self.poll_write_ready(cx)).map_err(Either::Right)?;
+
+            match Pin::new(&mut *stream).poll_next(cx) {
+                Poll::Ready(Some(item)) => self.write(item).map_err(Either::Left)?,
+                Poll::Ready(None) => {
+                    done = true;
+                    return self.flush(cx).map_err(Either::Right);
+                }
+                Poll::Pending => {
+                    if let Poll::Ready(Err(e)) = self.flush(cx) {
+                        return Poll::Ready(Err(Either::Right(e)));
+                    }
+                    return Poll::Pending;
+                }
+            }
+        })
+        .await
+    }
+
     /// Flush write buffer to underlying I/O stream.
     pub fn flush(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
         log::trace!("flushing framed transport");
@@ -453,14 +523,10 @@ where
 
     #[inline]
     fn poll_ready(
-        self: Pin<&mut Self>,
-        _: &mut Context<'_>,
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
     ) -> Poll<Result<(), Self::Error>> {
-        if self.is_write_ready() {
-            Poll::Ready(Ok(()))
-        } else {
-            Poll::Pending
-        }
+        self.poll_write_ready(cx).map_err(Either::Right)
     }
 
     #[inline]
@@ -546,6 +612,40 @@ impl<T, U> FramedParts<T, U> {
             write_buf: BytesMut::new(),
         }
     }
+
+    /// Consume the `FramedParts`, returning `FramedParts` with different io.
+    ///
+    /// Read and write buffers are preserved.
+    pub fn map_io<F, T2>(self, f: F) -> FramedParts<T2, U>
+    where
+        F: Fn(T) -> T2,
+    {
+        FramedParts {
+            io: f(self.io),
+            codec: self.codec,
+            flags: self.flags,
+            read_buf: self.read_buf,
+            write_buf: self.write_buf,
+            err: self.err,
+        }
+    }
+
+    /// Consume the `FramedParts`, returning `FramedParts` with different codec.
+    ///
+    /// Read and write buffers are preserved.
+    pub fn map_codec<F, U2>(self, f: F) -> FramedParts<T, U2>
+    where
+        F: Fn(U) -> U2,
+    {
+        FramedParts {
+            io: self.io,
+            codec: f(self.codec),
+            flags: self.flags,
+            read_buf: self.read_buf,
+            write_buf: self.write_buf,
+            err: self.err,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -570,6 +670,53 @@ mod tests {
         assert!(format!("{:?}", server).contains("Framed"));
     }
 
+    #[ntex::test]
+    async fn test_parts() {
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(1024);
+        let mut server = Framed::new(server, BytesCodec);
+        server.read_buf().extend_from_slice(b"read ahead");
+        server.write_buf().extend_from_slice(b"pending");
+
+        let parts = server
+            .into_parts()
+            .map_codec(|_| crate::LinesCodec::<String>::new())
+            .map_io(|io| io);
+        assert_eq!(&parts.read_buf[..], b"read ahead");
+        assert_eq!(&parts.write_buf[..], b"pending");
+
+        let mut server = Framed::from_parts(parts);
+        assert_eq!(server.read_buf(), b"read ahead".as_ref());
+        assert!(lazy(|cx| Pin::new(&mut server).poll_flush(cx))
+            .await
+            .is_ready());
+        assert_eq!(client.read_any(), b"pending".as_ref());
+    }
+
+    #[ntex::test]
+    async fn test_parts_read_buf() {
+        let (_client, server) = Io::create();
+        let parts = FramedParts::with_read_buf(
+            server,
+            crate::LinesCodec::<String>::new(),
+            BytesMut::from(&b"line1\nline2\n"[..]),
+        );
+
+        // buffered frames are decoded without io reads
+        let mut server = Framed::from_parts(parts);
+        match lazy(|cx| Pin::new(&mut server).next_item(cx)).await {
+            Poll::Ready(Some(Ok(item))) => assert_eq!(item, "line1"),
+            _ => panic!(),
+        }
+        match lazy(|cx| Pin::new(&mut server).next_item(cx)).await {
+            Poll::Ready(Some(Ok(item))) => assert_eq!(item, "line2"),
+            _ => panic!(),
+        }
+        assert!(lazy(|cx| Pin::new(&mut server).next_item(cx))
+            .await
+            .is_pending());
+    }
+
     #[ntex::test]
     async fn test_sink() {
         let (client, server) = Io::create();
@@ -601,6 +748,74 @@ mod tests {
         assert!(client.is_closed());
     }
 
+    #[ntex::test]
+    async fn test_feed_send_all() {
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(1024);
+        let mut server = Framed::new(server, BytesCodec);
+
+        server.feed(Bytes::from_static(b"feed")).await.unwrap();
+        assert_eq!(server.write_buf(), b"feed".as_ref());
+        assert_eq!(client.read_any(), b"".as_ref());
+
+        let data = Bytes::from(vec![b'a'; 4096]);
+        let mut stream = futures::stream::iter(vec![data.clone(); 4]);
+        client.remote_buffer_cap(3 * 4096);
+        let fut = server.send_all(&mut stream);
+        let res = ntex::rt::spawn(async move {
+            ntex::rt::time::sleep(std::time::Duration::from_millis(50)).await;
+            let buf = client.read_any();
+            client.remote_buffer_cap(1024 * 1024);
+            (client, buf)
+        });
+        fut.await.unwrap();
+        assert!(server.is_write_buf_empty());
+
+        let (client, buf) = res.await.unwrap();
+        assert_eq!(buf.len(), 3 * 4096);
+        assert_eq!(&buf[..4], b"feed");
+        assert_eq!(buf.len() + client.read_any().len(), 4 * 4096 + 4);
+    }
+
+    #[ntex::test]
+    async fn test_send_all_pending_flush() {
+        struct Once(Option<Bytes>, bool);
+
+        impl Stream for Once {
+            type Item = Bytes;
+
+            fn poll_next(
+                mut self: Pin<&mut Self>,
+                _: &mut Context<'_>,
+            ) -> Poll<Option<Bytes>> {
+                assert!(!self.1, "stream is polled after termination");
+                let item = self.0.take();
+                if item.is_none() {
+                    self.1 = true;
+                }
+                Poll::Ready(item)
+            }
+        }
+
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(3);
+        let mut server = Framed::new(server, BytesCodec);
+
+        let mut stream = Once(Some(Bytes::from_static(b"GET /test")), false);
+        let res = ntex::rt::spawn(async move {
+            ntex::rt::time::sleep(std::time::Duration::from_millis(50)).await;
+            let buf = client.read_any();
+            client.remote_buffer_cap(1024);
+            (client, buf)
+        });
+        server.send_all(&mut stream).await.unwrap();
+        assert!(server.is_write_buf_empty());
+
+        let (client, buf) = res.await.unwrap();
+        assert_eq!(buf, b"GET".as_ref());
+        assert_eq!(client.read_any(), b" /test".as_ref());
+    }
+
     #[ntex::test]
     async fn test_write_pending() {
         let (client, server) = Io::create();
@@ -0,0 +1,192 @@
+use ntex_bytes::{BufMut, Bytes, BytesMut};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{fmt, marker::PhantomData};
+
+use super::lines::DEFAULT_MAX_LENGTH;
+use super::{Decoder, Encoder, LinesCodec, LinesCodecError};
+
+/// JSON lines codec.
+///
+/// Each item is serialized as single line json document
+/// terminated with `\n`.
+pub struct JsonLinesCodec<T> {
+    lines: LinesCodec<Bytes>,
+    _t: PhantomData<T>,
+}
+
+/// JSON lines codec error
+#[derive(Debug)]
+pub enum JsonLinesCodecError {
+    /// Lines codec error
+    Lines(LinesCodecError),
+    /// Json serialization error
+    Json(serde_json::Error),
+}
+
+impl From<LinesCodecError> for JsonLinesCodecError {
+    fn from(err: LinesCodecError) -> Self {
+        JsonLinesCodecError::Lines(err)
+    }
+}
+
+impl From<serde_json::Error> for JsonLinesCodecError {
+    fn from(err: serde_json::Error) -> Self {
+        JsonLinesCodecError::Json(err)
+    }
+}
+
+impl fmt::Display for JsonLinesCodecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonLinesCodecError::Lines(e) => e.fmt(f),
+            JsonLinesCodecError::Json(e) => write!(f, "Json error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for JsonLinesCodecError {}
+
+impl<T> JsonLinesCodec<T> {
+    /// Create json lines codec with default max line length of 64Kb
+    pub fn new() -> Self {
+        Self::with_max_length(DEFAULT_MAX_LENGTH)
+    }
+
+    /// Create json lines codec with max line length
+    pub fn with_max_length(max_length: usize) -> Self {
+        JsonLinesCodec {
+            lines: LinesCodec::with_max_length(max_length),
+            _t: PhantomData,
+        }
+    }
+
+    /// Get max line length
+    pub fn max_length(&self) -> usize {
+        self.lines.max_length()
+    }
+}
+
+impl<T> Default for JsonLinesCodec<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Clone for JsonLinesCodec<T> {
+    fn clone(&self) -> Self {
+        Self::with_max_length(self.max_length())
+    }
+}
+
+impl<T> fmt::Debug for JsonLinesCodec<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JsonLinesCodec")
+            .field("max_length", &self.max_length())
+            .finish()
+    }
+}
+
+impl<T: Serialize> Encoder for JsonLinesCodec<T> {
+    type Item = T;
+    type Error = JsonLinesCodecError;
+
+    fn encode(&self, item: T, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        let len = dst.len();
+        if let Err(e) = serde_json::to_writer((&mut *dst).writer(), &item) {
+            dst.truncate(len);
+            return Err(e.into());
+        }
+        if dst.len() - len > self.max_length() {
+            dst.truncate(len);
+            return Err(LinesCodecError::MaxLineLengthExceeded.into());
+        }
+        dst.extend_from_slice(b"\n");
+        Ok(())
+    }
+}
+
+impl<T: DeserializeOwned> Decoder for JsonLinesCodec<T> {
+    type Item = T;
+    type Error = JsonLinesCodecError;
+
+    fn decode(&self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        match self.lines.decode(src)? {
+            Some(line) => Ok(Some(serde_json::from_slice(&line)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn decode_eof(&self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        match self.lines.decode_eof(src)? {
+            Some(line) => Ok(Some(serde_json::from_slice(&line)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Msg {
+        id: u32,
+        name: String,
+    }
+
+    #[test]
+    fn test_json_lines() {
+        let codec = JsonLinesCodec::<Msg>::new();
+
+        let mut buf = BytesMut::new();
+        codec
+            .encode(
+                Msg {
+                    id: 1,
+                    name: "test".to_string(),
+                },
+                &mut buf,
+            )
+            .unwrap();
+        assert_eq!(&buf[..], b"{\"id\":1,\"name\":\"test\"}\n");
+
+        buf.extend_from_slice(b"{\"id\":2,\"name\":\"\"}\r\n{\"id\":3");
+        assert_eq!(codec.decode(&mut buf).unwrap().unwrap().id, 1);
+        assert_eq!(codec.decode(&mut buf).unwrap().unwrap().id, 2);
+        assert!(codec.decode(&mut buf).unwrap().is_none());
+        assert!(matches!(
+            codec.decode_eof(&mut buf),
+            Err(JsonLinesCodecError::Json(_))
+        ));
+
+        let mut buf = BytesMut::from(&b"not json\n"[..]);
+        assert!(matches!(
+            codec.decode(&mut buf),
+            Err(JsonLinesCodecError::Json(_))
+        ));
+    }
+
+    #[test]
+    fn test_max_length() {
+        let codec = JsonLinesCodec::<Msg>::with_max_length(16);
+        assert_eq!(codec.max_length(), 16);
+
+        let mut buf = BytesMut::new();
+        let res = codec.encode(
+            Msg {
+                id: 1,
+                name: "long name".to_string(),
+            },
+            &mut buf,
+        );
+        assert!(matches!(
+            res,
+            Err(JsonLinesCodecError::Lines(
+                LinesCodecError::MaxLineLengthExceeded
+            ))
+        ));
+        assert!(buf.is_empty());
+    }
+}
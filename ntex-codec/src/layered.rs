@@ -0,0 +1,176 @@
+use ntex_bytes::BytesMut;
+use ntex_util::future::Either;
+use std::{cell::RefCell, fmt};
+
+use super::{Decoder, Encoder};
+
+/// Layered codec.
+///
+/// Stacks two codecs, outer codec transforms raw bytes (compression,
+/// encryption, etc) and inner codec does framing. Items decoded by outer
+/// codec are fed to inner decoder, frames encoded by inner encoder are
+/// passed to outer encoder.
+pub struct LayeredCodec<O, I> {
+    outer: O,
+    inner: I,
+    buf: RefCell<BytesMut>,
+}
+
+impl<O, I> LayeredCodec<O, I> {
+    /// Create layered codec
+    pub fn new(outer: O, inner: I) -> Self {
+        LayeredCodec {
+            outer,
+            inner,
+            buf: RefCell::new(BytesMut::new()),
+        }
+    }
+
+    /// Get reference to outer codec
+    pub fn outer(&self) -> &O {
+        &self.outer
+    }
+
+    /// Get reference to inner codec
+    pub fn inner(&self) -> &I {
+        &self.inner
+    }
+
+    /// Consume layered codec, returns outer and inner codecs
+    pub fn into_inner(self) -> (O, I) {
+        (self.outer, self.inner)
+    }
+}
+
+impl<O: fmt::Debug, I: fmt::Debug> fmt::Debug for LayeredCodec<O, I> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LayeredCodec")
+            .field("outer", &self.outer)
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+impl<O, I> Encoder for LayeredCodec<O, I>
+where
+    O: Encoder,
+    O::Item: From<BytesMut>,
+    I: Encoder,
+{
+    type Item = I::Item;
+    type Error = Either<O::Error, I::Error>;
+
+    fn encode(&self, item: I::Item, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        let mut buf = BytesMut::new();
+        self.inner.encode(item, &mut buf).map_err(Either::Right)?;
+        self.outer
+            .encode(O::Item::from(buf), dst)
+            .map_err(Either::Left)
+    }
+}
+
+impl<O, I> Decoder for LayeredCodec<O, I>
+where
+    O: Decoder,
+    O::Item: AsRef<[u8]>,
+    I: Decoder,
+{
+    type Item = I::Item;
+    type Error = Either<O::Error, I::Error>;
+
+    fn decode(&self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        let mut buf = self.buf.borrow_mut();
+        loop {
+            if let Some(item) = self.inner.decode(&mut buf).map_err(Either::Right)? {
+                return Ok(Some(item));
+            }
+            match self.outer.decode(src).map_err(Either::Left)? {
+                Some(chunk) => buf.extend_from_slice(chunk.as_ref()),
+                None => return Ok(None),
+            }
+        }
+    }
+
+    fn decode_eof(&self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        if let Some(item) = self.decode(src)? {
+            return Ok(Some(item));
+        }
+        let mut buf = self.buf.borrow_mut();
+        if let Some(chunk) = self.outer.decode_eof(src).map_err(Either::Left)? {
+            buf.extend_from_slice(chunk.as_ref());
+        }
+        self.inner.decode_eof(&mut buf).map_err(Either::Right)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ntex_bytes::Bytes;
+    use std::io;
+
+    use super::*;
+    use crate::LinesCodec;
+
+    /// xor all bytes
+    struct XorCodec;
+
+    impl Encoder for XorCodec {
+        type Item = Bytes;
+        type Error = io::Error;
+
+        fn encode(&self, item: Bytes, dst: &mut BytesMut) -> Result<(), Self::Error> {
+            dst.extend(item.iter().map(|b| b ^ 0xff));
+            Ok(())
+        }
+    }
+
+    impl Decoder for XorCodec {
+        type Item = BytesMut;
+        type Error = io::Error;
+
+        fn decode(&self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+            if src.is_empty() {
+                Ok(None)
+            } else {
+                let len = src.len();
+                let mut buf = src.split_to(len);
+                buf.iter_mut().for_each(|b| *b ^= 0xff);
+                Ok(Some(buf))
+            }
+        }
+    }
+
+    fn xor(data: &[u8]) -> BytesMut {
+        data.iter().map(|b| b ^ 0xff).collect::<Vec<_>>()[..].into()
+    }
+
+    #[test]
+    fn test_layered() {
+        let codec = LayeredCodec::new(XorCodec, LinesCodec::<String>::new());
+
+        let mut dst = BytesMut::new();
+        codec.encode("line".to_string(), &mut dst).unwrap();
+        assert_eq!(dst, xor(b"line\n"));
+
+        let mut src = xor(b"line 1\nline");
+        assert_eq!(codec.decode(&mut src).unwrap().unwrap(), "line 1");
+        assert!(codec.decode(&mut src).unwrap().is_none());
+        assert!(src.is_empty());
+
+        src.extend_from_slice(&xor(b" 2\nline 3\n"));
+        assert_eq!(codec.decode(&mut src).unwrap().unwrap(), "line 2");
+        assert_eq!(codec.decode(&mut src).unwrap().unwrap(), "line 3");
+        assert!(codec.decode(&mut src).unwrap().is_none());
+
+        src.extend_from_slice(&xor(b"line 4"));
+        assert_eq!(codec.decode_eof(&mut src).unwrap().unwrap(), "line 4");
+        assert!(codec.decode_eof(&mut src).unwrap().is_none());
+
+        let mut src = xor(b"\xff\n");
+        assert!(matches!(codec.decode(&mut src), Err(Either::Right(_))));
+
+        assert!(format!("{:?}", LayeredCodec::new(1, 2)).contains("LayeredCodec"));
+        let (outer, _) = codec.into_inner();
+        let _: XorCodec = outer;
+    }
+}
@@ -13,13 +13,33 @@ mod bcodec;
 mod decoder;
 mod encoder;
 mod framed;
+mod layered;
+mod lines;
+mod netstring;
+mod text;
 
-pub use self::bcodec::BytesCodec;
+#[cfg(feature = "compat")]
+pub mod compat;
+#[cfg(feature = "json")]
+mod json;
+#[cfg(feature = "msgpack")]
+mod msgpack;
+
+pub use self::bcodec::{BytesCodec, FrozenBytesCodec};
 pub use self::decoder::Decoder;
-pub use self::encoder::Encoder;
+pub use self::encoder::{Encoder, VectoredBuf};
 pub use self::framed::{Framed, FramedParts};
+pub use self::layered::LayeredCodec;
+pub use self::lines::{LinesCodec, LinesCodecError};
+pub use self::netstring::{NetstringCodec, NetstringCodecError};
+pub use self::text::TextCodec;
+
+#[cfg(feature = "json")]
+pub use self::json::{JsonLinesCodec, JsonLinesCodecError};
+#[cfg(feature = "msgpack")]
+pub use self::msgpack::{MsgPackCodec, MsgPackCodecError};
 
-pub use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+pub use tokio::io::{AsyncRead, AsyncSeek, AsyncWrite, ReadBuf};
 
 use ntex_bytes::{BufMut, BytesMut};
 
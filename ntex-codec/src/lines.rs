@@ -0,0 +1,284 @@
+use ntex_bytes::{Bytes, BytesMut};
+use std::{cell::Cell, fmt, marker::PhantomData, str};
+
+use super::{Decoder, Encoder};
+
+/// Default max line length
+pub(crate) const DEFAULT_MAX_LENGTH: usize = 64 * 1024;
+
+/// Lines codec.
+///
+/// Splits stream on `\n` or `\r\n`, line terminator is not included
+/// into decoded item. Decoded item is either `String` or `Bytes`.
+pub struct LinesCodec<T = String> {
+    max_length: usize,
+    // index of next byte to check for line terminator
+    next_index: Cell<usize>,
+    _t: PhantomData<T>,
+}
+
+/// Lines codec error
+#[derive(Debug)]
+pub enum LinesCodecError {
+    /// Line length exceeds max line length
+    MaxLineLengthExceeded,
+    /// Line is not valid utf-8 string
+    InvalidUtf8(str::Utf8Error),
+}
+
+impl fmt::Display for LinesCodecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LinesCodecError::MaxLineLengthExceeded => {
+                write!(f, "Max line length exceeded")
+            }
+            LinesCodecError::InvalidUtf8(e) => write!(f, "Invalid utf-8 line: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for LinesCodecError {}
+
+impl<T> LinesCodec<T> {
+    /// Create lines codec with default max line length of 64Kb
+    pub fn new() -> Self {
+        Self::with_max_length(DEFAULT_MAX_LENGTH)
+    }
+
+    /// Create lines codec with max line length
+    ///
+    /// Max length does not include line terminator.
+    pub fn with_max_length(max_length: usize) -> Self {
+        LinesCodec {
+            max_length,
+            next_index: Cell::new(0),
+            _t: PhantomData,
+        }
+    }
+
+    /// Get max line length
+    pub fn max_length(&self) -> usize {
+        self.max_length
+    }
+
+    fn decode_line(
+        &self,
+        src: &mut BytesMut,
+    ) -> Result<Option<BytesMut>, LinesCodecError> {
+        // do not rescan bytes checked by previous call
+        let start = self.next_index.get().min(src.len());
+        if let Some(pos) = src[start..].iter().position(|b| *b == b'\n') {
+            let pos = start + pos;
+            self.next_index.set(0);
+            let mut line = src.split_to(pos + 1);
+            line.truncate(pos);
+            if line.ends_with(b"\r") {
+                line.truncate(pos - 1);
+            }
+            self.check_length(line)
+        } else if src.len() > self.max_length.saturating_add(1) {
+            // line terminator could be `\r\n`
+            self.next_index.set(0);
+            Err(LinesCodecError::MaxLineLengthExceeded)
+        } else {
+            self.next_index.set(src.len());
+            Ok(None)
+        }
+    }
+
+    fn decode_line_eof(
+        &self,
+        src: &mut BytesMut,
+    ) -> Result<Option<BytesMut>, LinesCodecError> {
+        match self.decode_line(src)? {
+            Some(line) => Ok(Some(line)),
+            None if src.is_empty() => Ok(None),
+            None => {
+                let len = src.len();
+                self.next_index.set(0);
+                self.check_length(src.split_to(len))
+            }
+        }
+    }
+
+    fn check_length(&self, line: BytesMut) -> Result<Option<BytesMut>, LinesCodecError> {
+        if line.len() > self.max_length {
+            Err(LinesCodecError::MaxLineLengthExceeded)
+        } else {
+            Ok(Some(line))
+        }
+    }
+}
+
+impl<T> Default for LinesCodec<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Clone for LinesCodec<T> {
+    fn clone(&self) -> Self {
+        Self::with_max_length(self.max_length)
+    }
+}
+
+impl<T> fmt::Debug for LinesCodec<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LinesCodec")
+            .field("max_length", &self.max_length)
+            .finish()
+    }
+}
+
+fn to_string(line: BytesMut) -> Result<String, LinesCodecError> {
+    str::from_utf8(&line)
+        .map(|s| s.to_string())
+        .map_err(LinesCodecError::InvalidUtf8)
+}
+
+impl Encoder for LinesCodec<String> {
+    type Item = String;
+    type Error = LinesCodecError;
+
+    #[inline]
+    fn encode(&self, item: String, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        dst.reserve(item.len() + 1);
+        dst.extend_from_slice(item.as_bytes());
+        dst.extend_from_slice(b"\n");
+        Ok(())
+    }
+}
+
+impl Decoder for LinesCodec<String> {
+    type Item = String;
+    type Error = LinesCodecError;
+
+    fn decode(&self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        self.decode_line(src)?.map(to_string).transpose()
+    }
+
+    fn decode_eof(&self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        self.decode_line_eof(src)?.map(to_string).transpose()
+    }
+}
+
+impl Encoder for LinesCodec<Bytes> {
+    type Item = Bytes;
+    type Error = LinesCodecError;
+
+    #[inline]
+    fn encode(&self, item: Bytes, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        dst.reserve(item.len() + 1);
+        dst.extend_from_slice(&item[..]);
+        dst.extend_from_slice(b"\n");
+        Ok(())
+    }
+}
+
+impl Decoder for LinesCodec<Bytes> {
+    type Item = Bytes;
+    type Error = LinesCodecError;
+
+    fn decode(&self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        Ok(self.decode_line(src)?.map(|line| line.freeze()))
+    }
+
+    fn decode_eof(&self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        Ok(self.decode_line_eof(src)?.map(|line| line.freeze()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_string() {
+        let codec = LinesCodec::<String>::new();
+        let mut buf = BytesMut::from(&b"line 1\nline 2\r\nline"[..]);
+
+        assert_eq!(codec.decode(&mut buf).unwrap().unwrap(), "line 1");
+        assert_eq!(codec.decode(&mut buf).unwrap().unwrap(), "line 2");
+        assert!(codec.decode(&mut buf).unwrap().is_none());
+        assert_eq!(codec.decode_eof(&mut buf).unwrap().unwrap(), "line");
+        assert!(codec.decode_eof(&mut buf).unwrap().is_none());
+
+        let mut buf = BytesMut::from(&b"\xff\n"[..]);
+        assert!(matches!(
+            codec.decode(&mut buf),
+            Err(LinesCodecError::InvalidUtf8(_))
+        ));
+
+        let mut dst = BytesMut::new();
+        codec.encode("line".to_string(), &mut dst).unwrap();
+        assert_eq!(&dst[..], b"line\n");
+    }
+
+    #[test]
+    fn test_decode_bytes() {
+        let codec = LinesCodec::<Bytes>::new();
+        let mut buf = BytesMut::from(&b"line 1\r\n\nline 2\n"[..]);
+
+        assert_eq!(codec.decode(&mut buf).unwrap().unwrap(), "line 1");
+        assert_eq!(codec.decode(&mut buf).unwrap().unwrap(), "");
+        assert_eq!(codec.decode(&mut buf).unwrap().unwrap(), "line 2");
+        assert!(codec.decode(&mut buf).unwrap().is_none());
+
+        let mut dst = BytesMut::new();
+        codec.encode(Bytes::from_static(b"line"), &mut dst).unwrap();
+        assert_eq!(&dst[..], b"line\n");
+    }
+
+    #[test]
+    fn test_partial_line() {
+        let codec = LinesCodec::<String>::new();
+        assert_eq!(codec.max_length(), 64 * 1024);
+
+        let mut buf = BytesMut::from(&b"li"[..]);
+        assert!(codec.decode(&mut buf).unwrap().is_none());
+        assert_eq!(codec.next_index.get(), 2);
+        buf.extend_from_slice(b"ne 1");
+        assert!(codec.decode(&mut buf).unwrap().is_none());
+        assert_eq!(codec.next_index.get(), 6);
+        buf.extend_from_slice(b"\r\nline 2\n");
+        assert_eq!(codec.decode(&mut buf).unwrap().unwrap(), "line 1");
+        assert_eq!(codec.next_index.get(), 0);
+        assert_eq!(codec.decode(&mut buf).unwrap().unwrap(), "line 2");
+
+        let mut buf = BytesMut::from(&vec![b'a'; 64 * 1024 + 2][..]);
+        assert!(matches!(
+            codec.decode(&mut buf),
+            Err(LinesCodecError::MaxLineLengthExceeded)
+        ));
+    }
+
+    #[test]
+    fn test_max_length() {
+        let codec = LinesCodec::<String>::with_max_length(4);
+        assert_eq!(codec.max_length(), 4);
+
+        let mut buf = BytesMut::from(&b"1234\r\n12345"[..]);
+        assert_eq!(codec.decode(&mut buf).unwrap().unwrap(), "1234");
+        assert!(codec.decode(&mut buf).unwrap().is_none());
+        buf.extend_from_slice(b"6");
+        assert!(matches!(
+            codec.decode(&mut buf),
+            Err(LinesCodecError::MaxLineLengthExceeded)
+        ));
+
+        let mut buf = BytesMut::from(&b"12345\n"[..]);
+        assert!(matches!(
+            codec.decode(&mut buf),
+            Err(LinesCodecError::MaxLineLengthExceeded)
+        ));
+        let mut buf = BytesMut::from(&b"12345"[..]);
+        assert!(matches!(
+            codec.decode_eof(&mut buf),
+            Err(LinesCodecError::MaxLineLengthExceeded)
+        ));
+        assert_eq!(
+            format!("{}", LinesCodecError::MaxLineLengthExceeded),
+            "Max line length exceeded"
+        );
+    }
+}
@@ -0,0 +1,231 @@
+use ntex_bytes::{Buf, BufMut, BytesMut};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{fmt, marker::PhantomData};
+
+use super::{Decoder, Encoder};
+
+const HEADER_SIZE: usize = 4;
+
+/// Default max payload length
+const DEFAULT_MAX_LENGTH: usize = 64 * 1024;
+
+/// MessagePack codec.
+///
+/// Each item is serialized to MessagePack and prefixed with
+/// 4 bytes big-endian payload length.
+///
+/// By default max payload length is 64Kb.
+pub struct MsgPackCodec<T> {
+    max_length: usize,
+    _t: PhantomData<T>,
+}
+
+/// MessagePack codec error
+#[derive(Debug)]
+pub enum MsgPackCodecError {
+    /// Payload length exceeds max length
+    MaxLengthExceeded,
+    /// Serialization error
+    Encode(rmp_serde::encode::Error),
+    /// Deserialization error
+    Decode(rmp_serde::decode::Error),
+}
+
+impl fmt::Display for MsgPackCodecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MsgPackCodecError::MaxLengthExceeded => write!(f, "Max length exceeded"),
+            MsgPackCodecError::Encode(e) => write!(f, "MessagePack encode error: {}", e),
+            MsgPackCodecError::Decode(e) => write!(f, "MessagePack decode error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for MsgPackCodecError {}
+
+impl<T> MsgPackCodec<T> {
+    /// Create MessagePack codec with default max payload length of 64Kb
+    pub fn new() -> Self {
+        Self::with_max_length(DEFAULT_MAX_LENGTH)
+    }
+
+    /// Create MessagePack codec with max payload length
+    ///
+    /// Max payload length could not exceed `u32::MAX`.
+    pub fn with_max_length(max_length: usize) -> Self {
+        MsgPackCodec {
+            max_length: std::cmp::min(max_length, u32::MAX as usize),
+            _t: PhantomData,
+        }
+    }
+
+    /// Get max payload length
+    pub fn max_length(&self) -> usize {
+        self.max_length
+    }
+
+    fn payload_len(&self, src: &BytesMut) -> Result<Option<usize>, MsgPackCodecError> {
+        if src.len() < HEADER_SIZE {
+            Ok(None)
+        } else {
+            let len = u32::from_be_bytes([src[0], src[1], src[2], src[3]]) as usize;
+            if len > self.max_length {
+                Err(MsgPackCodecError::MaxLengthExceeded)
+            } else {
+                Ok(Some(len))
+            }
+        }
+    }
+}
+
+impl<T> Default for MsgPackCodec<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Clone for MsgPackCodec<T> {
+    fn clone(&self) -> Self {
+        Self::with_max_length(self.max_length)
+    }
+}
+
+impl<T> fmt::Debug for MsgPackCodec<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MsgPackCodec")
+            .field("max_length", &self.max_length)
+            .finish()
+    }
+}
+
+impl<T: Serialize> Encoder for MsgPackCodec<T> {
+    type Item = T;
+    type Error = MsgPackCodecError;
+
+    fn encode(&self, item: T, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        let start = dst.len();
+        dst.put_u32(0);
+
+        let mut wrt = dst.writer();
+        if let Err(e) = rmp_serde::encode::write_named(&mut wrt, &item) {
+            wrt.into_inner().truncate(start);
+            return Err(MsgPackCodecError::Encode(e));
+        }
+        let dst = wrt.into_inner();
+
+        let len = dst.len() - start - HEADER_SIZE;
+        if len > self.max_length {
+            dst.truncate(start);
+            return Err(MsgPackCodecError::MaxLengthExceeded);
+        }
+        dst[start..start + HEADER_SIZE].copy_from_slice(&(len as u32).to_be_bytes());
+        Ok(())
+    }
+}
+
+impl<T: DeserializeOwned> Decoder for MsgPackCodec<T> {
+    type Item = T;
+    type Error = MsgPackCodecError;
+
+    fn decode(&self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        match self.payload_len(src)? {
+            Some(len) if src.len() >= HEADER_SIZE + len => {
+                src.advance(HEADER_SIZE);
+                let payload = src.split_to(len);
+                rmp_serde::from_slice(&payload)
+                    .map(Some)
+                    .map_err(MsgPackCodecError::Decode)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn decode_hint(&self, src: &BytesMut) -> usize {
+        match self.payload_len(src) {
+            Ok(Some(len)) => (HEADER_SIZE + len).saturating_sub(src.len()),
+            Ok(None) => HEADER_SIZE - src.len(),
+            Err(_) => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Msg {
+        id: u32,
+        name: String,
+    }
+
+    #[test]
+    fn test_msgpack() {
+        let codec = MsgPackCodec::<Msg>::new();
+        let msg = Msg {
+            id: 1,
+            name: "test".to_string(),
+        };
+
+        let mut buf = BytesMut::new();
+        codec.encode(msg, &mut buf).unwrap();
+        let len = buf.len();
+        assert_eq!(&buf[..4], &((len - 4) as u32).to_be_bytes());
+
+        let mut src = buf.split_to(len - 2);
+        assert_eq!(codec.decode_hint(&BytesMut::new()), 4);
+        assert!(codec.decode(&mut src).unwrap().is_none());
+        assert_eq!(codec.decode_hint(&src), 2);
+
+        src.extend_from_slice(&buf);
+        let item = codec.decode(&mut src).unwrap().unwrap();
+        assert_eq!(
+            item,
+            Msg {
+                id: 1,
+                name: "test".to_string(),
+            }
+        );
+        assert!(src.is_empty());
+
+        let mut src = BytesMut::from(&b"\0\0\0\x01\xc1"[..]);
+        assert!(matches!(
+            codec.decode(&mut src),
+            Err(MsgPackCodecError::Decode(_))
+        ));
+    }
+
+    #[test]
+    fn test_max_length() {
+        let codec = MsgPackCodec::<Msg>::with_max_length(8);
+        assert_eq!(codec.max_length(), 8);
+
+        let mut buf = BytesMut::from(&b"\0\0\0\x09"[..]);
+        assert!(matches!(
+            codec.decode(&mut buf),
+            Err(MsgPackCodecError::MaxLengthExceeded)
+        ));
+
+        let mut buf = BytesMut::new();
+        let res = codec.encode(
+            Msg {
+                id: 1,
+                name: "long name".to_string(),
+            },
+            &mut buf,
+        );
+        assert!(matches!(res, Err(MsgPackCodecError::MaxLengthExceeded)));
+        assert!(buf.is_empty());
+
+        // default limit
+        let codec = MsgPackCodec::<Msg>::new();
+        assert_eq!(codec.max_length(), 64 * 1024);
+        let mut buf = BytesMut::from(&b"\xff\xff\xff\xff"[..]);
+        assert!(matches!(
+            codec.decode(&mut buf),
+            Err(MsgPackCodecError::MaxLengthExceeded)
+        ));
+    }
+}
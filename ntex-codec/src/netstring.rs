@@ -0,0 +1,212 @@
+use ntex_bytes::{Bytes, BytesMut};
+use std::fmt;
+
+use super::{Decoder, Encoder};
+
+/// Default max payload length
+const DEFAULT_MAX_LENGTH: usize = 64 * 1024;
+
+/// Netstring codec.
+///
+/// Frames are encoded as `<len>:<payload>,`, where `len` is payload
+/// length in ascii decimal digits. Decoded item is payload without
+/// length prefix and trailing comma.
+///
+/// By default max payload length is 64Kb.
+#[derive(Debug, Clone)]
+pub struct NetstringCodec {
+    max_length: usize,
+}
+
+/// Netstring codec error
+#[derive(Debug)]
+pub enum NetstringCodecError {
+    /// Payload length exceeds max length
+    MaxLengthExceeded,
+    /// Malformed length prefix or missing trailing comma
+    InvalidFormat,
+}
+
+impl fmt::Display for NetstringCodecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetstringCodecError::MaxLengthExceeded => write!(f, "Max length exceeded"),
+            NetstringCodecError::InvalidFormat => write!(f, "Invalid netstring"),
+        }
+    }
+}
+
+impl std::error::Error for NetstringCodecError {}
+
+impl NetstringCodec {
+    /// Create netstring codec with default max payload length of 64Kb
+    pub fn new() -> Self {
+        Self::with_max_length(DEFAULT_MAX_LENGTH)
+    }
+
+    /// Create netstring codec with max payload length
+    pub fn with_max_length(max_length: usize) -> Self {
+        NetstringCodec { max_length }
+    }
+
+    /// Get max payload length
+    pub fn max_length(&self) -> usize {
+        self.max_length
+    }
+
+    /// Parse length prefix, returns prefix size and payload length
+    fn parse_prefix(
+        &self,
+        src: &BytesMut,
+    ) -> Result<Option<(usize, usize)>, NetstringCodecError> {
+        let mut len: usize = 0;
+        for (idx, b) in src.iter().enumerate() {
+            match *b {
+                b':' if idx > 0 => return Ok(Some((idx + 1, len))),
+                // leading zeros are not allowed
+                b'0'..=b'9' if idx == 0 || len > 0 => {
+                    len = len
+                        .checked_mul(10)
+                        .and_then(|len| len.checked_add((*b - b'0') as usize))
+                        .ok_or(NetstringCodecError::MaxLengthExceeded)?;
+                    if len > self.max_length {
+                        return Err(NetstringCodecError::MaxLengthExceeded);
+                    }
+                }
+                _ => return Err(NetstringCodecError::InvalidFormat),
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl Default for NetstringCodec {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Encoder for NetstringCodec {
+    type Item = Bytes;
+    type Error = NetstringCodecError;
+
+    fn encode(&self, item: Bytes, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        if item.len() > self.max_length {
+            return Err(NetstringCodecError::MaxLengthExceeded);
+        }
+        let len = item.len().to_string();
+        dst.reserve(len.len() + item.len() + 2);
+        dst.extend_from_slice(len.as_bytes());
+        dst.extend_from_slice(b":");
+        dst.extend_from_slice(&item[..]);
+        dst.extend_from_slice(b",");
+        Ok(())
+    }
+}
+
+impl Decoder for NetstringCodec {
+    type Item = Bytes;
+    type Error = NetstringCodecError;
+
+    fn decode(&self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        if let Some((prefix, len)) = self.parse_prefix(src)? {
+            if src.len() < prefix + len + 1 {
+                return Ok(None);
+            }
+            if src[prefix + len] != b',' {
+                return Err(NetstringCodecError::InvalidFormat);
+            }
+            let mut frame = src.split_to(prefix + len + 1);
+            frame.truncate(prefix + len);
+            Ok(Some(frame.split_off(prefix).freeze()))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn decode_hint(&self, src: &BytesMut) -> usize {
+        match self.parse_prefix(src) {
+            Ok(Some((prefix, len))) => (prefix + len + 1).saturating_sub(src.len()),
+            _ => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_netstring() {
+        let codec = NetstringCodec::new();
+
+        let mut dst = BytesMut::new();
+        codec
+            .encode(Bytes::from_static(b"hello"), &mut dst)
+            .unwrap();
+        codec.encode(Bytes::new(), &mut dst).unwrap();
+        assert_eq!(&dst[..], b"5:hello,0:,");
+
+        let mut buf = BytesMut::from(&b"5:hello,0:,12:hello"[..]);
+        assert_eq!(codec.decode(&mut buf).unwrap().unwrap(), "hello");
+        assert_eq!(codec.decode(&mut buf).unwrap().unwrap(), "");
+        assert!(codec.decode(&mut buf).unwrap().is_none());
+        assert_eq!(codec.decode_hint(&buf), 8);
+
+        buf.extend_from_slice(b" world!,");
+        assert_eq!(codec.decode(&mut buf).unwrap().unwrap(), "hello world!");
+        assert!(buf.is_empty());
+        assert_eq!(codec.decode_hint(&buf), 0);
+    }
+
+    #[test]
+    fn test_invalid() {
+        let codec = NetstringCodec::new();
+
+        for data in &[&b"5:hello;"[..], b"05:hello,", b":", b"a:", b"5 :hello,"] {
+            let mut buf = BytesMut::from(*data);
+            assert!(matches!(
+                codec.decode(&mut buf),
+                Err(NetstringCodecError::InvalidFormat)
+            ));
+        }
+        assert_eq!(
+            format!("{}", NetstringCodecError::InvalidFormat),
+            "Invalid netstring"
+        );
+    }
+
+    #[test]
+    fn test_max_length() {
+        let codec = NetstringCodec::with_max_length(4);
+        assert_eq!(codec.max_length(), 4);
+
+        let mut buf = BytesMut::from(&b"4:1234,5"[..]);
+        assert_eq!(codec.decode(&mut buf).unwrap().unwrap(), "1234");
+        assert!(matches!(
+            codec.decode(&mut buf),
+            Err(NetstringCodecError::MaxLengthExceeded)
+        ));
+
+        let mut buf = BytesMut::from(&b"99999999999999999999999"[..]);
+        assert!(matches!(
+            NetstringCodec::new().decode(&mut buf),
+            Err(NetstringCodecError::MaxLengthExceeded)
+        ));
+
+        // default limit
+        assert_eq!(NetstringCodec::new().max_length(), 64 * 1024);
+        let mut buf = BytesMut::from(&b"65537:"[..]);
+        assert!(matches!(
+            NetstringCodec::new().decode(&mut buf),
+            Err(NetstringCodecError::MaxLengthExceeded)
+        ));
+
+        let mut dst = BytesMut::new();
+        assert!(matches!(
+            codec.encode(Bytes::from_static(b"12345"), &mut dst),
+            Err(NetstringCodecError::MaxLengthExceeded)
+        ));
+        assert!(dst.is_empty());
+    }
+}
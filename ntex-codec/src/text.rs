@@ -0,0 +1,132 @@
+use ntex_bytes::{ByteString, BytesMut};
+use std::{io, str};
+
+use super::{Decoder, Encoder, VectoredBuf};
+
+/// Text codec.
+///
+/// Same as `BytesCodec`, but decodes into `ByteString`. Only complete utf-8
+/// sequences are decoded, incomplete trailing sequence stays in the read
+/// buffer until more data is available. Decoded frames reference read buffer
+/// memory, data is validated once and never copied.
+#[derive(Debug, Copy, Clone)]
+pub struct TextCodec;
+
+fn invalid_utf8(err: str::Utf8Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+impl Encoder for TextCodec {
+    type Item = ByteString;
+    type Error = io::Error;
+
+    #[inline]
+    fn encode(&self, item: ByteString, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        dst.extend_from_slice(item.as_slice());
+        Ok(())
+    }
+
+    #[inline]
+    fn encode_vectored(
+        &self,
+        item: ByteString,
+        dst: &mut VectoredBuf<'_>,
+    ) -> Result<(), Self::Error> {
+        dst.put_chunk(item.into_bytes());
+        Ok(())
+    }
+}
+
+impl Decoder for TextCodec {
+    type Item = ByteString;
+    type Error = io::Error;
+
+    fn decode(&self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        let len = match str::from_utf8(src) {
+            Ok(_) => src.len(),
+            // incomplete sequence at the end of buffer, or invalid
+            // sequence that is reported on next call
+            Err(e) if e.error_len().is_none() || e.valid_up_to() > 0 => e.valid_up_to(),
+            Err(e) => return Err(invalid_utf8(e)),
+        };
+        if len == 0 {
+            Ok(None)
+        } else {
+            // Safety: data is validated above
+            let item = src.split_to(len).freeze();
+            Ok(Some(unsafe { ByteString::from_bytes_unchecked(item) }))
+        }
+    }
+
+    fn decode_eof(&self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        match self.decode(src)? {
+            Some(item) => Ok(Some(item)),
+            None if src.is_empty() => Ok(None),
+            None => Err(invalid_utf8(str::from_utf8(src).unwrap_err())),
+        }
+    }
+
+    fn decode_hint(&self, src: &BytesMut) -> usize {
+        // remaining bytes of incomplete utf-8 sequence
+        match src.first() {
+            Some(b) if *b >= 0xf0 => 4_usize.saturating_sub(src.len()),
+            Some(b) if *b >= 0xe0 => 3_usize.saturating_sub(src.len()),
+            Some(b) if *b >= 0xc0 => 2_usize.saturating_sub(src.len()),
+            _ => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ntex_bytes::Bytes;
+    use std::collections::VecDeque;
+
+    use super::*;
+
+    #[test]
+    fn test_text() {
+        let data = "тест".repeat(32);
+        let mut buf = BytesMut::from(data.as_bytes());
+        let ptr = buf.as_ptr();
+
+        // split multi-byte char
+        let mut tail = buf.split_off(5);
+        let item = TextCodec.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(item, "те");
+        assert_eq!(item.as_slice().as_ptr(), ptr);
+        assert_eq!(&buf[..], &[0xd1]);
+        assert_eq!(TextCodec.decode_hint(&buf), 1);
+
+        buf.extend_from_slice(&tail.split_to(1));
+        assert_eq!(TextCodec.decode(&mut buf).unwrap().unwrap(), "с");
+        assert!(TextCodec.decode(&mut buf).unwrap().is_none());
+
+        buf.extend_from_slice(&tail[..tail.len() - 1]);
+        let item = TextCodec.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(item.len(), data.len() - 8);
+        assert!(TextCodec.decode_eof(&mut buf).is_err());
+
+        let mut buf = BytesMut::from(&b"text\xff"[..]);
+        assert_eq!(TextCodec.decode(&mut buf).unwrap().unwrap(), "text");
+        assert!(TextCodec.decode(&mut buf).is_err());
+    }
+
+    #[test]
+    fn test_encode() {
+        let mut buf = BytesMut::new();
+        TextCodec
+            .encode(ByteString::from_static("text"), &mut buf)
+            .unwrap();
+        assert_eq!(&buf[..], b"text");
+
+        let data = ByteString::from("t".repeat(2048));
+        let mut chunks = VecDeque::new();
+        TextCodec
+            .encode_vectored(data.clone(), &mut VectoredBuf::new(&mut buf, &mut chunks))
+            .unwrap();
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0], Bytes::from_static(b"text"));
+        assert_eq!(chunks[1].as_ptr(), data.as_slice().as_ptr());
+    }
+}
@@ -19,6 +19,9 @@
 //! ### Attributes:
 //!
 //! - `"path"` - Raw literal string with path for which to register handle. Mandatory.
+//!   Path pattern is checked at compile time. Handler arguments named after dynamic
+//!   segments are extracted with typed `ntex::web::types::Path<T>` extractor.
+//!   Arguments of `Path<T>` type are used as regular extractors.
 //! - `guard = "function_name"` - Registers function as guard using `ntex::web::guard::fn_guard`
 //! - `error = "ErrorRenderer"` - Register handler for specified error renderer
 //!
@@ -37,6 +40,11 @@
 //! async fn async_test() -> Result<HttpResponse, Error> {
 //!     Ok(HttpResponse::Ok().finish())
 //! }
+//!
+//! #[get("/users/{id}")]
+//! async fn user(id: u32) -> HttpResponse {
+//!     HttpResponse::Ok().body(format!("user: {}", id))
+//! }
 //! ```
 
 extern crate proc_macro;
@@ -42,6 +42,7 @@ impl ToTokens for MethodType {
 
 struct Args {
     path: syn::LitStr,
+    params: Vec<String>,
     guards: Vec<Ident>,
     error: Path,
 }
@@ -95,8 +96,11 @@ impl Args {
                 }
             }
         }
+        let path = path.unwrap();
+        let params = check_path(&path)?;
         Ok(Args {
-            path: path.unwrap(),
+            path,
+            params,
             guards,
             error: error
                 .unwrap_or_else(|| syn::parse_str("ntex::web::DefaultError").unwrap()),
@@ -104,10 +108,116 @@ impl Args {
     }
 }
 
+/// Check path pattern, dynamic segments must be well formed
+/// and have unique names. Returns names of dynamic segments.
+fn check_path(path: &syn::LitStr) -> syn::Result<Vec<String>> {
+    let pattern = path.value();
+    let mut names = Vec::new();
+    let mut name = String::new();
+    let mut depth = 0;
+    let mut in_name = false;
+
+    for ch in pattern.chars() {
+        match ch {
+            '{' => {
+                depth += 1;
+                if depth == 1 {
+                    in_name = true;
+                    name.clear();
+                }
+            }
+            '}' if depth == 0 => {
+                return Err(syn::Error::new_spanned(
+                    path,
+                    "Unexpected `}` in path pattern",
+                ));
+            }
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    if name.is_empty() {
+                        return Err(syn::Error::new_spanned(
+                            path,
+                            "Dynamic segment name is empty",
+                        ));
+                    }
+                    if names.contains(&name) {
+                        return Err(syn::Error::new_spanned(
+                            path,
+                            format!("Duplicate dynamic segment name: {}", name),
+                        ));
+                    }
+                    names.push(name.clone());
+                    in_name = false;
+                }
+            }
+            ':' if depth == 1 => in_name = false,
+            ch if in_name => name.push(ch),
+            _ => (),
+        }
+    }
+
+    if depth != 0 {
+        Err(syn::Error::new_spanned(
+            path,
+            "Dynamic segment is not closed, expected `}`",
+        ))
+    } else {
+        Ok(names)
+    }
+}
+
+/// Handler argument named after dynamic segment
+struct PathParam {
+    idx: usize,
+    name: String,
+    ty: syn::Type,
+}
+
+/// Find handler arguments that are named after dynamic segments
+fn path_params(ast: &syn::ItemFn, names: &[String]) -> syn::Result<Vec<PathParam>> {
+    let mut params = Vec::new();
+    for (idx, arg) in ast.sig.inputs.iter().enumerate() {
+        if let syn::FnArg::Typed(arg) = arg {
+            if let syn::Pat::Ident(ref pat) = *arg.pat {
+                let name = pat.ident.to_string();
+                if names.contains(&name) && !is_path_extractor(&arg.ty) {
+                    if let syn::Type::Reference(_) = *arg.ty {
+                        return Err(syn::Error::new_spanned(
+                            &arg.ty,
+                            format!("Path parameter `{}` must be an owned type", name),
+                        ));
+                    }
+                    params.push(PathParam {
+                        idx,
+                        name,
+                        ty: (*arg.ty).clone(),
+                    });
+                }
+            }
+        }
+    }
+    Ok(params)
+}
+
+/// Check if argument is `Path<T>` extractor, it is used as is
+fn is_path_extractor(ty: &syn::Type) -> bool {
+    if let syn::Type::Path(ref ty) = *ty {
+        ty.path
+            .segments
+            .last()
+            .map(|seg| seg.ident == "Path")
+            .unwrap_or(false)
+    } else {
+        false
+    }
+}
+
 pub struct Route {
     name: syn::Ident,
     args: Args,
     ast: syn::ItemFn,
+    params: Vec<PathParam>,
     method: MethodType,
 }
 
@@ -129,11 +239,13 @@ impl Route {
         let ast: syn::ItemFn = syn::parse(input)?;
         let name = ast.sig.ident.clone();
         let args = Args::new(args)?;
+        let params = path_params(&ast, &args.params)?;
 
         Ok(Self {
             name,
             args,
             ast,
+            params,
             method,
         })
     }
@@ -147,6 +259,10 @@ impl Route {
         let error = &self.args.error;
         let method = &self.method;
 
+        if !self.params.is_empty() {
+            return self.generate_with_params();
+        }
+
         let stream = quote! {
             #[allow(non_camel_case_types)]
             pub struct #name;
@@ -168,4 +284,96 @@ impl Route {
         };
         stream.into()
     }
+
+    /// Generate handler wrapper that extracts arguments named
+    /// after dynamic segments with `Path<T>` extractor.
+    fn generate_with_params(&self) -> TokenStream {
+        let name = &self.name;
+        let resource_name = name.to_string();
+        let ast = &self.ast;
+        let path = &self.args.path;
+        let extra_guards = &self.args.guards;
+        let error = &self.args.error;
+        let method = &self.method;
+        let output = &ast.sig.output;
+
+        let param_tys: Vec<_> = self.params.iter().map(|p| &p.ty).collect();
+        let param_names: Vec<_> = self.params.iter().map(|p| &p.name).collect();
+
+        // wrapper arguments and original handler call arguments
+        let mut args = Vec::new();
+        let mut call_args = Vec::new();
+        for (idx, arg) in ast.sig.inputs.iter().enumerate() {
+            if let Some(pos) = self.params.iter().position(|p| p.idx == idx) {
+                let pos = syn::Index::from(pos);
+                call_args.push(quote!(__params.#pos));
+            } else if let syn::FnArg::Typed(arg) = arg {
+                let ident = Ident::new(&format!("__arg{}", idx), Span::call_site());
+                let ty = &arg.ty;
+                args.push(quote!(#ident: #ty));
+                call_args.push(quote!(#ident));
+            }
+        }
+        let handler = if ast.sig.asyncness.is_some() {
+            quote! {
+                async fn __handler(__params: __PathParams, #(#args),*) #output {
+                    #name(#(#call_args),*).await
+                }
+            }
+        } else {
+            quote! {
+                fn __handler(__params: __PathParams, #(#args),*) #output {
+                    #name(#(#call_args),*)
+                }
+            }
+        };
+
+        let stream = quote! {
+            #[allow(non_camel_case_types)]
+            pub struct #name;
+
+            impl ntex::web::dev::WebServiceFactory<#error> for #name
+            {
+                fn register(self, __config: &mut ntex::web::dev::WebServiceConfig<#error>) {
+                    #ast
+
+                    struct __PathParams(#(#param_tys),*);
+
+                    impl __PathParams {
+                        fn extract(
+                            __req: &ntex::web::HttpRequest,
+                        ) -> Result<Self, ntex::web::error::PathError> {
+                            Ok(__PathParams(#(
+                                ntex::web::types::Path::<#param_tys>::param(__req, #param_names)?
+                                    .into_inner()
+                            ),*))
+                        }
+                    }
+
+                    impl ntex::web::FromRequest<#error> for __PathParams {
+                        type Error = ntex::web::error::PathError;
+                        type Future = ntex::util::Ready<Self, Self::Error>;
+
+                        fn from_request(
+                            __req: &ntex::web::HttpRequest,
+                            _: &mut ntex::http::Payload,
+                        ) -> Self::Future {
+                            ntex::util::Ready::from(__PathParams::extract(__req))
+                        }
+                    }
+
+                    #handler
+
+                    let __resource = ntex::web::Resource::new(#path)
+                        .name(#resource_name)
+                        .guard(ntex::web::guard::#method())
+                        #(.guard(ntex::web::guard::fn_guard(#extra_guards)))*
+                        .to(__handler);
+
+                    ntex::web::dev::WebServiceFactory::register(__resource, __config)
+                }
+            }
+        };
+        stream.into()
+    }
 }
@@ -1,6 +1,8 @@
 use futures::{future, Future};
 use ntex::http::{Method, StatusCode};
-use ntex::web::{test, types::Path, App, Error, HttpResponse, HttpResponseBuilder};
+use ntex::web::{
+    test, types::Path, App, Error, HttpRequest, HttpResponse, HttpResponseBuilder,
+};
 use ntex_macros::{
     web_connect, web_delete, web_get, web_head, web_options, web_patch, web_post,
     web_put, web_trace,
@@ -72,6 +74,75 @@ async fn get_param_test(_: Path<String>) -> HttpResponse {
     HttpResponse::Ok().finish()
 }
 
+#[web_get("/user/{name}/{id:[[:digit:]]+}")]
+async fn get_typed_param_test(path: Path<(String, u32)>) -> HttpResponse {
+    let (name, id) = path.into_inner();
+    HttpResponse::Ok().body(format!("{}:{}", name, id + 1))
+}
+
+#[web_get("/item/{name}/{id:[[:digit:]]+}")]
+async fn get_named_param_test(id: u32, req: HttpRequest, name: String) -> HttpResponse {
+    assert_eq!(req.match_info().query("name"), name);
+    HttpResponse::Ok().body(format!("{}:{}", name, id + 1))
+}
+
+#[web_get("/fut/{id}")]
+fn named_param_fut(id: u32) -> impl Future<Output = Result<HttpResponse, Error>> {
+    future::ok(HttpResponse::Ok().body(format!("{}", id + 1)))
+}
+
+#[web_get("/path/{name}")]
+async fn named_path_extractor(name: ntex::web::types::Path<String>) -> HttpResponse {
+    HttpResponse::Ok().body(name.into_inner())
+}
+
+#[ntex::test]
+async fn test_named_params() {
+    let srv = test::server(|| {
+        App::new()
+            .service(get_named_param_test)
+            .service(named_param_fut)
+            .service(named_path_extractor)
+    });
+
+    let request = srv.request(Method::GET, srv.url("/path/ntex"));
+    let mut response = request.send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.body().await.unwrap();
+    assert_eq!(&body[..], b"ntex");
+
+    let request = srv.request(Method::GET, srv.url("/item/ntex/41"));
+    let mut response = request.send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.body().await.unwrap();
+    assert_eq!(&body[..], b"ntex:42");
+
+    let request = srv.request(Method::GET, srv.url("/fut/1"));
+    let mut response = request.send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.body().await.unwrap();
+    assert_eq!(&body[..], b"2");
+
+    let request = srv.request(Method::GET, srv.url("/fut/abc"));
+    let response = request.send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[ntex::test]
+async fn test_typed_params() {
+    let srv = test::server(|| App::new().service(get_typed_param_test));
+
+    let request = srv.request(Method::GET, srv.url("/user/ntex/41"));
+    let mut response = request.send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.body().await.unwrap();
+    assert_eq!(&body[..], b"ntex:42");
+
+    let request = srv.request(Method::GET, srv.url("/user/ntex/abc"));
+    let response = request.send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
 #[ntex::test]
 async fn test_params() {
     let srv = test::server(|| {
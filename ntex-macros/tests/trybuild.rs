@@ -0,0 +1,5 @@
+#[test]
+fn compile_macros() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/trybuild/*-fail.rs");
+}
@@ -0,0 +1,6 @@
+#[ntex::web::get("/{name}")]
+async fn index(name: &str) -> ntex::web::HttpResponse {
+    ntex::web::HttpResponse::Ok().body(name.to_string())
+}
+
+fn main() {}
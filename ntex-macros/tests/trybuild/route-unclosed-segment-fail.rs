@@ -0,0 +1,6 @@
+#[ntex::web::get("/{id")]
+async fn index() -> ntex::web::HttpResponse {
+    ntex::web::HttpResponse::Ok().finish()
+}
+
+fn main() {}
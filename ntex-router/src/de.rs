@@ -53,6 +53,21 @@ impl<'de, T: ResourcePath + 'de> PathDeserializer<'de, T> {
     pub fn new(path: &'de Path<T>) -> Self {
         PathDeserializer { path }
     }
+
+    /// Deserialize value of named dynamic segment
+    pub fn param<V>(&self, name: &str) -> Result<V, de::value::Error>
+    where
+        V: de::Deserialize<'de>,
+    {
+        if let Some(value) = self.path.get(name) {
+            V::deserialize(Value { value })
+        } else {
+            Err(de::value::Error::custom(format!(
+                "path parameter is not found: {}",
+                name
+            )))
+        }
+    }
 }
 
 impl<'de, T: ResourcePath + 'de> Deserializer<'de> for PathDeserializer<'de, T> {
@@ -644,6 +659,20 @@ mod tests {
         assert!(i.is_err());
     }
 
+    #[test]
+    fn test_extract_param() {
+        let mut path = Path::new("/name/user1/");
+        path.segments = vec![
+            ("key", PathItem::Static("name")),
+            ("value", PathItem::Static("32")),
+        ];
+        let de = PathDeserializer::new(&path);
+        assert_eq!(de.param::<String>("key").unwrap(), "name");
+        assert_eq!(de.param::<u32>("value").unwrap(), 32);
+        assert!(de.param::<u32>("key").is_err());
+        assert!(de.param::<String>("unknown").is_err());
+    }
+
     #[test]
     fn test_extract_enum() {
         let mut path = Path::new("/val1/");
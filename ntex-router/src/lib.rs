@@ -7,11 +7,15 @@ mod path;
 mod resource;
 mod router;
 mod tree;
+#[cfg(feature = "trie")]
+mod trie;
 
 pub use self::de::PathDeserializer;
 pub use self::path::{Path, PathIter};
 pub use self::resource::ResourceDef;
-pub use self::router::{ResourceInfo, Router, RouterBuilder};
+pub use self::router::{Matcher, ResourceInfo, Router, RouterBuilder};
+#[cfg(feature = "trie")]
+pub use self::trie::TrieMatcher;
 
 pub trait Resource<T: ResourcePath> {
     fn path(&self) -> &str;
@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
+use std::{fmt, sync::Arc};
 
 use regex::{escape, Regex};
 
@@ -56,11 +57,34 @@ pub(crate) enum Segment {
     Dynamic {
         pattern: Regex,
         names: Vec<&'static str>,
+        matchers: Vec<(&'static str, Matcher)>,
         len: usize,
         tail: bool,
     },
 }
 
+/// Custom matcher for a dynamic segment value
+#[derive(Clone)]
+pub(crate) struct Matcher(Arc<dyn Fn(&str) -> bool + Send + Sync>);
+
+impl Matcher {
+    pub(crate) fn is_match(&self, value: &str) -> bool {
+        (self.0)(value)
+    }
+}
+
+impl PartialEq for Matcher {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl fmt::Debug for Matcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Matcher")
+    }
+}
+
 impl Eq for Segment {}
 
 impl PartialEq for Segment {
@@ -72,15 +96,17 @@ impl PartialEq for Segment {
             },
             Segment::Dynamic {
                 pattern: ref p1,
+                matchers: ref m1,
                 tail: t1,
                 ..
             } => match other {
                 Segment::Static { .. } => false,
                 Segment::Dynamic {
                     pattern: ref p2,
+                    matchers: ref m2,
                     tail: t2,
                     ..
-                } => p1.as_str() == p2.as_str() && t1 == t2,
+                } => p1.as_str() == p2.as_str() && m1 == m2 && t1 == t2,
             },
         }
     }
@@ -175,6 +201,43 @@ impl ResourceDef {
         }
     }
 
+    /// Set custom matcher for dynamic segment `name`.
+    ///
+    /// Matcher receives segment value after regex match, if it returns
+    /// `false` resource does not match and router checks next resources.
+    ///
+    /// Panics if pattern does not contain dynamic segment `name`.
+    pub fn matcher<F>(mut self, name: &str, f: F) -> Self
+    where
+        F: Fn(&str) -> bool + Send + Sync + 'static,
+    {
+        let matcher = Matcher(Arc::new(f));
+        let mut found = false;
+
+        for segments in &mut self.tp {
+            for segment in &mut segments.tp {
+                if let Segment::Dynamic {
+                    ref names,
+                    ref mut matchers,
+                    ..
+                } = segment
+                {
+                    if let Some(name) = names.iter().find(|n| **n == name) {
+                        matchers.push((name, matcher.clone()));
+                        found = true;
+                    }
+                }
+            }
+        }
+        if !found {
+            panic!(
+                "Dynamic segment {:?} is not found in {:?}",
+                name, self.pattern
+            );
+        }
+        self
+    }
+
     /// Resource pattern name
     pub fn name(&self) -> &str {
         &self.name
@@ -373,6 +436,7 @@ impl ResourceDef {
                 .collect();
             pelems.push(Segment::Dynamic {
                 names,
+                matchers: Vec::new(),
                 tail,
                 pattern: re,
                 len: 0,
@@ -400,6 +464,7 @@ impl ResourceDef {
                 pelems.push(Segment::Dynamic {
                     pattern,
                     names: Vec::new(),
+                    matchers: Vec::new(),
                     tail: true,
                     len: 0,
                 });
@@ -720,6 +785,7 @@ mod tests {
         let seg2 = Segment::Dynamic {
             pattern: Regex::new("test").unwrap(),
             names: Vec::new(),
+            matchers: Vec::new(),
             len: 1,
             tail: false,
         };
@@ -1074,6 +1140,54 @@ mod tests {
         assert_eq!(tree.find(&mut Path::new("/test/index.html")), Some(4));
     }
 
+    #[test]
+    fn test_regex_overlap() {
+        let mut tree = Tree::new(&ResourceDef::new("/items/{id:\\d+}"), 1);
+        tree.insert(&ResourceDef::new("/items/{name}"), 2);
+
+        let mut p = Path::new("/items/123");
+        assert_eq!(tree.find(&mut p), Some(1));
+        assert_eq!(p.get("id"), Some("123"));
+
+        let mut p = Path::new("/items/test");
+        assert_eq!(tree.find(&mut p), Some(2));
+        assert_eq!(p.get("name"), Some("test"));
+        assert_eq!(p.get("id"), None);
+    }
+
+    #[test]
+    fn test_matcher() {
+        let re = ResourceDef::new("/items/{id}/info")
+            .matcher("id", |s| s.parse::<u32>().is_ok());
+        let mut tree = Tree::new(&re, 1);
+        tree.insert(&ResourceDef::new("/items/{name}/info"), 2);
+
+        let mut p = Path::new("/items/123/info");
+        assert_eq!(tree.find(&mut p), Some(1));
+        assert_eq!(p.get("id"), Some("123"));
+        assert_eq!(p.len(), 1);
+
+        let mut p = Path::new("/items/test/info");
+        assert_eq!(tree.find(&mut p), Some(2));
+        assert_eq!(p.get("name"), Some("test"));
+        assert_eq!(p.len(), 1);
+
+        // matcher gets decoded value
+        let re = ResourceDef::new("/{v1}-{v2}").matcher("v2", |s| s == "a%b");
+        let tree = Tree::new(&re, 1);
+        let mut p = Path::new(http::Uri::from_static("/x-a%25b"));
+        assert_eq!(tree.find(&mut p), Some(1));
+        assert_eq!(p.get("v1"), Some("x"));
+        assert_eq!(p.get("v2"), Some("a%b"));
+        assert_eq!(tree.find(&mut Path::new("/x-ab")), None);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_matcher_unknown_segment() {
+        let _ = ResourceDef::new("/items/{id}").matcher("name", |_| true);
+    }
+
     #[test]
     fn test_with_some_match() {
         let mut tree = Tree::new(&ResourceDef::new("/p/{tp}/{id}/{r}"), 1);
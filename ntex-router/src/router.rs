@@ -1,3 +1,5 @@
+use std::{cell::Cell, rc::Rc};
+
 use super::tree::Tree;
 use super::{IntoPattern, Resource, ResourceDef, ResourcePath};
 
@@ -10,12 +12,55 @@ pub struct ResourceInfo {
     resource: ResourceId,
 }
 
+/// Resource matching backend.
+///
+/// Matcher selects candidate resources for a path, router verifies
+/// each candidate with its resource definition. By default router uses
+/// prefix tree of all resource definitions.
+pub trait Matcher {
+    /// Register resource definition, `idx` is resource index in router.
+    fn insert(&mut self, rdef: &ResourceDef, idx: usize);
+
+    /// Router uses case insensitive matching.
+    fn case_insensitive(&mut self) {}
+
+    /// Call `f` for candidate resources of the `path` in registration
+    /// order, stop when `f` returns `true`.
+    fn find(&self, path: &str, f: &mut dyn FnMut(usize) -> bool);
+}
+
+impl<M: Matcher + ?Sized> Matcher for Box<M> {
+    fn insert(&mut self, rdef: &ResourceDef, idx: usize) {
+        (**self).insert(rdef, idx)
+    }
+
+    fn case_insensitive(&mut self) {
+        (**self).case_insensitive()
+    }
+
+    fn find(&self, path: &str, f: &mut dyn FnMut(usize) -> bool) {
+        (**self).find(path, f)
+    }
+}
+
 /// Resource router.
 #[derive(Clone)]
 pub struct Router<T, U = ()> {
     tree: Tree,
     resources: Vec<(ResourceDef, T, Option<U>)>,
     insensitive: bool,
+    matcher: Option<(Rc<dyn Matcher>, Vec<Tree>)>,
+    candidates: Candidates,
+}
+
+/// Reusable buffer for matcher candidates
+#[derive(Default)]
+struct Candidates(Cell<Vec<usize>>);
+
+impl Clone for Candidates {
+    fn clone(&self) -> Self {
+        Candidates::default()
+    }
 }
 
 impl<T, U> Router<T, U> {
@@ -23,6 +68,7 @@ impl<T, U> Router<T, U> {
         RouterBuilder {
             resources: Vec::new(),
             insensitive: false,
+            matcher: None,
         }
     }
 
@@ -31,11 +77,7 @@ impl<T, U> Router<T, U> {
         R: Resource<P>,
         P: ResourcePath,
     {
-        if let Some(idx) = if self.insensitive {
-            self.tree.find_insensitive(resource)
-        } else {
-            self.tree.find(resource)
-        } {
+        if let Some(idx) = self.find(resource, &|_, _| true) {
             let item = &self.resources[idx];
             Some((&item.1, ResourceId(item.0.id())))
         } else {
@@ -51,11 +93,7 @@ impl<T, U> Router<T, U> {
         R: Resource<P>,
         P: ResourcePath,
     {
-        if let Some(idx) = if self.insensitive {
-            self.tree.find_insensitive(resource)
-        } else {
-            self.tree.find(resource)
-        } {
+        if let Some(idx) = self.find(resource, &|_, _| true) {
             let item = &mut self.resources[idx];
             Some((&mut item.1, ResourceId(item.0.id())))
         } else {
@@ -73,17 +111,10 @@ impl<T, U> Router<T, U> {
         R: Resource<P>,
         P: ResourcePath,
     {
-        if let Some(idx) = if self.insensitive {
-            self.tree.find_checked_insensitive(resource, &|idx, res| {
-                let item = &self.resources[idx];
-                check(res, item.2.as_ref())
-            })
-        } else {
-            self.tree.find_checked(resource, &|idx, res| {
-                let item = &self.resources[idx];
-                check(res, item.2.as_ref())
-            })
-        } {
+        if let Some(idx) = self.find(resource, &|idx, res| {
+            let item = &self.resources[idx];
+            check(res, item.2.as_ref())
+        }) {
             let item = &self.resources[idx];
             Some((&item.1, ResourceId(item.0.id())))
         } else {
@@ -101,28 +132,53 @@ impl<T, U> Router<T, U> {
         R: Resource<P>,
         P: ResourcePath,
     {
-        if let Some(idx) = if self.insensitive {
-            self.tree.find_checked_insensitive(resource, &|idx, res| {
-                let item = &self.resources[idx];
-                check(res, item.2.as_ref())
-            })
-        } else {
-            self.tree.find_checked(resource, &|idx, res| {
-                let item = &self.resources[idx];
-                check(res, item.2.as_ref())
-            })
-        } {
+        if let Some(idx) = self.find(resource, &|idx, res| {
+            let item = &self.resources[idx];
+            check(res, item.2.as_ref())
+        }) {
             let item = &mut self.resources[idx];
             Some((&mut item.1, ResourceId(item.0.id())))
         } else {
             None
         }
     }
+
+    fn find<R, P, F>(&self, resource: &mut R, check: &F) -> Option<usize>
+    where
+        F: Fn(usize, &R) -> bool,
+        R: Resource<P>,
+        P: ResourcePath,
+    {
+        if let Some((ref matcher, ref trees)) = self.matcher {
+            // path is borrowed from resource, collect candidates first
+            let mut candidates = self.candidates.0.take();
+            matcher.find(resource.resource_path().path(), &mut |idx| {
+                candidates.push(idx);
+                false
+            });
+
+            let result = candidates.iter().find_map(|idx| {
+                if self.insensitive {
+                    trees[*idx].find_checked_insensitive(resource, check)
+                } else {
+                    trees[*idx].find_checked(resource, check)
+                }
+            });
+            candidates.clear();
+            self.candidates.0.set(candidates);
+            result
+        } else if self.insensitive {
+            self.tree.find_checked_insensitive(resource, check)
+        } else {
+            self.tree.find_checked(resource, check)
+        }
+    }
 }
 
 pub struct RouterBuilder<T, U = ()> {
     insensitive: bool,
     resources: Vec<(ResourceDef, T, Option<U>)>,
+    matcher: Option<Box<dyn Matcher>>,
 }
 
 impl<T, U> RouterBuilder<T, U> {
@@ -134,6 +190,14 @@ impl<T, U> RouterBuilder<T, U> {
         self.insensitive = true;
     }
 
+    /// Use custom resource matching backend.
+    ///
+    /// Matcher is used by this router only, routers of nested
+    /// resources use default matching backend.
+    pub fn matcher<M: Matcher + 'static>(&mut self, matcher: M) {
+        self.matcher = Some(Box::new(matcher));
+    }
+
     /// Register resource for specified path.
     pub fn path<P: IntoPattern>(
         &mut self,
@@ -168,20 +232,32 @@ impl<T, U> RouterBuilder<T, U> {
 
     /// Finish configuration and create router instance.
     pub fn finish(self) -> Router<T, U> {
-        let tree = if self.resources.is_empty() {
-            Tree::default()
+        let (tree, matcher) = if let Some(mut matcher) = self.matcher {
+            if self.insensitive {
+                matcher.case_insensitive();
+            }
+            let mut trees = Vec::with_capacity(self.resources.len());
+            for (idx, r) in self.resources.iter().enumerate() {
+                matcher.insert(&r.0, idx);
+                trees.push(Tree::new(&r.0, idx));
+            }
+            (Tree::default(), Some((Rc::from(matcher), trees)))
+        } else if self.resources.is_empty() {
+            (Tree::default(), None)
         } else {
             let mut tree = Tree::new(&self.resources[0].0, 0);
             for (idx, r) in self.resources[1..].iter().enumerate() {
                 tree.insert(&r.0, idx + 1)
             }
-            tree
+            (tree, None)
         };
 
         Router {
             tree,
+            matcher,
             resources: self.resources,
             insensitive: self.insensitive,
+            candidates: Candidates::default(),
         }
     }
 }
@@ -438,6 +438,7 @@ impl Tree {
                 Segment::Dynamic {
                     ref pattern,
                     ref names,
+                    ref matchers,
                     tail,
                     ..
                 } => {
@@ -448,6 +449,14 @@ impl Tree {
                         let mut is_match = true;
                         for name in names.iter() {
                             if let Some(m) = captures.name(&name) {
+                                // custom matchers
+                                if !matchers
+                                    .iter()
+                                    .all(|(n, f)| n != name || f.is_match(m.as_str()))
+                                {
+                                    is_match = false;
+                                    break;
+                                }
                                 let item = if quoted {
                                     PathItem::Segment(m.as_str().to_string())
                                 } else {
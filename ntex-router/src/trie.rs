@@ -0,0 +1,214 @@
+use std::collections::HashMap;
+
+use super::resource::{ResourceDef, Segment};
+use super::router::Matcher;
+
+/// Segment trie matcher, optimized for large number of static resources.
+///
+/// Static resources are stored in a trie of path segments, lookup
+/// cost does not depend on number of static resources. Prefix resources
+/// and resources with dynamic segments are checked for each path in
+/// registration order.
+///
+/// ```rust
+/// use ntex_router::{Path, Router, TrieMatcher};
+///
+/// let mut router = Router::<usize>::build();
+/// router.matcher(TrieMatcher::default());
+/// router.path("/index.html", 0);
+/// router.path("/user/{id}", 1);
+/// let router = router.finish();
+///
+/// let mut path = Path::new("/user/10");
+/// let (h, _) = router.recognize(&mut path).unwrap();
+/// assert_eq!(*h, 1);
+/// assert_eq!(path.get("id"), Some("10"));
+/// ```
+#[derive(Debug, Default)]
+pub struct TrieMatcher {
+    root: Node,
+    dynamic: Vec<usize>,
+    insensitive: bool,
+}
+
+#[derive(Debug, Default)]
+struct Node {
+    children: HashMap<String, Node>,
+    values: Vec<usize>,
+}
+
+impl Matcher for TrieMatcher {
+    fn insert(&mut self, rdef: &ResourceDef, idx: usize) {
+        let is_static = !rdef.prefix
+            && rdef.tp.iter().all(|segments| {
+                segments
+                    .tp
+                    .iter()
+                    .all(|seg| matches!(seg, Segment::Static(_)))
+            });
+        if !is_static {
+            self.dynamic.push(idx);
+            return;
+        }
+
+        for segments in &rdef.tp {
+            let mut node = &mut self.root;
+            for seg in &segments.tp {
+                if let Segment::Static(ref s) = seg {
+                    for part in s.split('/').filter(|s| !s.is_empty()) {
+                        let key = if self.insensitive {
+                            part.to_ascii_lowercase()
+                        } else {
+                            part.to_string()
+                        };
+                        node = node.children.entry(key).or_default();
+                    }
+                }
+            }
+            if node.values.last() != Some(&idx) {
+                node.values.push(idx);
+            }
+        }
+    }
+
+    fn case_insensitive(&mut self) {
+        self.insensitive = true;
+    }
+
+    fn find(&self, path: &str, f: &mut dyn FnMut(usize) -> bool) {
+        let mut node = Some(&self.root);
+        for part in path.split('/').filter(|s| !s.is_empty()) {
+            node = node.and_then(|node| {
+                if self.insensitive {
+                    node.children.get(&part.to_ascii_lowercase())
+                } else {
+                    node.children.get(part)
+                }
+            });
+            if node.is_none() {
+                break;
+            }
+        }
+        let values = node.map(|node| node.values.as_slice()).unwrap_or(&[]);
+
+        // merge static and dynamic candidates in registration order
+        let (mut s, mut d) = (values.iter().peekable(), self.dynamic.iter().peekable());
+        loop {
+            let idx = match (s.peek(), d.peek()) {
+                (Some(v1), Some(v2)) => {
+                    if v1 < v2 {
+                        s.next()
+                    } else {
+                        d.next()
+                    }
+                }
+                (Some(_), None) => s.next(),
+                (None, Some(_)) => d.next(),
+                (None, None) => return,
+            };
+            if f(*idx.unwrap()) {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::Path;
+    use crate::router::Router;
+
+    #[test]
+    fn test_trie_matcher() {
+        let patterns = [
+            "/name",
+            "/name/{val}",
+            "/name/",
+            "/file/{file}.{ext}",
+            "/static/index.html",
+            "/v/{tail}*",
+            "/{test}/index.html",
+            "/static/about.html",
+            "",
+        ];
+        let paths = [
+            "/name",
+            "/name/",
+            "/name/value",
+            "/file/file.gz",
+            "/static/index.html",
+            "/static/about.html",
+            "/static/missing.html",
+            "/v/blah-blah/index.html",
+            "/bbb/index.html",
+            "/",
+            "",
+            "/unknown",
+        ];
+
+        let mut router = Router::<usize>::build();
+        let mut trie = Router::<usize>::build();
+        trie.matcher(TrieMatcher::default());
+        for (idx, p) in patterns.iter().enumerate() {
+            router.path(*p, idx);
+            trie.path(*p, idx);
+        }
+        router.prefix("/scope", 100);
+        trie.prefix("/scope", 100);
+        let (router, trie) = (router.finish(), trie.finish());
+
+        for p in paths.iter().chain(["/scope/test"].iter()) {
+            let mut p1 = Path::new(*p);
+            let mut p2 = Path::new(*p);
+            let r1 = router.recognize(&mut p1).map(|(v, _)| *v);
+            let r2 = trie.recognize(&mut p2).map(|(v, _)| *v);
+            assert_eq!(r1, r2, "path: {:?}", p);
+            assert_eq!(p1.path(), p2.path());
+            assert_eq!(p1.iter().collect::<Vec<_>>(), p2.iter().collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn test_trie_matcher_order() {
+        let mut router = Router::<usize, usize>::build();
+        router.matcher(TrieMatcher::default());
+        router.path("/{name}", 10).2 = Some(0);
+        router.path("/name", 11).2 = Some(1);
+        router.path("/name", 12).2 = Some(2);
+        router.path(["/test", "/name"], 13);
+        let router = router.finish();
+
+        let mut p = Path::new("/name");
+        assert_eq!(*router.recognize(&mut p).unwrap().0, 10);
+        assert_eq!(p.get("name"), Some("name"));
+
+        let mut p = Path::new("/name");
+        let res = router.recognize_checked(&mut p, |_, v| v == Some(&2));
+        assert_eq!(*res.unwrap().0, 12);
+        assert!(p.get("name").is_none());
+
+        let mut p = Path::new("/name");
+        let res = router.recognize_checked(&mut p, |_, v| v.is_none());
+        assert_eq!(*res.unwrap().0, 13);
+    }
+
+    #[test]
+    fn test_trie_matcher_insensitive() {
+        let mut router = Router::<usize>::build();
+        router.matcher(TrieMatcher::default());
+        router.case_insensitive();
+        router.path("/Index.json", 10);
+        router.path("/{source}.json", 11);
+        let router = router.finish();
+
+        let mut path = Path::new("/index.JSON");
+        assert_eq!(*router.recognize(&mut path).unwrap().0, 10);
+
+        let mut path = Path::new("/test.json");
+        assert_eq!(*router.recognize(&mut path).unwrap().0, 11);
+
+        let mut path = Path::new("/test.jsoN");
+        assert!(router.recognize(&mut path).is_none());
+    }
+}
@@ -331,6 +331,23 @@ mod tests {
         assert_eq!(res, Poll::Ready(()));
     }
 
+    #[ntex::test]
+    async fn test_service_fn() {
+        let srv = pipeline(Ready::<_, ()>::Ok)
+            .and_then_fn(Srv, |req: &'static str, s| async move {
+                s.call(()).await.map(move |res| (req, res))
+            })
+            .clone();
+        let res = lazy(|cx| srv.poll_ready(cx)).await;
+        assert_eq!(res, Poll::Ready(Ok(())));
+
+        let res = srv.call("srv").await;
+        assert_eq!(res, Ok(("srv", ())));
+
+        let res = lazy(|cx| srv.poll_shutdown(cx, false)).await;
+        assert_eq!(res, Poll::Ready(()));
+    }
+
     #[ntex::test]
     async fn test_service_factory() {
         let new_srv =
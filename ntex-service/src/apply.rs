@@ -1,4 +1,6 @@
-use std::{future::Future, marker::PhantomData, pin::Pin, task::Context, task::Poll};
+use std::{
+    future::Future, marker::PhantomData, pin::Pin, rc::Rc, task::Context, task::Poll,
+};
 
 use super::{IntoService, IntoServiceFactory, Service, ServiceFactory};
 
@@ -30,6 +32,46 @@ where
     ApplyServiceFactory::new(service.into_factory(), f)
 }
 
+/// Apply tranform function with shared state to a service.
+///
+/// State is passed to the function alongside request and inner service.
+pub fn apply_fn_with_state<T, F, R, In, Out, Err, St, U>(
+    service: U,
+    state: St,
+    f: F,
+) -> ApplyState<T, F, R, In, Out, Err, St>
+where
+    T: Service<Error = Err>,
+    F: Fn(In, &T, Rc<St>) -> R,
+    R: Future<Output = Result<Out, Err>>,
+    U: IntoService<T>,
+{
+    ApplyState::new(service.into_service(), Rc::new(state), f)
+}
+
+/// Service factory that prodices `apply_fn_with_state` service.
+///
+/// New state is constructed for each created service.
+pub fn apply_fn_factory_with_state<T, F, R, In, Out, Err, S, St, U>(
+    service: U,
+    state: S,
+    f: F,
+) -> ApplyStateServiceFactory<T, F, R, In, Out, Err, S>
+where
+    T: ServiceFactory<Error = Err>,
+    F: Fn(In, &T::Service, Rc<St>) -> R + Clone,
+    R: Future<Output = Result<Out, Err>>,
+    S: Fn() -> St,
+    U: IntoServiceFactory<T>,
+{
+    ApplyStateServiceFactory {
+        state,
+        f,
+        service: service.into_factory(),
+        r: PhantomData,
+    }
+}
+
 /// `Apply` service combinator
 pub struct Apply<T, F, R, In, Out, Err>
 where
@@ -209,10 +251,177 @@ where
     }
 }
 
+/// `apply_fn_with_state()` service combinator
+pub struct ApplyState<T, F, R, In, Out, Err, St>
+where
+    T: Service<Error = Err>,
+{
+    service: T,
+    state: Rc<St>,
+    f: F,
+    r: PhantomData<(In, Out, R)>,
+}
+
+impl<T, F, R, In, Out, Err, St> ApplyState<T, F, R, In, Out, Err, St>
+where
+    T: Service<Error = Err>,
+    F: Fn(In, &T, Rc<St>) -> R,
+    R: Future<Output = Result<Out, Err>>,
+{
+    fn new(service: T, state: Rc<St>, f: F) -> Self {
+        Self {
+            service,
+            state,
+            f,
+            r: PhantomData,
+        }
+    }
+
+    /// Get reference to shared state
+    pub fn state(&self) -> &Rc<St> {
+        &self.state
+    }
+}
+
+impl<T, F, R, In, Out, Err, St> Clone for ApplyState<T, F, R, In, Out, Err, St>
+where
+    T: Service<Error = Err> + Clone,
+    F: Fn(In, &T, Rc<St>) -> R + Clone,
+    R: Future<Output = Result<Out, Err>>,
+{
+    fn clone(&self) -> Self {
+        ApplyState {
+            service: self.service.clone(),
+            state: self.state.clone(),
+            f: self.f.clone(),
+            r: PhantomData,
+        }
+    }
+}
+
+impl<T, F, R, In, Out, Err, St> Service for ApplyState<T, F, R, In, Out, Err, St>
+where
+    T: Service<Error = Err>,
+    F: Fn(In, &T, Rc<St>) -> R,
+    R: Future<Output = Result<Out, Err>>,
+{
+    type Request = In;
+    type Response = Out;
+    type Error = Err;
+    type Future = R;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    #[inline]
+    fn call(&self, req: In) -> Self::Future {
+        (self.f)(req, &self.service, self.state.clone())
+    }
+}
+
+/// `apply_fn_factory_with_state()` service factory
+pub struct ApplyStateServiceFactory<T, F, R, In, Out, Err, S>
+where
+    T: ServiceFactory<Error = Err>,
+{
+    service: T,
+    state: S,
+    f: F,
+    r: PhantomData<(R, In, Out)>,
+}
+
+impl<T, F, R, In, Out, Err, S> Clone
+    for ApplyStateServiceFactory<T, F, R, In, Out, Err, S>
+where
+    T: ServiceFactory<Error = Err> + Clone,
+    F: Clone,
+    S: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            service: self.service.clone(),
+            state: self.state.clone(),
+            f: self.f.clone(),
+            r: PhantomData,
+        }
+    }
+}
+
+impl<T, F, R, In, Out, Err, S, St> ServiceFactory
+    for ApplyStateServiceFactory<T, F, R, In, Out, Err, S>
+where
+    T: ServiceFactory<Error = Err>,
+    F: Fn(In, &T::Service, Rc<St>) -> R + Clone,
+    R: Future<Output = Result<Out, Err>>,
+    S: Fn() -> St,
+{
+    type Request = In;
+    type Response = Out;
+    type Error = Err;
+
+    type Config = T::Config;
+    type Service = ApplyState<T::Service, F, R, In, Out, Err, St>;
+    type InitError = T::InitError;
+    type Future = ApplyStateServiceFactoryResponse<T, F, R, In, Out, Err, St>;
+
+    fn new_service(&self, cfg: T::Config) -> Self::Future {
+        ApplyStateServiceFactoryResponse {
+            fut: self.service.new_service(cfg),
+            state: Some(Rc::new((self.state)())),
+            f: Some(self.f.clone()),
+            r: PhantomData,
+        }
+    }
+}
+
+pin_project_lite::pin_project! {
+pub struct ApplyStateServiceFactoryResponse<T, F, R, In, Out, Err, St>
+where
+    T: ServiceFactory<Error = Err>,
+{
+    #[pin]
+    fut: T::Future,
+    state: Option<Rc<St>>,
+    f: Option<F>,
+    r: PhantomData<(R, In, Out)>,
+}
+}
+
+impl<T, F, R, In, Out, Err, St> Future
+    for ApplyStateServiceFactoryResponse<T, F, R, In, Out, Err, St>
+where
+    T: ServiceFactory<Error = Err>,
+    F: Fn(In, &T::Service, Rc<St>) -> R,
+    R: Future<Output = Result<Out, Err>>,
+{
+    type Output = Result<ApplyState<T::Service, F, R, In, Out, Err, St>, T::InitError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        if let Poll::Ready(svc) = this.fut.poll(cx)? {
+            Poll::Ready(Ok(ApplyState::new(
+                svc,
+                this.state.take().unwrap(),
+                this.f.take().unwrap(),
+            )))
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use ntex_util::future::{lazy, Ready};
-    use std::task::{Context, Poll};
+    use std::{cell::Cell, task::Context, task::Poll};
 
     use super::*;
     use crate::{pipeline, pipeline_factory, Service, ServiceFactory};
@@ -283,4 +492,54 @@ mod tests {
         assert!(res.is_ok());
         assert_eq!(res.unwrap(), ("srv", ()));
     }
+
+    #[ntex::test]
+    async fn test_call_with_state() {
+        let srv = apply_fn_with_state(Srv, Cell::new(0), |req: usize, srv, st| {
+            let fut = srv.call(());
+            async move {
+                fut.await.unwrap();
+                st.set(st.get() + req);
+                Ok(st.get())
+            }
+        })
+        .clone();
+
+        assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Ready(Ok(())));
+        assert_eq!(
+            lazy(|cx| srv.poll_shutdown(cx, true)).await,
+            Poll::Ready(())
+        );
+        assert_eq!(srv.call(1).await, Ok(1));
+        assert_eq!(srv.call(2).await, Ok(3));
+        assert_eq!(srv.state().get(), 3);
+    }
+
+    #[ntex::test]
+    async fn test_new_service_with_state() {
+        let new_srv = pipeline_factory(
+            apply_fn_factory_with_state(
+                || Ready::<_, ()>::Ok(Srv),
+                || Cell::new(0),
+                |req: usize, srv, st| {
+                    let fut = srv.call(());
+                    async move {
+                        fut.await.unwrap();
+                        st.set(st.get() + req);
+                        Ok(st.get())
+                    }
+                },
+            )
+            .clone(),
+        );
+
+        let srv = new_srv.new_service(()).await.unwrap();
+        assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Ready(Ok(())));
+        assert_eq!(srv.call(1).await, Ok(1));
+        assert_eq!(srv.call(2).await, Ok(3));
+
+        // each service gets new state
+        let srv = new_srv.new_service(()).await.unwrap();
+        assert_eq!(srv.call(1).await, Ok(1));
+    }
 }
@@ -1,5 +1,5 @@
 use std::task::{Context, Poll};
-use std::{cell::Cell, cell::RefCell, future::Future, marker::PhantomData};
+use std::{cell::Cell, cell::RefCell, future::Future, marker::PhantomData, rc::Rc};
 
 use ntex_util::future::Ready;
 
@@ -27,6 +27,46 @@ where
     FnMutService::new(f)
 }
 
+#[inline]
+/// Create `ServiceFactory` for function with shared state
+///
+/// State is stored in `Rc` and passed to the function with each request,
+/// all services created by the factory share same state.
+///
+/// # Example
+///
+/// ```rust
+/// use std::cell::Cell;
+/// use ntex_service::{fn_service_with_state, Service, ServiceFactory};
+///
+/// #[ntex::main]
+/// async fn main() {
+///     let factory = fn_service_with_state(Cell::new(0), |n: usize, st| async move {
+///         st.set(st.get() + n);
+///         Ok::<_, ()>(st.get())
+///     });
+///
+///     let srv = factory.new_service(()).await.unwrap();
+///     assert_eq!(srv.call(1).await, Ok(1));
+///     assert_eq!(srv.call(2).await, Ok(3));
+///     assert_eq!(factory.state().get(), 3);
+/// }
+/// ```
+pub fn fn_service_with_state<F, St, Fut, Req, Res, Err, Cfg>(
+    state: St,
+    f: F,
+) -> FnStateServiceFactory<F, St, Fut, Req, Res, Err, Cfg>
+where
+    F: Fn(Req, Rc<St>) -> Fut,
+    Fut: Future<Output = Result<Res, Err>>,
+{
+    FnStateServiceFactory {
+        f: Rc::new(f),
+        state: Rc::new(state),
+        _t: PhantomData,
+    }
+}
+
 #[inline]
 /// Create `ServiceFactory` for function that can produce services
 ///
@@ -469,6 +509,128 @@ where
     }
 }
 
+/// `ServiceFactory` for function with shared state
+pub struct FnStateServiceFactory<F, St, Fut, Req, Res, Err, Cfg = ()>
+where
+    F: Fn(Req, Rc<St>) -> Fut,
+    Fut: Future<Output = Result<Res, Err>>,
+{
+    f: Rc<F>,
+    state: Rc<St>,
+    _t: PhantomData<(Req, Cfg)>,
+}
+
+impl<F, St, Fut, Req, Res, Err, Cfg>
+    FnStateServiceFactory<F, St, Fut, Req, Res, Err, Cfg>
+where
+    F: Fn(Req, Rc<St>) -> Fut,
+    Fut: Future<Output = Result<Res, Err>>,
+{
+    /// Get reference to shared state
+    pub fn state(&self) -> &Rc<St> {
+        &self.state
+    }
+}
+
+impl<F, St, Fut, Req, Res, Err, Cfg> Clone
+    for FnStateServiceFactory<F, St, Fut, Req, Res, Err, Cfg>
+where
+    F: Fn(Req, Rc<St>) -> Fut,
+    Fut: Future<Output = Result<Res, Err>>,
+{
+    #[inline]
+    fn clone(&self) -> Self {
+        Self {
+            f: self.f.clone(),
+            state: self.state.clone(),
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<F, St, Fut, Req, Res, Err, Cfg> ServiceFactory
+    for FnStateServiceFactory<F, St, Fut, Req, Res, Err, Cfg>
+where
+    F: Fn(Req, Rc<St>) -> Fut,
+    Fut: Future<Output = Result<Res, Err>>,
+{
+    type Request = Req;
+    type Response = Res;
+    type Error = Err;
+
+    type Config = Cfg;
+    type Service = FnStateService<F, St, Fut, Req, Res, Err>;
+    type InitError = ();
+    type Future = Ready<Self::Service, Self::InitError>;
+
+    #[inline]
+    fn new_service(&self, _: Cfg) -> Self::Future {
+        Ready::Ok(FnStateService {
+            f: self.f.clone(),
+            state: self.state.clone(),
+            _t: PhantomData,
+        })
+    }
+}
+
+/// Service for function with shared state
+pub struct FnStateService<F, St, Fut, Req, Res, Err>
+where
+    F: Fn(Req, Rc<St>) -> Fut,
+    Fut: Future<Output = Result<Res, Err>>,
+{
+    f: Rc<F>,
+    state: Rc<St>,
+    _t: PhantomData<Req>,
+}
+
+impl<F, St, Fut, Req, Res, Err> FnStateService<F, St, Fut, Req, Res, Err>
+where
+    F: Fn(Req, Rc<St>) -> Fut,
+    Fut: Future<Output = Result<Res, Err>>,
+{
+    /// Get reference to shared state
+    pub fn state(&self) -> &Rc<St> {
+        &self.state
+    }
+}
+
+impl<F, St, Fut, Req, Res, Err> Clone for FnStateService<F, St, Fut, Req, Res, Err>
+where
+    F: Fn(Req, Rc<St>) -> Fut,
+    Fut: Future<Output = Result<Res, Err>>,
+{
+    #[inline]
+    fn clone(&self) -> Self {
+        Self {
+            f: self.f.clone(),
+            state: self.state.clone(),
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<F, St, Fut, Req, Res, Err> Service for FnStateService<F, St, Fut, Req, Res, Err>
+where
+    F: Fn(Req, Rc<St>) -> Fut,
+    Fut: Future<Output = Result<Res, Err>>,
+{
+    type Request = Req;
+    type Response = Res;
+    type Error = Err;
+    type Future = Fut;
+
+    #[inline]
+    fn poll_ready(&self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    #[inline]
+    fn call(&self, req: Req) -> Self::Future {
+        (self.f)(req, self.state.clone())
+    }
+}
+
 pub struct FnMutService<F, Fut, Req, Res, Err>
 where
     F: FnMut(Req) -> Fut,
@@ -588,6 +750,23 @@ mod tests {
         assert!(*shutdown.borrow());
     }
 
+    #[ntex::test]
+    async fn test_fn_service_with_state() {
+        let factory = fn_service_with_state(Cell::new(0), |n: usize, st| async move {
+            st.set(st.get() + n);
+            Ok::<_, ()>(st.get())
+        })
+        .clone();
+
+        let srv = factory.new_service(()).await.unwrap().clone();
+        let srv2 = factory.new_service(()).await.unwrap();
+        assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Ready(Ok(())));
+        assert_eq!(srv.call(1).await, Ok(1));
+        assert_eq!(srv2.call(2).await, Ok(3));
+        assert_eq!(srv.state().get(), 3);
+        assert_eq!(factory.state().get(), 3);
+    }
+
     #[ntex::test]
     async fn test_fn_service_with_config() {
         let new_srv = fn_factory_with_config(|cfg: usize| async move {
@@ -1,5 +1,5 @@
 use ntex_util::future::Ready;
-use std::{future::Future, marker::PhantomData};
+use std::{future::Future, marker::PhantomData, rc::Rc, task::Context, task::Poll};
 
 use crate::{apply_fn, dev::Apply, Service, Transform};
 
@@ -65,14 +65,126 @@ where
     }
 }
 
+/// Use async function as transform service
+///
+/// Unlike `fn_transform`, function receives rc wrapped inner service,
+/// so it could be moved to the returned future.
+///
+/// ```rust
+/// use std::rc::Rc;
+/// use ntex_service::{fn_middleware, Service, Transform};
+///
+/// fn double<S>() -> impl Transform<S, Request = usize, Response = usize, InitError = ()>
+/// where
+///     S: Service<Request = usize, Response = usize>,
+/// {
+///     fn_middleware(|req: usize, srv: Rc<S>| async move {
+///         let res = srv.call(req).await?;
+///         Ok(res * 2)
+///     })
+/// }
+/// ```
+pub fn fn_middleware<S, F, R, Req, Res, Err>(
+    f: F,
+) -> FnMiddleware<S, F, R, Req, Res, Err>
+where
+    S: Service<Error = Err>,
+    F: Fn(Req, Rc<S>) -> R + Clone,
+    R: Future<Output = Result<Res, Err>>,
+{
+    FnMiddleware { f, _t: PhantomData }
+}
+
+/// `fn_middleware()` transform
+pub struct FnMiddleware<S, F, R, Req, Res, Err> {
+    f: F,
+    _t: PhantomData<(S, R, Req, Res, Err)>,
+}
+
+impl<S, F, R, Req, Res, Err> Transform<S> for FnMiddleware<S, F, R, Req, Res, Err>
+where
+    S: Service<Error = Err>,
+    F: Fn(Req, Rc<S>) -> R + Clone,
+    R: Future<Output = Result<Res, Err>>,
+{
+    type Request = Req;
+    type Response = Res;
+    type Error = Err;
+    type Transform = FnMiddlewareService<S, F, R, Req, Res, Err>;
+    type InitError = ();
+    type Future = Ready<Self::Transform, Self::InitError>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        Ready::Ok(FnMiddlewareService {
+            service: Rc::new(service),
+            f: self.f.clone(),
+            _t: PhantomData,
+        })
+    }
+}
+
+impl<S, F: Clone, R, Req, Res, Err> Clone for FnMiddleware<S, F, R, Req, Res, Err> {
+    fn clone(&self) -> Self {
+        FnMiddleware {
+            f: self.f.clone(),
+            _t: PhantomData,
+        }
+    }
+}
+
+/// `fn_middleware()` service
+pub struct FnMiddlewareService<S, F, R, Req, Res, Err> {
+    service: Rc<S>,
+    f: F,
+    _t: PhantomData<(R, Req, Res, Err)>,
+}
+
+impl<S, F: Clone, R, Req, Res, Err> Clone
+    for FnMiddlewareService<S, F, R, Req, Res, Err>
+{
+    fn clone(&self) -> Self {
+        FnMiddlewareService {
+            service: self.service.clone(),
+            f: self.f.clone(),
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<S, F, R, Req, Res, Err> Service for FnMiddlewareService<S, F, R, Req, Res, Err>
+where
+    S: Service<Error = Err>,
+    F: Fn(Req, Rc<S>) -> R,
+    R: Future<Output = Result<Res, Err>>,
+{
+    type Request = Req;
+    type Response = Res;
+    type Error = Err;
+    type Future = R;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    #[inline]
+    fn call(&self, req: Req) -> Self::Future {
+        (self.f)(req, self.service.clone())
+    }
+}
+
 #[cfg(test)]
 #[allow(clippy::redundant_clone)]
 mod tests {
     use ntex_util::future::lazy;
-    use std::task::{Context, Poll};
 
     use super::*;
-    use crate::Service;
+    use crate::{apply, fn_factory, Service, ServiceFactory};
 
     #[derive(Clone)]
     struct Srv;
@@ -111,4 +223,23 @@ mod tests {
         let res = lazy(|cx| srv.poll_shutdown(cx, true)).await;
         assert_eq!(res, Poll::Ready(()));
     }
+
+    #[ntex::test]
+    async fn middleware() {
+        let factory = apply(
+            fn_middleware(|i: usize, srv: Rc<Srv>| async move {
+                let res = srv.call(i + 1).await?;
+                Ok::<_, ()>(res.to_string())
+            }),
+            fn_factory(|| async { Ok::<_, ()>(Srv) }),
+        );
+        let srv = factory.clone().new_service(()).await.unwrap().clone();
+
+        let res = lazy(|cx| srv.poll_ready(cx)).await;
+        assert_eq!(res, Poll::Ready(Ok(())));
+        let res = lazy(|cx| srv.poll_shutdown(cx, true)).await;
+        assert_eq!(res, Poll::Ready(()));
+
+        assert_eq!(srv.call(10).await, Ok("22".to_string()));
+    }
 }
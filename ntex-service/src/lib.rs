@@ -21,11 +21,14 @@ mod then;
 mod transform;
 mod transform_err;
 
-pub use self::apply::{apply_fn, apply_fn_factory};
+pub use self::apply::{
+    apply_fn, apply_fn_factory, apply_fn_factory_with_state, apply_fn_with_state,
+};
 pub use self::fn_service::{
     fn_factory, fn_factory_with_config, fn_mut_service, fn_service,
+    fn_service_with_state,
 };
-pub use self::fn_transform::fn_transform;
+pub use self::fn_transform::{fn_middleware, fn_transform};
 pub use self::map_config::{map_config, map_config_service, unit_config};
 pub use self::pipeline::{pipeline, pipeline_factory, Pipeline, PipelineFactory};
 pub use self::transform::{apply, Transform};
@@ -155,6 +158,18 @@ pub trait Service {
     {
         crate::dev::MapErr::new(self, f)
     }
+
+    #[inline]
+    /// Convert this service's error to a different error via `From` trait.
+    ///
+    /// This function is similar to `map_err` but does not require closure.
+    fn map_err_into<E>(self) -> crate::dev::MapErr<Self, fn(Self::Error) -> E, E>
+    where
+        Self: Sized,
+        E: From<Self::Error>,
+    {
+        crate::dev::MapErr::new(self, E::from)
+    }
 }
 
 /// Creates new `Service` values.
@@ -216,6 +231,18 @@ pub trait ServiceFactory {
         crate::map_err::MapErrServiceFactory::new(self, f)
     }
 
+    #[inline]
+    /// Convert this service's error to a different error via `From` trait.
+    fn map_err_into<E>(
+        self,
+    ) -> crate::map_err::MapErrServiceFactory<Self, fn(Self::Error) -> E, E>
+    where
+        Self: Sized,
+        E: From<Self::Error>,
+    {
+        crate::map_err::MapErrServiceFactory::new(self, E::from)
+    }
+
     #[inline]
     /// Map this factory's init error to a different error, returning a new service.
     fn map_init_err<F, E>(self, f: F) -> crate::map_init_err::MapInitErr<Self, F, E>
@@ -225,6 +252,18 @@ pub trait ServiceFactory {
     {
         crate::map_init_err::MapInitErr::new(self, f)
     }
+
+    #[inline]
+    /// Convert this factory's init error to a different error via `From` trait.
+    fn map_init_err_into<E>(
+        self,
+    ) -> crate::map_init_err::MapInitErr<Self, fn(Self::InitError) -> E, E>
+    where
+        Self: Sized,
+        E: From<Self::InitError>,
+    {
+        crate::map_init_err::MapInitErr::new(self, E::from)
+    }
 }
 
 impl<S> Service for Box<S>
@@ -338,10 +377,14 @@ where
 }
 
 pub mod dev {
-    pub use crate::apply::{Apply, ApplyServiceFactory};
+    pub use crate::apply::{
+        Apply, ApplyServiceFactory, ApplyState, ApplyStateServiceFactory,
+    };
     pub use crate::fn_service::{
         FnMutService, FnService, FnServiceConfig, FnServiceFactory, FnServiceNoConfig,
+        FnStateService, FnStateServiceFactory,
     };
+    pub use crate::fn_transform::{FnMiddleware, FnMiddlewareService};
     pub use crate::map::{Map, MapServiceFactory};
     pub use crate::map_config::{MapConfig, UnitConfig};
     pub use crate::map_err::{MapErr, MapErrServiceFactory};
@@ -278,4 +278,37 @@ mod tests {
         assert!(res.is_err());
         assert_eq!(res.err().unwrap(), "error");
     }
+
+    #[derive(Debug, PartialEq)]
+    struct Error;
+
+    impl From<()> for Error {
+        fn from(_: ()) -> Self {
+            Error
+        }
+    }
+
+    #[ntex::test]
+    async fn test_map_err_into() {
+        let srv = Srv.map_err_into::<Error>();
+        let res = lazy(|cx| srv.poll_ready(cx)).await;
+        assert_eq!(res, Poll::Ready(Err(Error)));
+        assert_eq!(srv.call(()).await, Err(Error));
+
+        let srv = crate::pipeline(Srv).map_err_into::<Error>().clone();
+        assert_eq!(srv.call(()).await, Err(Error));
+
+        let new_srv = (|| Ready::<_, ()>::Ok(Srv))
+            .into_factory()
+            .map_err_into::<Error>();
+        let srv = new_srv.new_service(&()).await.unwrap();
+        assert_eq!(srv.call(()).await, Err(Error));
+
+        let new_srv =
+            crate::pipeline_factory((|| async { Ok::<_, ()>(Srv) }).into_factory())
+                .map_err_into::<Error>()
+                .clone();
+        let srv = new_srv.new_service(&()).await.unwrap();
+        assert_eq!(srv.call(()).await, Err(Error));
+    }
 }
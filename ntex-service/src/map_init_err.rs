@@ -111,4 +111,27 @@ mod tests {
         assert!(factory.new_service(true).await.is_err());
         assert!(factory.new_service(false).await.is_ok());
     }
+
+    #[ntex::test]
+    async fn map_init_err_into() {
+        let new_factory = || {
+            fn_factory_with_config(|err: bool| async move {
+                if err {
+                    Err("err")
+                } else {
+                    Ok(fn_service(|i: usize| async move { Ok::<_, ()>(i * 2) }))
+                }
+            })
+        };
+
+        let factory = pipeline_factory(new_factory())
+            .map_init_err_into::<Box<dyn std::error::Error>>()
+            .clone();
+        let err = factory.new_service(true).await.err().unwrap();
+        assert_eq!(err.to_string(), "err");
+        assert!(factory.new_service(false).await.is_ok());
+
+        let factory = new_factory().map_init_err_into::<String>();
+        assert_eq!(factory.new_service(true).await.err().unwrap(), "err");
+    }
 }
@@ -1,5 +1,5 @@
-use std::future::Future;
 use std::task::{Context, Poll};
+use std::{future::Future, rc::Rc};
 
 use crate::and_then::{AndThenService, AndThenServiceFactory};
 use crate::and_then_apply_fn::{AndThenApplyFn, AndThenApplyFnFactory};
@@ -84,6 +84,48 @@ impl<T: Service> Pipeline<T> {
         }
     }
 
+    /// Apply async function to specified service and use it as a next service
+    /// in chain.
+    ///
+    /// Unlike `and_then_apply_fn`, function receives rc wrapped service, so
+    /// it could be moved to the returned future.
+    ///
+    /// ```rust
+    /// use ntex_service::{fn_service, pipeline, Service};
+    ///
+    /// # #[ntex::main]
+    /// # async fn main() {
+    /// let next = fn_service(|req: usize| async move { Ok::<_, ()>(req * 2) });
+    /// let srv = pipeline(fn_service(|req: usize| async move { Ok::<_, ()>(req + 1) }))
+    ///     .and_then_fn(next, |res, srv| async move {
+    ///         let res = srv.call(res).await?;
+    ///         Ok::<_, ()>(res.to_string())
+    ///     });
+    /// assert_eq!(srv.call(1).await, Ok("4".to_string()));
+    /// # }
+    /// ```
+    pub fn and_then_fn<U, I, F, Fut, Res, Err>(
+        self,
+        service: I,
+        f: F,
+    ) -> Pipeline<impl Service<Request = T::Request, Response = Res, Error = Err> + Clone>
+    where
+        Self: Sized,
+        I: IntoService<U>,
+        U: Service,
+        F: Fn(T::Response, Rc<U>) -> Fut,
+        Fut: Future<Output = Result<Res, Err>>,
+        Err: From<T::Error> + From<U::Error>,
+    {
+        Pipeline {
+            service: AndThenApplyFn::new(
+                self.service,
+                Rc::new(service.into_service()),
+                move |res, srv: &Rc<U>| f(res, srv.clone()),
+            ),
+        }
+    }
+
     /// Chain on a computation for when a call to the service finished,
     /// passing the result of the call to the next service `U`.
     ///
@@ -141,6 +183,17 @@ impl<T: Service> Pipeline<T> {
             service: MapErr::new(self.service, f),
         }
     }
+
+    /// Convert this service's error to a different error via `From` trait.
+    pub fn map_err_into<E>(self) -> Pipeline<MapErr<T, fn(T::Error) -> E, E>>
+    where
+        Self: Sized,
+        E: From<T::Error>,
+    {
+        Pipeline {
+            service: MapErr::new(self.service, E::from),
+        }
+    }
 }
 
 impl<T> Clone for Pipeline<T>
@@ -311,6 +364,19 @@ impl<T: ServiceFactory> PipelineFactory<T> {
         }
     }
 
+    /// Convert this service's error to a different error via `From` trait.
+    pub fn map_err_into<E>(
+        self,
+    ) -> PipelineFactory<MapErrServiceFactory<T, fn(T::Error) -> E, E>>
+    where
+        Self: Sized,
+        E: From<T::Error>,
+    {
+        PipelineFactory {
+            factory: MapErrServiceFactory::new(self.factory, E::from),
+        }
+    }
+
     /// Map this factory's init error to a different error, returning a new service.
     pub fn map_init_err<F, E>(self, f: F) -> PipelineFactory<MapInitErr<T, F, E>>
     where
@@ -321,6 +387,19 @@ impl<T: ServiceFactory> PipelineFactory<T> {
             factory: MapInitErr::new(self.factory, f),
         }
     }
+
+    /// Convert this factory's init error to a different error via `From` trait.
+    pub fn map_init_err_into<E>(
+        self,
+    ) -> PipelineFactory<MapInitErr<T, fn(T::InitError) -> E, E>>
+    where
+        Self: Sized,
+        E: From<T::InitError>,
+    {
+        PipelineFactory {
+            factory: MapInitErr::new(self.factory, E::from),
+        }
+    }
 }
 
 impl<T> Clone for PipelineFactory<T>
@@ -0,0 +1,141 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use ntex::codec::{BytesCodec, Decoder, Encoder};
+use ntex::framed::{DispatchItem, Dispatcher, State, Timer};
+use ntex::rt::{System, SystemRunner};
+use ntex::testing::Io;
+use ntex::util::{Buf, BufMut, Bytes, BytesMut};
+use std::io;
+
+const SIZES: &[usize] = &[64, 1024, 16 * 1024];
+const FRAMES: usize = 32;
+
+/// Length delimited codec, 4 bytes big-endian length prefix
+struct LengthCodec;
+
+impl Decoder for LengthCodec {
+    type Item = Bytes;
+    type Error = io::Error;
+
+    fn decode(&self, src: &mut BytesMut) -> Result<Option<Bytes>, io::Error> {
+        if src.len() < 4 {
+            return Ok(None);
+        }
+        let len = u32::from_be_bytes([src[0], src[1], src[2], src[3]]) as usize;
+        if src.len() < len + 4 {
+            Ok(None)
+        } else {
+            src.advance(4);
+            Ok(Some(src.split_to(len).freeze()))
+        }
+    }
+
+    fn decode_hint(&self, src: &BytesMut) -> usize {
+        if src.len() < 4 {
+            4 - src.len()
+        } else {
+            let len = u32::from_be_bytes([src[0], src[1], src[2], src[3]]) as usize;
+            (len + 4).saturating_sub(src.len())
+        }
+    }
+}
+
+impl Encoder for LengthCodec {
+    type Item = Bytes;
+    type Error = io::Error;
+
+    fn encode(&self, item: Bytes, dst: &mut BytesMut) -> Result<(), io::Error> {
+        dst.reserve(item.len() + 4);
+        dst.put_u32(item.len() as u32);
+        dst.extend_from_slice(&item);
+        Ok(())
+    }
+}
+
+/// Start echo dispatcher, returns client side of the connection
+fn start<U>(sys: &mut SystemRunner, codec: U) -> Io
+where
+    U: Decoder + Encoder<Item = Bytes> + 'static,
+    <U as Decoder>::Item: Into<Bytes>,
+{
+    sys.block_on(async move {
+        let (client, server) = Io::create();
+
+        let disp = Dispatcher::new(
+            server,
+            codec,
+            State::new(),
+            ntex::fn_service(|msg: DispatchItem<U>| async move {
+                if let DispatchItem::Item(msg) = msg {
+                    Ok::<_, ()>(Some(msg.into()))
+                } else {
+                    Ok(None)
+                }
+            }),
+            Timer::default(),
+        );
+        ntex::rt::spawn(async move {
+            let _ = disp.await;
+        });
+        client
+    })
+}
+
+/// Send data to dispatcher and wait for echo
+fn roundtrip(sys: &mut SystemRunner, client: &Io, data: &Bytes) {
+    sys.block_on(async {
+        client.remote_buffer_cap(data.len());
+        client.write(data);
+        let mut received = 0;
+        while received < data.len() {
+            received += client.read().await.unwrap().len();
+        }
+    })
+}
+
+fn payload<U: Encoder<Item = Bytes>>(codec: &U, size: usize) -> Bytes {
+    let mut buf = BytesMut::new();
+    for _ in 0..FRAMES {
+        codec
+            .encode(Bytes::from(vec![b'x'; size]), &mut buf)
+            .ok()
+            .unwrap();
+    }
+    buf.freeze()
+}
+
+fn bench_codec<U>(c: &mut Criterion, name: &str, codec: fn() -> U)
+where
+    U: Decoder + Encoder<Item = Bytes> + 'static,
+    <U as Decoder>::Item: Into<Bytes>,
+{
+    let mut sys = System::new("bench");
+    let mut group = c.benchmark_group(name);
+
+    for size in SIZES {
+        let data = payload(&codec(), *size);
+        let client = start(&mut sys, codec());
+
+        #[cfg(feature = "bench-metrics")]
+        ntex::framed::metrics::reset();
+
+        group.throughput(Throughput::Bytes(data.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &data, |b, data| {
+            b.iter(|| roundtrip(&mut sys, &client, data))
+        });
+
+        #[cfg(feature = "bench-metrics")]
+        println!("{}/{}: {:?}", name, size, ntex::framed::metrics::snapshot());
+    }
+    group.finish();
+}
+
+fn bytes_codec(c: &mut Criterion) {
+    bench_codec(c, "dispatcher/bytes", || BytesCodec);
+}
+
+fn length_codec(c: &mut Criterion) {
+    bench_codec(c, "dispatcher/length", || LengthCodec);
+}
+
+criterion_group!(benches, bytes_codec, length_codec);
+criterion_main!(benches);
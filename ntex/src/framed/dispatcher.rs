@@ -1,15 +1,26 @@
 //! Framed transport dispatcher
 use std::{
-    cell::Cell, cell::RefCell, future::Future, pin::Pin, rc::Rc, task::Context,
-    task::Poll, time::Duration, time::Instant,
+    cell::Cell, cell::RefCell, collections::VecDeque, future::Future, pin::Pin, rc::Rc,
+    task::Context, task::Poll, task::Waker, time::Duration, time::Instant,
 };
 
 use crate::codec::{AsyncRead, AsyncWrite, Decoder, Encoder};
 use crate::framed::{DispatchItem, Read, ReadTask, State, Timer, Write, WriteTask};
+use crate::rt::time::{sleep, Sleep};
+use crate::service::boxed::{self, BoxService};
 use crate::service::{IntoService, Service};
 use crate::util::Either;
 
+/// Record tracing event, if `tracing` feature is enabled
+macro_rules! trace_event {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing_pkg::trace!($($arg)*);
+    };
+}
+
 type Response<U> = <U as Encoder>::Item;
+type ServiceResult<S, U> = Result<Option<Response<U>>, <S as Service>::Error>;
 
 pin_project_lite::pin_project! {
     /// Framed dispatcher - is a future that reads frames from Framed object
@@ -27,6 +38,8 @@ pin_project_lite::pin_project! {
         inner: DispatcherInner<S, U>,
         #[pin]
         fut: Option<S::Future>,
+        flush_delay: Option<Pin<Box<Sleep>>>,
+        rate_delay: Option<Pin<Box<Sleep>>>,
     }
 }
 
@@ -40,8 +53,38 @@ where
     timer: Timer,
     ka_timeout: u16,
     ka_updated: Cell<Instant>,
+    ka_io: bool,
+    timestamps: bool,
+    flush_timeout: u16,
+    rate_limit: Option<RateLimit>,
     error: Cell<Option<S::Error>>,
     shared: Rc<DispatcherShared<S, U>>,
+    reason: Cell<Option<ShutdownReason>>,
+    on_shutdown: Cell<Option<Box<dyn FnOnce(ShutdownReason)>>>,
+    error_service: Option<BoxService<DispatchItem<U>, Option<Response<U>>, S::Error>>,
+    #[cfg(feature = "tracing")]
+    span: tracing_pkg::Span,
+}
+
+/// Dispatcher shutdown reason
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ShutdownReason {
+    /// Dispatcher is stopped with `State::close()`
+    Closed,
+    /// Peer disconnected
+    Disconnected,
+    /// Io error
+    Io,
+    /// Keep-alive timeout
+    KeepAlive,
+    /// Decoder error
+    Decoder,
+    /// Encoder error
+    Encoder,
+    /// Service error
+    Service,
+    /// Read buffer exceeds max frame size
+    FrameTooLarge,
 }
 
 struct DispatcherShared<S, U>
@@ -49,9 +92,96 @@ where
     S: Service<Request = DispatchItem<U>, Response = Option<Response<U>>>,
     U: Encoder + Decoder,
 {
-    codec: U,
+    codec: RefCell<U>,
     error: Cell<Option<DispatcherError<S::Error, <U as Encoder>::Error>>>,
     inflight: Cell<usize>,
+    ordered: Cell<bool>,
+    responses: RefCell<VecDeque<Option<ServiceResult<S, U>>>>,
+    responses_seq: Cell<usize>,
+    responses_task: RefCell<Vec<Waker>>,
+}
+
+/// Dispatcher's encoder for streaming responses, see `StreamService`
+pub(super) trait StreamSink<U: Encoder> {
+    /// Response slot of the next service call, if dispatcher is in ordered mode
+    fn next_response(&self) -> Option<usize>;
+
+    /// Check if all responses preceding `seq` are written
+    fn poll_response_turn(&self, seq: Option<usize>, cx: &mut Context<'_>) -> Poll<()>;
+
+    /// Encode item with dispatcher's codec
+    ///
+    /// Returns `false` if encoder failed, error is handled by dispatcher.
+    fn encode(&self, item: <U as Encoder>::Item, write: Write<'_>) -> bool;
+}
+
+/// Token bucket for incoming frames
+struct RateLimit {
+    frames: u64,
+    bytes: u64,
+    // budgets are in 1/1000 of frame or byte
+    frames_budget: Cell<i64>,
+    bytes_budget: Cell<i64>,
+    updated: Cell<Instant>,
+}
+
+impl RateLimit {
+    fn new(frames: u32, bytes: u32) -> Self {
+        RateLimit {
+            frames: frames as u64,
+            bytes: bytes as u64,
+            frames_budget: Cell::new(frames as i64 * 1000),
+            bytes_budget: Cell::new(bytes as i64 * 1000),
+            updated: Cell::new(Instant::now()),
+        }
+    }
+
+    /// Get delay until next frame is allowed
+    fn delay(&self) -> Option<Duration> {
+        let now = Instant::now();
+        let elapsed = (now - self.updated.get()).as_millis() as i64;
+        if elapsed > 0 {
+            self.updated.set(now);
+            Self::refill(&self.frames_budget, self.frames, elapsed);
+            Self::refill(&self.bytes_budget, self.bytes, elapsed);
+        }
+
+        let frames = Self::wait(&self.frames_budget, self.frames);
+        let bytes = Self::wait(&self.bytes_budget, self.bytes);
+        let millis = std::cmp::max(frames, bytes);
+        if millis > 0 {
+            Some(Duration::from_millis(millis as u64))
+        } else {
+            None
+        }
+    }
+
+    fn consume(&self, bytes: usize) {
+        if self.frames != 0 {
+            self.frames_budget.set(self.frames_budget.get() - 1000);
+        }
+        if self.bytes != 0 {
+            self.bytes_budget
+                .set(self.bytes_budget.get() - bytes as i64 * 1000);
+        }
+    }
+
+    fn refill(budget: &Cell<i64>, rate: u64, elapsed: i64) {
+        if rate != 0 {
+            let max = rate as i64 * 1000;
+            budget.set(std::cmp::min(max, budget.get() + elapsed * rate as i64));
+        }
+    }
+
+    /// millis until budget allows one more frame
+    fn wait(budget: &Cell<i64>, rate: u64) -> i64 {
+        let budget = budget.get();
+        if rate == 0 || budget >= 1000 {
+            0
+        } else {
+            (1000 - budget + rate as i64 - 1) / rate as i64
+        }
+    }
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -90,6 +220,11 @@ where
     <U as Encoder>::Item: 'static,
 {
     /// Construct new `Dispatcher` instance.
+    ///
+    /// Io object does not need to be `Unpin`, it is pinned in place
+    /// and owned by read and write io tasks. Io object could not be
+    /// taken back with `State::take_io()`, use `Dispatcher::from_state()`
+    /// and start io tasks manually instead.
     pub fn new<T, F: IntoService<S>>(
         io: T,
         codec: U,
@@ -98,13 +233,16 @@ where
         timer: Timer,
     ) -> Self
     where
-        T: AsyncRead + AsyncWrite + Unpin + 'static,
+        T: AsyncRead + AsyncWrite + 'static,
     {
         let io = Rc::new(RefCell::new(io));
 
         // start support tasks
-        crate::rt::spawn(ReadTask::new(io.clone(), state.clone()));
-        crate::rt::spawn(WriteTask::new(io, state.clone()));
+        // io object is private to read and write tasks, it never moves
+        unsafe {
+            crate::rt::spawn(ReadTask::new_unchecked(io.clone(), state.clone()));
+            crate::rt::spawn(WriteTask::new_unchecked(io, state.clone()));
+        }
 
         Self::from_state(codec, state, service, timer)
     }
@@ -123,21 +261,40 @@ where
         let expire = updated + Duration::from_secs(ka_timeout as u64);
         timer.register(expire, expire, &state);
 
+        let shared = Rc::new(DispatcherShared {
+            codec: RefCell::new(codec),
+            error: Cell::new(None),
+            inflight: Cell::new(0),
+            ordered: Cell::new(false),
+            responses: RefCell::new(VecDeque::new()),
+            responses_seq: Cell::new(0),
+            responses_task: RefCell::new(Vec::new()),
+        });
+        // streaming responses are encoded with dispatcher's codec
+        state.set_stream_sink(Box::new(shared.clone() as Rc<dyn StreamSink<U>>));
+
         Dispatcher {
             service: service.into_service(),
             fut: None,
+            flush_delay: None,
+            rate_delay: None,
             inner: DispatcherInner {
                 state,
                 timer,
                 ka_timeout,
                 ka_updated: Cell::new(updated),
+                ka_io: false,
+                timestamps: false,
+                flush_timeout: 0,
+                rate_limit: None,
                 error: Cell::new(None),
                 st: Cell::new(DispatcherState::Processing),
-                shared: Rc::new(DispatcherShared {
-                    codec,
-                    error: Cell::new(None),
-                    inflight: Cell::new(0),
-                }),
+                reason: Cell::new(None),
+                on_shutdown: Cell::new(None),
+                error_service: None,
+                #[cfg(feature = "tracing")]
+                span: tracing_pkg::debug_span!("framed::dispatcher"),
+                shared,
             },
         }
     }
@@ -162,6 +319,35 @@ where
         self
     }
 
+    /// Count any io activity as connection liveness.
+    ///
+    /// By default keep-alive timer is updated only when a frame is decoded.
+    /// If enabled, keep-alive timeout is extended if any data has been read
+    /// from or written to io stream since last keep-alive update.
+    pub fn keepalive_io_activity(mut self, enabled: bool) -> Self {
+        self.inner.ka_io = enabled;
+        self
+    }
+
+    /// Emit `DispatchItem::TimedItem` instead of `DispatchItem::Item`.
+    ///
+    /// Timed item carries time when frame has been decoded, time is taken
+    /// from dispatcher's timer. By default timestamps are disabled.
+    pub fn frame_timestamps(mut self, enabled: bool) -> Self {
+        self.inner.timestamps = enabled;
+        self
+    }
+
+    /// Enable half-close mode.
+    ///
+    /// If peer shuts down its write side, dispatcher emits
+    /// `DispatchItem::ReadClosed` and keeps write side open until all
+    /// responses are written. By default read eof closes connection.
+    pub fn half_close(self, enabled: bool) -> Self {
+        self.inner.state.set_half_close(enabled);
+        self
+    }
+
     /// Set connection disconnect timeout in seconds.
     ///
     /// Defines a timeout for disconnect connection. If a disconnect procedure does not complete
@@ -174,6 +360,76 @@ where
         self.inner.state.set_disconnect_timeout(val);
         self
     }
+
+    /// Set flush timeout in seconds.
+    ///
+    /// Defines how long stopped dispatcher waits for uncompleted responses.
+    /// If responses are not completed within this time, connection get
+    /// force-closed. Write buffer get flushed within disconnect timeout.
+    ///
+    /// To disable timeout set value to 0.
+    ///
+    /// By default flush timeout is disabled.
+    pub fn flush_timeout(mut self, timeout: u16) -> Self {
+        self.inner.flush_timeout = timeout;
+        self
+    }
+
+    /// Set incoming frames rate limit.
+    ///
+    /// `frames` is max number of frames per second and `bytes` is max number
+    /// of decoded bytes per second. If budget is exhausted, dispatcher pauses
+    /// read task until budget is refilled.
+    ///
+    /// To disable a limit set value to 0. By default rate limit is disabled.
+    pub fn rate_limit(mut self, frames: u32, bytes: u32) -> Self {
+        self.inner.rate_limit = if frames == 0 && bytes == 0 {
+            None
+        } else {
+            Some(RateLimit::new(frames, bytes))
+        };
+        self
+    }
+
+    /// Write responses in the same order as requests.
+    ///
+    /// By default responses are written in completion order. In ordered mode
+    /// dispatcher buffers completed responses until all preceding responses
+    /// are written.
+    pub fn ordered_responses(self, enabled: bool) -> Self {
+        self.inner.shared.ordered.set(enabled);
+        self
+    }
+
+    /// Set dispatcher shutdown callback.
+    ///
+    /// Callback is called once, when dispatcher starts service shutdown.
+    pub fn on_shutdown<F>(self, f: F) -> Self
+    where
+        F: FnOnce(ShutdownReason) + 'static,
+    {
+        self.inner.on_shutdown.set(Some(Box::new(f)));
+        self
+    }
+
+    /// Set protocol errors handling service.
+    ///
+    /// `DecoderError`, `EncoderError`, `KeepAliveTimeout` and `FrameTooLarge`
+    /// items are passed to this service instead of main service. Response
+    /// is written to the peer before connection get closed. Service readiness
+    /// is not checked.
+    pub fn error_service<F, E>(mut self, service: F) -> Self
+    where
+        F: IntoService<E>,
+        E: Service<
+                Request = DispatchItem<U>,
+                Response = Option<Response<U>>,
+                Error = S::Error,
+            > + 'static,
+    {
+        self.inner.error_service = Some(boxed::service(service.into_service()));
+        self
+    }
 }
 
 impl<S, U> DispatcherShared<S, U>
@@ -184,28 +440,125 @@ where
     U: Encoder + Decoder,
     <U as Encoder>::Item: 'static,
 {
-    fn handle_result(&self, item: Result<S::Response, S::Error>, write: Write<'_>) {
+    /// reserve response slot, if dispatcher is in ordered mode
+    fn reserve_response(&self) -> Option<usize> {
+        if self.ordered.get() {
+            let mut responses = self.responses.borrow_mut();
+            let seq = self.responses_seq.get().wrapping_add(responses.len());
+            responses.push_back(None);
+            Some(seq)
+        } else {
+            None
+        }
+    }
+
+    fn handle_result(
+        &self,
+        seq: Option<usize>,
+        item: Result<S::Response, S::Error>,
+        write: Write<'_>,
+    ) {
+        trace_event!(ok = item.is_ok(), "service call completed");
         self.inflight.set(self.inflight.get() - 1);
-        match write.encode_result(item, &self.codec) {
+
+        if let Some(seq) = seq {
+            let mut responses = self.responses.borrow_mut();
+            let idx = seq.wrapping_sub(self.responses_seq.get());
+            responses[idx] = Some(item);
+
+            // write all completed responses in request order
+            while let Some(Some(_)) = responses.front() {
+                let item = responses.pop_front().unwrap().unwrap();
+                self.responses_seq
+                    .set(self.responses_seq.get().wrapping_add(1));
+                self.encode_result(item, write);
+            }
+            for waker in self.responses_task.borrow_mut().drain(..) {
+                waker.wake();
+            }
+        } else {
+            self.encode_result(item, write);
+        }
+        write.wake_dispatcher();
+    }
+
+    fn encode_result(&self, item: Result<S::Response, S::Error>, write: Write<'_>) {
+        match write.encode_result(item, &*self.codec.borrow()) {
             Ok(true) => (),
             Ok(false) => write.enable_backpressure(None),
             Err(err) => self.error.set(Some(err.into())),
         }
-        write.wake_dispatcher();
     }
 }
 
-impl<S, U> Future for Dispatcher<S, U>
+impl<S, U> StreamSink<U> for DispatcherShared<S, U>
+where
+    S: Service<Request = DispatchItem<U>, Response = Option<Response<U>>>,
+    S::Error: 'static,
+    S::Future: 'static,
+    U: Encoder + Decoder,
+    <U as Encoder>::Item: 'static,
+{
+    fn next_response(&self) -> Option<usize> {
+        if self.ordered.get() {
+            Some(
+                self.responses_seq
+                    .get()
+                    .wrapping_add(self.responses.borrow().len()),
+            )
+        } else {
+            None
+        }
+    }
+
+    fn poll_response_turn(&self, seq: Option<usize>, cx: &mut Context<'_>) -> Poll<()> {
+        match seq {
+            Some(seq) if seq != self.responses_seq.get() => {
+                let mut wakers = self.responses_task.borrow_mut();
+                if !wakers.iter().any(|w| w.will_wake(cx.waker())) {
+                    wakers.push(cx.waker().clone());
+                }
+                Poll::Pending
+            }
+            _ => Poll::Ready(()),
+        }
+    }
+
+    fn encode(&self, item: <U as Encoder>::Item, write: Write<'_>) -> bool {
+        match write.encode_result::<_, ()>(Ok(Some(item)), &*self.codec.borrow()) {
+            Ok(true) => true,
+            Ok(false) => {
+                write.enable_backpressure(None);
+                true
+            }
+            Err(err) => {
+                if let Either::Right(err) = err {
+                    self.error.set(Some(DispatcherError::Encoder(err)));
+                }
+                false
+            }
+        }
+    }
+}
+
+impl<S, U> Dispatcher<S, U>
 where
     S: Service<Request = DispatchItem<U>, Response = Option<Response<U>>> + 'static,
     U: Decoder + Encoder + 'static,
     <U as Encoder>::Item: 'static,
 {
-    type Output = Result<(), S::Error>;
-
-    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+    /// Poll dispatcher.
+    ///
+    /// Same as `Future::poll()`, allows to embed dispatcher into
+    /// other futures.
+    pub fn poll(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), S::Error>> {
         let mut this = self.as_mut().project();
         let slf = &this.inner;
+        #[cfg(feature = "tracing")]
+        let _enter = slf.span.enter();
         let state = &slf.state;
         let read = state.read();
         let write = state.write();
@@ -237,20 +590,53 @@ where
                                 write.enable_backpressure(Some(cx.waker()));
                                 slf.st.set(DispatcherState::Backpressure);
                                 DispatchItem::WBackPressureEnabled
-                            } else if read.is_ready() {
+                            } else if state.is_paused() {
+                                // reading is paused
+                                state.register_dispatcher(cx.waker());
+                                return Poll::Pending;
+                            } else if read.is_ready() || state.is_read_closed() {
+                                // check incoming frames rate
+                                if let Some(delay) =
+                                    slf.rate_limit.as_ref().and_then(|r| r.delay())
+                                {
+                                    log::trace!(
+                                        "rate limit is reached, pause read task"
+                                    );
+                                    let mut delay = Box::pin(sleep(delay));
+                                    let _ = delay.as_mut().poll(cx);
+                                    *this.rate_delay = Some(delay);
+                                    read.pause(cx.waker());
+                                    return Poll::Pending;
+                                }
+
                                 // decode incoming bytes if buffer is ready
-                                match read.decode(&slf.shared.codec) {
+                                match slf.decode(read) {
                                     Ok(Some(el)) => {
+                                        trace_event!("frame decoded");
                                         slf.update_keepalive();
-                                        DispatchItem::Item(el)
+                                        slf.item(el)
                                     }
                                     Ok(None) => {
-                                        log::trace!("not enough data to decode next frame, register dispatch task");
-                                        read.wake(cx.waker());
-                                        return Poll::Pending;
+                                        if read.is_frame_too_large() {
+                                            log::trace!("read buffer exceeds max frame size, stopping");
+                                            slf.stop(ShutdownReason::FrameTooLarge);
+                                            slf.unregister_keepalive();
+                                            DispatchItem::FrameTooLarge
+                                        } else if state.is_read_closed() {
+                                            log::trace!(
+                                                "peer closed read side, stopping"
+                                            );
+                                            slf.stop(ShutdownReason::Disconnected);
+                                            slf.unregister_keepalive();
+                                            DispatchItem::ReadClosed
+                                        } else {
+                                            log::trace!("not enough data to decode next frame, register dispatch task");
+                                            read.wake(cx.waker());
+                                            return Poll::Pending;
+                                        }
                                     }
                                     Err(err) => {
-                                        slf.st.set(DispatcherState::Stop);
+                                        slf.stop(ShutdownReason::Decoder);
                                         slf.unregister_keepalive();
                                         DispatchItem::DecoderError(err)
                                     }
@@ -265,8 +651,15 @@ where
                         PollService::ServiceError => continue,
                     };
 
+                    // protocol errors are handled by error service
+                    let item = match slf.handle_error(item) {
+                        Some(item) => item,
+                        None => continue,
+                    };
+
                     // call service
-                    if this.fut.is_none() {
+                    trace_event!("service call");
+                    if this.fut.is_none() && !slf.shared.ordered.get() {
                         // optimize first service call
                         this.fut.set(Some(this.service.call(item)));
                         match this.fut.as_mut().as_pin_mut().unwrap().poll(cx) {
@@ -301,8 +694,15 @@ where
                         PollService::ServiceError => continue,
                     };
 
+                    // protocol errors are handled by error service
+                    let item = match slf.handle_error(item) {
+                        Some(item) => item,
+                        None => continue,
+                    };
+
                     // call service
-                    if this.fut.is_none() {
+                    trace_event!("service call");
+                    if this.fut.is_none() && !slf.shared.ordered.get() {
                         // optimize first service call
                         this.fut.set(Some(this.service.call(item)));
                         match this.fut.as_mut().as_pin_mut().unwrap().poll(cx) {
@@ -324,12 +724,32 @@ where
                     let _ = this.service.poll_ready(cx);
 
                     if slf.shared.inflight.get() == 0 {
+                        trace_event!("responses are completed, shutdown io");
                         slf.st.set(DispatcherState::Shutdown);
                         state.shutdown_io();
+                    } else if slf.flush_timeout != 0 {
+                        let delay = this.flush_delay.get_or_insert_with(|| {
+                            Box::pin(sleep(Duration::from_secs(
+                                slf.flush_timeout as u64,
+                            )))
+                        });
+                        if delay.as_mut().poll(cx).is_ready() {
+                            log::trace!("flush timeout, force close connection");
+                            trace_event!("flush timeout, force close connection");
+                            slf.st.set(DispatcherState::Shutdown);
+                            state.force_close();
+                        } else {
+                            state.register_dispatcher(cx.waker());
+                            return Poll::Pending;
+                        }
                     } else {
                         state.register_dispatcher(cx.waker());
                         return Poll::Pending;
                     }
+
+                    if let Some(f) = slf.on_shutdown.take() {
+                        f(slf.reason.get().unwrap_or(ShutdownReason::Closed));
+                    }
                 }
                 // shutdown service
                 DispatcherState::Shutdown => {
@@ -337,6 +757,7 @@ where
 
                     return if this.service.poll_shutdown(cx, err.is_some()).is_ready() {
                         log::trace!("service shutdown is completed, stop");
+                        trace_event!(error = err.is_some(), "dispatcher is stopped");
 
                         Poll::Ready(if let Some(err) = err {
                             Err(err)
@@ -351,6 +772,41 @@ where
             }
         }
     }
+
+    /// Initiate graceful dispatcher shutdown and poll it to completion.
+    ///
+    /// Dispatcher stops reading new frames, waits for in-flight responses,
+    /// flushes write buffer, shuts down io and then service.
+    pub fn poll_shutdown(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), S::Error>> {
+        let inner = &self.inner;
+        match inner.st.get() {
+            DispatcherState::Processing | DispatcherState::Backpressure => {
+                log::trace!("dispatcher shutdown is requested");
+                inner.state.close();
+                inner.stop(ShutdownReason::Closed);
+                inner.unregister_keepalive();
+            }
+            DispatcherState::Stop | DispatcherState::Shutdown => (),
+        }
+        self.poll(cx)
+    }
+}
+
+impl<S, U> Future for Dispatcher<S, U>
+where
+    S: Service<Request = DispatchItem<U>, Response = Option<Response<U>>> + 'static,
+    U: Decoder + Encoder + 'static,
+    <U as Encoder>::Item: 'static,
+{
+    type Output = Result<(), S::Error>;
+
+    #[inline]
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Dispatcher::poll(self, cx)
+    }
 }
 
 impl<S, U> DispatcherInner<S, U>
@@ -359,15 +815,39 @@ where
     U: Decoder + Encoder + 'static,
 {
     /// spawn service call
-    fn spawn_service_call(&self, fut: S::Future) {
+    fn spawn_service_call<F>(&self, fut: F)
+    where
+        F: Future<Output = ServiceResult<S, U>> + 'static,
+    {
         self.shared.inflight.set(self.shared.inflight.get() + 1);
 
         let st = self.state.clone();
         let shared = self.shared.clone();
-        crate::rt::spawn(async move {
+        let seq = shared.reserve_response();
+        let fut = async move {
             let item = fut.await;
-            shared.handle_result(item, st.write());
-        });
+            shared.handle_result(seq, item, st.write());
+        };
+        #[cfg(feature = "tracing")]
+        let fut = tracing_pkg::Instrument::instrument(fut, self.span.clone());
+        crate::rt::spawn(fut);
+    }
+
+    /// pass protocol error to error service, if it is set
+    fn handle_error(&self, item: DispatchItem<U>) -> Option<DispatchItem<U>> {
+        if let Some(ref srv) = self.error_service {
+            match item {
+                DispatchItem::DecoderError(_)
+                | DispatchItem::EncoderError(_)
+                | DispatchItem::KeepAliveTimeout
+                | DispatchItem::FrameTooLarge => {
+                    self.spawn_service_call(srv.call(item));
+                    return None;
+                }
+                _ => (),
+            }
+        }
+        Some(item)
     }
 
     fn handle_result(
@@ -375,7 +855,8 @@ where
         item: Result<Option<<U as Encoder>::Item>, S::Error>,
         write: Write<'_>,
     ) {
-        match write.encode_result(item, &self.shared.codec) {
+        trace_event!(ok = item.is_ok(), "service call completed");
+        match write.encode_result(item, &*self.shared.codec.borrow()) {
             Ok(true) => (),
             Ok(false) => write.enable_backpressure(None),
             Err(Either::Left(err)) => {
@@ -398,6 +879,9 @@ where
                 // service is ready, wake io read task
                 read.resume();
 
+                // replace codec if requested
+                self.update_codec();
+
                 // check keepalive timeout
                 self.check_keepalive();
 
@@ -405,16 +889,18 @@ where
                 Poll::Ready(if let Some(err) = self.shared.error.take() {
                     log::trace!("error occured, stopping dispatcher");
                     self.unregister_keepalive();
-                    self.st.set(DispatcherState::Stop);
 
                     match err {
                         DispatcherError::KeepAlive => {
+                            self.stop(ShutdownReason::KeepAlive);
                             PollService::Item(DispatchItem::KeepAliveTimeout)
                         }
                         DispatcherError::Encoder(err) => {
+                            self.stop(ShutdownReason::Encoder);
                             PollService::Item(DispatchItem::EncoderError(err))
                         }
                         DispatcherError::Service(err) => {
+                            self.stop(ShutdownReason::Service);
                             self.error.set(Some(err));
                             PollService::ServiceError
                         }
@@ -425,16 +911,29 @@ where
                     self.unregister_keepalive();
 
                     // process unhandled data
-                    if let Ok(Some(el)) = read.decode(&self.shared.codec) {
-                        PollService::Item(DispatchItem::Item(el))
-                    } else {
-                        self.st.set(DispatcherState::Stop);
-
-                        // get io error
-                        if let Some(err) = self.state.take_io_error() {
-                            PollService::Item(DispatchItem::IoError(err))
-                        } else {
-                            PollService::ServiceError
+                    match self.decode(read) {
+                        Ok(Some(el)) => PollService::Item(self.item(el)),
+                        Err(err) if self.state.is_read_eof() => {
+                            self.stop(ShutdownReason::Decoder);
+                            PollService::Item(DispatchItem::DecoderError(err))
+                        }
+                        _ => {
+                            // get io error
+                            if let Some(err) = self.state.take_io_error() {
+                                self.stop(ShutdownReason::Io);
+                                PollService::Item(DispatchItem::IoError(err))
+                            } else {
+                                let err = self.error.take();
+                                self.stop(if err.is_some() {
+                                    ShutdownReason::Service
+                                } else if self.state.is_io_err() {
+                                    ShutdownReason::Disconnected
+                                } else {
+                                    ShutdownReason::Closed
+                                });
+                                self.error.set(err);
+                                PollService::ServiceError
+                            }
                         }
                     }
                 } else {
@@ -450,7 +949,7 @@ where
             // handle service readiness error
             Poll::Ready(Err(err)) => {
                 log::trace!("service readiness check failed, stopping");
-                self.st.set(DispatcherState::Stop);
+                self.stop(ShutdownReason::Service);
                 self.error.set(Some(err));
                 self.unregister_keepalive();
                 Poll::Ready(PollService::ServiceError)
@@ -458,6 +957,67 @@ where
         }
     }
 
+    /// decode frame from read buffer and update rate limit
+    fn decode(
+        &self,
+        read: Read<'_>,
+    ) -> Result<Option<<U as Decoder>::Item>, <U as Decoder>::Error> {
+        if let Some(ref rate) = self.rate_limit {
+            let len = read.with_buf(|buf| buf.len());
+            let item = self.decode_frame(read);
+            if let Ok(Some(_)) = item {
+                rate.consume(len - read.with_buf(|buf| buf.len()));
+            }
+            item
+        } else {
+            self.decode_frame(read)
+        }
+    }
+
+    /// use `Decoder::decode_eof()` if read side reached eof
+    fn decode_frame(
+        &self,
+        read: Read<'_>,
+    ) -> Result<Option<<U as Decoder>::Item>, <U as Decoder>::Error> {
+        if self.state.is_read_eof() {
+            read.decode_eof(&*self.shared.codec.borrow())
+        } else {
+            read.decode(&*self.shared.codec.borrow())
+        }
+    }
+
+    /// wrap decoded frame, add timestamp if enabled
+    fn item(&self, el: <U as Decoder>::Item) -> DispatchItem<U> {
+        if self.timestamps {
+            DispatchItem::TimedItem(el, self.timer.now())
+        } else {
+            DispatchItem::Item(el)
+        }
+    }
+
+    /// stop dispatcher, first reason is preserved
+    fn stop(&self, reason: ShutdownReason) {
+        trace_event!(?reason, "dispatcher stop");
+        self.st.set(DispatcherState::Stop);
+        if self.reason.get().is_none() {
+            self.reason.set(Some(reason));
+        }
+    }
+
+    /// replace codec, if new codec has been set via `State::map_codec()`
+    fn update_codec(&self) {
+        if let Some(f) = self.state.take_codec_map() {
+            match f.downcast::<Box<dyn FnOnce(&U) -> U>>() {
+                Ok(f) => {
+                    log::trace!("replace dispatcher codec");
+                    let codec = f(&*self.shared.codec.borrow());
+                    *self.shared.codec.borrow_mut() = codec;
+                }
+                Err(_) => log::error!("codec type does not match dispatcher codec"),
+            }
+        }
+    }
+
     fn ka(&self) -> Duration {
         Duration::from_secs(self.ka_timeout as u64)
     }
@@ -469,7 +1029,26 @@ where
     /// check keepalive timeout
     fn check_keepalive(&self) {
         if self.state.is_keepalive() {
+            if self.state.is_paused() || (self.ka_io && self.state.take_io_activity()) {
+                log::trace!(
+                    "keepalive timeout, io is active or paused, extend keepalive"
+                );
+                trace_event!("keep-alive is extended");
+                self.state.reset_keepalive();
+
+                let updated = self.timer.now();
+                let ka = self.ka();
+                self.timer.register(
+                    updated + ka,
+                    self.ka_updated.get() + ka,
+                    &self.state,
+                );
+                self.ka_updated.set(updated);
+                return;
+            }
+
             log::trace!("keepalive timeout");
+            trace_event!("keep-alive timeout");
             if let Some(err) = self.shared.error.take() {
                 self.shared.error.set(Some(err));
             } else {
@@ -481,6 +1060,9 @@ where
     /// update keep-alive timer
     fn update_keepalive(&self) {
         if self.ka_enabled() {
+            if self.ka_io {
+                self.state.take_io_activity();
+            }
             let updated = self.timer.now();
             if updated != self.ka_updated.get() {
                 let ka = self.ka();
@@ -511,13 +1093,38 @@ mod tests {
     use crate::codec::BytesCodec;
     use crate::rt::time::sleep;
     use crate::testing::Io;
-    use crate::util::Bytes;
+    use crate::util::{Bytes, BytesMut};
 
     use super::*;
 
+    struct LineCodec;
+
+    impl Encoder for LineCodec {
+        type Item = Bytes;
+        type Error = std::io::Error;
+
+        fn encode(&self, item: Bytes, dst: &mut BytesMut) -> Result<(), Self::Error> {
+            dst.extend_from_slice(&item[..]);
+            Ok(())
+        }
+    }
+
+    impl Decoder for LineCodec {
+        type Item = BytesMut;
+        type Error = std::io::Error;
+
+        fn decode(&self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+            if let Some(pos) = src.iter().position(|b| *b == b'\n') {
+                Ok(Some(src.split_to(pos + 1)))
+            } else {
+                Ok(None)
+            }
+        }
+    }
+
     impl<S, U> Dispatcher<S, U>
     where
-        S: Service<Request = DispatchItem<U>, Response = Option<Response<U>>>,
+        S: Service<Request = DispatchItem<U>, Response = Option<Response<U>>> + 'static,
         S::Error: 'static,
         S::Future: 'static,
         U: Decoder + Encoder + 'static,
@@ -530,7 +1137,7 @@ mod tests {
             service: F,
         ) -> (Self, State)
         where
-            T: AsyncRead + AsyncWrite + Unpin + 'static,
+            T: AsyncRead + AsyncWrite + 'static,
         {
             let timer = Timer::default();
             let ka_timeout = 1;
@@ -538,29 +1145,47 @@ mod tests {
             let state = State::new();
             let io = Rc::new(RefCell::new(io));
             let shared = Rc::new(DispatcherShared {
-                codec: codec,
+                codec: RefCell::new(codec),
                 error: Cell::new(None),
                 inflight: Cell::new(0),
+                ordered: Cell::new(false),
+                responses: RefCell::new(VecDeque::new()),
+                responses_seq: Cell::new(0),
+                responses_task: RefCell::new(Vec::new()),
             });
+            state.set_stream_sink(Box::new(shared.clone() as Rc<dyn StreamSink<U>>));
 
             let expire = ka_updated + Duration::from_millis(500);
             timer.register(expire, expire, &state);
 
-            crate::rt::spawn(ReadTask::new(io.clone(), state.clone()));
-            crate::rt::spawn(WriteTask::new(io.clone(), state.clone()));
+            unsafe {
+                crate::rt::spawn(ReadTask::new_unchecked(io.clone(), state.clone()));
+                crate::rt::spawn(WriteTask::new_unchecked(io, state.clone()));
+            }
 
             (
                 Dispatcher {
                     service: service.into_service(),
                     fut: None,
+                    flush_delay: None,
+                    rate_delay: None,
                     inner: DispatcherInner {
                         shared,
                         timer,
                         ka_timeout,
                         ka_updated: Cell::new(ka_updated),
+                        ka_io: false,
+                        timestamps: false,
+                        flush_timeout: 0,
+                        rate_limit: None,
                         state: state.clone(),
                         error: Cell::new(None),
                         st: Cell::new(DispatcherState::Processing),
+                        reason: Cell::new(None),
+                        on_shutdown: Cell::new(None),
+                        error_service: None,
+                        #[cfg(feature = "tracing")]
+                        span: tracing_pkg::debug_span!("framed::dispatcher"),
                     },
                 },
                 state,
@@ -597,52 +1222,382 @@ mod tests {
         assert!(client.is_server_dropped());
     }
 
+    pin_project_lite::pin_project! {
+        struct PinnedIo {
+            #[pin]
+            io: Io,
+            _t: std::marker::PhantomPinned,
+        }
+    }
+
+    impl AsyncRead for PinnedIo {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut crate::codec::ReadBuf<'_>,
+        ) -> Poll<std::io::Result<()>> {
+            self.project().io.poll_read(cx, buf)
+        }
+    }
+
+    impl AsyncWrite for PinnedIo {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<std::io::Result<usize>> {
+            self.project().io.poll_write(cx, buf)
+        }
+
+        fn poll_flush(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+        ) -> Poll<std::io::Result<()>> {
+            self.project().io.poll_flush(cx)
+        }
+
+        fn poll_shutdown(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+        ) -> Poll<std::io::Result<()>> {
+            self.project().io.poll_shutdown(cx)
+        }
+    }
+
     #[crate::rt_test]
-    async fn test_sink() {
+    async fn test_not_unpin_io() {
         let (client, server) = Io::create();
         client.remote_buffer_cap(1024);
         client.write("GET /test HTTP/1\r\n\r\n");
 
-        let (disp, st) = Dispatcher::debug(
-            server,
+        let io = PinnedIo {
+            io: server,
+            _t: std::marker::PhantomPinned,
+        };
+        let disp = Dispatcher::new(
+            io,
             BytesCodec,
+            State::new(),
             crate::fn_service(|msg: DispatchItem<BytesCodec>| async move {
                 if let DispatchItem::Item(msg) = msg {
                     Ok::<_, ()>(Some(msg.freeze()))
                 } else {
-                    panic!()
+                    Ok(None)
                 }
             }),
+            Timer::default(),
         );
         crate::rt::spawn(async move {
-            let _ = disp.disconnect_timeout(25).await;
+            let _ = disp.await;
         });
 
         let buf = client.read().await.unwrap();
         assert_eq!(buf, Bytes::from_static(b"GET /test HTTP/1\r\n\r\n"));
 
-        assert!(st
-            .write()
-            .encode(Bytes::from_static(b"test"), &mut BytesCodec)
-            .is_ok());
-        let buf = client.read().await.unwrap();
-        assert_eq!(buf, Bytes::from_static(b"test"));
-
-        st.close();
-        sleep(Duration::from_millis(200)).await;
+        client.close().await;
         assert!(client.is_server_dropped());
     }
 
     #[crate::rt_test]
-    async fn test_err_in_service() {
+    async fn test_poll_shutdown() {
         let (client, server) = Io::create();
-        client.remote_buffer_cap(0);
+        client.remote_buffer_cap(1024);
         client.write("GET /test HTTP/1\r\n\r\n");
 
         let (disp, state) = Dispatcher::debug(
             server,
             BytesCodec,
-            crate::fn_service(|_: DispatchItem<BytesCodec>| async move {
+            crate::fn_service(|msg: DispatchItem<BytesCodec>| async move {
+                sleep(Duration::from_millis(100)).await;
+                if let DispatchItem::Item(msg) = msg {
+                    Ok::<_, ()>(Some(msg.freeze()))
+                } else {
+                    Ok(None)
+                }
+            }),
+        );
+        let mut disp = Box::pin(disp);
+        sleep(Duration::from_millis(25)).await;
+        assert!(crate::util::lazy(|cx| disp.as_mut().poll(cx))
+            .await
+            .is_pending());
+
+        // in-flight response is written before shutdown
+        let res = crate::util::poll_fn(|cx| disp.as_mut().poll_shutdown(cx)).await;
+        assert!(res.is_ok());
+        assert!(state.is_dispatcher_stopped());
+
+        let buf = client.read().await.unwrap();
+        assert_eq!(buf, Bytes::from_static(b"GET /test HTTP/1\r\n\r\n"));
+        sleep(Duration::from_millis(50)).await;
+        assert!(client.is_closed());
+    }
+
+    #[crate::rt_test]
+    async fn test_frame_timestamps() {
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(1024);
+
+        let (disp, _) = Dispatcher::debug(
+            server,
+            BytesCodec,
+            crate::fn_service(|msg: DispatchItem<BytesCodec>| async move {
+                if let DispatchItem::TimedItem(msg, time) = msg {
+                    assert!(time <= Instant::now());
+                    Ok::<_, ()>(Some(msg.freeze()))
+                } else {
+                    panic!()
+                }
+            }),
+        );
+        crate::rt::spawn(async move {
+            let _ = disp.frame_timestamps(true).await;
+        });
+
+        client.write("GET /test HTTP/1\r\n\r\n");
+        let buf = client.read().await.unwrap();
+        assert_eq!(buf, Bytes::from_static(b"GET /test HTTP/1\r\n\r\n"));
+    }
+
+    #[crate::rt_test]
+    async fn test_half_close() {
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(1024);
+
+        let (disp, state) = Dispatcher::debug(
+            server,
+            BytesCodec,
+            crate::fn_service(|msg: DispatchItem<BytesCodec>| async move {
+                match msg {
+                    DispatchItem::Item(msg) => Ok::<_, ()>(Some(msg.freeze())),
+                    DispatchItem::ReadClosed => {
+                        sleep(Duration::from_millis(50)).await;
+                        Ok(Some(Bytes::from_static(b"bye")))
+                    }
+                    _ => Ok(None),
+                }
+            }),
+        );
+        crate::rt::spawn(async move {
+            let _ = disp.half_close(true).await;
+        });
+
+        client.write("test");
+        let buf = client.read().await.unwrap();
+        assert_eq!(buf, Bytes::from_static(b"test"));
+
+        client.close().await;
+        assert!(state.is_read_closed());
+        assert!(!client.is_closed());
+
+        let buf = client.read().await.unwrap();
+        assert_eq!(buf, Bytes::from_static(b"bye"));
+        sleep(Duration::from_millis(50)).await;
+        assert!(client.is_closed());
+    }
+
+    #[crate::rt_test]
+    async fn test_sink() {
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(1024);
+        client.write("GET /test HTTP/1\r\n\r\n");
+
+        let (disp, st) = Dispatcher::debug(
+            server,
+            BytesCodec,
+            crate::fn_service(|msg: DispatchItem<BytesCodec>| async move {
+                if let DispatchItem::Item(msg) = msg {
+                    Ok::<_, ()>(Some(msg.freeze()))
+                } else {
+                    panic!()
+                }
+            }),
+        );
+        crate::rt::spawn(async move {
+            let _ = disp.disconnect_timeout(25).await;
+        });
+
+        let buf = client.read().await.unwrap();
+        assert_eq!(buf, Bytes::from_static(b"GET /test HTTP/1\r\n\r\n"));
+
+        assert!(st
+            .write()
+            .encode(Bytes::from_static(b"test"), &mut BytesCodec)
+            .is_ok());
+        let buf = client.read().await.unwrap();
+        assert_eq!(buf, Bytes::from_static(b"test"));
+
+        st.close();
+        sleep(Duration::from_millis(200)).await;
+        assert!(client.is_server_dropped());
+    }
+
+    #[crate::rt_test]
+    async fn test_ordered_responses() {
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(1024);
+
+        let (disp, _) = Dispatcher::debug(
+            server,
+            BytesCodec,
+            crate::fn_service(|msg: DispatchItem<BytesCodec>| async move {
+                if let DispatchItem::Item(msg) = msg {
+                    if &msg[..] == b"1" {
+                        sleep(Duration::from_millis(100)).await;
+                    }
+                    Ok::<_, ()>(Some(msg.freeze()))
+                } else {
+                    panic!()
+                }
+            }),
+        );
+        crate::rt::spawn(async move {
+            let _ = disp.ordered_responses(true).await;
+        });
+
+        client.write("1");
+        sleep(Duration::from_millis(25)).await;
+        client.write("2");
+        sleep(Duration::from_millis(25)).await;
+        assert!(client.read_any().is_empty());
+
+        sleep(Duration::from_millis(150)).await;
+        assert_eq!(client.read_any(), Bytes::from_static(b"12"));
+    }
+
+    #[crate::rt_test]
+    async fn test_map_codec() {
+        struct UpperCodec(bool);
+
+        impl Encoder for UpperCodec {
+            type Item = Bytes;
+            type Error = std::io::Error;
+
+            fn encode(
+                &self,
+                item: Bytes,
+                dst: &mut BytesMut,
+            ) -> Result<(), Self::Error> {
+                if self.0 {
+                    dst.extend_from_slice(&item.to_ascii_uppercase());
+                } else {
+                    dst.extend_from_slice(&item[..]);
+                }
+                Ok(())
+            }
+        }
+
+        impl Decoder for UpperCodec {
+            type Item = BytesMut;
+            type Error = std::io::Error;
+
+            fn decode(
+                &self,
+                src: &mut BytesMut,
+            ) -> Result<Option<Self::Item>, Self::Error> {
+                BytesCodec.decode(src)
+            }
+        }
+
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(1024);
+
+        let (disp, state) = Dispatcher::debug(
+            server,
+            UpperCodec(false),
+            crate::fn_service(|msg: DispatchItem<UpperCodec>| async move {
+                if let DispatchItem::Item(msg) = msg {
+                    Ok::<_, ()>(Some(msg.freeze()))
+                } else {
+                    panic!()
+                }
+            }),
+        );
+        crate::rt::spawn(async move {
+            let _ = disp.await;
+        });
+
+        client.write("test");
+        let buf = client.read().await.unwrap();
+        assert_eq!(buf, Bytes::from_static(b"test"));
+
+        state.map_codec(|codec: &UpperCodec| UpperCodec(!codec.0));
+        client.write("test");
+        let buf = client.read().await.unwrap();
+        assert_eq!(buf, Bytes::from_static(b"TEST"));
+    }
+
+    #[crate::rt_test]
+    async fn test_flush_timeout() {
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(1024);
+        client.write("GET /test HTTP/1\r\n\r\n");
+
+        let (disp, state) = Dispatcher::debug(
+            server,
+            BytesCodec,
+            crate::fn_service(|_: DispatchItem<BytesCodec>| async move {
+                sleep(Duration::from_secs(999_999)).await;
+                Ok::<Option<Bytes>, ()>(None)
+            }),
+        );
+        crate::rt::spawn(async move {
+            let _ = disp.keepalive_timeout(0).flush_timeout(1).await;
+        });
+        sleep(Duration::from_millis(50)).await;
+
+        state.close();
+        sleep(Duration::from_millis(50)).await;
+        assert!(!client.is_closed());
+
+        sleep(Duration::from_millis(1100)).await;
+        assert!(client.is_closed());
+    }
+
+    #[crate::rt_test]
+    async fn test_rate_limit() {
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(1024);
+
+        let counter = Rc::new(Cell::new(0));
+        let counter2 = counter.clone();
+
+        let (disp, _) = Dispatcher::debug(
+            server,
+            BytesCodec,
+            crate::fn_service(move |msg: DispatchItem<BytesCodec>| {
+                if let DispatchItem::Item(_) = msg {
+                    counter2.set(counter2.get() + 1);
+                }
+                async move { Ok::<Option<Bytes>, ()>(None) }
+            }),
+        );
+        crate::rt::spawn(async move {
+            let _ = disp.rate_limit(2, 0).await;
+        });
+
+        client.write("1");
+        sleep(Duration::from_millis(20)).await;
+        client.write("2");
+        sleep(Duration::from_millis(20)).await;
+        client.write("3");
+        sleep(Duration::from_millis(20)).await;
+        assert_eq!(counter.get(), 2);
+
+        sleep(Duration::from_millis(600)).await;
+        assert_eq!(counter.get(), 3);
+    }
+
+    #[crate::rt_test]
+    async fn test_err_in_service() {
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(0);
+        client.write("GET /test HTTP/1\r\n\r\n");
+
+        let (disp, state) = Dispatcher::debug(
+            server,
+            BytesCodec,
+            crate::fn_service(|_: DispatchItem<BytesCodec>| async move {
                 Err::<Option<Bytes>, _>(())
             }),
         );
@@ -670,6 +1625,52 @@ mod tests {
         assert!(client.is_server_dropped());
     }
 
+    #[crate::rt_test]
+    async fn test_on_shutdown() {
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(1024);
+        client.write("GET /test HTTP/1\r\n\r\n");
+
+        let reason = Rc::new(Cell::new(None));
+        let reason2 = reason.clone();
+
+        let (disp, _) = Dispatcher::debug(
+            server,
+            BytesCodec,
+            crate::fn_service(|_: DispatchItem<BytesCodec>| async move {
+                Err::<Option<Bytes>, _>(())
+            }),
+        );
+        crate::rt::spawn(async move {
+            let _ = disp
+                .on_shutdown(move |r| reason2.set(Some(r)))
+                .disconnect_timeout(0)
+                .await;
+        });
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(reason.get(), Some(ShutdownReason::Service));
+
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(1024);
+
+        let reason = Rc::new(Cell::new(None));
+        let reason2 = reason.clone();
+
+        let (disp, _) = Dispatcher::debug(
+            server,
+            BytesCodec,
+            crate::fn_service(|_: DispatchItem<BytesCodec>| async move {
+                Ok::<Option<Bytes>, ()>(None)
+            }),
+        );
+        crate::rt::spawn(async move {
+            let _ = disp.on_shutdown(move |r| reason2.set(Some(r))).await;
+        });
+        client.close().await;
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(reason.get(), Some(ShutdownReason::Disconnected));
+    }
+
     #[crate::rt_test]
     async fn test_write_backpressure() {
         let (client, server) = Io::create();
@@ -733,6 +1734,13 @@ mod tests {
         sleep(Duration::from_millis(50)).await;
         assert_eq!(state.write().with_buf(|buf| buf.len()), 10240);
 
+        // write buffer is still above low watermark
+        assert!(!state.write().is_ready());
+
+        client.remote_buffer_cap(10240);
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(state.write().with_buf(|buf| buf.len()), 0);
+
         // backpressure disabled
         assert!(state.write().is_ready());
         assert_eq!(&data.lock().unwrap().borrow()[..], &[0, 1, 2]);
@@ -787,6 +1795,189 @@ mod tests {
         assert_eq!(&data.lock().unwrap().borrow()[..], &[0, 1]);
     }
 
+    #[crate::rt_test]
+    async fn test_error_service() {
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(1024);
+        client.write("GET /test HTTP/1\r\n\r\n");
+
+        let (disp, state) = Dispatcher::debug(
+            server,
+            BytesCodec,
+            crate::fn_service(|msg: DispatchItem<BytesCodec>| async move {
+                if let DispatchItem::Item(msg) = msg {
+                    Ok::<_, ()>(Some(msg.freeze()))
+                } else {
+                    panic!()
+                }
+            }),
+        );
+        crate::rt::spawn(async move {
+            let _ = disp
+                .keepalive_timeout(1)
+                .error_service(crate::fn_service(
+                    |msg: DispatchItem<BytesCodec>| async move {
+                        if let DispatchItem::KeepAliveTimeout = msg {
+                            Ok(Some(Bytes::from_static(b"bye")))
+                        } else {
+                            Ok(None)
+                        }
+                    },
+                ))
+                .await;
+        });
+        state.set_disconnect_timeout(1);
+
+        let buf = client.read().await.unwrap();
+        assert_eq!(buf, Bytes::from_static(b"GET /test HTTP/1\r\n\r\n"));
+
+        let buf = client.read().await.unwrap();
+        assert_eq!(buf, Bytes::from_static(b"bye"));
+        sleep(Duration::from_millis(1100)).await;
+        assert!(client.is_closed());
+    }
+
+    #[crate::rt_test]
+    async fn test_frame_too_large() {
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(1024);
+
+        let data = Arc::new(Mutex::new(RefCell::new(Vec::new())));
+        let data2 = data.clone();
+
+        let (disp, state) = Dispatcher::debug(
+            server,
+            LineCodec,
+            crate::fn_service(move |msg: DispatchItem<LineCodec>| {
+                let data = data2.clone();
+                async move {
+                    match msg {
+                        DispatchItem::Item(bytes) => {
+                            data.lock().unwrap().borrow_mut().push(0);
+                            return Ok::<_, ()>(Some(bytes.freeze()));
+                        }
+                        DispatchItem::FrameTooLarge => {
+                            data.lock().unwrap().borrow_mut().push(1);
+                        }
+                        _ => (),
+                    }
+                    Ok(None)
+                }
+            }),
+        );
+        state.set_max_frame_size(16);
+        crate::rt::spawn(async move {
+            let _ = disp.await;
+        });
+
+        client.write("test\n");
+        let buf = client.read().await.unwrap();
+        assert_eq!(buf, Bytes::from_static(b"test\n"));
+
+        client.write("0123456789012345678901234567890");
+        sleep(Duration::from_millis(50)).await;
+        assert!(!state.is_open());
+        assert_eq!(&data.lock().unwrap().borrow()[..], &[0, 1]);
+    }
+
+    #[crate::rt_test]
+    async fn test_keepalive_io_activity() {
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(1024);
+
+        let data = Arc::new(Mutex::new(RefCell::new(Vec::new())));
+        let data2 = data.clone();
+
+        let (disp, state) = Dispatcher::debug(
+            server,
+            LineCodec,
+            crate::fn_service(move |msg: DispatchItem<LineCodec>| {
+                let data = data2.clone();
+                async move {
+                    if let DispatchItem::KeepAliveTimeout = msg {
+                        data.lock().unwrap().borrow_mut().push(1);
+                    }
+                    Ok::<_, ()>(None)
+                }
+            }),
+        );
+        crate::rt::spawn(async move {
+            let _ = disp.keepalive_io_activity(true).await;
+        });
+
+        // slowly send large frame
+        for _ in 0..6 {
+            client.write("data");
+            sleep(Duration::from_millis(400)).await;
+        }
+        assert!(state.is_open());
+        assert!(data.lock().unwrap().borrow().is_empty());
+    }
+
+    #[crate::rt_test]
+    async fn test_pause_read() {
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(1024);
+
+        let (disp, state) = Dispatcher::debug(
+            server,
+            BytesCodec,
+            crate::fn_service(|msg: DispatchItem<BytesCodec>| async move {
+                if let DispatchItem::Item(msg) = msg {
+                    Ok::<_, ()>(Some(msg.freeze()))
+                } else {
+                    Ok(None)
+                }
+            }),
+        );
+        crate::rt::spawn(async move {
+            let _ = disp.await;
+        });
+
+        state.pause_read();
+        assert!(state.is_paused());
+        client.write("test");
+        sleep(Duration::from_millis(50)).await;
+        assert!(client.read_any().is_empty());
+
+        state.resume_read();
+        let buf = client.read().await.unwrap();
+        assert_eq!(buf, Bytes::from_static(b"test"));
+    }
+
+    #[crate::rt_test]
+    async fn test_decode_eof() {
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(1024);
+        client.write("line 1\nline 2");
+
+        let data = Arc::new(Mutex::new(RefCell::new(Vec::new())));
+        let data2 = data.clone();
+        let (disp, state) = Dispatcher::debug(
+            server,
+            crate::codec::LinesCodec::<String>::new(),
+            crate::fn_service(
+                move |msg: DispatchItem<crate::codec::LinesCodec<String>>| {
+                    if let DispatchItem::Item(msg) = msg {
+                        data2.lock().unwrap().borrow_mut().push(msg);
+                    }
+                    async { Ok::<_, ()>(None) }
+                },
+            ),
+        );
+        crate::rt::spawn(async move {
+            let _ = disp.await;
+        });
+        sleep(Duration::from_millis(25)).await;
+        client.close().await;
+
+        assert!(state.is_read_eof());
+        assert_eq!(
+            &data.lock().unwrap().borrow()[..],
+            &["line 1".to_string(), "line 2".to_string()]
+        );
+    }
+
     #[crate::rt_test]
     async fn test_unhandled_data() {
         let handled = Arc::new(AtomicBool::new(false));
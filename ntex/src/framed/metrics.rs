@@ -0,0 +1,55 @@
+//! Framed io counters
+//!
+//! Counters are global and shared by all framed states,
+//! available only if `bench-metrics` feature is enabled.
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+pub(super) static READ_CALLS: AtomicUsize = AtomicUsize::new(0);
+pub(super) static BYTES_READ: AtomicUsize = AtomicUsize::new(0);
+pub(super) static FRAMES_DECODED: AtomicUsize = AtomicUsize::new(0);
+pub(super) static FRAMES_ENCODED: AtomicUsize = AtomicUsize::new(0);
+pub(super) static WRITE_CALLS: AtomicUsize = AtomicUsize::new(0);
+pub(super) static BYTES_WRITTEN: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+/// Snapshot of framed io counters
+pub struct Metrics {
+    /// Number of non-empty reads from io stream
+    pub read_calls: usize,
+    /// Number of bytes read from io stream
+    pub bytes_read: usize,
+    /// Number of decoded frames
+    pub frames_decoded: usize,
+    /// Number of encoded frames
+    pub frames_encoded: usize,
+    /// Number of non-empty writes to io stream
+    pub write_calls: usize,
+    /// Number of bytes written to io stream
+    pub bytes_written: usize,
+}
+
+/// Get current counters values
+pub fn snapshot() -> Metrics {
+    Metrics {
+        read_calls: READ_CALLS.load(Ordering::Relaxed),
+        bytes_read: BYTES_READ.load(Ordering::Relaxed),
+        frames_decoded: FRAMES_DECODED.load(Ordering::Relaxed),
+        frames_encoded: FRAMES_ENCODED.load(Ordering::Relaxed),
+        write_calls: WRITE_CALLS.load(Ordering::Relaxed),
+        bytes_written: BYTES_WRITTEN.load(Ordering::Relaxed),
+    }
+}
+
+/// Reset all counters
+pub fn reset() {
+    for counter in &[
+        &READ_CALLS,
+        &BYTES_READ,
+        &FRAMES_DECODED,
+        &FRAMES_ENCODED,
+        &WRITE_CALLS,
+        &BYTES_WRITTEN,
+    ] {
+        counter.store(0, Ordering::Relaxed);
+    }
+}
@@ -1,14 +1,27 @@
-use std::{fmt, io};
+use std::{fmt, io, time::Instant};
+
+/// Update framed io counter, if `bench-metrics` feature is enabled
+macro_rules! metric {
+    ($name:ident, $n:expr) => {
+        #[cfg(feature = "bench-metrics")]
+        crate::framed::metrics::$name
+            .fetch_add($n, std::sync::atomic::Ordering::Relaxed);
+    };
+}
 
 mod dispatcher;
+#[cfg(feature = "bench-metrics")]
+pub mod metrics;
 mod read;
 mod state;
+mod stream;
 mod time;
 mod write;
 
-pub use self::dispatcher::Dispatcher;
+pub use self::dispatcher::{Dispatcher, ShutdownReason};
 pub use self::read::ReadTask;
-pub use self::state::{OnDisconnect, Read, State, Write};
+pub use self::state::{set_pool_size, MemoryTracker, OnDisconnect, Read, State, Write};
+pub use self::stream::StreamService;
 pub use self::time::Timer;
 pub use self::write::WriteTask;
 
@@ -17,12 +30,27 @@ use crate::codec::{Decoder, Encoder};
 /// Framed transport item
 pub enum DispatchItem<U: Encoder + Decoder> {
     Item(<U as Decoder>::Item),
+    /// Decoded item and time when it has been decoded
+    ///
+    /// Emitted instead of `Item` if `Dispatcher::frame_timestamps()` is enabled.
+    TimedItem(<U as Decoder>::Item, Instant),
     /// Write back-pressure enabled
+    ///
+    /// Write buffer size exceeds high watermark, service should stop
+    /// generating new frames until `WBackPressureDisabled` is received.
     WBackPressureEnabled,
     /// Write back-pressure disabled
+    ///
+    /// Write buffer drained below low watermark.
     WBackPressureDisabled,
     /// Keep alive timeout
     KeepAliveTimeout,
+    /// Peer closed its write side
+    ///
+    /// Emitted in half-close mode, see `Dispatcher::half_close()`.
+    ReadClosed,
+    /// Read buffer exceeds max frame size
+    FrameTooLarge,
     /// Decoder parse error
     DecoderError(<U as Decoder>::Error),
     /// Encoder parse error
@@ -41,6 +69,9 @@ where
             DispatchItem::Item(ref item) => {
                 write!(fmt, "DispatchItem::Item({:?})", item)
             }
+            DispatchItem::TimedItem(ref item, ref time) => {
+                write!(fmt, "DispatchItem::TimedItem({:?}, {:?})", item, time)
+            }
             DispatchItem::WBackPressureEnabled => {
                 write!(fmt, "DispatchItem::WBackPressureEnabled")
             }
@@ -50,6 +81,12 @@ where
             DispatchItem::KeepAliveTimeout => {
                 write!(fmt, "DispatchItem::KeepAliveTimeout")
             }
+            DispatchItem::ReadClosed => {
+                write!(fmt, "DispatchItem::ReadClosed")
+            }
+            DispatchItem::FrameTooLarge => {
+                write!(fmt, "DispatchItem::FrameTooLarge")
+            }
             DispatchItem::EncoderError(ref e) => {
                 write!(fmt, "DispatchItem::EncoderError({:?})", e)
             }
@@ -85,5 +122,11 @@ mod tests {
             .contains("DispatchItem::WBackPressureDisabled"));
         assert!(format!("{:?}", T::KeepAliveTimeout)
             .contains("DispatchItem::KeepAliveTimeout"));
+        assert!(format!("{:?}", T::ReadClosed).contains("DispatchItem::ReadClosed"));
+        assert!(
+            format!("{:?}", T::FrameTooLarge).contains("DispatchItem::FrameTooLarge")
+        );
+        let item = T::TimedItem(crate::util::BytesMut::new(), Instant::now());
+        assert!(format!("{:?}", item).contains("DispatchItem::TimedItem"));
     }
 }
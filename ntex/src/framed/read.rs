@@ -6,7 +6,7 @@ use crate::framed::State;
 /// Read io task
 pub struct ReadTask<T>
 where
-    T: AsyncRead + AsyncWrite + Unpin,
+    T: AsyncRead + AsyncWrite,
 {
     io: Rc<RefCell<T>>,
     state: State,
@@ -22,9 +22,23 @@ where
     }
 }
 
+impl<T> ReadTask<T>
+where
+    T: AsyncRead + AsyncWrite,
+{
+    /// Create new read io task for `!Unpin` io object
+    ///
+    /// # Safety
+    ///
+    /// Io object is pinned in place, it must not be moved out of `RefCell`.
+    pub(super) unsafe fn new_unchecked(io: Rc<RefCell<T>>, state: State) -> Self {
+        Self { io, state }
+    }
+}
+
 impl<T> Future for ReadTask<T>
 where
-    T: AsyncRead + AsyncWrite + Unpin,
+    T: AsyncRead + AsyncWrite,
 {
     type Output = ();
 
@@ -33,14 +47,15 @@ where
             log::trace!("read task is instructed to shutdown");
             Poll::Ready(())
         } else if self.state.is_io_stop() {
-            self.state.wake_dispatcher();
             Poll::Ready(())
         } else if self.state.is_read_paused() {
             self.state.register_read_task(cx.waker());
             Poll::Pending
         } else {
             let mut io = self.io.borrow_mut();
-            if self.state.read_io(&mut *io, cx) {
+            // io object is either `Unpin` or never moved out of `RefCell`
+            let io = unsafe { Pin::new_unchecked(&mut *io) };
+            if self.state.read_io(io, cx) {
                 Poll::Pending
             } else {
                 Poll::Ready(())
@@ -48,3 +63,13 @@ where
         }
     }
 }
+
+impl<T> Drop for ReadTask<T>
+where
+    T: AsyncRead + AsyncWrite,
+{
+    fn drop(&mut self) {
+        // notify waiters, io object is released after drop
+        self.state.io_task_stopped();
+    }
+}
@@ -1,17 +1,22 @@
 //! Framed transport dispatcher
+use std::sync::{atomic::AtomicUsize, atomic::Ordering, Arc};
 use std::task::{Context, Poll, Waker};
-use std::{cell::Cell, cell::RefCell, future::Future, hash, io, pin::Pin, rc::Rc};
+use std::{any::Any, cell::Cell, cell::RefCell, collections::VecDeque};
+use std::{cmp, future::Future, hash, io, pin::Pin, rc::Rc};
 
 use slab::Slab;
 
+use crate::codec::VectoredBuf;
 use crate::codec::{AsyncRead, AsyncWrite, Decoder, Encoder, Framed, FramedParts};
 use crate::task::LocalWaker;
-use crate::util::{poll_fn, Buf, BytesMut, Either};
+use crate::util::{poll_fn, Buf, Bytes, BytesMut, Either};
 
 bitflags::bitflags! {
     pub struct Flags: u16 {
         const DSP_STOP       = 0b0000_0000_0001;
         const DSP_KEEPALIVE  = 0b0000_0000_0010;
+        /// dispatcher is paused with `State::pause_read()`
+        const DSP_PAUSED     = 0b0100_0000_0000_0000;
 
         /// io error occured
         const IO_ERR         = 0b0000_0000_0100;
@@ -26,38 +31,89 @@ bitflags::bitflags! {
         const RD_READY       = 0b0000_0100_0000;
         /// read buffer is full
         const RD_BUF_FULL    = 0b0000_1000_0000;
+        /// half-close mode, keep write side open on read eof
+        const RD_HALF_CLOSE  = 0b0000_0010_0000_0000;
+        /// peer closed its write side
+        const RD_CLOSED      = 0b0000_0100_0000_0000;
+        /// read side reached eof
+        const RD_EOF         = 0b0000_1000_0000_0000;
 
         /// write buffer is full
         const WR_BACKPRESSURE = 0b0000_0001_0000_0000;
 
         const ST_DSP_ERR      = 0b0001_0000_0000_0000;
+
+        /// data has been read from or written to io stream
+        const IO_ACTIVITY     = 0b0010_0000_0000_0000;
     }
 }
 
 pub struct State(Rc<IoStateInner>);
 
+/// Buffers memory accounting
+///
+/// Tracks total number of bytes buffered by all states
+/// that share the same tracker. Tracker could be shared between threads.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryTracker(Arc<AtomicUsize>);
+
+impl MemoryTracker {
+    /// Create new memory tracker
+    pub fn new() -> Self {
+        MemoryTracker::default()
+    }
+
+    /// Total number of buffered bytes
+    pub fn usage(&self) -> usize {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
 pub(crate) struct IoStateInner {
     flags: Cell<Flags>,
     lw: Cell<u16>,
     read_hw: Cell<u16>,
     write_hw: Cell<u16>,
+    max_frame_size: Cell<usize>,
     disconnect_timeout: Cell<u16>,
     error: Cell<Option<io::Error>>,
     read_task: LocalWaker,
     write_task: LocalWaker,
     dispatch_task: LocalWaker,
+    write_ready_task: RefCell<Vec<Waker>>,
+    io_stop_task: LocalWaker,
     read_buf: Cell<Option<BytesMut>>,
+    read_hint: Cell<usize>,
     write_buf: Cell<Option<BytesMut>>,
+    write_chunks: RefCell<VecDeque<Bytes>>,
+    write_frames: RefCell<VecDeque<usize>>,
+    write_head: Cell<usize>,
     on_disconnect: RefCell<Slab<Option<LocalWaker>>>,
+    codec_map: Cell<Option<Box<dyn Any>>>,
+    stream_sink: RefCell<Option<Box<dyn Any>>>,
+    memory: Cell<usize>,
+    memory_tracker: RefCell<Option<MemoryTracker>>,
 }
 
 thread_local!(static R_BYTES_POOL: RefCell<Vec<BytesMut>> = RefCell::new(Vec::with_capacity(16)));
 thread_local!(static W_BYTES_POOL: RefCell<Vec<BytesMut>> = RefCell::new(Vec::with_capacity(16)));
 
+thread_local!(static POOL_SIZE: Cell<(usize, usize)> = Cell::new((16, 16)));
+
+/// Set max number of buffers in current thread's read and write buffers pools
+///
+/// Framed state returns empty buffers to the pool, so idle connections
+/// do not hold dedicated buffers. Default size is 16 for each pool.
+pub fn set_pool_size(read: usize, write: usize) {
+    POOL_SIZE.with(|size| size.set((read, write)));
+    R_BYTES_POOL.with(|pool| pool.borrow_mut().truncate(read));
+    W_BYTES_POOL.with(|pool| pool.borrow_mut().truncate(write));
+}
+
 fn release_to_r_pool(mut buf: BytesMut) {
     R_BYTES_POOL.with(|pool| {
         let v = &mut pool.borrow_mut();
-        if v.len() < 16 {
+        if v.len() < POOL_SIZE.with(|size| size.get().0) {
             buf.clear();
             v.push(buf);
         }
@@ -67,7 +123,7 @@ fn release_to_r_pool(mut buf: BytesMut) {
 fn release_to_w_pool(mut buf: BytesMut) {
     W_BYTES_POOL.with(|pool| {
         let v = &mut pool.borrow_mut();
-        if v.len() < 16 {
+        if v.len() < POOL_SIZE.with(|size| size.get().1) {
             buf.clear();
             v.push(buf);
         }
@@ -117,7 +173,8 @@ impl IoStateInner {
 
     fn release_read_buf(&self, buf: BytesMut) {
         if buf.is_empty() {
-            if buf.capacity() > (self.lw.get() as usize) {
+            let cap = buf.capacity();
+            if cap > (self.lw.get() as usize) && cap <= self.read_hw.get() as usize {
                 release_to_r_pool(buf);
             }
         } else {
@@ -125,6 +182,94 @@ impl IoStateInner {
         }
     }
 
+    fn wake_write_ready(&self) {
+        for waker in self.write_ready_task.borrow_mut().drain(..) {
+            waker.wake();
+        }
+    }
+
+    /// new frame is added to write buffer
+    fn write_frame(&self, len: usize) {
+        if len != 0 {
+            self.write_frames.borrow_mut().push_back(len);
+        }
+    }
+
+    /// data is written to io stream
+    fn write_frames_written(&self, written: usize, remaining: usize) {
+        let mut frames = self.write_frames.borrow_mut();
+        if remaining == 0 {
+            frames.clear();
+            self.write_head.set(0);
+        } else {
+            let mut head = self.write_head.get() + written;
+            while let Some(len) = frames.front() {
+                if head >= *len {
+                    head -= *len;
+                    frames.pop_front();
+                } else {
+                    break;
+                }
+            }
+            self.write_head.set(head);
+        }
+    }
+
+    /// number of bytes in read and write buffers
+    fn buffered(&self) -> usize {
+        let mut size = 0;
+        if let Some(buf) = self.read_buf.take() {
+            size += buf.len();
+            self.read_buf.set(Some(buf));
+        }
+        if let Some(buf) = self.write_buf.take() {
+            size += buf.len();
+            self.write_buf.set(Some(buf));
+        }
+        size + self.write_chunks_len()
+    }
+
+    /// size of queued write chunks
+    fn write_chunks_len(&self) -> usize {
+        self.write_chunks.borrow().iter().map(|c| c.len()).sum()
+    }
+
+    /// encode item to write buffer, returns size of buffered data
+    fn encode_item<U>(
+        &self,
+        item: U::Item,
+        codec: &U,
+        buf: &mut BytesMut,
+    ) -> Result<usize, U::Error>
+    where
+        U: Encoder,
+    {
+        let mut chunks = self.write_chunks.borrow_mut();
+        let chunks_len: usize = chunks.iter().map(|c| c.len()).sum();
+        let len = buf.len() + chunks_len;
+        codec.encode_vectored(item, &mut VectoredBuf::new(buf, &mut chunks))?;
+        let chunks_len: usize = chunks.iter().map(|c| c.len()).sum();
+        drop(chunks);
+
+        let total = buf.len() + chunks_len;
+        self.write_frame(total - len);
+        metric!(FRAMES_ENCODED, 1);
+        Ok(total)
+    }
+
+    /// update memory tracker with current buffers size
+    fn update_memory(&self) {
+        if let Some(ref tracker) = *self.memory_tracker.borrow() {
+            let size = self.buffered();
+            let prev = self.memory.replace(size);
+            if size > prev {
+                tracker.0.fetch_add(size - prev, Ordering::Relaxed);
+            } else if size < prev {
+                tracker.0.fetch_sub(prev - size, Ordering::Relaxed);
+            }
+        }
+    }
+
     fn release_write_buf(&self, buf: BytesMut) {
         if buf.is_empty() {
             let cap = buf.capacity();
@@ -139,6 +284,9 @@ impl IoStateInner {
 
 impl Drop for IoStateInner {
     fn drop(&mut self) {
+        if let Some(tracker) = self.memory_tracker.take() {
+            tracker.0.fetch_sub(self.memory.get(), Ordering::Relaxed);
+        }
         if let Some(buf) = self.read_buf.take() {
             let cap = buf.capacity();
             if cap > (self.lw.get() as usize) && cap <= self.read_hw.get() as usize {
@@ -184,13 +332,24 @@ impl State {
             lw: Cell::new(1024),
             read_hw: Cell::new(8 * 1024),
             write_hw: Cell::new(8 * 1024),
+            max_frame_size: Cell::new(0),
             disconnect_timeout: Cell::new(1),
             dispatch_task: LocalWaker::new(),
+            write_ready_task: RefCell::new(Vec::new()),
+            io_stop_task: LocalWaker::new(),
             read_task: LocalWaker::new(),
             write_task: LocalWaker::new(),
             read_buf: Cell::new(None),
+            read_hint: Cell::new(0),
             write_buf: Cell::new(None),
+            write_chunks: RefCell::new(VecDeque::new()),
+            write_frames: RefCell::new(VecDeque::new()),
+            write_head: Cell::new(0),
             on_disconnect: RefCell::new(Slab::new()),
+            codec_map: Cell::new(None),
+            stream_sink: RefCell::new(None),
+            memory: Cell::new(0),
+            memory_tracker: RefCell::new(None),
         }))
     }
 
@@ -203,7 +362,9 @@ impl State {
         } else {
             Cell::new(None)
         };
+        let mut write_frames = VecDeque::new();
         let write_buf = if !parts.write_buf.is_empty() {
+            write_frames.push_back(parts.write_buf.len());
             Cell::new(Some(parts.write_buf))
         } else {
             Cell::new(None)
@@ -211,17 +372,28 @@ impl State {
 
         let state = State(Rc::new(IoStateInner {
             read_buf,
+            read_hint: Cell::new(0),
             write_buf,
+            write_chunks: RefCell::new(VecDeque::new()),
+            write_frames: RefCell::new(write_frames),
+            write_head: Cell::new(0),
             flags: Cell::new(Flags::empty()),
             error: Cell::new(None),
             lw: Cell::new(1024),
             read_hw: Cell::new(8 * 1024),
             write_hw: Cell::new(8 * 1024),
+            max_frame_size: Cell::new(0),
             disconnect_timeout: Cell::new(1),
             dispatch_task: LocalWaker::new(),
+            write_ready_task: RefCell::new(Vec::new()),
+            io_stop_task: LocalWaker::new(),
             read_task: LocalWaker::new(),
             write_task: LocalWaker::new(),
             on_disconnect: RefCell::new(Slab::new()),
+            codec_map: Cell::new(None),
+            stream_sink: RefCell::new(None),
+            memory: Cell::new(0),
+            memory_tracker: RefCell::new(None),
         }));
         (parts.io, parts.codec, state)
     }
@@ -240,13 +412,24 @@ impl State {
             lw: Cell::new(min_buf_size),
             read_hw: Cell::new(max_read_buf_size),
             write_hw: Cell::new(max_write_buf_size),
+            max_frame_size: Cell::new(0),
             disconnect_timeout: Cell::new(disconnect_timeout),
             dispatch_task: LocalWaker::new(),
+            write_ready_task: RefCell::new(Vec::new()),
+            io_stop_task: LocalWaker::new(),
             read_buf: Cell::new(None),
+            read_hint: Cell::new(0),
             read_task: LocalWaker::new(),
             write_buf: Cell::new(None),
+            write_chunks: RefCell::new(VecDeque::new()),
+            write_frames: RefCell::new(VecDeque::new()),
+            write_head: Cell::new(0),
             write_task: LocalWaker::new(),
             on_disconnect: RefCell::new(Slab::new()),
+            codec_map: Cell::new(None),
+            stream_sink: RefCell::new(None),
+            memory: Cell::new(0),
+            memory_tracker: RefCell::new(None),
         }))
     }
 
@@ -260,11 +443,22 @@ impl State {
         } else {
             BytesMut::new()
         };
-        parts.write_buf = if let Some(buf) = self.0.write_buf.take() {
-            buf
+        let buf = self.0.write_buf.take();
+        let mut chunks = self.0.write_chunks.borrow_mut();
+        parts.write_buf = if chunks.is_empty() {
+            buf.unwrap_or_else(BytesMut::new)
         } else {
-            BytesMut::new()
+            // queued chunks precede write buffer
+            let mut write_buf = BytesMut::new();
+            for chunk in chunks.drain(..) {
+                write_buf.extend_from_slice(&chunk);
+            }
+            if let Some(buf) = buf {
+                write_buf.extend_from_slice(&buf);
+            }
+            write_buf
         };
+        drop(chunks);
         Framed::from_parts(parts)
     }
 
@@ -302,7 +496,12 @@ impl State {
     #[inline]
     /// Set read/write buffer sizes
     ///
+    /// Write task enables write back-pressure when write buffer size exceeds
+    /// `max_write_buf_size` and disables it when write buffer drains
+    /// below `min_buf_size`.
+    ///
     /// By default read max buf size is 8kb, write max buf size is 8kb
+    /// and min buf size is 1kb.
     pub fn set_buffer_params(
         &self,
         max_read_buf_size: u16,
@@ -314,12 +513,59 @@ impl State {
         self.0.lw.set(min_buf_size);
     }
 
+    #[inline]
+    /// Set max size of undecoded data in read buffer
+    ///
+    /// If read buffer contains more data than `size` and decoder
+    /// could not decode a frame, or decoder's hint for the next frame
+    /// exceeds `size`, dispatcher emits `DispatchItem::FrameTooLarge`
+    /// and stops. To disable limit set value to 0.
+    ///
+    /// By default limit is disabled.
+    pub fn set_max_frame_size(&self, size: usize) {
+        self.0.max_frame_size.set(size)
+    }
+
     #[inline]
     /// Set io disconnect timeout in secs
     pub fn set_disconnect_timeout(&self, timeout: u16) {
         self.0.disconnect_timeout.set(timeout)
     }
 
+    #[inline]
+    /// Replace dispatcher's codec
+    ///
+    /// Dispatcher calls `f` with current codec and uses returned codec
+    /// before decoding next frame. Unprocessed data in read buffer is
+    /// decoded with new codec.
+    pub fn map_codec<U, F>(&self, f: F)
+    where
+        U: 'static,
+        F: FnOnce(&U) -> U + 'static,
+    {
+        let f: Box<dyn FnOnce(&U) -> U> = Box::new(f);
+        self.0.codec_map.set(Some(Box::new(f)));
+        self.0.dispatch_task.wake();
+    }
+
+    pub(super) fn take_codec_map(&self) -> Option<Box<dyn Any>> {
+        self.0.codec_map.take()
+    }
+
+    /// Register dispatcher's encoder for streaming responses
+    pub(super) fn set_stream_sink(&self, sink: Box<dyn Any>) {
+        *self.0.stream_sink.borrow_mut() = Some(sink);
+    }
+
+    pub(super) fn stream_sink<T: Clone + 'static>(&self) -> Option<T> {
+        self.0
+            .stream_sink
+            .borrow()
+            .as_ref()
+            .and_then(|sink| sink.downcast_ref::<T>())
+            .cloned()
+    }
+
     #[inline]
     /// Notify when socket get disconnected
     pub fn on_disconnect(&self) -> OnDisconnect {
@@ -354,10 +600,85 @@ impl State {
         self.0.flags.get().contains(Flags::IO_STOP)
     }
 
+    /// Check and reset io activity flag
+    pub(super) fn take_io_activity(&self) -> bool {
+        let flags = self.0.flags.get();
+        if flags.contains(Flags::IO_ACTIVITY) {
+            self.remove_flags(Flags::IO_ACTIVITY);
+            true
+        } else {
+            false
+        }
+    }
+
     pub(super) fn is_read_paused(&self) -> bool {
         self.0.flags.get().contains(Flags::RD_PAUSED)
     }
 
+    #[inline]
+    /// Number of bytes currently buffered in read and write buffers
+    pub fn buffered_bytes(&self) -> usize {
+        self.0.buffered()
+    }
+
+    #[inline]
+    /// Register state in memory tracker
+    ///
+    /// Tracker is updated by io tasks, every time data is read from
+    /// or written to io stream.
+    pub fn set_memory_tracker(&self, tracker: &MemoryTracker) {
+        if let Some(prev) = self.0.memory_tracker.replace(Some(tracker.clone())) {
+            prev.0
+                .fetch_sub(self.0.memory.replace(0), Ordering::Relaxed);
+        }
+        self.0.update_memory();
+    }
+
+    #[inline]
+    /// Check if io stream read side reached eof
+    pub fn is_read_eof(&self) -> bool {
+        self.0.flags.get().contains(Flags::RD_EOF)
+    }
+
+    #[inline]
+    /// Check if peer closed its write side in half-close mode
+    pub fn is_read_closed(&self) -> bool {
+        self.0.flags.get().contains(Flags::RD_CLOSED)
+    }
+
+    pub(super) fn set_half_close(&self, enabled: bool) {
+        if enabled {
+            self.insert_flags(Flags::RD_HALF_CLOSE);
+        } else {
+            self.remove_flags(Flags::RD_HALF_CLOSE);
+        }
+    }
+
+    #[inline]
+    /// Pause reading and decoding of new frames
+    ///
+    /// Connection and in-flight service calls stay alive, keep-alive timer
+    /// does not expire while dispatcher is paused.
+    pub fn pause_read(&self) {
+        self.insert_flags(Flags::DSP_PAUSED | Flags::RD_PAUSED);
+    }
+
+    #[inline]
+    /// Resume reading and decoding of new frames
+    pub fn resume_read(&self) {
+        if self.0.flags.get().contains(Flags::DSP_PAUSED) {
+            self.remove_flags(Flags::DSP_PAUSED | Flags::RD_PAUSED);
+            self.0.read_task.wake();
+            self.0.dispatch_task.wake();
+        }
+    }
+
+    #[inline]
+    /// Check if reading is paused with `State::pause_read()`
+    pub fn is_paused(&self) -> bool {
+        self.0.flags.get().contains(Flags::DSP_PAUSED)
+    }
+
     #[inline]
     /// Check if keep-alive timeout occured
     pub fn is_keepalive(&self) -> bool {
@@ -384,6 +705,7 @@ impl State {
         self.0.read_task.wake();
         self.0.write_task.wake();
         self.0.dispatch_task.wake();
+        self.0.wake_write_ready();
         self.insert_flags(Flags::IO_ERR | Flags::DSP_STOP);
         self.notify_disconnect();
     }
@@ -411,6 +733,44 @@ impl State {
         self.0.dispatch_task.register(waker);
     }
 
+    pub(super) fn io_task_stopped(&self) {
+        self.0.dispatch_task.wake();
+        self.0.io_stop_task.wake();
+    }
+
+    /// Stop io tasks and take io object
+    ///
+    /// Dispatcher gets stopped as well. Waits until read and write tasks
+    /// release io object, then returns io object and unprocessed data
+    /// from read buffer. Io stream does not get shutdown.
+    ///
+    /// `io` must be the same object that is passed to `ReadTask` and `WriteTask`,
+    /// `Dispatcher::new()` keeps io object private, use `Dispatcher::from_state()`
+    /// and start io tasks manually if io object needs to be taken back.
+    pub async fn take_io<T>(&self, io: Rc<RefCell<T>>) -> (T, BytesMut) {
+        self.insert_flags(Flags::IO_STOP | Flags::DSP_STOP);
+        self.0.read_task.wake();
+        self.0.write_task.wake();
+        self.0.dispatch_task.wake();
+
+        poll_fn(|cx| {
+            if Rc::strong_count(&io) == 1 {
+                Poll::Ready(())
+            } else {
+                self.0.io_stop_task.register(cx.waker());
+                Poll::Pending
+            }
+        })
+        .await;
+
+        let io = match Rc::try_unwrap(io) {
+            Ok(io) => io.into_inner(),
+            Err(_) => unreachable!(),
+        };
+        let buf = self.0.read_buf.take().unwrap_or_else(BytesMut::new);
+        (io, buf)
+    }
+
     #[inline]
     /// Gracefully shutdown read and write io tasks
     pub fn shutdown_io(&self) {
@@ -421,6 +781,7 @@ impl State {
             self.insert_flags(Flags::IO_SHUTDOWN);
             self.0.read_task.wake();
             self.0.write_task.wake();
+            self.0.wake_write_ready();
         }
     }
 
@@ -491,6 +852,36 @@ impl State {
         self.0.read_task.wake();
         self.0.write_task.wake();
         self.0.dispatch_task.wake();
+        self.0.wake_write_ready();
+    }
+
+    #[inline]
+    /// Check if write buffer has room for more data
+    ///
+    /// Returns `Poll::Ready(true)` if write back-pressure is disabled and
+    /// `Poll::Ready(false)` if io stream is closed.
+    pub fn poll_write_ready(&self, cx: &mut Context<'_>) -> Poll<bool> {
+        let flags = self.0.flags.get();
+
+        if flags.intersects(Flags::IO_ERR | Flags::IO_SHUTDOWN) {
+            Poll::Ready(false)
+        } else if flags.contains(Flags::WR_BACKPRESSURE) {
+            let mut wakers = self.0.write_ready_task.borrow_mut();
+            if !wakers.iter().any(|w| w.will_wake(cx.waker())) {
+                wakers.push(cx.waker().clone());
+            }
+            Poll::Pending
+        } else {
+            Poll::Ready(true)
+        }
+    }
+
+    #[inline]
+    /// Wait until write buffer has room for more data
+    ///
+    /// Returns `false` if io stream is closed.
+    pub async fn write_ready(&self) -> bool {
+        poll_fn(|cx| self.poll_write_ready(cx)).await
     }
 }
 
@@ -513,13 +904,14 @@ impl State {
             let result = match item {
                 Ok(Some(el)) => Ok(Some(el)),
                 Ok(None) => {
+                    buf.reserve(codec.decode_hint(&buf));
                     let n = poll_fn(|cx| {
                         crate::codec::poll_read_buf(Pin::new(&mut *io), cx, &mut buf)
                     })
                     .await
                     .map_err(Either::Right)?;
                     if n == 0 {
-                        Ok(None)
+                        codec.decode_eof(&mut buf).map_err(Either::Left)
                     } else {
                         continue;
                     }
@@ -547,10 +939,12 @@ impl State {
         U: Encoder,
     {
         let mut buf = self.0.get_write_buf();
+        let len = buf.len();
         codec.encode(item, &mut buf).map_err(Either::Left)?;
 
+        self.0.write_frame(buf.len() - len);
         self.0.write_buf.set(Some(buf));
-        if !poll_fn(|cx| self.flush_io(io, cx)).await {
+        if !poll_fn(|cx| self.flush_io(Pin::new(&mut *io), cx)).await {
             let err = self.0.error.take().unwrap_or_else(|| {
                 io::Error::new(io::ErrorKind::Other, "Internal error")
             });
@@ -577,12 +971,15 @@ impl State {
             let item = match codec.decode(&mut buf) {
                 Ok(Some(el)) => Poll::Ready(Ok(Some(el))),
                 Ok(None) => {
+                    buf.reserve(codec.decode_hint(&buf));
                     match crate::codec::poll_read_buf(Pin::new(&mut *io), cx, &mut buf) {
                         Poll::Pending => Poll::Pending,
                         Poll::Ready(Err(err)) => Poll::Ready(Err(Either::Right(err))),
                         Poll::Ready(Ok(n)) => {
                             if n == 0 {
-                                Poll::Ready(Ok(None))
+                                Poll::Ready(
+                                    codec.decode_eof(&mut buf).map_err(Either::Left),
+                                )
                             } else {
                                 continue;
                             }
@@ -600,41 +997,65 @@ impl State {
     }
 
     /// read data from io steram and update internal state
-    pub(super) fn read_io<T>(&self, io: &mut T, cx: &mut Context<'_>) -> bool
+    pub(super) fn read_io<T>(&self, mut io: Pin<&mut T>, cx: &mut Context<'_>) -> bool
     where
-        T: AsyncRead + AsyncWrite + Unpin,
+        T: AsyncRead + AsyncWrite,
     {
         let inner = self.0.as_ref();
         let lw = inner.lw.get() as usize;
+        let hw = inner.read_hw.get() as usize;
+        // decode hint is controlled by peer, it never exceeds read buffer
+        // high watermark, oversized frames are reported by `is_frame_too_large()`
+        let hint = cmp::min(inner.read_hint.get(), hw);
         let mut buf = inner.get_read_buf();
 
+        // make sure we've got room for the next frame
+        if hint > buf.capacity() {
+            buf.reserve(hint - buf.len());
+        }
+
         // read data from socket
         let mut updated = false;
         loop {
             // make sure we've got room
             let remaining = buf.capacity() - buf.len();
             if remaining < lw {
-                buf.reserve((inner.read_hw.get() as usize) - remaining);
+                buf.reserve(hw - remaining);
             }
 
-            match crate::codec::poll_read_buf(Pin::new(&mut *io), cx, &mut buf) {
+            match crate::codec::poll_read_buf(io.as_mut(), cx, &mut buf) {
                 Poll::Pending => break,
                 Poll::Ready(Ok(n)) => {
                     if n == 0 {
-                        log::trace!("io stream is disconnected");
                         inner.release_read_buf(buf);
-                        self.set_io_error(None);
+                        inner.update_memory();
+                        inner.insert_flags(Flags::RD_EOF);
+                        if inner.flags.get().contains(Flags::RD_HALF_CLOSE) {
+                            log::trace!("io stream read side is closed");
+                            inner.insert_flags(Flags::RD_CLOSED);
+                            inner.dispatch_task.wake();
+                        } else {
+                            log::trace!("io stream is disconnected");
+                            self.set_io_error(None);
+                        }
                         return false;
                     } else {
-                        if buf.len() > inner.read_hw.get() as usize {
+                        metric!(READ_CALLS, 1);
+                        metric!(BYTES_READ, n);
+                        if buf.len() > hw {
                             log::trace!(
                                 "buffer is too large {}, enable read back-pressure",
                                 buf.len()
                             );
                             inner.dispatch_task.wake();
                             inner.read_buf.set(Some(buf));
+                            inner.update_memory();
                             inner.read_task.register(cx.waker());
-                            inner.insert_flags(Flags::RD_READY | Flags::RD_BUF_FULL);
+                            inner.insert_flags(
+                                Flags::RD_READY
+                                    | Flags::RD_BUF_FULL
+                                    | Flags::IO_ACTIVITY,
+                            );
                             return true;
                         }
 
@@ -644,6 +1065,7 @@ impl State {
                 Poll::Ready(Err(err)) => {
                     log::trace!("read task failed on io {:?}", err);
                     inner.release_read_buf(buf);
+                    inner.update_memory();
                     self.set_io_error(Some(err));
                     return false;
                 }
@@ -651,36 +1073,110 @@ impl State {
         }
 
         if updated {
-            inner.read_buf.set(Some(buf));
-            self.insert_flags(Flags::RD_READY);
-            self.0.dispatch_task.wake();
+            if buf.len() < hint {
+                log::trace!("not enough data for next frame, {} of {}", buf.len(), hint);
+                inner.read_buf.set(Some(buf));
+                self.insert_flags(Flags::IO_ACTIVITY);
+            } else {
+                inner.read_buf.set(Some(buf));
+                self.insert_flags(Flags::RD_READY | Flags::IO_ACTIVITY);
+                self.0.dispatch_task.wake();
+            }
         } else {
             inner.release_read_buf(buf);
         }
+        inner.update_memory();
         self.0.read_task.register(cx.waker());
         true
     }
 
     /// Flush write buffer to underlying I/O stream.
-    pub(super) fn flush_io<T>(&self, io: &mut T, cx: &mut Context<'_>) -> Poll<bool>
+    pub(super) fn flush_io<T>(
+        &self,
+        mut io: Pin<&mut T>,
+        cx: &mut Context<'_>,
+    ) -> Poll<bool>
     where
-        T: AsyncRead + AsyncWrite + Unpin,
+        T: AsyncRead + AsyncWrite,
     {
         let inner = self.0.as_ref();
         let mut buf = if let Some(buf) = inner.write_buf.take() {
             buf
-        } else {
+        } else if inner.write_chunks.borrow().is_empty() {
             self.0.write_task.register(cx.waker());
             return Poll::Ready(true);
+        } else {
+            BytesMut::new()
         };
+
+        // write queued chunks and write buffer with vectored io
+        let mut chunks = inner.write_chunks.borrow_mut();
+        let mut written = 0;
+        while !chunks.is_empty() {
+            let mut slices = [io::IoSlice::new(&[]); 16];
+            let mut cnt = 0;
+            for chunk in chunks.iter().take(slices.len() - 1) {
+                slices[cnt] = io::IoSlice::new(chunk);
+                cnt += 1;
+            }
+            slices[cnt] = io::IoSlice::new(&buf);
+            cnt += 1;
+
+            let mut n = match io.as_mut().poll_write_vectored(cx, &slices[..cnt]) {
+                Poll::Pending => break,
+                Poll::Ready(Ok(n)) if n > 0 => n,
+                Poll::Ready(res) => {
+                    let err = res.err().unwrap_or_else(|| {
+                        io::Error::new(
+                            io::ErrorKind::WriteZero,
+                            "failed to write frame to transport",
+                        )
+                    });
+                    log::trace!("Error during vectored flush: {}", err);
+                    chunks.clear();
+                    drop(chunks);
+                    buf.clear();
+                    inner.write_frames_written(written, 0);
+                    inner.release_write_buf(buf);
+                    inner.update_memory();
+                    self.set_io_error(Some(err));
+                    return Poll::Ready(false);
+                }
+            };
+            written += n;
+            metric!(WRITE_CALLS, 1);
+            metric!(BYTES_WRITTEN, n);
+
+            // remove written data
+            while n > 0 {
+                if let Some(chunk) = chunks.front_mut() {
+                    if n >= chunk.len() {
+                        n -= chunk.len();
+                        chunks.pop_front();
+                    } else {
+                        chunk.advance(n);
+                        n = 0;
+                    }
+                } else {
+                    buf.advance(n);
+                    n = 0;
+                }
+            }
+        }
+        let chunks_len: usize = chunks.iter().map(|c| c.len()).sum();
+        drop(chunks);
+        if written != 0 {
+            self.insert_flags(Flags::IO_ACTIVITY);
+            inner.write_frames_written(written, chunks_len + buf.len());
+        }
         let len = buf.len();
 
-        if len != 0 {
+        if len != 0 && chunks_len == 0 {
             // log::trace!("flushing framed transport: {}", len);
 
             let mut written = 0;
             while written < len {
-                match Pin::new(&mut *io).poll_write(cx, &buf[written..]) {
+                match io.as_mut().poll_write(cx, &buf[written..]) {
                     Poll::Pending => break,
                     Poll::Ready(Ok(n)) => {
                         if n == 0 {
@@ -689,20 +1185,26 @@ impl State {
                                 written
                             );
                             buf.clear();
+                            inner.write_frames_written(written, 0);
                             inner.release_write_buf(buf);
+                            inner.update_memory();
                             self.set_io_error(Some(io::Error::new(
                                 io::ErrorKind::WriteZero,
                                 "failed to write frame to transport",
                             )));
                             return Poll::Ready(false);
                         } else {
+                            metric!(WRITE_CALLS, 1);
+                            metric!(BYTES_WRITTEN, n);
                             written += n
                         }
                     }
                     Poll::Ready(Err(e)) => {
                         log::trace!("Error during flush: {}", e);
                         buf.clear();
+                        inner.write_frames_written(written, 0);
                         inner.release_write_buf(buf);
+                        inner.update_memory();
                         self.set_io_error(Some(e));
                         return Poll::Ready(false);
                     }
@@ -710,31 +1212,39 @@ impl State {
             }
             // log::trace!("flushed {} bytes", written);
 
+            if written != 0 {
+                self.insert_flags(Flags::IO_ACTIVITY);
+            }
+
             // remove written data
             if written == len {
                 buf.clear()
             } else {
                 buf.advance(written);
             }
+            inner.write_frames_written(written, buf.len());
         }
 
-        // if write buffer is smaller than high watermark value, turn off back-pressure
-        if buf.len() < self.0.write_hw.get() as usize {
+        // if write buffer is larger than high watermark value, turn on back-pressure.
+        // back-pressure stays enabled until buffer drains below low watermark value
+        let len = buf.len() + chunks_len;
+        if len >= self.0.write_hw.get() as usize {
+            self.insert_flags(Flags::WR_BACKPRESSURE);
+        } else if len < self.0.lw.get() as usize {
             let mut flags = self.0.flags.get();
             if flags.contains(Flags::WR_BACKPRESSURE) {
                 flags.remove(Flags::WR_BACKPRESSURE);
                 self.0.flags.set(flags);
                 self.0.dispatch_task.wake();
+                self.0.wake_write_ready();
             }
-        } else {
-            self.insert_flags(Flags::WR_BACKPRESSURE);
         }
         self.0.write_task.register(cx.waker());
 
         // flush
-        let result = match Pin::new(&mut *io).poll_flush(cx) {
+        let result = match io.poll_flush(cx) {
             Poll::Ready(Ok(_)) => {
-                if buf.is_empty() {
+                if len == 0 {
                     Poll::Ready(true)
                 } else {
                     Poll::Pending
@@ -748,6 +1258,7 @@ impl State {
             }
         };
         inner.release_write_buf(buf);
+        inner.update_memory();
         result
     }
 }
@@ -765,13 +1276,14 @@ impl<'a> Write<'a> {
     #[inline]
     /// Check if write buffer is full
     pub fn is_full(&self) -> bool {
-        if let Some(buf) = self.0.read_buf.take() {
-            let result = buf.len() >= self.0.write_hw.get() as usize;
+        let len = if let Some(buf) = self.0.write_buf.take() {
+            let len = buf.len();
             self.0.write_buf.set(Some(buf));
-            result
+            len
         } else {
-            false
-        }
+            0
+        };
+        len + self.0.write_chunks_len() >= self.0.write_hw.get() as usize
     }
 
     #[inline]
@@ -802,7 +1314,20 @@ impl<'a> Write<'a> {
             self.0.write_task.wake();
         }
 
+        let len = buf.len();
         let result = f(&mut buf);
+        if buf.len() > len {
+            self.0.write_frame(buf.len() - len);
+        } else if buf.len() < len {
+            // buffer is modified, treat remaining data as one frame
+            let len = buf.len() + self.0.write_chunks_len();
+            let mut frames = self.0.write_frames.borrow_mut();
+            frames.clear();
+            self.0.write_head.set(0);
+            if len != 0 {
+                frames.push_back(len);
+            }
+        }
         self.0.release_write_buf(buf);
         result
     }
@@ -832,11 +1357,16 @@ impl<'a> Write<'a> {
             }
 
             // encode item and wake write task
-            let result = codec.encode(item, &mut buf).map(|_| {
+            let result = self.0.encode_item(item, codec, &mut buf).map(|len| {
                 if is_write_sleep {
                     self.0.write_task.wake();
                 }
-                buf.len() < self.0.write_hw.get() as usize
+                if len < self.0.write_hw.get() as usize {
+                    true
+                } else {
+                    self.0.insert_flags(Flags::WR_BACKPRESSURE);
+                    false
+                }
             });
             self.0.write_buf.set(Some(buf));
             result
@@ -870,18 +1400,22 @@ impl<'a> Write<'a> {
                     }
 
                     // encode item
-                    if let Err(err) = codec.encode(item, &mut buf) {
-                        log::trace!("Encoder error: {:?}", err);
-                        self.0.release_write_buf(buf);
-                        self.0.insert_flags(Flags::DSP_STOP | Flags::ST_DSP_ERR);
-                        self.0.dispatch_task.wake();
-                        return Err(Either::Right(err));
-                    } else if is_write_sleep {
-                        self.0.write_task.wake();
+                    match self.0.encode_item(item, codec, &mut buf) {
+                        Ok(len) => {
+                            if is_write_sleep {
+                                self.0.write_task.wake();
+                            }
+                            self.0.write_buf.set(Some(buf));
+                            Ok(len < self.0.write_hw.get() as usize)
+                        }
+                        Err(err) => {
+                            log::trace!("Encoder error: {:?}", err);
+                            self.0.release_write_buf(buf);
+                            self.0.insert_flags(Flags::DSP_STOP | Flags::ST_DSP_ERR);
+                            self.0.dispatch_task.wake();
+                            Err(Either::Right(err))
+                        }
                     }
-                    let result = Ok(buf.len() < self.0.write_hw.get() as usize);
-                    self.0.write_buf.set(Some(buf));
-                    result
                 }
                 Err(err) => {
                     self.0.insert_flags(Flags::DSP_STOP | Flags::ST_DSP_ERR);
@@ -896,6 +1430,72 @@ impl<'a> Write<'a> {
     }
 }
 
+impl<'a> Write<'a> {
+    /// Write item ahead of buffered frames and wake up write task
+    ///
+    /// Item is placed right after frame that is partially written to io stream.
+    /// If vectored chunks are queued, item is placed after all buffered frames.
+    /// Returns write buffer state, false is returned if write buffer if full.
+    pub fn encode_priority<U>(
+        &self,
+        item: U::Item,
+        codec: &U,
+    ) -> Result<bool, <U as Encoder>::Error>
+    where
+        U: Encoder,
+    {
+        let flags = self.0.flags.get();
+
+        if !flags.intersects(Flags::IO_ERR | Flags::IO_SHUTDOWN) {
+            let mut buf = self.0.get_write_buf();
+            let is_write_sleep = buf.is_empty();
+
+            let mut item_buf = BytesMut::new();
+            if let Err(err) = codec.encode(item, &mut item_buf) {
+                self.0.release_write_buf(buf);
+                return Err(err);
+            }
+
+            if !item_buf.is_empty() && !self.0.write_chunks.borrow().is_empty() {
+                buf.extend_from_slice(&item_buf);
+                self.0.write_frame(item_buf.len());
+            } else if !item_buf.is_empty() {
+                let mut frames = self.0.write_frames.borrow_mut();
+
+                // skip partially written frame
+                let head = self.0.write_head.get();
+                let (pos, idx) = if head != 0 {
+                    (frames.front().map(|len| len - head).unwrap_or(0), 1)
+                } else {
+                    (0, 0)
+                };
+                let pos = std::cmp::min(pos, buf.len());
+                let idx = std::cmp::min(idx, frames.len());
+
+                let tail = buf.split_off(pos);
+                buf.extend_from_slice(&item_buf);
+                buf.extend_from_slice(&tail);
+                frames.insert(idx, item_buf.len());
+            }
+
+            if is_write_sleep {
+                self.0.write_task.wake();
+            }
+            let len = buf.len() + self.0.write_chunks_len();
+            let result = if len < self.0.write_hw.get() as usize {
+                true
+            } else {
+                self.0.insert_flags(Flags::WR_BACKPRESSURE);
+                false
+            };
+            self.0.write_buf.set(Some(buf));
+            Ok(result)
+        } else {
+            Ok(true)
+        }
+    }
+}
+
 #[derive(Copy, Clone)]
 pub struct Read<'a>(&'a IoStateInner);
 
@@ -918,6 +1518,24 @@ impl<'a> Read<'a> {
         }
     }
 
+    #[inline]
+    /// Check if read buffer or decoder's hint for the next frame
+    /// exceeds max frame size
+    pub fn is_frame_too_large(&self) -> bool {
+        let max = self.0.max_frame_size.get();
+        if max == 0 {
+            false
+        } else if self.0.read_hint.get() > max {
+            true
+        } else if let Some(buf) = self.0.read_buf.take() {
+            let result = buf.len() > max;
+            self.0.read_buf.set(Some(buf));
+            result
+        } else {
+            false
+        }
+    }
+
     #[inline]
     /// Pause read task
     ///
@@ -931,7 +1549,7 @@ impl<'a> Read<'a> {
     /// Wake read io task if it is paused
     pub fn resume(&self) {
         let flags = self.0.flags.get();
-        if flags.contains(Flags::RD_PAUSED) {
+        if flags.contains(Flags::RD_PAUSED) && !flags.contains(Flags::DSP_PAUSED) {
             self.0.remove_flags(Flags::RD_PAUSED);
             self.0.read_task.wake();
         }
@@ -960,15 +1578,44 @@ impl<'a> Read<'a> {
         &self,
         codec: &U,
     ) -> Result<Option<<U as Decoder>::Item>, <U as Decoder>::Error>
+    where
+        U: Decoder,
+    {
+        let mut buf = self.0.read_buf.take().unwrap_or_else(BytesMut::new);
+        let result = codec.decode(&mut buf);
+        metric!(FRAMES_DECODED, matches!(result, Ok(Some(_))) as usize);
+
+        // remember how much data codec needs for the next frame
+        let hint = if let Ok(None) = result {
+            codec.decode_hint(&buf)
+        } else {
+            0
+        };
+        self.0
+            .read_hint
+            .set(if hint > 0 { buf.len() + hint } else { 0 });
+        self.0.release_read_buf(buf);
+        result
+    }
+
+    #[inline]
+    /// Attempts to decode a frame from the read buffer, when read side
+    /// reached eof.
+    ///
+    /// Codec could emit final frame or report truncated frame.
+    pub fn decode_eof<U>(
+        &self,
+        codec: &U,
+    ) -> Result<Option<<U as Decoder>::Item>, <U as Decoder>::Error>
     where
         U: Decoder,
     {
         if let Some(mut buf) = self.0.read_buf.take() {
-            let result = codec.decode(&mut buf);
+            let result = codec.decode_eof(&mut buf);
             self.0.release_read_buf(buf);
             result
         } else {
-            codec.decode(&mut BytesMut::new())
+            codec.decode_eof(&mut BytesMut::new())
         }
     }
 
@@ -979,6 +1626,7 @@ impl<'a> Read<'a> {
     {
         if let Some(mut buf) = self.0.read_buf.take() {
             let res = f(&mut buf);
+            self.0.read_hint.set(0);
             self.0.release_read_buf(buf);
             res
         } else {
@@ -1046,6 +1694,7 @@ impl Drop for OnDisconnect {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::framed::{ReadTask, WriteTask};
     use crate::{codec::BytesCodec, testing::Io, util::lazy, util::Bytes};
 
     const BIN: &[u8] = b"GET /test HTTP/1\r\n\r\n";
@@ -1116,6 +1765,346 @@ mod tests {
         state.flags().contains(Flags::IO_SHUTDOWN);
     }
 
+    /// Fixed size frames codec
+    struct FixedCodec(usize);
+
+    impl Decoder for FixedCodec {
+        type Item = BytesMut;
+        type Error = io::Error;
+
+        fn decode(&self, src: &mut BytesMut) -> Result<Option<BytesMut>, io::Error> {
+            if src.len() >= self.0 {
+                Ok(Some(src.split_to(self.0)))
+            } else {
+                Ok(None)
+            }
+        }
+
+        fn decode_hint(&self, src: &BytesMut) -> usize {
+            self.0.saturating_sub(src.len())
+        }
+    }
+
+    #[crate::rt_test]
+    async fn test_decode_hint() {
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(1024);
+        let mut server = Box::pin(server);
+
+        let state = State::new();
+        state.set_buffer_params(32 * 1024, 8 * 1024, 1024);
+        let codec = FixedCodec(16 * 1024);
+        assert!(state.read().decode(&codec).unwrap().is_none());
+
+        // partial frame does not wake dispatcher
+        client.write(vec![b'a'; 8 * 1024]);
+        assert!(lazy(|cx| state.read_io(server.as_mut(), cx)).await);
+        assert!(!state.read().is_ready());
+        assert!(state.read().with_buf(|buf| buf.capacity() >= 16 * 1024));
+        assert_eq!(state.buffered_bytes(), 8 * 1024);
+
+        client.write(vec![b'a'; 8 * 1024]);
+        assert!(lazy(|cx| state.read_io(server.as_mut(), cx)).await);
+        assert!(state.read().is_ready());
+        let item = state.read().decode(&codec).unwrap().unwrap();
+        assert_eq!(item.len(), 16 * 1024);
+
+        // codec without hint
+        client.write(TEXT);
+        assert!(lazy(|cx| state.read_io(server.as_mut(), cx)).await);
+        assert!(state.read().is_ready());
+        assert!(state.read().decode(&BytesCodec).unwrap().is_some());
+    }
+
+    #[crate::rt_test]
+    async fn test_decode_hint_oversized() {
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(1024);
+        let mut server = Box::pin(server);
+
+        let state = State::new();
+        let codec = FixedCodec(usize::MAX / 2);
+        assert!(state.read().decode(&codec).unwrap().is_none());
+        assert!(!state.read().is_frame_too_large());
+
+        // hint is clamped to read buffer high watermark
+        client.write(vec![b'a'; 1024]);
+        assert!(lazy(|cx| state.read_io(server.as_mut(), cx)).await);
+        assert!(!state.read().is_ready());
+        assert!(state.read().with_buf(|buf| buf.capacity() <= 16 * 1024));
+
+        // hint exceeds max frame size
+        state.set_max_frame_size(64 * 1024);
+        assert!(state.read().decode(&codec).unwrap().is_none());
+        assert!(state.read().is_frame_too_large());
+    }
+
+    #[test]
+    fn test_pool_size() {
+        set_pool_size(1, 0);
+        release_to_r_pool(BytesMut::with_capacity(2048));
+        release_to_r_pool(BytesMut::with_capacity(2048));
+        release_to_w_pool(BytesMut::with_capacity(2048));
+        assert_eq!(R_BYTES_POOL.with(|pool| pool.borrow().len()), 1);
+        assert_eq!(W_BYTES_POOL.with(|pool| pool.borrow().len()), 0);
+
+        set_pool_size(0, 0);
+        assert_eq!(R_BYTES_POOL.with(|pool| pool.borrow().len()), 0);
+        set_pool_size(16, 16);
+    }
+
+    #[test]
+    fn test_release_oversized_read_buf() {
+        set_pool_size(0, 0);
+        set_pool_size(16, 16);
+
+        let state = State::new();
+        state.set_buffer_params(1024, 1024, 256);
+
+        // oversized buffer is dropped
+        state.0.release_read_buf(BytesMut::with_capacity(4096));
+        assert_eq!(R_BYTES_POOL.with(|pool| pool.borrow().len()), 0);
+
+        // small buffer is dropped
+        state.0.release_read_buf(BytesMut::with_capacity(128));
+        assert_eq!(R_BYTES_POOL.with(|pool| pool.borrow().len()), 0);
+
+        state.0.release_read_buf(BytesMut::with_capacity(1024));
+        assert_eq!(R_BYTES_POOL.with(|pool| pool.borrow().len()), 1);
+        assert!(state.0.get_read_buf().capacity() <= 1024);
+
+        // non empty buffer is kept by state
+        let mut buf = BytesMut::with_capacity(4096);
+        buf.extend_from_slice(b"data");
+        state.0.release_read_buf(buf);
+        assert_eq!(R_BYTES_POOL.with(|pool| pool.borrow().len()), 0);
+        assert_eq!(&state.0.get_read_buf()[..], b"data");
+    }
+
+    #[crate::rt_test]
+    async fn test_write_ready() {
+        let (client, mut server) = Io::create();
+        client.remote_buffer_cap(0);
+
+        let state = State::new();
+        state.set_buffer_params(8 * 1024, 16, 8);
+        assert!(state.write_ready().await);
+
+        let res = state
+            .write()
+            .encode(Bytes::from_static(b"0123456789012345"), &BytesCodec);
+        assert!(!res.unwrap());
+        assert!(lazy(|cx| state.poll_write_ready(cx)).await.is_pending());
+
+        client.remote_buffer_cap(1024);
+        assert!(poll_fn(|cx| state.flush_io(Pin::new(&mut server), cx)).await);
+        assert!(state.write_ready().await);
+        let buf = client.read().await.unwrap();
+        assert_eq!(buf, Bytes::from_static(b"0123456789012345"));
+
+        state.force_close();
+        assert!(!state.write_ready().await);
+    }
+
+    #[crate::rt_test]
+    async fn test_encode_priority() {
+        let (client, mut server) = Io::create();
+        client.remote_buffer_cap(2);
+
+        let state = State::new();
+        let write = state.write();
+        write
+            .encode(Bytes::from_static(b"aaaa"), &BytesCodec)
+            .unwrap();
+        write
+            .encode(Bytes::from_static(b"bbbb"), &BytesCodec)
+            .unwrap();
+        assert!(lazy(|cx| state.flush_io(Pin::new(&mut server), cx))
+            .await
+            .is_pending());
+        assert_eq!(client.read_any(), Bytes::from_static(b"aa"));
+
+        // priority frame goes after partially written frame
+        write
+            .encode_priority(Bytes::from_static(b"P1"), &BytesCodec)
+            .unwrap();
+        assert_eq!(write.with_buf(|buf| buf.split().freeze()), "aaP1bbbb");
+
+        write
+            .encode(Bytes::from_static(b"aaaa"), &BytesCodec)
+            .unwrap();
+        write
+            .encode(Bytes::from_static(b"bbbb"), &BytesCodec)
+            .unwrap();
+        client.remote_buffer_cap(4);
+        assert!(lazy(|cx| state.flush_io(Pin::new(&mut server), cx))
+            .await
+            .is_pending());
+        assert_eq!(client.read_any(), Bytes::from_static(b"aaaa"));
+
+        // frame is fully written, priority frame goes first
+        write
+            .encode_priority(Bytes::from_static(b"P2"), &BytesCodec)
+            .unwrap();
+        client.remote_buffer_cap(1024);
+        assert!(poll_fn(|cx| state.flush_io(Pin::new(&mut server), cx)).await);
+        assert_eq!(client.read_any(), Bytes::from_static(b"P2bbbb"));
+    }
+
+    #[crate::rt_test]
+    async fn test_memory_tracker() {
+        let (client, mut server) = Io::create();
+        client.remote_buffer_cap(0);
+
+        let tracker = MemoryTracker::new();
+        let state = State::new();
+        state.set_memory_tracker(&tracker);
+        let state2 = State::new();
+        state2.set_memory_tracker(&tracker);
+
+        state
+            .write()
+            .encode(Bytes::from_static(b"0123456789"), &BytesCodec)
+            .unwrap();
+        state2
+            .write()
+            .encode(Bytes::from_static(b"01234"), &BytesCodec)
+            .unwrap();
+        assert_eq!(state.buffered_bytes(), 10);
+        assert_eq!(tracker.usage(), 0);
+
+        assert!(lazy(|cx| state.flush_io(Pin::new(&mut server), cx))
+            .await
+            .is_pending());
+        assert_eq!(tracker.usage(), 10);
+
+        client.remote_buffer_cap(4);
+        assert!(lazy(|cx| state.flush_io(Pin::new(&mut server), cx))
+            .await
+            .is_pending());
+        assert_eq!(state.buffered_bytes(), 6);
+        assert_eq!(tracker.usage(), 6);
+
+        state2.set_memory_tracker(&tracker);
+        assert_eq!(tracker.usage(), 11);
+        drop(state2);
+        assert_eq!(tracker.usage(), 6);
+    }
+
+    #[crate::rt_test]
+    async fn test_next_eof() {
+        let (client, mut server) = Io::create();
+        client.remote_buffer_cap(1024);
+        client.write("line");
+        client.close().await;
+
+        let state = State::new();
+        let codec = crate::codec::LinesCodec::<String>::new();
+        let item = state.next(&mut server, &codec).await.ok().unwrap();
+        assert_eq!(item.unwrap(), "line");
+    }
+
+    #[crate::rt_test]
+    async fn test_encode_vectored() {
+        struct ChunkCodec;
+
+        impl Encoder for ChunkCodec {
+            type Item = Bytes;
+            type Error = io::Error;
+
+            fn encode(&self, item: Bytes, dst: &mut BytesMut) -> Result<(), io::Error> {
+                dst.extend_from_slice(&item);
+                Ok(())
+            }
+
+            fn encode_vectored(
+                &self,
+                item: Bytes,
+                dst: &mut VectoredBuf<'_>,
+            ) -> Result<(), io::Error> {
+                dst.buf().extend_from_slice(b"HDR");
+                dst.put_chunk(item);
+                Ok(())
+            }
+        }
+
+        let (client, mut server) = Io::create();
+        client.remote_buffer_cap(1000);
+
+        let state = State::new();
+        let payload = Bytes::from(vec![b'x'; 2048]);
+        let write = state.write();
+        assert!(write.encode(payload.clone(), &ChunkCodec).unwrap());
+        assert!(write
+            .encode(Bytes::from_static(b"small"), &ChunkCodec)
+            .unwrap());
+        assert_eq!(state.buffered_bytes(), 2048 + 11);
+
+        assert!(lazy(|cx| state.flush_io(Pin::new(&mut server), cx))
+            .await
+            .is_pending());
+        assert_eq!(state.buffered_bytes(), 2048 + 11 - 1000);
+
+        client.remote_buffer_cap(4096);
+        assert!(poll_fn(|cx| state.flush_io(Pin::new(&mut server), cx)).await);
+        assert_eq!(state.buffered_bytes(), 0);
+
+        let buf = client.read_any();
+        assert_eq!(buf.len(), 2048 + 11);
+        assert_eq!(&buf[..3], b"HDR");
+        assert_eq!(&buf[3..2051], &payload[..]);
+        assert_eq!(&buf[2051..], b"HDRsmall");
+    }
+
+    #[crate::rt_test]
+    async fn test_take_io() {
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(1024);
+
+        let state = State::new();
+        let io = Rc::new(RefCell::new(server));
+        crate::rt::spawn(ReadTask::new(io.clone(), state.clone()));
+        crate::rt::spawn(WriteTask::new(io.clone(), state.clone()));
+
+        client.write(TEXT);
+        crate::rt::time::sleep(std::time::Duration::from_millis(50)).await;
+
+        let (mut io, buf) = state.take_io(io).await;
+        assert_eq!(buf, Bytes::from_static(BIN));
+        assert!(state.is_dispatcher_stopped());
+        assert!(!client.is_closed());
+
+        state
+            .send(&mut io, &BytesCodec, Bytes::from_static(b"test"))
+            .await
+            .unwrap();
+        let buf = client.read().await.unwrap();
+        assert_eq!(buf, Bytes::from_static(b"test"));
+    }
+
+    #[crate::rt_test]
+    async fn test_take_io_after_shutdown() {
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(1024);
+
+        let state = State::new();
+        let io = Rc::new(RefCell::new(server));
+        crate::rt::spawn(ReadTask::new(io.clone(), state.clone()));
+        crate::rt::spawn(WriteTask::new(io.clone(), state.clone()));
+
+        // read task exits on io error
+        client.read_error(io::Error::new(io::ErrorKind::Other, "err"));
+        crate::rt::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(state.is_io_err());
+
+        let res = crate::rt::time::timeout(
+            std::time::Duration::from_millis(500),
+            state.take_io(io),
+        )
+        .await;
+        assert!(res.is_ok());
+    }
+
     #[crate::rt_test]
     async fn test_on_disconnect() {
         let state = State::new();
@@ -0,0 +1,333 @@
+//! Service adapter for streaming responses
+use std::{
+    future::Future, marker::PhantomData, pin::Pin, rc::Rc, task::Context, task::Poll,
+};
+
+use crate::codec::{Decoder, Encoder};
+use crate::framed::dispatcher::StreamSink;
+use crate::framed::{DispatchItem, State};
+use crate::service::{IntoService, Service};
+use crate::Stream;
+
+/// Service adapter for streaming responses
+///
+/// Wrapped service responds with a stream of encoder items. Adapter writes
+/// stream items with dispatcher's codec and waits for write back-pressure
+/// between items. Adapter's response is always `None`, so it could be used
+/// with `Dispatcher`. In ordered mode, stream items are written after all
+/// preceding responses. Encoder errors are handled by dispatcher and passed
+/// to the service as `DispatchItem::EncoderError`.
+pub struct StreamService<S, U> {
+    service: S,
+    state: State,
+    _t: PhantomData<U>,
+}
+
+impl<S, U, St> StreamService<S, U>
+where
+    S: Service<Request = DispatchItem<U>, Response = Option<St>>,
+    U: Encoder + Decoder + 'static,
+    St: Stream<Item = Result<<U as Encoder>::Item, S::Error>>,
+{
+    /// Construct new `StreamService` instance.
+    ///
+    /// `state` must be the state of dispatcher that runs this service.
+    pub fn new<F: IntoService<S>>(service: F, state: State) -> Self {
+        StreamService {
+            state,
+            service: service.into_service(),
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<S, U, St> Service for StreamService<S, U>
+where
+    S: Service<Request = DispatchItem<U>, Response = Option<St>>,
+    U: Encoder + Decoder + 'static,
+    St: Stream<Item = Result<<U as Encoder>::Item, S::Error>>,
+{
+    type Request = DispatchItem<U>;
+    type Response = Option<<U as Encoder>::Item>;
+    type Error = S::Error;
+    type Future = StreamServiceResponse<S, U, St>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    #[inline]
+    fn call(&self, req: DispatchItem<U>) -> Self::Future {
+        // dispatcher reserves response slot right after service call
+        let sink = self.state.stream_sink::<Rc<dyn StreamSink<U>>>();
+        let seq = sink.as_ref().and_then(|sink| sink.next_response());
+
+        StreamServiceResponse {
+            seq,
+            sink,
+            fut: self.service.call(req),
+            stream: None,
+            state: self.state.clone(),
+        }
+    }
+}
+
+pin_project_lite::pin_project! {
+    #[doc(hidden)]
+    pub struct StreamServiceResponse<S: Service, U: Encoder, St> {
+        #[pin]
+        fut: S::Future,
+        stream: Option<Pin<Box<St>>>,
+        state: State,
+        sink: Option<Rc<dyn StreamSink<U>>>,
+        seq: Option<usize>,
+    }
+}
+
+impl<S, U, St> Future for StreamServiceResponse<S, U, St>
+where
+    S: Service<Request = DispatchItem<U>, Response = Option<St>>,
+    U: Encoder + Decoder + 'static,
+    St: Stream<Item = Result<<U as Encoder>::Item, S::Error>>,
+{
+    type Output = Result<Option<<U as Encoder>::Item>, S::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        if this.stream.is_none() {
+            match this.fut.poll(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Ok(Some(stream))) => *this.stream = Some(Box::pin(stream)),
+                Poll::Ready(Ok(None)) => return Poll::Ready(Ok(None)),
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+            }
+        }
+        let stream = this.stream.as_mut().unwrap();
+
+        let sink = if let Some(ref sink) = this.sink {
+            sink
+        } else {
+            log::error!("codec type does not match dispatcher codec");
+            return Poll::Ready(Ok(None));
+        };
+
+        // wait until preceding responses are written
+        if sink.poll_response_turn(*this.seq, cx).is_pending() {
+            return Poll::Pending;
+        }
+
+        loop {
+            // wait for write back-pressure
+            match this.state.poll_write_ready(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(false) => return Poll::Ready(Ok(None)),
+                Poll::Ready(true) => (),
+            }
+
+            match stream.as_mut().poll_next(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(None) => return Poll::Ready(Ok(None)),
+                Poll::Ready(Some(Err(err))) => return Poll::Ready(Err(err)),
+                Poll::Ready(Some(Ok(item))) => {
+                    if !sink.encode(item, this.state.write()) {
+                        return Poll::Ready(Ok(None));
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use std::cell::Cell;
+
+    use super::*;
+    use crate::codec::BytesCodec;
+    use crate::framed::{Dispatcher, Timer};
+    use crate::{rt::time::sleep, testing::Io, util::Bytes, util::BytesMut};
+
+    struct UpperCodec(bool);
+
+    impl Encoder for UpperCodec {
+        type Item = Bytes;
+        type Error = std::io::Error;
+
+        fn encode(&self, item: Bytes, dst: &mut BytesMut) -> Result<(), Self::Error> {
+            if &item[..] == b"err" {
+                Err(std::io::Error::new(std::io::ErrorKind::Other, "err"))
+            } else if self.0 {
+                dst.extend_from_slice(&item.to_ascii_uppercase());
+                Ok(())
+            } else {
+                dst.extend_from_slice(&item[..]);
+                Ok(())
+            }
+        }
+    }
+
+    impl Decoder for UpperCodec {
+        type Item = BytesMut;
+        type Error = std::io::Error;
+
+        fn decode(&self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+            BytesCodec.decode(src)
+        }
+    }
+
+    #[crate::rt_test]
+    async fn test_stream() {
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(1024);
+        client.write("GET /test HTTP/1\r\n\r\n");
+
+        let state = State::new();
+        let srv = StreamService::new(
+            crate::fn_service(|msg: DispatchItem<BytesCodec>| async move {
+                if let DispatchItem::Item(_) = msg {
+                    Ok::<_, ()>(Some(futures::stream::iter(vec![
+                        Ok(Bytes::from_static(b"1")),
+                        Ok(Bytes::from_static(b"2")),
+                        Ok(Bytes::from_static(b"3")),
+                    ])))
+                } else {
+                    Ok(None)
+                }
+            }),
+            state.clone(),
+        );
+        let disp = Dispatcher::new(server, BytesCodec, state, srv, Timer::default());
+        crate::rt::spawn(async move {
+            let _ = disp.await;
+        });
+        sleep(Duration::from_millis(50)).await;
+
+        assert_eq!(client.read_any(), Bytes::from_static(b"123"));
+    }
+
+    #[crate::rt_test]
+    async fn test_stream_ordered() {
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(1024);
+
+        let state = State::new();
+        let srv = StreamService::new(
+            crate::fn_service(|msg: DispatchItem<BytesCodec>| async move {
+                if let DispatchItem::Item(msg) = msg {
+                    if &msg[..] == b"1" {
+                        sleep(Duration::from_millis(100)).await;
+                    }
+                    Ok::<_, ()>(Some(futures::stream::iter(vec![
+                        Ok(msg.freeze()),
+                        Ok(Bytes::from_static(b"x")),
+                    ])))
+                } else {
+                    Ok(None)
+                }
+            }),
+            state.clone(),
+        );
+        let disp = Dispatcher::new(server, BytesCodec, state, srv, Timer::default())
+            .ordered_responses(true);
+        crate::rt::spawn(async move {
+            let _ = disp.await;
+        });
+
+        client.write("1");
+        sleep(Duration::from_millis(25)).await;
+        client.write("2");
+        sleep(Duration::from_millis(25)).await;
+        assert!(client.read_any().is_empty());
+
+        sleep(Duration::from_millis(150)).await;
+        assert_eq!(client.read_any(), Bytes::from_static(b"1x2x"));
+    }
+
+    #[crate::rt_test]
+    async fn test_stream_map_codec() {
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(1024);
+
+        let state = State::new();
+        let srv = StreamService::new(
+            crate::fn_service(|msg: DispatchItem<UpperCodec>| async move {
+                if let DispatchItem::Item(msg) = msg {
+                    Ok::<_, ()>(Some(futures::stream::iter(vec![Ok(msg.freeze())])))
+                } else {
+                    Ok(None)
+                }
+            }),
+            state.clone(),
+        );
+        let disp = Dispatcher::new(
+            server,
+            UpperCodec(false),
+            state.clone(),
+            srv,
+            Timer::default(),
+        );
+        crate::rt::spawn(async move {
+            let _ = disp.await;
+        });
+
+        client.write("test");
+        let buf = client.read().await.unwrap();
+        assert_eq!(buf, Bytes::from_static(b"test"));
+
+        state.map_codec(|codec: &UpperCodec| UpperCodec(!codec.0));
+        client.write("test");
+        let buf = client.read().await.unwrap();
+        assert_eq!(buf, Bytes::from_static(b"TEST"));
+    }
+
+    #[crate::rt_test]
+    async fn test_stream_encoder_error() {
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(1024);
+        client.write("test");
+
+        let err = Rc::new(Cell::new(false));
+        let err2 = err.clone();
+        let state = State::new();
+        let srv = StreamService::new(
+            crate::fn_service(move |msg: DispatchItem<UpperCodec>| {
+                let err = err2.clone();
+                async move {
+                    match msg {
+                        DispatchItem::Item(_) => {
+                            Ok::<_, ()>(Some(futures::stream::iter(vec![
+                                Ok(Bytes::from_static(b"1")),
+                                Ok(Bytes::from_static(b"err")),
+                                Ok(Bytes::from_static(b"2")),
+                            ])))
+                        }
+                        DispatchItem::EncoderError(_) => {
+                            err.set(true);
+                            Ok(None)
+                        }
+                        _ => Ok(None),
+                    }
+                }
+            }),
+            state.clone(),
+        );
+        let disp =
+            Dispatcher::new(server, UpperCodec(false), state, srv, Timer::default());
+        crate::rt::spawn(async move {
+            let _ = disp.await;
+        });
+        sleep(Duration::from_millis(50)).await;
+
+        assert!(err.get());
+        assert_eq!(client.read_any(), Bytes::from_static(b"1"));
+    }
+}
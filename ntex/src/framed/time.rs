@@ -4,10 +4,23 @@ use crate::framed::State;
 use crate::rt::time::sleep;
 use crate::util::HashSet;
 
+/// Keep-alive timer for framed connections
+///
+/// Timer checks registered expiration times once per tick. Tick length is
+/// defined by timer resolution, default resolution is 1 second.
+/// Timer could be shared between multiple connections.
+///
+/// ```rust
+/// use std::time::Duration;
+/// use ntex::framed::Timer;
+///
+/// let timer = Timer::with(Duration::from_millis(100)).capacity(1024);
+/// ```
 pub struct Timer(Rc<RefCell<Inner>>);
 
 struct Inner {
     resolution: Duration,
+    capacity: usize,
     current: Option<Instant>,
     notifications: BTreeMap<Instant, HashSet<State>>,
 }
@@ -16,6 +29,7 @@ impl Inner {
     fn new(resolution: Duration) -> Self {
         Inner {
             resolution,
+            capacity: 0,
             current: None,
             notifications: BTreeMap::default(),
         }
@@ -44,19 +58,37 @@ impl Default for Timer {
 }
 
 impl Timer {
+    /// Create timer with custom resolution
     pub fn with(resolution: Duration) -> Timer {
         Timer(Rc::new(RefCell::new(Inner::new(resolution))))
     }
 
+    /// Set initial capacity of the per-tick connections set
+    ///
+    /// Capacity is an expected number of connections that expire within
+    /// the same tick. By default capacity is 0, set grows on demand.
+    pub fn capacity(self, capacity: usize) -> Self {
+        self.0.borrow_mut().capacity = capacity;
+        self
+    }
+
+    /// Get timer resolution
+    pub fn resolution(&self) -> Duration {
+        self.0.borrow().resolution
+    }
+
     pub fn register(&self, expire: Instant, previous: Instant, state: &State) {
         {
             let mut inner = self.0.borrow_mut();
 
             inner.unregister(previous, state);
+            let capacity = inner.capacity;
             inner
                 .notifications
                 .entry(expire)
-                .or_insert_with(HashSet::default)
+                .or_insert_with(|| {
+                    HashSet::with_capacity_and_hasher(capacity, Default::default())
+                })
                 .insert(state.clone());
         }
 
@@ -86,7 +118,8 @@ impl Timer {
                 sleep(interval).await;
                 let empty = {
                     let mut i = inner.borrow_mut();
-                    let now = i.current.take().unwrap_or_else(Instant::now);
+                    i.current = None;
+                    let now = Instant::now();
 
                     // notify io dispatcher
                     while let Some(key) = i.notifications.keys().next() {
@@ -112,3 +145,25 @@ impl Timer {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[crate::rt_test]
+    async fn test_resolution() {
+        let timer = Timer::with(Duration::from_millis(100)).capacity(16);
+        assert_eq!(timer.resolution(), Duration::from_millis(100));
+
+        let state = State::new();
+        let expire = timer.now() + Duration::from_millis(150);
+        timer.register(expire, expire, &state);
+        assert!(timer.0.borrow().notifications[&expire].capacity() >= 16);
+
+        sleep(Duration::from_millis(50)).await;
+        assert!(!state.is_keepalive());
+
+        sleep(Duration::from_millis(250)).await;
+        assert!(state.is_keepalive());
+    }
+}
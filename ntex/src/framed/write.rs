@@ -21,7 +21,7 @@ enum Shutdown {
 /// Write io task
 pub struct WriteTask<T>
 where
-    T: AsyncRead + AsyncWrite + Unpin,
+    T: AsyncRead + AsyncWrite,
 {
     st: IoWriteState,
     io: Rc<RefCell<T>>,
@@ -57,13 +57,32 @@ where
     }
 }
 
+impl<T> WriteTask<T>
+where
+    T: AsyncRead + AsyncWrite,
+{
+    /// Create new write io task for `!Unpin` io object
+    ///
+    /// # Safety
+    ///
+    /// Io object is pinned in place, it must not be moved out of `RefCell`.
+    pub(super) unsafe fn new_unchecked(io: Rc<RefCell<T>>, state: State) -> Self {
+        Self {
+            io,
+            state,
+            st: IoWriteState::Processing,
+        }
+    }
+}
+
 impl<T> Future for WriteTask<T>
 where
-    T: AsyncRead + AsyncWrite + Unpin,
+    T: AsyncRead + AsyncWrite,
 {
     type Output = ();
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // WriteTask itself is Unpin, io object is stored in `Rc<RefCell<T>>`
         let mut this = self.as_mut().get_mut();
 
         // IO error occured
@@ -71,7 +90,6 @@ where
             log::trace!("write io is closed");
             return Poll::Ready(());
         } else if this.state.is_io_stop() {
-            self.state.wake_dispatcher();
             return Poll::Ready(());
         }
 
@@ -95,7 +113,8 @@ where
                 }
 
                 // flush framed instance
-                match this.state.flush_io(&mut *this.io.borrow_mut(), cx) {
+                let mut io = this.io.borrow_mut();
+                match this.state.flush_io(io_pin(&mut *io), cx) {
                     Poll::Pending | Poll::Ready(true) => Poll::Pending,
                     Poll::Ready(false) => Poll::Ready(()),
                 }
@@ -107,8 +126,8 @@ where
                     match st {
                         Shutdown::None => {
                             // flush write buffer
-                            let result =
-                                this.state.flush_io(&mut *this.io.borrow_mut(), cx);
+                            let mut io = this.io.borrow_mut();
+                            let result = this.state.flush_io(io_pin(&mut *io), cx);
                             match result {
                                 Poll::Ready(true) => {
                                     *st = Shutdown::Flushed;
@@ -126,8 +145,8 @@ where
                         }
                         Shutdown::Flushed => {
                             // shutdown WRITE side
-                            match Pin::new(&mut *this.io.borrow_mut()).poll_shutdown(cx)
-                            {
+                            let mut io = this.io.borrow_mut();
+                            match io_pin(&mut *io).poll_shutdown(cx) {
                                 Poll::Ready(Ok(_)) => {
                                     *st = Shutdown::Shutdown;
                                     continue;
@@ -148,7 +167,7 @@ where
                             let mut io = this.io.borrow_mut();
                             loop {
                                 let mut read_buf = ReadBuf::new(&mut buf);
-                                match Pin::new(&mut *io).poll_read(cx, &mut read_buf) {
+                                match io_pin(&mut *io).poll_read(cx, &mut read_buf) {
                                     Poll::Ready(Err(_)) | Poll::Ready(Ok(_))
                                         if read_buf.filled().is_empty() =>
                                     {
@@ -177,3 +196,19 @@ where
         }
     }
 }
+
+impl<T> Drop for WriteTask<T>
+where
+    T: AsyncRead + AsyncWrite,
+{
+    fn drop(&mut self) {
+        // notify waiters, io object is released after drop
+        self.state.io_task_stopped();
+    }
+}
+
+#[inline]
+fn io_pin<T>(io: &mut T) -> Pin<&mut T> {
+    // io object is either `Unpin` or never moved out of `RefCell`
+    unsafe { Pin::new_unchecked(io) }
+}
@@ -466,12 +466,18 @@ where
         let service = apply_fn(
             service.into_service().map_err(ws::WsError::Service),
             |req, srv| match req {
-                DispatchItem::Item(item) => Either::Left(srv.call(item)),
+                DispatchItem::Item(item) | DispatchItem::TimedItem(item, _) => {
+                    Either::Left(srv.call(item))
+                }
                 DispatchItem::WBackPressureEnabled
-                | DispatchItem::WBackPressureDisabled => Either::Right(Ready::Ok(None)),
+                | DispatchItem::WBackPressureDisabled
+                | DispatchItem::ReadClosed => Either::Right(Ready::Ok(None)),
                 DispatchItem::KeepAliveTimeout => {
                     Either::Right(Ready::Err(ws::WsError::KeepAlive))
                 }
+                DispatchItem::FrameTooLarge => Either::Right(Ready::Err(
+                    ws::WsError::Protocol(ws::ProtocolError::Overflow),
+                )),
                 DispatchItem::DecoderError(e) | DispatchItem::EncoderError(e) => {
                     Either::Right(Ready::Err(ws::WsError::Protocol(e)))
                 }
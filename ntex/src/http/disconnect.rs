@@ -0,0 +1,68 @@
+//! Client disconnect notification
+use crate::framed::{OnDisconnect, State};
+use crate::util::Extensions;
+
+/// Connection's io state, stored in request extensions
+#[derive(Clone)]
+pub(crate) struct Disconnect(State);
+
+impl Disconnect {
+    pub(crate) fn new(state: State) -> Self {
+        Disconnect(state)
+    }
+
+    /// Store connection state in request extensions
+    pub(crate) fn set(&self, ext: &mut Extensions) {
+        ext.insert(self.clone());
+    }
+
+    /// Create disconnect notification future for request's connection
+    pub(crate) fn get(ext: &Extensions) -> Option<OnDisconnect> {
+        ext.get::<Disconnect>().map(|item| item.0.on_disconnect())
+    }
+}
+
+/// Notify waiters when connection get dropped.
+///
+/// Used by dispatchers that do not use framed io state.
+pub(crate) struct DisconnectGuard(Disconnect);
+
+impl DisconnectGuard {
+    pub(crate) fn new() -> Self {
+        DisconnectGuard(Disconnect::new(State::new()))
+    }
+
+    pub(crate) fn get_ref(&self) -> &Disconnect {
+        &self.0
+    }
+}
+
+impl Drop for DisconnectGuard {
+    fn drop(&mut self) {
+        (self.0).0.set_io_error(None);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::lazy;
+    use std::{future::Future, pin::Pin, task::Poll};
+
+    #[crate::rt_test]
+    async fn test_disconnect_guard() {
+        let guard = DisconnectGuard::new();
+        let mut ext = Extensions::new();
+        guard.get_ref().set(&mut ext);
+
+        let mut waiter = Disconnect::get(&ext).unwrap();
+        assert_eq!(
+            lazy(|cx| Pin::new(&mut waiter).poll(cx)).await,
+            Poll::Pending
+        );
+        drop(guard);
+        assert_eq!(waiter.await, ());
+        assert_eq!(Disconnect::get(&ext).unwrap().await, ());
+        assert!(Disconnect::get(&Extensions::new()).is_none());
+    }
+}
@@ -2,6 +2,7 @@ use std::{future::Future, io, io::Write, pin::Pin, task::Context, task::Poll};
 
 use brotli2::write::BrotliDecoder;
 use flate2::write::{GzDecoder, ZlibDecoder};
+use zstd::stream::write::Decoder as ZstdDecoder;
 
 use super::Writer;
 use crate::http::error::PayloadError;
@@ -15,7 +16,7 @@ pub struct Decoder<S> {
     decoder: Option<ContentDecoder>,
     stream: S,
     eof: bool,
-    fut: Option<JoinHandle<Result<(Option<Bytes>, ContentDecoder), io::Error>>>,
+    fut: Option<JoinHandle<Result<(Option<Bytes>, ContentDecoder), PayloadError>>>,
 }
 
 impl<S> Decoder<S>
@@ -35,6 +36,13 @@ where
             ContentEncoding::Gzip => Some(ContentDecoder::Gzip(Box::new(
                 GzDecoder::new(Writer::new()),
             ))),
+            ContentEncoding::Zstd => match ZstdDecoder::new(Writer::new()) {
+                Ok(decoder) => Some(ContentDecoder::Zstd(Box::new(decoder))),
+                Err(err) => {
+                    log::error!("Cannot create zstd decoder: {}", err);
+                    None
+                }
+            },
             _ => None,
         };
         Decoder {
@@ -61,6 +69,17 @@ where
 
         Self::new(stream, encoding)
     }
+
+    /// Set max size of decoded payload.
+    ///
+    /// Decoder returns `PayloadError::Overflow` if decoded payload is
+    /// bigger than limit. By default size is not limited.
+    pub fn limit(mut self, limit: usize) -> Self {
+        if let Some(ref mut decoder) = self.decoder {
+            decoder.writer_mut().limit = limit;
+        }
+        self
+    }
 }
 
 impl<S> Stream for Decoder<S>
@@ -77,7 +96,7 @@ where
             if let Some(ref mut fut) = self.fut {
                 let (chunk, decoder) = match Pin::new(fut).poll(cx) {
                     Poll::Ready(Ok(Ok(item))) => item,
-                    Poll::Ready(Ok(Err(e))) => return Poll::Ready(Some(Err(e.into()))),
+                    Poll::Ready(Ok(Err(e))) => return Poll::Ready(Some(Err(e))),
                     Poll::Ready(Err(e)) => return Poll::Ready(Some(Err(e.into()))),
                     Poll::Pending => return Poll::Pending,
                 };
@@ -97,14 +116,18 @@ where
                 Poll::Ready(Some(Ok(chunk))) => {
                     if let Some(mut decoder) = self.decoder.take() {
                         if chunk.len() < INPLACE {
-                            let chunk = decoder.feed_data(chunk)?;
+                            let chunk = decoder
+                                .feed_data(chunk)
+                                .map_err(|e| decoder.error(e))?;
                             self.decoder = Some(decoder);
                             if let Some(chunk) = chunk {
                                 return Poll::Ready(Some(Ok(chunk)));
                             }
                         } else {
                             self.fut = Some(spawn_blocking(move || {
-                                let chunk = decoder.feed_data(chunk)?;
+                                let chunk = decoder
+                                    .feed_data(chunk)
+                                    .map_err(|e| decoder.error(e))?;
                                 Ok((chunk, decoder))
                             }));
                         }
@@ -119,7 +142,7 @@ where
                         match decoder.feed_eof() {
                             Ok(Some(res)) => Poll::Ready(Some(Ok(res))),
                             Ok(None) => Poll::Ready(None),
-                            Err(err) => Poll::Ready(Some(Err(err.into()))),
+                            Err(err) => Poll::Ready(Some(Err(decoder.error(err)))),
                         }
                     } else {
                         Poll::Ready(None)
@@ -136,9 +159,27 @@ enum ContentDecoder {
     Deflate(Box<ZlibDecoder<Writer>>),
     Gzip(Box<GzDecoder<Writer>>),
     Br(Box<BrotliDecoder<Writer>>),
+    Zstd(Box<ZstdDecoder<'static, Writer>>),
 }
 
 impl ContentDecoder {
+    fn writer_mut(&mut self) -> &mut Writer {
+        match self {
+            ContentDecoder::Br(ref mut decoder) => decoder.get_mut(),
+            ContentDecoder::Gzip(ref mut decoder) => decoder.get_mut(),
+            ContentDecoder::Deflate(ref mut decoder) => decoder.get_mut(),
+            ContentDecoder::Zstd(ref mut decoder) => decoder.get_mut(),
+        }
+    }
+
+    fn error(&mut self, err: io::Error) -> PayloadError {
+        if self.writer_mut().overflow {
+            PayloadError::Overflow
+        } else {
+            err.into()
+        }
+    }
+
     fn feed_eof(&mut self) -> io::Result<Option<Bytes>> {
         match self {
             ContentDecoder::Br(ref mut decoder) => match decoder.flush() {
@@ -174,6 +215,17 @@ impl ContentDecoder {
                 }
                 Err(e) => Err(e),
             },
+            ContentDecoder::Zstd(ref mut decoder) => match decoder.flush() {
+                Ok(_) => {
+                    let b = decoder.get_mut().take();
+                    if !b.is_empty() {
+                        Ok(Some(b))
+                    } else {
+                        Ok(None)
+                    }
+                }
+                Err(e) => Err(e),
+            },
         }
     }
 
@@ -215,6 +267,18 @@ impl ContentDecoder {
                 }
                 Err(e) => Err(e),
             },
+            ContentDecoder::Zstd(ref mut decoder) => match decoder.write_all(&data) {
+                Ok(_) => {
+                    decoder.flush()?;
+                    let b = decoder.get_mut().take();
+                    if !b.is_empty() {
+                        Ok(Some(b))
+                    } else {
+                        Ok(None)
+                    }
+                }
+                Err(e) => Err(e),
+            },
         }
     }
 }
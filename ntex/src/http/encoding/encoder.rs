@@ -1,8 +1,9 @@
 //! Stream encoder
-use std::{future::Future, io, io::Write, pin::Pin, task::Context, task::Poll};
+use std::{cmp, future::Future, io, io::Write, pin::Pin, task::Context, task::Poll};
 
 use brotli2::write::BrotliEncoder;
 use flate2::write::{GzEncoder, ZlibEncoder};
+use zstd::stream::write::Encoder as ZstdEncoder;
 
 use crate::http::body::{Body, BodySize, MessageBody, ResponseBody};
 use crate::http::header::{ContentEncoding, HeaderValue, CONTENT_ENCODING};
@@ -26,6 +27,19 @@ impl<B: MessageBody + 'static> Encoder<B> {
         encoding: ContentEncoding,
         head: &mut ResponseHead,
         body: ResponseBody<B>,
+    ) -> ResponseBody<B> {
+        Encoder::response_with_level(encoding, None, head, body)
+    }
+
+    /// Encode response body with specified compression level.
+    ///
+    /// Level is clamped to the range supported by encoding, `None` means
+    /// default level.
+    pub fn response_with_level(
+        encoding: ContentEncoding,
+        level: Option<u32>,
+        head: &mut ResponseHead,
+        body: ResponseBody<B>,
     ) -> ResponseBody<B> {
         let can_encode = ContentEncoder::can_encode(encoding)
             && !(head.headers().contains_key(&CONTENT_ENCODING)
@@ -48,7 +62,18 @@ impl<B: MessageBody + 'static> Encoder<B> {
             };
 
             // Modify response body only if encoder is not None
-            let encoder = ContentEncoder::encoder(encoding).unwrap();
+            let encoder = match ContentEncoder::encoder(encoding, level) {
+                Ok(encoder) => encoder,
+                Err(err) => {
+                    log::error!("Cannot create {:?} encoder: {}", encoding, err);
+                    return ResponseBody::Other(Body::from_message(Encoder {
+                        body,
+                        eof: false,
+                        fut: None,
+                        encoder: None,
+                    }));
+                }
+            };
             update_head(encoding, head);
             head.no_chunking(false);
             ResponseBody::Other(Body::from_message(Encoder {
@@ -172,32 +197,48 @@ enum ContentEncoder {
     Deflate(ZlibEncoder<Writer>),
     Gzip(GzEncoder<Writer>),
     Br(BrotliEncoder<Writer>),
+    Zstd(ZstdEncoder<'static, Writer>),
 }
 
 impl ContentEncoder {
     fn can_encode(encoding: ContentEncoding) -> bool {
         match encoding {
-            ContentEncoding::Deflate | ContentEncoding::Gzip | ContentEncoding::Br => {
-                true
-            }
+            ContentEncoding::Deflate
+            | ContentEncoding::Gzip
+            | ContentEncoding::Br
+            | ContentEncoding::Zstd => true,
             _ => false,
         }
     }
 
-    fn encoder(encoding: ContentEncoding) -> Option<Self> {
+    fn encoder(encoding: ContentEncoding, level: Option<u32>) -> io::Result<Self> {
+        let flate_level = || {
+            level
+                .map(|l| flate2::Compression::new(cmp::min(l, 9)))
+                .unwrap_or_else(flate2::Compression::fast)
+        };
+
         match encoding {
-            ContentEncoding::Deflate => Some(ContentEncoder::Deflate(ZlibEncoder::new(
+            ContentEncoding::Deflate => Ok(ContentEncoder::Deflate(ZlibEncoder::new(
                 Writer::new(),
-                flate2::Compression::fast(),
+                flate_level(),
             ))),
-            ContentEncoding::Gzip => Some(ContentEncoder::Gzip(GzEncoder::new(
+            ContentEncoding::Gzip => Ok(ContentEncoder::Gzip(GzEncoder::new(
                 Writer::new(),
-                flate2::Compression::fast(),
+                flate_level(),
             ))),
-            ContentEncoding::Br => {
-                Some(ContentEncoder::Br(BrotliEncoder::new(Writer::new(), 3)))
-            }
-            _ => None,
+            ContentEncoding::Br => Ok(ContentEncoder::Br(BrotliEncoder::new(
+                Writer::new(),
+                level.map(|l| cmp::min(l, 11)).unwrap_or(3),
+            ))),
+            ContentEncoding::Zstd => Ok(ContentEncoder::Zstd(ZstdEncoder::new(
+                Writer::new(),
+                level.map(|l| cmp::min(l, 22) as i32).unwrap_or(3),
+            )?)),
+            _ => Err(io::Error::new(
+                io::ErrorKind::Other,
+                "Unsupported content encoding",
+            )),
         }
     }
 
@@ -206,6 +247,7 @@ impl ContentEncoder {
             ContentEncoder::Br(ref mut encoder) => encoder.get_mut().take(),
             ContentEncoder::Deflate(ref mut encoder) => encoder.get_mut().take(),
             ContentEncoder::Gzip(ref mut encoder) => encoder.get_mut().take(),
+            ContentEncoder::Zstd(ref mut encoder) => encoder.get_mut().take(),
         }
     }
 
@@ -223,6 +265,10 @@ impl ContentEncoder {
                 Ok(writer) => Ok(writer.buf.freeze()),
                 Err(err) => Err(err),
             },
+            ContentEncoder::Zstd(encoder) => match encoder.finish() {
+                Ok(writer) => Ok(writer.buf.freeze()),
+                Err(err) => Err(err),
+            },
         }
     }
 
@@ -249,6 +295,13 @@ impl ContentEncoder {
                     Err(err)
                 }
             },
+            ContentEncoder::Zstd(ref mut encoder) => match encoder.write_all(data) {
+                Ok(_) => Ok(()),
+                Err(err) => {
+                    trace!("Error decoding zstd encoding: {}", err);
+                    Err(err)
+                }
+            },
         }
     }
 }
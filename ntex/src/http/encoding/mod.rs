@@ -11,12 +11,18 @@ pub use self::encoder::Encoder;
 
 pub(self) struct Writer {
     buf: BytesMut,
+    limit: usize,
+    written: usize,
+    overflow: bool,
 }
 
 impl Writer {
     fn new() -> Writer {
         Writer {
             buf: BytesMut::with_capacity(8192),
+            limit: usize::MAX,
+            written: 0,
+            overflow: false,
         }
     }
 
@@ -27,6 +33,14 @@ impl Writer {
 
 impl io::Write for Writer {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.written += buf.len();
+        if self.written > self.limit {
+            self.overflow = true;
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "Size limit is reached",
+            ));
+        }
         self.buf.extend_from_slice(buf);
         Ok(buf.len())
     }
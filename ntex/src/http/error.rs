@@ -240,6 +240,8 @@ pub enum BlockingError<E: fmt::Debug> {
     Error(E),
     #[display(fmt = "Thread pool is gone")]
     Canceled,
+    #[display(fmt = "Thread pool is overloaded")]
+    Overloaded,
 }
 
 impl<E: fmt::Debug> std::error::Error for BlockingError<E> {}
@@ -261,6 +263,10 @@ impl From<BlockingError<io::Error>> for PayloadError {
                 io::ErrorKind::Other,
                 "Operation is canceled",
             )),
+            BlockingError::Overloaded => PayloadError::Io(io::Error::new(
+                io::ErrorKind::Other,
+                "Thread pool is overloaded",
+            )),
         }
     }
 }
@@ -13,6 +13,7 @@ use crate::util::Bytes;
 use crate::http;
 use crate::http::body::{BodySize, MessageBody, ResponseBody};
 use crate::http::config::DispatcherConfig;
+use crate::http::disconnect::Disconnect;
 use crate::http::error::{DispatchError, ParseError, PayloadError, ResponseError};
 use crate::http::helpers::DataFactory;
 use crate::http::request::Request;
@@ -346,6 +347,8 @@ where
                                 {
                                     on_connect.set(&mut req.extensions_mut());
                                 }
+                                Disconnect::new(this.inner.state.clone())
+                                    .set(&mut req.extensions_mut());
 
                                 if upgrade {
                                     // Handle UPGRADE request
@@ -1154,6 +1157,31 @@ mod tests {
         assert!(lazy(|cx| Pin::new(&mut h1).poll(cx)).await.is_ready());
     }
 
+    #[crate::rt_test]
+    async fn test_on_disconnect() {
+        let (client, server) = Io::create();
+        client.remote_buffer_cap(1024);
+
+        let disconnected = Rc::new(Cell::new(false));
+        let disconnected2 = disconnected.clone();
+        spawn_h1(server, move |req: Request| {
+            let disconnected = disconnected2.clone();
+            async move {
+                req.on_disconnect().unwrap().await;
+                disconnected.set(true);
+                Ok::<_, io::Error>(Response::Ok().finish())
+            }
+        });
+
+        client.write("GET /test HTTP/1.1\r\n\r\n");
+        sleep(time::Duration::from_millis(50)).await;
+        assert!(!disconnected.get());
+
+        client.close().await;
+        sleep(time::Duration::from_millis(50)).await;
+        assert!(disconnected.get());
+    }
+
     #[crate::rt_test]
     async fn test_service_error() {
         let (client, server) = Io::create();
@@ -62,6 +62,27 @@ impl Payload {
         self.inner.borrow_mut().unread_data(data);
     }
 
+    /// Stop reading payload data from the connection.
+    ///
+    /// Already buffered chunks are still available, but dispatcher does not
+    /// read new data until payload is resumed.
+    #[inline]
+    pub fn pause(&mut self) {
+        self.inner.borrow_mut().pause();
+    }
+
+    /// Resume reading payload data from the connection
+    #[inline]
+    pub fn resume(&mut self) {
+        self.inner.borrow_mut().resume();
+    }
+
+    /// Check if payload reading is paused
+    #[inline]
+    pub fn is_paused(&self) -> bool {
+        self.inner.borrow().paused
+    }
+
     #[inline]
     pub fn readany(
         &mut self,
@@ -136,6 +157,7 @@ struct Inner {
     eof: bool,
     err: Option<PayloadError>,
     need_read: bool,
+    paused: bool,
     items: VecDeque<Bytes>,
     task: LocalWaker,
     io_task: LocalWaker,
@@ -149,6 +171,7 @@ impl Inner {
             err: None,
             items: VecDeque::new(),
             need_read: true,
+            paused: false,
             task: LocalWaker::new(),
             io_task: LocalWaker::new(),
         }
@@ -167,17 +190,30 @@ impl Inner {
     fn feed_data(&mut self, data: Bytes) {
         self.len += data.len();
         self.items.push_back(data);
-        self.need_read = self.len < MAX_BUFFER_SIZE;
+        self.need_read = !self.paused && self.len < MAX_BUFFER_SIZE;
         self.task.wake();
     }
 
+    fn pause(&mut self) {
+        self.paused = true;
+        self.need_read = false;
+    }
+
+    fn resume(&mut self) {
+        if self.paused {
+            self.paused = false;
+            self.need_read = self.len < MAX_BUFFER_SIZE;
+            self.io_task.wake();
+        }
+    }
+
     fn readany(
         &mut self,
         cx: &mut Context<'_>,
     ) -> Poll<Option<Result<Bytes, PayloadError>>> {
         if let Some(data) = self.items.pop_front() {
             self.len -= data.len();
-            self.need_read = self.len < MAX_BUFFER_SIZE;
+            self.need_read = !self.paused && self.len < MAX_BUFFER_SIZE;
 
             if self.need_read && !self.eof {
                 self.task.register(cx.waker());
@@ -189,9 +225,11 @@ impl Inner {
         } else if self.eof {
             Poll::Ready(None)
         } else {
-            self.need_read = true;
             self.task.register(cx.waker());
-            self.io_task.wake();
+            if !self.paused {
+                self.need_read = true;
+                self.io_task.wake();
+            }
             Poll::Pending
         }
     }
@@ -219,4 +257,33 @@ mod tests {
             poll_fn(|cx| payload.readany(cx)).await.unwrap().unwrap()
         );
     }
+
+    #[crate::rt_test]
+    async fn test_pause() {
+        let (mut sender, mut payload) = Payload::create(false);
+        payload.pause();
+        assert!(payload.is_paused());
+        assert_eq!(
+            poll_fn(|cx| Poll::Ready(sender.poll_data_required(cx))).await,
+            PayloadStatus::Pause
+        );
+
+        // buffered data is available while paused
+        sender.feed_data(Bytes::from("data"));
+        assert_eq!(
+            Bytes::from("data"),
+            poll_fn(|cx| payload.readany(cx)).await.unwrap().unwrap()
+        );
+        assert_eq!(
+            poll_fn(|cx| Poll::Ready(sender.poll_data_required(cx))).await,
+            PayloadStatus::Pause
+        );
+
+        payload.resume();
+        assert!(!payload.is_paused());
+        assert_eq!(
+            poll_fn(|cx| Poll::Ready(sender.poll_data_required(cx))).await,
+            PayloadStatus::Read
+        );
+    }
 }
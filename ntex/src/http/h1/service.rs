@@ -100,6 +100,7 @@ where
 mod openssl {
     use super::*;
 
+    use crate::http::{peer_cert, PeerCert};
     use crate::server::openssl::{Acceptor, SslAcceptor, SslStream};
     use crate::server::SslError;
 
@@ -126,7 +127,7 @@ mod openssl {
     {
         /// Create openssl based service
         pub fn openssl(
-            self,
+            mut self,
             acceptor: SslAcceptor,
         ) -> impl ServiceFactory<
             Config = (),
@@ -135,6 +136,9 @@ mod openssl {
             Error = SslError<DispatchError>,
             InitError = (),
         > {
+            self.on_connect =
+                peer_cert::on_connect(self.on_connect.take(), PeerCert::openssl);
+
             pipeline_factory(
                 Acceptor::new(acceptor)
                     .timeout((self.handshake_timeout as u64) * 1000)
@@ -153,6 +157,7 @@ mod openssl {
 #[cfg(feature = "rustls")]
 mod rustls {
     use super::*;
+    use crate::http::{peer_cert, PeerCert};
     use crate::server::rustls::{Acceptor, ServerConfig, TlsStream};
     use crate::server::SslError;
     use std::fmt;
@@ -180,7 +185,7 @@ mod rustls {
     {
         /// Create rustls based service
         pub fn rustls(
-            self,
+            mut self,
             config: ServerConfig,
         ) -> impl ServiceFactory<
             Config = (),
@@ -189,6 +194,9 @@ mod rustls {
             Error = SslError<DispatchError>,
             InitError = (),
         > {
+            self.on_connect =
+                peer_cert::on_connect(self.on_connect.take(), PeerCert::rustls);
+
             pipeline_factory(
                 Acceptor::new(config)
                     .timeout((self.handshake_timeout as u64) * 1000)
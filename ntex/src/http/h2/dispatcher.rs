@@ -11,6 +11,7 @@ use log::{error, trace};
 use crate::codec::{AsyncRead, AsyncWrite};
 use crate::http::body::{BodySize, MessageBody, ResponseBody};
 use crate::http::config::{DateService, DispatcherConfig};
+use crate::http::disconnect::DisconnectGuard;
 use crate::http::error::{DispatchError, ResponseError};
 use crate::http::helpers::DataFactory;
 use crate::http::message::ResponseHead;
@@ -30,6 +31,7 @@ pin_project_lite::pin_project! {
         connection: Connection<T, Bytes>,
         on_connect: Option<Box<dyn DataFactory>>,
         peer_addr: Option<net::SocketAddr>,
+        disconnect: DisconnectGuard,
         ka_expire: Instant,
         ka_timer: Option<Sleep>,
         _t: PhantomData<B>,
@@ -65,6 +67,7 @@ where
             peer_addr,
             connection,
             on_connect,
+            disconnect: DisconnectGuard::new(),
             ka_expire,
             ka_timer,
             _t: PhantomData,
@@ -117,6 +120,7 @@ where
                     if let Some(ref on_connect) = this.on_connect {
                         on_connect.set(&mut req.extensions_mut());
                     }
+                    this.disconnect.get_ref().set(&mut req.extensions_mut());
 
                     crate::rt::spawn(ServiceResponse {
                         state: ServiceResponseState::ServiceCall {
@@ -15,11 +15,42 @@ use crate::{http::error::PayloadError, util::Bytes, Stream};
 #[derive(Debug)]
 pub struct Payload {
     pl: RecvStream,
+    paused: bool,
+    unreleased: usize,
 }
 
 impl Payload {
     pub(crate) fn new(pl: RecvStream) -> Self {
-        Self { pl }
+        Self {
+            pl,
+            paused: false,
+            unreleased: 0,
+        }
+    }
+
+    /// Stop releasing flow control capacity.
+    ///
+    /// Already received chunks are still available, but peer stops
+    /// sending data once stream's flow control window is exhausted.
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    /// Release capacity of consumed chunks and resume data flow
+    pub fn resume(&mut self) {
+        self.paused = false;
+        if self.unreleased != 0 {
+            let len = std::mem::take(&mut self.unreleased);
+            if let Err(err) = self.pl.flow_control().release_capacity(len) {
+                // stream error is reported by next read
+                log::trace!("Cannot release payload capacity: {}", err);
+            }
+        }
+    }
+
+    /// Check if payload is paused
+    pub fn is_paused(&self) -> bool {
+        self.paused
     }
 }
 
@@ -35,7 +66,10 @@ impl Stream for Payload {
         match Pin::new(&mut this.pl).poll_data(cx) {
             Poll::Ready(Some(Ok(chunk))) => {
                 let len = chunk.len();
-                if let Err(err) = this.pl.flow_control().release_capacity(len) {
+                if this.paused {
+                    this.unreleased += len;
+                    Poll::Ready(Some(Ok(Bytes::copy_from_slice(&chunk[..]))))
+                } else if let Err(err) = this.pl.flow_control().release_capacity(len) {
                     Poll::Ready(Some(Err(err.into())))
                 } else {
                     Poll::Ready(Some(Ok(Bytes::copy_from_slice(&chunk[..]))))
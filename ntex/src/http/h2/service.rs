@@ -94,6 +94,7 @@ where
 
 #[cfg(feature = "openssl")]
 mod openssl {
+    use crate::http::{peer_cert, PeerCert};
     use crate::server::openssl::{Acceptor, SslAcceptor, SslStream};
     use crate::server::SslError;
 
@@ -111,7 +112,7 @@ mod openssl {
     {
         /// Create ssl based service
         pub fn openssl(
-            self,
+            mut self,
             acceptor: SslAcceptor,
         ) -> impl ServiceFactory<
             Config = (),
@@ -120,6 +121,9 @@ mod openssl {
             Error = SslError<DispatchError>,
             InitError = S::InitError,
         > {
+            self.on_connect =
+                peer_cert::on_connect(self.on_connect.take(), PeerCert::openssl);
+
             pipeline_factory(
                 Acceptor::new(acceptor)
                     .timeout(self.handshake_timeout)
@@ -142,6 +146,7 @@ mod openssl {
 #[cfg(feature = "rustls")]
 mod rustls {
     use super::*;
+    use crate::http::{peer_cert, PeerCert};
     use crate::server::rustls::{Acceptor, ServerConfig, TlsStream};
     use crate::server::SslError;
 
@@ -156,7 +161,7 @@ mod rustls {
     {
         /// Create openssl based service
         pub fn rustls(
-            self,
+            mut self,
             mut config: ServerConfig,
         ) -> impl ServiceFactory<
             Config = (),
@@ -167,6 +172,8 @@ mod rustls {
         > {
             let protos = vec!["h2".to_string().into()];
             config.set_protocols(&protos);
+            self.on_connect =
+                peer_cert::on_connect(self.on_connect.take(), PeerCert::rustls);
 
             pipeline_factory(
                 Acceptor::new(config)
@@ -3,6 +3,7 @@ pub mod body;
 mod builder;
 pub mod client;
 mod config;
+pub(crate) mod disconnect;
 #[cfg(feature = "compress")]
 pub mod encoding;
 pub(crate) mod helpers;
@@ -10,6 +11,7 @@ mod httpcodes;
 mod httpmessage;
 mod message;
 mod payload;
+mod peer_cert;
 mod request;
 mod response;
 mod service;
@@ -31,6 +33,7 @@ pub use self::header::HeaderMap;
 pub use self::httpmessage::HttpMessage;
 pub use self::message::{ConnectionType, RequestHead, RequestHeadType, ResponseHead};
 pub use self::payload::{Payload, PayloadStream};
+pub use self::peer_cert::PeerCert;
 pub use self::request::Request;
 pub use self::response::{Response, ResponseBuilder};
 pub use self::service::HttpService;
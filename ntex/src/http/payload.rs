@@ -70,6 +70,30 @@ impl Payload {
     {
         Payload::Stream(Box::pin(stream))
     }
+
+    /// Stop reading payload data from the peer.
+    ///
+    /// For http/1 payload, dispatcher stops reading request body from
+    /// the connection. For http/2 payload, stream's flow control capacity
+    /// is not released, so peer stops sending data once flow control window
+    /// is exhausted. Already received data is still available. Stream
+    /// payloads are not affected.
+    pub fn pause(&mut self) {
+        match self {
+            Payload::H1(ref mut pl) => pl.pause(),
+            Payload::H2(ref mut pl) => pl.pause(),
+            Payload::None | Payload::Stream(_) => (),
+        }
+    }
+
+    /// Resume reading payload data from the peer
+    pub fn resume(&mut self) {
+        match self {
+            Payload::H1(ref mut pl) => pl.resume(),
+            Payload::H2(ref mut pl) => pl.resume(),
+            Payload::None | Payload::Stream(_) => (),
+        }
+    }
 }
 
 impl Stream for Payload {
@@ -0,0 +1,111 @@
+//! Tls peer certificate
+use std::rc::Rc;
+
+use crate::util::{Bytes, Extensions};
+
+use super::helpers::DataFactory;
+
+/// Client certificate verified by tls acceptor.
+///
+/// Certificate is stored in request extensions for connections
+/// accepted with `openssl` or `rustls` acceptors.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PeerCert(Bytes);
+
+impl PeerCert {
+    /// Create peer certificate from DER encoded bytes
+    pub fn from_der<T: Into<Bytes>>(der: T) -> Self {
+        PeerCert(der.into())
+    }
+
+    /// DER encoded certificate
+    pub fn der(&self) -> &[u8] {
+        &self.0
+    }
+
+    #[cfg(feature = "openssl")]
+    /// Parse certificate with openssl
+    pub fn to_x509(&self) -> Result<open_ssl::x509::X509, open_ssl::error::ErrorStack> {
+        open_ssl::x509::X509::from_der(&self.0)
+    }
+}
+
+#[cfg(feature = "openssl")]
+impl PeerCert {
+    /// Verified peer certificate of openssl stream
+    pub(crate) fn openssl<T>(io: &tokio_openssl::SslStream<T>) -> Option<PeerCert> {
+        let ssl = io.ssl();
+        if ssl.verify_result() != open_ssl::x509::X509VerifyResult::OK {
+            return None;
+        }
+        ssl.peer_certificate()
+            .and_then(|cert| cert.to_der().ok())
+            .map(PeerCert::from_der)
+    }
+}
+
+#[cfg(feature = "rustls")]
+impl PeerCert {
+    /// Verified peer certificate of rustls stream
+    pub(crate) fn rustls<T>(
+        io: &tokio_rustls::server::TlsStream<T>,
+    ) -> Option<PeerCert> {
+        use rust_tls::Session;
+
+        io.get_ref()
+            .1
+            .get_peer_certificates()
+            .and_then(|certs| certs.into_iter().next())
+            .map(|cert| PeerCert::from_der(cert.0))
+    }
+}
+
+type OnConnect<T> = Rc<dyn Fn(&T) -> Box<dyn DataFactory>>;
+
+/// Extend on-connect callback, store peer certificate in request extensions
+#[allow(dead_code)]
+pub(crate) fn on_connect<T: 'static>(
+    on_connect: Option<OnConnect<T>>,
+    cert: fn(&T) -> Option<PeerCert>,
+) -> Option<OnConnect<T>> {
+    Some(Rc::new(move |io: &T| {
+        Box::new(PeerCertData(cert(io), on_connect.as_ref().map(|f| f(io))))
+    }))
+}
+
+struct PeerCertData(Option<PeerCert>, Option<Box<dyn DataFactory>>);
+
+impl DataFactory for PeerCertData {
+    fn set(&self, ext: &mut Extensions) {
+        if let Some(ref cert) = self.0 {
+            ext.insert(cert.clone());
+        }
+        if let Some(ref data) = self.1 {
+            data.set(ext);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::helpers::Data;
+
+    #[test]
+    fn test_on_connect() {
+        let f = on_connect::<()>(Some(Rc::new(|_: &()| Box::new(Data(10u32)))), |_| {
+            Some(PeerCert::from_der(&b"cert"[..]))
+        })
+        .unwrap();
+
+        let mut ext = Extensions::new();
+        f(&()).set(&mut ext);
+        assert_eq!(ext.get::<PeerCert>().unwrap().der(), b"cert");
+        assert_eq!(ext.get::<u32>(), Some(&10));
+
+        let f = on_connect::<()>(None, |_| None).unwrap();
+        let mut ext = Extensions::new();
+        f(&()).set(&mut ext);
+        assert!(ext.get::<PeerCert>().is_none());
+    }
+}
@@ -2,6 +2,8 @@ use std::{cell::Ref, cell::RefMut, fmt, mem, net};
 
 use http::{header, Method, Uri, Version};
 
+use crate::framed::OnDisconnect;
+use crate::http::disconnect::Disconnect;
 use crate::http::header::HeaderMap;
 use crate::http::httpmessage::HttpMessage;
 use crate::http::message::{Message, RequestHead};
@@ -135,6 +137,15 @@ impl Request {
         self.head().peer_addr
     }
 
+    /// Future that resolves when client connection get disconnected
+    ///
+    /// Returns `None` if request is not bound to a connection.
+    /// For http/2 requests, future resolves when http/2 connection is closed.
+    #[inline]
+    pub fn on_disconnect(&self) -> Option<OnDisconnect> {
+        Disconnect::get(&self.head.extensions())
+    }
+
     /// Get request's payload
     pub fn payload(&mut self) -> &mut Payload {
         &mut self.payload
@@ -3,6 +3,8 @@ use std::{cell::Ref, cell::RefMut, convert::TryFrom, error::Error, fmt, str};
 
 use serde::Serialize;
 
+#[cfg(feature = "secure-cookie")]
+use coo_kie::Key;
 #[cfg(feature = "cookie")]
 use coo_kie::{Cookie, CookieJar};
 
@@ -474,6 +476,31 @@ impl ResponseBuilder {
         self
     }
 
+    #[cfg(feature = "secure-cookie")]
+    /// Set a signed cookie
+    ///
+    /// Cookie value is signed with the key, client could read it but
+    /// could not modify it.
+    pub fn signed_cookie<'c>(&mut self, cookie: Cookie<'c>, key: &Key) -> &mut Self {
+        self.cookies
+            .get_or_insert_with(CookieJar::new)
+            .signed_mut(key)
+            .add(cookie.into_owned());
+        self
+    }
+
+    #[cfg(feature = "secure-cookie")]
+    /// Set a private cookie
+    ///
+    /// Cookie value is encrypted and authenticated with the key.
+    pub fn private_cookie<'c>(&mut self, cookie: Cookie<'c>, key: &Key) -> &mut Self {
+        self.cookies
+            .get_or_insert_with(CookieJar::new)
+            .private_mut(key)
+            .add(cookie.into_owned());
+        self
+    }
+
     #[cfg(feature = "cookie")]
     /// Remove cookie
     ///
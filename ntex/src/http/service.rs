@@ -217,6 +217,7 @@ where
 #[cfg(feature = "openssl")]
 mod openssl {
     use super::*;
+    use crate::http::{peer_cert, PeerCert};
     use crate::server::openssl::{Acceptor, SslAcceptor, SslStream};
     use crate::server::SslError;
 
@@ -246,7 +247,7 @@ mod openssl {
     {
         /// Create openssl based service
         pub fn openssl(
-            self,
+            mut self,
             acceptor: SslAcceptor,
         ) -> impl ServiceFactory<
             Config = (),
@@ -255,6 +256,9 @@ mod openssl {
             Error = SslError<DispatchError>,
             InitError = (),
         > {
+            self.on_connect =
+                peer_cert::on_connect(self.on_connect.take(), PeerCert::openssl);
+
             pipeline_factory(
                 Acceptor::new(acceptor)
                     .timeout((self.cfg.0.ssl_handshake_timeout as u64) * 1000)
@@ -282,6 +286,7 @@ mod openssl {
 #[cfg(feature = "rustls")]
 mod rustls {
     use super::*;
+    use crate::http::{peer_cert, PeerCert};
     use crate::server::rustls::{Acceptor, ServerConfig, Session, TlsStream};
     use crate::server::SslError;
 
@@ -311,7 +316,7 @@ mod rustls {
     {
         /// Create openssl based service
         pub fn rustls(
-            self,
+            mut self,
             mut config: ServerConfig,
         ) -> impl ServiceFactory<
             Config = (),
@@ -322,6 +327,8 @@ mod rustls {
         > {
             let protos = vec!["h2".to_string().into(), "http/1.1".to_string().into()];
             config.set_protocols(&protos);
+            self.on_connect =
+                peer_cert::on_connect(self.on_connect.take(), PeerCert::rustls);
 
             pipeline_factory(
                 Acceptor::new(config)
@@ -6,6 +6,8 @@
 //! * `rustls` - enables ssl support via `rustls` crate
 //! * `compress` - enables compression support in http and web modules
 //! * `cookie` - enables cookie support in http and web modules
+//! * `secure-cookie` - enables signed and private cookies
+//! * `session` - enables session support in web module
 
 #![warn(
     rust_2018_idioms,
@@ -0,0 +1,445 @@
+//! Service that distributes calls across set of services.
+use std::{cell::Cell, future::Future, pin::Pin, rc::Rc, task::Context, task::Poll};
+
+use crate::service::{IntoServiceFactory, Service, ServiceFactory};
+
+/// Balancing strategy
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Strategy {
+    /// Select ready services in turn
+    RoundRobin,
+    /// Select ready service with least number of in-flight calls
+    LeastInFlight,
+}
+
+/// Service that distributes calls across set of inner services.
+///
+/// Readiness of each inner service is checked in `poll_ready`, services
+/// that are not ready or failed are skipped until next `poll_ready` call.
+/// Balance service is ready if at least one inner service is ready.
+pub struct Balance<S> {
+    services: Vec<S>,
+    ready: Vec<Cell<bool>>,
+    checked: Cell<bool>,
+    inflight: Rc<Vec<Cell<usize>>>,
+    next: Cell<usize>,
+    strategy: Strategy,
+}
+
+impl<S> Balance<S>
+where
+    S: Service,
+{
+    /// Create balance service, round-robin strategy is used by default
+    ///
+    /// Panics if `services` is empty.
+    pub fn new<I>(services: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+    {
+        let services: Vec<_> = services.into_iter().collect();
+        assert!(
+            !services.is_empty(),
+            "Balance requires at least one service"
+        );
+
+        Balance {
+            ready: services.iter().map(|_| Cell::new(false)).collect(),
+            checked: Cell::new(false),
+            inflight: Rc::new(services.iter().map(|_| Cell::new(0)).collect()),
+            next: Cell::new(0),
+            strategy: Strategy::RoundRobin,
+            services,
+        }
+    }
+
+    /// Set balancing strategy
+    pub fn strategy(mut self, strategy: Strategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Number of inner services
+    pub fn len(&self) -> usize {
+        self.services.len()
+    }
+
+    /// Check if there are no inner services, always false
+    pub fn is_empty(&self) -> bool {
+        self.services.is_empty()
+    }
+
+    /// Number of in-flight calls for inner service
+    pub fn inflight(&self, idx: usize) -> usize {
+        self.inflight[idx].get()
+    }
+
+    fn select(&self) -> usize {
+        let len = self.services.len();
+        // if readiness is not checked, use all services
+        let checked = self.checked.get();
+        let mut candidates = (0..len)
+            .map(|i| (self.next.get() + i) % len)
+            .filter(|idx| !checked || self.ready[*idx].get());
+
+        let idx = match self.strategy {
+            Strategy::RoundRobin => candidates.next(),
+            Strategy::LeastInFlight => {
+                candidates.min_by_key(|idx| self.inflight[*idx].get())
+            }
+        }
+        .unwrap_or_else(|| self.next.get());
+
+        self.next.set((idx + 1) % len);
+        idx
+    }
+}
+
+impl<S> Service for Balance<S>
+where
+    S: Service,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BalanceResponse<S>;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let mut ready = false;
+        let mut failed = 0;
+        let mut error = None;
+
+        self.checked.set(true);
+        for (idx, srv) in self.services.iter().enumerate() {
+            let res = srv.poll_ready(cx);
+            self.ready[idx].set(matches!(res, Poll::Ready(Ok(()))));
+            match res {
+                Poll::Ready(Ok(())) => ready = true,
+                Poll::Ready(Err(e)) => {
+                    log::trace!("Balance member {} failed, skipping", idx);
+                    failed += 1;
+                    error = Some(e);
+                }
+                Poll::Pending => (),
+            }
+        }
+
+        if ready {
+            Poll::Ready(Ok(()))
+        } else if failed == self.services.len() {
+            // all members failed
+            Poll::Ready(Err(error.unwrap()))
+        } else {
+            Poll::Pending
+        }
+    }
+
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        let mut ready = true;
+        for srv in &self.services {
+            ready &= srv.poll_shutdown(cx, is_error).is_ready();
+        }
+        if ready {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+
+    fn call(&self, req: S::Request) -> Self::Future {
+        let idx = self.select();
+
+        let inflight = self.inflight.clone();
+        inflight[idx].set(inflight[idx].get() + 1);
+
+        BalanceResponse {
+            fut: self.services[idx].call(req),
+            _guard: Guard { idx, inflight },
+        }
+    }
+}
+
+/// Construct worker pool service factory.
+///
+/// Pool creates `size` services from inner factory and dispatches calls
+/// to ready service with least number of in-flight calls.
+pub fn pool<T, F>(size: usize, factory: F) -> Pool<T>
+where
+    T: ServiceFactory,
+    T::Config: Clone,
+    F: IntoServiceFactory<T>,
+{
+    assert!(size > 0, "Pool size must be greater than 0");
+    Pool {
+        size,
+        factory: factory.into_factory(),
+    }
+}
+
+/// Worker pool service factory
+#[derive(Clone)]
+pub struct Pool<T> {
+    size: usize,
+    factory: T,
+}
+
+impl<T> ServiceFactory for Pool<T>
+where
+    T: ServiceFactory,
+    T::Config: Clone,
+{
+    type Request = T::Request;
+    type Response = T::Response;
+    type Error = T::Error;
+    type Config = T::Config;
+    type Service = Balance<T::Service>;
+    type InitError = T::InitError;
+    type Future = PoolResponse<T>;
+
+    fn new_service(&self, cfg: T::Config) -> Self::Future {
+        PoolResponse {
+            futs: (0..self.size)
+                .map(|_| Box::pin(self.factory.new_service(cfg.clone())))
+                .collect(),
+            services: (0..self.size).map(|_| None).collect(),
+        }
+    }
+}
+
+#[doc(hidden)]
+pub struct PoolResponse<T: ServiceFactory> {
+    futs: Vec<Pin<Box<T::Future>>>,
+    services: Vec<Option<T::Service>>,
+}
+
+impl<T: ServiceFactory> Unpin for PoolResponse<T> {}
+
+impl<T: ServiceFactory> Future for PoolResponse<T> {
+    type Output = Result<Balance<T::Service>, T::InitError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+
+        let mut ready = true;
+        for (fut, srv) in this.futs.iter_mut().zip(this.services.iter_mut()) {
+            if srv.is_none() {
+                match fut.as_mut().poll(cx)? {
+                    Poll::Ready(s) => *srv = Some(s),
+                    Poll::Pending => ready = false,
+                }
+            }
+        }
+
+        if ready {
+            let services = this.services.drain(..).map(|s| s.unwrap());
+            Poll::Ready(Ok(Balance::new(services).strategy(Strategy::LeastInFlight)))
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+pin_project_lite::pin_project! {
+    #[doc(hidden)]
+    pub struct BalanceResponse<S: Service> {
+        #[pin]
+        fut: S::Future,
+        _guard: Guard,
+    }
+}
+
+impl<S: Service> Future for BalanceResponse<S> {
+    type Output = Result<S::Response, S::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.project().fut.poll(cx)
+    }
+}
+
+struct Guard {
+    idx: usize,
+    inflight: Rc<Vec<Cell<usize>>>,
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        let cnt = &self.inflight[self.idx];
+        cnt.set(cnt.get() - 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::task::{Context, Poll};
+
+    use super::*;
+    use crate::util::{lazy, Ready};
+
+    struct TestService {
+        id: usize,
+        ready: Rc<Cell<bool>>,
+    }
+
+    impl Service for TestService {
+        type Request = ();
+        type Response = usize;
+        type Error = ();
+        type Future = Pin<Box<dyn Future<Output = Result<usize, ()>>>>;
+
+        fn poll_ready(&self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            if self.ready.get() {
+                Poll::Ready(Ok(()))
+            } else {
+                Poll::Pending
+            }
+        }
+
+        fn call(&self, _: ()) -> Self::Future {
+            let id = self.id;
+            Box::pin(async move {
+                crate::rt::time::sleep(std::time::Duration::from_millis(10)).await;
+                Ok(id)
+            })
+        }
+    }
+
+    fn services(n: usize) -> (Vec<Rc<Cell<bool>>>, Vec<TestService>) {
+        let ready: Vec<_> = (0..n).map(|_| Rc::new(Cell::new(true))).collect();
+        let srvs = ready
+            .iter()
+            .enumerate()
+            .map(|(id, ready)| TestService {
+                id,
+                ready: ready.clone(),
+            })
+            .collect();
+        (ready, srvs)
+    }
+
+    #[crate::rt_test]
+    async fn test_round_robin() {
+        let (ready, srvs) = services(3);
+        let srv = Balance::new(srvs);
+        assert_eq!(srv.len(), 3);
+        assert!(!srv.is_empty());
+
+        for id in &[0, 1, 2, 0] {
+            assert!(lazy(|cx| srv.poll_ready(cx)).await.is_ready());
+            assert_eq!(srv.call(()).await, Ok(*id));
+        }
+
+        // skip service that is not ready
+        ready[2].set(false);
+        for id in &[1, 0, 1] {
+            assert!(lazy(|cx| srv.poll_ready(cx)).await.is_ready());
+            assert_eq!(srv.call(()).await, Ok(*id));
+        }
+
+        ready.iter().for_each(|r| r.set(false));
+        assert!(lazy(|cx| srv.poll_ready(cx)).await.is_pending());
+        assert!(lazy(|cx| srv.poll_shutdown(cx, false)).await.is_ready());
+    }
+
+    #[crate::rt_test]
+    async fn test_ready_cycle() {
+        let (ready, srvs) = services(3);
+        let srv = Balance::new(srvs);
+
+        // only member that reported ready is used until next readiness check
+        ready[1].set(false);
+        ready[2].set(false);
+        assert!(lazy(|cx| srv.poll_ready(cx)).await.is_ready());
+        assert_eq!(srv.call(()).await, Ok(0));
+        assert_eq!(srv.call(()).await, Ok(0));
+
+        ready[1].set(true);
+        assert!(lazy(|cx| srv.poll_ready(cx)).await.is_ready());
+        assert_eq!(srv.call(()).await, Ok(1));
+    }
+
+    #[crate::rt_test]
+    async fn test_least_inflight() {
+        let (_, srvs) = services(3);
+        let srv = Balance::new(srvs).strategy(Strategy::LeastInFlight);
+
+        assert!(lazy(|cx| srv.poll_ready(cx)).await.is_ready());
+        let fut1 = srv.call(());
+        assert!(lazy(|cx| srv.poll_ready(cx)).await.is_ready());
+        let fut2 = srv.call(());
+        assert_eq!(srv.inflight(0), 1);
+        assert_eq!(srv.inflight(1), 1);
+        assert_eq!(srv.inflight(2), 0);
+
+        assert!(lazy(|cx| srv.poll_ready(cx)).await.is_ready());
+        assert_eq!(srv.call(()).await, Ok(2));
+        drop(fut1);
+        assert_eq!(srv.inflight(0), 0);
+        assert!(lazy(|cx| srv.poll_ready(cx)).await.is_ready());
+        assert_eq!(srv.call(()).await, Ok(0));
+        assert_eq!(fut2.await, Ok(1));
+        assert_eq!(srv.inflight(1), 0);
+    }
+
+    struct ErrService(Cell<bool>);
+
+    impl Service for ErrService {
+        type Request = ();
+        type Response = usize;
+        type Error = ();
+        type Future = Ready<usize, ()>;
+
+        fn poll_ready(&self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            if self.0.get() {
+                Poll::Ready(Err(()))
+            } else {
+                Poll::Pending
+            }
+        }
+
+        fn call(&self, _: ()) -> Self::Future {
+            Ready::Ok(0)
+        }
+    }
+
+    #[crate::rt_test]
+    async fn test_error() {
+        let srv = Balance::new(vec![
+            ErrService(Cell::new(true)),
+            ErrService(Cell::new(false)),
+        ]);
+        assert!(lazy(|cx| srv.poll_ready(cx)).await.is_pending());
+
+        srv.services[1].0.set(true);
+        assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Ready(Err(())));
+    }
+
+    #[crate::rt_test]
+    async fn test_pool() {
+        let factory = pool(
+            3,
+            crate::service::fn_factory_with_config(|_: ()| async {
+                Ok::<_, ()>(TestService {
+                    id: 0,
+                    ready: Rc::new(Cell::new(true)),
+                })
+            }),
+        );
+        let srv = factory.clone().new_service(()).await.unwrap();
+        assert_eq!(srv.len(), 3);
+
+        assert!(lazy(|cx| srv.poll_ready(cx)).await.is_ready());
+        let fut1 = srv.call(());
+        assert!(lazy(|cx| srv.poll_ready(cx)).await.is_ready());
+        let fut2 = srv.call(());
+        assert_eq!(
+            (srv.inflight(0), srv.inflight(1), srv.inflight(2)),
+            (1, 1, 0)
+        );
+        let _ = fut1.await;
+        let _ = fut2.await;
+        assert_eq!(
+            (srv.inflight(0), srv.inflight(1), srv.inflight(2)),
+            (0, 0, 0)
+        );
+    }
+}
@@ -19,6 +19,7 @@ pub struct Buffer<E> {
 }
 
 impl<E> Buffer<E> {
+    /// Create transform, `f` creates error for requests dropped from buffer
     pub fn new<F>(f: F) -> Self
     where
         F: Fn() -> E + 'static,
@@ -29,6 +30,7 @@ impl<E> Buffer<E> {
         }
     }
 
+    /// Set max number of buffered requests
     pub fn buf_size(mut self, size: usize) -> Self {
         self.buf_size = size;
         self
@@ -89,6 +91,7 @@ impl<S, E> BufferService<S, E>
 where
     S: Service<Error = E>,
 {
+    /// Create service with max number of buffered requests
     pub fn new<U, F>(size: usize, err: F, service: U) -> Self
     where
         U: IntoService<S>,
@@ -105,6 +108,11 @@ where
             }),
         }
     }
+
+    /// Get number of buffered requests
+    pub fn buffered(&self) -> usize {
+        self.inner.buf.borrow().len()
+    }
 }
 
 impl<S, E> Clone for BufferService<S, E>
@@ -288,11 +296,13 @@ mod tests {
 
         let fut2 = srv.call(());
         assert_eq!(inner.count.get(), 0);
+        assert_eq!(srv.buffered(), 2);
         assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Pending);
 
         inner.ready.set(true);
         inner.waker.wake();
         assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Ready(Ok(())));
+        assert_eq!(srv.buffered(), 1);
 
         let _ = fut1.await;
         assert_eq!(inner.count.get(), 1);
@@ -0,0 +1,207 @@
+//! Service that caches inner service readiness.
+use std::{cell::Cell, convert::Infallible, task::Context, task::Poll, time::Duration};
+
+use crate::rt::time::Instant;
+use crate::service::{IntoService, Service, Transform};
+use crate::util::Ready;
+
+/// CacheReady - service factory for service that caches successful
+/// readiness check of inner service.
+///
+/// Inner service readiness is checked again after configured number of calls
+/// or after configured period. Default number of calls is 16.
+#[derive(Debug, Clone)]
+pub struct CacheReady {
+    max_calls: usize,
+    max_age: Option<Duration>,
+}
+
+impl Default for CacheReady {
+    fn default() -> Self {
+        CacheReady::new(16)
+    }
+}
+
+impl CacheReady {
+    /// Create transform, readiness is cached for `max_calls` calls
+    pub fn new(max_calls: usize) -> Self {
+        CacheReady {
+            max_calls: std::cmp::max(1, max_calls),
+            max_age: None,
+        }
+    }
+
+    /// Set max period readiness is cached for
+    ///
+    /// By default period is not limited.
+    pub fn max_age(mut self, age: Duration) -> Self {
+        self.max_age = Some(age);
+        self
+    }
+}
+
+impl<S> Transform<S> for CacheReady
+where
+    S: Service,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type InitError = Infallible;
+    type Transform = CacheReadyService<S>;
+    type Future = Ready<Self::Transform, Self::InitError>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        Ready::Ok(CacheReadyService::create(self.clone(), service))
+    }
+}
+
+/// Service that caches successful readiness check of inner service.
+pub struct CacheReadyService<S> {
+    service: S,
+    cfg: CacheReady,
+    calls: Cell<usize>,
+    expires: Cell<Option<Instant>>,
+}
+
+impl<S> CacheReadyService<S>
+where
+    S: Service,
+{
+    /// Create service, readiness is cached for `max_calls` calls
+    pub fn new<U>(max_calls: usize, service: U) -> Self
+    where
+        U: IntoService<S>,
+    {
+        Self::create(CacheReady::new(max_calls), service.into_service())
+    }
+
+    fn create(cfg: CacheReady, service: S) -> Self {
+        CacheReadyService {
+            cfg,
+            service,
+            calls: Cell::new(0),
+            expires: Cell::new(None),
+        }
+    }
+
+    /// Drop cached readiness, next `poll_ready` checks inner service
+    pub fn reset(&self) {
+        self.calls.set(0);
+    }
+
+    fn is_cached(&self) -> bool {
+        if self.calls.get() == 0 {
+            false
+        } else if let Some(expires) = self.expires.get() {
+            Instant::now() < expires
+        } else {
+            true
+        }
+    }
+}
+
+impl<S> Service for CacheReadyService<S>
+where
+    S: Service,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if self.is_cached() {
+            return Poll::Ready(Ok(()));
+        }
+
+        let res = self.service.poll_ready(cx);
+        if let Poll::Ready(Ok(())) = res {
+            self.calls.set(self.cfg.max_calls);
+            self.expires
+                .set(self.cfg.max_age.map(|age| Instant::now() + age));
+        } else {
+            self.calls.set(0);
+        }
+        res
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    #[inline]
+    fn call(&self, req: S::Request) -> Self::Future {
+        self.calls.set(self.calls.get().saturating_sub(1));
+        self.service.call(req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::service::{apply, fn_factory, ServiceFactory};
+    use crate::util::lazy;
+
+    #[derive(Clone)]
+    struct TestService(Rc<Cell<usize>>);
+
+    impl Service for TestService {
+        type Request = ();
+        type Response = ();
+        type Error = ();
+        type Future = Ready<(), ()>;
+
+        fn poll_ready(&self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            self.0.set(self.0.get() + 1);
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&self, _: ()) -> Self::Future {
+            Ready::Ok(())
+        }
+    }
+
+    #[crate::rt_test]
+    async fn test_calls() {
+        let cnt = Rc::new(Cell::new(0));
+        let srv = CacheReadyService::new(2, TestService(cnt.clone()));
+
+        for _ in 0..4 {
+            assert!(lazy(|cx| srv.poll_ready(cx)).await.is_ready());
+            assert_eq!(srv.call(()).await, Ok(()));
+        }
+        assert_eq!(cnt.get(), 2);
+
+        srv.reset();
+        assert!(lazy(|cx| srv.poll_ready(cx)).await.is_ready());
+        assert_eq!(cnt.get(), 3);
+        assert!(lazy(|cx| srv.poll_shutdown(cx, false)).await.is_ready());
+    }
+
+    #[crate::rt_test]
+    async fn test_max_age() {
+        let cnt = Rc::new(Cell::new(0));
+        let cnt2 = cnt.clone();
+        let factory = apply(
+            CacheReady::new(100).max_age(Duration::from_millis(25)),
+            fn_factory(move || {
+                let cnt = cnt2.clone();
+                async move { Ok(TestService(cnt)) }
+            }),
+        );
+        let srv = factory.new_service(&()).await.unwrap();
+
+        assert!(lazy(|cx| srv.poll_ready(cx)).await.is_ready());
+        assert!(lazy(|cx| srv.poll_ready(cx)).await.is_ready());
+        assert_eq!(cnt.get(), 1);
+
+        crate::rt::time::sleep(Duration::from_millis(50)).await;
+        assert!(lazy(|cx| srv.poll_ready(cx)).await.is_ready());
+        assert_eq!(cnt.get(), 2);
+    }
+}
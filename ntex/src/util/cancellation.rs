@@ -0,0 +1,332 @@
+//! Service that could be cancelled with cancellation token.
+//!
+//! After token is cancelled, service readiness check fails and new calls
+//! are rejected. In-flight calls are allowed to complete within grace
+//! period, after that they fail with `CancellationError::Cancelled`.
+use std::{
+    cell::Cell, fmt, future::Future, pin::Pin, rc::Rc, task::Context, task::Poll,
+    time::Duration,
+};
+
+use crate::channel::condition::{Condition, Waiter};
+use crate::rt::time::{sleep, Sleep};
+use crate::service::{IntoService, Service, Transform};
+use crate::util::{Either, Ready};
+
+/// Cancellation token
+///
+/// Token could be cloned, all clones are cancelled at the same time.
+#[derive(Clone, Default)]
+pub struct CancellationToken(Rc<TokenInner>);
+
+#[derive(Default)]
+struct TokenInner {
+    cancelled: Cell<bool>,
+    cond: Condition,
+}
+
+impl fmt::Debug for CancellationToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CancellationToken")
+            .field("cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
+
+impl CancellationToken {
+    /// Create new token
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel token and notify all waiters
+    pub fn cancel(&self) {
+        if !self.0.cancelled.replace(true) {
+            self.0.cond.notify();
+        }
+    }
+
+    /// Check if token is cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancelled.get()
+    }
+
+    /// Returns future that resolves when token is cancelled
+    pub fn cancelled(&self) -> Cancelled {
+        Cancelled {
+            waiter: self.0.cond.wait(),
+            token: self.clone(),
+        }
+    }
+}
+
+/// Future that resolves when token is cancelled
+#[must_use = "Future do nothing unless polled"]
+pub struct Cancelled {
+    token: CancellationToken,
+    waiter: Waiter,
+}
+
+impl Cancelled {
+    /// Poll token cancellation
+    pub fn poll_cancelled(&self, cx: &mut Context<'_>) -> Poll<()> {
+        if self.token.is_cancelled() {
+            return Poll::Ready(());
+        }
+        let _ = self.waiter.poll_ready(cx);
+        if self.token.is_cancelled() {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl Future for Cancelled {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.poll_cancelled(cx)
+    }
+}
+
+/// Cancellation error
+#[derive(Debug, PartialEq)]
+pub enum CancellationError<E> {
+    /// Service error
+    Service(E),
+    /// Service is cancelled
+    Cancelled,
+}
+
+impl<E: fmt::Display> fmt::Display for CancellationError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CancellationError::Service(e) => e.fmt(f),
+            CancellationError::Cancelled => write!(f, "Service is cancelled"),
+        }
+    }
+}
+
+/// WithCancellation - service factory for service that could be cancelled
+/// with cancellation token.
+///
+/// Default grace period is 0, in-flight calls are cancelled immediately.
+#[derive(Debug, Clone)]
+pub struct WithCancellation {
+    token: CancellationToken,
+    grace: Duration,
+}
+
+impl WithCancellation {
+    /// Create transform with cancellation token
+    pub fn new(token: CancellationToken) -> Self {
+        WithCancellation {
+            token,
+            grace: Duration::from_millis(0),
+        }
+    }
+
+    /// Set grace period for in-flight calls
+    pub fn grace_period(mut self, grace: Duration) -> Self {
+        self.grace = grace;
+        self
+    }
+}
+
+impl<S> Transform<S> for WithCancellation
+where
+    S: Service,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = CancellationError<S::Error>;
+    type InitError = std::convert::Infallible;
+    type Transform = WithCancellationService<S>;
+    type Future = Ready<Self::Transform, Self::InitError>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        Ready::Ok(WithCancellationService::create(
+            self.token.clone(),
+            self.grace,
+            service,
+        ))
+    }
+}
+
+/// Service that could be cancelled with cancellation token.
+pub struct WithCancellationService<S> {
+    service: S,
+    grace: Duration,
+    cancelled: Cancelled,
+}
+
+impl<S> WithCancellationService<S>
+where
+    S: Service,
+{
+    /// Create service with cancellation token and grace period
+    pub fn new<U>(token: CancellationToken, grace: Duration, service: U) -> Self
+    where
+        U: IntoService<S>,
+    {
+        Self::create(token, grace, service.into_service())
+    }
+
+    fn create(token: CancellationToken, grace: Duration, service: S) -> Self {
+        WithCancellationService {
+            service,
+            grace,
+            cancelled: token.cancelled(),
+        }
+    }
+}
+
+impl<S> Service for WithCancellationService<S>
+where
+    S: Service,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = CancellationError<S::Error>;
+    type Future = Either<
+        WithCancellationResponse<S>,
+        Ready<S::Response, CancellationError<S::Error>>,
+    >;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if self.cancelled.poll_cancelled(cx).is_ready() {
+            log::trace!("Service is cancelled");
+            Poll::Ready(Err(CancellationError::Cancelled))
+        } else {
+            self.service
+                .poll_ready(cx)
+                .map_err(CancellationError::Service)
+        }
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&self, req: S::Request) -> Self::Future {
+        if self.cancelled.token.is_cancelled() {
+            Either::Right(Ready::Err(CancellationError::Cancelled))
+        } else {
+            Either::Left(WithCancellationResponse {
+                fut: self.service.call(req),
+                cancelled: self.cancelled.token.cancelled(),
+                grace: self.grace,
+                sleep: None,
+            })
+        }
+    }
+}
+
+pin_project_lite::pin_project! {
+    #[doc(hidden)]
+    pub struct WithCancellationResponse<S: Service> {
+        #[pin]
+        fut: S::Future,
+        cancelled: Cancelled,
+        grace: Duration,
+        sleep: Option<Pin<Box<Sleep>>>,
+    }
+}
+
+impl<S: Service> Future for WithCancellationResponse<S> {
+    type Output = Result<S::Response, CancellationError<S::Error>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        if let Poll::Ready(res) = this.fut.poll(cx) {
+            return Poll::Ready(res.map_err(CancellationError::Service));
+        }
+
+        if this.sleep.is_none() {
+            if this.cancelled.poll_cancelled(cx).is_pending() {
+                return Poll::Pending;
+            }
+            *this.sleep = Some(Box::pin(sleep(*this.grace)));
+        }
+        match this.sleep.as_mut().unwrap().as_mut().poll(cx) {
+            Poll::Ready(_) => {
+                log::trace!("Grace period is elapsed, cancel service call");
+                Poll::Ready(Err(CancellationError::Cancelled))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::{apply, fn_factory, fn_service, ServiceFactory};
+    use crate::util::lazy;
+
+    async fn sleep_srv(ms: u64) -> Result<u64, ()> {
+        sleep(Duration::from_millis(ms)).await;
+        Ok(ms)
+    }
+
+    #[crate::rt_test]
+    async fn test_token() {
+        let token = CancellationToken::new();
+        let fut = token.cancelled();
+        assert!(lazy(|cx| fut.poll_cancelled(cx)).await.is_pending());
+
+        let token2 = token.clone();
+        crate::rt::spawn(async move {
+            sleep(Duration::from_millis(25)).await;
+            token2.cancel();
+        });
+        fut.await;
+        assert!(token.is_cancelled());
+        token.cancelled().await;
+        assert!(format!("{:?}", token).contains("cancelled: true"));
+    }
+
+    #[crate::rt_test]
+    async fn test_cancel() {
+        let token = CancellationToken::new();
+        let srv = WithCancellationService::new(
+            token.clone(),
+            Duration::from_millis(0),
+            sleep_srv,
+        );
+        assert!(lazy(|cx| srv.poll_ready(cx)).await.is_ready());
+        assert_eq!(srv.call(5).await, Ok(5));
+
+        let fut = srv.call(1000);
+        token.cancel();
+        assert_eq!(fut.await, Err(CancellationError::Cancelled));
+        assert_eq!(
+            lazy(|cx| srv.poll_ready(cx)).await,
+            Poll::Ready(Err(CancellationError::Cancelled))
+        );
+        assert_eq!(srv.call(5).await, Err(CancellationError::Cancelled));
+    }
+
+    #[crate::rt_test]
+    async fn test_grace_period() {
+        let token = CancellationToken::new();
+        let factory = apply(
+            WithCancellation::new(token.clone()).grace_period(Duration::from_millis(50)),
+            fn_factory(|| async { Ok(fn_service(sleep_srv)) }),
+        );
+        let srv = factory.new_service(&()).await.unwrap();
+
+        let fut1 = srv.call(25);
+        let fut2 = srv.call(1000);
+        token.cancel();
+        assert_eq!(fut1.await, Ok(25));
+        assert_eq!(fut2.await, Err(CancellationError::Cancelled));
+        assert_eq!(
+            format!("{}", CancellationError::<&str>::Cancelled),
+            "Service is cancelled"
+        );
+    }
+}
@@ -0,0 +1,203 @@
+//! Chain of responsibility service.
+//!
+//! `Chain` passes request to services in order, until one of them
+//! handles it. Service returns `Outcome::Unhandled` with the request to
+//! pass it to the next service. Services in chain must be of the same
+//! type, use boxed services for handlers of different types.
+use std::{future::Future, pin::Pin, rc::Rc, task::Context, task::Poll};
+
+use crate::service::Service;
+
+/// Chain member service result
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome<Req, Res> {
+    /// Request is handled
+    Handled(Res),
+    /// Request is not handled, pass request to next service
+    Unhandled(Req),
+}
+
+impl<Req, Res> Outcome<Req, Res> {
+    /// Check if request is handled
+    pub fn is_handled(&self) -> bool {
+        matches!(self, Outcome::Handled(_))
+    }
+}
+
+/// Service that passes request to services in order until one handles it
+///
+/// If none of services handles request, `Outcome::Unhandled` is returned.
+/// Service is ready if all services in chain are ready.
+pub struct Chain<S> {
+    services: Rc<Vec<S>>,
+}
+
+impl<S> Chain<S>
+where
+    S: Service,
+{
+    /// Create chain service
+    ///
+    /// Panics if `services` is empty.
+    pub fn new<I>(services: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+    {
+        let services: Vec<_> = services.into_iter().collect();
+        assert!(!services.is_empty(), "Chain requires at least one service");
+
+        Chain {
+            services: Rc::new(services),
+        }
+    }
+
+    /// Number of services in chain
+    pub fn len(&self) -> usize {
+        self.services.len()
+    }
+
+    /// Chain always contains at least one service
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+}
+
+impl<S> Clone for Chain<S> {
+    fn clone(&self) -> Self {
+        Chain {
+            services: self.services.clone(),
+        }
+    }
+}
+
+impl<S, Req, Res> Service for Chain<S>
+where
+    S: Service<Request = Req, Response = Outcome<Req, Res>>,
+{
+    type Request = Req;
+    type Response = Outcome<Req, Res>;
+    type Error = S::Error;
+    type Future = ChainResponse<S>;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let mut ready = true;
+        for srv in self.services.iter() {
+            ready &= srv.poll_ready(cx)?.is_ready();
+        }
+        if ready {
+            Poll::Ready(Ok(()))
+        } else {
+            Poll::Pending
+        }
+    }
+
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        let mut ready = true;
+        for srv in self.services.iter() {
+            ready &= srv.poll_shutdown(cx, is_error).is_ready();
+        }
+        if ready {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+
+    fn call(&self, req: Req) -> Self::Future {
+        ChainResponse {
+            fut: self.services[0].call(req),
+            services: self.services.clone(),
+            idx: 0,
+        }
+    }
+}
+
+pin_project_lite::pin_project! {
+    #[doc(hidden)]
+    pub struct ChainResponse<S: Service> {
+        #[pin]
+        fut: S::Future,
+        services: Rc<Vec<S>>,
+        idx: usize,
+    }
+}
+
+impl<S, Req, Res> Future for ChainResponse<S>
+where
+    S: Service<Request = Req, Response = Outcome<Req, Res>>,
+{
+    type Output = Result<Outcome<Req, Res>, S::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut this = self.project();
+
+        loop {
+            match this.fut.as_mut().poll(cx)? {
+                Poll::Ready(Outcome::Unhandled(req)) => {
+                    *this.idx += 1;
+                    if let Some(srv) = this.services.get(*this.idx) {
+                        log::trace!(
+                            "Request is not handled, pass to service {}",
+                            this.idx
+                        );
+                        let fut = srv.call(req);
+                        this.fut.set(fut);
+                    } else {
+                        return Poll::Ready(Ok(Outcome::Unhandled(req)));
+                    }
+                }
+                Poll::Ready(res) => return Poll::Ready(Ok(res)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::boxed;
+    use crate::service::fn_service;
+    use crate::util::{lazy, Ready};
+
+    fn handler(
+        cmd: &'static str,
+    ) -> boxed::BoxService<String, Outcome<String, usize>, ()> {
+        boxed::service(fn_service(move |req: String| {
+            if req == "error" {
+                Ready::Err(())
+            } else if req == cmd {
+                Ready::Ok(Outcome::Handled(req.len()))
+            } else {
+                Ready::Ok(Outcome::Unhandled(req))
+            }
+        }))
+    }
+
+    #[crate::rt_test]
+    async fn test_chain() {
+        let srv = Chain::new(vec![handler("get"), handler("set"), handler("delete")]);
+        assert_eq!(srv.len(), 3);
+        assert!(!srv.is_empty());
+        assert!(lazy(|cx| srv.poll_ready(cx)).await.is_ready());
+        assert!(lazy(|cx| srv.poll_shutdown(cx, false)).await.is_ready());
+
+        assert_eq!(srv.call("get".to_string()).await, Ok(Outcome::Handled(3)));
+        assert_eq!(
+            srv.call("delete".to_string()).await,
+            Ok(Outcome::Handled(6))
+        );
+        let res = srv.clone().call("unknown".to_string()).await.unwrap();
+        assert!(!res.is_handled());
+        assert_eq!(res, Outcome::Unhandled("unknown".to_string()));
+        assert_eq!(srv.call("error".to_string()).await, Err(()));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_empty() {
+        let _ = Chain::new(
+            Vec::<boxed::BoxService<String, Outcome<String, usize>, ()>>::new(),
+        );
+    }
+}
@@ -0,0 +1,538 @@
+//! Service that stops calling inner service after too many failures.
+//!
+//! Circuit breaker tracks inner service errors. If error rate exceeds
+//! configured threshold, circuit opens and all calls fail fast, service
+//! readiness is pending until reset timeout elapses. After that, circuit is
+//! half-open, limited number of probe calls is allowed. Successful probe
+//! closes circuit, failed probe opens it again.
+use std::cell::{Cell, RefCell};
+use std::{
+    convert::Infallible, fmt, future::Future, pin::Pin, rc::Rc, task::Context,
+    task::Poll, time::Duration,
+};
+
+use crate::rt::time::{sleep_until, Instant, Sleep};
+use crate::service::{IntoService, Service, Transform};
+use crate::task::LocalWaker;
+use crate::util::{Either, Ready};
+
+/// Circuit breaker error
+#[derive(Debug, PartialEq)]
+pub enum CircuitBreakerError<E> {
+    /// Service error
+    Service(E),
+    /// Circuit is open, call is rejected
+    Open,
+}
+
+impl<E: fmt::Display> fmt::Display for CircuitBreakerError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CircuitBreakerError::Service(e) => e.fmt(f),
+            CircuitBreakerError::Open => write!(f, "Circuit breaker is open"),
+        }
+    }
+}
+
+/// Circuit state
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Calls are passed to inner service
+    Closed,
+    /// Calls are rejected
+    Open,
+    /// Limited number of probe calls is allowed
+    HalfOpen,
+}
+
+/// CircuitBreaker - service factory for service that fails fast if inner
+/// service error rate is too high.
+///
+/// By default circuit opens if at least half of calls fails, with at least
+/// 20 calls within 10 seconds window. Open circuit is half-opened after 5
+/// seconds.
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    cfg: Config,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Config {
+    error_rate: usize,
+    min_calls: usize,
+    window: Duration,
+    reset_timeout: Duration,
+    probes: usize,
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        CircuitBreaker::new()
+    }
+}
+
+impl CircuitBreaker {
+    /// Create circuit breaker transform with default settings
+    pub fn new() -> Self {
+        CircuitBreaker {
+            cfg: Config {
+                error_rate: 50,
+                min_calls: 20,
+                window: Duration::from_secs(10),
+                reset_timeout: Duration::from_secs(5),
+                probes: 1,
+            },
+        }
+    }
+
+    /// Set error rate in percents that opens circuit
+    ///
+    /// By default error rate is set to 50%
+    pub fn error_rate(mut self, rate: u8) -> Self {
+        self.cfg.error_rate = std::cmp::max(1, std::cmp::min(rate, 100)) as usize;
+        self
+    }
+
+    /// Set min number of calls within window before circuit could open
+    ///
+    /// By default min number of calls is set to 20
+    pub fn min_calls(mut self, calls: usize) -> Self {
+        self.cfg.min_calls = std::cmp::max(1, calls);
+        self
+    }
+
+    /// Set period for calculating error rate
+    ///
+    /// By default window is set to 10 seconds
+    pub fn window(mut self, window: Duration) -> Self {
+        self.cfg.window = window;
+        self
+    }
+
+    /// Set time before open circuit becomes half-open
+    ///
+    /// By default reset timeout is set to 5 seconds
+    pub fn reset_timeout(mut self, timeout: Duration) -> Self {
+        self.cfg.reset_timeout = timeout;
+        self
+    }
+
+    /// Set number of concurrent probe calls in half-open state
+    ///
+    /// By default one probe call is allowed
+    pub fn probes(mut self, probes: usize) -> Self {
+        self.cfg.probes = std::cmp::max(1, probes);
+        self
+    }
+}
+
+impl<S> Transform<S> for CircuitBreaker
+where
+    S: Service,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = CircuitBreakerError<S::Error>;
+    type InitError = Infallible;
+    type Transform = CircuitBreakerService<S>;
+    type Future = Ready<Self::Transform, Self::InitError>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        Ready::Ok(CircuitBreakerService::create(self.cfg, service))
+    }
+}
+
+/// Service that fails fast if inner service error rate is too high.
+pub struct CircuitBreakerService<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    cfg: Config,
+    state: Cell<CircuitState>,
+    calls: Cell<usize>,
+    failures: Cell<usize>,
+    window_start: Cell<Instant>,
+    opened: Cell<Instant>,
+    probes: Cell<usize>,
+    period: Cell<usize>,
+    sleep: RefCell<Option<Pin<Box<Sleep>>>>,
+    waker: LocalWaker,
+}
+
+impl Inner {
+    fn open(&self) {
+        log::trace!("Circuit breaker is open");
+        self.state.set(CircuitState::Open);
+        self.opened.set(Instant::now());
+        *self.sleep.borrow_mut() = None;
+        self.waker.wake();
+    }
+
+    fn close(&self) {
+        log::trace!("Circuit breaker is closed");
+        self.state.set(CircuitState::Closed);
+        self.calls.set(0);
+        self.failures.set(0);
+        self.window_start.set(Instant::now());
+        self.waker.wake();
+    }
+
+    /// Check open circuit reset timeout, returns true if circuit is open
+    fn is_open(&self, cx: Option<&mut Context<'_>>) -> bool {
+        if self.state.get() != CircuitState::Open {
+            return false;
+        }
+
+        let deadline = self.opened.get() + self.cfg.reset_timeout;
+        if Instant::now() < deadline {
+            if let Some(cx) = cx {
+                let mut sleep = self.sleep.borrow_mut();
+                let sleep = sleep.get_or_insert_with(|| Box::pin(sleep_until(deadline)));
+                if sleep.as_mut().poll(cx).is_pending() {
+                    return true;
+                }
+            } else {
+                return true;
+            }
+        }
+
+        log::trace!("Circuit breaker is half-open");
+        self.state.set(CircuitState::HalfOpen);
+        self.probes.set(0);
+        self.period.set(self.period.get().wrapping_add(1));
+        *self.sleep.borrow_mut() = None;
+        false
+    }
+
+    /// Check if probe call is started in current half-open period
+    fn is_current(&self, period: usize) -> bool {
+        self.state.get() == CircuitState::HalfOpen && self.period.get() == period
+    }
+
+    fn record(&self, success: bool, probe: Option<usize>) {
+        if let Some(period) = probe {
+            // ignore probes from previous half-open periods
+            if !self.is_current(period) {
+                return;
+            }
+            self.probes.set(self.probes.get() - 1);
+            if success {
+                self.close();
+            } else {
+                self.open();
+            }
+        } else if self.state.get() == CircuitState::Closed {
+            let now = Instant::now();
+            if now.duration_since(self.window_start.get()) > self.cfg.window {
+                self.calls.set(0);
+                self.failures.set(0);
+                self.window_start.set(now);
+            }
+
+            let calls = self.calls.get() + 1;
+            self.calls.set(calls);
+            if !success {
+                let failures = self.failures.get() + 1;
+                self.failures.set(failures);
+                if calls >= self.cfg.min_calls
+                    && failures * 100 >= self.cfg.error_rate * calls
+                {
+                    self.open();
+                }
+            }
+        }
+    }
+}
+
+impl<S> CircuitBreakerService<S>
+where
+    S: Service,
+{
+    /// Create circuit breaker service with default settings
+    pub fn new<U>(service: U) -> Self
+    where
+        U: IntoService<S>,
+    {
+        Self::create(CircuitBreaker::new().cfg, service.into_service())
+    }
+
+    fn create(cfg: Config, service: S) -> Self {
+        let now = Instant::now();
+        CircuitBreakerService {
+            service,
+            inner: Rc::new(Inner {
+                cfg,
+                state: Cell::new(CircuitState::Closed),
+                calls: Cell::new(0),
+                failures: Cell::new(0),
+                window_start: Cell::new(now),
+                opened: Cell::new(now),
+                probes: Cell::new(0),
+                period: Cell::new(0),
+                sleep: RefCell::new(None),
+                waker: LocalWaker::new(),
+            }),
+        }
+    }
+
+    /// Get current circuit state
+    pub fn state(&self) -> CircuitState {
+        self.inner.state.get()
+    }
+}
+
+impl<S> Service for CircuitBreakerService<S>
+where
+    S: Service,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = CircuitBreakerError<S::Error>;
+    type Future = Either<
+        CircuitBreakerResponse<S>,
+        Ready<S::Response, CircuitBreakerError<S::Error>>,
+    >;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if self.inner.is_open(Some(cx)) {
+            return Poll::Pending;
+        }
+        if self.inner.state.get() == CircuitState::HalfOpen
+            && self.inner.probes.get() >= self.inner.cfg.probes
+        {
+            self.inner.waker.register(cx.waker());
+            return Poll::Pending;
+        }
+        self.service
+            .poll_ready(cx)
+            .map_err(CircuitBreakerError::Service)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&self, req: S::Request) -> Self::Future {
+        let probe = match self.inner.state.get() {
+            CircuitState::Closed => None,
+            _ if self.inner.is_open(None) => {
+                return Either::Right(Ready::Err(CircuitBreakerError::Open))
+            }
+            _ => {
+                if self.inner.probes.get() >= self.inner.cfg.probes {
+                    return Either::Right(Ready::Err(CircuitBreakerError::Open));
+                }
+                self.inner.probes.set(self.inner.probes.get() + 1);
+                Some(self.inner.period.get())
+            }
+        };
+
+        Either::Left(CircuitBreakerResponse {
+            fut: self.service.call(req),
+            guard: Guard {
+                probe,
+                inner: self.inner.clone(),
+                completed: false,
+            },
+        })
+    }
+}
+
+pin_project_lite::pin_project! {
+    #[doc(hidden)]
+    pub struct CircuitBreakerResponse<S: Service> {
+        #[pin]
+        fut: S::Future,
+        guard: Guard,
+    }
+}
+
+impl<S: Service> Future for CircuitBreakerResponse<S> {
+    type Output = Result<S::Response, CircuitBreakerError<S::Error>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        match this.fut.poll(cx) {
+            Poll::Ready(res) => {
+                this.guard.completed = true;
+                this.guard.inner.record(res.is_ok(), this.guard.probe);
+                Poll::Ready(res.map_err(CircuitBreakerError::Service))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+struct Guard {
+    /// Half-open period of probe call
+    probe: Option<usize>,
+    completed: bool,
+    inner: Rc<Inner>,
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        // canceled probe call, allow another probe
+        if let Some(period) = self.probe {
+            if !self.completed && self.inner.is_current(period) {
+                self.inner.probes.set(self.inner.probes.get() - 1);
+                self.inner.waker.wake();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use super::*;
+    use crate::service::{apply, fn_factory, ServiceFactory};
+    use crate::util::lazy;
+
+    #[derive(Clone)]
+    struct TestService(Rc<Cell<bool>>);
+
+    impl Service for TestService {
+        type Request = ();
+        type Response = ();
+        type Error = ();
+        type Future = Ready<(), ()>;
+
+        fn poll_ready(&self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&self, _: ()) -> Self::Future {
+            if self.0.get() {
+                Ready::Ok(())
+            } else {
+                Ready::Err(())
+            }
+        }
+    }
+
+    #[crate::rt_test]
+    async fn test_circuit_breaker() {
+        let ok = Rc::new(Cell::new(true));
+        let ok2 = ok.clone();
+        let factory = apply(
+            CircuitBreaker::default()
+                .error_rate(50)
+                .min_calls(4)
+                .reset_timeout(Duration::from_millis(50)),
+            fn_factory(move || {
+                let ok = ok2.clone();
+                async move { Ok(TestService(ok)) }
+            }),
+        );
+        let srv = factory.new_service(&()).await.unwrap();
+
+        assert_eq!(srv.call(()).await, Ok(()));
+        assert_eq!(srv.call(()).await, Ok(()));
+        ok.set(false);
+        assert_eq!(srv.call(()).await, Err(CircuitBreakerError::Service(())));
+        assert_eq!(srv.state(), CircuitState::Closed);
+        assert_eq!(srv.call(()).await, Err(CircuitBreakerError::Service(())));
+        assert_eq!(srv.state(), CircuitState::Open);
+
+        // fail fast
+        ok.set(true);
+        assert!(lazy(|cx| srv.poll_ready(cx)).await.is_pending());
+        assert_eq!(srv.call(()).await, Err(CircuitBreakerError::Open));
+
+        // failed probe
+        crate::rt::time::sleep(Duration::from_millis(75)).await;
+        ok.set(false);
+        assert!(lazy(|cx| srv.poll_ready(cx)).await.is_ready());
+        assert_eq!(srv.state(), CircuitState::HalfOpen);
+        assert_eq!(srv.call(()).await, Err(CircuitBreakerError::Service(())));
+        assert_eq!(srv.state(), CircuitState::Open);
+
+        // successful probe
+        ok.set(true);
+        crate::rt::time::sleep(Duration::from_millis(75)).await;
+        assert!(lazy(|cx| srv.poll_ready(cx)).await.is_ready());
+        let fut = srv.call(());
+        assert!(lazy(|cx| srv.poll_ready(cx)).await.is_pending());
+        assert_eq!(srv.call(()).await, Err(CircuitBreakerError::Open));
+        assert_eq!(fut.await, Ok(()));
+        assert_eq!(srv.state(), CircuitState::Closed);
+        assert!(lazy(|cx| srv.poll_ready(cx)).await.is_ready());
+    }
+
+    #[crate::rt_test]
+    async fn test_canceled_probe() {
+        let ok = Rc::new(Cell::new(false));
+        let srv = CircuitBreakerService::new(TestService(ok.clone()));
+        for _ in 0..20 {
+            let _ = srv.call(()).await;
+        }
+        assert_eq!(srv.state(), CircuitState::Open);
+
+        srv.inner
+            .opened
+            .set(Instant::now() - Duration::from_secs(10));
+        assert!(lazy(|cx| srv.poll_ready(cx)).await.is_ready());
+        let fut = srv.call(());
+        assert!(lazy(|cx| srv.poll_ready(cx)).await.is_pending());
+        drop(fut);
+        assert!(lazy(|cx| srv.poll_ready(cx)).await.is_ready());
+        assert_eq!(srv.state(), CircuitState::HalfOpen);
+    }
+
+    #[crate::rt_test]
+    async fn test_stale_probes() {
+        let ok = Rc::new(Cell::new(false));
+        let srv = CircuitBreaker::new()
+            .probes(3)
+            .new_transform(TestService(ok.clone()))
+            .await
+            .unwrap();
+        for _ in 0..20 {
+            let _ = srv.call(()).await;
+        }
+        assert_eq!(srv.state(), CircuitState::Open);
+
+        // three probes in first half-open period
+        srv.inner
+            .opened
+            .set(Instant::now() - Duration::from_secs(10));
+        assert!(lazy(|cx| srv.poll_ready(cx)).await.is_ready());
+        let fut1 = srv.call(());
+        let fut2 = srv.call(());
+        let fut3 = srv.call(());
+        assert_eq!(fut1.await, Err(CircuitBreakerError::Service(())));
+        assert_eq!(srv.state(), CircuitState::Open);
+
+        // second half-open period
+        srv.inner
+            .opened
+            .set(Instant::now() - Duration::from_secs(10));
+        assert!(lazy(|cx| srv.poll_ready(cx)).await.is_ready());
+        assert_eq!(srv.state(), CircuitState::HalfOpen);
+        ok.set(true);
+        let fut4 = srv.call(());
+
+        // stale probes do not affect current period
+        drop(fut2);
+        assert_eq!(srv.inner.probes.get(), 1);
+        assert_eq!(fut3.await, Err(CircuitBreakerError::Service(())));
+        assert_eq!(srv.state(), CircuitState::HalfOpen);
+        assert_eq!(srv.inner.probes.get(), 1);
+
+        assert_eq!(fut4.await, Ok(()));
+        assert_eq!(srv.state(), CircuitState::Closed);
+    }
+
+    #[test]
+    fn test_error() {
+        let err = CircuitBreakerError::<&str>::Open;
+        assert_eq!(format!("{}", err), "Circuit breaker is open");
+        assert_eq!(format!("{}", CircuitBreakerError::Service("err")), "err");
+    }
+}
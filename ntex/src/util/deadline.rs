@@ -0,0 +1,143 @@
+//! Request-scoped call context.
+//!
+//! `CallContext` carries call deadline through service pipeline. Context is
+//! kept in thread local storage while service is called and while response
+//! future is polled, so nested services could read it with
+//! `CallContext::current()`. `Timeout` and `Retry` services respect deadline
+//! of outer context, nested timeouts never extend outer deadline.
+use std::{
+    cell::Cell, future::Future, pin::Pin, task::Context, task::Poll, time::Duration,
+};
+
+use crate::rt::time::Instant;
+
+thread_local! {
+    static CURRENT: Cell<CallContext> = Cell::new(CallContext::default());
+}
+
+/// Request-scoped call context
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct CallContext {
+    deadline: Option<Instant>,
+}
+
+impl CallContext {
+    /// Context of current call
+    pub fn current() -> Self {
+        CURRENT.with(|ctx| ctx.get())
+    }
+
+    /// Call deadline
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// Time remaining until deadline
+    ///
+    /// Returns zero duration if deadline is expired.
+    pub fn remaining(&self) -> Option<Duration> {
+        self.deadline
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+
+    /// Check if deadline is expired
+    pub fn is_expired(&self) -> bool {
+        self.deadline
+            .map(|deadline| deadline <= Instant::now())
+            .unwrap_or(false)
+    }
+
+    /// Create context with deadline, existing deadline is not extended
+    pub fn with_deadline(self, deadline: Instant) -> Self {
+        let deadline = match self.deadline {
+            Some(current) if current < deadline => current,
+            _ => deadline,
+        };
+        CallContext {
+            deadline: Some(deadline),
+        }
+    }
+
+    /// Create context with timeout relative to now, existing deadline is not extended
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        self.with_deadline(Instant::now() + timeout)
+    }
+
+    /// Run function with this context as current context
+    pub fn scope<F, R>(self, f: F) -> R
+    where
+        F: FnOnce() -> R,
+    {
+        let prev = CURRENT.with(|ctx| ctx.replace(self));
+        let _guard = Guard(prev);
+        f()
+    }
+
+    /// Wrap future, context is set as current context while future is polled
+    pub fn wrap<F: Future>(self, fut: F) -> Scoped<F> {
+        Scoped { ctx: self, fut }
+    }
+}
+
+struct Guard(CallContext);
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        CURRENT.with(|ctx| ctx.set(self.0));
+    }
+}
+
+pin_project_lite::pin_project! {
+    /// Future with call context
+    pub struct Scoped<F> {
+        ctx: CallContext,
+        #[pin]
+        fut: F,
+    }
+}
+
+impl<F: Future> Future for Scoped<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let fut = this.fut;
+        this.ctx.scope(|| fut.poll(cx))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::lazy;
+
+    #[crate::rt_test]
+    async fn test_scope() {
+        assert_eq!(CallContext::current(), CallContext::default());
+        assert!(CallContext::current().remaining().is_none());
+        assert!(!CallContext::current().is_expired());
+
+        let ctx = CallContext::default().with_timeout(Duration::from_millis(100));
+        let deadline = ctx.deadline().unwrap();
+        ctx.scope(|| {
+            assert_eq!(CallContext::current(), ctx);
+
+            // nested timeout does not extend deadline
+            let ctx2 = CallContext::current().with_timeout(Duration::from_secs(10));
+            assert_eq!(ctx2.deadline(), Some(deadline));
+            let ctx3 = CallContext::current().with_timeout(Duration::from_millis(10));
+            assert!(ctx3.deadline().unwrap() < deadline);
+            ctx3.scope(|| assert_eq!(CallContext::current(), ctx3));
+            assert_eq!(CallContext::current(), ctx);
+        });
+        assert_eq!(CallContext::current(), CallContext::default());
+
+        let mut fut = Box::pin(ctx.wrap(async { CallContext::current() }));
+        assert_eq!(lazy(|cx| fut.as_mut().poll(cx)).await, Poll::Ready(ctx));
+        assert_eq!(CallContext::current(), CallContext::default());
+
+        let ctx = CallContext::default().with_timeout(Duration::from_millis(0));
+        assert!(ctx.is_expired());
+        assert_eq!(ctx.remaining(), Some(Duration::from_millis(0)));
+    }
+}
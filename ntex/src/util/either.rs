@@ -0,0 +1,385 @@
+//! Services that dispatch requests to one of two services.
+//!
+//! `EitherService` and `EitherFactory` wrap one of two service types, variant
+//! is selected on construction. `Select` calls one of two services, service
+//! is selected for each request by selector function.
+use std::{future::Future, pin::Pin, rc::Rc, task::Context, task::Poll};
+
+use crate::service::{IntoServiceFactory, Service, ServiceFactory};
+use crate::util::Either;
+
+/// Service that is one of two service types
+#[derive(Debug, Clone)]
+pub enum EitherService<A, B> {
+    /// First service type
+    Left(A),
+    /// Second service type
+    Right(B),
+}
+
+impl<A, B> Service for EitherService<A, B>
+where
+    A: Service,
+    B: Service<Request = A::Request, Response = A::Response, Error = A::Error>,
+{
+    type Request = A::Request;
+    type Response = A::Response;
+    type Error = A::Error;
+    type Future = Either<A::Future, B::Future>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match self {
+            EitherService::Left(srv) => srv.poll_ready(cx),
+            EitherService::Right(srv) => srv.poll_ready(cx),
+        }
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        match self {
+            EitherService::Left(srv) => srv.poll_shutdown(cx, is_error),
+            EitherService::Right(srv) => srv.poll_shutdown(cx, is_error),
+        }
+    }
+
+    #[inline]
+    fn call(&self, req: A::Request) -> Self::Future {
+        match self {
+            EitherService::Left(srv) => Either::Left(srv.call(req)),
+            EitherService::Right(srv) => Either::Right(srv.call(req)),
+        }
+    }
+}
+
+/// Service factory that is one of two service factory types
+#[derive(Debug, Clone)]
+pub enum EitherFactory<A, B> {
+    /// First factory type
+    Left(A),
+    /// Second factory type
+    Right(B),
+}
+
+impl<A, B> EitherFactory<A, B> {
+    /// Create factory from first factory type
+    pub fn left<F: IntoServiceFactory<A>>(factory: F) -> Self
+    where
+        A: ServiceFactory,
+    {
+        EitherFactory::Left(factory.into_factory())
+    }
+
+    /// Create factory from second factory type
+    pub fn right<F: IntoServiceFactory<B>>(factory: F) -> Self
+    where
+        B: ServiceFactory,
+    {
+        EitherFactory::Right(factory.into_factory())
+    }
+}
+
+impl<A, B> ServiceFactory for EitherFactory<A, B>
+where
+    A: ServiceFactory,
+    B: ServiceFactory<
+        Config = A::Config,
+        Request = A::Request,
+        Response = A::Response,
+        Error = A::Error,
+        InitError = A::InitError,
+    >,
+{
+    type Request = A::Request;
+    type Response = A::Response;
+    type Error = A::Error;
+    type Config = A::Config;
+    type Service = EitherService<A::Service, B::Service>;
+    type InitError = A::InitError;
+    type Future = EitherFactoryResponse<A, B>;
+
+    fn new_service(&self, cfg: A::Config) -> Self::Future {
+        match self {
+            EitherFactory::Left(f) => EitherFactoryResponse::Left {
+                fut: f.new_service(cfg),
+            },
+            EitherFactory::Right(f) => EitherFactoryResponse::Right {
+                fut: f.new_service(cfg),
+            },
+        }
+    }
+}
+
+pin_project_lite::pin_project! {
+    #[doc(hidden)]
+    #[project = EitherFactoryResponseProject]
+    pub enum EitherFactoryResponse<A: ServiceFactory, B: ServiceFactory> {
+        Left { #[pin] fut: A::Future },
+        Right { #[pin] fut: B::Future },
+    }
+}
+
+impl<A, B> Future for EitherFactoryResponse<A, B>
+where
+    A: ServiceFactory,
+    B: ServiceFactory<InitError = A::InitError>,
+{
+    type Output = Result<EitherService<A::Service, B::Service>, A::InitError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project() {
+            EitherFactoryResponseProject::Left { fut } => {
+                fut.poll(cx).map_ok(EitherService::Left)
+            }
+            EitherFactoryResponseProject::Right { fut } => {
+                fut.poll(cx).map_ok(EitherService::Right)
+            }
+        }
+    }
+}
+
+/// Construct `Select` service factory.
+///
+/// Selector function is called for each request, if it returns `true`
+/// request is handled by `left` service, otherwise by `right` service.
+pub fn select<S, A, B, F1, F2>(selector: S, left: F1, right: F2) -> Select<S, A, B>
+where
+    S: Fn(&A::Request) -> bool,
+    A: ServiceFactory,
+    A::Config: Clone,
+    B: ServiceFactory<
+        Config = A::Config,
+        Request = A::Request,
+        Response = A::Response,
+        Error = A::Error,
+        InitError = A::InitError,
+    >,
+    F1: IntoServiceFactory<A>,
+    F2: IntoServiceFactory<B>,
+{
+    Select {
+        selector: Rc::new(selector),
+        left: left.into_factory(),
+        right: right.into_factory(),
+    }
+}
+
+/// Service factory that selects service for each request
+pub struct Select<S, A, B> {
+    selector: Rc<S>,
+    left: A,
+    right: B,
+}
+
+impl<S, A: Clone, B: Clone> Clone for Select<S, A, B> {
+    fn clone(&self) -> Self {
+        Select {
+            selector: self.selector.clone(),
+            left: self.left.clone(),
+            right: self.right.clone(),
+        }
+    }
+}
+
+impl<S, A, B> ServiceFactory for Select<S, A, B>
+where
+    S: Fn(&A::Request) -> bool,
+    A: ServiceFactory,
+    A::Config: Clone,
+    B: ServiceFactory<
+        Config = A::Config,
+        Request = A::Request,
+        Response = A::Response,
+        Error = A::Error,
+        InitError = A::InitError,
+    >,
+{
+    type Request = A::Request;
+    type Response = A::Response;
+    type Error = A::Error;
+    type Config = A::Config;
+    type Service = SelectService<S, A::Service, B::Service>;
+    type InitError = A::InitError;
+    type Future = SelectFactoryResponse<S, A, B>;
+
+    fn new_service(&self, cfg: A::Config) -> Self::Future {
+        SelectFactoryResponse {
+            selector: Some(self.selector.clone()),
+            left_fut: self.left.new_service(cfg.clone()),
+            right_fut: self.right.new_service(cfg),
+            left: None,
+            right: None,
+        }
+    }
+}
+
+pin_project_lite::pin_project! {
+    #[doc(hidden)]
+    pub struct SelectFactoryResponse<S, A: ServiceFactory, B: ServiceFactory> {
+        selector: Option<Rc<S>>,
+        #[pin]
+        left_fut: A::Future,
+        #[pin]
+        right_fut: B::Future,
+        left: Option<A::Service>,
+        right: Option<B::Service>,
+    }
+}
+
+impl<S, A, B> Future for SelectFactoryResponse<S, A, B>
+where
+    A: ServiceFactory,
+    B: ServiceFactory<InitError = A::InitError>,
+{
+    type Output = Result<SelectService<S, A::Service, B::Service>, A::InitError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        if this.left.is_none() {
+            if let Poll::Ready(srv) = this.left_fut.poll(cx)? {
+                *this.left = Some(srv);
+            }
+        }
+        if this.right.is_none() {
+            if let Poll::Ready(srv) = this.right_fut.poll(cx)? {
+                *this.right = Some(srv);
+            }
+        }
+
+        if this.left.is_some() && this.right.is_some() {
+            Poll::Ready(Ok(SelectService {
+                selector: this.selector.take().unwrap(),
+                left: this.left.take().unwrap(),
+                right: this.right.take().unwrap(),
+            }))
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+/// Service that selects inner service for each request
+///
+/// Service is ready if both inner services are ready.
+pub struct SelectService<S, A, B> {
+    selector: Rc<S>,
+    left: A,
+    right: B,
+}
+
+impl<S, A: Clone, B: Clone> Clone for SelectService<S, A, B> {
+    fn clone(&self) -> Self {
+        SelectService {
+            selector: self.selector.clone(),
+            left: self.left.clone(),
+            right: self.right.clone(),
+        }
+    }
+}
+
+impl<S, A, B> Service for SelectService<S, A, B>
+where
+    S: Fn(&A::Request) -> bool,
+    A: Service,
+    B: Service<Request = A::Request, Response = A::Response, Error = A::Error>,
+{
+    type Request = A::Request;
+    type Response = A::Response;
+    type Error = A::Error;
+    type Future = Either<A::Future, B::Future>;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let left = self.left.poll_ready(cx)?.is_ready();
+        let right = self.right.poll_ready(cx)?.is_ready();
+        if left && right {
+            Poll::Ready(Ok(()))
+        } else {
+            Poll::Pending
+        }
+    }
+
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        let left = self.left.poll_shutdown(cx, is_error).is_ready();
+        let right = self.right.poll_shutdown(cx, is_error).is_ready();
+        if left && right {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+
+    fn call(&self, req: A::Request) -> Self::Future {
+        if (self.selector)(&req) {
+            Either::Left(self.left.call(req))
+        } else {
+            Either::Right(self.right.call(req))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::{fn_factory, fn_service};
+    use crate::util::{lazy, Ready};
+
+    #[derive(Clone)]
+    struct Srv1;
+
+    impl Service for Srv1 {
+        type Request = usize;
+        type Response = usize;
+        type Error = ();
+        type Future = Ready<usize, ()>;
+
+        fn poll_ready(&self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_shutdown(&self, _: &mut Context<'_>, _: bool) -> Poll<()> {
+            Poll::Pending
+        }
+
+        fn call(&self, req: usize) -> Self::Future {
+            Ready::Ok(req + 1)
+        }
+    }
+
+    #[crate::rt_test]
+    async fn test_either() {
+        let factory = |left| {
+            if left {
+                EitherFactory::left(fn_factory(|| async { Ok::<_, ()>(Srv1) }))
+            } else {
+                EitherFactory::right(fn_service(|req: usize| {
+                    Ready::<_, ()>::Ok(req * 10)
+                }))
+            }
+        };
+
+        let srv = factory(true).clone().new_service(()).await.unwrap();
+        assert!(lazy(|cx| srv.poll_ready(cx)).await.is_ready());
+        assert!(lazy(|cx| srv.poll_shutdown(cx, false)).await.is_pending());
+        assert_eq!(srv.call(2).await, Ok(3));
+
+        let srv = factory(false).new_service(()).await.unwrap().clone();
+        assert!(lazy(|cx| srv.poll_ready(cx)).await.is_ready());
+        assert!(lazy(|cx| srv.poll_shutdown(cx, false)).await.is_ready());
+        assert_eq!(srv.call(2).await, Ok(20));
+    }
+
+    #[crate::rt_test]
+    async fn test_select() {
+        let factory = select(
+            |req: &usize| *req < 10,
+            fn_factory(|| async { Ok::<_, ()>(Srv1) }),
+            fn_service(|req: usize| Ready::<_, ()>::Ok(req * 10)),
+        );
+        let srv = factory.clone().new_service(()).await.unwrap().clone();
+        assert!(lazy(|cx| srv.poll_ready(cx)).await.is_ready());
+        assert!(lazy(|cx| srv.poll_shutdown(cx, false)).await.is_pending());
+        assert_eq!(srv.call(2).await, Ok(3));
+        assert_eq!(srv.call(20).await, Ok(200));
+    }
+}
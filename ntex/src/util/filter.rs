@@ -0,0 +1,307 @@
+//! Service that filters requests with predicate service.
+//!
+//! Predicate service is called for each request before inner service, it
+//! either passes request to inner service or rejects it with alternative
+//! response. Rejection is not an error, it is a regular response, the same
+//! way web guards work.
+use std::{future::Future, pin::Pin, rc::Rc, task::Context, task::Poll};
+
+use crate::service::{IntoServiceFactory, Service, ServiceFactory};
+
+/// Predicate service result
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filtered<Req, Res> {
+    /// Pass request to inner service
+    Pass(Req),
+    /// Reject request with response
+    Reject(Res),
+}
+
+/// Construct `Filter` service factory.
+///
+/// `predicate` service is called for each request, if it returns
+/// `Filtered::Pass` request is handled by `service`, otherwise
+/// predicate's response is returned.
+pub fn filter<P, S, F1, F2>(predicate: F1, service: F2) -> Filter<P, S>
+where
+    S: ServiceFactory,
+    S::Config: Clone,
+    P: ServiceFactory<
+        Config = S::Config,
+        Request = S::Request,
+        Response = Filtered<S::Request, S::Response>,
+        Error = S::Error,
+        InitError = S::InitError,
+    >,
+    F1: IntoServiceFactory<P>,
+    F2: IntoServiceFactory<S>,
+{
+    Filter {
+        predicate: predicate.into_factory(),
+        service: service.into_factory(),
+    }
+}
+
+/// Service factory that filters requests with predicate service
+#[derive(Debug, Clone)]
+pub struct Filter<P, S> {
+    predicate: P,
+    service: S,
+}
+
+impl<P, S> ServiceFactory for Filter<P, S>
+where
+    S: ServiceFactory,
+    S::Config: Clone,
+    P: ServiceFactory<
+        Config = S::Config,
+        Request = S::Request,
+        Response = Filtered<S::Request, S::Response>,
+        Error = S::Error,
+        InitError = S::InitError,
+    >,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Config = S::Config;
+    type Service = FilterService<P::Service, S::Service>;
+    type InitError = S::InitError;
+    type Future = FilterFactoryResponse<P, S>;
+
+    fn new_service(&self, cfg: S::Config) -> Self::Future {
+        FilterFactoryResponse {
+            predicate_fut: self.predicate.new_service(cfg.clone()),
+            service_fut: self.service.new_service(cfg),
+            predicate: None,
+            service: None,
+        }
+    }
+}
+
+pin_project_lite::pin_project! {
+    #[doc(hidden)]
+    pub struct FilterFactoryResponse<P: ServiceFactory, S: ServiceFactory> {
+        #[pin]
+        predicate_fut: P::Future,
+        #[pin]
+        service_fut: S::Future,
+        predicate: Option<P::Service>,
+        service: Option<S::Service>,
+    }
+}
+
+impl<P, S> Future for FilterFactoryResponse<P, S>
+where
+    S: ServiceFactory,
+    P: ServiceFactory<InitError = S::InitError>,
+{
+    type Output = Result<FilterService<P::Service, S::Service>, S::InitError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        if this.predicate.is_none() {
+            if let Poll::Ready(srv) = this.predicate_fut.poll(cx)? {
+                *this.predicate = Some(srv);
+            }
+        }
+        if this.service.is_none() {
+            if let Poll::Ready(srv) = this.service_fut.poll(cx)? {
+                *this.service = Some(srv);
+            }
+        }
+
+        if this.predicate.is_some() && this.service.is_some() {
+            Poll::Ready(Ok(FilterService(Rc::new((
+                this.predicate.take().unwrap(),
+                this.service.take().unwrap(),
+            )))))
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+/// Service that filters requests with predicate service
+///
+/// Service is ready if both predicate and inner services are ready.
+pub struct FilterService<P, S>(Rc<(P, S)>);
+
+impl<P, S> FilterService<P, S>
+where
+    S: Service,
+    P: Service<
+        Request = S::Request,
+        Response = Filtered<S::Request, S::Response>,
+        Error = S::Error,
+    >,
+{
+    /// Create service with predicate and inner services
+    pub fn new(predicate: P, service: S) -> Self {
+        FilterService(Rc::new((predicate, service)))
+    }
+}
+
+impl<P, S> Clone for FilterService<P, S> {
+    fn clone(&self) -> Self {
+        FilterService(self.0.clone())
+    }
+}
+
+impl<P, S> Service for FilterService<P, S>
+where
+    S: Service,
+    P: Service<
+        Request = S::Request,
+        Response = Filtered<S::Request, S::Response>,
+        Error = S::Error,
+    >,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = FilterServiceResponse<P, S>;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let predicate = self.0 .0.poll_ready(cx)?.is_ready();
+        let service = self.0 .1.poll_ready(cx)?.is_ready();
+        if predicate && service {
+            Poll::Ready(Ok(()))
+        } else {
+            Poll::Pending
+        }
+    }
+
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        let predicate = self.0 .0.poll_shutdown(cx, is_error).is_ready();
+        let service = self.0 .1.poll_shutdown(cx, is_error).is_ready();
+        if predicate && service {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+
+    fn call(&self, req: S::Request) -> Self::Future {
+        FilterServiceResponse {
+            state: State::Predicate {
+                fut: self.0 .0.call(req),
+                srv: Some(self.0.clone()),
+            },
+        }
+    }
+}
+
+pin_project_lite::pin_project! {
+    #[doc(hidden)]
+    pub struct FilterServiceResponse<P: Service, S: Service> {
+        #[pin]
+        state: State<P, S>,
+    }
+}
+
+pin_project_lite::pin_project! {
+    #[project = StateProject]
+    enum State<P: Service, S: Service> {
+        Predicate { #[pin] fut: P::Future, srv: Option<Rc<(P, S)>> },
+        Service { #[pin] fut: S::Future },
+        Empty,
+    }
+}
+
+impl<P, S> Future for FilterServiceResponse<P, S>
+where
+    S: Service,
+    P: Service<
+        Request = S::Request,
+        Response = Filtered<S::Request, S::Response>,
+        Error = S::Error,
+    >,
+{
+    type Output = Result<S::Response, S::Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut this = self.as_mut().project();
+
+        match this.state.as_mut().project() {
+            StateProject::Predicate { fut, srv } => match fut.poll(cx)? {
+                Poll::Ready(Filtered::Pass(req)) => {
+                    let srv = srv.take().unwrap();
+                    this.state.set(State::Empty);
+                    let fut = srv.as_ref().1.call(req);
+                    this.state.set(State::Service { fut });
+                    self.poll(cx)
+                }
+                Poll::Ready(Filtered::Reject(res)) => {
+                    log::trace!("Request is rejected by predicate service");
+                    this.state.set(State::Empty);
+                    Poll::Ready(Ok(res))
+                }
+                Poll::Pending => Poll::Pending,
+            },
+            StateProject::Service { fut } => fut.poll(cx).map(|res| {
+                this.state.set(State::Empty);
+                res
+            }),
+            StateProject::Empty => {
+                panic!("future must not be polled after it returned `Poll::Ready`")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+    use crate::service::{fn_factory, fn_service};
+    use crate::util::{lazy, Ready};
+
+    async fn acl(req: usize) -> Result<Filtered<usize, String>, ()> {
+        if req < 10 {
+            Ok(Filtered::Pass(req))
+        } else if req < 100 {
+            Ok(Filtered::Reject("rejected".to_string()))
+        } else {
+            Err(())
+        }
+    }
+
+    #[crate::rt_test]
+    async fn test_filter() {
+        let calls = Rc::new(Cell::new(0));
+        let calls2 = calls.clone();
+        let factory = filter(
+            fn_service(acl),
+            fn_factory(move || {
+                let calls = calls2.clone();
+                async move {
+                    Ok::<_, ()>(fn_service(move |req: usize| {
+                        calls.set(calls.get() + 1);
+                        Ready::<_, ()>::Ok(format!("{}", req * 2))
+                    }))
+                }
+            }),
+        );
+        let srv = factory.clone().new_service(()).await.unwrap().clone();
+        assert!(lazy(|cx| srv.poll_ready(cx)).await.is_ready());
+        assert!(lazy(|cx| srv.poll_shutdown(cx, false)).await.is_ready());
+
+        assert_eq!(srv.call(2).await, Ok("4".to_string()));
+        assert_eq!(srv.call(20).await, Ok("rejected".to_string()));
+        assert_eq!(srv.call(200).await, Err(()));
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[crate::rt_test]
+    async fn test_filter_service() {
+        let srv = FilterService::new(
+            fn_service(acl),
+            fn_service(|req: usize| Ready::<_, ()>::Ok(req.to_string())),
+        );
+        assert_eq!(srv.call(5).await, Ok("5".to_string()));
+        assert_eq!(srv.call(50).await, Ok("rejected".to_string()));
+    }
+}
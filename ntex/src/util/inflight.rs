@@ -13,6 +13,7 @@ pub struct InFlight {
 }
 
 impl InFlight {
+    /// Create transform with max number of in-flight requests
     pub fn new(max: usize) -> Self {
         Self { max_inflight: max }
     }
@@ -40,6 +41,10 @@ where
     }
 }
 
+/// Service that limits number of in-flight async requests.
+///
+/// Service readiness is pending while number of in-flight requests
+/// is at the limit.
 pub struct InFlightService<S> {
     count: Counter,
     service: S,
@@ -49,6 +54,7 @@ impl<S> InFlightService<S>
 where
     S: Service,
 {
+    /// Create service with max number of in-flight requests
     pub fn new<U>(max: usize, service: U) -> Self
     where
         U: IntoService<S>,
@@ -58,6 +64,11 @@ where
             service: service.into_service(),
         }
     }
+
+    /// Get number of in-flight requests
+    pub fn inflight(&self) -> usize {
+        self.count.total()
+    }
 }
 
 impl<T> Service for InFlightService<T>
@@ -150,9 +161,11 @@ mod tests {
         assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Ready(Ok(())));
 
         let res = srv.call(());
+        assert_eq!(srv.inflight(), 1);
         assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Pending);
 
         let _ = res.await;
+        assert_eq!(srv.inflight(), 0);
         assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Ready(Ok(())));
 
         assert!(lazy(|cx| srv.poll_shutdown(cx, false)).await.is_ready());
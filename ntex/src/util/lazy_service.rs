@@ -0,0 +1,304 @@
+//! Service factory that defers service creation until first call.
+//!
+//! `lazy()` factory creates service immediately, but inner service is
+//! created only when first request arrives. Created service is memoized,
+//! if creation fails next call tries to create service again.
+use std::{cell::RefCell, future::Future, pin::Pin, rc::Rc, task::Context, task::Poll};
+
+use crate::channel::condition::{Condition, Waiter};
+use crate::service::{IntoServiceFactory, Service, ServiceFactory};
+use crate::util::{Either, Ready};
+
+/// Construct lazy service factory.
+///
+/// Inner factory's init error must be convertible to service error.
+pub fn lazy<T, F>(factory: F) -> Lazy<T>
+where
+    T: ServiceFactory,
+    T::Config: Clone,
+    T::Error: From<T::InitError>,
+    F: IntoServiceFactory<T>,
+{
+    Lazy {
+        factory: Rc::new(factory.into_factory()),
+    }
+}
+
+/// Service factory that defers service creation until first call
+pub struct Lazy<T> {
+    factory: Rc<T>,
+}
+
+impl<T> Clone for Lazy<T> {
+    fn clone(&self) -> Self {
+        Lazy {
+            factory: self.factory.clone(),
+        }
+    }
+}
+
+impl<T> ServiceFactory for Lazy<T>
+where
+    T: ServiceFactory,
+    T::Config: Clone,
+    T::Error: From<T::InitError>,
+{
+    type Request = T::Request;
+    type Response = T::Response;
+    type Error = T::Error;
+    type Config = T::Config;
+    type Service = LazyService<T>;
+    type InitError = T::InitError;
+    type Future = Ready<Self::Service, Self::InitError>;
+
+    fn new_service(&self, cfg: T::Config) -> Self::Future {
+        let cond = Condition::new();
+        Ready::Ok(LazyService {
+            waiter: cond.wait(),
+            inner: Rc::new(Inner {
+                cfg,
+                cond,
+                factory: self.factory.clone(),
+                state: RefCell::new(State::Empty),
+            }),
+        })
+    }
+}
+
+/// Service that creates inner service on first call
+pub struct LazyService<T: ServiceFactory> {
+    inner: Rc<Inner<T>>,
+    waiter: Waiter,
+}
+
+struct Inner<T: ServiceFactory> {
+    cfg: T::Config,
+    cond: Condition,
+    factory: Rc<T>,
+    state: RefCell<State<T>>,
+}
+
+enum State<T: ServiceFactory> {
+    Empty,
+    Creating(Pin<Box<T::Future>>),
+    Created(T::Service),
+}
+
+impl<T> Inner<T>
+where
+    T: ServiceFactory,
+    T::Config: Clone,
+{
+    fn poll_service(&self, cx: &mut Context<'_>) -> Poll<Result<(), T::InitError>> {
+        let mut state = self.state.borrow_mut();
+        if let State::Empty = *state {
+            log::trace!("Create lazy service");
+            *state =
+                State::Creating(Box::pin(self.factory.new_service(self.cfg.clone())));
+        }
+
+        let res = match *state {
+            State::Created(_) => return Poll::Ready(Ok(())),
+            State::Creating(ref mut fut) => match fut.as_mut().poll(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(res) => res,
+            },
+            State::Empty => unreachable!(),
+        };
+        self.cond.notify();
+
+        match res {
+            Ok(srv) => {
+                *state = State::Created(srv);
+                Poll::Ready(Ok(()))
+            }
+            Err(e) => {
+                log::trace!("Cannot create lazy service");
+                *state = State::Empty;
+                Poll::Ready(Err(e))
+            }
+        }
+    }
+}
+
+impl<T> LazyService<T>
+where
+    T: ServiceFactory,
+{
+    /// Check if inner service is created
+    pub fn is_created(&self) -> bool {
+        matches!(*self.inner.state.borrow(), State::Created(_))
+    }
+}
+
+impl<T: ServiceFactory> Clone for LazyService<T> {
+    fn clone(&self) -> Self {
+        LazyService {
+            inner: self.inner.clone(),
+            waiter: self.inner.cond.wait(),
+        }
+    }
+}
+
+impl<T> Service for LazyService<T>
+where
+    T: ServiceFactory,
+    T::Config: Clone,
+    T::Error: From<T::InitError>,
+{
+    type Request = T::Request;
+    type Response = T::Response;
+    type Error = T::Error;
+    type Future = Either<<T::Service as Service>::Future, LazyServiceResponse<T>>;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match *self.inner.state.borrow() {
+            State::Empty => return Poll::Ready(Ok(())),
+            State::Created(ref srv) => return srv.poll_ready(cx),
+            State::Creating(_) => (),
+        }
+
+        match self.inner.poll_service(cx) {
+            Poll::Ready(Ok(())) => self.poll_ready(cx),
+            Poll::Ready(Err(e)) => Poll::Ready(Err(e.into())),
+            Poll::Pending => {
+                let _ = self.waiter.poll_ready(cx);
+                Poll::Pending
+            }
+        }
+    }
+
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        if let State::Created(ref srv) = *self.inner.state.borrow() {
+            srv.poll_shutdown(cx, is_error)
+        } else {
+            Poll::Ready(())
+        }
+    }
+
+    fn call(&self, req: T::Request) -> Self::Future {
+        if let State::Created(ref srv) = *self.inner.state.borrow() {
+            return Either::Left(srv.call(req));
+        }
+
+        Either::Right(LazyServiceResponse {
+            req: Some(req),
+            waiter: self.inner.cond.wait(),
+            inner: self.inner.clone(),
+            fut: None,
+        })
+    }
+}
+
+pin_project_lite::pin_project! {
+    #[doc(hidden)]
+    pub struct LazyServiceResponse<T: ServiceFactory> {
+        req: Option<T::Request>,
+        waiter: Waiter,
+        inner: Rc<Inner<T>>,
+        #[pin]
+        fut: Option<<T::Service as Service>::Future>,
+    }
+}
+
+impl<T> Future for LazyServiceResponse<T>
+where
+    T: ServiceFactory,
+    T::Config: Clone,
+    T::Error: From<T::InitError>,
+{
+    type Output = Result<T::Response, T::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut this = self.project();
+
+        if let Some(fut) = this.fut.as_mut().as_pin_mut() {
+            return fut.poll(cx);
+        }
+
+        match this.inner.poll_service(cx) {
+            Poll::Ready(Ok(())) => (),
+            Poll::Ready(Err(e)) => return Poll::Ready(Err(e.into())),
+            Poll::Pending => {
+                let _ = this.waiter.poll_ready(cx);
+                return Poll::Pending;
+            }
+        }
+
+        let fut = if let State::Created(ref srv) = *this.inner.state.borrow() {
+            match srv.poll_ready(cx) {
+                Poll::Ready(Ok(())) => srv.call(this.req.take().unwrap()),
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending,
+            }
+        } else {
+            unreachable!()
+        };
+        this.fut.set(Some(fut));
+        this.fut.as_pin_mut().unwrap().poll(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, time::Duration};
+
+    use super::*;
+    use crate::service::{fn_factory_with_config, fn_service};
+    use crate::util::lazy as lazy_fn;
+
+    #[crate::rt_test]
+    async fn test_lazy() {
+        let cnt = Rc::new(Cell::new(0));
+        let cnt2 = cnt.clone();
+        let factory = lazy(fn_factory_with_config(move |fail: bool| {
+            let cnt = cnt2.clone();
+            async move {
+                crate::rt::time::sleep(Duration::from_millis(10)).await;
+                cnt.set(cnt.get() + 1);
+                if fail && cnt.get() == 2 {
+                    Err(())
+                } else {
+                    Ok(fn_service(|req: usize| async move { Ok::<_, ()>(req * 2) }))
+                }
+            }
+        }))
+        .clone();
+
+        let srv = factory.new_service(false).await.unwrap();
+        assert!(!srv.is_created());
+        assert!(lazy_fn(|cx| srv.poll_ready(cx)).await.is_ready());
+        assert!(lazy_fn(|cx| srv.poll_shutdown(cx, false)).await.is_ready());
+        assert_eq!(cnt.get(), 0);
+
+        let srv2 = srv.clone();
+        let (res1, res2) = futures::join!(srv.call(1), srv2.call(2));
+        assert_eq!((res1, res2), (Ok(2), Ok(4)));
+        assert!(srv.is_created());
+        assert_eq!(srv.call(3).await, Ok(6));
+        assert_eq!(cnt.get(), 1);
+
+        // failed creation is retried on next call
+        let srv = factory.new_service(true).await.unwrap();
+        assert_eq!(srv.call(1).await, Err(()));
+        assert!(!srv.is_created());
+        assert_eq!(srv.call(1).await, Ok(2));
+        assert_eq!(cnt.get(), 3);
+    }
+
+    #[crate::rt_test]
+    async fn test_poll_ready() {
+        let factory = lazy(fn_factory_with_config(|_: ()| async {
+            crate::rt::time::sleep(Duration::from_millis(10)).await;
+            Ok::<_, ()>(fn_service(|req: usize| async move { Ok::<_, ()>(req) }))
+        }));
+        let srv = factory.new_service(()).await.unwrap();
+
+        let mut fut = Box::pin(srv.call(1));
+        assert!(lazy_fn(|cx| fut.as_mut().poll(cx)).await.is_pending());
+        assert!(lazy_fn(|cx| srv.poll_ready(cx)).await.is_pending());
+        crate::util::poll_fn(|cx| srv.poll_ready(cx)).await.unwrap();
+        assert!(srv.is_created());
+        assert_eq!(fut.await, Ok(1));
+    }
+}
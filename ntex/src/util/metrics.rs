@@ -0,0 +1,410 @@
+//! Service that records calls metrics.
+//!
+//! `Metrics` transform measures time spent waiting for inner service
+//! readiness and duration of each call, and reports them to `MetricsSink`.
+//! `Recorder` is simple sink that keeps counters and latency histogram,
+//! custom sink could forward metrics to prometheus, statsd, etc.
+use std::cell::{Cell, RefCell};
+use std::{
+    convert::Infallible, fmt, future::Future, pin::Pin, rc::Rc, task::Context,
+    task::Poll, time::Duration,
+};
+
+use crate::rt::time::Instant;
+use crate::service::{IntoService, Service, Transform};
+use crate::util::Ready;
+
+/// Metrics sink
+pub trait MetricsSink {
+    /// Service was not ready for `wait` period
+    fn ready_wait(&self, _name: &str, _wait: Duration) {}
+
+    /// Service call is completed
+    fn call(&self, name: &str, duration: Duration, success: bool);
+}
+
+impl<T: MetricsSink> MetricsSink for Rc<T> {
+    fn ready_wait(&self, name: &str, wait: Duration) {
+        self.as_ref().ready_wait(name, wait)
+    }
+
+    fn call(&self, name: &str, duration: Duration, success: bool) {
+        self.as_ref().call(name, duration, success)
+    }
+}
+
+/// Default latency histogram buckets
+const BUCKETS: &[Duration] = &[
+    Duration::from_millis(1),
+    Duration::from_millis(5),
+    Duration::from_millis(10),
+    Duration::from_millis(50),
+    Duration::from_millis(100),
+    Duration::from_millis(500),
+    Duration::from_millis(1000),
+];
+
+/// Metrics sink that keeps counters and latency histogram
+///
+/// Recorder does not distinguish services, use separate recorder
+/// for each service.
+pub struct Recorder {
+    calls: Cell<usize>,
+    errors: Cell<usize>,
+    ready_wait: Cell<Duration>,
+    buckets: Vec<Duration>,
+    histogram: RefCell<Vec<usize>>,
+}
+
+impl Default for Recorder {
+    fn default() -> Self {
+        Recorder::new(BUCKETS)
+    }
+}
+
+impl fmt::Debug for Recorder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Recorder")
+            .field("calls", &self.calls.get())
+            .field("errors", &self.errors.get())
+            .field("ready_wait", &self.ready_wait.get())
+            .field("histogram", &self.histogram())
+            .finish()
+    }
+}
+
+impl Recorder {
+    /// Create recorder with latency histogram buckets upper bounds
+    ///
+    /// Calls that take longer than last bucket are counted in extra bucket.
+    pub fn new(buckets: &[Duration]) -> Self {
+        let mut buckets = buckets.to_vec();
+        buckets.sort();
+        Recorder {
+            calls: Cell::new(0),
+            errors: Cell::new(0),
+            ready_wait: Cell::new(Duration::from_millis(0)),
+            histogram: RefCell::new(vec![0; buckets.len() + 1]),
+            buckets,
+        }
+    }
+
+    /// Number of completed calls
+    pub fn calls(&self) -> usize {
+        self.calls.get()
+    }
+
+    /// Number of failed calls
+    pub fn errors(&self) -> usize {
+        self.errors.get()
+    }
+
+    /// Total time spent waiting for service readiness
+    pub fn ready_wait(&self) -> Duration {
+        self.ready_wait.get()
+    }
+
+    /// Latency histogram, list of bucket upper bound and number of calls.
+    ///
+    /// Last bucket has no upper bound.
+    pub fn histogram(&self) -> Vec<(Option<Duration>, usize)> {
+        self.histogram
+            .borrow()
+            .iter()
+            .enumerate()
+            .map(|(idx, cnt)| (self.buckets.get(idx).copied(), *cnt))
+            .collect()
+    }
+
+    /// Reset all counters
+    pub fn reset(&self) {
+        self.calls.set(0);
+        self.errors.set(0);
+        self.ready_wait.set(Duration::from_millis(0));
+        self.histogram
+            .borrow_mut()
+            .iter_mut()
+            .for_each(|cnt| *cnt = 0);
+    }
+}
+
+impl MetricsSink for Recorder {
+    fn ready_wait(&self, _: &str, wait: Duration) {
+        self.ready_wait.set(self.ready_wait.get() + wait);
+    }
+
+    fn call(&self, _: &str, duration: Duration, success: bool) {
+        self.calls.set(self.calls.get() + 1);
+        if !success {
+            self.errors.set(self.errors.get() + 1);
+        }
+        let idx = self
+            .buckets
+            .iter()
+            .position(|b| duration <= *b)
+            .unwrap_or(self.buckets.len());
+        self.histogram.borrow_mut()[idx] += 1;
+    }
+}
+
+/// Metrics - service factory for service that records calls metrics.
+pub struct Metrics<M> {
+    name: Rc<str>,
+    sink: Rc<M>,
+}
+
+impl<M: MetricsSink> Metrics<M> {
+    /// Create transform, metrics are reported with specified service name
+    pub fn new<T: AsRef<str>>(name: T, sink: M) -> Self {
+        Metrics {
+            name: name.as_ref().into(),
+            sink: Rc::new(sink),
+        }
+    }
+}
+
+impl<M> Clone for Metrics<M> {
+    fn clone(&self) -> Self {
+        Metrics {
+            name: self.name.clone(),
+            sink: self.sink.clone(),
+        }
+    }
+}
+
+impl<S, M> Transform<S> for Metrics<M>
+where
+    S: Service,
+    M: MetricsSink,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type InitError = Infallible;
+    type Transform = MetricsService<S, M>;
+    type Future = Ready<Self::Transform, Self::InitError>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        Ready::Ok(MetricsService {
+            service,
+            name: self.name.clone(),
+            sink: self.sink.clone(),
+            pending: Cell::new(None),
+        })
+    }
+}
+
+/// Service that records calls metrics.
+pub struct MetricsService<S, M> {
+    service: S,
+    name: Rc<str>,
+    sink: Rc<M>,
+    pending: Cell<Option<Instant>>,
+}
+
+impl<S, M> MetricsService<S, M>
+where
+    S: Service,
+    M: MetricsSink,
+{
+    /// Create service, metrics are reported with specified service name
+    pub fn new<T, U>(name: T, sink: M, service: U) -> Self
+    where
+        T: AsRef<str>,
+        U: IntoService<S>,
+    {
+        MetricsService {
+            name: name.as_ref().into(),
+            sink: Rc::new(sink),
+            service: service.into_service(),
+            pending: Cell::new(None),
+        }
+    }
+
+    /// Get reference to metrics sink
+    pub fn sink(&self) -> &M {
+        &self.sink
+    }
+}
+
+impl<S, M> Service for MetricsService<S, M>
+where
+    S: Service,
+    M: MetricsSink,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = MetricsServiceResponse<S, M>;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let res = self.service.poll_ready(cx);
+        if res.is_pending() {
+            if self.pending.get().is_none() {
+                self.pending.set(Some(Instant::now()));
+            }
+        } else if let Some(start) = self.pending.take() {
+            self.sink.ready_wait(&self.name, start.elapsed());
+        }
+        res
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&self, req: S::Request) -> Self::Future {
+        MetricsServiceResponse {
+            fut: self.service.call(req),
+            start: Instant::now(),
+            name: self.name.clone(),
+            sink: self.sink.clone(),
+        }
+    }
+}
+
+pin_project_lite::pin_project! {
+    #[doc(hidden)]
+    pub struct MetricsServiceResponse<S: Service, M> {
+        #[pin]
+        fut: S::Future,
+        start: Instant,
+        name: Rc<str>,
+        sink: Rc<M>,
+    }
+}
+
+impl<S, M> Future for MetricsServiceResponse<S, M>
+where
+    S: Service,
+    M: MetricsSink,
+{
+    type Output = Result<S::Response, S::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        match this.fut.poll(cx) {
+            Poll::Ready(res) => {
+                this.sink.call(this.name, this.start.elapsed(), res.is_ok());
+                Poll::Ready(res)
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::{apply, fn_factory, fn_service, ServiceFactory};
+    use crate::util::lazy;
+
+    struct TestService(Rc<Cell<bool>>);
+
+    impl Service for TestService {
+        type Request = u64;
+        type Response = ();
+        type Error = ();
+        type Future = Pin<Box<dyn Future<Output = Result<(), ()>>>>;
+
+        fn poll_ready(&self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            if self.0.get() {
+                Poll::Ready(Ok(()))
+            } else {
+                Poll::Pending
+            }
+        }
+
+        fn call(&self, ms: u64) -> Self::Future {
+            Box::pin(async move {
+                crate::rt::time::sleep(Duration::from_millis(ms)).await;
+                if ms > 0 {
+                    Ok(())
+                } else {
+                    Err(())
+                }
+            })
+        }
+    }
+
+    #[crate::rt_test]
+    async fn test_metrics() {
+        let ready = Rc::new(Cell::new(false));
+        let srv =
+            MetricsService::new("test", Recorder::default(), TestService(ready.clone()));
+
+        assert!(lazy(|cx| srv.poll_ready(cx)).await.is_pending());
+        crate::rt::time::sleep(Duration::from_millis(25)).await;
+        ready.set(true);
+        assert!(lazy(|cx| srv.poll_ready(cx)).await.is_ready());
+        assert!(srv.sink().ready_wait() >= Duration::from_millis(25));
+
+        assert_eq!(srv.call(0).await, Err(()));
+        assert_eq!(srv.call(20).await, Ok(()));
+        assert_eq!(srv.call(150).await, Ok(()));
+        assert_eq!(srv.sink().calls(), 3);
+        assert_eq!(srv.sink().errors(), 1);
+
+        let hist = srv.sink().histogram();
+        assert_eq!(hist.len(), BUCKETS.len() + 1);
+        assert_eq!(hist.iter().map(|(_, cnt)| cnt).sum::<usize>(), 3);
+        assert_eq!(hist[3], (Some(Duration::from_millis(50)), 1));
+        assert_eq!(hist[5], (Some(Duration::from_millis(500)), 1));
+        assert!(format!("{:?}", srv.sink()).contains("Recorder"));
+
+        srv.sink().reset();
+        assert_eq!(srv.sink().calls(), 0);
+        assert!(lazy(|cx| srv.poll_shutdown(cx, false)).await.is_ready());
+    }
+
+    struct Sink(RefCell<Vec<(String, bool)>>);
+
+    impl MetricsSink for Sink {
+        fn call(&self, name: &str, _: Duration, success: bool) {
+            self.0.borrow_mut().push((name.to_string(), success));
+        }
+    }
+
+    #[crate::rt_test]
+    async fn test_transform() {
+        let sink = Rc::new(Sink(RefCell::new(Vec::new())));
+        let factory = apply(
+            Metrics::new("srv", sink.clone()).clone(),
+            fn_factory(|| async {
+                Ok(fn_service(|ok: bool| async move {
+                    if ok {
+                        Ok(())
+                    } else {
+                        Err(())
+                    }
+                }))
+            }),
+        );
+        let srv = factory.new_service(&()).await.unwrap();
+        assert!(lazy(|cx| srv.poll_ready(cx)).await.is_ready());
+        let _ = srv.call(true).await;
+        let _ = srv.call(false).await;
+        assert_eq!(
+            &*sink.0.borrow(),
+            &[("srv".to_string(), true), ("srv".to_string(), false)]
+        );
+    }
+
+    #[test]
+    fn test_recorder() {
+        let rec = Recorder::new(&[Duration::from_millis(10), Duration::from_millis(1)]);
+        rec.call("", Duration::from_millis(1), true);
+        rec.call("", Duration::from_millis(5), true);
+        rec.call("", Duration::from_millis(50), false);
+        assert_eq!(
+            rec.histogram(),
+            vec![
+                (Some(Duration::from_millis(1)), 1),
+                (Some(Duration::from_millis(10)), 1),
+                (None, 1)
+            ]
+        );
+    }
+}
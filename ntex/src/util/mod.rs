@@ -1,12 +1,28 @@
+pub mod balance;
 pub mod buffer;
+pub mod cache_ready;
+pub mod cancellation;
+pub mod chain;
+pub mod circuit_breaker;
 pub mod counter;
+pub mod deadline;
+pub mod either;
 mod extensions;
+pub mod filter;
 pub mod inflight;
 pub mod keepalive;
+pub mod lazy_service;
+pub mod metrics;
+pub mod ratelimit;
+pub mod retry;
+pub mod shared;
+pub mod shutdown;
 pub mod sink;
 pub mod stream;
 pub mod time;
 pub mod timeout;
+#[cfg(feature = "tower")]
+pub mod tower;
 pub mod variant;
 
 pub use self::extensions::Extensions;
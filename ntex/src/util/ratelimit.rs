@@ -0,0 +1,272 @@
+//! Service that limits rate of requests.
+//!
+//! Rate limiting uses token bucket algorithm. Each request consumes one
+//! token, tokens are replenished at configured rate up to burst size.
+//! Service readiness is pending until next token is available.
+use std::cell::{Cell, RefCell};
+use std::{
+    convert::Infallible, future::Future, pin::Pin, task::Context, task::Poll,
+    time::Duration,
+};
+
+use crate::rt::time::{sleep_until, Instant, Sleep};
+use crate::service::{IntoService, Service, Transform};
+use crate::util::Ready;
+
+/// RateLimit - service factory for service that limits rate of requests.
+///
+/// By default burst size is equal to number of requests per period.
+#[derive(Debug, Clone)]
+pub struct RateLimit {
+    interval: Duration,
+    burst: u32,
+}
+
+impl RateLimit {
+    /// Create transform, allows `num` requests per `per` period
+    ///
+    /// Panics if `num` is 0.
+    pub fn new(num: u32, per: Duration) -> Self {
+        assert!(num > 0, "Number of requests must be greater than 0");
+        RateLimit {
+            interval: per / num,
+            burst: num,
+        }
+    }
+
+    /// Create transform, allows `num` requests per second
+    pub fn per_second(num: u32) -> Self {
+        RateLimit::new(num, Duration::from_secs(1))
+    }
+
+    /// Set max number of requests that could be processed without delay
+    pub fn burst(mut self, burst: u32) -> Self {
+        self.burst = std::cmp::max(1, burst);
+        self
+    }
+}
+
+impl<S> Transform<S> for RateLimit
+where
+    S: Service,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type InitError = Infallible;
+    type Transform = RateLimitService<S>;
+    type Future = Ready<Self::Transform, Self::InitError>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        Ready::Ok(RateLimitService::create(self.interval, self.burst, service))
+    }
+}
+
+/// Service that limits rate of requests.
+pub struct RateLimitService<S> {
+    service: S,
+    bucket: TokenBucket,
+    sleep: RefCell<Option<Pin<Box<Sleep>>>>,
+}
+
+impl<S> RateLimitService<S>
+where
+    S: Service,
+{
+    /// Create service, allows `num` requests per `per` period
+    ///
+    /// Panics if `num` is 0.
+    pub fn new<U>(num: u32, per: Duration, service: U) -> Self
+    where
+        U: IntoService<S>,
+    {
+        let cfg = RateLimit::new(num, per);
+        Self::create(cfg.interval, cfg.burst, service.into_service())
+    }
+
+    fn create(interval: Duration, burst: u32, service: S) -> Self {
+        RateLimitService {
+            service,
+            bucket: TokenBucket::new(interval, burst),
+            sleep: RefCell::new(None),
+        }
+    }
+
+    /// Get number of available tokens
+    pub fn available(&self) -> u32 {
+        self.bucket.available()
+    }
+}
+
+impl<S> Service for RateLimitService<S>
+where
+    S: Service,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if self.bucket.available() == 0 {
+            // wait for next token
+            let deadline = self.bucket.next_token();
+            let mut sleep = self.sleep.borrow_mut();
+            let sleep = sleep.get_or_insert_with(|| Box::pin(sleep_until(deadline)));
+            if sleep.deadline() != deadline {
+                sleep.as_mut().reset(deadline);
+            }
+            if sleep.as_mut().poll(cx).is_pending() {
+                log::trace!("Rate limit exceeded");
+                return Poll::Pending;
+            }
+        }
+
+        self.service.poll_ready(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    #[inline]
+    fn call(&self, req: S::Request) -> Self::Future {
+        let _ = self.bucket.acquire();
+        self.service.call(req)
+    }
+}
+
+/// Token bucket, one token is added every `interval` up to `burst` tokens
+pub(crate) struct TokenBucket {
+    interval: Duration,
+    burst: u32,
+    tokens: Cell<u32>,
+    updated: Cell<Instant>,
+}
+
+impl TokenBucket {
+    pub(crate) fn new(interval: Duration, burst: u32) -> Self {
+        TokenBucket {
+            interval,
+            burst,
+            tokens: Cell::new(burst),
+            updated: Cell::new(Instant::now()),
+        }
+    }
+
+    /// Get number of available tokens
+    pub(crate) fn available(&self) -> u32 {
+        self.refill();
+        self.tokens.get()
+    }
+
+    /// Check if bucket is full
+    pub(crate) fn is_full(&self) -> bool {
+        self.available() >= self.burst
+    }
+
+    /// Instant when next token is added
+    pub(crate) fn next_token(&self) -> Instant {
+        self.updated.get() + self.interval
+    }
+
+    /// Take token from the bucket, returns time until next token on failure
+    pub(crate) fn acquire(&self) -> Result<(), Duration> {
+        let tokens = self.available();
+        if tokens > 0 {
+            self.tokens.set(tokens - 1);
+            Ok(())
+        } else {
+            Err(self.next_token().saturating_duration_since(Instant::now()))
+        }
+    }
+
+    fn refill(&self) {
+        let tokens = self.tokens.get();
+        if tokens >= self.burst {
+            self.updated.set(Instant::now());
+            return;
+        }
+
+        let elapsed = self.updated.get().elapsed().as_nanos();
+        let interval = std::cmp::max(1, self.interval.as_nanos());
+        let added = elapsed / interval;
+        if added > 0 {
+            let added = std::cmp::min(added, u128::from(self.burst - tokens)) as u32;
+            self.tokens.set(tokens + added);
+            if tokens + added >= self.burst {
+                self.updated.set(Instant::now());
+            } else {
+                self.updated.set(self.updated.get() + self.interval * added);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::task::Poll;
+
+    use super::*;
+    use crate::service::{apply, fn_factory, fn_service, ServiceFactory};
+    use crate::util::lazy;
+
+    #[crate::rt_test]
+    async fn test_rate_limit() {
+        let srv = RateLimitService::new(
+            2,
+            Duration::from_millis(100),
+            fn_service(|_: ()| Ready::<_, ()>::Ok(())),
+        );
+        assert_eq!(srv.available(), 2);
+        assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Ready(Ok(())));
+        assert_eq!(srv.call(()).await, Ok(()));
+        assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Ready(Ok(())));
+        assert_eq!(srv.call(()).await, Ok(()));
+        assert_eq!(srv.available(), 0);
+        assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Pending);
+
+        crate::rt::time::sleep(Duration::from_millis(75)).await;
+        assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Ready(Ok(())));
+        assert_eq!(srv.available(), 1);
+
+        assert!(lazy(|cx| srv.poll_shutdown(cx, false)).await.is_ready());
+    }
+
+    #[crate::rt_test]
+    async fn test_burst() {
+        let factory = apply(
+            RateLimit::per_second(100).burst(1),
+            fn_factory(|| async { Ok(fn_service(|_: ()| Ready::<_, ()>::Ok(()))) }),
+        );
+        let srv = factory.new_service(&()).await.unwrap();
+
+        assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Ready(Ok(())));
+        assert_eq!(srv.call(()).await, Ok(()));
+        assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Pending);
+
+        // wait for readiness
+        crate::util::poll_fn(|cx| srv.poll_ready(cx)).await.unwrap();
+        assert_eq!(srv.call(()).await, Ok(()));
+
+        crate::rt::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(srv.available(), 1);
+    }
+
+    #[crate::rt_test]
+    async fn test_token_bucket() {
+        let bucket = TokenBucket::new(Duration::from_millis(100), 2);
+        assert!(bucket.is_full());
+        assert!(bucket.acquire().is_ok());
+        assert!(!bucket.is_full());
+        assert!(bucket.acquire().is_ok());
+        let wait = bucket.acquire().unwrap_err();
+        assert!(wait > Duration::from_millis(0) && wait <= Duration::from_millis(100));
+
+        crate::rt::time::sleep(Duration::from_millis(110)).await;
+        assert_eq!(bucket.available(), 1);
+        assert!(bucket.acquire().is_ok());
+        assert!(bucket.acquire().is_err());
+    }
+}
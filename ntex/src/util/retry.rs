@@ -0,0 +1,432 @@
+//! Service that retries failed requests.
+//!
+//! Failed request is not retried if retry delay exceeds deadline of
+//! outer call context.
+use std::{
+    convert::Infallible, future::Future, pin::Pin, rc::Rc, task::Context, task::Poll,
+    time::Duration,
+};
+
+use nanorand::{WyRand, RNG};
+
+use crate::rt::time::{sleep, Sleep};
+use crate::service::{IntoService, Service, Transform};
+use crate::util::{deadline::CallContext, Ready};
+
+/// Delay between retry attempts
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Backoff {
+    /// Retry immediately
+    None,
+    /// Fixed delay
+    Fixed(Duration),
+    /// Delay doubles after each attempt, up to max delay
+    Exponential { base: Duration, max: Duration },
+}
+
+impl Backoff {
+    /// Delay before specified retry, attempts start from 1
+    pub fn delay(&self, attempt: usize) -> Duration {
+        match self {
+            Backoff::None => Duration::from_millis(0),
+            Backoff::Fixed(delay) => *delay,
+            Backoff::Exponential { base, max } => {
+                let shift = std::cmp::min(attempt.saturating_sub(1), 31) as u32;
+                base.checked_mul(1 << shift)
+                    .map(|delay| std::cmp::min(delay, *max))
+                    .unwrap_or(*max)
+            }
+        }
+    }
+}
+
+/// Retry policy, decides if failed request should be retried
+pub trait RetryPolicy<E> {
+    /// Check if request should be retried after error
+    fn retry(&self, err: &E) -> bool;
+}
+
+impl<E, F> RetryPolicy<E> for F
+where
+    F: Fn(&E) -> bool,
+{
+    fn retry(&self, err: &E) -> bool {
+        (self)(err)
+    }
+}
+
+/// Retry policy that retries on any error
+#[derive(Debug, Copy, Clone)]
+pub struct AnyError;
+
+impl<E> RetryPolicy<E> for AnyError {
+    fn retry(&self, _: &E) -> bool {
+        true
+    }
+}
+
+/// Retry - service factory for service that re-invokes inner service
+/// on retryable errors.
+///
+/// Request must be cloneable. By default any error is retried up to 3 times
+/// without delay.
+#[derive(Debug, Clone)]
+pub struct Retry<P = AnyError> {
+    max_attempts: usize,
+    backoff: Backoff,
+    jitter: bool,
+    policy: P,
+}
+
+impl Retry {
+    /// Create retry transform, `max_attempts` includes initial call
+    pub fn new(max_attempts: usize) -> Self {
+        Retry {
+            max_attempts,
+            backoff: Backoff::None,
+            jitter: false,
+            policy: AnyError,
+        }
+    }
+}
+
+impl Default for Retry {
+    fn default() -> Self {
+        Retry::new(3)
+    }
+}
+
+impl<P> Retry<P> {
+    /// Set delay between attempts
+    pub fn backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Randomize delay between zero and backoff delay
+    pub fn jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Set retry policy
+    pub fn policy<P2>(self, policy: P2) -> Retry<P2> {
+        Retry {
+            policy,
+            max_attempts: self.max_attempts,
+            backoff: self.backoff,
+            jitter: self.jitter,
+        }
+    }
+}
+
+impl<S, P> Transform<S> for Retry<P>
+where
+    S: Service,
+    S::Request: Clone,
+    P: RetryPolicy<S::Error> + Clone,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type InitError = Infallible;
+    type Transform = RetryService<S, P>;
+    type Future = Ready<Self::Transform, Self::InitError>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        Ready::Ok(RetryService {
+            inner: Rc::new(Inner {
+                service,
+                policy: self.policy.clone(),
+                max_attempts: self.max_attempts,
+                backoff: self.backoff,
+                jitter: self.jitter,
+            }),
+        })
+    }
+}
+
+/// Service that re-invokes inner service on retryable errors.
+pub struct RetryService<S, P = AnyError> {
+    inner: Rc<Inner<S, P>>,
+}
+
+struct Inner<S, P> {
+    service: S,
+    policy: P,
+    max_attempts: usize,
+    backoff: Backoff,
+    jitter: bool,
+}
+
+impl<S, P> Inner<S, P> {
+    fn delay(&self, attempt: usize) -> Duration {
+        let delay = self.backoff.delay(attempt);
+        if self.jitter && delay.as_millis() > 0 {
+            let ms = delay.as_millis() as u64;
+            Duration::from_millis(WyRand::new().generate::<u64>() % (ms + 1))
+        } else {
+            delay
+        }
+    }
+}
+
+impl<S> RetryService<S>
+where
+    S: Service,
+    S::Request: Clone,
+{
+    /// Create retry service, retries any error without delay
+    pub fn new<U>(max_attempts: usize, service: U) -> Self
+    where
+        U: IntoService<S>,
+    {
+        RetryService {
+            inner: Rc::new(Inner {
+                max_attempts,
+                service: service.into_service(),
+                policy: AnyError,
+                backoff: Backoff::None,
+                jitter: false,
+            }),
+        }
+    }
+}
+
+impl<S, P> Clone for RetryService<S, P> {
+    fn clone(&self) -> Self {
+        RetryService {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<S, P> Service for RetryService<S, P>
+where
+    S: Service,
+    S::Request: Clone,
+    P: RetryPolicy<S::Error>,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = RetryServiceResponse<S, P>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.service.poll_ready(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.inner.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&self, req: S::Request) -> Self::Future {
+        RetryServiceResponse {
+            state: State::Call {
+                fut: self.inner.service.call(req.clone()),
+            },
+            req,
+            attempt: 1,
+            inner: self.inner.clone(),
+            ctx: CallContext::current(),
+        }
+    }
+}
+
+pin_project_lite::pin_project! {
+    #[doc(hidden)]
+    pub struct RetryServiceResponse<S: Service, P> {
+        #[pin]
+        state: State<S>,
+        req: S::Request,
+        attempt: usize,
+        inner: Rc<Inner<S, P>>,
+        ctx: CallContext,
+    }
+}
+
+pin_project_lite::pin_project! {
+    #[project = StateProject]
+    enum State<S: Service> {
+        Call { #[pin] fut: S::Future },
+        Wait { #[pin] sleep: Sleep },
+        Ready,
+    }
+}
+
+impl<S, P> Future for RetryServiceResponse<S, P>
+where
+    S: Service,
+    S::Request: Clone,
+    P: RetryPolicy<S::Error>,
+{
+    type Output = Result<S::Response, S::Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut this = self.as_mut().project();
+
+        loop {
+            match this.state.as_mut().project() {
+                StateProject::Call { fut } => match this.ctx.scope(|| fut.poll(cx)) {
+                    Poll::Ready(Err(err))
+                        if *this.attempt < this.inner.max_attempts
+                            && this.inner.policy.retry(&err) =>
+                    {
+                        let delay = this.inner.delay(*this.attempt);
+                        if this.ctx.remaining().map(|r| r <= delay).unwrap_or(false) {
+                            log::trace!("Call deadline is reached, do not retry");
+                            return Poll::Ready(Err(err));
+                        }
+                        *this.attempt += 1;
+                        log::trace!(
+                            "Service call failed, retry attempt {}",
+                            this.attempt
+                        );
+                        if delay.as_millis() == 0 {
+                            this.state.set(State::Ready);
+                        } else {
+                            this.state.set(State::Wait {
+                                sleep: sleep(delay),
+                            });
+                        }
+                    }
+                    res => return res,
+                },
+                StateProject::Wait { sleep } => {
+                    if sleep.poll(cx).is_pending() {
+                        return Poll::Pending;
+                    }
+                    this.state.set(State::Ready);
+                }
+                StateProject::Ready => {
+                    if this.inner.service.poll_ready(cx)?.is_pending() {
+                        return Poll::Pending;
+                    }
+                    let fut =
+                        this.ctx.scope(|| this.inner.service.call(this.req.clone()));
+                    this = self.as_mut().project();
+                    this.state.set(State::Call { fut });
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, time::Duration};
+
+    use super::*;
+    use crate::service::{apply, fn_factory, fn_service, ServiceFactory};
+    use crate::util::lazy;
+
+    #[derive(Clone)]
+    struct TestService(Rc<Cell<usize>>, usize);
+
+    impl Service for TestService {
+        type Request = usize;
+        type Response = usize;
+        type Error = usize;
+        type Future = Ready<usize, usize>;
+
+        fn poll_ready(&self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&self, req: usize) -> Self::Future {
+            let cnt = self.0.get() + 1;
+            self.0.set(cnt);
+            if cnt < self.1 {
+                Ready::Err(cnt)
+            } else {
+                Ready::Ok(req)
+            }
+        }
+    }
+
+    #[crate::rt_test]
+    async fn test_retry() {
+        let cnt = Rc::new(Cell::new(0));
+        let srv = RetryService::new(3, TestService(cnt.clone(), 3)).clone();
+        assert!(lazy(|cx| srv.poll_ready(cx)).await.is_ready());
+        assert_eq!(srv.call(10).await, Ok(10));
+        assert_eq!(cnt.get(), 3);
+
+        cnt.set(0);
+        let srv = RetryService::new(3, TestService(cnt.clone(), 5));
+        assert_eq!(srv.call(10).await, Err(3));
+        assert_eq!(cnt.get(), 3);
+    }
+
+    #[crate::rt_test]
+    async fn test_policy() {
+        let cnt = Rc::new(Cell::new(0));
+        let cnt2 = cnt.clone();
+        let factory = apply(
+            Retry::new(5)
+                .backoff(Backoff::Fixed(Duration::from_millis(10)))
+                .policy(|err: &usize| *err < 2),
+            fn_factory(move || {
+                let cnt = cnt2.clone();
+                async move { Ok(TestService(cnt, 5)) }
+            }),
+        );
+        let srv = factory.new_service(&()).await.unwrap();
+        assert_eq!(srv.call(10).await, Err(2));
+        assert_eq!(cnt.get(), 2);
+    }
+
+    #[crate::rt_test]
+    async fn test_jitter() {
+        let cnt = Rc::new(Cell::new(0));
+        let srv = Retry::default()
+            .backoff(Backoff::Exponential {
+                base: Duration::from_millis(5),
+                max: Duration::from_millis(20),
+            })
+            .jitter(true)
+            .new_transform(TestService(cnt.clone(), 3))
+            .await
+            .unwrap();
+        assert_eq!(srv.call(1).await, Ok(1));
+        assert_eq!(cnt.get(), 3);
+
+        let srv = RetryService::new(2, fn_service(|_: ()| async { Err::<(), _>(()) }));
+        assert_eq!(srv.call(()).await, Err(()));
+    }
+
+    #[crate::rt_test]
+    async fn test_deadline() {
+        let cnt = Rc::new(Cell::new(0));
+        let srv = Retry::new(5)
+            .backoff(Backoff::Fixed(Duration::from_millis(25)))
+            .new_transform(TestService(cnt.clone(), 5))
+            .await
+            .unwrap();
+
+        let ctx = CallContext::default().with_timeout(Duration::from_millis(60));
+        let fut = ctx.scope(|| srv.call(1));
+        assert_eq!(ctx.wrap(fut).await, Err(3));
+        assert_eq!(cnt.get(), 3);
+    }
+
+    #[test]
+    fn test_backoff() {
+        let backoff = Backoff::Exponential {
+            base: Duration::from_millis(100),
+            max: Duration::from_secs(1),
+        };
+        assert_eq!(backoff.delay(1), Duration::from_millis(100));
+        assert_eq!(backoff.delay(2), Duration::from_millis(200));
+        assert_eq!(backoff.delay(4), Duration::from_millis(800));
+        assert_eq!(backoff.delay(5), Duration::from_secs(1));
+        assert_eq!(backoff.delay(100), Duration::from_secs(1));
+        assert_eq!(
+            Backoff::Fixed(Duration::from_millis(5)).delay(10),
+            Duration::from_millis(5)
+        );
+        assert_eq!(Backoff::None.delay(1), Duration::from_millis(0));
+    }
+}
@@ -0,0 +1,181 @@
+//! Clonable wrapper for non-clonable services.
+//!
+//! Inner service usually keeps only last waker passed to `poll_ready`, if
+//! several dispatchers share one service instance all of them but last
+//! would never be woken up. `Shared` keeps waker of each clone and wakes
+//! all of them when inner service becomes ready.
+use std::{cell::Cell, rc::Rc, task::Context, task::Poll};
+
+use crate::channel::condition::{Condition, Waiter};
+use crate::service::{IntoService, Service};
+
+/// Clonable wrapper for single service instance
+///
+/// All clones call the same service instance.
+pub struct Shared<S> {
+    inner: Rc<Inner<S>>,
+    waiter: Waiter,
+}
+
+struct Inner<S> {
+    service: S,
+    cond: Condition,
+    pending: Cell<bool>,
+}
+
+impl<S> Shared<S>
+where
+    S: Service,
+{
+    /// Wrap service
+    pub fn new<U: IntoService<S>>(service: U) -> Self {
+        let cond = Condition::new();
+        Shared {
+            waiter: cond.wait(),
+            inner: Rc::new(Inner {
+                cond,
+                service: service.into_service(),
+                pending: Cell::new(false),
+            }),
+        }
+    }
+
+    /// Get reference to inner service
+    pub fn get_ref(&self) -> &S {
+        &self.inner.service
+    }
+}
+
+impl<S> Clone for Shared<S> {
+    fn clone(&self) -> Self {
+        Shared {
+            inner: self.inner.clone(),
+            waiter: self.inner.cond.wait(),
+        }
+    }
+}
+
+impl<S> Drop for Shared<S> {
+    fn drop(&mut self) {
+        // inner service could keep waker of this clone only,
+        // wake other clones so one of them polls inner service
+        if self.inner.pending.get() {
+            self.inner.cond.notify();
+        }
+    }
+}
+
+impl<S> Service for Shared<S>
+where
+    S: Service,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let res = self.inner.service.poll_ready(cx);
+        if res.is_pending() {
+            self.inner.pending.set(true);
+            let _ = self.waiter.poll_ready(cx);
+        } else if self.inner.pending.replace(false) {
+            log::trace!("Shared service is ready, wake up waiters");
+            self.inner.cond.notify();
+        }
+        res
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.inner.service.poll_shutdown(cx, is_error)
+    }
+
+    #[inline]
+    fn call(&self, req: S::Request) -> Self::Future {
+        self.inner.service.call(req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::task::LocalWaker;
+    use crate::util::{lazy, poll_fn, Ready};
+
+    #[derive(Default)]
+    struct TestService {
+        ready: Cell<bool>,
+        waker: LocalWaker,
+    }
+
+    impl Service for TestService {
+        type Request = ();
+        type Response = ();
+        type Error = ();
+        type Future = Ready<(), ()>;
+
+        fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            if self.ready.get() {
+                Poll::Ready(Ok(()))
+            } else {
+                self.waker.register(cx.waker());
+                Poll::Pending
+            }
+        }
+
+        fn call(&self, _: ()) -> Self::Future {
+            Ready::Ok(())
+        }
+    }
+
+    #[crate::rt_test]
+    async fn test_shared() {
+        let srv = Shared::new(TestService::default());
+        let cnt = Rc::new(Cell::new(0));
+
+        for _ in 0..3 {
+            let srv = srv.clone();
+            let cnt = cnt.clone();
+            crate::rt::spawn(async move {
+                poll_fn(|cx| srv.poll_ready(cx)).await.unwrap();
+                cnt.set(cnt.get() + 1);
+            });
+        }
+        crate::rt::time::sleep(Duration::from_millis(25)).await;
+        assert_eq!(cnt.get(), 0);
+
+        srv.get_ref().ready.set(true);
+        srv.get_ref().waker.wake();
+        crate::rt::time::sleep(Duration::from_millis(25)).await;
+        assert_eq!(cnt.get(), 3);
+
+        assert!(lazy(|cx| srv.poll_ready(cx)).await.is_ready());
+        assert!(lazy(|cx| srv.poll_shutdown(cx, false)).await.is_ready());
+        assert_eq!(srv.call(()).await, Ok(()));
+    }
+
+    #[crate::rt_test]
+    async fn test_drop() {
+        let srv = Shared::new(TestService::default());
+        let srv2 = srv.clone();
+        let cnt = Rc::new(Cell::new(0));
+
+        let cnt2 = cnt.clone();
+        let srv3 = srv.clone();
+        crate::rt::spawn(async move {
+            poll_fn(|cx| srv3.poll_ready(cx)).await.unwrap();
+            cnt2.set(cnt2.get() + 1);
+        });
+        crate::rt::time::sleep(Duration::from_millis(25)).await;
+
+        // inner service keeps waker of last poller only
+        assert!(lazy(|cx| srv2.poll_ready(cx)).await.is_pending());
+        drop(srv2);
+        srv.get_ref().ready.set(true);
+        crate::rt::time::sleep(Duration::from_millis(25)).await;
+        assert_eq!(cnt.get(), 1);
+    }
+}
@@ -0,0 +1,276 @@
+//! Graceful shutdown helper for composed services.
+//!
+//! `ShutdownExt::shutdown()` drives `poll_shutdown` of composed service until
+//! it completes or deadline is reached. Composed service is opaque, to find
+//! out which layer stalled wrap layers with `ShutdownTrace` transform,
+//! traced layers that are still pending are reported in `ShutdownError`.
+use std::{
+    cell::RefCell, convert::Infallible, fmt, future::Future, pin::Pin, rc::Rc,
+    task::Context, task::Poll, time::Duration,
+};
+
+use crate::rt::time::{sleep, Sleep};
+use crate::service::{IntoService, Service, Transform};
+use crate::util::Ready;
+
+thread_local! {
+    static PENDING: RefCell<Option<Vec<Rc<str>>>> = RefCell::new(None);
+}
+
+/// Shutdown error, service did not complete shutdown within deadline
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShutdownError {
+    pending: Vec<Rc<str>>,
+}
+
+impl ShutdownError {
+    /// Innermost traced layer that did not complete shutdown
+    pub fn stalled(&self) -> Option<&str> {
+        self.pending.first().map(|s| s.as_ref())
+    }
+
+    /// All traced layers that did not complete shutdown, from inner to outer
+    pub fn pending(&self) -> impl Iterator<Item = &str> {
+        self.pending.iter().map(|s| s.as_ref())
+    }
+}
+
+impl fmt::Display for ShutdownError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(name) = self.stalled() {
+            write!(f, "Service shutdown timeout, stalled layer: {}", name)
+        } else {
+            write!(f, "Service shutdown timeout")
+        }
+    }
+}
+
+impl std::error::Error for ShutdownError {}
+
+/// Extension trait that drives service shutdown
+pub trait ShutdownExt: Service {
+    /// Shutdown service, wait until shutdown completes or timeout elapses
+    fn shutdown(&self, timeout: Duration, is_error: bool) -> Shutdown<'_, Self>
+    where
+        Self: Sized,
+    {
+        Shutdown {
+            srv: self,
+            is_error,
+            sleep: sleep(timeout),
+            pending: Vec::new(),
+        }
+    }
+}
+
+impl<S: Service> ShutdownExt for S {}
+
+pin_project_lite::pin_project! {
+    /// Service shutdown future
+    pub struct Shutdown<'a, S> {
+        srv: &'a S,
+        is_error: bool,
+        #[pin]
+        sleep: Sleep,
+        pending: Vec<Rc<str>>,
+    }
+}
+
+impl<'a, S: Service> Future for Shutdown<'a, S> {
+    type Output = Result<(), ShutdownError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        let prev = PENDING.with(|p| p.replace(Some(Vec::new())));
+        let res = this.srv.poll_shutdown(cx, *this.is_error);
+        let pending = PENDING.with(|p| p.replace(prev)).unwrap_or_default();
+
+        if res.is_ready() {
+            return Poll::Ready(Ok(()));
+        }
+        *this.pending = pending;
+
+        match this.sleep.poll(cx) {
+            Poll::Ready(_) => {
+                log::trace!("Service shutdown timeout, pending: {:?}", this.pending);
+                Poll::Ready(Err(ShutdownError {
+                    pending: std::mem::take(this.pending),
+                }))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// ShutdownTrace - service factory for service that reports pending shutdown
+/// to `ShutdownExt::shutdown()`.
+#[derive(Debug, Clone)]
+pub struct ShutdownTrace {
+    name: Rc<str>,
+}
+
+impl ShutdownTrace {
+    /// Create transform, layer is reported with specified name
+    pub fn new<T: AsRef<str>>(name: T) -> Self {
+        ShutdownTrace {
+            name: name.as_ref().into(),
+        }
+    }
+}
+
+impl<S> Transform<S> for ShutdownTrace
+where
+    S: Service,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type InitError = Infallible;
+    type Transform = ShutdownTraceService<S>;
+    type Future = Ready<Self::Transform, Self::InitError>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        Ready::Ok(ShutdownTraceService {
+            service,
+            name: self.name.clone(),
+        })
+    }
+}
+
+/// Service that reports pending shutdown to `ShutdownExt::shutdown()`
+pub struct ShutdownTraceService<S> {
+    service: S,
+    name: Rc<str>,
+}
+
+impl<S> ShutdownTraceService<S>
+where
+    S: Service,
+{
+    /// Create service, layer is reported with specified name
+    pub fn new<T, U>(name: T, service: U) -> Self
+    where
+        T: AsRef<str>,
+        U: IntoService<S>,
+    {
+        ShutdownTraceService {
+            name: name.as_ref().into(),
+            service: service.into_service(),
+        }
+    }
+}
+
+impl<S> Service for ShutdownTraceService<S>
+where
+    S: Service,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        let res = self.service.poll_shutdown(cx, is_error);
+        if res.is_pending() {
+            PENDING.with(|p| {
+                if let Some(ref mut pending) = *p.borrow_mut() {
+                    pending.push(self.name.clone());
+                }
+            });
+        }
+        res
+    }
+
+    #[inline]
+    fn call(&self, req: S::Request) -> Self::Future {
+        self.service.call(req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+    use crate::service::{apply, fn_factory, fn_service, ServiceFactory};
+    use crate::util::lazy;
+
+    struct TestService(Rc<Cell<bool>>);
+
+    impl Service for TestService {
+        type Request = ();
+        type Response = ();
+        type Error = ();
+        type Future = Ready<(), ()>;
+
+        fn poll_ready(&self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_shutdown(&self, _: &mut Context<'_>, _: bool) -> Poll<()> {
+            if self.0.get() {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        }
+
+        fn call(&self, _: ()) -> Self::Future {
+            Ready::Ok(())
+        }
+    }
+
+    #[crate::rt_test]
+    async fn test_shutdown() {
+        let done = Rc::new(Cell::new(false));
+        let done2 = done.clone();
+        let factory = apply(
+            ShutdownTrace::new("outer"),
+            apply(
+                ShutdownTrace::new("inner"),
+                fn_factory(move || {
+                    let done = done2.clone();
+                    async move { Ok(TestService(done)) }
+                }),
+            ),
+        );
+        let srv = factory.new_service(()).await.unwrap();
+        assert!(lazy(|cx| srv.poll_ready(cx)).await.is_ready());
+        assert_eq!(srv.call(()).await, Ok(()));
+
+        let err = srv
+            .shutdown(Duration::from_millis(25), false)
+            .await
+            .unwrap_err();
+        assert_eq!(err.stalled(), Some("inner"));
+        assert_eq!(err.pending().collect::<Vec<_>>(), vec!["inner", "outer"]);
+        assert_eq!(
+            err.to_string(),
+            "Service shutdown timeout, stalled layer: inner"
+        );
+
+        done.set(true);
+        assert!(srv.shutdown(Duration::from_millis(25), false).await.is_ok());
+    }
+
+    #[crate::rt_test]
+    async fn test_untraced() {
+        let srv =
+            ShutdownTraceService::new("srv", fn_service(|_: ()| Ready::<_, ()>::Ok(())));
+        assert!(srv.shutdown(Duration::from_millis(25), true).await.is_ok());
+
+        let srv = TestService(Rc::new(Cell::new(false)));
+        let err = srv
+            .shutdown(Duration::from_millis(25), false)
+            .await
+            .unwrap_err();
+        assert_eq!(err.stalled(), None);
+        assert_eq!(err.to_string(), "Service shutdown timeout");
+    }
+}
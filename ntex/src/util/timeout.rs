@@ -1,12 +1,13 @@
 //! Service that applies a timeout to requests.
 //!
 //! If the response does not complete within the specified timeout, the response
-//! will be aborted.
+//! will be aborted. Timeout respects deadline of outer call context, nested
+//! timeouts never extend outer deadline.
 use std::{fmt, future::Future, marker, pin::Pin, task::Context, task::Poll, time};
 
-use crate::rt::time::{sleep, Sleep};
+use crate::rt::time::{sleep_until, Sleep};
 use crate::service::{IntoService, Service, Transform};
-use crate::util::{Either, Ready};
+use crate::util::{deadline::CallContext, Either, Ready};
 
 const ZERO: time::Duration = time::Duration::from_millis(0);
 
@@ -27,6 +28,37 @@ pub enum TimeoutError<E> {
     Timeout,
 }
 
+impl<E> TimeoutError<E> {
+    /// Check if error is caused by service call timeout
+    pub fn is_timeout(&self) -> bool {
+        matches!(self, TimeoutError::Timeout)
+    }
+
+    /// Map service error
+    pub fn map<E2, F>(self, f: F) -> TimeoutError<E2>
+    where
+        F: FnOnce(E) -> E2,
+    {
+        match self {
+            TimeoutError::Service(e) => TimeoutError::Service(f(e)),
+            TimeoutError::Timeout => TimeoutError::Timeout,
+        }
+    }
+
+    /// Convert to service error, timeout is converted with provided function
+    ///
+    /// Useful with `map_err` combinator, if pipeline uses single error type.
+    pub fn into_error<F>(self, f: F) -> E
+    where
+        F: FnOnce() -> E,
+    {
+        match self {
+            TimeoutError::Service(e) => e,
+            TimeoutError::Timeout => f(),
+        }
+    }
+}
+
 impl<E> From<E> for TimeoutError<E> {
     fn from(err: E) -> Self {
         TimeoutError::Service(err)
@@ -147,9 +179,11 @@ where
                 fut: self.service.call(request),
             })
         } else {
+            let ctx = CallContext::current().with_timeout(self.timeout);
             Either::Left(TimeoutServiceResponse {
-                fut: self.service.call(request),
-                sleep: Box::pin(sleep(self.timeout)),
+                fut: ctx.scope(|| self.service.call(request)),
+                sleep: Box::pin(sleep_until(ctx.deadline().unwrap())),
+                ctx,
             })
         }
     }
@@ -163,6 +197,7 @@ pin_project_lite::pin_project! {
         #[pin]
         fut: T::Future,
         sleep: Pin<Box<Sleep>>,
+        ctx: CallContext,
     }
 }
 
@@ -176,7 +211,8 @@ where
         let mut this = self.project();
 
         // First, try polling the future
-        match this.fut.poll(cx) {
+        let fut = this.fut;
+        match this.ctx.scope(|| fut.poll(cx)) {
             Poll::Ready(Ok(v)) => return Poll::Ready(Ok(v)),
             Poll::Ready(Err(e)) => return Poll::Ready(Err(TimeoutError::Service(e))),
             Poll::Pending => {}
@@ -307,6 +343,61 @@ mod tests {
         assert_eq!(res, TimeoutError::Timeout);
     }
 
+    #[crate::rt_test]
+    async fn test_map_err() {
+        #[derive(Debug, PartialEq)]
+        enum Error {
+            Service(SrvError),
+            Timeout,
+        }
+
+        let timeout = apply(
+            Timeout::new(Duration::from_millis(50)),
+            fn_factory(|| async {
+                Ok::<_, ()>(SleepService(Duration::from_millis(500)))
+            }),
+        )
+        .map_err(|e: TimeoutError<SrvError>| {
+            e.map(Error::Service).into_error(|| Error::Timeout)
+        });
+        let srv = timeout.new_service(&()).await.unwrap();
+        assert_eq!(srv.call(()).await, Err(Error::Timeout));
+
+        let err = TimeoutError::Service(SrvError);
+        assert!(!err.is_timeout());
+        assert_eq!(
+            err.map(Error::Service).into_error(|| Error::Timeout),
+            Error::Service(SrvError)
+        );
+        assert!(TimeoutError::<SrvError>::Timeout.is_timeout());
+    }
+
+    #[crate::rt_test]
+    async fn test_nested_timeout() {
+        // inner timeout does not extend outer deadline
+        let srv = TimeoutService::new(
+            Duration::from_millis(50),
+            TimeoutService::new(
+                Duration::from_secs(1),
+                SleepService(Duration::from_secs(5)),
+            ),
+        );
+        assert_eq!(
+            srv.call(()).await,
+            Err(TimeoutError::Service(TimeoutError::Timeout))
+        );
+
+        let srv = TimeoutService::new(
+            Duration::from_millis(50),
+            crate::service::fn_service(|_: ()| async {
+                Ok::<_, ()>(CallContext::current().deadline())
+            }),
+        );
+        let ctx = CallContext::default().with_timeout(Duration::from_millis(10));
+        let deadline = ctx.scope(|| srv.call(())).await.unwrap();
+        assert_eq!(deadline, ctx.deadline());
+    }
+
     #[test]
     fn test_error() {
         let err1 = TimeoutError::<SrvError>::Timeout;
@@ -0,0 +1,159 @@
+//! Adapters between ntex and tower services.
+//!
+//! `TowerCompat` wraps ntex service and implements `tower::Service`.
+//! `NtexCompat` wraps tower service and implements ntex `Service`, tower
+//! service requires mutable access so it is stored in `RefCell`.
+//! Service errors are passed through, use `map_err` to box them.
+use std::{cell::RefCell, task::Context, task::Poll};
+
+use crate::service::{IntoService, Service};
+
+/// Boxed error type, commonly used by tower middlewares
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Wraps ntex service and implements `tower::Service`
+#[derive(Debug, Clone)]
+pub struct TowerCompat<S> {
+    service: S,
+}
+
+impl<S: Service> TowerCompat<S> {
+    /// Wrap ntex service
+    pub fn new<U: IntoService<S>>(service: U) -> Self {
+        TowerCompat {
+            service: service.into_service(),
+        }
+    }
+
+    /// Get reference to inner service
+    pub fn get_ref(&self) -> &S {
+        &self.service
+    }
+
+    /// Consume adapter, returns inner service
+    pub fn into_inner(self) -> S {
+        self.service
+    }
+}
+
+impl<S: Service> tower_service::Service<S::Request> for TowerCompat<S> {
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    #[inline]
+    fn call(&mut self, req: S::Request) -> Self::Future {
+        self.service.call(req)
+    }
+}
+
+/// Wraps tower service and implements ntex `Service`
+pub struct NtexCompat<T, Req> {
+    service: RefCell<T>,
+    _t: std::marker::PhantomData<Req>,
+}
+
+impl<T, Req> NtexCompat<T, Req>
+where
+    T: tower_service::Service<Req>,
+{
+    /// Wrap tower service
+    pub fn new(service: T) -> Self {
+        NtexCompat {
+            service: RefCell::new(service),
+            _t: std::marker::PhantomData,
+        }
+    }
+
+    /// Consume adapter, returns inner service
+    pub fn into_inner(self) -> T {
+        self.service.into_inner()
+    }
+}
+
+impl<T: Clone, Req> Clone for NtexCompat<T, Req> {
+    fn clone(&self) -> Self {
+        NtexCompat {
+            service: RefCell::new(self.service.borrow().clone()),
+            _t: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<T, Req> Service for NtexCompat<T, Req>
+where
+    T: tower_service::Service<Req>,
+{
+    type Request = Req;
+    type Response = T::Response;
+    type Error = T::Error;
+    type Future = T::Future;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.borrow_mut().poll_ready(cx)
+    }
+
+    #[inline]
+    fn call(&self, req: Req) -> Self::Future {
+        self.service.borrow_mut().call(req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::fn_service;
+    use crate::util::{lazy, Ready};
+
+    struct Tower(usize);
+
+    impl tower_service::Service<usize> for Tower {
+        type Response = usize;
+        type Error = BoxError;
+        type Future = Ready<usize, BoxError>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), BoxError>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, req: usize) -> Self::Future {
+            self.0 += 1;
+            if req == 0 {
+                Ready::Err("zero".into())
+            } else {
+                Ready::Ok(req + self.0)
+            }
+        }
+    }
+
+    #[crate::rt_test]
+    async fn test_tower_compat() {
+        use tower_service::Service as _;
+
+        let mut srv =
+            TowerCompat::new(fn_service(|req: usize| Ready::<_, ()>::Ok(req * 2)))
+                .clone();
+        assert!(lazy(|cx| srv.poll_ready(cx)).await.is_ready());
+        assert_eq!(srv.call(2).await, Ok(4));
+        assert_eq!(srv.get_ref().call(3).await, Ok(6));
+        let _ = srv.into_inner();
+    }
+
+    #[crate::rt_test]
+    async fn test_ntex_compat() {
+        let srv = NtexCompat::new(Tower(0));
+        assert!(lazy(|cx| Service::poll_ready(&srv, cx)).await.is_ready());
+        assert_eq!(Service::call(&srv, 2).await.unwrap(), 3);
+        assert_eq!(
+            Service::call(&srv, 0).await.unwrap_err().to_string(),
+            "zero"
+        );
+        assert_eq!(srv.into_inner().0, 2);
+    }
+}
@@ -1,26 +1,29 @@
-use std::{cell::RefCell, fmt, future::Future, pin::Pin, rc::Rc};
+use std::{cell::RefCell, fmt, future::Future, rc::Rc};
 
 use crate::http::Request;
-use crate::router::ResourceDef;
+use crate::router::{IntoPattern, Matcher, ResourceDef};
 use crate::service::boxed::{self, BoxServiceFactory};
-use crate::service::{apply, apply_fn_factory, pipeline_factory};
+use crate::service::{apply, apply_fn_factory, fn_service, pipeline_factory};
 use crate::service::{IntoServiceFactory, Service, ServiceFactory, Transform};
 use crate::util::{Either, Extensions, Ready};
 
-use super::app_service::{AppEntry, AppFactory, AppRoutingFactory};
+use super::app_service::{AppEntry, AppFactory, AppRoutingFactory, MatcherFactory};
 use super::config::{AppConfig, ServiceConfig};
+use super::module::{Mount, WebModule};
+#[cfg(feature = "openapi")]
+use super::openapi::{OpenApi, OpenApiService};
 use super::request::WebRequest;
 use super::resource::Resource;
 use super::response::WebResponse;
 use super::route::Route;
-use super::service::{AppServiceFactory, ServiceFactoryWrapper, WebServiceFactory};
-use super::types::data::{Data, DataFactory};
+use super::service::{
+    AppServiceFactory, HostService, ServiceFactoryWrapper, WebServiceFactory,
+};
+use super::types::data::{Data, DataFactory, DataInit, FnDataFactory};
 use super::{DefaultError, ErrorRenderer};
 
 type HttpNewService<Err: ErrorRenderer> =
     BoxServiceFactory<(), WebRequest<Err>, WebResponse, Err::Container, ()>;
-type FnDataFactory =
-    Box<dyn Fn() -> Pin<Box<dyn Future<Output = Result<Box<dyn DataFactory>, ()>>>>>;
 
 /// Application builder - structure that follows the builder pattern
 /// for building application instances.
@@ -35,6 +38,8 @@ pub struct App<T, Err: ErrorRenderer = DefaultError> {
     extensions: Extensions,
     error_renderer: Err,
     case_insensitive: bool,
+    matcher: Option<MatcherFactory>,
+    method_not_allowed: bool,
 }
 
 impl App<AppEntry<DefaultError>, DefaultError> {
@@ -52,6 +57,8 @@ impl App<AppEntry<DefaultError>, DefaultError> {
             extensions: Extensions::new(),
             error_renderer: DefaultError,
             case_insensitive: false,
+            matcher: None,
+            method_not_allowed: true,
         }
     }
 }
@@ -71,6 +78,8 @@ impl<Err: ErrorRenderer> App<AppEntry<Err>, Err> {
             extensions: Extensions::new(),
             error_renderer: err,
             case_insensitive: false,
+            matcher: None,
+            method_not_allowed: true,
         }
     }
 }
@@ -125,28 +134,22 @@ where
     /// Set application data factory. This function is
     /// similar to `.data()` but it accepts data factory. Data object get
     /// constructed asynchronously during application initialization.
-    pub fn data_factory<F, Out, D, E>(mut self, data: F) -> Self
+    pub fn data_factory<F, Out, D, E>(self, data: F) -> Self
     where
         F: Fn() -> Out + 'static,
         Out: Future<Output = Result<D, E>> + 'static,
         D: 'static,
         E: std::fmt::Debug,
     {
-        self.data_factories.push(Box::new(move || {
-            let fut = data();
-            Box::pin(async move {
-                match fut.await {
-                    Err(e) => {
-                        log::error!("Cannot construct data instance: {:?}", e);
-                        Err(())
-                    }
-                    Ok(data) => {
-                        let data: Box<dyn DataFactory> = Box::new(Data::new(data));
-                        Ok(data)
-                    }
-                }
-            })
-        }));
+        self.data_init(Data::from_fn(data))
+    }
+
+    /// Set application data initializer.
+    ///
+    /// Initializer is created with `Data::from_fn()` method, data object
+    /// get constructed asynchronously during application initialization.
+    pub fn data_init<D: 'static>(mut self, init: DataInit<D>) -> Self {
+        self.data_factories.push(init.into_factory());
         self
     }
 
@@ -244,6 +247,58 @@ where
         self
     }
 
+    /// Mount module to a path.
+    ///
+    /// Module is configured with the scope for specified path, see
+    /// [`WebModule`](trait.WebModule.html) for details.
+    pub fn mount<P, M>(self, path: P, module: M) -> Self
+    where
+        P: IntoPattern,
+        M: WebModule<Err> + 'static,
+    {
+        self.service(Mount::new(path, module))
+    }
+
+    /// Register http service that handles requests only for specified host.
+    ///
+    /// Host name could start with wildcard, i.e. `*.example.com` matches
+    /// any subdomain of `example.com`. Host guard is added to every service
+    /// registered by the factory, requests for other hosts are handled by
+    /// the rest of application's services. See
+    /// [`guard::Host`](guard/fn.Host.html) for details.
+    ///
+    /// ```rust
+    /// use ntex::web::{self, App, HttpResponse};
+    ///
+    /// fn main() {
+    ///     let app = App::new()
+    ///         .service_on_host(
+    ///             "api.example.com",
+    ///             web::scope("/v1").route("/users", web::get().to(|| async { HttpResponse::Ok() })),
+    ///         )
+    ///         .service_on_host(
+    ///             "*.example.com",
+    ///             web::resource("/").to(|| async { HttpResponse::Ok() }),
+    ///         );
+    /// }
+    /// ```
+    pub fn service_on_host<F>(self, host: &str, factory: F) -> Self
+    where
+        F: WebServiceFactory<Err> + 'static,
+    {
+        self.service(HostService::new(host, factory))
+    }
+
+    #[cfg(feature = "openapi")]
+    /// Serve OpenAPI document at specified path.
+    ///
+    /// Document describes all routes registered with `Route::to_api()`
+    /// or `Route::operation()`, see [`openapi`](openapi/index.html) module
+    /// for details.
+    pub fn openapi(self, path: &str, api: OpenApi) -> Self {
+        self.service(OpenApiService::new(path, api))
+    }
+
     /// Default service to be used if no matching resource could be found.
     ///
     /// It is possible to use services like `Resource`, `Route`.
@@ -397,6 +452,8 @@ where
             extensions: self.extensions,
             error_renderer: self.error_renderer,
             case_insensitive: self.case_insensitive,
+            matcher: self.matcher,
+            method_not_allowed: self.method_not_allowed,
         }
     }
 
@@ -462,6 +519,8 @@ where
             extensions: self.extensions,
             error_renderer: self.error_renderer,
             case_insensitive: self.case_insensitive,
+            matcher: self.matcher,
+            method_not_allowed: self.method_not_allowed,
         }
     }
 
@@ -523,6 +582,129 @@ where
             extensions: self.extensions,
             error_renderer: self.error_renderer,
             case_insensitive: self.case_insensitive,
+            matcher: self.matcher,
+            method_not_allowed: self.method_not_allowed,
+        }
+    }
+
+    /// Register async function that runs for every request before
+    /// application routing.
+    ///
+    /// This is lightweight alternative to `App::wrap()` for simple
+    /// request modifications.
+    ///
+    /// ```rust
+    /// use ntex::http::header::{HeaderValue, ACCEPT};
+    /// use ntex::web::{self, App};
+    ///
+    /// fn main() {
+    ///     let app = App::new()
+    ///         .map_request(|mut req| async move {
+    ///             req.headers_mut()
+    ///                 .insert(ACCEPT, HeaderValue::from_static("application/json"));
+    ///             req
+    ///         })
+    ///         .route("/index.html", web::get().to(|| async { "Welcome!" }));
+    /// }
+    /// ```
+    pub fn map_request<F, R>(
+        self,
+        f: F,
+    ) -> App<
+        impl ServiceFactory<
+            Config = (),
+            Request = WebRequest<Err>,
+            Response = WebResponse,
+            Error = Err::Container,
+            InitError = (),
+        >,
+        Err,
+    >
+    where
+        F: Fn(WebRequest<Err>) -> R + Clone + 'static,
+        R: Future<Output = WebRequest<Err>> + 'static,
+    {
+        let endpoint = pipeline_factory(fn_service(move |req| {
+            let fut = f(req);
+            async move { Ok(fut.await) }
+        }))
+        .and_then(self.endpoint);
+
+        App {
+            endpoint,
+            data: self.data,
+            data_factories: self.data_factories,
+            services: self.services,
+            default: self.default,
+            factory_ref: self.factory_ref,
+            external: self.external,
+            extensions: self.extensions,
+            error_renderer: self.error_renderer,
+            case_insensitive: self.case_insensitive,
+            matcher: self.matcher,
+            method_not_allowed: self.method_not_allowed,
+        }
+    }
+
+    /// Register async function that runs for every response produced
+    /// by the application.
+    ///
+    /// This is lightweight alternative to `App::wrap()` for simple
+    /// response modifications, like adding security headers.
+    /// Function is not called if application returns error.
+    ///
+    /// ```rust
+    /// use ntex::http::header::{HeaderName, HeaderValue};
+    /// use ntex::web::{self, App};
+    ///
+    /// fn main() {
+    ///     let app = App::new()
+    ///         .map_response(|mut res| async move {
+    ///             res.headers_mut().insert(
+    ///                 HeaderName::from_static("x-frame-options"),
+    ///                 HeaderValue::from_static("DENY"),
+    ///             );
+    ///             res
+    ///         })
+    ///         .route("/index.html", web::get().to(|| async { "Welcome!" }));
+    /// }
+    /// ```
+    pub fn map_response<F, R>(
+        self,
+        f: F,
+    ) -> App<
+        impl ServiceFactory<
+            Config = (),
+            Request = WebRequest<Err>,
+            Response = WebResponse,
+            Error = Err::Container,
+            InitError = (),
+        >,
+        Err,
+    >
+    where
+        F: Fn(WebResponse) -> R + Clone + 'static,
+        R: Future<Output = WebResponse> + 'static,
+    {
+        let endpoint =
+            pipeline_factory(self.endpoint).and_then(fn_service(move |res| {
+                let fut = f(res);
+                async move { Ok(fut.await) }
+            }));
+
+        App {
+            endpoint,
+            data: self.data,
+            data_factories: self.data_factories,
+            services: self.services,
+            default: self.default,
+            factory_ref: self.factory_ref,
+            external: self.external,
+            extensions: self.extensions,
+            error_renderer: self.error_renderer,
+            case_insensitive: self.case_insensitive,
+            matcher: self.matcher,
+            method_not_allowed: self.method_not_allowed,
         }
     }
 
@@ -534,6 +716,52 @@ where
         self
     }
 
+    /// Use custom resource matching backend for application router.
+    ///
+    /// Factory is called for each application instance. Matcher is used
+    /// by top level application router only, nested scopes use default
+    /// matcher.
+    ///
+    /// Segment trie matcher for large number of static resources is
+    /// available with `router-trie` feature.
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "router-trie")]
+    /// # fn main() {
+    /// use ntex::router::TrieMatcher;
+    /// use ntex::web::{self, App, HttpResponse};
+    ///
+    /// let mut app = App::new().router_matcher(TrieMatcher::default);
+    /// for idx in 0..1000 {
+    ///     app = app.service(
+    ///         web::resource(format!("/page{}.html", idx))
+    ///             .to(|| async { HttpResponse::Ok() }),
+    ///     );
+    /// }
+    /// # }
+    /// # #[cfg(not(feature = "router-trie"))]
+    /// # fn main() {}
+    /// ```
+    pub fn router_matcher<F, U>(mut self, f: F) -> Self
+    where
+        F: Fn() -> U + 'static,
+        U: Matcher + 'static,
+    {
+        self.matcher = Some(Rc::new(move || Box::new(f())));
+        self
+    }
+
+    /// Disable automatic *405 Method Not Allowed* responses.
+    ///
+    /// By default, if request path matches registered resources but request
+    /// method does not, router responds with *405* and `Allow` header that
+    /// lists allowed methods. With this option such requests are handled
+    /// by default service. Setting applies to all nested scopes.
+    pub fn disable_method_not_allowed(mut self) -> Self {
+        self.method_not_allowed = false;
+        self
+    }
+
     /// Construct service factory with default `AppConfig`, suitable for `http::HttpService`.
     ///
     /// ```rust,no_run
@@ -619,6 +847,8 @@ where
             factory_ref: self.factory_ref,
             extensions: RefCell::new(Some(self.extensions)),
             case_insensitive: self.case_insensitive,
+            matcher: self.matcher,
+            method_not_allowed: self.method_not_allowed,
         }
     }
 }
@@ -631,7 +861,7 @@ mod tests {
     use crate::web::middleware::DefaultHeaders;
     use crate::web::request::WebRequest;
     use crate::web::test::{call_service, init_service, read_body, TestRequest};
-    use crate::web::{self, DefaultError, HttpRequest, HttpResponse};
+    use crate::web::{self, guard, DefaultError, HttpRequest, HttpResponse};
     use crate::{fn_service, util::Bytes, Service};
 
     #[crate::rt_test]
@@ -832,6 +1062,60 @@ mod tests {
         );
     }
 
+    #[crate::rt_test]
+    async fn test_method_not_allowed() {
+        let srv = init_service(
+            App::new()
+                .route("/test", web::get().to(|| async { HttpResponse::Ok() }))
+                .route("/test", web::post().to(|| async { HttpResponse::Ok() }))
+                .route(
+                    "/test",
+                    web::put()
+                        .guard(guard::Header("x-token", "secret"))
+                        .to(|| async { HttpResponse::Ok() }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/test")
+            .method(Method::DELETE)
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(
+            resp.headers().get(header::ALLOW).unwrap(),
+            HeaderValue::from_static("GET, POST")
+        );
+
+        let req = TestRequest::with_uri("/test")
+            .method(Method::DELETE)
+            .header("x-token", "secret")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(
+            resp.headers().get(header::ALLOW).unwrap(),
+            HeaderValue::from_static("GET, POST, PUT")
+        );
+
+        let req = TestRequest::with_uri("/unknown")
+            .method(Method::DELETE)
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let srv = init_service(
+            App::new()
+                .disable_method_not_allowed()
+                .route("/test", web::get().to(|| async { HttpResponse::Ok() })),
+        )
+        .await;
+        let req = TestRequest::with_uri("/test")
+            .method(Method::DELETE)
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
     #[crate::rt_test]
     async fn test_case_insensitive_router() {
         let srv = init_service(
@@ -849,6 +1133,109 @@ mod tests {
         assert_eq!(resp.status(), StatusCode::OK);
     }
 
+    #[crate::rt_test]
+    async fn test_map_request_response() {
+        let srv = init_service(
+            App::new()
+                .map_request(|mut req| async move {
+                    req.headers_mut()
+                        .insert(header::ACCEPT, HeaderValue::from_static("text/plain"));
+                    req
+                })
+                .map_response(|mut res| async move {
+                    res.headers_mut().insert(
+                        header::CONTENT_TYPE,
+                        HeaderValue::from_static("application/json"),
+                    );
+                    res
+                })
+                .route(
+                    "/test",
+                    web::get().to(|req: HttpRequest| async move {
+                        HttpResponse::Ok().body(
+                            req.headers()
+                                .get(header::ACCEPT)
+                                .unwrap()
+                                .to_str()
+                                .unwrap()
+                                .to_string(),
+                        )
+                    }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/test").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            HeaderValue::from_static("application/json")
+        );
+        assert_eq!(read_body(resp).await, Bytes::from_static(b"text/plain"));
+
+        let req = TestRequest::with_uri("/unknown").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            HeaderValue::from_static("application/json")
+        );
+    }
+
+    #[crate::rt_test]
+    async fn test_router_matcher() {
+        use std::{cell::Cell, rc::Rc};
+
+        // candidates are resources with the same number of path segments
+        struct SegMatcher(Vec<(usize, usize)>, Rc<Cell<usize>>);
+
+        impl Matcher for SegMatcher {
+            fn insert(&mut self, rdef: &ResourceDef, idx: usize) {
+                self.0.push((rdef.pattern().matches('/').count(), idx));
+            }
+
+            fn find(&self, path: &str, f: &mut dyn FnMut(usize) -> bool) {
+                self.1.set(self.1.get() + 1);
+                let len = path.matches('/').count();
+                for (_, idx) in self.0.iter().filter(|(l, _)| *l == len) {
+                    if f(*idx) {
+                        return;
+                    }
+                }
+            }
+        }
+
+        let calls = Rc::new(Cell::new(0));
+        let calls2 = calls.clone();
+        let srv = init_service(
+            App::new()
+                .router_matcher(move || SegMatcher(Vec::new(), calls2.clone()))
+                .route("/test", web::get().to(|| async { HttpResponse::Ok() }))
+                .route(
+                    "/test/{id}",
+                    web::get().to(|id: web::types::Path<String>| async move {
+                        HttpResponse::Ok().body(id.into_inner())
+                    }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/test").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = TestRequest::with_uri("/test/10").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(read_body(resp).await, Bytes::from_static(b"10"));
+
+        let req = TestRequest::with_uri("/test/10/11").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert!(calls.get() >= 3);
+    }
+
     #[crate::rt_test]
     async fn test_external_resource() {
         let srv = init_service(
@@ -871,4 +1258,47 @@ mod tests {
         let body = read_body(resp).await;
         assert_eq!(body, Bytes::from_static(b"https://youtube.com/watch/12345"));
     }
+
+    #[crate::rt_test]
+    async fn test_service_on_host() {
+        let srv = init_service(
+            App::new()
+                .service_on_host(
+                    "api.example.com",
+                    (
+                        web::resource("/").to(|| async { "api" }),
+                        web::scope("/v1")
+                            .route("/users", web::get().to(|| async { "users" })),
+                    ),
+                )
+                .service_on_host(
+                    "*.example.com",
+                    web::resource("/").to(|| async { "wildcard" }),
+                )
+                .route("/", web::get().to(|| async { "default" })),
+        )
+        .await;
+
+        for (host, path, body) in &[
+            ("api.example.com", "/", "api"),
+            ("API.example.com:8080", "/", "api"),
+            ("api.example.com", "/v1/users", "users"),
+            ("www.example.com", "/", "wildcard"),
+            ("example.com", "/", "default"),
+            ("localhost", "/", "default"),
+        ] {
+            let req = TestRequest::with_uri(path)
+                .header(header::HOST, HeaderValue::from_static(host))
+                .to_request();
+            let resp = call_service(&srv, req).await;
+            assert_eq!(resp.status(), StatusCode::OK, "{}", host);
+            assert_eq!(read_body(resp).await, Bytes::from_static(body.as_bytes()));
+        }
+
+        let req = TestRequest::with_uri("/v1/users")
+            .header(header::HOST, HeaderValue::from_static("www.example.com"))
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
 }
@@ -1,8 +1,8 @@
 use std::task::{Context, Poll};
 use std::{cell::RefCell, future::Future, marker::PhantomData, pin::Pin, rc::Rc};
 
-use crate::http::{Request, Response};
-use crate::router::{Path, ResourceDef, ResourceInfo, Router};
+use crate::http::{Method, Request, Response};
+use crate::router::{Matcher, Path, ResourceDef, ResourceInfo, Router};
 use crate::service::boxed::{self, BoxService, BoxServiceFactory};
 use crate::util::Extensions;
 use crate::{fn_service, Service, ServiceFactory};
@@ -12,10 +12,11 @@ use super::error::ErrorRenderer;
 use super::guard::Guard;
 use super::httprequest::{HttpRequest, HttpRequestPool};
 use super::request::WebRequest;
+use super::resource::method_not_allowed;
 use super::response::WebResponse;
 use super::rmap::ResourceMap;
 use super::service::{AppServiceFactory, WebServiceConfig};
-use super::types::data::DataFactory;
+use super::types::data::{DataFactory, FnDataFactory};
 
 type Guards = Vec<Box<dyn Guard>>;
 type HttpService<Err: ErrorRenderer> =
@@ -24,8 +25,7 @@ type HttpNewService<Err: ErrorRenderer> =
     BoxServiceFactory<(), WebRequest<Err>, WebResponse, Err::Container, ()>;
 type BoxResponse<Err: ErrorRenderer> =
     Pin<Box<dyn Future<Output = Result<WebResponse, Err::Container>>>>;
-type FnDataFactory =
-    Box<dyn Fn() -> Pin<Box<dyn Future<Output = Result<Box<dyn DataFactory>, ()>>>>>;
+pub(super) type MatcherFactory = Rc<dyn Fn() -> Box<dyn Matcher>>;
 
 /// Service factory to convert `Request` to a `WebRequest<S>`.
 /// It also executes data factories.
@@ -50,6 +50,8 @@ where
     pub(super) factory_ref: Rc<RefCell<Option<AppRoutingFactory<Err>>>>,
     pub(super) external: RefCell<Vec<ResourceDef>>,
     pub(super) case_insensitive: bool,
+    pub(super) matcher: Option<MatcherFactory>,
+    pub(super) method_not_allowed: bool,
 }
 
 impl<T, Err> ServiceFactory for AppFactory<T, Err>
@@ -84,13 +86,17 @@ where
 
         // App config
         let mut config =
-            WebServiceConfig::new(config, default.clone(), self.data.clone());
+            WebServiceConfig::new(config, default.clone(), self.method_not_allowed);
 
         // register services
         std::mem::take(&mut *self.services.borrow_mut())
             .into_iter()
             .for_each(|mut srv| srv.register(&mut config));
 
+        // generate api documents
+        #[cfg(feature = "openapi")]
+        config.generate_openapi();
+
         let mut rmap = ResourceMap::new(ResourceDef::new(""));
 
         let (config, services) = config.into_services();
@@ -108,6 +114,8 @@ where
                     .collect(),
             ),
             case_insensitive: self.case_insensitive,
+            matcher: self.matcher.clone(),
+            method_not_allowed: self.method_not_allowed,
         });
 
         // external resources
@@ -206,7 +214,8 @@ where
             inner.path.set(head.uri.clone());
             inner.head = head;
             inner.payload = payload;
-            inner.app_data = self.data.clone();
+            inner.app_data.clear();
+            inner.app_data.push(self.data.clone());
             req
         } else {
             HttpRequest::new(
@@ -241,6 +250,8 @@ pub struct AppRoutingFactory<Err: ErrorRenderer> {
     services: Rc<Vec<(ResourceDef, HttpNewService<Err>, RefCell<Option<Guards>>)>>,
     default: Rc<HttpNewService<Err>>,
     case_insensitive: bool,
+    matcher: Option<MatcherFactory>,
+    method_not_allowed: bool,
 }
 
 impl<Err: ErrorRenderer> ServiceFactory for AppRoutingFactory<Err> {
@@ -255,11 +266,15 @@ impl<Err: ErrorRenderer> ServiceFactory for AppRoutingFactory<Err> {
     fn new_service(&self, _: ()) -> Self::Future {
         let services = self.services.clone();
         let default_fut = self.default.new_service(());
+        let method_not_allowed = self.method_not_allowed;
 
         let mut router = Router::build();
         if self.case_insensitive {
             router.case_insensitive();
         }
+        if let Some(ref matcher) = self.matcher {
+            router.matcher(matcher());
+        }
 
         Box::pin(async move {
             // create http services
@@ -272,6 +287,7 @@ impl<Err: ErrorRenderer> ServiceFactory for AppRoutingFactory<Err> {
                 ready: None,
                 router: router.finish(),
                 default: Some(default_fut.await?),
+                method_not_allowed,
             })
         })
     }
@@ -281,6 +297,7 @@ pub struct AppRouting<Err: ErrorRenderer> {
     router: Router<HttpService<Err>, Guards>,
     ready: Option<(WebRequest<Err>, ResourceInfo)>,
     default: Option<HttpService<Err>>,
+    method_not_allowed: bool,
 }
 
 impl<Err: ErrorRenderer> Service for AppRouting<Err> {
@@ -311,8 +328,18 @@ impl<Err: ErrorRenderer> Service for AppRouting<Err> {
         });
 
         if let Some((srv, _info)) = res {
-            srv.call(req)
-        } else if let Some(ref default) = self.default {
+            return srv.call(req);
+        }
+
+        if self.method_not_allowed {
+            let methods = allowed_methods(&self.router, &mut req);
+            if !methods.is_empty() {
+                let res = method_not_allowed(req, &methods);
+                return Box::pin(async { Ok(res) });
+            }
+        }
+
+        if let Some(ref default) = self.default {
             default.call(req)
         } else {
             let req = req.into_parts().0;
@@ -321,6 +348,36 @@ impl<Err: ErrorRenderer> Service for AppRouting<Err> {
     }
 }
 
+/// Methods of guarded services that match request path and all
+/// guards except method guards
+pub(super) fn allowed_methods<T, Err: ErrorRenderer>(
+    router: &Router<T, Guards>,
+    req: &mut WebRequest<Err>,
+) -> Vec<Method> {
+    let methods = RefCell::new(Vec::new());
+    router.recognize_checked(req, |req, guards| {
+        if let Some(guards) = guards {
+            let mut allowed = Vec::new();
+            for f in guards {
+                if let Some(method) = f.method() {
+                    allowed.push(method);
+                } else if !f.check(req.head()) {
+                    return false;
+                }
+            }
+
+            let mut methods = methods.borrow_mut();
+            for method in allowed {
+                if !methods.contains(method) {
+                    methods.push(method.clone());
+                }
+            }
+        }
+        false
+    });
+    methods.into_inner()
+}
+
 /// Wrapper service for routing
 pub struct AppEntry<Err: ErrorRenderer> {
     factory: Rc<RefCell<Option<AppRoutingFactory<Err>>>>,
@@ -0,0 +1,229 @@
+//! Thread pool for blocking operations
+use std::sync::{Arc, Condvar, Mutex};
+use std::{cell::RefCell, collections::VecDeque, panic, thread, time::Duration};
+
+use crate::http::error::BlockingError;
+
+/// Idle thread lifetime
+const KEEP_ALIVE: Duration = Duration::from_secs(10);
+
+/// Default size of the queue
+pub(super) const DEFAULT_QUEUE_SIZE: usize = 1024;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+thread_local! {
+    static POOL: RefCell<Option<BlockingPool>> = RefCell::new(None);
+}
+
+/// Bounded thread pool.
+///
+/// Threads get started on demand and stop after 10 seconds
+/// of inactivity.
+#[derive(Clone)]
+pub(super) struct BlockingPool(Arc<Inner>);
+
+struct Inner {
+    threads: usize,
+    queue_size: usize,
+    state: Mutex<State>,
+    cond: Condvar,
+}
+
+struct State {
+    queue: VecDeque<Job>,
+    threads: usize,
+    idle: usize,
+}
+
+impl Default for BlockingPool {
+    fn default() -> Self {
+        BlockingPool::new(default_threads(), DEFAULT_QUEUE_SIZE)
+    }
+}
+
+impl BlockingPool {
+    /// Create thread pool with max number of threads and max queue size
+    pub(super) fn new(threads: usize, queue_size: usize) -> Self {
+        BlockingPool(Arc::new(Inner {
+            queue_size,
+            threads: std::cmp::max(threads, 1),
+            state: Mutex::new(State {
+                queue: VecDeque::new(),
+                threads: 0,
+                idle: 0,
+            }),
+            cond: Condvar::new(),
+        }))
+    }
+
+    /// Use pool for blocking operations on current thread
+    pub(super) fn set_current(&self) {
+        POOL.with(|pool| *pool.borrow_mut() = Some(self.clone()));
+    }
+
+    /// Blocking pool of current thread
+    ///
+    /// Threads without configured pool share global pool with default parameters.
+    fn current() -> BlockingPool {
+        POOL.with(|pool| {
+            pool.borrow_mut()
+                .get_or_insert_with(BlockingPool::global)
+                .clone()
+        })
+    }
+
+    /// Global pool with default parameters
+    fn global() -> BlockingPool {
+        static GLOBAL: Mutex<Option<BlockingPool>> = Mutex::new(None);
+
+        GLOBAL
+            .lock()
+            .unwrap()
+            .get_or_insert_with(BlockingPool::default)
+            .clone()
+    }
+
+    /// Execute job, returns `false` if queue is full
+    fn execute(&self, job: Job) -> bool {
+        let mut st = self.0.state.lock().unwrap();
+
+        // jobs that could not be picked up by idle or new threads
+        let available = st.idle + (self.0.threads - st.threads);
+        if st.queue.len().saturating_sub(available) >= self.0.queue_size {
+            return false;
+        }
+        st.queue.push_back(job);
+
+        if st.idle < st.queue.len() && st.threads < self.0.threads {
+            let inner = self.0.clone();
+            let res = thread::Builder::new()
+                .name("ntex-blocking".to_string())
+                .spawn(move || run(inner));
+            match res {
+                Ok(_) => st.threads += 1,
+                Err(e) => log::error!("Cannot start blocking thread: {}", e),
+            }
+        }
+        self.0.cond.notify_one();
+        true
+    }
+}
+
+fn run(inner: Arc<Inner>) {
+    let mut st = inner.state.lock().unwrap();
+    loop {
+        if let Some(job) = st.queue.pop_front() {
+            drop(st);
+            // panic drops result sender, caller gets `Canceled` error
+            let _ = panic::catch_unwind(panic::AssertUnwindSafe(job));
+            st = inner.state.lock().unwrap();
+        } else {
+            st.idle += 1;
+            let (guard, res) = inner.cond.wait_timeout(st, KEEP_ALIVE).unwrap();
+            st = guard;
+            st.idle -= 1;
+
+            if res.timed_out() && st.queue.is_empty() {
+                st.threads -= 1;
+                return;
+            }
+        }
+    }
+}
+
+pub(super) fn default_threads() -> usize {
+    num_cpus::get() * 5
+}
+
+/// Execute blocking function on a thread pool, returns future that resolves
+/// to result of the function execution.
+///
+/// Http server workers use thread pool configured with
+/// `HttpServer::blocking_threads()` and `HttpServer::blocking_queue()`
+/// methods. Other threads share global pool with default parameters.
+/// If pool's queue is full, function is not executed and
+/// `BlockingError::Overloaded` error get returned.
+///
+/// ```rust
+/// use ntex::web::{self, error::BlockingError, HttpResponse};
+///
+/// async fn index() -> Result<HttpResponse, BlockingError<std::io::Error>> {
+///     let data = web::block(|| std::fs::read_to_string("Cargo.toml")).await?;
+///     Ok(HttpResponse::Ok().body(data))
+/// }
+/// ```
+pub async fn block<F, I, E>(f: F) -> Result<I, BlockingError<E>>
+where
+    F: FnOnce() -> Result<I, E> + Send + 'static,
+    I: Send + 'static,
+    E: Send + std::fmt::Debug + 'static,
+{
+    let (tx, rx) = async_channel::bounded(1);
+    let job = Box::new(move || {
+        let _ = tx.try_send(f());
+    });
+
+    if !BlockingPool::current().execute(job) {
+        return Err(BlockingError::Overloaded);
+    }
+
+    match rx.recv().await {
+        Ok(res) => res.map_err(BlockingError::Error),
+        Err(_) => Err(BlockingError::Canceled),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[crate::rt_test]
+    async fn test_block() {
+        let res = block(|| Ok::<_, ()>(thread::current().name().map(String::from)))
+            .await
+            .unwrap();
+        assert_eq!(res.as_deref(), Some("ntex-blocking"));
+
+        let res = block(|| Err::<(), _>("error")).await;
+        assert!(matches!(res, Err(BlockingError::Error("error"))));
+
+        let res = block(|| -> Result<(), ()> { panic!() }).await;
+        assert!(matches!(res, Err(BlockingError::Canceled)));
+
+        // result does not need to be `Sync`
+        let res = block(|| Ok::<_, ()>(std::cell::Cell::new(1)))
+            .await
+            .unwrap();
+        assert_eq!(res.get(), 1);
+    }
+
+    #[test]
+    fn test_global_pool() {
+        let pool = thread::spawn(BlockingPool::current).join().unwrap();
+        let pool2 = thread::spawn(BlockingPool::current).join().unwrap();
+        assert!(Arc::ptr_eq(&pool.0, &pool2.0));
+    }
+
+    #[crate::rt_test]
+    async fn test_overloaded() {
+        let pool = BlockingPool::new(1, 1);
+        pool.set_current();
+
+        // occupy the only thread
+        let (tx, rx) = mpsc::channel::<()>();
+        let busy = crate::rt::spawn(block(move || rx.recv().map_err(|_| ())));
+        crate::rt::time::sleep(Duration::from_millis(50)).await;
+
+        // second job is queued, third is rejected
+        let queued = crate::rt::spawn(block(|| Ok::<_, ()>(1)));
+        crate::rt::time::sleep(Duration::from_millis(50)).await;
+        let res = block(|| Ok::<_, ()>(2)).await;
+        assert!(matches!(res, Err(BlockingError::Overloaded)));
+
+        tx.send(()).unwrap();
+        assert!(busy.await.unwrap().is_ok());
+        assert_eq!(queued.await.unwrap().unwrap(), 1);
+    }
+}
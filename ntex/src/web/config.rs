@@ -2,6 +2,7 @@ use std::{net::SocketAddr, rc::Rc};
 
 use crate::router::ResourceDef;
 
+use super::info::Cidr;
 use super::resource::Resource;
 use super::route::Route;
 use super::service::{AppServiceFactory, ServiceFactoryWrapper, WebServiceFactory};
@@ -16,11 +17,25 @@ struct AppConfigInner {
     secure: bool,
     host: String,
     addr: SocketAddr,
+    trusted_proxies: Option<Vec<Cidr>>,
 }
 
 impl AppConfig {
     pub(crate) fn new(secure: bool, addr: SocketAddr, host: String) -> Self {
-        AppConfig(Rc::new(AppConfigInner { secure, host, addr }))
+        AppConfig(Rc::new(AppConfigInner {
+            secure,
+            host,
+            addr,
+            trusted_proxies: None,
+        }))
+    }
+
+    /// Set trusted proxies
+    pub(crate) fn set_trusted_proxies(mut self, proxies: Option<Vec<Cidr>>) -> Self {
+        if let Some(inner) = Rc::get_mut(&mut self.0) {
+            inner.trusted_proxies = proxies;
+        }
+        self
     }
 
     /// Server host name.
@@ -43,6 +58,11 @@ impl AppConfig {
     pub fn local_addr(&self) -> SocketAddr {
         self.0.addr
     }
+
+    /// Trusted proxy networks, `None` if all peers are trusted
+    pub(crate) fn trusted_proxies(&self) -> Option<&[Cidr]> {
+        self.0.trusted_proxies.as_deref()
+    }
 }
 
 impl Default for AppConfig {
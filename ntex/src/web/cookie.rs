@@ -0,0 +1,212 @@
+//! Signed and private cookies.
+//!
+//! `CookieKeys` holds master key used for signing and encrypting cookies.
+//! Keys are configured with `App::app_data()` method. Signed cookies are
+//! readable by client but could not be modified, private cookies are encrypted
+//! and authenticated.
+//!
+//! ```rust
+//! use ntex::web::{self, App, HttpRequest, HttpResponse};
+//! use ntex::web::cookie::{Cookie, CookieKeys, Key};
+//!
+//! async fn index(req: HttpRequest, keys: CookieKeys) -> HttpResponse {
+//!     if let Some(cookie) = req.signed_cookie("user") {
+//!         HttpResponse::Ok().body(format!("User: {}", cookie.value()))
+//!     } else {
+//!         HttpResponse::Ok()
+//!             .signed_cookie(Cookie::new("user", "ntex"), keys.key())
+//!             .finish()
+//!     }
+//! }
+//!
+//! fn main() {
+//!     let keys = CookieKeys::new(Key::generate());
+//!
+//!     let app = App::new()
+//!         .app_data(keys)
+//!         .service(web::resource("/").to(index));
+//! }
+//! ```
+use std::{iter, rc::Rc};
+
+use coo_kie::CookieJar;
+
+use crate::http::Payload;
+use crate::util::Ready;
+use crate::web::error::{DataExtractorError, ErrorRenderer};
+use crate::web::{FromRequest, HttpRequest};
+
+pub use coo_kie::{Cookie, Key};
+
+/// Keys for signed and private cookies.
+///
+/// New cookies are signed or encrypted with current key. Previous keys are
+/// used only for verification, so cookies issued before key rotation remain
+/// valid until they get re-issued.
+#[derive(Clone)]
+pub struct CookieKeys(Rc<Inner>);
+
+struct Inner {
+    key: Key,
+    previous: Vec<Key>,
+}
+
+impl CookieKeys {
+    /// Create cookie keys with current master key
+    pub fn new(key: Key) -> Self {
+        CookieKeys(Rc::new(Inner {
+            key,
+            previous: Vec::new(),
+        }))
+    }
+
+    /// Add previous master key
+    ///
+    /// Cookies signed or encrypted with previous key are still accepted.
+    pub fn previous_key(mut self, key: Key) -> Self {
+        Rc::get_mut(&mut self.0)
+            .expect("Cannot modify shared cookie keys")
+            .previous
+            .push(key);
+        self
+    }
+
+    /// Current master key
+    pub fn key(&self) -> &Key {
+        &self.0.key
+    }
+
+    fn keys(&self) -> impl Iterator<Item = &Key> {
+        iter::once(&self.0.key).chain(self.0.previous.iter())
+    }
+
+    /// Verify signed cookie, returns cookie with original value
+    pub fn verify(&self, cookie: Cookie<'static>) -> Option<Cookie<'static>> {
+        let name = cookie.name().to_string();
+        let mut jar = CookieJar::new();
+        jar.add_original(cookie);
+        self.keys().find_map(|key| jar.signed(key).get(&name))
+    }
+
+    /// Decrypt private cookie, returns cookie with original value
+    pub fn decrypt(&self, cookie: Cookie<'static>) -> Option<Cookie<'static>> {
+        let name = cookie.name().to_string();
+        let mut jar = CookieJar::new();
+        jar.add_original(cookie);
+        self.keys().find_map(|key| jar.private(key).get(&name))
+    }
+}
+
+/// Extract cookie keys configured with `App::app_data()`
+impl<Err: ErrorRenderer> FromRequest<Err> for CookieKeys {
+    type Error = DataExtractorError;
+    type Future = Ready<Self, Self::Error>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        if let Some(keys) = req.app_data::<CookieKeys>() {
+            Ready::Ok(keys.clone())
+        } else {
+            log::debug!("Cookie keys are not configured, use App::app_data()");
+            Ready::Err(DataExtractorError::NotConfigured)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{header, StatusCode};
+    use crate::web::test::{call_service, init_service, TestRequest};
+    use crate::web::{self, App, HttpResponse};
+
+    fn signed(key: &Key, cookie: Cookie<'static>) -> Cookie<'static> {
+        let mut jar = CookieJar::new();
+        jar.signed_mut(key).add(cookie);
+        jar.delta().next().unwrap().clone()
+    }
+
+    fn private(key: &Key, cookie: Cookie<'static>) -> Cookie<'static> {
+        let mut jar = CookieJar::new();
+        jar.private_mut(key).add(cookie);
+        jar.delta().next().unwrap().clone()
+    }
+
+    #[test]
+    fn test_key_rotation() {
+        let (old, new) = (Key::generate(), Key::generate());
+        let keys = CookieKeys::new(new.clone()).previous_key(old.clone());
+
+        let cookie = signed(&old, Cookie::new("name", "value"));
+        assert_eq!(keys.verify(cookie.clone()).unwrap().value(), "value");
+        assert!(CookieKeys::new(new.clone()).verify(cookie).is_none());
+
+        let cookie = private(&old, Cookie::new("name", "value"));
+        assert_eq!(keys.decrypt(cookie.clone()).unwrap().value(), "value");
+        assert!(keys.verify(cookie).is_none());
+
+        let cookie = signed(&new, Cookie::new("name", "value"));
+        assert_eq!(keys.verify(cookie).unwrap().value(), "value");
+
+        let mut cookie = signed(&new, Cookie::new("name", "value"));
+        let value = cookie.value().replace("value", "other");
+        cookie.set_value(value);
+        assert!(keys.verify(cookie).is_none());
+        assert!(keys.decrypt(Cookie::new("name", "value")).is_none());
+    }
+
+    #[crate::rt_test]
+    async fn test_cookies() {
+        let keys = CookieKeys::new(Key::generate());
+
+        let srv = init_service(
+            App::new().app_data(keys.clone()).service(
+                web::resource("/")
+                    .route(web::post().to(|keys: CookieKeys| async move {
+                        HttpResponse::Ok()
+                            .signed_cookie(Cookie::new("signed", "value1"), keys.key())
+                            .private_cookie(Cookie::new("private", "value2"), keys.key())
+                            .finish()
+                    }))
+                    .route(web::get().to(|req: HttpRequest| async move {
+                        let signed = req.signed_cookie("signed").unwrap();
+                        let private = req.private_cookie("private").unwrap();
+                        assert!(req.signed_cookie("private").is_none());
+                        assert!(req.private_cookie("signed").is_none());
+                        HttpResponse::Ok().body(format!(
+                            "{}:{}",
+                            signed.value(),
+                            private.value()
+                        ))
+                    })),
+            ),
+        )
+        .await;
+
+        let req = TestRequest::post().uri("/").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let cookies = res
+            .response()
+            .cookies()
+            .map(|c| format!("{}={}", c.name(), c.value()))
+            .collect::<Vec<_>>();
+        assert_eq!(cookies.len(), 2);
+
+        let req = TestRequest::get()
+            .uri("/")
+            .header(header::COOKIE, cookies.join("; "))
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = crate::web::test::read_body(res).await;
+        assert_eq!(body, "value1:value2");
+
+        let srv = init_service(App::new().service(
+            web::resource("/").to(|_: CookieKeys| async { HttpResponse::Ok() }),
+        ))
+        .await;
+        let res = call_service(&srv, TestRequest::default().to_request()).await;
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
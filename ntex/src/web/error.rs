@@ -84,6 +84,33 @@ pub enum DataExtractorError {
     NotConfigured,
 }
 
+/// Errors which can occur when attempting to work with `RequestId` extractor
+#[derive(Debug, PartialEq, Display)]
+pub enum RequestIdError {
+    #[display(
+        fmt = "Request id is not configured, to configure use RequestIdentifier middleware"
+    )]
+    NotConfigured,
+}
+
+/// Errors which can occur when attempting to work with `PeerCert` extractor
+#[derive(Debug, PartialEq, Display)]
+pub enum PeerCertError {
+    #[display(fmt = "Client certificate is not present")]
+    NotPresent,
+}
+
+/// Errors which can occur when attempting to work with `Header` extractor
+#[derive(Debug, PartialEq, Display)]
+pub enum HeaderError {
+    /// Header is not present
+    #[display(fmt = "Header {} is missing", _0)]
+    Missing(header::HeaderName),
+    /// Header is malformed
+    #[display(fmt = "Header {} is malformed", _0)]
+    Invalid(header::HeaderName),
+}
+
 /// Errors which can occur when attempting to generate resource uri.
 #[derive(Debug, PartialEq, Display, From)]
 pub enum UrlGenerationError {
@@ -124,6 +151,12 @@ pub enum UrlencodedError {
     /// Payload error
     #[display(fmt = "Error that occur during reading payload: {}", _0)]
     Payload(error::PayloadError),
+    /// Error with custom response, see `FormConfig::error_handler()`
+    #[display(fmt = "{}", error)]
+    Response {
+        error: Box<UrlencodedError>,
+        response: RefCell<Option<HttpResponse>>,
+    },
 }
 
 /// A set of errors that can occur during parsing json payloads
@@ -141,6 +174,81 @@ pub enum JsonPayloadError {
     /// Payload error
     #[display(fmt = "Error that occur during reading payload: {}", _0)]
     Payload(error::PayloadError),
+    /// Error with custom response, see `JsonConfig::error_handler()`
+    #[display(fmt = "{}", error)]
+    Response {
+        error: Box<JsonPayloadError>,
+        response: RefCell<Option<HttpResponse>>,
+    },
+}
+
+/// A set of errors that can occur during content negotiation
+#[derive(Debug, Display, From)]
+pub enum NegotiateError {
+    /// Acceptable format is not found
+    #[display(fmt = "Acceptable format is not found")]
+    NotAcceptable,
+    /// Content type is not supported
+    #[display(fmt = "Content type is not supported")]
+    UnsupportedMediaType,
+    /// Payload size is bigger than allowed. (default: 32kB)
+    #[display(fmt = "Payload size is bigger than allowed")]
+    Overflow,
+    /// Serialize error
+    #[from(ignore)]
+    #[display(fmt = "Serialize error: {}", _0)]
+    Serialize(Box<dyn std::error::Error>),
+    /// Deserialize error
+    #[from(ignore)]
+    #[display(fmt = "Deserialize error: {}", _0)]
+    Deserialize(Box<dyn std::error::Error>),
+    /// Payload error
+    #[display(fmt = "Error that occur during reading payload: {}", _0)]
+    Payload(error::PayloadError),
+}
+
+/// A set of errors that can occur during parsing multipart payloads
+#[derive(Debug, Display, From)]
+pub enum MultipartError {
+    /// Content type error
+    #[display(fmt = "Content type error")]
+    ContentType,
+    /// Multipart boundary is not found
+    #[display(fmt = "Multipart boundary is not found")]
+    Boundary,
+    /// Cannot parse field headers
+    #[display(fmt = "Cannot parse multipart field headers")]
+    Headers,
+    /// Payload ended before closing boundary
+    #[display(fmt = "Multipart payload is incomplete")]
+    Incomplete,
+    /// Field size is bigger than allowed
+    #[display(
+        fmt = "Multipart field {:?} size is bigger than allowed ({} bytes)",
+        name,
+        limit
+    )]
+    Overflow { name: String, limit: usize },
+    /// Number of fields is bigger than allowed
+    #[display(fmt = "Number of multipart fields is bigger than allowed ({})", _0)]
+    TooManyFields(usize),
+    /// Payload error
+    #[display(fmt = "Error that occur during reading payload: {}", _0)]
+    Payload(error::PayloadError),
+}
+
+/// A set of errors that can occur during CORS requests processing
+#[derive(Debug, Display, PartialEq)]
+pub enum CorsError {
+    /// Origin is not in the list of allowed origins
+    #[display(fmt = "Origin is not allowed to make this request")]
+    OriginNotAllowed,
+    /// Requested method is not allowed
+    #[display(fmt = "Requested method is not allowed")]
+    MethodNotAllowed,
+    /// One or more requested headers are not allowed
+    #[display(fmt = "One or more request headers are not allowed")]
+    HeadersNotAllowed,
 }
 
 /// A set of errors that can occur during parsing request paths
@@ -833,6 +941,34 @@ mod tests {
         assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
     }
 
+    #[test]
+    fn test_multipart_error() {
+        let req = TestRequest::default().to_http_request();
+        let resp: HttpResponse = WebResponseError::<DefaultError>::error_response(
+            &MultipartError::Overflow {
+                name: "file".to_string(),
+                limit: 10,
+            },
+            &req,
+        );
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        let resp: HttpResponse = WebResponseError::<DefaultError>::error_response(
+            &MultipartError::Boundary,
+            &req,
+        );
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_cors_error() {
+        let req = TestRequest::default().to_http_request();
+        let resp: HttpResponse = WebResponseError::<DefaultError>::error_response(
+            &CorsError::OriginNotAllowed,
+            &req,
+        );
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
     #[test]
     fn test_query_payload_error() {
         let req = TestRequest::default().to_http_request();
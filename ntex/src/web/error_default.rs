@@ -97,6 +97,23 @@ impl<E: WebResponseError<DefaultError>> WebResponseError<DefaultError>
 /// `InternalServerError` for `DataExtractorError`
 impl WebResponseError<DefaultError> for error::DataExtractorError {}
 
+/// `InternalServerError` for `RequestIdError`
+impl WebResponseError<DefaultError> for error::RequestIdError {}
+
+/// Return `UNAUTHORIZED` for `PeerCertError`
+impl WebResponseError<DefaultError> for error::PeerCertError {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::UNAUTHORIZED
+    }
+}
+
+/// Return `BAD_REQUEST` for `HeaderError`
+impl WebResponseError<DefaultError> for error::HeaderError {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::BAD_REQUEST
+    }
+}
+
 /// `InternalServerError` for `JsonError`
 impl WebResponseError<DefaultError> for JsonError {}
 
@@ -114,6 +131,10 @@ impl<T: fmt::Debug + 'static> WebResponseError<DefaultError>
 {
 }
 
+#[cfg(feature = "session")]
+/// `InternalServerError` for `SessionError`
+impl WebResponseError<DefaultError> for super::session::SessionError {}
+
 /// Return `BAD_REQUEST` for `de::value::Error`
 impl WebResponseError<DefaultError> for DeError {
     fn status_code(&self) -> StatusCode {
@@ -124,10 +145,17 @@ impl WebResponseError<DefaultError> for DeError {
 /// `InternalServerError` for `Canceled`
 impl WebResponseError<DefaultError> for crate::http::error::Canceled {}
 
-/// `InternalServerError` for `BlockingError`
+/// `InternalServerError` for `BlockingError`, `ServiceUnavailable`
+/// if thread pool is overloaded
 impl<E: fmt::Debug + 'static> WebResponseError<DefaultError>
     for crate::http::error::BlockingError<E>
 {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            error::BlockingError::Overloaded => StatusCode::SERVICE_UNAVAILABLE,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
 }
 
 /// Return `BAD_REQUEST` for `Utf8Error`
@@ -161,9 +189,21 @@ impl WebResponseError<DefaultError> for error::UrlencodedError {
         match *self {
             error::UrlencodedError::Overflow { .. } => StatusCode::PAYLOAD_TOO_LARGE,
             error::UrlencodedError::UnknownLength => StatusCode::LENGTH_REQUIRED,
+            error::UrlencodedError::Response { ref error, .. } => {
+                WebResponseError::<DefaultError>::status_code(error.as_ref())
+            }
             _ => StatusCode::BAD_REQUEST,
         }
     }
+
+    fn error_response(&self, _: &HttpRequest) -> HttpResponse {
+        if let error::UrlencodedError::Response { ref response, .. } = *self {
+            if let Some(res) = response.borrow_mut().take() {
+                return res;
+            }
+        }
+        text_response(self.status_code(), self)
+    }
 }
 
 /// Return `BadRequest` for `JsonPayloadError`
@@ -171,9 +211,66 @@ impl WebResponseError<DefaultError> for error::JsonPayloadError {
     fn status_code(&self) -> StatusCode {
         match *self {
             error::JsonPayloadError::Overflow => StatusCode::PAYLOAD_TOO_LARGE,
+            error::JsonPayloadError::Response { ref error, .. } => {
+                WebResponseError::<DefaultError>::status_code(error.as_ref())
+            }
             _ => StatusCode::BAD_REQUEST,
         }
     }
+
+    fn error_response(&self, _: &HttpRequest) -> HttpResponse {
+        if let error::JsonPayloadError::Response { ref response, .. } = *self {
+            if let Some(res) = response.borrow_mut().take() {
+                return res;
+            }
+        }
+        text_response(self.status_code(), self)
+    }
+}
+
+/// Return `NotAcceptable`, `UnsupportedMediaType` or `BadRequest` for `NegotiateError`
+impl WebResponseError<DefaultError> for error::NegotiateError {
+    fn status_code(&self) -> StatusCode {
+        match *self {
+            error::NegotiateError::NotAcceptable => StatusCode::NOT_ACCEPTABLE,
+            error::NegotiateError::UnsupportedMediaType => {
+                StatusCode::UNSUPPORTED_MEDIA_TYPE
+            }
+            error::NegotiateError::Overflow => StatusCode::PAYLOAD_TOO_LARGE,
+            error::NegotiateError::Serialize(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+/// Render error as `text/plain` response
+fn text_response(status: StatusCode, err: &dyn fmt::Display) -> HttpResponse {
+    let mut resp = HttpResponse::new(status);
+    let mut buf = BytesMut::new();
+    let _ = write!(Writer(&mut buf), "{}", err);
+    resp.headers_mut().insert(
+        header::CONTENT_TYPE,
+        header::HeaderValue::from_static("text/plain; charset=utf-8"),
+    );
+    resp.set_body(Body::from(buf))
+}
+
+/// Return `BadRequest` for `MultipartError`
+impl WebResponseError<DefaultError> for error::MultipartError {
+    fn status_code(&self) -> StatusCode {
+        match *self {
+            error::MultipartError::Overflow { .. }
+            | error::MultipartError::TooManyFields(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+/// Return `BadRequest` for `CorsError`
+impl WebResponseError<DefaultError> for error::CorsError {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::BAD_REQUEST
+    }
 }
 
 /// Error renderer for `PathError`
@@ -192,7 +289,12 @@ impl WebResponseError<DefaultError> for error::QueryPayloadError {
 
 impl WebResponseError<DefaultError> for error::PayloadError {
     fn status_code(&self) -> StatusCode {
-        StatusCode::BAD_REQUEST
+        match *self {
+            error::PayloadError::Payload(http::error::PayloadError::Overflow) => {
+                StatusCode::PAYLOAD_TOO_LARGE
+            }
+            _ => StatusCode::BAD_REQUEST,
+        }
     }
 }
 
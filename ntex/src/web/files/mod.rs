@@ -0,0 +1,719 @@
+//! Static files support.
+//!
+//! `Files` service serves files from a directory. Service supports
+//! conditional requests with `ETag` and `Last-Modified` headers,
+//! single range requests, directory index files and directory listings.
+//! `RangeBody` responder serves byte ranges of any seekable async source.
+//!
+//! ```rust
+//! use ntex::web::{self, files::Files, App};
+//!
+//! let app = App::new().service(
+//!     Files::new("/static", ".")
+//!         .index_file("index.html")
+//!         .show_files_listing()
+//! );
+//! ```
+use std::fmt::Write;
+use std::path::{Component, Path, PathBuf};
+use std::task::{Context, Poll};
+use std::{future::Future, io, marker::PhantomData, pin::Pin, rc::Rc};
+
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, CONTROLS};
+
+use crate::http::{header, Method, RequestHead, Response};
+use crate::router::ResourceDef;
+use crate::service::{Service, ServiceFactory};
+use crate::util::Ready;
+use crate::web::dev::{WebRequest, WebResponse, WebServiceConfig, WebServiceFactory};
+use crate::web::error::BlockingError;
+use crate::web::guard::Guard;
+use crate::web::{block, DefaultError, ErrorRenderer};
+
+mod named;
+mod range;
+
+pub use self::range::RangeBody;
+
+use self::named::NamedFile;
+
+/// Characters that are percent-encoded in directory listing links
+const LINK_SET: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+type PathFilter = dyn Fn(&Path, &RequestHead) -> bool;
+
+/// Static files handling service
+///
+/// `Files` service must be registered with `App::service()` method.
+///
+/// ```rust
+/// use ntex::web::{self, files::Files, App};
+///
+/// let app = App::new()
+///     .service(Files::new("/static", "."));
+/// ```
+pub struct Files<Err = DefaultError> {
+    path: String,
+    inner: Rc<Inner>,
+    guards: Vec<Box<dyn Guard>>,
+    _t: PhantomData<Err>,
+}
+
+struct Inner {
+    directory: PathBuf,
+    index: Option<String>,
+    show_index: bool,
+    hidden_files: bool,
+    use_etag: bool,
+    use_last_modified: bool,
+    filter: Option<Box<PathFilter>>,
+}
+
+impl<Err: ErrorRenderer> Files<Err> {
+    /// Create new `Files` instance for specified base directory.
+    ///
+    /// `path` - the url path prefix the service is mounted at.
+    /// `dir` - the directory files are served from. Files that resolve
+    /// outside of the directory, i.e. via symlinks, are not served.
+    pub fn new<T: Into<PathBuf>>(path: &str, dir: T) -> Self {
+        Files {
+            path: path.trim_end_matches('/').to_owned(),
+            inner: Rc::new(Inner {
+                directory: dir.into(),
+                index: None,
+                show_index: false,
+                hidden_files: false,
+                use_etag: true,
+                use_last_modified: true,
+                filter: None,
+            }),
+            guards: Vec::new(),
+            _t: PhantomData,
+        }
+    }
+
+    /// Set index file for directories.
+    ///
+    /// Index file is served if request path points to a directory.
+    pub fn index_file<T: Into<String>>(mut self, index: T) -> Self {
+        Rc::get_mut(&mut self.inner).unwrap().index = Some(index.into());
+        self
+    }
+
+    /// Show files listing for directories without index file.
+    ///
+    /// By default directory requests are rejected with `404 Not Found`.
+    pub fn show_files_listing(mut self) -> Self {
+        Rc::get_mut(&mut self.inner).unwrap().show_index = true;
+        self
+    }
+
+    /// Serve hidden files, file names that start with `.`.
+    ///
+    /// By default hidden files are not served.
+    pub fn use_hidden_files(mut self) -> Self {
+        Rc::get_mut(&mut self.inner).unwrap().hidden_files = true;
+        self
+    }
+
+    /// Specifies whether to use `ETag` header, default is `true`.
+    pub fn use_etag(mut self, value: bool) -> Self {
+        Rc::get_mut(&mut self.inner).unwrap().use_etag = value;
+        self
+    }
+
+    /// Specifies whether to use `Last-Modified` header, default is `true`.
+    pub fn use_last_modified(mut self, value: bool) -> Self {
+        Rc::get_mut(&mut self.inner).unwrap().use_last_modified = value;
+        self
+    }
+
+    /// Set path filter.
+    ///
+    /// Filter receives file path relative to the base directory, if filter
+    /// returns `false` request is rejected with `404 Not Found`. Filter is
+    /// also used for files listing.
+    ///
+    /// ```rust
+    /// use ntex::web::{self, files::Files, App};
+    ///
+    /// let app = App::new().service(
+    ///     Files::new("/static", ".")
+    ///         .path_filter(|path, _| path.extension().map(|ext| ext != "key").unwrap_or(true))
+    /// );
+    /// ```
+    pub fn path_filter<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&Path, &RequestHead) -> bool + 'static,
+    {
+        Rc::get_mut(&mut self.inner).unwrap().filter = Some(Box::new(f));
+        self
+    }
+
+    /// Add match guard to the service.
+    pub fn guard<G: Guard + 'static>(mut self, guard: G) -> Self {
+        self.guards.push(Box::new(guard));
+        self
+    }
+}
+
+impl<Err: ErrorRenderer> WebServiceFactory<Err> for Files<Err> {
+    fn register(mut self, config: &mut WebServiceConfig<Err>) {
+        let guards = if self.guards.is_empty() {
+            None
+        } else {
+            Some(std::mem::take(&mut self.guards))
+        };
+        let rdef = if self.path.is_empty() {
+            ResourceDef::root_prefix("/")
+        } else {
+            ResourceDef::root_prefix(self.path.as_str())
+        };
+        config.register_service(rdef, guards, self, None)
+    }
+}
+
+impl<Err: ErrorRenderer> ServiceFactory for Files<Err> {
+    type Config = ();
+    type Request = WebRequest<Err>;
+    type Response = WebResponse;
+    type Error = Err::Container;
+    type InitError = ();
+    type Service = FilesService<Err>;
+    type Future = Ready<Self::Service, Self::InitError>;
+
+    fn new_service(&self, _: ()) -> Self::Future {
+        Ready::Ok(FilesService {
+            inner: self.inner.clone(),
+            _t: PhantomData,
+        })
+    }
+}
+
+/// Static files service
+pub struct FilesService<Err> {
+    inner: Rc<Inner>,
+    _t: PhantomData<Err>,
+}
+
+impl<Err: ErrorRenderer> Service for FilesService<Err> {
+    type Request = WebRequest<Err>;
+    type Response = WebResponse;
+    type Error = Err::Container;
+    type Future = Pin<Box<dyn Future<Output = Result<WebResponse, Err::Container>>>>;
+
+    #[inline]
+    fn poll_ready(&self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&self, req: WebRequest<Err>) -> Self::Future {
+        let inner = self.inner.clone();
+
+        Box::pin(async move {
+            if !matches!(*req.method(), Method::GET | Method::HEAD) {
+                return Ok(req.into_response(
+                    Response::MethodNotAllowed()
+                        .header(header::ALLOW, "GET, HEAD")
+                        .finish(),
+                ));
+            }
+
+            let rel_path = match inner.rel_path(req.match_info().unprocessed()) {
+                Some(path) => path,
+                None => return Ok(req.into_response(Response::NotFound().finish())),
+            };
+            if let Some(ref filter) = inner.filter {
+                if !filter(&rel_path, req.head()) {
+                    return Ok(req.into_response(Response::NotFound().finish()));
+                }
+            }
+            let (mut path, is_dir) =
+                match resolve(inner.directory.clone(), inner.directory.join(&rel_path))
+                    .await
+                {
+                    Ok(item) => item,
+                    Err(e) => {
+                        log::debug!("Cannot resolve path {:?}: {}", rel_path, e);
+                        return Ok(req.into_response(error_response(e)));
+                    }
+                };
+
+            if is_dir {
+                if let Some(ref index) = inner.index {
+                    // index file could be a symlink, check it as well
+                    path = match resolve(inner.directory.clone(), path.join(index)).await
+                    {
+                        Ok((path, _)) => path,
+                        Err(e) => {
+                            log::debug!("Cannot resolve index {:?}: {}", rel_path, e);
+                            return Ok(req.into_response(error_response(e)));
+                        }
+                    };
+                } else if inner.show_index {
+                    let res = match inner.listing(&path, &rel_path, &req).await {
+                        Ok(body) => Response::Ok()
+                            .content_type("text/html; charset=utf-8")
+                            .body(body),
+                        Err(e) => {
+                            log::debug!("Cannot read directory {:?}: {}", path, e);
+                            Response::NotFound().finish()
+                        }
+                    };
+                    return Ok(req.into_response(res));
+                } else {
+                    return Ok(req.into_response(Response::NotFound().finish()));
+                }
+            }
+
+            let res = match NamedFile::open(&path).await {
+                Ok(file) => file.into_response(
+                    req.head(),
+                    inner.use_etag,
+                    inner.use_last_modified,
+                ),
+                Err(e) => {
+                    log::debug!("Cannot open file {:?}: {}", path, e);
+                    error_response(e)
+                }
+            };
+            Ok(req.into_response(res))
+        })
+    }
+}
+
+impl Inner {
+    /// Convert request path to file path relative to base directory
+    fn rel_path(&self, path: &str) -> Option<PathBuf> {
+        let mut buf = PathBuf::new();
+
+        for segment in path.split('/') {
+            let segment = percent_decode_str(segment).decode_utf8().ok()?;
+            if segment.is_empty() || segment == "." {
+                continue;
+            } else if segment == ".."
+                || segment.contains(&['/', '\\', ':', '\0'][..])
+                || (segment.starts_with('.') && !self.hidden_files)
+            {
+                return None;
+            }
+            // decoded segment must be a single plain file name
+            let mut components = Path::new(segment.as_ref()).components();
+            if !matches!(
+                (components.next(), components.next()),
+                (Some(Component::Normal(_)), None)
+            ) {
+                return None;
+            }
+            buf.push(segment.as_ref());
+        }
+        Some(buf)
+    }
+
+    /// Render directory listing
+    async fn listing<Err>(
+        &self,
+        dir: &Path,
+        rel_path: &Path,
+        req: &WebRequest<Err>,
+    ) -> io::Result<String> {
+        let dir = dir.to_owned();
+        let mut entries = block(move || {
+            let mut entries = Vec::new();
+            for entry in dir.read_dir()? {
+                let entry = entry?;
+                let is_dir = entry.file_type()?.is_dir();
+                if let Ok(name) = entry.file_name().into_string() {
+                    entries.push((name, is_dir));
+                }
+            }
+            Ok::<_, io::Error>(entries)
+        })
+        .await
+        .map_err(blocking_error)?;
+        entries.sort();
+
+        let base = req.path().trim_end_matches('/');
+        let title = escape_html(if base.is_empty() { "/" } else { base });
+        let mut body = format!(
+            "<html><head><meta charset=\"utf-8\"><title>Index of {}</title></head>\
+             <body><h1>Index of {}</h1><ul>",
+            title, title
+        );
+        for (name, is_dir) in entries {
+            if name.starts_with('.') && !self.hidden_files {
+                continue;
+            }
+            if let Some(ref filter) = self.filter {
+                if !filter(&rel_path.join(&name), req.head()) {
+                    continue;
+                }
+            }
+            let slash = if is_dir { "/" } else { "" };
+            let _ = write!(
+                body,
+                "<li><a href=\"{}/{}{}\">{}{}</a></li>",
+                escape_html(base),
+                utf8_percent_encode(&name, LINK_SET),
+                slash,
+                escape_html(&name),
+                slash
+            );
+        }
+        body.push_str("</ul></body></html>");
+        Ok(body)
+    }
+}
+
+/// Canonicalize file path, check that it does not escape base directory
+///
+/// Returns canonical path and `true` if path points to a directory.
+async fn resolve(dir: PathBuf, path: PathBuf) -> io::Result<(PathBuf, bool)> {
+    block(move || {
+        let path = path.canonicalize()?;
+        if !path.starts_with(dir.canonicalize()?) {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "Path is outside of base directory",
+            ));
+        }
+        let is_dir = path.is_dir();
+        Ok((path, is_dir))
+    })
+    .await
+    .map_err(blocking_error)
+}
+
+/// Blocking thread pool is overloaded
+#[derive(Debug, Display)]
+#[display(fmt = "Thread pool is overloaded")]
+struct Overloaded;
+
+impl std::error::Error for Overloaded {}
+
+pub(super) fn blocking_error(e: BlockingError<io::Error>) -> io::Error {
+    match e {
+        BlockingError::Error(e) => e,
+        BlockingError::Canceled => io::Error::new(io::ErrorKind::Other, "Canceled"),
+        BlockingError::Overloaded => io::Error::new(io::ErrorKind::Other, Overloaded),
+    }
+}
+
+fn error_response(e: io::Error) -> Response {
+    match e.kind() {
+        io::ErrorKind::NotFound => Response::NotFound().finish(),
+        io::ErrorKind::PermissionDenied => Response::Forbidden().finish(),
+        _ if matches!(e.get_ref(), Some(e) if e.is::<Overloaded>()) => {
+            Response::ServiceUnavailable().finish()
+        }
+        _ => Response::InternalServerError().finish(),
+    }
+}
+
+fn escape_html(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&#39;"),
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, time::SystemTime};
+
+    use super::*;
+    use crate::http::{header::HeaderValue, StatusCode};
+    use crate::web::test::{call_service, init_service, read_body, TestRequest};
+    use crate::web::{self, App};
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "ntex-files-{}-{}-{}",
+            name,
+            std::process::id(),
+            SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        ));
+        fs::create_dir_all(dir.join("sub dir")).unwrap();
+        fs::write(dir.join("test.txt"), "0123456789").unwrap();
+        fs::write(dir.join(".hidden"), "hidden").unwrap();
+        fs::write(dir.join("secret.key"), "key").unwrap();
+        fs::write(dir.join("sub dir").join("index.html"), "<h1>index</h1>").unwrap();
+        dir
+    }
+
+    #[crate::rt_test]
+    async fn test_files() {
+        let dir = test_dir("files");
+        let srv =
+            init_service(App::new().service(Files::new("/static/", &dir).path_filter(
+                |path, _| path.extension().map(|e| e != "key").unwrap_or(true),
+            )))
+            .await;
+
+        let req = TestRequest::with_uri("/static/test.txt").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            res.headers().get(header::CONTENT_TYPE).unwrap(),
+            "text/plain; charset=utf-8"
+        );
+        assert_eq!(res.headers().get(header::ACCEPT_RANGES).unwrap(), "bytes");
+        let etag = res.headers().get(header::ETAG).unwrap().clone();
+        let modified = res.headers().get(header::LAST_MODIFIED).unwrap().clone();
+        assert_eq!(read_body(res).await, "0123456789");
+
+        let req = TestRequest::with_uri("/static/test.txt")
+            .method(Method::HEAD)
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(read_body(res).await, "");
+
+        // not found
+        for path in &[
+            "/static/unknown.txt",
+            "/static/%2e%2e/test.txt",
+            "/static/%2Fetc%2Fpasswd",
+            "/static/%2fetc/passwd",
+            "/static/sub%20dir%2F..%2F..%2Fetc%2Fpasswd",
+            "/static/a%2F..%2F..%2Fetc",
+            "/static/%5C..%5Ctest.txt",
+            "/static/C:%2Fwindows",
+            "/static/.hidden",
+            "/static/secret.key",
+            "/static/sub%20dir/",
+        ] {
+            let req = TestRequest::with_uri(path).to_request();
+            let res = call_service(&srv, req).await;
+            assert_eq!(res.status(), StatusCode::NOT_FOUND, "{}", path);
+        }
+
+        let req = TestRequest::with_uri("/static/test.txt")
+            .method(Method::POST)
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::METHOD_NOT_ALLOWED);
+
+        // conditional requests
+        let req = TestRequest::with_uri("/static/test.txt")
+            .header(header::IF_NONE_MATCH, etag.clone())
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+
+        let req = TestRequest::with_uri("/static/test.txt")
+            .header(header::IF_MODIFIED_SINCE, modified.clone())
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+
+        let req = TestRequest::with_uri("/static/test.txt")
+            .header(header::IF_NONE_MATCH, "\"other\"")
+            .header(header::IF_MODIFIED_SINCE, modified.clone())
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let req = TestRequest::with_uri("/static/test.txt")
+            .header(header::IF_MATCH, "\"other\"")
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::PRECONDITION_FAILED);
+
+        let req = TestRequest::with_uri("/static/test.txt")
+            .header(header::IF_MATCH, etag.clone())
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_rel_path() {
+        let files = Files::<DefaultError>::new("/", ".");
+        let inner = &files.inner;
+        assert_eq!(
+            inner.rel_path("/sub%20dir/index.html"),
+            Some(PathBuf::from("sub dir/index.html"))
+        );
+        for path in &[
+            "/%2e%2e/test.txt",
+            "/%2Fetc%2Fpasswd",
+            "/a%2F..%2F..%2Fetc",
+            "/a%2Fb",
+            "/..%5Ctest",
+            "/C:test",
+            "/test%00.txt",
+        ] {
+            assert!(inner.rel_path(path).is_none(), "{}", path);
+        }
+    }
+
+    #[test]
+    fn test_error_response() {
+        let res = error_response(blocking_error(BlockingError::Overloaded));
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let res = error_response(blocking_error(BlockingError::Canceled));
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let res = error_response(io::Error::new(io::ErrorKind::NotFound, "err"));
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[cfg(unix)]
+    #[crate::rt_test]
+    async fn test_symlink_outside() {
+        let dir = test_dir("symlink");
+        let outside = test_dir("outside");
+        std::os::unix::fs::symlink(outside.join("test.txt"), dir.join("link.txt"))
+            .unwrap();
+        std::os::unix::fs::symlink(dir.join("test.txt"), dir.join("inside.txt"))
+            .unwrap();
+        fs::create_dir(dir.join("linked")).unwrap();
+        std::os::unix::fs::symlink(
+            outside.join("sub dir").join("index.html"),
+            dir.join("linked").join("index.html"),
+        )
+        .unwrap();
+        let srv = init_service(
+            App::new().service(Files::new("/", &dir).index_file("index.html")),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/link.txt").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        let req = TestRequest::with_uri("/inside.txt").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let req = TestRequest::with_uri("/linked/").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        let req = TestRequest::with_uri("/sub%20dir/").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        fs::remove_dir_all(dir).unwrap();
+        fs::remove_dir_all(outside).unwrap();
+    }
+
+    #[crate::rt_test]
+    async fn test_range() {
+        let dir = test_dir("range");
+        let srv = init_service(App::new().service(Files::new("/", &dir))).await;
+
+        let req = TestRequest::with_uri("/test.txt")
+            .header(header::RANGE, "bytes=2-5")
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(
+            res.headers().get(header::CONTENT_RANGE).unwrap(),
+            "bytes 2-5/10"
+        );
+        let etag = res.headers().get(header::ETAG).unwrap().clone();
+        assert_eq!(read_body(res).await, "2345");
+
+        let req = TestRequest::with_uri("/test.txt")
+            .header(header::RANGE, "bytes=-3")
+            .header(header::IF_RANGE, etag)
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(read_body(res).await, "789");
+
+        // stale If-Range, full content
+        let req = TestRequest::with_uri("/test.txt")
+            .header(header::RANGE, "bytes=-3")
+            .header(header::IF_RANGE, "\"stale\"")
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(read_body(res).await, "0123456789");
+
+        let req = TestRequest::with_uri("/test.txt")
+            .header(header::RANGE, "bytes=20-")
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(
+            res.headers().get(header::CONTENT_RANGE).unwrap(),
+            HeaderValue::from_static("bytes */10")
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[crate::rt_test]
+    async fn test_index() {
+        let dir = test_dir("index");
+        let srv = init_service(
+            App::new()
+                .service(Files::new("/index", &dir).index_file("index.html"))
+                .service(
+                    Files::new("/list", &dir)
+                        .show_files_listing()
+                        .use_etag(false)
+                        .use_last_modified(false),
+                )
+                .service(web::scope("/scope").service(Files::new("", &dir))),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/index/sub%20dir/").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            res.headers().get(header::CONTENT_TYPE).unwrap(),
+            "text/html; charset=utf-8"
+        );
+        assert_eq!(read_body(res).await, "<h1>index</h1>");
+
+        let req = TestRequest::with_uri("/list/").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = read_body(res).await;
+        let body = std::str::from_utf8(&body).unwrap();
+        assert!(body.contains("<title>Index of /list</title>"));
+        assert!(body.contains("<a href=\"/list/sub%20dir/\">sub dir/</a>"));
+        assert!(body.contains("<a href=\"/list/test.txt\">test.txt</a>"));
+        assert!(!body.contains(".hidden"));
+
+        let req = TestRequest::with_uri("/list/test.txt").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(res.headers().get(header::ETAG).is_none());
+        assert!(res.headers().get(header::LAST_MODIFIED).is_none());
+
+        let req = TestRequest::with_uri("/scope/test.txt").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(read_body(res).await, "0123456789");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
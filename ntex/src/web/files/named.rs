@@ -0,0 +1,291 @@
+use std::fs::{File, Metadata};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::task::{Context, Poll};
+use std::{cmp, error::Error, future::Future, path::Path, pin::Pin, time};
+
+use crate::http::body::SizedStream;
+use crate::http::header;
+use crate::http::{Method, RequestHead, Response, StatusCode};
+use crate::rt::task::{spawn_blocking, JoinHandle};
+use crate::util::Bytes;
+use crate::web::block;
+use crate::Stream;
+
+use super::{blocking_error, range::HttpRange};
+
+/// Max size of the file chunk
+const CHUNK_SIZE: u64 = 65_536;
+
+/// Opened file
+pub(super) struct NamedFile {
+    file: File,
+    md: Metadata,
+    content_type: &'static str,
+}
+
+impl NamedFile {
+    /// Open file in blocking thread pool
+    pub(super) async fn open(path: &Path) -> io::Result<NamedFile> {
+        let content_type = content_type(path);
+        let path = path.to_owned();
+
+        let (file, md) = block(move || {
+            let file = File::open(&path)?;
+            let md = file.metadata()?;
+            Ok::<_, io::Error>((file, md))
+        })
+        .await
+        .map_err(blocking_error)?;
+
+        Ok(NamedFile {
+            file,
+            md,
+            content_type,
+        })
+    }
+
+    fn etag(&self) -> Option<String> {
+        self.md.modified().ok().and_then(|mtime| {
+            mtime.duration_since(time::UNIX_EPOCH).ok().map(|dur| {
+                format!(
+                    "\"{:x}-{:x}-{:x}\"",
+                    self.md.len(),
+                    dur.as_secs(),
+                    dur.subsec_nanos()
+                )
+            })
+        })
+    }
+
+    /// Create response for request
+    pub(super) fn into_response(
+        self,
+        head: &RequestHead,
+        use_etag: bool,
+        use_last_modified: bool,
+    ) -> Response {
+        let etag = if use_etag { self.etag() } else { None };
+        let modified = if use_last_modified {
+            self.md.modified().ok().map(secs)
+        } else {
+            None
+        };
+
+        // preconditions
+        let precondition_failed = if let Some(val) = header_str(head, &header::IF_MATCH)
+        {
+            !etag_matches(etag.as_deref(), val, false)
+        } else if let Some(since) = header_date(head, &header::IF_UNMODIFIED_SINCE) {
+            modified.map(|m| m > since).unwrap_or(false)
+        } else {
+            false
+        };
+        let not_modified = if let Some(val) = header_str(head, &header::IF_NONE_MATCH) {
+            etag_matches(etag.as_deref(), val, true)
+        } else if let Some(since) = header_date(head, &header::IF_MODIFIED_SINCE) {
+            modified.map(|m| m <= since).unwrap_or(false)
+        } else {
+            false
+        };
+
+        let mut builder = if precondition_failed {
+            Response::PreconditionFailed()
+        } else if not_modified {
+            Response::NotModified()
+        } else {
+            Response::Ok()
+        };
+        if let Some(ref etag) = etag {
+            builder.header(header::ETAG, etag.as_str());
+        }
+        if let Some(modified) = modified {
+            let modified = time::UNIX_EPOCH + time::Duration::from_secs(modified);
+            builder.header(
+                header::LAST_MODIFIED,
+                httpdate::fmt_http_date(modified).as_str(),
+            );
+        }
+        if precondition_failed || not_modified {
+            return builder.finish();
+        }
+
+        builder
+            .header(header::CONTENT_TYPE, self.content_type)
+            .header(header::ACCEPT_RANGES, "bytes");
+
+        let size = self.md.len();
+        let mut offset = 0;
+        let mut length = size;
+
+        if let Some(range) = header_str(head, &header::RANGE) {
+            let if_range = match header_str(head, &header::IF_RANGE) {
+                Some(val) if val.trim_start().starts_with(&['"', 'W'][..]) => {
+                    etag_matches(etag.as_deref(), val, false)
+                }
+                Some(val) => match httpdate::parse_http_date(val) {
+                    Ok(date) => modified == Some(secs(date)),
+                    Err(_) => false,
+                },
+                None => true,
+            };
+
+            if if_range {
+                match HttpRange::parse(range, size) {
+                    Ok(Some(range)) => {
+                        offset = range.start;
+                        length = range.length;
+                        builder.status(StatusCode::PARTIAL_CONTENT).header(
+                            header::CONTENT_RANGE,
+                            format!("bytes {}-{}/{}", offset, offset + length - 1, size),
+                        );
+                    }
+                    Ok(None) => (),
+                    Err(_) => {
+                        return builder
+                            .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                            .header(header::CONTENT_RANGE, format!("bytes */{}", size))
+                            .finish();
+                    }
+                }
+            }
+        }
+
+        let remaining = if head.method == Method::HEAD {
+            0
+        } else {
+            length
+        };
+        builder.body(SizedStream::new(
+            length,
+            ChunkedReadFile {
+                offset,
+                remaining,
+                file: Some(self.file),
+                fut: None,
+            },
+        ))
+    }
+}
+
+fn secs(time: time::SystemTime) -> u64 {
+    time.duration_since(time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn header_str<'a>(head: &'a RequestHead, name: &header::HeaderName) -> Option<&'a str> {
+    head.headers.get(name).and_then(|v| v.to_str().ok())
+}
+
+fn header_date(head: &RequestHead, name: &header::HeaderName) -> Option<u64> {
+    header_str(head, name)
+        .and_then(|v| httpdate::parse_http_date(v).ok())
+        .map(secs)
+}
+
+/// Check if `If-Match` like header matches etag
+fn etag_matches(etag: Option<&str>, header: &str, weak: bool) -> bool {
+    let etag = if let Some(etag) = etag {
+        etag
+    } else {
+        return false;
+    };
+    if header.trim() == "*" {
+        return true;
+    }
+
+    header.split(',').map(|s| s.trim()).any(|tag| {
+        if let Some(tag) = tag.strip_prefix("W/") {
+            weak && tag == etag
+        } else {
+            tag == etag
+        }
+    })
+}
+
+/// Content type of the file
+fn content_type(path: &Path) -> &'static str {
+    let ext = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase())
+        .unwrap_or_default();
+
+    match ext.as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" | "mjs" => "application/javascript; charset=utf-8",
+        "json" | "map" => "application/json",
+        "txt" | "md" => "text/plain; charset=utf-8",
+        "csv" => "text/csv; charset=utf-8",
+        "xml" => "text/xml; charset=utf-8",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "ico" => "image/x-icon",
+        "webp" => "image/webp",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "wasm" => "application/wasm",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "gz" => "application/gzip",
+        "mp3" => "audio/mpeg",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Stream of file chunks, file is read in blocking thread pool
+struct ChunkedReadFile {
+    offset: u64,
+    remaining: u64,
+    file: Option<File>,
+    fut: Option<JoinHandle<io::Result<(File, Bytes)>>>,
+}
+
+impl Stream for ChunkedReadFile {
+    type Item = Result<Bytes, Box<dyn Error>>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        if let Some(ref mut fut) = self.fut {
+            return match Pin::new(fut).poll(cx) {
+                Poll::Ready(Ok(Ok((file, bytes)))) => {
+                    self.fut.take();
+                    self.file = Some(file);
+                    self.offset += bytes.len() as u64;
+                    self.remaining -= bytes.len() as u64;
+                    Poll::Ready(Some(Ok(bytes)))
+                }
+                Poll::Ready(Ok(Err(e))) => Poll::Ready(Some(Err(Box::new(e)))),
+                Poll::Ready(Err(e)) => Poll::Ready(Some(Err(Box::new(e)))),
+                Poll::Pending => Poll::Pending,
+            };
+        }
+
+        if self.remaining == 0 {
+            return Poll::Ready(None);
+        }
+
+        let offset = self.offset;
+        let size = cmp::min(self.remaining, CHUNK_SIZE);
+        let mut file = self.file.take().unwrap();
+        self.fut = Some(spawn_blocking(move || {
+            let mut buf = Vec::with_capacity(size as usize);
+            file.seek(SeekFrom::Start(offset))?;
+            let n = (&mut file).take(size).read_to_end(&mut buf)?;
+            if n == 0 {
+                Err(io::ErrorKind::UnexpectedEof.into())
+            } else {
+                Ok((file, Bytes::from(buf)))
+            }
+        }));
+        self.poll_next(cx)
+    }
+}
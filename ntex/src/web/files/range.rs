@@ -0,0 +1,608 @@
+//! Byte range requests support.
+use std::collections::VecDeque;
+use std::task::{Context, Poll};
+use std::{cmp, error::Error, io, io::SeekFrom, pin::Pin};
+
+use nanorand::{WyRand, RNG};
+
+use crate::codec::{AsyncRead, AsyncSeek, ReadBuf};
+use crate::http::body::SizedStream;
+use crate::http::header;
+use crate::http::{Method, Response, StatusCode};
+use crate::util::{Bytes, BytesMut};
+use crate::web::responder::{Ready, Responder};
+use crate::web::{ErrorRenderer, HttpRequest};
+use crate::Stream;
+
+/// Max size of the body chunk
+const CHUNK_SIZE: u64 = 65_536;
+
+/// Max number of ranges in one request, bigger requests get full body
+const MAX_RANGES: usize = 32;
+
+/// Byte range of the file
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(super) struct HttpRange {
+    pub(super) start: u64,
+    pub(super) length: u64,
+}
+
+impl HttpRange {
+    /// Parse `Range` header value, returns first satisfiable range.
+    ///
+    /// Returns `Ok(None)` if header is malformed and must be ignored,
+    /// and `Err(())` if none of the ranges is satisfiable.
+    pub(super) fn parse(header: &str, size: u64) -> Result<Option<HttpRange>, ()> {
+        HttpRange::parse_ranges(header, size).map(|r| r.map(|r| r[0]))
+    }
+
+    /// Parse `Range` header value, returns all satisfiable ranges.
+    ///
+    /// Overlapping and adjacent ranges are coalesced.
+    pub(super) fn parse_all(
+        header: &str,
+        size: u64,
+    ) -> Result<Option<Vec<HttpRange>>, ()> {
+        let mut ranges = if let Some(ranges) = HttpRange::parse_ranges(header, size)? {
+            ranges
+        } else {
+            return Ok(None);
+        };
+
+        let overlap = ranges.iter().enumerate().any(|(idx, r1)| {
+            ranges[idx + 1..].iter().any(|r2| {
+                r1.start <= r2.start + r2.length && r2.start <= r1.start + r1.length
+            })
+        });
+        if overlap {
+            ranges.sort_by_key(|r| r.start);
+            let mut merged: Vec<HttpRange> = Vec::with_capacity(ranges.len());
+            for range in ranges {
+                match merged.last_mut() {
+                    Some(last) if range.start <= last.start + last.length => {
+                        let end = cmp::max(
+                            last.start + last.length,
+                            range.start + range.length,
+                        );
+                        last.length = end - last.start;
+                    }
+                    _ => merged.push(range),
+                }
+            }
+            ranges = merged;
+        }
+        Ok(Some(ranges))
+    }
+
+    fn parse_ranges(header: &str, size: u64) -> Result<Option<Vec<HttpRange>>, ()> {
+        let ranges = if let Some(ranges) = header.trim().strip_prefix("bytes=") {
+            ranges
+        } else {
+            return Ok(None);
+        };
+
+        let mut result = Vec::new();
+        for spec in ranges
+            .split(',')
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+        {
+            let (start, end) = match spec.find('-') {
+                Some(pos) => (spec[..pos].trim(), spec[pos + 1..].trim()),
+                None => return Ok(None),
+            };
+
+            let range = if start.is_empty() {
+                // suffix range, last N bytes
+                let length = match end.parse::<u64>() {
+                    Ok(length) => length.min(size),
+                    Err(_) => return Ok(None),
+                };
+                if length == 0 {
+                    None
+                } else {
+                    Some(HttpRange {
+                        start: size - length,
+                        length,
+                    })
+                }
+            } else {
+                let start = match start.parse::<u64>() {
+                    Ok(start) => start,
+                    Err(_) => return Ok(None),
+                };
+                let end = if end.is_empty() {
+                    size.saturating_sub(1)
+                } else {
+                    match end.parse::<u64>() {
+                        Ok(end) if end >= start => end.min(size.saturating_sub(1)),
+                        _ => return Ok(None),
+                    }
+                };
+                if start >= size {
+                    None
+                } else {
+                    Some(HttpRange {
+                        start,
+                        length: end - start + 1,
+                    })
+                }
+            };
+
+            result.extend(range);
+        }
+
+        if result.is_empty() {
+            Err(())
+        } else {
+            Ok(Some(result))
+        }
+    }
+}
+
+/// Responder for byte range requests.
+///
+/// Serves any seekable async source according to `RFC 7233`. Single range
+/// is served with *206 Partial Content* response, multiple ranges are served
+/// as `multipart/byteranges` body. Unsatisfiable ranges are rejected with
+/// *416 Range Not Satisfiable*, if request does not contain valid `Range`
+/// header full body is returned.
+///
+/// ```rust
+/// use std::io::Cursor;
+/// use ntex::web::{self, files::RangeBody, App};
+///
+/// async fn video() -> RangeBody<Cursor<&'static [u8]>> {
+///     let data: &'static [u8] = b"video data";
+///     RangeBody::new(Cursor::new(data), data.len() as u64).content_type("video/mp4")
+/// }
+///
+/// fn main() {
+///     let app = App::new().route("/video", web::get().to(video));
+/// }
+/// ```
+pub struct RangeBody<S> {
+    source: S,
+    size: u64,
+    content_type: String,
+    etag: Option<String>,
+}
+
+impl<S> RangeBody<S>
+where
+    S: AsyncRead + AsyncSeek + Unpin + 'static,
+{
+    /// Create new `RangeBody` for the source of the `size` bytes.
+    pub fn new(source: S, size: u64) -> Self {
+        RangeBody {
+            source,
+            size,
+            content_type: "application/octet-stream".to_string(),
+            etag: None,
+        }
+    }
+
+    /// Set content type of the source, default is `application/octet-stream`.
+    pub fn content_type(mut self, content_type: &str) -> Self {
+        self.content_type = content_type.to_string();
+        self
+    }
+
+    /// Set entity tag of the source.
+    ///
+    /// Entity tag is used for `If-Range` header validation, if it is not set
+    /// requests with `If-Range` header get full body.
+    pub fn etag(mut self, etag: &str) -> Self {
+        self.etag = Some(etag.to_string());
+        self
+    }
+
+    fn into_response(self, req: &HttpRequest) -> Response {
+        let size = self.size;
+        let mut builder = Response::Ok();
+        builder.header(header::ACCEPT_RANGES, "bytes");
+        if let Some(ref etag) = self.etag {
+            builder.header(header::ETAG, etag.as_str());
+        }
+
+        let range = req
+            .headers()
+            .get(header::RANGE)
+            .and_then(|v| v.to_str().ok());
+        let if_range = match req.headers().get(header::IF_RANGE) {
+            // only strong comparison is allowed
+            Some(val) => match self.etag {
+                Some(ref etag) => !etag.starts_with("W/") && val == etag.as_str(),
+                None => false,
+            },
+            None => true,
+        };
+
+        let ranges = match range {
+            Some(range) if if_range => match HttpRange::parse_all(range, size) {
+                Ok(Some(ranges)) if ranges.len() <= MAX_RANGES => Some(ranges),
+                Ok(_) => None,
+                Err(_) => {
+                    log::trace!("Range is not satisfiable: {:?}", range);
+                    return builder
+                        .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                        .header(header::CONTENT_RANGE, format!("bytes */{}", size))
+                        .finish();
+                }
+            },
+            _ => None,
+        };
+
+        let mut chunks = VecDeque::new();
+        match ranges {
+            None => {
+                builder.header(header::CONTENT_TYPE, self.content_type.as_str());
+                if size > 0 {
+                    chunks.push_back(Chunk::Range(HttpRange {
+                        start: 0,
+                        length: size,
+                    }));
+                }
+            }
+            Some(ranges) if ranges.len() == 1 => {
+                let range = ranges[0];
+                builder
+                    .status(StatusCode::PARTIAL_CONTENT)
+                    .header(header::CONTENT_TYPE, self.content_type.as_str())
+                    .header(header::CONTENT_RANGE, content_range(&range, size));
+                chunks.push_back(Chunk::Range(range));
+            }
+            Some(ranges) => {
+                let boundary = format!("{:016x}", WyRand::new().generate::<u64>());
+                builder.status(StatusCode::PARTIAL_CONTENT).header(
+                    header::CONTENT_TYPE,
+                    format!("multipart/byteranges; boundary={}", boundary),
+                );
+
+                for (idx, range) in ranges.into_iter().enumerate() {
+                    let mut part = BytesMut::new();
+                    if idx > 0 {
+                        part.extend_from_slice(b"\r\n");
+                    }
+                    part.extend_from_slice(
+                        format!(
+                            "--{}\r\nContent-Type: {}\r\nContent-Range: {}\r\n\r\n",
+                            boundary,
+                            self.content_type,
+                            content_range(&range, size)
+                        )
+                        .as_bytes(),
+                    );
+                    chunks.push_back(Chunk::Bytes(part.freeze()));
+                    chunks.push_back(Chunk::Range(range));
+                }
+                chunks.push_back(Chunk::Bytes(Bytes::from(format!(
+                    "\r\n--{}--\r\n",
+                    boundary
+                ))));
+            }
+        }
+
+        let length = chunks
+            .iter()
+            .map(|chunk| match chunk {
+                Chunk::Bytes(b) => b.len() as u64,
+                Chunk::Range(r) => r.length,
+            })
+            .sum();
+        if req.method() == Method::HEAD {
+            chunks.clear();
+        }
+
+        builder.body(SizedStream::new(
+            length,
+            RangeStream {
+                chunks,
+                source: self.source,
+                seek: Seek::Start,
+            },
+        ))
+    }
+}
+
+impl<S, Err> Responder<Err> for RangeBody<S>
+where
+    S: AsyncRead + AsyncSeek + Unpin + 'static,
+    Err: ErrorRenderer,
+{
+    type Error = Err::Container;
+    type Future = Ready<Response>;
+
+    fn respond_to(self, req: &HttpRequest) -> Self::Future {
+        self.into_response(req).into()
+    }
+}
+
+fn content_range(range: &HttpRange, size: u64) -> String {
+    format!(
+        "bytes {}-{}/{}",
+        range.start,
+        range.start + range.length - 1,
+        size
+    )
+}
+
+enum Chunk {
+    Bytes(Bytes),
+    Range(HttpRange),
+}
+
+enum Seek {
+    Start,
+    Pending,
+    Done,
+}
+
+/// Stream of body chunks, ranges are read from the source
+struct RangeStream<S> {
+    chunks: VecDeque<Chunk>,
+    source: S,
+    seek: Seek,
+}
+
+impl<S> Stream for RangeStream<S>
+where
+    S: AsyncRead + AsyncSeek + Unpin,
+{
+    type Item = Result<Bytes, Box<dyn Error>>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        let range = match this.chunks.front_mut() {
+            None => return Poll::Ready(None),
+            Some(Chunk::Bytes(_)) => {
+                return match this.chunks.pop_front() {
+                    Some(Chunk::Bytes(b)) => Poll::Ready(Some(Ok(b))),
+                    _ => unreachable!(),
+                };
+            }
+            Some(Chunk::Range(range)) => range,
+        };
+
+        loop {
+            match this.seek {
+                Seek::Start => {
+                    // complete previous operation, if any
+                    match Pin::new(&mut this.source).poll_complete(cx) {
+                        Poll::Ready(Ok(_)) => (),
+                        Poll::Ready(Err(e)) => {
+                            return Poll::Ready(Some(Err(Box::new(e))))
+                        }
+                        Poll::Pending => return Poll::Pending,
+                    }
+                    let pos = SeekFrom::Start(range.start);
+                    if let Err(e) = Pin::new(&mut this.source).start_seek(pos) {
+                        return Poll::Ready(Some(Err(Box::new(e))));
+                    }
+                    this.seek = Seek::Pending;
+                }
+                Seek::Pending => match Pin::new(&mut this.source).poll_complete(cx) {
+                    Poll::Ready(Ok(_)) => this.seek = Seek::Done,
+                    Poll::Ready(Err(e)) => return Poll::Ready(Some(Err(Box::new(e)))),
+                    Poll::Pending => return Poll::Pending,
+                },
+                Seek::Done => break,
+            }
+        }
+
+        let mut buf = vec![0; cmp::min(range.length, CHUNK_SIZE) as usize];
+        let mut read_buf = ReadBuf::new(&mut buf);
+        match Pin::new(&mut this.source).poll_read(cx, &mut read_buf) {
+            Poll::Ready(Ok(())) => (),
+            Poll::Ready(Err(e)) => return Poll::Ready(Some(Err(Box::new(e)))),
+            Poll::Pending => return Poll::Pending,
+        }
+
+        let n = read_buf.filled().len();
+        if n == 0 {
+            let e = io::Error::from(io::ErrorKind::UnexpectedEof);
+            return Poll::Ready(Some(Err(Box::new(e))));
+        }
+        buf.truncate(n);
+
+        range.start += n as u64;
+        range.length -= n as u64;
+        if range.length == 0 {
+            this.chunks.pop_front();
+            this.seek = Seek::Start;
+        }
+        Poll::Ready(Some(Ok(Bytes::from(buf))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::http::body::{MessageBody, ResponseBody};
+    use crate::util::next;
+    use crate::web::test::{respond_to, TestRequest};
+
+    const DATA: &[u8] = b"0123456789abcdefghij";
+
+    fn body() -> RangeBody<Cursor<&'static [u8]>> {
+        RangeBody::new(Cursor::new(DATA), DATA.len() as u64).content_type("text/plain")
+    }
+
+    async fn load(mut body: ResponseBody<crate::http::body::Body>) -> Bytes {
+        let mut bytes = BytesMut::new();
+        while let Some(item) = next(&mut body).await {
+            bytes.extend_from_slice(&item.unwrap());
+        }
+        bytes.freeze()
+    }
+
+    #[test]
+    fn test_parse() {
+        let r = |start, length| Ok(Some(HttpRange { start, length }));
+
+        assert_eq!(HttpRange::parse("bytes=0-9", 100), r(0, 10));
+        assert_eq!(HttpRange::parse("bytes=90-", 100), r(90, 10));
+        assert_eq!(HttpRange::parse("bytes=-10", 100), r(90, 10));
+        assert_eq!(HttpRange::parse("bytes=-200", 100), r(0, 100));
+        assert_eq!(HttpRange::parse("bytes=90-200", 100), r(90, 10));
+        assert_eq!(HttpRange::parse("bytes=200-300, 10-19", 100), r(10, 10));
+        assert_eq!(HttpRange::parse("bytes= 0-0 ,5-", 100), r(0, 1));
+
+        assert_eq!(HttpRange::parse("bytes=100-", 100), Err(()));
+        assert_eq!(HttpRange::parse("bytes=-0", 100), Err(()));
+        assert_eq!(HttpRange::parse("bytes=0-", 0), Err(()));
+        assert_eq!(HttpRange::parse("bytes=", 100), Err(()));
+
+        assert_eq!(HttpRange::parse("items=0-9", 100), Ok(None));
+        assert_eq!(HttpRange::parse("bytes=a-9", 100), Ok(None));
+        assert_eq!(HttpRange::parse("bytes=9-1", 100), Ok(None));
+        assert_eq!(HttpRange::parse("bytes=10", 100), Ok(None));
+    }
+
+    #[test]
+    fn test_parse_all() {
+        let r = |ranges: &[(u64, u64)]| {
+            Ok(Some(
+                ranges
+                    .iter()
+                    .map(|(start, length)| HttpRange {
+                        start: *start,
+                        length: *length,
+                    })
+                    .collect::<Vec<_>>(),
+            ))
+        };
+
+        assert_eq!(HttpRange::parse_all("bytes=0-9", 100), r(&[(0, 10)]));
+        assert_eq!(
+            HttpRange::parse_all("bytes=50-59, 0-9, 200-", 100),
+            r(&[(50, 10), (0, 10)])
+        );
+        // overlapping and adjacent ranges are coalesced
+        assert_eq!(
+            HttpRange::parse_all("bytes=50-59, 0-9, 5-19, 20-29", 100),
+            r(&[(0, 30), (50, 10)])
+        );
+        assert_eq!(HttpRange::parse_all("bytes=0-, -10", 100), r(&[(0, 100)]));
+        assert_eq!(HttpRange::parse_all("bytes=100-", 100), Err(()));
+        assert_eq!(HttpRange::parse_all("bytes=a-", 100), Ok(None));
+    }
+
+    #[crate::rt_test]
+    async fn test_range_body() {
+        // full body
+        let req = TestRequest::default().to_http_request();
+        let mut resp = respond_to(body(), &req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers().get(header::ACCEPT_RANGES).unwrap(), "bytes");
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            "text/plain"
+        );
+        assert_eq!(load(resp.take_body()).await, DATA);
+
+        // single range
+        let req = TestRequest::with_header(header::RANGE, "bytes=5-9").to_http_request();
+        let mut resp = respond_to(body(), &req).await;
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_RANGE).unwrap(),
+            "bytes 5-9/20"
+        );
+        assert_eq!(load(resp.take_body()).await, "56789");
+
+        // suffix range
+        let req = TestRequest::with_header(header::RANGE, "bytes=-3").to_http_request();
+        let mut resp = respond_to(body(), &req).await;
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(load(resp.take_body()).await, "hij");
+
+        // malformed range
+        let req = TestRequest::with_header(header::RANGE, "bytes=x-9").to_http_request();
+        let resp = respond_to(body(), &req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        // not satisfiable
+        let req = TestRequest::with_header(header::RANGE, "bytes=20-").to_http_request();
+        let resp = respond_to(body(), &req).await;
+        assert_eq!(resp.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_RANGE).unwrap(),
+            "bytes */20"
+        );
+    }
+
+    #[crate::rt_test]
+    async fn test_multipart() {
+        let req =
+            TestRequest::with_header(header::RANGE, "bytes=0-1, 18-").to_http_request();
+        let mut resp = respond_to(body(), &req).await;
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+
+        let ct = resp
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .unwrap()
+            .to_str()
+            .unwrap();
+        let boundary = ct
+            .strip_prefix("multipart/byteranges; boundary=")
+            .unwrap()
+            .to_string();
+
+        let body = resp.take_body();
+        let size = match body.size() {
+            crate::http::body::BodySize::Sized(size) => size,
+            _ => panic!(),
+        };
+        let expected = format!(
+            "--{b}\r\nContent-Type: text/plain\r\nContent-Range: bytes 0-1/20\r\n\r\n01\r\n\
+             --{b}\r\nContent-Type: text/plain\r\nContent-Range: bytes 18-19/20\r\n\r\nij\r\n\
+             --{b}--\r\n",
+            b = boundary
+        );
+        let bytes = load(body).await;
+        assert_eq!(bytes, expected.as_bytes());
+        assert_eq!(size, bytes.len() as u64);
+    }
+
+    #[crate::rt_test]
+    async fn test_if_range_and_head() {
+        let etag = "\"abc\"";
+
+        let req = TestRequest::with_header(header::RANGE, "bytes=0-1")
+            .header(header::IF_RANGE, etag)
+            .to_http_request();
+        let resp = respond_to(body(), &req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = TestRequest::with_header(header::RANGE, "bytes=0-1")
+            .header(header::IF_RANGE, etag)
+            .to_http_request();
+        let mut resp = respond_to(body().etag(etag), &req).await;
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(resp.headers().get(header::ETAG).unwrap(), etag);
+        assert_eq!(load(resp.take_body()).await, "01");
+
+        let req = TestRequest::with_header(header::RANGE, "bytes=0-1")
+            .header(header::IF_RANGE, "\"other\"")
+            .to_http_request();
+        let resp = respond_to(body().etag(etag), &req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = TestRequest::with_header(header::RANGE, "bytes=0-1")
+            .method(Method::HEAD)
+            .to_http_request();
+        let mut resp = respond_to(body(), &req).await;
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+        let body = resp.take_body();
+        assert_eq!(body.size(), crate::http::body::BodySize::Sized(2));
+        assert!(load(body).await.is_empty());
+    }
+}
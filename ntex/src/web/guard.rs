@@ -26,10 +26,12 @@
 //! }
 //! ```
 #![allow(non_snake_case)]
-use std::convert::TryFrom;
+use std::{convert::TryFrom, future::Future, pin::Pin};
 
 use crate::http::{header, RequestHead, Uri};
 
+use super::httprequest::HttpRequest;
+
 /// Trait defines resource guards. Guards are used for route selection.
 ///
 /// Guards can not modify the request object. But it is possible
@@ -38,6 +40,13 @@ use crate::http::{header, RequestHead, Uri};
 pub trait Guard {
     /// Check if request matches predicate
     fn check(&self, request: &RequestHead) -> bool;
+
+    /// Http method matched by the guard.
+    ///
+    /// Router uses it for *405 Method Not Allowed* responses.
+    fn method(&self) -> Option<&http::Method> {
+        None
+    }
 }
 
 /// Create guard object for supplied function.
@@ -83,6 +92,71 @@ where
     }
 }
 
+/// Trait defines async route guards.
+///
+/// Async guards are checked after all sync guards of the route matched.
+/// Guard borrows the request for the duration of the check, so it could
+/// inspect application state. If guard rejects request, next route of
+/// the same resource is checked and then resource's default service.
+/// Rejected request does not fall through to other resources or scopes.
+///
+/// Async guard could be implemented for `async fn(&HttpRequest) -> bool`.
+///
+/// ```rust
+/// use ntex::web::{self, App, HttpRequest, HttpResponse};
+///
+/// async fn has_state(req: &HttpRequest) -> bool {
+///     req.app_data::<web::types::Data<String>>().is_some()
+/// }
+///
+/// fn main() {
+///     App::new().service(web::resource("/index.html").route(
+///         web::route()
+///             .async_guard(has_state)
+///             .to(|| async { HttpResponse::Ok() }))
+///     );
+/// }
+/// ```
+pub trait AsyncGuard {
+    /// Check if request matches predicate
+    fn check<'a>(
+        &'a self,
+        request: &'a HttpRequest,
+    ) -> Pin<Box<dyn Future<Output = bool> + 'a>>;
+}
+
+#[doc(hidden)]
+/// Helper trait for async guard functions that borrow the request
+pub trait AsyncGuardFn<'a> {
+    type Future: Future<Output = bool> + 'a;
+
+    fn call(&self, request: &'a HttpRequest) -> Self::Future;
+}
+
+impl<'a, F, R> AsyncGuardFn<'a> for F
+where
+    F: Fn(&'a HttpRequest) -> R,
+    R: Future<Output = bool> + 'a,
+{
+    type Future = R;
+
+    fn call(&self, request: &'a HttpRequest) -> R {
+        (self)(request)
+    }
+}
+
+impl<F> AsyncGuard for F
+where
+    F: for<'a> AsyncGuardFn<'a>,
+{
+    fn check<'a>(
+        &'a self,
+        request: &'a HttpRequest,
+    ) -> Pin<Box<dyn Future<Output = bool> + 'a>> {
+        Box::pin(AsyncGuardFn::call(self, request))
+    }
+}
+
 /// Return guard that matches if any of supplied guards.
 ///
 /// ```rust
@@ -184,6 +258,10 @@ impl Guard for MethodGuard {
     fn check(&self, request: &RequestHead) -> bool {
         request.method == self.0
     }
+
+    fn method(&self) -> Option<&http::Method> {
+        Some(&self.0)
+    }
 }
 
 /// Guard to match *GET* http method
@@ -259,6 +337,10 @@ impl Guard for HeaderGuard {
 
 /// Return predicate that matches if request contains specified Host name.
 ///
+/// Host name is compared case-insensitively. Leading `*` matches any
+/// subdomain, i.e. `*.example.com` matches `api.example.com` but
+/// does not match `example.com`.
+///
 /// ```rust
 /// use ntex::web::{self, guard::Host, App, HttpResponse};
 ///
@@ -285,6 +367,7 @@ fn get_host_uri(req: &RequestHead) -> Option<Uri> {
 }
 
 #[doc(hidden)]
+#[derive(Clone, Debug)]
 pub struct HostGuard(String, Option<String>);
 
 impl HostGuard {
@@ -304,7 +387,7 @@ impl Guard for HostGuard {
         };
 
         if let Some(uri_host) = req_host_uri.host() {
-            if self.0 != uri_host {
+            if !host_matches(&self.0, uri_host) {
                 return false;
             }
         } else {
@@ -321,6 +404,17 @@ impl Guard for HostGuard {
     }
 }
 
+/// Match host name against pattern with optional leading wildcard
+fn host_matches(pattern: &str, host: &str) -> bool {
+    if let Some(suffix) = pattern.strip_prefix('*') {
+        host.len() > suffix.len()
+            && host.as_bytes()[host.len() - suffix.len()..]
+                .eq_ignore_ascii_case(suffix.as_bytes())
+    } else {
+        pattern.eq_ignore_ascii_case(host)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -370,6 +464,30 @@ mod tests {
         assert!(!pred.check(req.head()));
     }
 
+    #[test]
+    fn test_host_wildcard() {
+        let req = TestRequest::default()
+            .header(
+                header::HOST,
+                header::HeaderValue::from_static("API.example.com:8080"),
+            )
+            .to_http_request();
+
+        assert!(Host("api.example.com").check(req.head()));
+        assert!(Host("*.example.com").check(req.head()));
+        assert!(Host("*").check(req.head()));
+        assert!(!Host("*.api.example.com").check(req.head()));
+        assert!(!Host("*.rust-lang.org").check(req.head()));
+
+        let req = TestRequest::default()
+            .header(
+                header::HOST,
+                header::HeaderValue::from_static("example.com"),
+            )
+            .to_http_request();
+        assert!(!Host("*.example.com").check(req.head()));
+    }
+
     #[test]
     fn test_host_scheme() {
         let req = TestRequest::default()
@@ -3,6 +3,7 @@ use std::{future::Future, marker::PhantomData, pin::Pin, task::Context, task::Po
 use super::error::ErrorRenderer;
 use super::extract::FromRequest;
 use super::httprequest::HttpRequest;
+use super::middleware::timeout::{Deadline, DeadlineWait};
 use super::request::WebRequest;
 use super::responder::Responder;
 use super::response::WebResponse;
@@ -89,6 +90,7 @@ where
             from_request: Some(T::from_request(&req, &mut payload)),
             handler: None,
             responder: None,
+            deadline: Deadline::get(&req),
             req: Some(req),
         })
     }
@@ -117,6 +119,7 @@ pin_project_lite::pin_project! {
         handler: Option<F::Future>,
         #[pin]
         responder: Option<<F::Output as Responder<Err>>::Future>,
+        deadline: Option<DeadlineWait>,
         req: Option<HttpRequest>,
     }
 }
@@ -132,6 +135,42 @@ where
     type Output = Result<WebResponse, Err::Container>;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.as_mut().poll_handler(cx) {
+            Poll::Ready(Ok(res)) => {
+                if let Some(deadline) = self.as_mut().project().deadline.take() {
+                    Poll::Ready(Ok(deadline.wrap_body(res)))
+                } else {
+                    Poll::Ready(Ok(res))
+                }
+            }
+            Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
+            Poll::Pending => {
+                let this = self.as_mut().project();
+                match this.deadline {
+                    Some(ref deadline) if deadline.poll_expired(cx) => {
+                        let req = this.req.take().unwrap();
+                        let res = deadline.response(&req);
+                        Poll::Ready(Ok(WebResponse::new(res, req)))
+                    }
+                    _ => Poll::Pending,
+                }
+            }
+        }
+    }
+}
+
+impl<F, T, Err> HandlerWrapperResponse<F, T, Err>
+where
+    F: Handler<T, Err>,
+    T: FromRequest<Err>,
+    T::Error: Into<Err::Container>,
+    <F::Output as Responder<Err>>::Error: Into<Err::Container>,
+    Err: ErrorRenderer,
+{
+    fn poll_handler(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<WebResponse, Err::Container>> {
         let mut this = self.as_mut().project();
 
         if let Some(fut) = this.from_request.as_pin_mut() {
@@ -141,7 +180,7 @@ where
                     this = self.as_mut().project();
                     this.from_request.set(None);
                     this.handler.set(Some(fut));
-                    self.poll(cx)
+                    self.poll_handler(cx)
                 }
                 Poll::Pending => Poll::Pending,
                 Poll::Ready(Err(e)) => Poll::Ready(Ok(WebResponse::from_err::<Err, _>(
@@ -158,7 +197,7 @@ where
                     this = self.as_mut().project();
                     this.handler.set(None);
                     this.responder.set(Some(fut));
-                    self.poll(cx)
+                    self.poll_handler(cx)
                 }
                 Poll::Pending => Poll::Pending,
             };
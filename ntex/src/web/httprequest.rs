@@ -1,5 +1,7 @@
 use std::{cell::Ref, cell::RefCell, cell::RefMut, fmt, net, rc::Rc};
 
+use crate::framed::OnDisconnect;
+use crate::http::disconnect::Disconnect;
 use crate::http::{
     HeaderMap, HttpMessage, Message, Method, Payload, RequestHead, Uri, Version,
 };
@@ -7,6 +9,8 @@ use crate::router::Path;
 use crate::util::{Extensions, Ready};
 
 use super::config::AppConfig;
+#[cfg(feature = "secure-cookie")]
+use super::cookie::{Cookie, CookieKeys};
 use super::error::ErrorRenderer;
 use super::extract::FromRequest;
 use super::info::ConnectionInfo;
@@ -20,7 +24,7 @@ pub(crate) struct HttpRequestInner {
     pub(crate) head: Message<RequestHead>,
     pub(crate) path: Path<Uri>,
     pub(crate) payload: Payload,
-    pub(crate) app_data: Rc<Extensions>,
+    pub(crate) app_data: Vec<Rc<Extensions>>,
     rmap: Rc<ResourceMap>,
     config: AppConfig,
     pool: &'static HttpRequestPool,
@@ -41,7 +45,7 @@ impl HttpRequest {
             head,
             path,
             payload,
-            app_data,
+            app_data: vec![app_data],
             rmap,
             config,
             pool,
@@ -136,6 +140,11 @@ impl HttpRequest {
     #[cfg(feature = "url")]
     /// Generate url for named resource
     ///
+    /// Resource could be registered anywhere in the application, generated
+    /// url includes prefixes of all parent scopes. Scheme and host are taken
+    /// from request's `ConnectionInfo`, external resources generate
+    /// url as is.
+    ///
     /// ```rust
     /// # use ntex::web::{self, App, HttpRequest, HttpResponse};
     /// #
@@ -194,6 +203,57 @@ impl HttpRequest {
         self.head().peer_addr
     }
 
+    #[cfg(feature = "secure-cookie")]
+    /// Return signed request cookie
+    ///
+    /// Cookie is verified with keys configured with `App::app_data(CookieKeys)`,
+    /// returns `None` if cookie is missing or verification fails.
+    pub fn signed_cookie(&self, name: &str) -> Option<Cookie<'static>> {
+        self.cookie_keys()?.verify(self.cookie(name)?)
+    }
+
+    #[cfg(feature = "secure-cookie")]
+    /// Return decrypted private request cookie
+    ///
+    /// Cookie is decrypted with keys configured with `App::app_data(CookieKeys)`,
+    /// returns `None` if cookie is missing or decryption fails.
+    pub fn private_cookie(&self, name: &str) -> Option<Cookie<'static>> {
+        self.cookie_keys()?.decrypt(self.cookie(name)?)
+    }
+
+    #[cfg(feature = "secure-cookie")]
+    fn cookie_keys(&self) -> Option<&CookieKeys> {
+        let keys = self.app_data::<CookieKeys>();
+        if keys.is_none() {
+            log::error!("Cookie keys are not configured, use App::app_data(CookieKeys)");
+        }
+        keys
+    }
+
+    /// Future that resolves when client connection get disconnected
+    ///
+    /// Long running handlers could use it to abort work when client goes away.
+    /// Returns `None` if request is not bound to a connection, for example
+    /// requests created with `TestRequest`.
+    ///
+    /// ```rust
+    /// use ntex::web::{HttpRequest, HttpResponse};
+    ///
+    /// async fn index(req: HttpRequest) -> HttpResponse {
+    ///     if let Some(disconnect) = req.on_disconnect() {
+    ///         ntex::rt::spawn(async move {
+    ///             disconnect.await;
+    ///             println!("client is gone");
+    ///         });
+    ///     }
+    ///     HttpResponse::Ok().finish()
+    /// }
+    /// ```
+    #[inline]
+    pub fn on_disconnect(&self) -> Option<OnDisconnect> {
+        Disconnect::get(&self.head().extensions())
+    }
+
     /// Get *ConnectionInfo* for the current request.
     ///
     /// This method panics if request's extensions container is already
@@ -212,13 +272,20 @@ impl HttpRequest {
     /// Get an application data object stored with `App::data` or `App::app_data`
     /// methods during application configuration.
     ///
+    /// Data registered on a scope or a resource shadows data of the same type
+    /// registered on the outer scopes or the application.
+    ///
     /// If `App::data` was used to store object, use `Data<T>`:
     ///
     /// ```rust,ignore
     /// let opt_t = req.app_data::<Data<T>>();
     /// ```
     pub fn app_data<T: 'static>(&self) -> Option<&T> {
-        self.0.app_data.get::<T>()
+        self.0
+            .app_data
+            .iter()
+            .rev()
+            .find_map(|data| data.get::<T>())
     }
 }
 
@@ -1,4 +1,4 @@
-use std::cell::Ref;
+use std::{cell::Ref, net::IpAddr};
 
 use crate::http::header::{self, HeaderName};
 use crate::http::RequestHead;
@@ -30,34 +30,39 @@ impl ConnectionInfo {
     fn new(req: &RequestHead, cfg: &AppConfig) -> ConnectionInfo {
         let mut host = None;
         let mut scheme = None;
-        let mut remote = None;
-        let mut peer = None;
+        let mut remote = Vec::new();
+
+        // forwarding headers are used only if peer is a trusted proxy
+        let trusted = cfg.trusted_proxies();
+        let forwarded = trusted.map_or(true, |proxies| {
+            req.peer_addr
+                .map(|addr| is_trusted(proxies, addr.ip()))
+                .unwrap_or(false)
+        });
 
         // load forwarded header
-        for hdr in req.headers.get_all(&header::FORWARDED) {
-            if let Ok(val) = hdr.to_str() {
-                for pair in val.split(';') {
-                    for el in pair.split(',') {
-                        let mut items = el.trim().splitn(2, '=');
-                        if let Some(name) = items.next() {
-                            if let Some(val) = items.next() {
-                                match &name.to_lowercase() as &str {
-                                    "for" => {
-                                        if remote.is_none() {
-                                            remote = Some(val.trim());
+        if forwarded {
+            for hdr in req.headers.get_all(&header::FORWARDED) {
+                if let Ok(val) = hdr.to_str() {
+                    for pair in val.split(';') {
+                        for el in pair.split(',') {
+                            let mut items = el.trim().splitn(2, '=');
+                            if let Some(name) = items.next() {
+                                if let Some(val) = items.next() {
+                                    match &name.to_lowercase() as &str {
+                                        "for" => remote.push(forwarded_node(val.trim())),
+                                        "proto" => {
+                                            if scheme.is_none() {
+                                                scheme = Some(val.trim());
+                                            }
                                         }
-                                    }
-                                    "proto" => {
-                                        if scheme.is_none() {
-                                            scheme = Some(val.trim());
+                                        "host" => {
+                                            if host.is_none() {
+                                                host = Some(val.trim());
+                                            }
                                         }
+                                        _ => (),
                                     }
-                                    "host" => {
-                                        if host.is_none() {
-                                            host = Some(val.trim());
-                                        }
-                                    }
-                                    _ => (),
                                 }
                             }
                         }
@@ -68,12 +73,14 @@ impl ConnectionInfo {
 
         // scheme
         if scheme.is_none() {
-            if let Some(h) = req
-                .headers
-                .get(&HeaderName::from_lowercase(X_FORWARDED_PROTO).unwrap())
-            {
-                if let Ok(h) = h.to_str() {
-                    scheme = h.split(',').next().map(|v| v.trim());
+            if forwarded {
+                if let Some(h) = req
+                    .headers
+                    .get(&HeaderName::from_lowercase(X_FORWARDED_PROTO).unwrap())
+                {
+                    if let Ok(h) = h.to_str() {
+                        scheme = h.split(',').next().map(|v| v.trim());
+                    }
                 }
             }
             if scheme.is_none() {
@@ -86,12 +93,14 @@ impl ConnectionInfo {
 
         // host
         if host.is_none() {
-            if let Some(h) = req
-                .headers
-                .get(&HeaderName::from_lowercase(X_FORWARDED_HOST).unwrap())
-            {
-                if let Ok(h) = h.to_str() {
-                    host = h.split(',').next().map(|v| v.trim());
+            if forwarded {
+                if let Some(h) = req
+                    .headers
+                    .get(&HeaderName::from_lowercase(X_FORWARDED_HOST).unwrap())
+                {
+                    if let Ok(h) = h.to_str() {
+                        host = h.split(',').next().map(|v| v.trim());
+                    }
                 }
             }
             if host.is_none() {
@@ -108,26 +117,35 @@ impl ConnectionInfo {
         }
 
         // remote addr
-        if remote.is_none() {
-            if let Some(h) = req
+        if remote.is_empty() && forwarded {
+            for hdr in req
                 .headers
-                .get(&HeaderName::from_lowercase(X_FORWARDED_FOR).unwrap())
+                .get_all(&HeaderName::from_lowercase(X_FORWARDED_FOR).unwrap())
             {
-                if let Ok(h) = h.to_str() {
-                    remote = h.split(',').next().map(|v| v.trim());
+                if let Ok(h) = hdr.to_str() {
+                    remote.extend(h.split(',').map(|v| v.trim()));
                 }
             }
-            if remote.is_none() {
-                // get peeraddr from socketaddr
-                peer = req.peer_addr.map(|addr| format!("{}", addr));
-            }
         }
+        let remote = if let Some(proxies) = trusted {
+            // closest to the server address that is not a trusted proxy
+            remote
+                .iter()
+                .rev()
+                .find(|addr| parse_ip(addr).map_or(true, |ip| !is_trusted(proxies, ip)))
+                .or_else(|| remote.first())
+        } else {
+            remote.first()
+        };
+
+        // get peeraddr from socketaddr
+        let peer = req.peer_addr.map(|addr| format!("{}", addr));
 
         ConnectionInfo {
             peer,
             scheme: scheme.unwrap_or("http").to_owned(),
             host: host.unwrap_or("localhost").to_owned(),
-            remote: remote.map(|s| s.to_owned()),
+            remote: remote.map(|s| s.to_string()),
         }
     }
 
@@ -164,13 +182,20 @@ impl ConnectionInfo {
     /// - X-Forwarded-For
     /// - peer name of opened socket
     ///
+    /// If trusted proxies are configured, headers are used only for requests
+    /// from trusted proxies and the addr is the last one in the forwarding
+    /// chain that is not a trusted proxy.
+    ///
+    /// Quoted `Forwarded` nodes are unquoted, IPv6 addrs are returned without
+    /// brackets unless the node contains a port, i.e. `[2001:db8::1]:4711`.
+    ///
     /// # Security
-    /// Do not use this function for security purposes, unless you can ensure the Forwarded and
-    /// X-Forwarded-For headers cannot be spoofed by the client. If you want the client's socket
-    /// address explicitly, use
+    /// Without trusted proxies configuration, Forwarded and X-Forwarded-For headers
+    /// could be spoofed by the client. Use `HttpServer::trusted_proxy()` to configure
+    /// trusted proxies. If you want the client's socket address explicitly, use
     /// [`HttpRequest::peer_addr()`](../web/struct.HttpRequest.html#method.peer_addr) instead.
     #[inline]
-    pub fn remote(&self) -> Option<&str> {
+    pub fn realip_remote_addr(&self) -> Option<&str> {
         if let Some(ref r) = self.remote {
             Some(r)
         } else if let Some(ref peer) = self.peer {
@@ -179,6 +204,77 @@ impl ConnectionInfo {
             None
         }
     }
+
+    /// Remote socket addr of client initiated HTTP request.
+    ///
+    /// Same as `realip_remote_addr()`.
+    #[inline]
+    pub fn remote(&self) -> Option<&str> {
+        self.realip_remote_addr()
+    }
+}
+
+/// Trusted proxy network
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Cidr {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    /// Parse network in `addr/prefix` or `addr` form
+    pub(crate) fn parse(s: &str) -> Option<Cidr> {
+        let mut items = s.trim().splitn(2, '/');
+        let addr: IpAddr = items.next()?.parse().ok()?;
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = if let Some(prefix) = items.next() {
+            prefix.parse().ok().filter(|p| *p <= max)?
+        } else {
+            max
+        };
+        Some(Cidr { addr, prefix })
+    }
+
+    fn contains(&self, ip: IpAddr) -> bool {
+        let (net, ip, bits) = match (self.addr, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                (u32::from(net) as u128, u32::from(ip) as u128, 32)
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => (u128::from(net), u128::from(ip), 128),
+            _ => return false,
+        };
+        (net ^ ip)
+            .checked_shr(bits - self.prefix as u32)
+            .unwrap_or(0)
+            == 0
+    }
+}
+
+fn is_trusted(proxies: &[Cidr], ip: IpAddr) -> bool {
+    proxies.iter().any(|net| net.contains(ip))
+}
+
+/// Unquote forwarding header node, i.e. `"[2001:db8::1]:4711"` becomes
+/// `[2001:db8::1]:4711` and `"[2001:db8::1]"` becomes `2001:db8::1`
+fn forwarded_node(node: &str) -> &str {
+    let node = node.trim_matches('"');
+    node.strip_prefix('[')
+        .and_then(|n| n.strip_suffix(']'))
+        .unwrap_or(node)
+}
+
+/// Parse ip from forwarding header node, i.e. `"[2001:db8::1]:4711"`
+fn parse_ip(node: &str) -> Option<IpAddr> {
+    let node = node.trim_matches('"');
+    if let Some(node) = node.strip_prefix('[') {
+        node.splitn(2, ']').next()?.parse().ok()
+    } else if let Ok(ip) = node.parse() {
+        Some(ip)
+    } else {
+        node.parse::<std::net::SocketAddr>()
+            .ok()
+            .map(|addr| addr.ip())
+    }
 }
 
 #[cfg(test)]
@@ -233,4 +329,107 @@ mod tests {
         let info = req.connection_info();
         assert_eq!(info.scheme(), "https");
     }
+
+    #[test]
+    fn test_trusted_proxies() {
+        let cfg = AppConfig::default().set_trusted_proxies(Some(vec![
+            Cidr::parse("10.0.0.0/8").unwrap(),
+            Cidr::parse("fd00::/8").unwrap(),
+        ]));
+
+        // untrusted peer
+        let req = TestRequest::default()
+            .peer_addr("192.0.2.1:1234".parse().unwrap())
+            .header(
+                header::FORWARDED,
+                "for=192.0.2.60; proto=https; host=rust-lang.org",
+            )
+            .header(X_FORWARDED_FOR, "192.0.2.61")
+            .header(header::HOST, "example.com")
+            .to_http_request();
+        let info = ConnectionInfo::new(req.head(), &cfg);
+        assert_eq!(info.scheme(), "http");
+        assert_eq!(info.host(), "example.com");
+        assert_eq!(info.realip_remote_addr(), Some("192.0.2.1:1234"));
+
+        // trusted peer
+        let req = TestRequest::default()
+            .peer_addr("10.0.0.1:1234".parse().unwrap())
+            .header(
+                header::FORWARDED,
+                "for=192.0.2.60; proto=https; host=rust-lang.org",
+            )
+            .to_http_request();
+        let info = ConnectionInfo::new(req.head(), &cfg);
+        assert_eq!(info.scheme(), "https");
+        assert_eq!(info.host(), "rust-lang.org");
+        assert_eq!(info.realip_remote_addr(), Some("192.0.2.60"));
+
+        // skip trusted proxies in forwarding chain
+        let req = TestRequest::default()
+            .peer_addr("[fd00::1]:1234".parse().unwrap())
+            .header(X_FORWARDED_FOR, "192.0.2.1, 192.0.2.60, 10.1.1.1")
+            .header(X_FORWARDED_PROTO, "https")
+            .header(X_FORWARDED_HOST, "rust-lang.org")
+            .to_http_request();
+        let info = ConnectionInfo::new(req.head(), &cfg);
+        assert_eq!(info.scheme(), "https");
+        assert_eq!(info.host(), "rust-lang.org");
+        assert_eq!(info.realip_remote_addr(), Some("192.0.2.60"));
+
+        let req = TestRequest::default()
+            .peer_addr("10.0.0.1:1234".parse().unwrap())
+            .header(
+                header::FORWARDED,
+                "for=\"[2001:db8::1]:4711\", for=10.0.0.2",
+            )
+            .to_http_request();
+        let info = ConnectionInfo::new(req.head(), &cfg);
+        assert_eq!(info.realip_remote_addr(), Some("[2001:db8::1]:4711"));
+
+        let req = TestRequest::default()
+            .peer_addr("10.0.0.1:1234".parse().unwrap())
+            .header(header::FORWARDED, "for=\"[2001:db8::2]\"")
+            .to_http_request();
+        let info = ConnectionInfo::new(req.head(), &cfg);
+        assert_eq!(info.realip_remote_addr(), Some("2001:db8::2"));
+
+        // all addrs are trusted
+        let req = TestRequest::default()
+            .peer_addr("10.0.0.1:1234".parse().unwrap())
+            .header(X_FORWARDED_FOR, "10.0.0.3, 10.0.0.2")
+            .to_http_request();
+        let info = ConnectionInfo::new(req.head(), &cfg);
+        assert_eq!(info.realip_remote_addr(), Some("10.0.0.3"));
+    }
+
+    #[test]
+    fn test_cidr() {
+        let net = Cidr::parse("192.168.0.0/16").unwrap();
+        assert!(net.contains("192.168.10.1".parse().unwrap()));
+        assert!(!net.contains("192.169.0.1".parse().unwrap()));
+        assert!(!net.contains("::1".parse().unwrap()));
+
+        let net = Cidr::parse("127.0.0.1").unwrap();
+        assert!(net.contains("127.0.0.1".parse().unwrap()));
+        assert!(!net.contains("127.0.0.2".parse().unwrap()));
+
+        let net = Cidr::parse("::/0").unwrap();
+        assert!(net.contains("2001:db8::1".parse().unwrap()));
+
+        assert!(Cidr::parse("10.0.0.0/33").is_none());
+        assert!(Cidr::parse("localhost").is_none());
+        assert_eq!(
+            parse_ip("\"[2001:db8::1]:4711\""),
+            "2001:db8::1".parse().ok()
+        );
+        assert_eq!(parse_ip("192.0.2.1:80"), "192.0.2.1".parse().ok());
+        assert_eq!(parse_ip("unknown"), None);
+        assert_eq!(
+            forwarded_node("\"[2001:db8::1]:4711\""),
+            "[2001:db8::1]:4711"
+        );
+        assert_eq!(forwarded_node("\"[2001:db8::1]\""), "2001:db8::1");
+        assert_eq!(forwarded_node("192.0.2.60"), "192.0.2.60");
+    }
 }
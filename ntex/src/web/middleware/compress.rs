@@ -2,8 +2,9 @@
 use std::task::{Context, Poll};
 use std::{cmp, future::Future, marker, pin::Pin, str::FromStr};
 
+use crate::http::body::{BodySize, MessageBody};
 use crate::http::encoding::Encoder;
-use crate::http::header::{ContentEncoding, ACCEPT_ENCODING};
+use crate::http::header::{ContentEncoding, HeaderValue, ACCEPT_ENCODING, VARY};
 use crate::service::{Service, Transform};
 use crate::util::Ready;
 
@@ -16,12 +17,14 @@ use crate::web::{BodyEncoding, ErrorRenderer};
 /// Use `BodyEncoding` trait for overriding response compression.
 /// To disable compression set encoding to `ContentEncoding::Identity` value.
 ///
+/// Middleware adds `Vary: Accept-Encoding` header to negotiated responses.
+///
 /// ```rust
 /// use ntex::web::{self, middleware, App, HttpResponse};
 ///
 /// fn main() {
 ///     let app = App::new()
-///         .wrap(middleware::Compress::default())
+///         .wrap(middleware::Compress::default().min_size(256))
 ///         .service(
 ///             web::resource("/test")
 ///                 .route(web::get().to(|| async { HttpResponse::Ok() }))
@@ -31,12 +34,37 @@ use crate::web::{BodyEncoding, ErrorRenderer};
 /// ```
 pub struct Compress {
     enc: ContentEncoding,
+    level: Option<u32>,
+    min_size: usize,
 }
 
 impl Compress {
     /// Create new `Compress` middleware with default encoding.
     pub fn new(encoding: ContentEncoding) -> Self {
-        Compress { enc: encoding }
+        Compress {
+            enc: encoding,
+            level: None,
+            min_size: 0,
+        }
+    }
+
+    /// Set compression level.
+    ///
+    /// Level is clamped to the range supported by selected encoding,
+    /// 0-9 for gzip and deflate, 0-11 for brotli and 1-22 for zstd.
+    /// By default fast compression level is used.
+    pub fn level(mut self, level: u32) -> Self {
+        self.level = Some(level);
+        self
+    }
+
+    /// Set minimal size of response body to compress.
+    ///
+    /// Bodies of known size smaller than this value are sent uncompressed,
+    /// streaming bodies are always compressed. By default is 0.
+    pub fn min_size(mut self, size: usize) -> Self {
+        self.min_size = size;
+        self
     }
 }
 
@@ -62,6 +90,8 @@ where
         Ready::Ok(CompressMiddleware {
             service,
             encoding: self.enc,
+            level: self.level,
+            min_size: self.min_size,
             _t: marker::PhantomData,
         })
     }
@@ -70,6 +100,8 @@ where
 pub struct CompressMiddleware<S, E> {
     service: S,
     encoding: ContentEncoding,
+    level: Option<u32>,
+    min_size: usize,
     _t: marker::PhantomData<E>,
 }
 
@@ -107,6 +139,9 @@ where
 
         CompressResponse {
             encoding,
+            vary: self.encoding != ContentEncoding::Identity,
+            level: self.level,
+            min_size: self.min_size,
             fut: self.service.call(req),
             _t: marker::PhantomData,
         }
@@ -120,6 +155,9 @@ pin_project_lite::pin_project! {
         #[pin]
         fut: S::Future,
         encoding: ContentEncoding,
+        vary: bool,
+        level: Option<u32>,
+        min_size: usize,
         _t: marker::PhantomData<E>,
     }
 }
@@ -135,22 +173,51 @@ where
         let this = self.project();
 
         match this.fut.poll(cx)? {
-            Poll::Ready(resp) => {
+            Poll::Ready(mut resp) => {
+                // body is too small
+                if let BodySize::Sized(size) = resp.response().body().size() {
+                    if size < *this.min_size as u64 {
+                        return Poll::Ready(Ok(resp));
+                    }
+                }
+
                 let enc = if let Some(enc) = resp.response().get_encoding() {
                     enc
                 } else {
+                    if *this.vary {
+                        add_vary(&mut resp);
+                    }
                     *this.encoding
                 };
 
-                Poll::Ready(Ok(
-                    resp.map_body(move |head, body| Encoder::response(enc, head, body))
-                ))
+                let level = *this.level;
+                Poll::Ready(Ok(resp.map_body(move |head, body| {
+                    Encoder::response_with_level(enc, level, head, body)
+                })))
             }
             Poll::Pending => Poll::Pending,
         }
     }
 }
 
+/// Add `Accept-Encoding` to `Vary` header
+fn add_vary(resp: &mut WebResponse) {
+    let exists = resp.headers().get_all(&VARY).any(|hdr| {
+        hdr.to_str()
+            .map(|s| {
+                s.split(',').any(|v| {
+                    let v = v.trim();
+                    v == "*" || v.eq_ignore_ascii_case("accept-encoding")
+                })
+            })
+            .unwrap_or(false)
+    });
+    if !exists {
+        resp.headers_mut()
+            .append(VARY, HeaderValue::from_static("Accept-Encoding"));
+    }
+}
+
 struct AcceptEncoding {
     encoding: ContentEncoding,
     quality: f64,
@@ -216,3 +283,131 @@ impl AcceptEncoding {
         ContentEncoding::Identity
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::*;
+    use crate::http::header::CONTENT_ENCODING;
+    use crate::web::test::{call_service, init_service, read_body, TestRequest};
+    use crate::web::{self, App, HttpResponse};
+
+    const DATA: &str = "Hello World Hello World Hello World Hello World Hello World \
+                        Hello World Hello World Hello World Hello World Hello World";
+
+    #[crate::rt_test]
+    async fn test_zstd() {
+        let srv = init_service(App::new().wrap(Compress::default()).route(
+            "/",
+            web::get().to(|| async { HttpResponse::Ok().body(DATA) }),
+        ))
+        .await;
+
+        let req =
+            TestRequest::with_header(ACCEPT_ENCODING, "gzip;q=0.5, zstd").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.headers().get(CONTENT_ENCODING).unwrap(), "zstd");
+        assert_eq!(resp.headers().get(VARY).unwrap(), "Accept-Encoding");
+
+        let body = read_body(resp).await;
+        let data = zstd::stream::decode_all(&body[..]).unwrap();
+        assert_eq!(data, DATA.as_bytes());
+
+        // no compression, but response varies
+        let resp = call_service(&srv, TestRequest::default().to_request()).await;
+        assert!(!resp.headers().contains_key(CONTENT_ENCODING));
+        assert_eq!(resp.headers().get(VARY).unwrap(), "Accept-Encoding");
+    }
+
+    #[crate::rt_test]
+    async fn test_level() {
+        let srv = init_service(
+            App::new()
+                .wrap(Compress::new(ContentEncoding::Gzip).level(100))
+                .route(
+                    "/",
+                    web::get().to(|| async { HttpResponse::Ok().body(DATA) }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::with_header(ACCEPT_ENCODING, "gzip").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.headers().get(CONTENT_ENCODING).unwrap(), "gzip");
+
+        let body = read_body(resp).await;
+        let mut data = String::new();
+        flate2::read::GzDecoder::new(&body[..])
+            .read_to_string(&mut data)
+            .unwrap();
+        assert_eq!(data, DATA);
+    }
+
+    #[crate::rt_test]
+    async fn test_min_size() {
+        let srv = init_service(
+            App::new()
+                .wrap(Compress::default().min_size(64))
+                .route(
+                    "/small",
+                    web::get().to(|| async { HttpResponse::Ok().body("small") }),
+                )
+                .route(
+                    "/",
+                    web::get().to(|| async { HttpResponse::Ok().body(DATA) }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/small")
+            .header(ACCEPT_ENCODING, "br")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert!(!resp.headers().contains_key(CONTENT_ENCODING));
+        assert!(!resp.headers().contains_key(VARY));
+        assert_eq!(read_body(resp).await, "small");
+
+        let req = TestRequest::with_header(ACCEPT_ENCODING, "br").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.headers().get(CONTENT_ENCODING).unwrap(), "br");
+    }
+
+    #[crate::rt_test]
+    async fn test_vary() {
+        let srv = init_service(
+            App::new()
+                .wrap(Compress::default())
+                .route(
+                    "/origin",
+                    web::get().to(|| async {
+                        HttpResponse::Ok().header(VARY, "Origin").body(DATA)
+                    }),
+                )
+                .route(
+                    "/exists",
+                    web::get().to(|| async {
+                        HttpResponse::Ok()
+                            .header(VARY, "Origin, accept-encoding")
+                            .body(DATA)
+                    }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/origin")
+            .header(ACCEPT_ENCODING, "gzip")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        let vary: Vec<_> = resp.headers().get_all(VARY).collect();
+        assert_eq!(vary.len(), 2);
+        assert!(vary.contains(&&HeaderValue::from_static("Origin")));
+        assert!(vary.contains(&&HeaderValue::from_static("Accept-Encoding")));
+
+        let req = TestRequest::with_uri("/exists")
+            .header(ACCEPT_ENCODING, "gzip")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.headers().get_all(VARY).count(), 1);
+    }
+}
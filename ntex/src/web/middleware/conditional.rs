@@ -0,0 +1,338 @@
+//! `Middleware` for conditional requests handling.
+use std::task::{Context, Poll};
+use std::{future::Future, marker::PhantomData, pin::Pin, time::SystemTime};
+
+use sha1::{Digest, Sha1};
+
+use crate::http::body::{Body, ResponseBody};
+use crate::http::header::{self, HeaderName, HeaderValue};
+use crate::http::{Method, Response, StatusCode};
+use crate::service::{Service, Transform};
+use crate::util::Ready;
+use crate::web::dev::{WebRequest, WebResponse};
+
+/// Headers that are preserved in *304 Not Modified* response
+const NOT_MODIFIED_HEADERS: [HeaderName; 7] = [
+    header::CACHE_CONTROL,
+    header::CONTENT_LOCATION,
+    header::DATE,
+    header::ETAG,
+    header::EXPIRES,
+    header::LAST_MODIFIED,
+    header::VARY,
+];
+
+#[derive(Debug, Clone, Default)]
+/// `Middleware` for conditional requests handling.
+///
+/// Middleware handles `GET` and `HEAD` requests with *200 OK* responses.
+/// If response does not contain `ETag` header and response body is
+/// in memory, strong `ETag` is computed from the body content.
+///
+/// If `If-None-Match` request header matches response `ETag`, or
+/// `If-Modified-Since` request header is not older than response
+/// `Last-Modified` header, *304 Not Modified* response without body
+/// is returned. `If-Modified-Since` is ignored if `If-None-Match` is present.
+///
+/// Middleware must be registered before `Compress` middleware, so `ETag`
+/// is computed for uncompressed content.
+///
+/// ```rust
+/// use ntex::web::{self, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new().service(
+///         web::scope("/api")
+///             .wrap(middleware::Conditional::new())
+///             .route("/index.html", web::get().to(|| async { HttpResponse::Ok().body("data") }))
+///     );
+/// }
+/// ```
+pub struct Conditional;
+
+impl Conditional {
+    /// Create new `Conditional` middleware.
+    pub fn new() -> Self {
+        Conditional
+    }
+}
+
+impl<S, E> Transform<S> for Conditional
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse>,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse;
+    type Error = S::Error;
+    type InitError = ();
+    type Transform = ConditionalMiddleware<S, E>;
+    type Future = Ready<Self::Transform, Self::InitError>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        Ready::Ok(ConditionalMiddleware {
+            service,
+            _t: PhantomData,
+        })
+    }
+}
+
+pub struct ConditionalMiddleware<S, E> {
+    service: S,
+    _t: PhantomData<E>,
+}
+
+impl<S, E> Service for ConditionalMiddleware<S, E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse>,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse;
+    type Error = S::Error;
+    type Future = ConditionalResponse<S, E>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&self, req: WebRequest<E>) -> Self::Future {
+        ConditionalResponse {
+            fut: self.service.call(req),
+            _t: PhantomData,
+        }
+    }
+}
+
+pin_project_lite::pin_project! {
+    #[doc(hidden)]
+    pub struct ConditionalResponse<S: Service, E>
+    {
+        #[pin]
+        fut: S::Future,
+        _t: PhantomData<E>,
+    }
+}
+
+impl<S, E> Future for ConditionalResponse<S, E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse>,
+{
+    type Output = Result<WebResponse, S::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project().fut.poll(cx)? {
+            Poll::Ready(resp) => Poll::Ready(Ok(process(resp))),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+fn process(mut resp: WebResponse) -> WebResponse {
+    let method = resp.request().method();
+    if (method != Method::GET && method != Method::HEAD)
+        || resp.status() != StatusCode::OK
+    {
+        return resp;
+    }
+
+    if !resp.headers().contains_key(header::ETAG) {
+        let etag = match resp.response().body() {
+            ResponseBody::Body(Body::Bytes(ref bytes))
+            | ResponseBody::Other(Body::Bytes(ref bytes)) => {
+                Some(format!("\"{}\"", base64::encode(Sha1::digest(bytes))))
+            }
+            _ => None,
+        };
+        if let Some(etag) = etag {
+            resp.headers_mut()
+                .insert(header::ETAG, HeaderValue::from_str(&etag).unwrap());
+        }
+    }
+
+    let req_headers = resp.request().headers();
+    let not_modified = if let Some(val) = req_headers.get(header::IF_NONE_MATCH) {
+        match (val.to_str(), resp.headers().get(header::ETAG)) {
+            (Ok(val), Some(etag)) => etag_matches(val, etag.to_str().unwrap_or("")),
+            _ => false,
+        }
+    } else if let Some(since) = header_date(req_headers.get(header::IF_MODIFIED_SINCE)) {
+        match header_date(resp.headers().get(header::LAST_MODIFIED)) {
+            Some(modified) => modified <= since,
+            None => false,
+        }
+    } else {
+        false
+    };
+
+    if not_modified {
+        log::trace!("Resource is not modified: {:?}", resp.request().path());
+
+        let mut res = Response::new(StatusCode::NOT_MODIFIED);
+        for name in NOT_MODIFIED_HEADERS.iter() {
+            for value in resp.headers().get_all(name) {
+                res.headers_mut().append(name.clone(), value.clone());
+            }
+        }
+        resp.into_response(res)
+    } else {
+        resp
+    }
+}
+
+fn header_date(value: Option<&HeaderValue>) -> Option<SystemTime> {
+    value
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| httpdate::parse_http_date(v).ok())
+}
+
+/// Weak comparison of `If-None-Match` header and etag
+fn etag_matches(header: &str, etag: &str) -> bool {
+    if header.trim() == "*" {
+        return true;
+    }
+    let etag = etag.strip_prefix("W/").unwrap_or(etag);
+    header.split(',').any(|tag| {
+        let tag = tag.trim();
+        tag.strip_prefix("W/").unwrap_or(tag) == etag
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::web::test::{call_service, init_service, read_body, TestRequest};
+    use crate::web::{self, App, HttpResponse};
+
+    #[crate::rt_test]
+    async fn test_etag() {
+        let srv = init_service(App::new().wrap(Conditional::new()).route(
+            "/",
+            web::get().to(|| async {
+                HttpResponse::Ok()
+                    .header(header::CACHE_CONTROL, "max-age=60")
+                    .body("test data")
+            }),
+        ))
+        .await;
+
+        let req = TestRequest::default().to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let etag = resp.headers().get(header::ETAG).unwrap().clone();
+        assert_eq!(etag, "\"9I3YU4IIYIFsddVND1hNyGMyenw=\"");
+        assert_eq!(read_body(resp).await, "test data");
+
+        let req =
+            TestRequest::with_header(header::IF_NONE_MATCH, etag.clone()).to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(resp.headers().get(header::ETAG).unwrap(), etag);
+        assert_eq!(
+            resp.headers().get(header::CACHE_CONTROL).unwrap(),
+            "max-age=60"
+        );
+        assert!(!resp.headers().contains_key(header::CONTENT_TYPE));
+        assert!(read_body(resp).await.is_empty());
+
+        // weak comparison, list of etags
+        let req = TestRequest::with_header(
+            header::IF_NONE_MATCH,
+            format!("\"other\", W/{}", etag.to_str().unwrap()),
+        )
+        .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+
+        let req = TestRequest::with_header(header::IF_NONE_MATCH, "*").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+
+        let req =
+            TestRequest::with_header(header::IF_NONE_MATCH, "\"other\"").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(read_body(resp).await, "test data");
+    }
+
+    #[crate::rt_test]
+    async fn test_last_modified() {
+        let srv = init_service(App::new().wrap(Conditional::new()).route(
+            "/",
+            web::get().to(|| async {
+                HttpResponse::Ok()
+                    .header(header::ETAG, "\"v1\"")
+                    .header(header::LAST_MODIFIED, "Sun, 06 Nov 1994 08:49:37 GMT")
+                    .body("test data")
+            }),
+        ))
+        .await;
+
+        // existing etag is used
+        let req = TestRequest::default().to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.headers().get(header::ETAG).unwrap(), "\"v1\"");
+
+        let req = TestRequest::with_header(
+            header::IF_MODIFIED_SINCE,
+            "Sun, 06 Nov 1994 08:49:37 GMT",
+        )
+        .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+        assert!(resp.headers().contains_key(header::LAST_MODIFIED));
+
+        let req = TestRequest::with_header(
+            header::IF_MODIFIED_SINCE,
+            "Sat, 05 Nov 1994 08:49:37 GMT",
+        )
+        .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        // If-None-Match takes precedence
+        let req = TestRequest::with_header(
+            header::IF_MODIFIED_SINCE,
+            "Sun, 06 Nov 1994 08:49:37 GMT",
+        )
+        .header(header::IF_NONE_MATCH, "\"v2\"")
+        .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[crate::rt_test]
+    async fn test_skip() {
+        let srv = init_service(
+            App::new()
+                .wrap(Conditional::new())
+                .route(
+                    "/",
+                    web::post().to(|| async { HttpResponse::Ok().body("test data") }),
+                )
+                .route(
+                    "/created",
+                    web::get()
+                        .to(|| async { HttpResponse::Created().body("test data") }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::post()
+            .header(header::IF_NONE_MATCH, "*")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(!resp.headers().contains_key(header::ETAG));
+
+        let req = TestRequest::with_uri("/created")
+            .header(header::IF_NONE_MATCH, "*")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+    }
+}
@@ -0,0 +1,610 @@
+//! Cross-origin resource sharing (CORS) middleware
+use std::task::{Context, Poll};
+use std::{convert::TryFrom, future::Future, marker::PhantomData, pin::Pin, rc::Rc};
+
+use crate::http::error::HttpError;
+use crate::http::header::{self, HeaderName, HeaderValue};
+use crate::http::{Method, RequestHead};
+use crate::service::{Service, Transform};
+use crate::util::{Either, Ready};
+use crate::web::dev::{WebRequest, WebResponse};
+use crate::web::error::{CorsError, ErrorRenderer, WebResponseError};
+use crate::web::HttpResponse;
+
+type OriginFn = dyn Fn(&HeaderValue, &RequestHead) -> bool;
+
+/// `Middleware` for cross-origin resource sharing support.
+///
+/// By default requests from all origins are allowed. If at least one
+/// origin is configured with `allowed_origin()` or `allowed_origin_fn()`,
+/// only matching origins are allowed, requests from other origins are
+/// rejected with `CorsError`. Requests without `Origin` header are passed
+/// through unchanged.
+///
+/// Preflight requests are handled by middleware and never reach
+/// the wrapped service.
+///
+/// ```rust
+/// use ntex::http::{header, Method};
+/// use ntex::web::{self, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new().service(
+///         web::scope("/api")
+///             .wrap(
+///                 middleware::Cors::new()
+///                     .allowed_origin("https://www.rust-lang.org")
+///                     .allowed_methods(vec![Method::GET, Method::POST])
+///                     .allowed_header(header::AUTHORIZATION)
+///                     .supports_credentials()
+///                     .max_age(3600),
+///             )
+///             .route("/index.html", web::get().to(|| async { HttpResponse::Ok() }))
+///     );
+/// }
+/// ```
+#[derive(Clone)]
+pub struct Cors {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    origins: Vec<HeaderValue>,
+    origin_fns: Vec<Box<OriginFn>>,
+    methods: Vec<Method>,
+    headers: Vec<HeaderName>,
+    expose_headers: Vec<HeaderName>,
+    max_age: Option<usize>,
+    credentials: bool,
+    send_wildcard: bool,
+}
+
+impl Default for Cors {
+    fn default() -> Self {
+        Cors {
+            inner: Rc::new(Inner {
+                origins: Vec::new(),
+                origin_fns: Vec::new(),
+                methods: vec![
+                    Method::GET,
+                    Method::HEAD,
+                    Method::POST,
+                    Method::PUT,
+                    Method::PATCH,
+                    Method::DELETE,
+                    Method::OPTIONS,
+                ],
+                headers: Vec::new(),
+                expose_headers: Vec::new(),
+                max_age: None,
+                credentials: false,
+                send_wildcard: false,
+            }),
+        }
+    }
+}
+
+impl Cors {
+    /// Construct `Cors` middleware.
+    pub fn new() -> Cors {
+        Cors::default()
+    }
+
+    fn inner(&mut self) -> &mut Inner {
+        Rc::get_mut(&mut self.inner).expect("Multiple copies exist")
+    }
+
+    /// Add allowed origin, i.e. `https://www.example.com`.
+    pub fn allowed_origin(mut self, origin: &str) -> Self {
+        match HeaderValue::try_from(origin) {
+            Ok(origin) => self.inner().origins.push(origin),
+            Err(_) => panic!("Cannot create origin header value"),
+        }
+        self
+    }
+
+    /// Add function that checks if origin is allowed.
+    pub fn allowed_origin_fn<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&HeaderValue, &RequestHead) -> bool + 'static,
+    {
+        self.inner().origin_fns.push(Box::new(f));
+        self
+    }
+
+    /// Set allowed methods for preflight requests.
+    ///
+    /// By default `GET`, `HEAD`, `POST`, `PUT`, `PATCH`, `DELETE`
+    /// and `OPTIONS` methods are allowed.
+    pub fn allowed_methods<U>(mut self, methods: U) -> Self
+    where
+        U: IntoIterator<Item = Method>,
+    {
+        self.inner().methods = methods.into_iter().collect();
+        self
+    }
+
+    /// Add allowed request header.
+    ///
+    /// If no headers are set, all requested headers are allowed.
+    pub fn allowed_header<H>(mut self, header: H) -> Self
+    where
+        HeaderName: TryFrom<H>,
+        <HeaderName as TryFrom<H>>::Error: Into<HttpError>,
+    {
+        match HeaderName::try_from(header) {
+            Ok(header) => self.inner().headers.push(header),
+            Err(_) => panic!("Cannot create header name"),
+        }
+        self
+    }
+
+    /// Add response header that browser is allowed to access.
+    pub fn expose_header<H>(mut self, header: H) -> Self
+    where
+        HeaderName: TryFrom<H>,
+        <HeaderName as TryFrom<H>>::Error: Into<HttpError>,
+    {
+        match HeaderName::try_from(header) {
+            Ok(header) => self.inner().expose_headers.push(header),
+            Err(_) => panic!("Cannot create header name"),
+        }
+        self
+    }
+
+    /// Set how long, in seconds, results of preflight request could be cached.
+    pub fn max_age(mut self, max_age: usize) -> Self {
+        self.inner().max_age = Some(max_age);
+        self
+    }
+
+    /// Allow requests with credentials (cookies, authorization headers).
+    ///
+    /// Credentials require explicit list of allowed origins, configured with
+    /// `allowed_origin()` or `allowed_origin_fn()`, otherwise middleware
+    /// initialization fails.
+    pub fn supports_credentials(mut self) -> Self {
+        self.inner().credentials = true;
+        self
+    }
+
+    /// Send `*` instead of request's origin if all origins are allowed.
+    ///
+    /// Wildcard is not used for requests with credentials.
+    pub fn send_wildcard(mut self) -> Self {
+        self.inner().send_wildcard = true;
+        self
+    }
+}
+
+impl Inner {
+    /// Check request origin, returns value for `Access-Control-Allow-Origin`
+    fn check_origin(
+        &self,
+        head: &RequestHead,
+    ) -> Result<Option<HeaderValue>, CorsError> {
+        let origin = if let Some(origin) = head.headers.get(header::ORIGIN) {
+            origin
+        } else {
+            return Ok(None);
+        };
+
+        if self.origins.is_empty() && self.origin_fns.is_empty() {
+            if self.send_wildcard && !self.credentials {
+                Ok(Some(HeaderValue::from_static("*")))
+            } else {
+                Ok(Some(origin.clone()))
+            }
+        } else if self.origins.iter().any(|o| o == origin)
+            || self.origin_fns.iter().any(|f| f(origin, head))
+        {
+            Ok(Some(origin.clone()))
+        } else {
+            Err(CorsError::OriginNotAllowed)
+        }
+    }
+
+    fn check_method(&self, head: &RequestHead) -> Result<(), CorsError> {
+        head.headers
+            .get(header::ACCESS_CONTROL_REQUEST_METHOD)
+            .and_then(|hdr| Method::from_bytes(hdr.as_bytes()).ok())
+            .filter(|method| self.methods.contains(method))
+            .map(|_| ())
+            .ok_or(CorsError::MethodNotAllowed)
+    }
+
+    fn check_headers(&self, head: &RequestHead) -> Result<(), CorsError> {
+        if self.headers.is_empty() {
+            return Ok(());
+        }
+        if let Some(hdr) = head.headers.get(header::ACCESS_CONTROL_REQUEST_HEADERS) {
+            let hdr = hdr.to_str().map_err(|_| CorsError::HeadersNotAllowed)?;
+            for name in hdr.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()) {
+                match HeaderName::try_from(name) {
+                    Ok(name) if self.headers.contains(&name) => (),
+                    _ => return Err(CorsError::HeadersNotAllowed),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn preflight_response(
+        &self,
+        head: &RequestHead,
+        origin: HeaderValue,
+    ) -> HttpResponse {
+        let mut res = HttpResponse::Ok();
+        self.set_origin(&mut res, origin);
+
+        res.header(header::ACCESS_CONTROL_ALLOW_METHODS, join(&self.methods));
+        if self.headers.is_empty() {
+            // allow all requested headers
+            if let Some(hdr) = head.headers.get(header::ACCESS_CONTROL_REQUEST_HEADERS) {
+                res.header(header::ACCESS_CONTROL_ALLOW_HEADERS, hdr.clone());
+            }
+        } else {
+            res.header(header::ACCESS_CONTROL_ALLOW_HEADERS, join(&self.headers));
+        }
+        if let Some(max_age) = self.max_age {
+            res.header(header::ACCESS_CONTROL_MAX_AGE, max_age);
+        }
+        res.finish()
+    }
+
+    fn set_origin(&self, res: &mut crate::http::ResponseBuilder, origin: HeaderValue) {
+        if origin != "*" {
+            res.header(header::VARY, HeaderValue::from_static("Origin"));
+        }
+        res.header(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+        if self.credentials {
+            res.header(
+                header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
+                HeaderValue::from_static("true"),
+            );
+        }
+    }
+
+    fn update_response(&self, res: &mut WebResponse, origin: HeaderValue) {
+        let headers = res.headers_mut();
+        if origin != "*" {
+            headers.append(header::VARY, HeaderValue::from_static("Origin"));
+        }
+        headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+        if self.credentials {
+            headers.insert(
+                header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
+                HeaderValue::from_static("true"),
+            );
+        }
+        if !self.expose_headers.is_empty() {
+            headers.insert(
+                header::ACCESS_CONTROL_EXPOSE_HEADERS,
+                join(&self.expose_headers),
+            );
+        }
+    }
+}
+
+fn join<T: AsRef<str>>(items: &[T]) -> HeaderValue {
+    let s = items
+        .iter()
+        .map(|item| item.as_ref())
+        .collect::<Vec<_>>()
+        .join(", ");
+    HeaderValue::try_from(s).unwrap()
+}
+
+impl<S, E> Transform<S> for Cors
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse>,
+    S::Future: 'static,
+    E: ErrorRenderer,
+    CorsError: WebResponseError<E>,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse;
+    type Error = S::Error;
+    type InitError = ();
+    type Transform = CorsMiddleware<S, E>;
+    type Future = Ready<Self::Transform, Self::InitError>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        if self.inner.credentials
+            && self.inner.origins.is_empty()
+            && self.inner.origin_fns.is_empty()
+        {
+            log::error!("Cors credentials support requires explicitly allowed origins");
+            return Ready::Err(());
+        }
+        Ready::Ok(CorsMiddleware {
+            service,
+            inner: self.inner.clone(),
+            _t: PhantomData,
+        })
+    }
+}
+
+pub struct CorsMiddleware<S, E> {
+    service: S,
+    inner: Rc<Inner>,
+    _t: PhantomData<E>,
+}
+
+impl<S, E> Service for CorsMiddleware<S, E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse>,
+    S::Future: 'static,
+    E: ErrorRenderer,
+    CorsError: WebResponseError<E>,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse;
+    type Error = S::Error;
+    type Future = Either<
+        Ready<Self::Response, Self::Error>,
+        Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>,
+    >;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&self, req: WebRequest<E>) -> Self::Future {
+        let origin = match self.inner.check_origin(req.head()) {
+            Ok(Some(origin)) => origin,
+            Ok(None) => return Either::Right(Box::pin(self.service.call(req))),
+            Err(e) => return Either::Left(Ready::Ok(req.render_error(e))),
+        };
+
+        // preflight request
+        if req.method() == Method::OPTIONS
+            && req
+                .headers()
+                .contains_key(header::ACCESS_CONTROL_REQUEST_METHOD)
+        {
+            let res = self
+                .inner
+                .check_method(req.head())
+                .and_then(|_| self.inner.check_headers(req.head()));
+            return Either::Left(Ready::Ok(match res {
+                Ok(_) => {
+                    let res = self.inner.preflight_response(req.head(), origin);
+                    req.into_response(res)
+                }
+                Err(e) => {
+                    log::trace!("Cors preflight request is rejected: {}", e);
+                    req.render_error(e)
+                }
+            }));
+        }
+
+        let inner = self.inner.clone();
+        let fut = self.service.call(req);
+
+        Either::Right(Box::pin(async move {
+            let mut res = fut.await?;
+            inner.update_response(&mut res, origin);
+            Ok(res)
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::web::test::{call_service, init_service, ok_service, TestRequest};
+    use crate::web::{self, App};
+
+    #[crate::rt_test]
+    async fn test_no_origin() {
+        let mw = Cors::new().new_transform(ok_service()).await.unwrap();
+
+        let req = TestRequest::default().to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(!resp
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+    }
+
+    #[crate::rt_test]
+    async fn test_all_origins() {
+        let mw = Cors::new().new_transform(ok_service()).await.unwrap();
+        let req = TestRequest::with_header(header::ORIGIN, "https://www.example.com")
+            .to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers()
+                .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+                .unwrap(),
+            "https://www.example.com"
+        );
+        assert_eq!(resp.headers().get(header::VARY).unwrap(), "Origin");
+
+        let mw = Cors::new()
+            .send_wildcard()
+            .new_transform(ok_service())
+            .await
+            .unwrap();
+        let req = TestRequest::with_header(header::ORIGIN, "https://www.example.com")
+            .to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(
+            resp.headers()
+                .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+                .unwrap(),
+            "*"
+        );
+        assert!(!resp.headers().contains_key(header::VARY));
+    }
+
+    #[crate::rt_test]
+    async fn test_credentials_all_origins() {
+        let res = Cors::new()
+            .supports_credentials()
+            .new_transform(ok_service())
+            .await;
+        assert!(res.is_err());
+    }
+
+    #[crate::rt_test]
+    async fn test_allowed_origins() {
+        let mw = Cors::new()
+            .allowed_origin("https://www.example.com")
+            .allowed_origin_fn(|origin, _| {
+                origin.as_bytes().ends_with(b".rust-lang.org")
+            })
+            .supports_credentials()
+            .expose_header("x-version")
+            .expose_header("x-request-id")
+            .new_transform(ok_service())
+            .await
+            .unwrap();
+
+        for origin in &["https://www.example.com", "https://www.rust-lang.org"] {
+            let req = TestRequest::with_header(header::ORIGIN, *origin).to_srv_request();
+            let resp = mw.call(req).await.unwrap();
+            assert_eq!(resp.status(), StatusCode::OK);
+            let headers = resp.headers();
+            assert_eq!(
+                headers.get(header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(),
+                *origin
+            );
+            assert_eq!(
+                headers
+                    .get(header::ACCESS_CONTROL_ALLOW_CREDENTIALS)
+                    .unwrap(),
+                "true"
+            );
+            assert_eq!(
+                headers.get(header::ACCESS_CONTROL_EXPOSE_HEADERS).unwrap(),
+                "x-version, x-request-id"
+            );
+        }
+
+        let req = TestRequest::with_header(header::ORIGIN, "https://www.unknown.com")
+            .to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        assert!(!resp
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+    }
+
+    #[crate::rt_test]
+    async fn test_preflight() {
+        let srv = init_service(
+            App::new().service(
+                web::resource("/test")
+                    .wrap(
+                        Cors::new()
+                            .allowed_origin("https://www.example.com")
+                            .allowed_methods(vec![Method::GET, Method::POST])
+                            .allowed_header(header::AUTHORIZATION)
+                            .allowed_header("x-custom")
+                            .max_age(3600),
+                    )
+                    .route(web::get().to(|| async { HttpResponse::Ok() })),
+            ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/test")
+            .method(Method::OPTIONS)
+            .header(header::ORIGIN, "https://www.example.com")
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+            .header(
+                header::ACCESS_CONTROL_REQUEST_HEADERS,
+                "Authorization, X-Custom",
+            )
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let headers = resp.headers();
+        assert_eq!(
+            headers.get(header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(),
+            "https://www.example.com"
+        );
+        assert_eq!(
+            headers.get(header::ACCESS_CONTROL_ALLOW_METHODS).unwrap(),
+            "GET, POST"
+        );
+        assert_eq!(
+            headers.get(header::ACCESS_CONTROL_ALLOW_HEADERS).unwrap(),
+            "authorization, x-custom"
+        );
+        assert_eq!(headers.get(header::ACCESS_CONTROL_MAX_AGE).unwrap(), "3600");
+        assert!(!headers.contains_key(header::ACCESS_CONTROL_ALLOW_CREDENTIALS));
+
+        // method is not allowed
+        let req = TestRequest::with_uri("/test")
+            .method(Method::OPTIONS)
+            .header(header::ORIGIN, "https://www.example.com")
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "DELETE")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        // header is not allowed
+        let req = TestRequest::with_uri("/test")
+            .method(Method::OPTIONS)
+            .header(header::ORIGIN, "https://www.example.com")
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "GET")
+            .header(header::ACCESS_CONTROL_REQUEST_HEADERS, "x-other")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        // origin is not allowed
+        let req = TestRequest::with_uri("/test")
+            .method(Method::OPTIONS)
+            .header(header::ORIGIN, "https://www.unknown.com")
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "GET")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        // actual request
+        let req = TestRequest::with_uri("/test")
+            .header(header::ORIGIN, "https://www.example.com")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers()
+                .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+                .unwrap(),
+            "https://www.example.com"
+        );
+    }
+
+    #[crate::rt_test]
+    async fn test_preflight_all_headers() {
+        let mw = Cors::new().new_transform(ok_service()).await.unwrap();
+
+        let req = TestRequest::default()
+            .method(Method::OPTIONS)
+            .header(header::ORIGIN, "https://www.example.com")
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "PUT")
+            .header(header::ACCESS_CONTROL_REQUEST_HEADERS, "x-any, x-other")
+            .to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers()
+                .get(header::ACCESS_CONTROL_ALLOW_HEADERS)
+                .unwrap(),
+            "x-any, x-other"
+        );
+        assert!(!resp.headers().contains_key(header::ACCESS_CONTROL_MAX_AGE));
+    }
+}
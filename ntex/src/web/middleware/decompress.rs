@@ -0,0 +1,209 @@
+//! `Middleware` for decompressing request payload.
+use std::task::{Context, Poll};
+
+use crate::http::encoding::Decoder;
+use crate::http::header::{ContentEncoding, CONTENT_ENCODING, CONTENT_LENGTH};
+use crate::http::Payload;
+use crate::service::{Service, Transform};
+use crate::util::Ready;
+use crate::web::dev::{WebRequest, WebResponse};
+
+#[derive(Debug, Clone)]
+/// `Middleware` for decompressing request payload.
+///
+/// Middleware decodes `gzip`, `deflate`, `br` and `zstd` encoded payloads
+/// before extractors see them, `Content-Encoding` header is removed from
+/// decoded requests. Payloads with unknown encodings are passed unchanged.
+///
+/// Decoded payload size is limited, `PayloadError::Overflow` error is
+/// returned from payload stream if limit is reached.
+///
+/// ```rust
+/// use ntex::web::{self, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(middleware::Decompress::default().limit(1_048_576))
+///         .service(
+///             web::resource("/test")
+///                 .route(web::post().to(|body: String| async move {
+///                     HttpResponse::Ok().body(body)
+///                 }))
+///         );
+/// }
+/// ```
+pub struct Decompress {
+    limit: usize,
+}
+
+impl Decompress {
+    /// Create new `Decompress` middleware.
+    pub fn new() -> Self {
+        Decompress::default()
+    }
+
+    /// Set max size of decoded payload. By default max size is 10Mb.
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+}
+
+impl Default for Decompress {
+    fn default() -> Self {
+        Decompress { limit: 10_485_760 }
+    }
+}
+
+impl<S, E> Transform<S> for Decompress
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse>,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse;
+    type Error = S::Error;
+    type InitError = ();
+    type Transform = DecompressMiddleware<S>;
+    type Future = Ready<Self::Transform, Self::InitError>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        Ready::Ok(DecompressMiddleware {
+            service,
+            limit: self.limit,
+        })
+    }
+}
+
+pub struct DecompressMiddleware<S> {
+    service: S,
+    limit: usize,
+}
+
+impl<S, E> Service for DecompressMiddleware<S>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse>,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&self, mut req: WebRequest<E>) -> Self::Future {
+        let encoding = req
+            .headers()
+            .get(&CONTENT_ENCODING)
+            .and_then(|val| val.to_str().ok())
+            .map(ContentEncoding::from)
+            .unwrap_or(ContentEncoding::Identity);
+
+        if encoding.is_compressed() {
+            log::trace!("Decode {:?} request payload", encoding);
+
+            let decoder = Decoder::new(req.take_payload(), encoding).limit(self.limit);
+            req.set_payload(Payload::from_stream(decoder));
+
+            let headers = req.headers_mut();
+            headers.remove(&CONTENT_ENCODING);
+            headers.remove(&CONTENT_LENGTH);
+        }
+        self.service.call(req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::write::GzEncoder;
+
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::util::Bytes;
+    use crate::web::test::{call_service, init_service, read_body, TestRequest};
+    use crate::web::{self, App, HttpResponse};
+
+    fn gzip(data: &[u8]) -> Bytes {
+        let mut e = GzEncoder::new(Vec::new(), flate2::Compression::default());
+        e.write_all(data).unwrap();
+        Bytes::from(e.finish().unwrap())
+    }
+
+    #[crate::rt_test]
+    async fn test_decompress() {
+        let srv = init_service(App::new().wrap(Decompress::new()).route(
+            "/",
+            web::post().to(|req: web::HttpRequest, body: Bytes| async move {
+                assert!(!req.headers().contains_key(CONTENT_ENCODING));
+                HttpResponse::Ok().body(body)
+            }),
+        ))
+        .await;
+
+        let req = TestRequest::with_header(CONTENT_ENCODING, "gzip")
+            .method(crate::http::Method::POST)
+            .set_payload(gzip(b"test data"))
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(read_body(resp).await, "test data");
+
+        let data = zstd::stream::encode_all(&b"zstd data"[..], 3).unwrap();
+        let req = TestRequest::with_header(CONTENT_ENCODING, "zstd")
+            .method(crate::http::Method::POST)
+            .set_payload(data)
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(read_body(resp).await, "zstd data");
+    }
+
+    #[crate::rt_test]
+    async fn test_unknown_encoding() {
+        let srv = init_service(App::new().wrap(Decompress::new()).route(
+            "/",
+            web::post().to(|req: web::HttpRequest, body: Bytes| async move {
+                assert!(req.headers().contains_key(CONTENT_ENCODING));
+                HttpResponse::Ok().body(body)
+            }),
+        ))
+        .await;
+
+        let req = TestRequest::with_header(CONTENT_ENCODING, "unknown")
+            .method(crate::http::Method::POST)
+            .set_payload("data")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(read_body(resp).await, "data");
+    }
+
+    #[crate::rt_test]
+    async fn test_limit() {
+        let srv = init_service(App::new().wrap(Decompress::new().limit(1024)).route(
+            "/",
+            web::post().to(|body: Bytes| async move { HttpResponse::Ok().body(body) }),
+        ))
+        .await;
+
+        // compressed payload is small, decoded payload is big
+        let payload = gzip(&[b'x'; 1_048_576][..]);
+        assert!(payload.len() < 2048);
+
+        let req = TestRequest::with_header(CONTENT_ENCODING, "gzip")
+            .method(crate::http::Method::POST)
+            .set_payload(payload)
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+}
@@ -0,0 +1,234 @@
+//! `Middleware` for custom error responses.
+use std::task::{Context, Poll};
+use std::{future::Future, marker::PhantomData, pin::Pin, rc::Rc};
+
+use crate::http::StatusCode;
+use crate::service::{Service, Transform};
+use crate::util::{HashMap, Ready};
+use crate::web::dev::{WebRequest, WebResponse};
+
+type ErrorHandler =
+    Box<dyn Fn(WebResponse) -> Pin<Box<dyn Future<Output = WebResponse>>>>;
+
+/// `Middleware` for custom error responses.
+///
+/// Middleware calls registered handler if response has matching status
+/// code. Handler receives response and could rewrite it, for example
+/// render custom error page or json error envelope. Middleware handles
+/// responses generated by router as well, i.e. *404 Not Found* for
+/// unknown paths if it is registered on application level.
+///
+/// ```rust
+/// use ntex::http::{header, StatusCode};
+/// use ntex::web::{self, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(
+///             middleware::ErrorHandlers::new()
+///                 .handler(StatusCode::NOT_FOUND, |res: web::WebResponse| async move {
+///                     res.into_response(
+///                         HttpResponse::NotFound()
+///                             .content_type("text/html")
+///                             .body("<h1>Page not found</h1>"),
+///                     )
+///                 }),
+///         )
+///         .route("/index.html", web::get().to(|| async { HttpResponse::Ok() }));
+/// }
+/// ```
+#[derive(Clone, Default)]
+pub struct ErrorHandlers {
+    handlers: Rc<HashMap<StatusCode, ErrorHandler>>,
+}
+
+impl ErrorHandlers {
+    /// Create new `ErrorHandlers` middleware.
+    pub fn new() -> Self {
+        ErrorHandlers::default()
+    }
+
+    /// Register error handler for specified status code.
+    ///
+    /// Handler replaces previously registered handler for the same status code.
+    pub fn handler<F, R>(mut self, status: StatusCode, handler: F) -> Self
+    where
+        F: Fn(WebResponse) -> R + 'static,
+        R: Future<Output = WebResponse> + 'static,
+    {
+        Rc::get_mut(&mut self.handlers)
+            .expect("Multiple copies exist")
+            .insert(status, Box::new(move |res| Box::pin(handler(res))));
+        self
+    }
+}
+
+impl<S, E> Transform<S> for ErrorHandlers
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse>,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse;
+    type Error = S::Error;
+    type InitError = ();
+    type Transform = ErrorHandlersMiddleware<S, E>;
+    type Future = Ready<Self::Transform, Self::InitError>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        Ready::Ok(ErrorHandlersMiddleware {
+            service,
+            handlers: self.handlers.clone(),
+            _t: PhantomData,
+        })
+    }
+}
+
+pub struct ErrorHandlersMiddleware<S, E> {
+    service: S,
+    handlers: Rc<HashMap<StatusCode, ErrorHandler>>,
+    _t: PhantomData<E>,
+}
+
+impl<S, E> Service for ErrorHandlersMiddleware<S, E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse>,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse;
+    type Error = S::Error;
+    type Future = ErrorHandlersResponse<S, E>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&self, req: WebRequest<E>) -> Self::Future {
+        ErrorHandlersResponse {
+            fut: self.service.call(req),
+            handler: None,
+            handlers: self.handlers.clone(),
+            _t: PhantomData,
+        }
+    }
+}
+
+pin_project_lite::pin_project! {
+    #[doc(hidden)]
+    pub struct ErrorHandlersResponse<S: Service, E>
+    {
+        #[pin]
+        fut: S::Future,
+        handler: Option<Pin<Box<dyn Future<Output = WebResponse>>>>,
+        handlers: Rc<HashMap<StatusCode, ErrorHandler>>,
+        _t: PhantomData<E>,
+    }
+}
+
+impl<S, E> Future for ErrorHandlersResponse<S, E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse>,
+{
+    type Output = Result<WebResponse, S::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        if let Some(ref mut fut) = this.handler {
+            return fut.as_mut().poll(cx).map(Ok);
+        }
+
+        match this.fut.poll(cx)? {
+            Poll::Ready(resp) => {
+                if let Some(handler) = this.handlers.get(&resp.status()) {
+                    log::trace!("Call error handler for {:?}", resp.status());
+
+                    let mut fut = handler(resp);
+                    match fut.as_mut().poll(cx) {
+                        Poll::Ready(resp) => Poll::Ready(Ok(resp)),
+                        Poll::Pending => {
+                            *this.handler = Some(fut);
+                            Poll::Pending
+                        }
+                    }
+                } else {
+                    Poll::Ready(Ok(resp))
+                }
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::header::CONTENT_TYPE;
+    use crate::web::test::{call_service, init_service, read_body, TestRequest};
+    use crate::web::{self, App, HttpResponse};
+
+    #[crate::rt_test]
+    async fn test_handler() {
+        let srv = init_service(
+            App::new()
+                .wrap(ErrorHandlers::new().handler(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    |res: WebResponse| async move {
+                        let body = format!("{{\"error\":{}}}", res.status().as_u16());
+                        res.into_response(
+                            HttpResponse::InternalServerError()
+                                .content_type("application/json")
+                                .body(body),
+                        )
+                    },
+                ))
+                .route(
+                    "/",
+                    web::get().to(|| async { HttpResponse::InternalServerError() }),
+                )
+                .route("/ok", web::get().to(|| async { HttpResponse::Ok() })),
+        )
+        .await;
+
+        let req = TestRequest::default().to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(
+            resp.headers().get(CONTENT_TYPE).unwrap(),
+            "application/json"
+        );
+        assert_eq!(read_body(resp).await, "{\"error\":500}");
+
+        let req = TestRequest::with_uri("/ok").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[crate::rt_test]
+    async fn test_default_resource() {
+        let srv = init_service(
+            App::new()
+                .wrap(ErrorHandlers::new().handler(
+                    StatusCode::NOT_FOUND,
+                    |res: WebResponse| async move {
+                        crate::rt::time::sleep(std::time::Duration::from_millis(10))
+                            .await;
+                        let body = format!("Not found: {}", res.request().path());
+                        res.into_response(HttpResponse::NotFound().body(body))
+                    },
+                ))
+                .route("/", web::get().to(|| async { HttpResponse::Ok() })),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/unknown").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert_eq!(read_body(resp).await, "Not found: /unknown");
+    }
+}
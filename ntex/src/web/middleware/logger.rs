@@ -1,17 +1,20 @@
 //! Request logging middleware
 use std::fmt::{self, Display};
 use std::task::{Context, Poll};
-use std::{convert::TryFrom, env, error::Error, future::Future, pin::Pin, rc::Rc, time};
+use std::{cell::RefCell, collections::VecDeque, convert::TryFrom, env, error::Error};
+use std::{future::Future, pin::Pin, rc::Rc, time};
 
 use regex::Regex;
 
 use crate::http::body::{Body, BodySize, MessageBody, ResponseBody};
-use crate::http::header::HeaderName;
+use crate::http::header::{self, HeaderName};
 use crate::service::{Service, Transform};
-use crate::util::{Bytes, Either, HashSet, Ready};
+use crate::util::{Bytes, Either, Extensions, HashSet, Ready};
 use crate::web::dev::{WebRequest, WebResponse};
 use crate::web::HttpResponse;
 
+use super::RequestId;
+
 /// `Middleware` for logging request and response info to the terminal.
 ///
 /// `Logger` middleware uses standard log crate to log information. You should
@@ -45,7 +48,8 @@ use crate::web::HttpResponse;
 ///
 /// `%%`  The percent sign
 ///
-/// `%a`  Remote IP-address (IP-address of proxy if using reverse proxy)
+/// `%a`  Remote IP-address (real client address if request comes from trusted proxy,
+/// check `ConnectionInfo::realip_remote_addr()`)
 ///
 /// `%t`  Time when the request was started to process (in rfc3339 format)
 ///
@@ -62,28 +66,69 @@ use crate::web::HttpResponse;
 ///
 /// `%U`  Request URL
 ///
+/// `%L`  Request id, see `RequestIdentifier` middleware
+///
 /// `%{FOO}i`  request.headers['FOO']
 ///
 /// `%{FOO}o`  response.headers['FOO']
 ///
 /// `%{FOO}e`  os.environ['FOO']
 ///
+/// `%{FOO}x`  custom field 'FOO', see `Logger::custom_field()`
+///
+/// ## JSON output
+///
+/// `Logger::json()` creates logger that writes one JSON object per request
+/// with `remote_addr`, `time`, `request`, `status`, `size`, `duration_ms`,
+/// `referer` and `user_agent` fields, custom fields are added to the object.
+/// Missing values are rendered as `null`.
 pub struct Logger {
     inner: Rc<Inner>,
 }
 
 struct Inner {
     format: Format,
+    json: Option<Vec<String>>,
     exclude: HashSet<String>,
+    fields: Vec<(String, Box<dyn Fn(&Extensions) -> Option<String>>)>,
+    writer: Option<Box<dyn LogWriter>>,
+    times: Option<ResponseTimes>,
+}
+
+impl Inner {
+    fn write(&self, record: &dyn Display) {
+        if let Some(ref writer) = self.writer {
+            writer.write(&record.to_string());
+        } else {
+            log::info!("{}", record);
+        }
+    }
 }
 
 impl Logger {
     /// Create `Logger` middleware with the specified `format`.
     pub fn new(format: &str) -> Logger {
+        Logger::with_format(Format::new(format), None)
+    }
+
+    /// Create `Logger` middleware with JSON lines output.
+    pub fn json() -> Logger {
+        let (keys, units) = JSON_FIELDS
+            .iter()
+            .map(|(key, unit)| (key.to_string(), unit()))
+            .unzip();
+        Logger::with_format(Format(units), Some(keys))
+    }
+
+    fn with_format(format: Format, json: Option<Vec<String>>) -> Logger {
         Logger {
             inner: Rc::new(Inner {
-                format: Format::new(format),
+                format,
+                json,
                 exclude: HashSet::default(),
+                fields: Vec::new(),
+                writer: None,
+                times: None,
             }),
         }
     }
@@ -96,6 +141,133 @@ impl Logger {
             .insert(path.into());
         self
     }
+
+    /// Add custom field, value is sourced from request extensions.
+    ///
+    /// Function is called after response is generated, so it sees
+    /// extensions set by handlers. Field is available as `%{name}x`
+    /// in format string, for JSON output field is added to the object.
+    ///
+    /// ```rust
+    /// use ntex::web::middleware::Logger;
+    ///
+    /// struct UserId(u64);
+    ///
+    /// let logger = Logger::new("%r %s %{user}x").custom_field("user", |ext| {
+    ///     ext.get::<UserId>().map(|id| id.0.to_string())
+    /// });
+    /// ```
+    pub fn custom_field<T, F>(mut self, name: T, f: F) -> Self
+    where
+        T: Into<String>,
+        F: Fn(&Extensions) -> Option<String> + 'static,
+    {
+        let name = name.into();
+        let inner = Rc::get_mut(&mut self.inner).unwrap();
+        if let Some(ref mut keys) = inner.json {
+            keys.push(name.clone());
+            inner.format.0.push(FormatText::Custom(name.clone()));
+        }
+        inner.fields.push((name, Box::new(f)));
+        self
+    }
+
+    /// Set log records writer.
+    ///
+    /// By default records are written with `log::info!()`.
+    pub fn writer<W: LogWriter + 'static>(mut self, writer: W) -> Self {
+        Rc::get_mut(&mut self.inner).unwrap().writer = Some(Box::new(writer));
+        self
+    }
+
+    /// Collect response times of last `window` requests.
+    ///
+    /// Use `Logger::response_times()` to get collected response times.
+    pub fn collect_times(mut self, window: usize) -> Self {
+        Rc::get_mut(&mut self.inner).unwrap().times = Some(ResponseTimes::new(window));
+        self
+    }
+
+    /// Collected response times, if enabled with `Logger::collect_times()`
+    pub fn response_times(&self) -> Option<ResponseTimes> {
+        self.inner.times.clone()
+    }
+}
+
+/// Access log writer
+pub trait LogWriter {
+    /// Write log record
+    fn write(&self, record: &str);
+}
+
+impl<F> LogWriter for F
+where
+    F: Fn(&str),
+{
+    fn write(&self, record: &str) {
+        (self)(record)
+    }
+}
+
+/// Response times of recent requests
+///
+/// Response time is measured from request start until response body
+/// is completely sent.
+#[derive(Clone)]
+pub struct ResponseTimes(Rc<RefCell<TimesInner>>);
+
+struct TimesInner {
+    window: usize,
+    samples: VecDeque<time::Duration>,
+}
+
+impl ResponseTimes {
+    fn new(window: usize) -> Self {
+        ResponseTimes(Rc::new(RefCell::new(TimesInner {
+            window,
+            samples: VecDeque::with_capacity(window),
+        })))
+    }
+
+    fn record(&self, duration: time::Duration) {
+        let mut inner = self.0.borrow_mut();
+        if inner.window != 0 {
+            if inner.samples.len() == inner.window {
+                inner.samples.pop_front();
+            }
+            inner.samples.push_back(duration);
+        }
+    }
+
+    /// Response time percentile, `p` is in range `0.0..=100.0`
+    ///
+    /// Returns `None` if no requests are collected.
+    pub fn percentile(&self, p: f64) -> Option<time::Duration> {
+        let inner = self.0.borrow();
+        if inner.samples.is_empty() {
+            return None;
+        }
+
+        let mut samples: Vec<_> = inner.samples.iter().copied().collect();
+        samples.sort_unstable();
+        let rank = (p.clamp(0.0, 100.0) / 100.0 * samples.len() as f64).ceil() as usize;
+        Some(samples[rank.max(1) - 1])
+    }
+
+    /// Number of collected requests
+    pub fn len(&self) -> usize {
+        self.0.borrow().samples.len()
+    }
+
+    /// Check if no requests are collected
+    pub fn is_empty(&self) -> bool {
+        self.0.borrow().samples.is_empty()
+    }
+
+    /// Clear collected response times
+    pub fn clear(&self) {
+        self.0.borrow_mut().samples.clear()
+    }
 }
 
 impl Default for Logger {
@@ -105,12 +277,7 @@ impl Default for Logger {
     /// %a "%r" %s %b "%{Referer}i" "%{User-Agent}i" %T
     /// ```
     fn default() -> Self {
-        Logger {
-            inner: Rc::new(Inner {
-                format: Format::default(),
-                exclude: HashSet::default(),
-            }),
-        }
+        Logger::with_format(Format::default(), None)
     }
 }
 
@@ -172,6 +339,7 @@ where
             Either::Left(LoggerResponse {
                 time,
                 format: Some(format),
+                inner: self.inner.clone(),
                 fut: self.service.call(req),
             })
         }
@@ -186,6 +354,7 @@ pin_project_lite::pin_project! {
         fut: S::Future,
         time: time::SystemTime,
         format: Option<Format>,
+        inner: Rc<Inner>,
     }
 }
 
@@ -205,19 +374,23 @@ where
         };
 
         if let Some(ref mut format) = this.format {
+            let extensions = res.request().extensions();
             for unit in &mut format.0 {
                 unit.render_response(res.response());
+                unit.render_extensions(&this.inner.fields, &extensions);
             }
         }
 
         let time = *this.time;
         let format = this.format.take();
+        let inner = this.inner.clone();
 
         Poll::Ready(Ok(res.map_body(move |_, body| {
             ResponseBody::Other(Body::from_message(StreamLog {
                 body,
                 time,
                 format,
+                inner,
                 size: 0,
             }))
         })))
@@ -227,20 +400,34 @@ where
 struct StreamLog {
     body: ResponseBody<Body>,
     format: Option<Format>,
+    inner: Rc<Inner>,
     size: usize,
     time: time::SystemTime,
 }
 
 impl Drop for StreamLog {
     fn drop(&mut self) {
+        if let Some(ref times) = self.inner.times {
+            times.record(self.time.elapsed().unwrap_or_default());
+        }
+
         if let Some(ref format) = self.format {
-            let render = |fmt: &mut fmt::Formatter<'_>| {
-                for unit in &format.0 {
-                    unit.render(fmt, self.size, self.time)?;
-                }
-                Ok(())
-            };
-            log::info!("{}", FormatDisplay(&render));
+            if let Some(ref keys) = self.inner.json {
+                let record: serde_json::Map<_, _> = keys
+                    .iter()
+                    .zip(format.0.iter())
+                    .map(|(key, unit)| (key.clone(), unit.json(self.size, self.time)))
+                    .collect();
+                self.inner.write(&serde_json::Value::Object(record));
+            } else {
+                let render = |fmt: &mut fmt::Formatter<'_>| {
+                    for unit in &format.0 {
+                        unit.render(fmt, self.size, self.time)?;
+                    }
+                    Ok(())
+                };
+                self.inner.write(&FormatDisplay(&render));
+            }
         }
     }
 }
@@ -277,13 +464,27 @@ impl Default for Format {
     }
 }
 
+/// Fields of JSON log record
+const JSON_FIELDS: [(&str, fn() -> FormatText); 8] = [
+    ("remote_addr", || FormatText::RemoteAddr),
+    ("time", || FormatText::RequestTime),
+    ("request", || FormatText::RequestLine),
+    ("status", || FormatText::ResponseStatus),
+    ("size", || FormatText::ResponseSize),
+    ("duration_ms", || FormatText::TimeMillis),
+    ("referer", || FormatText::RequestHeader(header::REFERER)),
+    ("user_agent", || {
+        FormatText::RequestHeader(header::USER_AGENT)
+    }),
+];
+
 impl Format {
     /// Create a `Format` from a format string.
     ///
     /// Returns `None` if the format string syntax is incorrect.
     fn new(s: &str) -> Format {
         log::trace!("Access log format: {}", s);
-        let fmt = Regex::new(r"%(\{([A-Za-z0-9\-_]+)\}([ioe])|[atPrUsbTD]?)").unwrap();
+        let fmt = Regex::new(r"%(\{([A-Za-z0-9\-_]+)\}([ioex])|[atPrUsbTDL]?)").unwrap();
 
         let mut idx = 0;
         let mut results = Vec::new();
@@ -304,6 +505,7 @@ impl Format {
                         HeaderName::try_from(key.as_str()).unwrap(),
                     ),
                     "e" => FormatText::EnvironHeader(key.as_str().to_owned()),
+                    "x" => FormatText::Custom(key.as_str().to_owned()),
                     _ => unreachable!(),
                 })
             } else {
@@ -318,6 +520,7 @@ impl Format {
                     "U" => FormatText::UrlPath,
                     "T" => FormatText::Time,
                     "D" => FormatText::TimeMillis,
+                    "L" => FormatText::RequestId,
                     _ => FormatText::Str(m.as_str().to_owned()),
                 });
             }
@@ -340,14 +543,17 @@ enum FormatText {
     RequestLine,
     RequestTime,
     ResponseStatus,
+    Status(u16),
     ResponseSize,
     Time,
     TimeMillis,
     RemoteAddr,
     UrlPath,
+    RequestId,
     RequestHeader(HeaderName),
     ResponseHeader(HeaderName),
     EnvironHeader(String),
+    Custom(String),
 }
 
 impl FormatText {
@@ -360,6 +566,7 @@ impl FormatText {
         match *self {
             FormatText::Str(ref string) => fmt.write_str(string),
             FormatText::Percent => "%".fmt(fmt),
+            FormatText::Status(status) => status.fmt(fmt),
             FormatText::ResponseSize => size.fmt(fmt),
             FormatText::Time => {
                 let rt = entry_time.elapsed().unwrap();
@@ -385,7 +592,7 @@ impl FormatText {
     fn render_response<B>(&mut self, res: &HttpResponse<B>) {
         match *self {
             FormatText::ResponseStatus => {
-                *self = FormatText::Str(format!("{}", res.status().as_u16()))
+                *self = FormatText::Status(res.status().as_u16())
             }
             FormatText::ResponseHeader(ref name) => {
                 let s = if let Some(val) = res.headers().get(name) {
@@ -403,6 +610,41 @@ impl FormatText {
         }
     }
 
+    fn render_extensions(
+        &mut self,
+        fields: &[(String, Box<dyn Fn(&Extensions) -> Option<String>>)],
+        extensions: &Extensions,
+    ) {
+        match *self {
+            FormatText::Custom(ref name) => {
+                let val = fields
+                    .iter()
+                    .find(|(n, _)| n == name)
+                    .and_then(|(_, f)| f(extensions));
+                *self = FormatText::Str(val.unwrap_or_else(|| "-".to_string()));
+            }
+            FormatText::RequestId => {
+                let val = extensions.get::<RequestId>().map(|id| id.to_string());
+                *self = FormatText::Str(val.unwrap_or_else(|| "-".to_string()));
+            }
+            _ => (),
+        }
+    }
+
+    fn json(&self, size: usize, entry_time: time::SystemTime) -> serde_json::Value {
+        match *self {
+            FormatText::Str(ref s) if s == "-" => serde_json::Value::Null,
+            FormatText::Str(ref s) => serde_json::Value::from(s.as_str()),
+            FormatText::Status(status) => serde_json::Value::from(status),
+            FormatText::ResponseSize => serde_json::Value::from(size),
+            FormatText::TimeMillis => {
+                let rt = entry_time.elapsed().unwrap_or_default();
+                serde_json::Value::from((rt.as_nanos() as f64) / 1_000_000.0)
+            }
+            _ => serde_json::Value::Null,
+        }
+    }
+
     fn render_request<E>(&mut self, now: time::SystemTime, req: &WebRequest<E>) {
         match *self {
             FormatText::RequestLine => {
@@ -440,7 +682,8 @@ impl FormatText {
                 *self = FormatText::Str(s.to_string());
             }
             FormatText::RemoteAddr => {
-                let s = if let Some(remote) = req.connection_info().remote() {
+                let s = if let Some(remote) = req.connection_info().realip_remote_addr()
+                {
                     FormatText::Str(remote.to_string())
                 } else {
                     FormatText::Str("-".to_string())
@@ -468,8 +711,9 @@ mod tests {
     use crate::http::{header, StatusCode};
     use crate::service::{IntoService, Service, Transform};
     use crate::util::lazy;
+    use crate::web::middleware::RequestIdentifier;
     use crate::web::test::{self, TestRequest};
-    use crate::web::{DefaultError, Error};
+    use crate::web::{self, App, DefaultError, Error};
 
     #[crate::rt_test]
     async fn test_logger() {
@@ -510,6 +754,140 @@ mod tests {
         assert_eq!(body, Bytes::from_static(b"TEST"));
     }
 
+    #[crate::rt_test]
+    async fn test_json() {
+        struct UserId(u64);
+
+        let srv = |req: WebRequest<DefaultError>| async move {
+            if req.path() == "/user" {
+                req.extensions_mut().insert(UserId(10));
+            }
+            Ok::<_, Error>(req.into_response(HttpResponse::Ok().body("TEST")))
+        };
+        let records = Rc::new(RefCell::new(Vec::new()));
+        let records2 = records.clone();
+        let logger = Logger::json()
+            .custom_field("user", |ext| ext.get::<UserId>().map(|id| id.0.to_string()))
+            .writer(move |rec: &str| records2.borrow_mut().push(rec.to_string()))
+            .collect_times(2);
+        let times = logger.response_times().unwrap();
+        assert!(times.is_empty());
+        assert_eq!(times.percentile(50.0), None);
+
+        let srv = Transform::new_transform(&logger, srv.into_service())
+            .await
+            .unwrap();
+
+        let req = TestRequest::with_uri("/user")
+            .header(header::USER_AGENT, "NTEX-WEB")
+            .to_srv_request();
+        let res = srv.call(req).await.unwrap();
+        let _ = test::read_body(res).await;
+
+        let rec: serde_json::Value = serde_json::from_str(&records.borrow()[0]).unwrap();
+        assert_eq!(rec["request"], "GET /user HTTP/1.1");
+        assert_eq!(rec["status"], 200);
+        assert_eq!(rec["size"], 4);
+        assert_eq!(rec["user_agent"], "NTEX-WEB");
+        assert_eq!(rec["referer"], serde_json::Value::Null);
+        assert_eq!(rec["user"], "10");
+        assert!(rec["duration_ms"].is_f64());
+
+        let req = TestRequest::with_uri("/").to_srv_request();
+        let res = srv.call(req).await.unwrap();
+        let _ = test::read_body(res).await;
+        let rec: serde_json::Value = serde_json::from_str(&records.borrow()[1]).unwrap();
+        assert_eq!(rec["user"], serde_json::Value::Null);
+
+        let req = TestRequest::with_uri("/").to_srv_request();
+        let res = srv.call(req).await.unwrap();
+        let _ = test::read_body(res).await;
+        assert_eq!(records.borrow().len(), 3);
+        assert_eq!(times.len(), 2);
+        assert!(times.percentile(0.0).unwrap() <= times.percentile(99.0).unwrap());
+        times.clear();
+        assert!(times.is_empty());
+    }
+
+    #[crate::rt_test]
+    async fn test_custom_field() {
+        let srv = |req: WebRequest<DefaultError>| async move {
+            req.extensions_mut().insert(10usize);
+            Ok::<_, Error>(req.into_response(HttpResponse::Ok().finish()))
+        };
+        let records = Rc::new(RefCell::new(Vec::new()));
+        let records2 = records.clone();
+        let logger = Logger::new("%s %{num}x %{unknown}x")
+            .custom_field("num", |ext| ext.get::<usize>().map(|n| n.to_string()))
+            .writer(move |rec: &str| records2.borrow_mut().push(rec.to_string()));
+
+        let srv = Transform::new_transform(&logger, srv.into_service())
+            .await
+            .unwrap();
+        let res = srv.call(TestRequest::default().to_srv_request()).await;
+        drop(res);
+        assert_eq!(records.borrow()[0], "200 10 -");
+    }
+
+    #[crate::rt_test]
+    async fn test_request_id() {
+        let records = Rc::new(RefCell::new(Vec::new()));
+        let records2 = records.clone();
+        let logger = Logger::new("%L %s")
+            .writer(move |rec: &str| records2.borrow_mut().push(rec.to_string()));
+
+        // logger is registered before request id middleware
+        let srv = test::init_service(
+            App::new()
+                .wrap(RequestIdentifier::new().generator(|| "id-1".to_string()))
+                .wrap(logger)
+                .route("/", web::get().to(|| async { HttpResponse::Ok() })),
+        )
+        .await;
+        let res = test::call_service(&srv, TestRequest::default().to_request()).await;
+        drop(res);
+
+        let srv = test::init_service(
+            App::new()
+                .wrap(Logger::new("%L %s").writer({
+                    let records = records.clone();
+                    move |rec: &str| records.borrow_mut().push(rec.to_string())
+                }))
+                .route("/", web::get().to(|| async { HttpResponse::Ok() })),
+        )
+        .await;
+        let res = test::call_service(&srv, TestRequest::default().to_request()).await;
+        drop(res);
+
+        assert_eq!(records.borrow()[0], "id-1 200");
+        assert_eq!(records.borrow()[1], "- 200");
+    }
+
+    #[test]
+    fn test_response_times() {
+        let times = ResponseTimes::new(100);
+        for i in 1..=100 {
+            times.record(time::Duration::from_millis(i));
+        }
+        assert_eq!(
+            times.percentile(50.0),
+            Some(time::Duration::from_millis(50))
+        );
+        assert_eq!(
+            times.percentile(99.0),
+            Some(time::Duration::from_millis(99))
+        );
+        assert_eq!(
+            times.percentile(100.0),
+            Some(time::Duration::from_millis(100))
+        );
+        assert_eq!(times.percentile(0.0), Some(time::Duration::from_millis(1)));
+
+        times.record(time::Duration::from_millis(200));
+        assert_eq!(times.len(), 100);
+        assert_eq!(times.percentile(0.0), Some(time::Duration::from_millis(2)));
+    }
+
     #[crate::rt_test]
     async fn test_url_path() {
         let mut format = Format::new("%T %U");
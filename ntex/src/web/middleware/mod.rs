@@ -5,8 +5,34 @@ mod compress;
 #[cfg(feature = "compress")]
 pub use self::compress::Compress;
 
+#[cfg(feature = "compress")]
+mod decompress;
+#[cfg(feature = "compress")]
+pub use self::decompress::Decompress;
+
+mod conditional;
+pub use self::conditional::Conditional;
+
+mod cors;
+pub use self::cors::Cors;
+
+mod errhandlers;
+pub use self::errhandlers::ErrorHandlers;
+
 mod logger;
-pub use self::logger::Logger;
+pub use self::logger::{LogWriter, Logger, ResponseTimes};
 
 mod defaultheaders;
 pub use self::defaultheaders::DefaultHeaders;
+
+mod normalize;
+pub use self::normalize::{NormalizePath, TrailingSlash};
+
+mod ratelimit;
+pub use self::ratelimit::RateLimit;
+
+mod requestid;
+pub use self::requestid::{RequestId, RequestIdentifier};
+
+pub(super) mod timeout;
+pub use self::timeout::Timeout;
@@ -0,0 +1,331 @@
+//! `Middleware` to normalize request's path
+use std::task::{Context, Poll};
+use std::{convert::TryFrom, marker::PhantomData};
+
+use crate::http::header::LOCATION;
+use crate::http::uri::{PathAndQuery, Uri};
+use crate::http::StatusCode;
+use crate::service::{Service, Transform};
+use crate::util::{Either, Ready};
+use crate::web::dev::{WebRequest, WebResponse};
+use crate::web::HttpResponse;
+
+/// Trailing slash policy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrailingSlash {
+    /// Keep trailing slash as is, only merge duplicate slashes
+    MergeOnly,
+    /// Remove trailing slash
+    Trim,
+    /// Append trailing slash
+    Always,
+}
+
+/// `Middleware` to normalize request's path.
+///
+/// Middleware merges duplicate slashes and applies trailing slash policy.
+/// By default normalized path is used for routing, alternatively middleware
+/// could respond with redirect to normalized path, see
+/// `NormalizePath::redirect()`.
+///
+/// If middleware is registered on application level, whole path is
+/// normalized. If middleware is registered on scope level, only part
+/// of the path after scope's prefix is normalized.
+///
+/// ```rust
+/// use ntex::http::StatusCode;
+/// use ntex::web::{self, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(middleware::NormalizePath::default())
+///         .service(
+///             web::scope("/api")
+///                 .wrap(
+///                     middleware::NormalizePath::new(middleware::TrailingSlash::Trim)
+///                         .redirect(StatusCode::PERMANENT_REDIRECT),
+///                 )
+///                 .route("/users", web::get().to(|| async { HttpResponse::Ok() })),
+///         );
+/// }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct NormalizePath {
+    trailing: TrailingSlash,
+    redirect: Option<StatusCode>,
+}
+
+impl Default for NormalizePath {
+    fn default() -> Self {
+        NormalizePath {
+            trailing: TrailingSlash::MergeOnly,
+            redirect: None,
+        }
+    }
+}
+
+impl NormalizePath {
+    /// Create new `NormalizePath` middleware with trailing slash policy.
+    pub fn new(trailing: TrailingSlash) -> Self {
+        NormalizePath {
+            trailing,
+            redirect: None,
+        }
+    }
+
+    /// Redirect to normalized path instead of internal rewrite.
+    ///
+    /// Status should be *301 Moved Permanently* or *308 Permanent Redirect*,
+    /// 308 preserves request method and body. Panics if status is
+    /// not a redirection status.
+    pub fn redirect(mut self, status: StatusCode) -> Self {
+        assert!(status.is_redirection(), "Status must be a redirection");
+        self.redirect = Some(status);
+        self
+    }
+}
+
+impl<S, E> Transform<S> for NormalizePath
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse>,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse;
+    type Error = S::Error;
+    type InitError = ();
+    type Transform = NormalizePathMiddleware<S, E>;
+    type Future = Ready<Self::Transform, Self::InitError>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        Ready::Ok(NormalizePathMiddleware {
+            service,
+            trailing: self.trailing,
+            redirect: self.redirect,
+            _t: PhantomData,
+        })
+    }
+}
+
+pub struct NormalizePathMiddleware<S, E> {
+    service: S,
+    trailing: TrailingSlash,
+    redirect: Option<StatusCode>,
+    _t: PhantomData<E>,
+}
+
+impl<S, E> Service for NormalizePathMiddleware<S, E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse>,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse;
+    type Error = S::Error;
+    type Future = Either<Ready<Self::Response, Self::Error>, S::Future>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&self, mut req: WebRequest<E>) -> Self::Future {
+        let path = req.path();
+        let unprocessed = req.match_info().path();
+        let processed = &path[..path.len() - unprocessed.len()];
+
+        if let Some(normalized) = normalize(processed, unprocessed, self.trailing) {
+            let pq = if let Some(query) = req.uri().query() {
+                format!("{}?{}", normalized, query)
+            } else {
+                normalized
+            };
+
+            if let Some(status) = self.redirect {
+                // browsers treat `\` as `/`, leading `/\` or `//` in location
+                // is a protocol-relative url, path is rewritten instead
+                let location = pq.replace('\\', "%5C");
+                if !location.starts_with("//") {
+                    log::trace!(
+                        "Redirect {:?} to normalized path {:?}",
+                        req.path(),
+                        location
+                    );
+                    let res = HttpResponse::build(status)
+                        .header(LOCATION, location)
+                        .finish();
+                    return Either::Left(Ready::Ok(req.into_response(res)));
+                }
+            }
+
+            if let Ok(pq) = PathAndQuery::try_from(pq.as_str()) {
+                let mut parts = req.uri().clone().into_parts();
+                parts.path_and_query = Some(pq);
+                if let Ok(uri) = Uri::from_parts(parts) {
+                    log::trace!("Rewrite {:?} to normalized path {:?}", req.path(), uri);
+                    *req.match_info_mut().get_mut() = uri.clone();
+                    req.head_mut().uri = uri;
+                }
+            }
+        }
+        Either::Right(self.service.call(req))
+    }
+}
+
+/// Normalize unprocessed part of the path, returns `None` if
+/// path is already normalized
+fn normalize(
+    processed: &str,
+    unprocessed: &str,
+    trailing: TrailingSlash,
+) -> Option<String> {
+    let mut path = String::with_capacity(processed.len() + unprocessed.len() + 1);
+    path.push_str(processed);
+
+    let mut prev_slash = processed.ends_with('/');
+    for ch in unprocessed.chars() {
+        if ch == '/' {
+            if prev_slash {
+                continue;
+            }
+            prev_slash = true;
+        } else {
+            prev_slash = false;
+        }
+        path.push(ch);
+    }
+
+    match trailing {
+        TrailingSlash::MergeOnly => (),
+        TrailingSlash::Trim => {
+            while path.len() > processed.len().max(1) && path.ends_with('/') {
+                path.pop();
+            }
+        }
+        TrailingSlash::Always => {
+            if !path.ends_with('/') {
+                path.push('/');
+            }
+        }
+    }
+
+    if path.len() == processed.len() + unprocessed.len()
+        && path[processed.len()..] == *unprocessed
+    {
+        None
+    } else {
+        Some(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::web::test::{call_service, init_service, read_body, TestRequest};
+    use crate::web::{self, App, HttpRequest};
+
+    #[test]
+    fn test_normalize() {
+        use TrailingSlash::*;
+
+        assert_eq!(normalize("", "/", MergeOnly), None);
+        assert_eq!(normalize("", "//a//b/", MergeOnly), Some("/a/b/".into()));
+        assert_eq!(normalize("", "/a/b", MergeOnly), None);
+        assert_eq!(normalize("", "/", Trim), None);
+        assert_eq!(normalize("", "//", Trim), Some("/".into()));
+        assert_eq!(normalize("", "/a//b//", Trim), Some("/a/b".into()));
+        assert_eq!(normalize("", "/a/b", Always), Some("/a/b/".into()));
+        assert_eq!(normalize("", "/a/b/", Always), None);
+        assert_eq!(normalize("/api", "/", Trim), Some("/api".into()));
+        assert_eq!(normalize("/api", "", Trim), None);
+        assert_eq!(normalize("/api/", "/a", MergeOnly), Some("/api/a".into()));
+    }
+
+    #[crate::rt_test]
+    async fn test_rewrite() {
+        let srv = init_service(
+            App::new()
+                .wrap(NormalizePath::new(TrailingSlash::Trim))
+                .route(
+                    "/a/b",
+                    web::get()
+                        .to(|req: HttpRequest| async move { format!("{}", req.uri()) }),
+                ),
+        )
+        .await;
+
+        for uri in &["/a/b", "//a//b", "/a/b/", "/a///b//"] {
+            let req = TestRequest::with_uri(uri).to_request();
+            let resp = call_service(&srv, req).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            assert_eq!(read_body(resp).await, "/a/b");
+        }
+
+        let req = TestRequest::with_uri("//a/b/?q=1").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(read_body(resp).await, "/a/b?q=1");
+    }
+
+    #[crate::rt_test]
+    async fn test_redirect() {
+        let srv = init_service(
+            App::new()
+                .wrap(
+                    NormalizePath::new(TrailingSlash::Always)
+                        .redirect(StatusCode::PERMANENT_REDIRECT),
+                )
+                .route("/a/", web::get().to(|| async { HttpResponse::Ok() })),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/a/").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = TestRequest::with_uri("//a?q=1").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::PERMANENT_REDIRECT);
+        assert_eq!(resp.headers().get(LOCATION).unwrap(), "/a/?q=1");
+
+        // protocol-relative location
+        let req = TestRequest::with_uri("//\\evil.com//").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::PERMANENT_REDIRECT);
+        assert_eq!(resp.headers().get(LOCATION).unwrap(), "/%5Cevil.com/");
+    }
+
+    #[crate::rt_test]
+    async fn test_scope() {
+        let srv = init_service(
+            App::new()
+                .service(
+                    web::scope("/api")
+                        .wrap(NormalizePath::new(TrailingSlash::Trim))
+                        .route("", web::get().to(|| async { HttpResponse::Ok() }))
+                        .route("/users", web::get().to(|| async { HttpResponse::Ok() })),
+                )
+                .route("/other", web::get().to(|| async { HttpResponse::Ok() })),
+        )
+        .await;
+
+        for uri in &["/api", "/api/", "/api/users", "/api//users/"] {
+            let req = TestRequest::with_uri(uri).to_request();
+            let resp = call_service(&srv, req).await;
+            assert_eq!(resp.status(), StatusCode::OK, "{}", uri);
+        }
+
+        // paths outside of the scope are not normalized
+        let req = TestRequest::with_uri("/other/").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_redirect_status() {
+        let _ = NormalizePath::default().redirect(StatusCode::OK);
+    }
+}
@@ -0,0 +1,339 @@
+//! Rate limiting middleware
+use std::task::{Context, Poll};
+use std::time::Duration;
+use std::{cell::RefCell, collections::HashMap, marker::PhantomData, rc::Rc};
+
+use crate::http::header::{HeaderValue, RETRY_AFTER};
+use crate::rt::time::Instant;
+use crate::service::{Service, Transform};
+use crate::util::ratelimit::TokenBucket;
+use crate::util::{Either, Ready};
+use crate::web::dev::{WebRequest, WebResponse};
+use crate::web::{HttpRequest, HttpResponse};
+
+type KeyFn = dyn Fn(&HttpRequest) -> Option<String>;
+
+/// `Middleware` for limiting request rate.
+///
+/// Every key gets token bucket, each request consumes one token, tokens are
+/// replenished at configured rate up to burst size. If bucket is empty,
+/// middleware responds with *429 Too Many Requests* and sets `Retry-After`
+/// header. By default burst size is equal to number of requests per period.
+///
+/// By default requests are keyed by peer ip address, custom key could be
+/// set with `RateLimit::key()` method. Requests without key are not limited.
+///
+/// Limits state is shared between clones of `RateLimit`. Each server worker
+/// constructs its own application, so limits are tracked per worker.
+///
+/// ```rust
+/// use std::time::Duration;
+/// use ntex::web::{self, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     // 10 requests per second per api key
+///     let limit = middleware::RateLimit::new(10, Duration::from_secs(1))
+///         .key(|req| {
+///             req.headers()
+///                 .get("x-api-key")
+///                 .and_then(|v| v.to_str().ok())
+///                 .map(|v| v.to_string())
+///         });
+///
+///     let app = App::new().service(
+///         web::scope("/api")
+///             .wrap(limit)
+///             .route("/index.html", web::get().to(|| async { HttpResponse::Ok() }))
+///     );
+/// }
+/// ```
+#[derive(Clone)]
+pub struct RateLimit {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    interval: Duration,
+    burst: u32,
+    key: Box<KeyFn>,
+    buckets: RefCell<Buckets>,
+}
+
+struct Buckets {
+    items: HashMap<String, TokenBucket>,
+    purged: Instant,
+}
+
+impl RateLimit {
+    /// Construct `RateLimit` middleware, allows `num` requests per `per` period
+    ///
+    /// Panics if `num` or `per` is zero.
+    pub fn new(num: u32, per: Duration) -> Self {
+        assert!(num > 0, "Number of requests must be greater than 0");
+        assert!(
+            per > Duration::from_secs(0),
+            "Period must be greater than 0"
+        );
+
+        RateLimit {
+            inner: Rc::new(Inner {
+                interval: per / num,
+                burst: num,
+                key: Box::new(|req| req.peer_addr().map(|addr| addr.ip().to_string())),
+                buckets: RefCell::new(Buckets {
+                    items: HashMap::new(),
+                    purged: Instant::now(),
+                }),
+            }),
+        }
+    }
+
+    /// Construct `RateLimit` middleware, allows `num` requests per second
+    pub fn per_second(num: u32) -> Self {
+        RateLimit::new(num, Duration::from_secs(1))
+    }
+
+    /// Set max number of requests that could be processed without delay
+    pub fn burst(mut self, burst: u32) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .burst = std::cmp::max(1, burst);
+        self
+    }
+
+    /// Set function that extracts limit key from request.
+    ///
+    /// Key could be ip address, header value, session id etc.
+    /// If function returns `None`, request is not limited.
+    pub fn key<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&HttpRequest) -> Option<String> + 'static,
+    {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .key = Box::new(f);
+        self
+    }
+}
+
+impl Inner {
+    /// Take token from the bucket, returns time to wait on failure
+    fn acquire(&self, key: String) -> Result<(), Duration> {
+        let now = Instant::now();
+        let mut buckets = self.buckets.borrow_mut();
+
+        // remove buckets that are full anyway
+        if now.duration_since(buckets.purged) >= self.interval * self.burst {
+            buckets.items.retain(|_, b| !b.is_full());
+            buckets.purged = now;
+        }
+
+        let (interval, burst) = (self.interval, self.burst);
+        buckets
+            .items
+            .entry(key)
+            .or_insert_with(|| TokenBucket::new(interval, burst))
+            .acquire()
+    }
+}
+
+impl<S, E> Transform<S> for RateLimit
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse>,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse;
+    type Error = S::Error;
+    type InitError = ();
+    type Transform = RateLimitMiddleware<S, E>;
+    type Future = Ready<Self::Transform, Self::InitError>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        Ready::Ok(RateLimitMiddleware {
+            service,
+            inner: self.inner.clone(),
+            _t: PhantomData,
+        })
+    }
+}
+
+pub struct RateLimitMiddleware<S, E> {
+    service: S,
+    inner: Rc<Inner>,
+    _t: PhantomData<E>,
+}
+
+impl<S, E> Service for RateLimitMiddleware<S, E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse>,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse;
+    type Error = S::Error;
+    type Future = Either<Ready<Self::Response, Self::Error>, S::Future>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&self, req: WebRequest<E>) -> Self::Future {
+        if let Some(key) = (*self.inner.key)(req.http_request()) {
+            if let Err(wait) = self.inner.acquire(key) {
+                log::trace!("Rate limit exceeded for {:?}", req.path());
+
+                // round up to whole seconds
+                let secs = wait.as_secs() + u64::from(wait.subsec_nanos() > 0);
+                let res = HttpResponse::TooManyRequests()
+                    .header(RETRY_AFTER, HeaderValue::from(secs.max(1)))
+                    .finish();
+                return Either::Left(Ready::Ok(req.into_response(res)));
+            }
+        }
+        Either::Right(self.service.call(req))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::rt::time::sleep;
+    use crate::service::IntoService;
+    use crate::web::test::{call_service, init_service, ok_service, TestRequest};
+    use crate::web::{self, App, DefaultError, Error};
+
+    #[crate::rt_test]
+    async fn test_rate_limit() {
+        let mw = RateLimit::new(2, Duration::from_millis(200))
+            .new_transform(ok_service())
+            .await
+            .unwrap();
+
+        let addr = "127.0.0.1:8081".parse().unwrap();
+        for _ in 0..2 {
+            let req = TestRequest::default().peer_addr(addr).to_srv_request();
+            let resp = mw.call(req).await.unwrap();
+            assert_eq!(resp.status(), StatusCode::OK);
+        }
+        let req = TestRequest::default().peer_addr(addr).to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(resp.headers().get(RETRY_AFTER).unwrap(), "1");
+
+        // other peer
+        let req = TestRequest::default()
+            .peer_addr("127.0.0.2:8081".parse().unwrap())
+            .to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        // no key, no limit
+        for _ in 0..3 {
+            let req = TestRequest::default().to_srv_request();
+            let resp = mw.call(req).await.unwrap();
+            assert_eq!(resp.status(), StatusCode::OK);
+        }
+
+        // refill
+        sleep(Duration::from_millis(150)).await;
+        let req = TestRequest::default().peer_addr(addr).to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[crate::rt_test]
+    async fn test_burst() {
+        let mw = RateLimit::per_second(1)
+            .burst(3)
+            .key(|_| Some("key".to_string()))
+            .new_transform(ok_service())
+            .await
+            .unwrap();
+
+        for _ in 0..3 {
+            let req = TestRequest::default().to_srv_request();
+            let resp = mw.call(req).await.unwrap();
+            assert_eq!(resp.status(), StatusCode::OK);
+        }
+        let req = TestRequest::default().to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(resp.headers().get(RETRY_AFTER).unwrap(), "1");
+    }
+
+    #[crate::rt_test]
+    async fn test_key() {
+        let srv = |req: WebRequest<DefaultError>| async move {
+            Ok::<_, Error>(req.into_response(HttpResponse::Ok().finish()))
+        };
+        let mw = RateLimit::new(1, Duration::from_secs(10))
+            .key(|req| {
+                req.headers()
+                    .get("x-api-key")
+                    .and_then(|v| v.to_str().ok())
+                    .map(|v| v.to_string())
+            })
+            .new_transform(srv.into_service())
+            .await
+            .unwrap();
+
+        let req = TestRequest::with_header("x-api-key", "a").to_srv_request();
+        assert_eq!(mw.call(req).await.unwrap().status(), StatusCode::OK);
+        let req = TestRequest::with_header("x-api-key", "a").to_srv_request();
+        let resp = mw.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(resp.headers().get(RETRY_AFTER).unwrap(), "10");
+
+        let req = TestRequest::with_header("x-api-key", "b").to_srv_request();
+        assert_eq!(mw.call(req).await.unwrap().status(), StatusCode::OK);
+    }
+
+    #[crate::rt_test]
+    async fn test_scope_and_resource() {
+        let key = |_: &HttpRequest| Some("key".to_string());
+        let srv = init_service(
+            App::new()
+                .service(
+                    web::scope("/scope")
+                        .wrap(RateLimit::new(1, Duration::from_secs(10)).key(key))
+                        .route("/test", web::get().to(|| async { HttpResponse::Ok() })),
+                )
+                .service(
+                    web::resource("/res")
+                        .wrap(RateLimit::new(2, Duration::from_secs(10)).key(key))
+                        .route(web::get().to(|| async { HttpResponse::Ok() })),
+                )
+                .route("/free", web::get().to(|| async { HttpResponse::Ok() })),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/scope/test").to_request();
+        assert_eq!(call_service(&srv, req).await.status(), StatusCode::OK);
+        let req = TestRequest::with_uri("/scope/test").to_request();
+        assert_eq!(
+            call_service(&srv, req).await.status(),
+            StatusCode::TOO_MANY_REQUESTS
+        );
+
+        for _ in 0..2 {
+            let req = TestRequest::with_uri("/res").to_request();
+            assert_eq!(call_service(&srv, req).await.status(), StatusCode::OK);
+        }
+        let req = TestRequest::with_uri("/res").to_request();
+        assert_eq!(
+            call_service(&srv, req).await.status(),
+            StatusCode::TOO_MANY_REQUESTS
+        );
+
+        for _ in 0..3 {
+            let req = TestRequest::with_uri("/free").to_request();
+            assert_eq!(call_service(&srv, req).await.status(), StatusCode::OK);
+        }
+    }
+}
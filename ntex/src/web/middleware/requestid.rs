@@ -0,0 +1,336 @@
+//! `Middleware` for request identification.
+use std::task::{Context, Poll};
+use std::{
+    convert::TryFrom, fmt, future::Future, marker::PhantomData, pin::Pin, rc::Rc,
+};
+
+use nanorand::{WyRand, RNG};
+
+use crate::http::header::{HeaderName, HeaderValue};
+use crate::http::Payload;
+use crate::service::{Service, Transform};
+use crate::util::Ready;
+use crate::web::dev::{WebRequest, WebResponse};
+use crate::web::error::{ErrorRenderer, RequestIdError};
+use crate::web::{FromRequest, HttpRequest};
+
+/// Max length of incoming request id
+const MAX_LENGTH: usize = 128;
+
+/// Request id.
+///
+/// Id is set by `RequestIdentifier` middleware and stored in request
+/// extensions. `RequestId` could be used as extractor, extraction fails if
+/// middleware is not registered.
+///
+/// ```rust
+/// use ntex::web::{self, middleware::RequestId};
+///
+/// async fn index(id: RequestId) -> String {
+///     format!("Request id: {}", id)
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RequestId(Rc<str>);
+
+impl RequestId {
+    /// Get request id as string
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl<Err: ErrorRenderer> FromRequest<Err> for RequestId {
+    type Error = RequestIdError;
+    type Future = Ready<Self, Self::Error>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        if let Some(id) = req.extensions().get::<RequestId>() {
+            Ready::Ok(id.clone())
+        } else {
+            Ready::Err(RequestIdError::NotConfigured)
+        }
+    }
+}
+
+/// `Middleware` for request identification.
+///
+/// Middleware uses request id from the incoming request header, or generates
+/// new random id if header is missing or invalid. Id is stored in request
+/// extensions as `RequestId` and is added to the response headers.
+/// Default header is `x-request-id`.
+///
+/// Request id is available in `Logger` format as `%L`.
+///
+/// ```rust
+/// use ntex::web::{self, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new()
+///         .wrap(middleware::RequestIdentifier::new().header("x-correlation-id"))
+///         .wrap(middleware::Logger::new("%L %r %s"))
+///         .route("/index.html", web::get().to(|| async { HttpResponse::Ok() }));
+/// }
+/// ```
+#[derive(Clone)]
+pub struct RequestIdentifier {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    header: HeaderName,
+    incoming: bool,
+    generator: Box<dyn Fn() -> String>,
+}
+
+impl Default for RequestIdentifier {
+    fn default() -> Self {
+        RequestIdentifier {
+            inner: Rc::new(Inner {
+                header: HeaderName::from_static("x-request-id"),
+                incoming: true,
+                generator: Box::new(generate),
+            }),
+        }
+    }
+}
+
+impl RequestIdentifier {
+    /// Create new `RequestIdentifier` middleware.
+    pub fn new() -> Self {
+        RequestIdentifier::default()
+    }
+
+    /// Set request and response header name.
+    ///
+    /// Panics if header name is not valid.
+    pub fn header(mut self, name: &str) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .header = HeaderName::try_from(name).unwrap();
+        self
+    }
+
+    /// Use request id from the incoming request header.
+    ///
+    /// By default incoming id is used.
+    pub fn use_incoming(mut self, value: bool) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .incoming = value;
+        self
+    }
+
+    /// Set request id generator.
+    ///
+    /// By default random 128 bits hex encoded id is generated.
+    pub fn generator<F>(mut self, f: F) -> Self
+    where
+        F: Fn() -> String + 'static,
+    {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .generator = Box::new(f);
+        self
+    }
+}
+
+fn generate() -> String {
+    let mut rng = WyRand::new();
+    format!(
+        "{:016x}{:016x}",
+        rng.generate::<u64>(),
+        rng.generate::<u64>()
+    )
+}
+
+impl<S, E> Transform<S> for RequestIdentifier
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse>,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse;
+    type Error = S::Error;
+    type InitError = ();
+    type Transform = RequestIdentifierMiddleware<S, E>;
+    type Future = Ready<Self::Transform, Self::InitError>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        Ready::Ok(RequestIdentifierMiddleware {
+            service,
+            inner: self.inner.clone(),
+            _t: PhantomData,
+        })
+    }
+}
+
+pub struct RequestIdentifierMiddleware<S, E> {
+    service: S,
+    inner: Rc<Inner>,
+    _t: PhantomData<E>,
+}
+
+impl<S, E> Service for RequestIdentifierMiddleware<S, E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse>,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse;
+    type Error = S::Error;
+    type Future = RequestIdResponse<S, E>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&self, req: WebRequest<E>) -> Self::Future {
+        let incoming = if self.inner.incoming {
+            req.headers()
+                .get(&self.inner.header)
+                .and_then(|val| val.to_str().ok())
+                .filter(|val| is_valid(val))
+                .map(|val| val.to_string())
+        } else {
+            None
+        };
+        let id = incoming.unwrap_or_else(|| (*self.inner.generator)());
+        log::trace!("Request id {:?} for {:?}", id, req.path());
+
+        let id = RequestId(id.into());
+        req.extensions_mut().insert(id.clone());
+
+        RequestIdResponse {
+            id,
+            inner: self.inner.clone(),
+            fut: self.service.call(req),
+            _t: PhantomData,
+        }
+    }
+}
+
+/// Check if incoming request id could be used
+fn is_valid(val: &str) -> bool {
+    !val.is_empty()
+        && val.len() <= MAX_LENGTH
+        && val.bytes().all(|b| b.is_ascii_graphic())
+}
+
+pin_project_lite::pin_project! {
+    #[doc(hidden)]
+    pub struct RequestIdResponse<S: Service, E>
+    {
+        #[pin]
+        fut: S::Future,
+        id: RequestId,
+        inner: Rc<Inner>,
+        _t: PhantomData<E>,
+    }
+}
+
+impl<S, E> Future for RequestIdResponse<S, E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse>,
+{
+    type Output = Result<WebResponse, S::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        match this.fut.poll(cx)? {
+            Poll::Ready(mut resp) => {
+                if !resp.headers().contains_key(&this.inner.header) {
+                    if let Ok(val) = HeaderValue::from_str(this.id.as_str()) {
+                        resp.headers_mut().insert(this.inner.header.clone(), val);
+                    }
+                }
+                Poll::Ready(Ok(resp))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::web::test::{call_service, init_service, read_body, TestRequest};
+    use crate::web::{self, App};
+
+    #[crate::rt_test]
+    async fn test_request_id() {
+        let srv = init_service(App::new().wrap(RequestIdentifier::new()).route(
+            "/",
+            web::get().to(|id: RequestId| async move { id.to_string() }),
+        ))
+        .await;
+
+        let req = TestRequest::default().to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let id = resp.headers().get("x-request-id").unwrap().clone();
+        assert_eq!(id.len(), 32);
+        assert_eq!(read_body(resp).await, id.as_bytes());
+
+        // incoming id
+        let req = TestRequest::with_header("x-request-id", "abc-123").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.headers().get("x-request-id").unwrap(), "abc-123");
+        assert_eq!(read_body(resp).await, "abc-123");
+
+        // invalid incoming id
+        let req = TestRequest::with_header("x-request-id", "a b").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.headers().get("x-request-id").unwrap().len(), 32);
+    }
+
+    #[crate::rt_test]
+    async fn test_config() {
+        let srv = init_service(
+            App::new()
+                .wrap(
+                    RequestIdentifier::new()
+                        .header("x-correlation-id")
+                        .use_incoming(false)
+                        .generator(|| "generated".to_string()),
+                )
+                .route(
+                    "/",
+                    web::get().to(|id: RequestId| async move { id.to_string() }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::with_header("x-correlation-id", "abc").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.headers().get("x-correlation-id").unwrap(), "generated");
+        assert!(!resp.headers().contains_key("x-request-id"));
+        assert_eq!(read_body(resp).await, "generated");
+    }
+
+    #[crate::rt_test]
+    async fn test_not_configured() {
+        let srv = init_service(App::new().route(
+            "/",
+            web::get().to(|id: RequestId| async move { id.to_string() }),
+        ))
+        .await;
+
+        let req = TestRequest::default().to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
@@ -0,0 +1,472 @@
+//! `Middleware` for handler execution timeout.
+use std::task::{Context, Poll, Waker};
+use std::{
+    cell::Cell, cell::RefCell, collections::BTreeMap, error::Error, io,
+    marker::PhantomData, rc::Rc, time::Duration, time::Instant,
+};
+
+use crate::http::body::{Body, BodySize, MessageBody, ResponseBody};
+use crate::http::StatusCode;
+use crate::rt::time::sleep;
+use crate::service::{Service, Transform};
+use crate::util::{time::LowResTimeService, Bytes, Ready};
+use crate::web::dev::{WebRequest, WebResponse};
+use crate::web::{HttpRequest, HttpResponse};
+
+/// Timer resolution
+const RESOLUTION: Duration = Duration::from_millis(100);
+
+type ResponseFn = dyn Fn(&HttpRequest) -> HttpResponse;
+
+/// `Middleware` for handler execution timeout.
+///
+/// Middleware bounds total execution time of route handler, including
+/// request extraction and response generation. If handler does not complete
+/// in time, handler is dropped and *504 Gateway Timeout* response is returned.
+/// Response status or whole response could be customized.
+///
+/// By default response body streaming is not limited, body streaming could be
+/// included with `Timeout::include_body()` method, in that case
+/// connection is closed if body is not completed in time.
+///
+/// Middleware uses low resolution timer, timeout precision is 100 millis.
+/// Nested timeouts never extend deadline of the outer timeout.
+///
+/// ```rust
+/// use std::time::Duration;
+/// use ntex::http::StatusCode;
+/// use ntex::web::{self, middleware, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new().service(
+///         web::resource("/index.html")
+///             .wrap(
+///                 middleware::Timeout::new(Duration::from_secs(5))
+///                     .status(StatusCode::SERVICE_UNAVAILABLE)
+///             )
+///             .route(web::get().to(|| async { HttpResponse::Ok() }))
+///     );
+/// }
+/// ```
+#[derive(Clone)]
+pub struct Timeout {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    timeout: Duration,
+    status: StatusCode,
+    response: Option<Box<ResponseFn>>,
+    body: bool,
+    timer: Timer,
+}
+
+impl Timeout {
+    /// Create new `Timeout` middleware.
+    pub fn new(timeout: Duration) -> Self {
+        Timeout {
+            inner: Rc::new(Inner {
+                timeout,
+                status: StatusCode::GATEWAY_TIMEOUT,
+                response: None,
+                body: false,
+                timer: Timer::new(),
+            }),
+        }
+    }
+
+    /// Set timeout response status. By default *504 Gateway Timeout* is used.
+    pub fn status(mut self, status: StatusCode) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .status = status;
+        self
+    }
+
+    /// Set timeout response factory.
+    pub fn response<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&HttpRequest) -> HttpResponse + 'static,
+    {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .response = Some(Box::new(f));
+        self
+    }
+
+    /// Apply timeout to response body streaming.
+    ///
+    /// By default timeout is not applied.
+    pub fn include_body(mut self, value: bool) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .body = value;
+        self
+    }
+}
+
+impl<S, E> Transform<S> for Timeout
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse>,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse;
+    type Error = S::Error;
+    type InitError = ();
+    type Transform = TimeoutMiddleware<S, E>;
+    type Future = Ready<Self::Transform, Self::InitError>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        Ready::Ok(TimeoutMiddleware {
+            service,
+            inner: self.inner.clone(),
+            _t: PhantomData,
+        })
+    }
+}
+
+pub struct TimeoutMiddleware<S, E> {
+    service: S,
+    inner: Rc<Inner>,
+    _t: PhantomData<E>,
+}
+
+impl<S, E> Service for TimeoutMiddleware<S, E>
+where
+    S: Service<Request = WebRequest<E>, Response = WebResponse>,
+{
+    type Request = WebRequest<E>;
+    type Response = WebResponse;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&self, req: WebRequest<E>) -> Self::Future {
+        let expire = self.inner.timer.now() + self.inner.timeout;
+        {
+            let mut ext = req.extensions_mut();
+            let outer = ext.get::<Deadline>().map(|d| d.expire);
+            if outer.map(|outer| expire < outer).unwrap_or(true) {
+                ext.insert(Deadline {
+                    expire,
+                    inner: self.inner.clone(),
+                });
+            }
+        }
+        self.service.call(req)
+    }
+}
+
+/// Handler deadline, stored in request extensions
+#[derive(Clone)]
+pub(in crate::web) struct Deadline {
+    expire: Instant,
+    inner: Rc<Inner>,
+}
+
+impl Deadline {
+    pub(in crate::web) fn get(req: &HttpRequest) -> Option<DeadlineWait> {
+        req.extensions()
+            .get::<Deadline>()
+            .map(|deadline| DeadlineWait {
+                deadline: deadline.clone(),
+                id: Cell::new(None),
+            })
+    }
+}
+
+/// Deadline wait registration
+pub(in crate::web) struct DeadlineWait {
+    deadline: Deadline,
+    id: Cell<Option<usize>>,
+}
+
+impl DeadlineWait {
+    /// Check if deadline is expired, register task for wake up otherwise
+    pub(in crate::web) fn poll_expired(&self, cx: &mut Context<'_>) -> bool {
+        if Instant::now() >= self.deadline.expire {
+            true
+        } else {
+            let timer = &self.deadline.inner.timer;
+            let id = timer.register(self.deadline.expire, self.id.get(), cx.waker());
+            self.id.set(Some(id));
+            false
+        }
+    }
+
+    /// Timeout response
+    pub(in crate::web) fn response(&self, req: &HttpRequest) -> HttpResponse {
+        log::trace!("Handler timeout for {:?}", req.path());
+
+        if let Some(ref f) = self.deadline.inner.response {
+            f(req)
+        } else {
+            HttpResponse::new(self.deadline.inner.status)
+        }
+    }
+
+    /// Apply deadline to response body, if it is configured
+    pub(in crate::web) fn wrap_body(self, res: WebResponse) -> WebResponse {
+        if self.deadline.inner.body {
+            res.map_body(|_, body| {
+                ResponseBody::Other(Body::from_message(TimeoutBody { body, wait: self }))
+            })
+        } else {
+            res
+        }
+    }
+}
+
+impl Drop for DeadlineWait {
+    fn drop(&mut self) {
+        if let Some(id) = self.id.get() {
+            self.deadline
+                .inner
+                .timer
+                .unregister(self.deadline.expire, id);
+        }
+    }
+}
+
+/// Response body with deadline
+struct TimeoutBody {
+    body: ResponseBody<Body>,
+    wait: DeadlineWait,
+}
+
+impl MessageBody for TimeoutBody {
+    fn size(&self) -> BodySize {
+        self.body.size()
+    }
+
+    fn poll_next_chunk(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Box<dyn Error>>>> {
+        match self.body.poll_next_chunk(cx) {
+            Poll::Pending => {
+                if self.wait.poll_expired(cx) {
+                    log::trace!("Response body timeout");
+                    Poll::Ready(Some(Err(Box::new(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        "Response body timeout",
+                    )))))
+                } else {
+                    Poll::Pending
+                }
+            }
+            res => res,
+        }
+    }
+}
+
+/// Low resolution timer, wakes registered tasks once per tick
+#[derive(Clone)]
+struct Timer(Rc<RefCell<TimerInner>>);
+
+struct TimerInner {
+    time: LowResTimeService,
+    running: bool,
+    next_id: usize,
+    wakers: BTreeMap<(Instant, usize), Waker>,
+}
+
+impl Timer {
+    fn new() -> Self {
+        Timer(Rc::new(RefCell::new(TimerInner {
+            time: LowResTimeService::with(RESOLUTION),
+            running: false,
+            next_id: 0,
+            wakers: BTreeMap::new(),
+        })))
+    }
+
+    fn now(&self) -> Instant {
+        let time = self.0.borrow().time.clone();
+        time.now()
+    }
+
+    fn register(&self, expire: Instant, id: Option<usize>, waker: &Waker) -> usize {
+        let mut inner = self.0.borrow_mut();
+        let id = id.unwrap_or_else(|| {
+            inner.next_id = inner.next_id.wrapping_add(1);
+            inner.next_id
+        });
+        inner.wakers.insert((expire, id), waker.clone());
+
+        if !inner.running {
+            inner.running = true;
+            let timer = self.0.clone();
+
+            crate::rt::spawn(async move {
+                loop {
+                    sleep(RESOLUTION).await;
+
+                    let mut inner = timer.borrow_mut();
+                    let now = Instant::now();
+                    while let Some(key) = inner.wakers.keys().next().cloned() {
+                        if key.0 <= now {
+                            inner.wakers.remove(&key).unwrap().wake();
+                        } else {
+                            break;
+                        }
+                    }
+                    if inner.wakers.is_empty() {
+                        inner.running = false;
+                        break;
+                    }
+                }
+            });
+        }
+        id
+    }
+
+    fn unregister(&self, expire: Instant, id: usize) {
+        self.0.borrow_mut().wakers.remove(&(expire, id));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::header::CONTENT_TYPE;
+    use crate::util::next;
+    use crate::web::test::{call_service, init_service, read_body, TestRequest};
+    use crate::web::{self, App};
+
+    #[crate::rt_test]
+    async fn test_timeout() {
+        let srv = init_service(
+            App::new()
+                .wrap(Timeout::new(Duration::from_millis(150)))
+                .route(
+                    "/",
+                    web::get().to(|| async {
+                        sleep(Duration::from_millis(1000)).await;
+                        HttpResponse::Ok()
+                    }),
+                )
+                .route("/ok", web::get().to(|| async { HttpResponse::Ok() })),
+        )
+        .await;
+
+        let start = Instant::now();
+        let req = TestRequest::default().to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::GATEWAY_TIMEOUT);
+        assert!(start.elapsed() < Duration::from_millis(500));
+
+        let req = TestRequest::with_uri("/ok").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[crate::rt_test]
+    async fn test_custom_response() {
+        let srv = init_service(
+            App::new()
+                .wrap(Timeout::new(Duration::from_secs(10)))
+                .service(
+                    web::resource("/")
+                        .wrap(Timeout::new(Duration::from_millis(100)).response(|req| {
+                            HttpResponse::ServiceUnavailable()
+                                .content_type("application/json")
+                                .body(format!("{{\"timeout\":\"{}\"}}", req.path()))
+                        }))
+                        .route(web::get().to(|| async {
+                            sleep(Duration::from_millis(1000)).await;
+                            HttpResponse::Ok()
+                        })),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::default().to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(
+            resp.headers().get(CONTENT_TYPE).unwrap(),
+            "application/json"
+        );
+        assert_eq!(read_body(resp).await, "{\"timeout\":\"/\"}");
+    }
+
+    #[crate::rt_test]
+    async fn test_outer_deadline() {
+        let srv = init_service(
+            App::new()
+                .wrap(Timeout::new(Duration::from_millis(100)))
+                .service(
+                    web::resource("/")
+                        .wrap(
+                            Timeout::new(Duration::from_secs(10))
+                                .status(StatusCode::SERVICE_UNAVAILABLE),
+                        )
+                        .route(web::get().to(|| async {
+                            sleep(Duration::from_millis(1000)).await;
+                            HttpResponse::Ok()
+                        })),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::default().to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::GATEWAY_TIMEOUT);
+    }
+
+    #[crate::rt_test]
+    async fn test_body() {
+        let stream = || {
+            let (tx, rx) = crate::channel::mpsc::channel::<Result<Bytes, io::Error>>();
+            tx.send(Ok(Bytes::from_static(b"chunk"))).unwrap();
+            crate::rt::spawn(async move {
+                sleep(Duration::from_millis(1000)).await;
+                drop(tx);
+            });
+            HttpResponse::Ok().streaming(rx)
+        };
+
+        let srv = init_service(
+            App::new()
+                .service(
+                    web::resource("/")
+                        .wrap(Timeout::new(Duration::from_millis(100)))
+                        .route(web::get().to(move || async move { stream() })),
+                )
+                .service(
+                    web::resource("/body")
+                        .wrap(
+                            Timeout::new(Duration::from_millis(100)).include_body(true),
+                        )
+                        .route(web::get().to(move || async move { stream() })),
+                ),
+        )
+        .await;
+
+        // body streaming is not limited
+        let req = TestRequest::default().to_request();
+        let mut resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let mut body = resp.take_body();
+        assert_eq!(next(&mut body).await.unwrap().unwrap(), "chunk");
+        assert!(next(&mut body).await.is_none());
+
+        let req = TestRequest::with_uri("/body").to_request();
+        let mut resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let mut body = resp.take_body();
+        assert_eq!(next(&mut body).await.unwrap().unwrap(), "chunk");
+        let err = next(&mut body).await.unwrap().err().unwrap();
+        assert_eq!(err.to_string(), "Response body timeout");
+    }
+}
@@ -49,7 +49,7 @@
 //! * Streaming and pipelining
 //! * Keep-alive and slow requests handling
 //! * *WebSockets* server/client
-//! * Transparent content compression/decompression (br, gzip, deflate)
+//! * Transparent content compression/decompression (br, gzip, deflate, zstd)
 //! * Configurable request routing
 //! * SSL support with OpenSSL or `rustls`
 //! * Middlewares
@@ -58,21 +58,31 @@
 //! ## Package feature
 //!
 //! * `cookie` - enables http cookie support
+//! * `secure-cookie` - enables signed and private cookies, see `web::cookie`
+//! * `session` - enables session support, see `web::session`
+//! * `openapi` - enables OpenAPI document generation, see `web::openapi`
 //! * `compress` - enables content encoding compression support
 //! * `openssl` - enables ssl support via `openssl` crate
 //! * `rustls` - enables ssl support via `rustls` crate
 
 mod app;
 mod app_service;
+mod blocking;
 mod config;
+#[cfg(feature = "secure-cookie")]
+pub mod cookie;
 pub mod error;
 mod error_default;
 mod extract;
+pub mod files;
 pub mod guard;
 mod handler;
 mod httprequest;
 mod info;
 pub mod middleware;
+mod module;
+#[cfg(feature = "openapi")]
+pub mod openapi;
 mod request;
 mod resource;
 mod responder;
@@ -82,6 +92,9 @@ mod route;
 mod scope;
 mod server;
 mod service;
+#[cfg(feature = "session")]
+pub mod session;
+pub mod sse;
 pub mod test;
 pub mod types;
 mod util;
@@ -102,6 +115,7 @@ pub use crate::http::Response as HttpResponse;
 pub use crate::http::ResponseBuilder as HttpResponseBuilder;
 
 pub use self::app::App;
+pub use self::blocking::block;
 pub use self::config::ServiceConfig;
 pub use self::error::{
     DefaultError, Error, ErrorContainer, ErrorRenderer, WebResponseError,
@@ -109,6 +123,7 @@ pub use self::error::{
 pub use self::extract::FromRequest;
 pub use self::handler::Handler;
 pub use self::httprequest::HttpRequest;
+pub use self::module::WebModule;
 pub use self::request::WebRequest;
 pub use self::resource::Resource;
 pub use self::responder::Responder;
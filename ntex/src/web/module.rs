@@ -0,0 +1,156 @@
+use crate::router::IntoPattern;
+
+use super::error::ErrorRenderer;
+use super::scope::Scope;
+use super::service::{WebServiceConfig, WebServiceFactory};
+
+/// Reusable application module.
+///
+/// Module packages routes, middlewares and data into a single unit, that
+/// could be mounted to an application with `App::mount()` method. Module
+/// receives scope for mount path, configures it and registers it
+/// with provided service config. Data registered on module's scope shadows
+/// application data of the same type.
+///
+/// ```rust
+/// use ntex::web::{self, dev, middleware, App, HttpResponse, Scope};
+/// use ntex::web::{WebModule, WebServiceFactory};
+///
+/// struct Users {
+///     limit: usize,
+/// }
+///
+/// impl<Err: web::ErrorRenderer> WebModule<Err> for Users {
+///     fn configure(self, scope: Scope<Err>, config: &mut dev::WebServiceConfig<Err>) {
+///         scope
+///             .data(self.limit)
+///             .wrap(middleware::DefaultHeaders::new().header("x-module", "users"))
+///             .route("/list", web::get().to(|| async { HttpResponse::Ok() }))
+///             .register(config)
+///     }
+/// }
+///
+/// fn main() {
+///     let app = App::new().mount("/users", Users { limit: 10 });
+/// }
+/// ```
+pub trait WebModule<Err: ErrorRenderer> {
+    /// Configure module scope and register it.
+    fn configure(self, scope: Scope<Err>, config: &mut WebServiceConfig<Err>);
+}
+
+impl<F, Err> WebModule<Err> for F
+where
+    F: FnOnce(Scope<Err>, &mut WebServiceConfig<Err>),
+    Err: ErrorRenderer,
+{
+    fn configure(self, scope: Scope<Err>, config: &mut WebServiceConfig<Err>) {
+        (self)(scope, config)
+    }
+}
+
+/// Module mounted to a path
+pub(super) struct Mount<M> {
+    path: Vec<String>,
+    module: M,
+}
+
+impl<M> Mount<M> {
+    pub(super) fn new<T: IntoPattern>(path: T, module: M) -> Self {
+        Mount {
+            module,
+            path: path.patterns(),
+        }
+    }
+}
+
+impl<M, Err> WebServiceFactory<Err> for Mount<M>
+where
+    M: WebModule<Err>,
+    Err: ErrorRenderer,
+{
+    fn register(self, config: &mut WebServiceConfig<Err>) {
+        self.module.configure(Scope::new(self.path), config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::web::middleware::DefaultHeaders;
+    use crate::web::test::{call_service, init_service, read_body, TestRequest};
+    use crate::web::{self, types::Data, App, DefaultError, HttpResponse};
+
+    struct Module {
+        name: &'static str,
+    }
+
+    impl WebModule<DefaultError> for Module {
+        fn configure(
+            self,
+            scope: Scope<DefaultError>,
+            config: &mut WebServiceConfig<DefaultError>,
+        ) {
+            scope
+                .data(self.name)
+                .wrap(DefaultHeaders::new().header("x-module", self.name))
+                .route(
+                    "/name",
+                    web::get().to(|name: Data<&'static str>, limit: Data<usize>| {
+                        let body = format!("{}:{}", **name, **limit);
+                        async move { HttpResponse::Ok().body(body) }
+                    }),
+                )
+                .register(config)
+        }
+    }
+
+    #[crate::rt_test]
+    async fn test_mount() {
+        let srv = init_service(
+            App::new()
+                .data(10usize)
+                .data("app")
+                .mount("/m1", Module { name: "m1" })
+                .mount("/m2", Module { name: "m2" })
+                .mount(
+                    "/m3",
+                    |scope: Scope<DefaultError>,
+                     config: &mut WebServiceConfig<DefaultError>| {
+                        scope
+                            .route("/t", web::get().to(|| async { HttpResponse::Ok() }))
+                            .register(config)
+                    },
+                )
+                .route(
+                    "/name",
+                    web::get().to(|name: Data<&'static str>| {
+                        let body = name.to_string();
+                        async move { HttpResponse::Ok().body(body) }
+                    }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/m1/name").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers().get("x-module").unwrap(), "m1");
+        assert_eq!(read_body(resp).await, "m1:10");
+
+        let req = TestRequest::with_uri("/m2/name").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.headers().get("x-module").unwrap(), "m2");
+        assert_eq!(read_body(resp).await, "m2:10");
+
+        let req = TestRequest::with_uri("/m3/t").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = TestRequest::with_uri("/name").to_request();
+        let resp = call_service(&srv, req).await;
+        assert!(!resp.headers().contains_key("x-module"));
+        assert_eq!(read_body(resp).await, "app");
+    }
+}
@@ -0,0 +1,228 @@
+use serde_json::json;
+
+use crate::http::{Response, ResponseBuilder, StatusCode};
+use crate::util::{Bytes, BytesMut, Either};
+use crate::web::middleware::RequestId;
+use crate::web::responder::CustomResponder;
+use crate::web::sse::{LastEventId, SseResponder};
+use crate::web::types::{self, Data, Form, Formats, Json, Multipart, Negotiate};
+use crate::web::{HttpRequest, Responder};
+
+use super::operation::{ApiExtractor, ApiResponder, Operation};
+use super::schema::ApiSchema;
+
+macro_rules! extractor_noop {
+    ($($tp:ty),+) => {
+        $(impl ApiExtractor for $tp {
+            fn describe_request(op: Operation) -> Operation {
+                op
+            }
+        })+
+    };
+}
+
+extractor_noop!((), HttpRequest, types::Payload, RequestId);
+
+impl<T> ApiExtractor for Data<T> {
+    fn describe_request(op: Operation) -> Operation {
+        op
+    }
+}
+
+impl<T: ApiExtractor> ApiExtractor for Option<T> {
+    fn describe_request(op: Operation) -> Operation {
+        T::describe_request(op).request_body_required(false)
+    }
+}
+
+impl<T: ApiExtractor, E> ApiExtractor for Result<T, E> {
+    fn describe_request(op: Operation) -> Operation {
+        T::describe_request(op)
+    }
+}
+
+impl<T: ApiSchema> ApiExtractor for types::Path<T> {
+    fn describe_request(op: Operation) -> Operation {
+        op.path::<T>()
+    }
+}
+
+impl<T: ApiSchema> ApiExtractor for types::Query<T> {
+    fn describe_request(op: Operation) -> Operation {
+        op.query::<T>()
+    }
+}
+
+impl<T: ApiSchema> ApiExtractor for Json<T> {
+    fn describe_request(op: Operation) -> Operation {
+        op.request_body("application/json", T::schema())
+    }
+}
+
+impl<T: ApiSchema> ApiExtractor for Form<T> {
+    fn describe_request(op: Operation) -> Operation {
+        op.request_body("application/x-www-form-urlencoded", T::schema())
+    }
+}
+
+impl<T: ApiSchema, F: Formats> ApiExtractor for Negotiate<T, F> {
+    fn describe_request(op: Operation) -> Operation {
+        F::media_types()
+            .into_iter()
+            .fold(op, |op, ctype| op.request_body(ctype, T::schema()))
+    }
+}
+
+impl ApiExtractor for Multipart {
+    fn describe_request(op: Operation) -> Operation {
+        op.request_body("multipart/form-data", json!({"type": "object"}))
+    }
+}
+
+impl ApiExtractor for String {
+    fn describe_request(op: Operation) -> Operation {
+        op.request_body("text/plain", String::schema())
+    }
+}
+
+impl ApiExtractor for Bytes {
+    fn describe_request(op: Operation) -> Operation {
+        op.request_body(
+            "application/octet-stream",
+            json!({"type": "string", "format": "binary"}),
+        )
+    }
+}
+
+impl ApiExtractor for LastEventId {
+    fn describe_request(op: Operation) -> Operation {
+        op.parameter("last-event-id", "header", false, String::schema())
+    }
+}
+
+macro_rules! extractor_tuple ({ $($T:ident),+ } => {
+    impl<$($T: ApiExtractor),+> ApiExtractor for ($($T,)+) {
+        fn describe_request(op: Operation) -> Operation {
+            $(let op = $T::describe_request(op);)+
+            op
+        }
+    }
+});
+
+#[rustfmt::skip]
+mod m {
+    use super::*;
+
+    extractor_tuple!(A);
+    extractor_tuple!(A, B);
+    extractor_tuple!(A, B, C);
+    extractor_tuple!(A, B, C, D);
+    extractor_tuple!(A, B, C, D, E);
+    extractor_tuple!(A, B, C, D, E, F);
+    extractor_tuple!(A, B, C, D, E, F, G);
+    extractor_tuple!(A, B, C, D, E, F, G, H);
+    extractor_tuple!(A, B, C, D, E, F, G, H, I);
+    extractor_tuple!(A, B, C, D, E, F, G, H, I, J);
+}
+
+impl ApiResponder for Response {
+    fn describe_response(op: Operation) -> Operation {
+        op.response("default", "Response")
+    }
+}
+
+impl ApiResponder for ResponseBuilder {
+    fn describe_response(op: Operation) -> Operation {
+        op.response("default", "Response")
+    }
+}
+
+macro_rules! responder_text {
+    ($($tp:ty),+) => {
+        $(impl ApiResponder for $tp {
+            fn describe_response(op: Operation) -> Operation {
+                op.response_content("200", "text/plain", String::schema())
+            }
+        })+
+    };
+}
+
+responder_text!(String, &'static str, &'_ String);
+
+macro_rules! responder_binary {
+    ($($tp:ty),+) => {
+        $(impl ApiResponder for $tp {
+            fn describe_response(op: Operation) -> Operation {
+                op.response_content(
+                    "200",
+                    "application/octet-stream",
+                    json!({"type": "string", "format": "binary"}),
+                )
+            }
+        })+
+    };
+}
+
+responder_binary!(Bytes, BytesMut, &'static [u8]);
+
+impl<T: ApiSchema> ApiResponder for Json<T> {
+    fn describe_response(op: Operation) -> Operation {
+        op.response_content("200", "application/json", T::schema())
+    }
+}
+
+impl<T: ApiSchema> ApiResponder for Form<T> {
+    fn describe_response(op: Operation) -> Operation {
+        op.response_content("200", "application/x-www-form-urlencoded", T::schema())
+    }
+}
+
+impl<T: ApiSchema, F: Formats> ApiResponder for Negotiate<T, F> {
+    fn describe_response(op: Operation) -> Operation {
+        F::media_types()
+            .into_iter()
+            .fold(op, |op, ctype| {
+                op.response_content("200", ctype, T::schema())
+            })
+            .response("406", "Not Acceptable")
+    }
+}
+
+impl<S> ApiResponder for SseResponder<S> {
+    fn describe_response(op: Operation) -> Operation {
+        op.response_content("200", "text/event-stream", String::schema())
+    }
+}
+
+impl<T: ApiResponder> ApiResponder for Option<T> {
+    fn describe_response(op: Operation) -> Operation {
+        T::describe_response(op).response("404", "Not Found")
+    }
+}
+
+impl<T: ApiResponder, E> ApiResponder for Result<T, E> {
+    fn describe_response(op: Operation) -> Operation {
+        T::describe_response(op)
+    }
+}
+
+impl<T: ApiResponder> ApiResponder for (T, StatusCode) {
+    fn describe_response(op: Operation) -> Operation {
+        T::describe_response(op)
+    }
+}
+
+impl<A: ApiResponder, B: ApiResponder> ApiResponder for Either<A, B> {
+    fn describe_response(op: Operation) -> Operation {
+        B::describe_response(A::describe_response(op))
+    }
+}
+
+impl<T, Err> ApiResponder for CustomResponder<T, Err>
+where
+    T: ApiResponder + Responder<Err>,
+{
+    fn describe_response(op: Operation) -> Operation {
+        T::describe_response(op)
+    }
+}
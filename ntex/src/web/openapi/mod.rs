@@ -0,0 +1,389 @@
+//! OpenAPI document generation
+//!
+//! Routes registered with `Route::to_api()` are described by handler's
+//! extractors and responder. Extractors implement `ApiExtractor` trait,
+//! responders implement `ApiResponder` trait, and user types implement
+//! `ApiSchema` trait. Generated OpenAPI 3 document is served by application
+//! if `App::openapi()` is used.
+//!
+//! ```rust
+//! use ntex::web::{self, openapi::{ApiSchema, OpenApi}, types::Json, App};
+//! use serde_json::{json, Value};
+//!
+//! #[derive(serde::Serialize, serde::Deserialize)]
+//! struct User {
+//!     name: String,
+//! }
+//!
+//! impl ApiSchema for User {
+//!     fn schema() -> Value {
+//!         json!({
+//!             "type": "object",
+//!             "properties": {"name": String::schema()},
+//!             "required": ["name"]
+//!         })
+//!     }
+//! }
+//!
+//! async fn user(id: web::types::Path<u32>) -> Json<User> {
+//!     Json(User { name: format!("user-{}", id) })
+//! }
+//!
+//! fn main() {
+//!     let app = App::new()
+//!         .openapi("/openapi.json", OpenApi::new("Users", "1.0.0"))
+//!         .route(
+//!             "/users/{id}",
+//!             web::get()
+//!                 .operation(|op| op.summary("Get user").tag("users"))
+//!                 .to_api(user),
+//!         );
+//! }
+//! ```
+use std::{cell::RefCell, rc::Rc};
+
+use serde_json::{json, Map, Value};
+
+use crate::http::Method;
+
+use super::error::ErrorRenderer;
+use super::resource::Resource;
+use super::route::Route;
+use super::service::{WebServiceConfig, WebServiceFactory};
+use super::HttpResponse;
+
+mod impls;
+mod operation;
+mod schema;
+
+pub use self::operation::{ApiExtractor, ApiResponder, Operation};
+pub use self::schema::ApiSchema;
+
+/// Registered api operations, path pattern, method and operation
+pub(super) type Operations = Vec<(String, Method, Operation)>;
+
+/// OpenAPI document description.
+///
+/// Document is generated during application initialization, generated
+/// document is available via `OpenApi::document()` method.
+#[derive(Clone)]
+pub struct OpenApi {
+    inner: Rc<Inner>,
+    document: Rc<RefCell<Option<Value>>>,
+}
+
+struct Inner {
+    title: String,
+    version: String,
+    description: Option<String>,
+    servers: Vec<String>,
+}
+
+impl OpenApi {
+    /// Create new document description with api title and version.
+    pub fn new(title: &str, version: &str) -> Self {
+        OpenApi {
+            inner: Rc::new(Inner {
+                title: title.to_string(),
+                version: version.to_string(),
+                description: None,
+                servers: Vec::new(),
+            }),
+            document: Rc::new(RefCell::new(None)),
+        }
+    }
+
+    /// Set api description
+    pub fn description(mut self, description: &str) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .description = Some(description.to_string());
+        self
+    }
+
+    /// Add server url
+    pub fn server(mut self, url: &str) -> Self {
+        Rc::get_mut(&mut self.inner)
+            .expect("Multiple copies exist")
+            .servers
+            .push(url.to_string());
+        self
+    }
+
+    /// Generated document.
+    ///
+    /// Returns `None` if application is not initialized yet.
+    pub fn document(&self) -> Option<Value> {
+        self.document.borrow().clone()
+    }
+
+    /// Generate document for registered operations
+    pub(super) fn generate(&self, operations: &[(String, Method, Operation)]) {
+        let mut info = Map::new();
+        info.insert("title".to_string(), json!(self.inner.title));
+        info.insert("version".to_string(), json!(self.inner.version));
+        if let Some(ref description) = self.inner.description {
+            info.insert("description".to_string(), json!(description));
+        }
+
+        let mut paths = Map::new();
+        for (pattern, method, op) in operations {
+            let (path, params) = path_template(pattern);
+            let item = paths
+                .entry(path)
+                .or_insert_with(|| Value::Object(Map::new()));
+            if let Value::Object(ref mut item) = item {
+                item.insert(method.as_str().to_lowercase(), op.to_json(&params));
+            }
+        }
+
+        let mut doc = Map::new();
+        doc.insert("openapi".to_string(), json!("3.0.3"));
+        doc.insert("info".to_string(), Value::Object(info));
+        if !self.inner.servers.is_empty() {
+            let servers: Vec<_> = self
+                .inner
+                .servers
+                .iter()
+                .map(|url| json!({ "url": url }))
+                .collect();
+            doc.insert("servers".to_string(), Value::Array(servers));
+        }
+        doc.insert("paths".to_string(), Value::Object(paths));
+
+        *self.document.borrow_mut() = Some(Value::Object(doc));
+    }
+}
+
+/// Convert resource pattern to OpenAPI path template.
+///
+/// Regex and tail parameters are converted to simple parameters,
+/// i.e. `/{id:\d+}/{tail}*` becomes `/{id}/{tail}`.
+fn path_template(pattern: &str) -> (String, Vec<String>) {
+    let mut path = String::with_capacity(pattern.len());
+    let mut params = Vec::new();
+    let mut chars = pattern.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        if ch != '{' {
+            path.push(ch);
+            continue;
+        }
+
+        let mut name = String::new();
+        let mut depth = 1;
+        let mut in_name = true;
+        for ch in &mut chars {
+            match ch {
+                '{' => depth += 1,
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                ':' if depth == 1 => in_name = false,
+                _ => (),
+            }
+            if in_name {
+                name.push(ch);
+            }
+        }
+        if chars.peek() == Some(&'*') {
+            chars.next();
+        }
+        path.push('{');
+        path.push_str(&name);
+        path.push('}');
+        params.push(name);
+    }
+    if path.is_empty() {
+        path.push('/');
+    }
+    (path, params)
+}
+
+/// Service serves generated document
+pub(super) struct OpenApiService {
+    path: String,
+    api: OpenApi,
+}
+
+impl OpenApiService {
+    pub(super) fn new(path: &str, api: OpenApi) -> Self {
+        OpenApiService {
+            path: path.to_string(),
+            api,
+        }
+    }
+}
+
+impl<Err: ErrorRenderer> WebServiceFactory<Err> for OpenApiService {
+    fn register(self, config: &mut WebServiceConfig<Err>) {
+        let api = self.api.clone();
+        config.add_openapi(self.api);
+
+        Resource::new(self.path)
+            .route(Route::new().method(Method::GET).to(move || {
+                let doc = api.document().unwrap_or(Value::Null);
+                async move { HttpResponse::Ok().json(&doc) }
+            }))
+            .register(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::web::test::{call_service, init_service, read_body, TestRequest};
+    use crate::web::types::{Json, Path, Query};
+    use crate::web::{self, App};
+
+    #[derive(serde::Serialize, serde::Deserialize)]
+    struct User {
+        name: String,
+    }
+
+    impl ApiSchema for User {
+        fn schema() -> Value {
+            json!({
+                "type": "object",
+                "properties": {"name": String::schema()},
+                "required": ["name"]
+            })
+        }
+    }
+
+    #[derive(serde::Deserialize)]
+    struct Page {
+        #[allow(dead_code)]
+        offset: Option<usize>,
+    }
+
+    impl ApiSchema for Page {
+        fn schema() -> Value {
+            json!({
+                "type": "object",
+                "properties": {"offset": usize::schema()}
+            })
+        }
+    }
+
+    async fn get_user(path: Path<(String, u32)>) -> Option<Json<User>> {
+        Some(Json(User {
+            name: format!("{}-{}", path.0, path.1),
+        }))
+    }
+
+    async fn list_users(_: Query<Page>) -> Json<Vec<User>> {
+        Json(Vec::new())
+    }
+
+    async fn create_user(user: Json<User>) -> Json<User> {
+        user
+    }
+
+    #[test]
+    fn test_path_template() {
+        assert_eq!(path_template(""), ("/".to_string(), vec![]));
+        assert_eq!(
+            path_template("/users/{id}"),
+            ("/users/{id}".to_string(), vec!["id".to_string()])
+        );
+        assert_eq!(
+            path_template("/{id:\\d{2,3}}/{tail}*"),
+            (
+                "/{id}/{tail}".to_string(),
+                vec!["id".to_string(), "tail".to_string()]
+            )
+        );
+    }
+
+    #[crate::rt_test]
+    async fn test_document() {
+        let api = OpenApi::new("Test", "1.0")
+            .description("Test api")
+            .server("http://localhost");
+
+        let srv = init_service(
+            App::new()
+                .openapi("/openapi.json", api.clone())
+                .service(
+                    web::scope("/users")
+                        .route("", web::get().to_api(list_users))
+                        .route(
+                            "",
+                            web::post()
+                                .operation(|op| op.summary("Create user"))
+                                .to_api(create_user),
+                        )
+                        .route("/{org}/{id:\\d+}", web::get().to_api(get_user)),
+                )
+                .route(
+                    "/undocumented",
+                    web::get().to(|| async { HttpResponse::Ok() }),
+                )
+                .route(
+                    "/health",
+                    web::get()
+                        .operation(|op| op.tag("system").deprecated())
+                        .to(|| async { HttpResponse::Ok() }),
+                ),
+        )
+        .await;
+
+        let doc = api.document().unwrap();
+        assert_eq!(doc["openapi"], "3.0.3");
+        assert_eq!(doc["info"]["title"], "Test");
+        assert_eq!(doc["info"]["version"], "1.0");
+        assert_eq!(doc["info"]["description"], "Test api");
+        assert_eq!(doc["servers"][0]["url"], "http://localhost");
+
+        let paths = doc["paths"].as_object().unwrap();
+        assert_eq!(paths.len(), 3);
+        assert!(!paths.contains_key("/undocumented"));
+        assert!(!paths.contains_key("/openapi.json"));
+
+        let op = &paths["/users"]["get"];
+        assert_eq!(op["parameters"][0]["name"], "offset");
+        assert_eq!(op["parameters"][0]["in"], "query");
+        assert_eq!(op["parameters"][0]["required"], false);
+        assert_eq!(
+            op["responses"]["200"]["content"]["application/json"]["schema"]["items"],
+            User::schema()
+        );
+
+        let op = &paths["/users"]["post"];
+        assert_eq!(op["summary"], "Create user");
+        assert_eq!(op["requestBody"]["required"], true);
+        assert_eq!(
+            op["requestBody"]["content"]["application/json"]["schema"],
+            User::schema()
+        );
+
+        let op = &paths["/users/{org}/{id}"]["get"];
+        assert_eq!(op["parameters"][0]["name"], "org");
+        assert_eq!(op["parameters"][0]["schema"], String::schema());
+        assert_eq!(op["parameters"][1]["name"], "id");
+        assert_eq!(op["parameters"][1]["in"], "path");
+        assert_eq!(op["parameters"][1]["schema"], u32::schema());
+        assert_eq!(op["responses"]["404"]["description"], "Not Found");
+        assert_eq!(op["responses"]["200"]["description"], "OK");
+
+        let op = &paths["/health"]["get"];
+        assert_eq!(op["tags"][0], "system");
+        assert_eq!(op["deprecated"], true);
+        assert_eq!(op["responses"]["default"]["description"], "Response");
+
+        let req = TestRequest::with_uri("/openapi.json").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: Value = serde_json::from_slice(&read_body(resp).await).unwrap();
+        assert_eq!(body, doc);
+
+        let req = TestRequest::with_uri("/users/org/10").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(read_body(resp).await, "{\"name\":\"org-10\"}");
+    }
+}
@@ -0,0 +1,271 @@
+use serde_json::{json, Map, Value};
+
+use crate::http::StatusCode;
+
+use super::schema::ApiSchema;
+
+/// Description of an api operation.
+///
+/// Operation is generated from handler's extractors and responder,
+/// and could be extended with `Route::operation()` method.
+#[derive(Clone, Debug, Default)]
+pub struct Operation {
+    summary: Option<String>,
+    description: Option<String>,
+    operation_id: Option<String>,
+    tags: Vec<String>,
+    deprecated: bool,
+    path: Option<(Value, Vec<Value>)>,
+    parameters: Vec<Value>,
+    body: Vec<(String, Value)>,
+    body_required: bool,
+    responses: Vec<(String, String, Vec<(String, Value)>)>,
+}
+
+impl Operation {
+    /// Create new operation description
+    pub fn new() -> Self {
+        Operation::default()
+    }
+
+    /// Set short summary of the operation
+    pub fn summary(mut self, summary: &str) -> Self {
+        self.summary = Some(summary.to_string());
+        self
+    }
+
+    /// Set description of the operation
+    pub fn description(mut self, description: &str) -> Self {
+        self.description = Some(description.to_string());
+        self
+    }
+
+    /// Set unique operation id
+    pub fn operation_id(mut self, id: &str) -> Self {
+        self.operation_id = Some(id.to_string());
+        self
+    }
+
+    /// Add tag to the operation
+    pub fn tag(mut self, tag: &str) -> Self {
+        self.tags.push(tag.to_string());
+        self
+    }
+
+    /// Mark operation as deprecated
+    pub fn deprecated(mut self) -> Self {
+        self.deprecated = true;
+        self
+    }
+
+    /// Describe path parameters.
+    ///
+    /// Object properties are matched with path parameters by name,
+    /// other types are matched by position.
+    pub fn path<T: ApiSchema>(mut self) -> Self {
+        self.path = Some((T::schema(), T::elements()));
+        self
+    }
+
+    /// Describe query parameters.
+    ///
+    /// Each property of object schema becomes query parameter.
+    pub fn query<T: ApiSchema>(mut self) -> Self {
+        let schema = T::schema();
+        let required = required(&schema);
+        if let Some(props) = schema.get("properties").and_then(|p| p.as_object()) {
+            for (name, schema) in props {
+                self = self.parameter(
+                    name,
+                    "query",
+                    required.contains(&name.as_str()),
+                    schema.clone(),
+                );
+            }
+        }
+        self
+    }
+
+    /// Add parameter, `location` is one of `query`, `header` or `cookie`
+    pub fn parameter(
+        mut self,
+        name: &str,
+        location: &str,
+        required: bool,
+        schema: Value,
+    ) -> Self {
+        self.parameters.push(json!({
+            "name": name,
+            "in": location,
+            "required": required,
+            "schema": schema,
+        }));
+        self
+    }
+
+    /// Add request body content
+    pub fn request_body(mut self, content_type: &str, schema: Value) -> Self {
+        if self.body.is_empty() {
+            self.body_required = true;
+        }
+        self.body.push((content_type.to_string(), schema));
+        self
+    }
+
+    /// Set request body requirement. By default body is required.
+    pub fn request_body_required(mut self, required: bool) -> Self {
+        self.body_required = required;
+        self
+    }
+
+    /// Add response description
+    pub fn response(mut self, status: &str, description: &str) -> Self {
+        let idx = self.response_idx(status);
+        self.responses[idx].1 = description.to_string();
+        self
+    }
+
+    /// Add response content
+    pub fn response_content(
+        mut self,
+        status: &str,
+        content_type: &str,
+        schema: Value,
+    ) -> Self {
+        let idx = self.response_idx(status);
+        self.responses[idx]
+            .2
+            .push((content_type.to_string(), schema));
+        self
+    }
+
+    fn response_idx(&mut self, status: &str) -> usize {
+        if let Some(idx) = self.responses.iter().position(|r| r.0 == status) {
+            idx
+        } else {
+            let description = status
+                .parse::<u16>()
+                .ok()
+                .and_then(|s| StatusCode::from_u16(s).ok())
+                .and_then(|s| s.canonical_reason())
+                .unwrap_or("Response");
+            self.responses.push((
+                status.to_string(),
+                description.to_string(),
+                Vec::new(),
+            ));
+            self.responses.len() - 1
+        }
+    }
+
+    /// Schema of path parameter, match by name first then by position
+    fn path_param(&self, idx: usize, name: &str, total: usize) -> Value {
+        if let Some((ref schema, ref elements)) = self.path {
+            if let Some(schema) = schema.get("properties").and_then(|p| p.get(name)) {
+                return schema.clone();
+            }
+            if elements.len() == total {
+                return elements[idx].clone();
+            }
+        }
+        json!({"type": "string"})
+    }
+
+    /// Generate operation object for path with specified path parameters
+    pub(super) fn to_json(&self, params: &[String]) -> Value {
+        let mut op = Map::new();
+        if let Some(ref summary) = self.summary {
+            op.insert("summary".to_string(), json!(summary));
+        }
+        if let Some(ref description) = self.description {
+            op.insert("description".to_string(), json!(description));
+        }
+        if let Some(ref id) = self.operation_id {
+            op.insert("operationId".to_string(), json!(id));
+        }
+        if !self.tags.is_empty() {
+            op.insert("tags".to_string(), json!(self.tags));
+        }
+        if self.deprecated {
+            op.insert("deprecated".to_string(), json!(true));
+        }
+
+        // path parameters are always required
+        let mut parameters: Vec<_> = params
+            .iter()
+            .enumerate()
+            .map(|(idx, name)| {
+                let schema = self.path_param(idx, name, params.len());
+                json!({"name": name, "in": "path", "required": true, "schema": schema})
+            })
+            .collect();
+        parameters.extend(self.parameters.iter().cloned());
+        if !parameters.is_empty() {
+            op.insert("parameters".to_string(), Value::Array(parameters));
+        }
+
+        if !self.body.is_empty() {
+            op.insert(
+                "requestBody".to_string(),
+                json!({"required": self.body_required, "content": content(&self.body)}),
+            );
+        }
+
+        let mut responses = Map::new();
+        for (status, description, items) in &self.responses {
+            let mut res = Map::new();
+            res.insert("description".to_string(), json!(description));
+            if !items.is_empty() {
+                res.insert("content".to_string(), content(items));
+            }
+            responses.insert(status.clone(), Value::Object(res));
+        }
+        if responses.is_empty() {
+            responses.insert("default".to_string(), json!({"description": "Response"}));
+        }
+        op.insert("responses".to_string(), Value::Object(responses));
+
+        Value::Object(op)
+    }
+}
+
+fn required(schema: &Value) -> Vec<&str> {
+    schema
+        .get("required")
+        .and_then(|r| r.as_array())
+        .map(|r| r.iter().filter_map(|v| v.as_str()).collect())
+        .unwrap_or_default()
+}
+
+fn content(items: &[(String, Value)]) -> Value {
+    let mut content = Map::new();
+    for (ctype, schema) in items {
+        content.insert(ctype.clone(), json!({ "schema": schema }));
+    }
+    Value::Object(content)
+}
+
+/// Extractor that could be described in api operation
+///
+/// ```rust
+/// use ntex::web::openapi::{ApiExtractor, ApiSchema, Operation};
+///
+/// /// Api key extractor
+/// struct ApiKey(String);
+///
+/// impl ApiExtractor for ApiKey {
+///     fn describe_request(op: Operation) -> Operation {
+///         op.parameter("x-api-key", "header", true, String::schema())
+///     }
+/// }
+/// ```
+pub trait ApiExtractor {
+    /// Add extractor's parameters and request body to the operation
+    fn describe_request(op: Operation) -> Operation;
+}
+
+/// Responder that could be described in api operation
+pub trait ApiResponder {
+    /// Add responder's responses to the operation
+    fn describe_response(op: Operation) -> Operation;
+}
@@ -0,0 +1,172 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::{rc::Rc, sync::Arc};
+
+use serde_json::{json, Value};
+
+/// Type that could be described with JSON schema.
+///
+/// Schema is used for request parameters, request bodies and response bodies.
+///
+/// ```rust
+/// use ntex::web::openapi::ApiSchema;
+/// use serde_json::{json, Value};
+///
+/// #[derive(serde::Deserialize)]
+/// struct User {
+///     name: String,
+///     age: Option<u32>,
+/// }
+///
+/// impl ApiSchema for User {
+///     fn schema() -> Value {
+///         json!({
+///             "type": "object",
+///             "properties": {
+///                 "name": String::schema(),
+///                 "age": Option::<u32>::schema(),
+///             },
+///             "required": ["name"]
+///         })
+///     }
+/// }
+/// ```
+pub trait ApiSchema {
+    /// JSON schema of the type
+    fn schema() -> Value;
+
+    /// Schemas of tuple elements.
+    ///
+    /// Used for positional path parameters, i.e. `Path<(u32, String)>`.
+    fn elements() -> Vec<Value> {
+        vec![Self::schema()]
+    }
+}
+
+macro_rules! schema_impl {
+    ($tp:ty, $schema:tt) => {
+        impl ApiSchema for $tp {
+            fn schema() -> Value {
+                json!($schema)
+            }
+        }
+    };
+}
+
+schema_impl!(bool, {"type": "boolean"});
+schema_impl!(i8, {"type": "integer", "format": "int32"});
+schema_impl!(i16, {"type": "integer", "format": "int32"});
+schema_impl!(i32, {"type": "integer", "format": "int32"});
+schema_impl!(i64, {"type": "integer", "format": "int64"});
+schema_impl!(isize, {"type": "integer", "format": "int64"});
+schema_impl!(u8, {"type": "integer", "format": "int32", "minimum": 0});
+schema_impl!(u16, {"type": "integer", "format": "int32", "minimum": 0});
+schema_impl!(u32, {"type": "integer", "format": "int64", "minimum": 0});
+schema_impl!(u64, {"type": "integer", "format": "int64", "minimum": 0});
+schema_impl!(usize, {"type": "integer", "format": "int64", "minimum": 0});
+schema_impl!(f32, {"type": "number", "format": "float"});
+schema_impl!(f64, {"type": "number", "format": "double"});
+schema_impl!(char, {"type": "string", "minLength": 1, "maxLength": 1});
+schema_impl!(String, {"type": "string"});
+schema_impl!(str, {"type": "string"});
+schema_impl!(Value, {});
+
+impl<'a, T: ApiSchema + ?Sized> ApiSchema for &'a T {
+    fn schema() -> Value {
+        T::schema()
+    }
+}
+
+impl<T: ApiSchema + ?Sized> ApiSchema for Box<T> {
+    fn schema() -> Value {
+        T::schema()
+    }
+}
+
+impl<T: ApiSchema + ?Sized> ApiSchema for Rc<T> {
+    fn schema() -> Value {
+        T::schema()
+    }
+}
+
+impl<T: ApiSchema + ?Sized> ApiSchema for Arc<T> {
+    fn schema() -> Value {
+        T::schema()
+    }
+}
+
+impl<T: ApiSchema> ApiSchema for Option<T> {
+    fn schema() -> Value {
+        let mut schema = T::schema();
+        if let Value::Object(ref mut map) = schema {
+            map.insert("nullable".to_string(), Value::Bool(true));
+        }
+        schema
+    }
+}
+
+impl<T: ApiSchema> ApiSchema for [T] {
+    fn schema() -> Value {
+        json!({"type": "array", "items": T::schema()})
+    }
+}
+
+impl<T: ApiSchema> ApiSchema for Vec<T> {
+    fn schema() -> Value {
+        json!({"type": "array", "items": T::schema()})
+    }
+}
+
+impl<T: ApiSchema, S> ApiSchema for HashSet<T, S> {
+    fn schema() -> Value {
+        json!({"type": "array", "items": T::schema(), "uniqueItems": true})
+    }
+}
+
+impl<T: ApiSchema> ApiSchema for BTreeSet<T> {
+    fn schema() -> Value {
+        json!({"type": "array", "items": T::schema(), "uniqueItems": true})
+    }
+}
+
+impl<T: ApiSchema, S> ApiSchema for HashMap<String, T, S> {
+    fn schema() -> Value {
+        json!({"type": "object", "additionalProperties": T::schema()})
+    }
+}
+
+impl<T: ApiSchema> ApiSchema for BTreeMap<String, T> {
+    fn schema() -> Value {
+        json!({"type": "object", "additionalProperties": T::schema()})
+    }
+}
+
+macro_rules! schema_tuple ({ $($T:ident),+ } => {
+    impl<$($T: ApiSchema),+> ApiSchema for ($($T,)+) {
+        fn schema() -> Value {
+            let items = vec![$($T::schema(),)+];
+            let len = items.len();
+            json!({
+                "type": "array",
+                "items": {"oneOf": items},
+                "minItems": len,
+                "maxItems": len
+            })
+        }
+
+        fn elements() -> Vec<Value> {
+            vec![$($T::schema(),)+]
+        }
+    }
+});
+
+#[rustfmt::skip]
+mod m {
+    use super::*;
+
+    schema_tuple!(A);
+    schema_tuple!(A, B);
+    schema_tuple!(A, B, C);
+    schema_tuple!(A, B, C, D);
+    schema_tuple!(A, B, C, D, E);
+    schema_tuple!(A, B, C, D, E, F);
+}
@@ -87,6 +87,11 @@ impl<Err> WebRequest<Err> {
         WebResponse::new(res.into(), self.req)
     }
 
+    #[inline]
+    pub(crate) fn http_request(&self) -> &HttpRequest {
+        &self.req
+    }
+
     /// This method returns reference to the request head
     #[inline]
     pub fn head(&self) -> &RequestHead {
@@ -199,7 +204,7 @@ impl<Err> WebRequest<Err> {
     ///
     /// To get data stored with `App::data()` use `web::types::Data<T>` as type.
     pub fn app_data<T: 'static>(&self) -> Option<&T> {
-        (self.req).0.app_data.get::<T>()
+        self.req.app_data::<T>()
     }
 
     #[inline]
@@ -215,9 +220,12 @@ impl<Err> WebRequest<Err> {
     }
 
     #[doc(hidden)]
-    /// Set new app data container
-    pub fn set_data_container(&mut self, extensions: Rc<Extensions>) {
-        Rc::get_mut(&mut (self.req).0).unwrap().app_data = extensions;
+    /// Add app data container, container shadows previously added containers
+    pub fn add_data_container(&mut self, extensions: Rc<Extensions>) {
+        Rc::get_mut(&mut (self.req).0)
+            .unwrap()
+            .app_data
+            .push(extensions);
     }
 
     /// Request extensions
@@ -2,7 +2,7 @@ use std::{
     cell::RefCell, fmt, future::Future, pin::Pin, rc::Rc, task::Context, task::Poll,
 };
 
-use crate::http::Response;
+use crate::http::{header, Method, Response};
 use crate::router::{IntoPattern, ResourceDef};
 use crate::service::boxed::{self, BoxService, BoxServiceFactory};
 use crate::service::{apply, apply_fn_factory, pipeline_factory};
@@ -45,7 +45,8 @@ type HttpNewService<Err: ErrorRenderer> =
 /// }
 /// ```
 ///
-/// If no matching route could be found, *405* response code get returned.
+/// If no matching route could be found, *405* response code with `Allow` header
+/// get returned.
 /// Default behavior could be overriden with `default_resource()` method.
 pub struct Resource<Err: ErrorRenderer, T = ResourceEndpoint<Err>> {
     endpoint: T,
@@ -435,18 +436,32 @@ where
         } else {
             Some(std::mem::take(&mut self.guards))
         };
-        let mut rdef = if config.is_root() || !self.rdef.is_empty() {
-            ResourceDef::new(insert_slesh(self.rdef.clone()))
+        let patterns = if config.is_root() || !self.rdef.is_empty() {
+            insert_slesh(self.rdef.clone())
         } else {
-            ResourceDef::new(self.rdef.clone())
+            self.rdef.clone()
         };
+
+        // register api operations
+        #[cfg(feature = "openapi")]
+        for route in &mut self.routes {
+            if let Some((methods, op)) = route.take_operation() {
+                for pattern in &patterns {
+                    for method in methods {
+                        config.add_operation(
+                            pattern.clone(),
+                            method.clone(),
+                            op.clone(),
+                        );
+                    }
+                }
+            }
+        }
+
+        let mut rdef = ResourceDef::new(patterns);
         if let Some(ref name) = self.name {
             *rdef.name_mut() = name.clone();
         }
-        // custom app data storage
-        if let Some(ref mut ext) = self.data {
-            config.set_service_data(ext);
-        }
         config.register_service(rdef, guards, self, None)
     }
 }
@@ -490,18 +505,27 @@ impl<Err: ErrorRenderer> ServiceFactory for ResourceFactory<Err> {
 
     fn new_service(&self, _: ()) -> Self::Future {
         let data = self.data.clone();
-        let routes = self.routes.iter().map(|route| route.service()).collect();
+        let routes: Vec<_> = self.routes.iter().map(|route| route.service()).collect();
+        let mut methods = Vec::new();
+        for route in &routes {
+            for m in route.methods() {
+                if !methods.contains(m) {
+                    methods.push(m.clone());
+                }
+            }
+        }
         let default_fut = self.default.borrow().as_ref().map(|f| f.new_service(()));
 
         Box::pin(async move {
             let default = if let Some(fut) = default_fut {
-                Some(fut.await?)
+                Some(Rc::new(fut.await?))
             } else {
                 None
             };
 
             Ok(ResourceService {
-                routes,
+                routes: Rc::new(routes),
+                methods: Rc::new(methods),
                 data,
                 default,
             })
@@ -510,9 +534,58 @@ impl<Err: ErrorRenderer> ServiceFactory for ResourceFactory<Err> {
 }
 
 pub struct ResourceService<Err: ErrorRenderer> {
-    routes: Vec<RouteService<Err>>,
+    routes: Rc<Vec<RouteService<Err>>>,
+    methods: Rc<Vec<Method>>,
     data: Option<Rc<Extensions>>,
-    default: Option<HttpService<Err>>,
+    default: Option<Rc<HttpService<Err>>>,
+}
+
+impl<Err: ErrorRenderer> ResourceService<Err> {
+    fn call_route(
+        &self,
+        start: usize,
+        mut req: WebRequest<Err>,
+    ) -> Either<
+        Ready<WebResponse, Err::Container>,
+        Pin<Box<dyn Future<Output = Result<WebResponse, Err::Container>>>>,
+    > {
+        for (idx, route) in self.routes.iter().enumerate().skip(start) {
+            if route.check(&mut req) {
+                if let Some(ref data) = self.data {
+                    req.add_data_container(data.clone());
+                }
+                if !route.has_async_guards() {
+                    return Either::Right(route.call(req));
+                }
+
+                let srv = self.clone();
+                return Either::Right(Box::pin(async move {
+                    if srv.routes[idx].check_async(&req).await {
+                        srv.routes[idx].call(req).await
+                    } else {
+                        log::trace!("Route {} is rejected by async guard", idx);
+                        srv.call_route(idx + 1, req).await
+                    }
+                }));
+            }
+        }
+        if let Some(ref default) = self.default {
+            Either::Right(default.call(req))
+        } else {
+            Either::Left(Ready::Ok(method_not_allowed(req, &self.methods)))
+        }
+    }
+}
+
+impl<Err: ErrorRenderer> Clone for ResourceService<Err> {
+    fn clone(&self) -> Self {
+        ResourceService {
+            routes: self.routes.clone(),
+            methods: self.methods.clone(),
+            data: self.data.clone(),
+            default: self.default.clone(),
+        }
+    }
 }
 
 impl<Err: ErrorRenderer> Service for ResourceService<Err> {
@@ -529,24 +602,22 @@ impl<Err: ErrorRenderer> Service for ResourceService<Err> {
         Poll::Ready(Ok(()))
     }
 
-    fn call(&self, mut req: WebRequest<Err>) -> Self::Future {
-        for route in self.routes.iter() {
-            if route.check(&mut req) {
-                if let Some(ref data) = self.data {
-                    req.set_data_container(data.clone());
-                }
-                return Either::Right(route.call(req));
-            }
-        }
-        if let Some(ref default) = self.default {
-            Either::Right(default.call(req))
-        } else {
-            Either::Left(Ready::Ok(WebResponse::new(
-                Response::MethodNotAllowed().finish(),
-                req.into_parts().0,
-            )))
-        }
+    fn call(&self, req: WebRequest<Err>) -> Self::Future {
+        self.call_route(0, req)
+    }
+}
+
+/// Create *405 Method Not Allowed* response with `Allow` header
+pub(super) fn method_not_allowed<Err>(
+    req: WebRequest<Err>,
+    methods: &[Method],
+) -> WebResponse {
+    let mut res = Response::MethodNotAllowed();
+    if !methods.is_empty() {
+        let allow: Vec<_> = methods.iter().map(|m| m.as_str()).collect();
+        res.header(header::ALLOW, allow.join(", "));
     }
+    WebResponse::new(res.finish(), req.into_parts().0)
 }
 
 #[doc(hidden)]
@@ -584,7 +655,7 @@ mod tests {
     use crate::web::middleware::DefaultHeaders;
     use crate::web::request::WebRequest;
     use crate::web::test::{call_service, init_service, TestRequest};
-    use crate::web::{self, guard, App, DefaultError, HttpResponse};
+    use crate::web::{self, guard, App, DefaultError, HttpRequest, HttpResponse};
     use crate::{fn_service, util::Either, Service};
 
     #[crate::rt_test]
@@ -707,6 +778,10 @@ mod tests {
             .to_request();
         let resp = call_service(&srv, req).await;
         assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(
+            resp.headers().get(header::ALLOW).unwrap(),
+            HeaderValue::from_static("GET")
+        );
 
         let srv = init_service(
             App::new().service(
@@ -771,6 +846,75 @@ mod tests {
         assert_eq!(resp.status(), StatusCode::NO_CONTENT);
     }
 
+    #[crate::rt_test]
+    async fn test_async_guards() {
+        async fn has_token(req: &HttpRequest) -> bool {
+            sleep(Duration::from_millis(10)).await;
+            let tokens = req.app_data::<web::types::Data<Vec<String>>>().unwrap();
+            req.headers()
+                .get("x-token")
+                .and_then(|v| v.to_str().ok())
+                .map(|v| tokens.iter().any(|t| t == v))
+                .unwrap_or(false)
+        }
+
+        let srv = init_service(App::new().data(vec!["token".to_string()]).service(
+            web::resource("/test").route(vec![
+                    web::get()
+                        .async_guard(has_token)
+                        .to(|| async { HttpResponse::Ok() }),
+                    web::get()
+                        .async_guard(|_: &HttpRequest| async { false })
+                        .to(|| async { HttpResponse::Created() }),
+                    web::get().to(|| async { HttpResponse::Forbidden() }),
+                ]),
+        ))
+        .await;
+
+        let req = TestRequest::with_uri("/test")
+            .header("x-token", "token")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = TestRequest::with_uri("/test")
+            .header("x-token", "unknown")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+        let req = TestRequest::with_uri("/test")
+            .method(Method::POST)
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+    }
+
+    #[crate::rt_test]
+    async fn test_async_guard_payload() {
+        async fn is_text(req: &HttpRequest) -> bool {
+            sleep(Duration::from_millis(10)).await;
+            req.headers().contains_key(header::CONTENT_TYPE)
+        }
+
+        let srv = init_service(App::new().service(
+            web::resource("/test").route(web::post().async_guard(is_text).to(
+                |body: crate::util::Bytes| async move { HttpResponse::Ok().body(body) },
+            )),
+        ))
+        .await;
+
+        let req = TestRequest::with_uri("/test")
+            .method(Method::POST)
+            .header(header::CONTENT_TYPE, "text/plain")
+            .set_payload("payload")
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = crate::web::test::read_body(resp).await;
+        assert_eq!(&body[..], b"payload");
+    }
+
     #[crate::rt_test]
     async fn test_data() {
         let srv = init_service(
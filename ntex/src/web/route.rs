@@ -5,8 +5,10 @@ use crate::{http::Method, util::Ready, Service, ServiceFactory};
 use super::error::ErrorRenderer;
 use super::error_default::DefaultError;
 use super::extract::FromRequest;
-use super::guard::{self, Guard};
+use super::guard::{self, AsyncGuard, Guard};
 use super::handler::{Handler, HandlerFn, HandlerWrapper};
+#[cfg(feature = "openapi")]
+use super::openapi::{ApiExtractor, ApiResponder, Operation};
 use super::request::WebRequest;
 use super::responder::Responder;
 use super::response::WebResponse;
@@ -20,6 +22,9 @@ pub struct Route<Err: ErrorRenderer = DefaultError> {
     handler: Box<dyn HandlerFn<Err>>,
     methods: Vec<Method>,
     guards: Rc<Vec<Box<dyn Guard>>>,
+    async_guards: Rc<Vec<Box<dyn AsyncGuard>>>,
+    #[cfg(feature = "openapi")]
+    operation: Option<Operation>,
 }
 
 impl<Err: ErrorRenderer> Route<Err> {
@@ -31,6 +36,9 @@ impl<Err: ErrorRenderer> Route<Err> {
             })),
             methods: Vec::new(),
             guards: Rc::new(Vec::new()),
+            async_guards: Rc::new(Vec::new()),
+            #[cfg(feature = "openapi")]
+            operation: None,
         }
     }
 
@@ -44,10 +52,17 @@ impl<Err: ErrorRenderer> Route<Err> {
         mem::take(Rc::get_mut(&mut self.guards).unwrap())
     }
 
+    #[cfg(feature = "openapi")]
+    pub(super) fn take_operation(&mut self) -> Option<(&[Method], Operation)> {
+        let op = self.operation.take()?;
+        Some((&self.methods, op))
+    }
+
     pub(super) fn service(&self) -> RouteService<Err> {
         RouteService {
             handler: self.handler.clone_handler(),
             guards: self.guards.clone(),
+            async_guards: self.async_guards.clone(),
             methods: self.methods.clone(),
         }
     }
@@ -71,6 +86,7 @@ pub struct RouteService<Err: ErrorRenderer> {
     handler: Box<dyn HandlerFn<Err>>,
     methods: Vec<Method>,
     guards: Rc<Vec<Box<dyn Guard>>>,
+    async_guards: Rc<Vec<Box<dyn AsyncGuard>>>,
 }
 
 impl<Err: ErrorRenderer> RouteService<Err> {
@@ -86,6 +102,23 @@ impl<Err: ErrorRenderer> RouteService<Err> {
         }
         true
     }
+
+    pub(super) fn methods(&self) -> &[Method] {
+        &self.methods
+    }
+
+    pub(super) fn has_async_guards(&self) -> bool {
+        !self.async_guards.is_empty()
+    }
+
+    pub(super) async fn check_async(&self, req: &WebRequest<Err>) -> bool {
+        for f in self.async_guards.iter() {
+            if !f.check(req.http_request()).await {
+                return false;
+            }
+        }
+        true
+    }
 }
 
 impl<Err: ErrorRenderer> Service for RouteService<Err> {
@@ -142,6 +175,33 @@ impl<Err: ErrorRenderer> Route<Err> {
         self
     }
 
+    /// Add async guard to the route.
+    ///
+    /// Async guards are checked after all other guards of the route matched.
+    /// If async guard rejects request, next route of the same resource is
+    /// checked. Request does not fall through to other resources.
+    ///
+    /// ```rust
+    /// # use ntex::web::{self, *};
+    /// async fn has_token(req: &HttpRequest) -> bool {
+    ///     req.headers().contains_key("x-token")
+    /// }
+    ///
+    /// # fn main() {
+    /// App::new().service(web::resource("/path").route(
+    ///     web::get()
+    ///         .async_guard(has_token)
+    ///         .to(|req: HttpRequest| async { HttpResponse::Ok() }))
+    /// );
+    /// # }
+    /// ```
+    pub fn async_guard<F: AsyncGuard + 'static>(mut self, f: F) -> Self {
+        Rc::get_mut(&mut self.async_guards)
+            .unwrap()
+            .push(Box::new(f));
+        self
+    }
+
     /// Set handler function, use request extractors for parameters.
     ///
     /// ```rust
@@ -198,6 +258,61 @@ impl<Err: ErrorRenderer> Route<Err> {
         self.handler = Box::new(HandlerWrapper::new(handler));
         self
     }
+
+    #[cfg(feature = "openapi")]
+    /// Set handler function and describe route in OpenAPI document.
+    ///
+    /// Operation is described by handler's extractors and responder.
+    /// Only routes with method guards are documented.
+    ///
+    /// ```rust
+    /// use ntex::web::{self, types::Path};
+    ///
+    /// async fn index(id: Path<u32>) -> String {
+    ///     format!("Item {}", id)
+    /// }
+    ///
+    /// fn main() {
+    ///     let app = web::App::new().service(
+    ///         web::resource("/items/{id}").route(web::get().to_api(index))
+    ///     );
+    /// }
+    /// ```
+    pub fn to_api<F, Args>(mut self, handler: F) -> Self
+    where
+        F: Handler<Args, Err>,
+        F::Output: ApiResponder,
+        Args: FromRequest<Err> + ApiExtractor + 'static,
+        Args::Error: Into<Err::Container>,
+        <F::Output as Responder<Err>>::Error: Into<Err::Container>,
+    {
+        let op = self.operation.take().unwrap_or_default();
+        self.operation = Some(F::Output::describe_response(Args::describe_request(op)));
+        self.to(handler)
+    }
+
+    #[cfg(feature = "openapi")]
+    /// Describe route in OpenAPI document.
+    ///
+    /// ```rust
+    /// use ntex::web::{self, HttpResponse};
+    ///
+    /// fn main() {
+    ///     let app = web::App::new().route(
+    ///         "/health",
+    ///         web::get()
+    ///             .operation(|op| op.summary("Health check").tag("system"))
+    ///             .to(|| async { HttpResponse::Ok() }),
+    ///     );
+    /// }
+    /// ```
+    pub fn operation<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(Operation) -> Operation,
+    {
+        self.operation = Some(f(self.operation.take().unwrap_or_default()));
+        self
+    }
 }
 
 /// Convert object to a vec of routes
@@ -9,17 +9,20 @@ use crate::service::{apply, apply_fn_factory, pipeline_factory};
 use crate::service::{IntoServiceFactory, Service, ServiceFactory, Transform};
 use crate::util::{Either, Extensions, Ready};
 
+use super::app_service::allowed_methods;
 use super::config::ServiceConfig;
+#[cfg(feature = "openapi")]
+use super::dev::insert_slesh;
 use super::dev::{WebServiceConfig, WebServiceFactory};
 use super::error::ErrorRenderer;
 use super::guard::Guard;
 use super::request::WebRequest;
-use super::resource::Resource;
+use super::resource::{method_not_allowed, Resource};
 use super::response::WebResponse;
 use super::rmap::ResourceMap;
 use super::route::Route;
 use super::service::{AppServiceFactory, ServiceFactoryWrapper};
-use super::types::Data;
+use super::types::data::{Data, DataInit, FnDataFactory};
 
 type Guards = Vec<Box<dyn Guard>>;
 type HttpService<Err: ErrorRenderer> =
@@ -62,6 +65,7 @@ pub struct Scope<Err: ErrorRenderer, T = ScopeEndpoint<Err>> {
     endpoint: T,
     rdef: Vec<String>,
     data: Option<Extensions>,
+    data_factories: Vec<FnDataFactory>,
     services: Vec<Box<dyn AppServiceFactory<Err>>>,
     guards: Vec<Box<dyn Guard>>,
     default: Rc<RefCell<Option<Rc<HttpNewService<Err>>>>>,
@@ -78,6 +82,7 @@ impl<Err: ErrorRenderer> Scope<Err> {
             endpoint: ScopeEndpoint::new(fref.clone()),
             rdef: path.patterns(),
             data: None,
+            data_factories: Vec::new(),
             guards: Vec::new(),
             services: Vec::new(),
             default: Rc::new(RefCell::new(None)),
@@ -165,6 +170,28 @@ where
         self
     }
 
+    /// Set or override application data factory. This function is
+    /// similar to `.data()` but it accepts data factory. Data object get
+    /// constructed asynchronously during application initialization.
+    pub fn data_factory<F, Out, D, E>(self, data: F) -> Self
+    where
+        F: Fn() -> Out + 'static,
+        Out: Future<Output = Result<D, E>> + 'static,
+        D: 'static,
+        E: fmt::Debug,
+    {
+        self.data_init(Data::from_fn(data))
+    }
+
+    /// Set or override application data initializer.
+    ///
+    /// Initializer is created with `Data::from_fn()` method. Data set with
+    /// `.data()` or `.app_data()` methods takes precedence.
+    pub fn data_init<D: 'static>(mut self, init: DataInit<D>) -> Self {
+        self.data_factories.push(init.into_factory());
+        self
+    }
+
     /// Use ascii case-insensitive routing.
     ///
     /// Only static segments could be case-insensitive.
@@ -289,7 +316,28 @@ where
 
     /// Default service to be used if no matching route could be found.
     ///
-    /// If default resource is not registered, app's default resource is being used.
+    /// If default resource is not registered, parent scope's or app's default
+    /// resource is being used. Nested scopes inherit default service.
+    ///
+    /// ```rust
+    /// use ntex::web::{self, App, HttpResponse};
+    ///
+    /// fn main() {
+    ///     let app = App::new()
+    ///         .service(
+    ///             web::scope("/api")
+    ///                 .service(web::scope("/v1").route("/users", web::get().to(|| async {
+    ///                     HttpResponse::Ok()
+    ///                 })))
+    ///                 .default_service(web::to(|| async {
+    ///                     HttpResponse::NotFound().json(&serde_json::json!({"error": "not found"}))
+    ///                 })),
+    ///         )
+    ///         .default_service(web::to(|| async {
+    ///             HttpResponse::NotFound().content_type("text/html").body("<h1>Not found</h1>")
+    ///         }));
+    /// }
+    /// ```
     pub fn default_service<F, U>(mut self, f: F) -> Self
     where
         F: IntoServiceFactory<U>,
@@ -353,6 +401,7 @@ where
             endpoint,
             rdef: self.rdef,
             data: self.data,
+            data_factories: self.data_factories,
             guards: self.guards,
             services: self.services,
             default: self.default,
@@ -398,6 +447,7 @@ where
             endpoint: apply(mw, self.endpoint),
             rdef: self.rdef,
             data: self.data,
+            data_factories: self.data_factories,
             guards: self.guards,
             services: self.services,
             default: self.default,
@@ -461,6 +511,7 @@ where
             endpoint: apply_fn_factory(self.endpoint, mw),
             rdef: self.rdef,
             data: self.data,
+            data_factories: self.data_factories,
             guards: self.guards,
             services: self.services,
             default: self.default,
@@ -488,12 +539,22 @@ where
             *self.default.borrow_mut() = Some(config.default_service());
         }
 
-        // register nested services
+        // register nested services, nested scopes inherit default service
         let mut cfg = config.clone_config();
+        cfg.set_default_service(self.default.borrow().clone().unwrap());
         self.services
             .into_iter()
             .for_each(|mut srv| srv.register(&mut cfg));
 
+        // register nested api operations
+        #[cfg(feature = "openapi")]
+        for (path, method, op) in cfg.take_operations() {
+            for prefix in &self.rdef {
+                let prefix = insert_slesh(vec![prefix.clone()]).remove(0);
+                config.add_operation(prefix + &path, method.clone(), op.clone());
+            }
+        }
+
         let slesh = self.rdef.iter().any(|s| s.ends_with('/'));
         let mut rmap = ResourceMap::new(ResourceDef::root_prefix(self.rdef.clone()));
 
@@ -502,16 +563,13 @@ where
             rmap.add(&mut rdef, None);
         }
 
-        // custom app data storage
-        if let Some(ref mut ext) = self.data {
-            config.set_service_data(ext);
-        }
-
         // complete scope pipeline creation
         *self.factory_ref.borrow_mut() = Some(ScopeFactory {
             data: self.data.take().map(Rc::new),
+            data_factories: Rc::new(std::mem::take(&mut self.data_factories)),
             default: self.default.clone(),
             case_insensitive: self.case_insensitive,
+            method_not_allowed: config.method_not_allowed(),
             services: Rc::new(
                 cfg.into_services()
                     .1
@@ -550,9 +608,11 @@ where
 
 struct ScopeFactory<Err: ErrorRenderer> {
     data: Option<Rc<Extensions>>,
+    data_factories: Rc<Vec<FnDataFactory>>,
     services: Rc<Vec<(ResourceDef, HttpNewService<Err>, RefCell<Option<Guards>>)>>,
     default: Rc<RefCell<Option<Rc<HttpNewService<Err>>>>>,
     case_insensitive: bool,
+    method_not_allowed: bool,
 }
 
 impl<Err: ErrorRenderer> ServiceFactory for ScopeFactory<Err> {
@@ -567,7 +627,9 @@ impl<Err: ErrorRenderer> ServiceFactory for ScopeFactory<Err> {
     fn new_service(&self, _: ()) -> Self::Future {
         let services = self.services.clone();
         let case_insensitive = self.case_insensitive;
+        let method_not_allowed = self.method_not_allowed;
         let data = self.data.clone();
+        let data_factories = self.data_factories.clone();
         let default_fut = self
             .default
             .borrow()
@@ -591,9 +653,23 @@ impl<Err: ErrorRenderer> ServiceFactory for ScopeFactory<Err> {
                 None
             };
 
+            // async data factories
+            let mut containers = Vec::new();
+            if !data_factories.is_empty() {
+                let mut ext = Extensions::new();
+                for fut in data_factories.iter() {
+                    if let Ok(f) = fut().await {
+                        f.create(&mut ext);
+                    }
+                }
+                containers.push(Rc::new(ext));
+            }
+            containers.extend(data);
+
             Ok(ScopeService {
-                data,
+                data: containers,
                 default,
+                method_not_allowed,
                 router: router.finish(),
                 _ready: None,
             })
@@ -602,9 +678,10 @@ impl<Err: ErrorRenderer> ServiceFactory for ScopeFactory<Err> {
 }
 
 pub struct ScopeService<Err: ErrorRenderer> {
-    data: Option<Rc<Extensions>>,
+    data: Vec<Rc<Extensions>>,
     router: Router<HttpService<Err>, Vec<Box<dyn Guard>>>,
     default: Option<HttpService<Err>>,
+    method_not_allowed: bool,
     _ready: Option<(WebRequest<Err>, ResourceInfo)>,
 }
 
@@ -632,11 +709,20 @@ impl<Err: ErrorRenderer> Service for ScopeService<Err> {
         });
 
         if let Some((srv, _info)) = res {
-            if let Some(ref data) = self.data {
-                req.set_data_container(data.clone());
+            for data in self.data.iter() {
+                req.add_data_container(data.clone());
+            }
+            return Either::Left(srv.call(req));
+        }
+
+        if self.method_not_allowed {
+            let methods = allowed_methods(&self.router, &mut req);
+            if !methods.is_empty() {
+                return Either::Right(Ready::Ok(method_not_allowed(req, &methods)));
             }
-            Either::Left(srv.call(req))
-        } else if let Some(ref default) = self.default {
+        }
+
+        if let Some(ref default) = self.default {
             Either::Left(default.call(req))
         } else {
             let req = req.into_parts().0;
@@ -676,7 +762,7 @@ impl<Err: ErrorRenderer> ServiceFactory for ScopeEndpoint<Err> {
 #[cfg(test)]
 mod tests {
     use crate::http::body::{Body, ResponseBody};
-    use crate::http::header::{HeaderValue, CONTENT_TYPE};
+    use crate::http::header::{HeaderValue, ALLOW, CONTENT_TYPE};
     use crate::http::{Method, StatusCode};
     use crate::service::{fn_service, Service};
     use crate::util::{Bytes, Either};
@@ -828,7 +914,7 @@ mod tests {
         for (m, status) in &[
             (Method::GET, StatusCode::OK),
             (Method::DELETE, StatusCode::OK),
-            (Method::POST, StatusCode::NOT_FOUND),
+            (Method::POST, StatusCode::METHOD_NOT_ALLOWED),
         ] {
             let req = TestRequest::with_uri("/app/path1")
                 .method(m.clone())
@@ -836,6 +922,21 @@ mod tests {
             let resp = srv.call(req).await.unwrap();
             assert_eq!(resp.status(), status.clone());
         }
+
+        let req = TestRequest::with_uri("/app/path1")
+            .method(Method::POST)
+            .to_request();
+        let resp = srv.call(req).await.unwrap();
+        assert_eq!(
+            resp.headers().get(ALLOW).unwrap(),
+            HeaderValue::from_static("GET, DELETE")
+        );
+
+        let req = TestRequest::with_uri("/app/path2")
+            .method(Method::POST)
+            .to_request();
+        let resp = srv.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
     }
 
     #[crate::rt_test]
@@ -852,7 +953,7 @@ mod tests {
         for (m, status) in &[
             (Method::GET, StatusCode::OK),
             (Method::DELETE, StatusCode::OK),
-            (Method::POST, StatusCode::NOT_FOUND),
+            (Method::POST, StatusCode::METHOD_NOT_ALLOWED),
         ] {
             let req = TestRequest::with_uri("/app/path1")
                 .method(m.clone())
@@ -1024,7 +1125,7 @@ mod tests {
             .method(Method::POST)
             .to_request();
         let resp = srv.call(req).await.unwrap();
-        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
 
         let req = TestRequest::with_uri("/app/t1/path1")
             .method(Method::GET)
@@ -1133,6 +1234,53 @@ mod tests {
         assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
     }
 
+    #[crate::rt_test]
+    async fn test_default_resource_nested() {
+        let srv = init_service(
+            App::new()
+                .service(
+                    web::scope("/api")
+                        .service(web::scope("/v1").service(
+                            web::resource("/users").to(|| async { HttpResponse::Ok() }),
+                        ))
+                        .service(
+                            web::scope("/v2").default_service(web::to(|| async {
+                                HttpResponse::Gone()
+                            })),
+                        )
+                        .default_service(web::to(|| async {
+                            HttpResponse::NotFound().json(&"not found")
+                        })),
+                )
+                .default_service(web::to(|| async { HttpResponse::BadRequest() })),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/api/v1/users").to_request();
+        let resp = srv.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = TestRequest::with_uri("/api/v1/non-exist").to_request();
+        let resp = srv.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            resp.headers().get(CONTENT_TYPE).unwrap(),
+            HeaderValue::from_static("application/json")
+        );
+
+        let req = TestRequest::with_uri("/api/non-exist").to_request();
+        let resp = srv.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let req = TestRequest::with_uri("/api/v2/non-exist").to_request();
+        let resp = srv.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::GONE);
+
+        let req = TestRequest::with_uri("/non-exist").to_request();
+        let resp = srv.call(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
     #[crate::rt_test]
     async fn test_filter() {
         let srv = init_service(
@@ -1244,6 +1392,84 @@ mod tests {
         assert_eq!(resp.status(), StatusCode::OK);
     }
 
+    #[crate::rt_test]
+    async fn test_data_layers() {
+        let srv = init_service(
+            App::new()
+                .app_data(10u32)
+                .data(1usize)
+                .data_factory(|| async { Ok::<_, ()>(1i64) })
+                .service(web::scope("app").data(10usize).service(
+                    web::scope("nested").data('*').route(
+                        "/t",
+                        web::get().to(
+                            |req: HttpRequest,
+                             data1: web::types::Data<usize>,
+                             data2: web::types::Data<i64>,
+                             data3: web::types::Data<char>| {
+                                assert_eq!(**data1, 10);
+                                assert_eq!(**data2, 1);
+                                assert_eq!(**data3, '*');
+                                assert_eq!(*req.app_data::<u32>().unwrap(), 10);
+                                async { HttpResponse::Ok() }
+                            },
+                        ),
+                    ),
+                )),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/app/nested/t").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[crate::rt_test]
+    async fn test_data_factory() {
+        let srv = init_service(
+            App::new()
+                .data(1usize)
+                .service(
+                    web::scope("app")
+                        .data_factory(|| async { Ok::<_, ()>(10usize) })
+                        .data_init(web::types::Data::from_fn(|| async {
+                            Ok::<_, ()>("data".to_string())
+                        }))
+                        .data_factory(|| async { Ok::<_, ()>('-') })
+                        .data('*')
+                        .route(
+                            "/t",
+                            web::get().to(
+                                |data1: web::types::Data<usize>,
+                                 data2: web::types::Data<String>,
+                                 data3: web::types::Data<char>| {
+                                    assert_eq!(**data1, 10);
+                                    assert_eq!(data2.as_str(), "data");
+                                    assert_eq!(**data3, '*');
+                                    async { HttpResponse::Ok() }
+                                },
+                            ),
+                        ),
+                )
+                .route(
+                    "/t",
+                    web::get().to(|data: web::types::Data<usize>| {
+                        assert_eq!(**data, 1);
+                        async { HttpResponse::Ok() }
+                    }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/app/t").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = TestRequest::with_uri("/t").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
     #[crate::rt_test]
     async fn test_scope_config() {
         let srv = init_service(App::new().service(web::scope("/app").configure(|s| {
@@ -1319,4 +1545,35 @@ mod tests {
             Bytes::from_static(b"http://localhost:8080/a/b/c/12345")
         );
     }
+
+    #[crate::rt_test]
+    async fn test_url_for_parent() {
+        let srv = init_service(
+            App::new()
+                .external_resource("youtube", "https://youtube.com/watch/{video_id}")
+                .service(web::resource("/user/{id}").name("user"))
+                .service(web::scope("/a").service(web::scope("/b").route(
+                    "/c",
+                    web::get().to(|req: HttpRequest| async move {
+                        HttpResponse::Ok().body(format!(
+                            "{} {}",
+                            req.url_for("user", &["1"]).unwrap(),
+                            req.url_for("youtube", &["2"]).unwrap()
+                        ))
+                    }),
+                ))),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/a/b/c").to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = read_body(resp).await;
+        assert_eq!(
+            body,
+            Bytes::from_static(
+                b"http://localhost:8080/user/1 https://youtube.com/watch/2"
+            )
+        );
+    }
 }
@@ -15,7 +15,9 @@ use crate::pipeline_factory;
 use crate::server::{Server, ServerBuilder};
 use crate::{map_config, IntoServiceFactory, Service, ServiceFactory};
 
+use super::blocking::{default_threads, BlockingPool, DEFAULT_QUEUE_SIZE};
 use super::config::AppConfig;
+use super::info::Cidr;
 
 struct Config {
     host: Option<String>,
@@ -26,6 +28,20 @@ struct Config {
     lw: u16,
     read_hw: u16,
     write_hw: u16,
+    blocking_threads: usize,
+    blocking_queue: usize,
+    blocking: Option<BlockingPool>,
+    trusted_proxies: Option<Vec<Cidr>>,
+}
+
+impl Config {
+    /// Use server's blocking pool on current worker thread
+    fn set_blocking_pool(&mut self) {
+        let (threads, queue) = (self.blocking_threads, self.blocking_queue);
+        self.blocking
+            .get_or_insert_with(|| BlockingPool::new(threads, queue))
+            .set_current();
+    }
 }
 
 /// An HTTP Server.
@@ -88,6 +104,10 @@ where
                 lw: 1024,
                 read_hw: 8 * 1024,
                 write_hw: 8 * 1024,
+                blocking_threads: default_threads(),
+                blocking_queue: DEFAULT_QUEUE_SIZE,
+                blocking: None,
+                trusted_proxies: None,
             })),
             backlog: 1024,
             builder: ServerBuilder::default(),
@@ -104,6 +124,29 @@ where
         self
     }
 
+    /// Set max number of threads of blocking thread pool.
+    ///
+    /// Thread pool is used by `web::block()` function and is shared
+    /// by all workers. Threads get started on demand.
+    ///
+    /// By default max number of threads is set to a number of available
+    /// logical cpu multiplied by 5.
+    pub fn blocking_threads(self, num: usize) -> Self {
+        self.config.lock().unwrap().blocking_threads = num;
+        self
+    }
+
+    /// Set max number of queued operations of blocking thread pool.
+    ///
+    /// If all threads are busy and queue is full, `web::block()` returns
+    /// `BlockingError::Overloaded` error.
+    ///
+    /// By default queue size is set to 1024.
+    pub fn blocking_queue(self, num: usize) -> Self {
+        self.config.lock().unwrap().blocking_queue = num;
+        self
+    }
+
     /// Set the maximum number of pending connections.
     ///
     /// This refers to the number of clients that can be waiting to be served.
@@ -200,6 +243,26 @@ where
         self
     }
 
+    /// Add trusted proxy network, i.e. `10.0.0.0/8` or `192.168.1.1`.
+    ///
+    /// Once trusted proxies are configured, `Forwarded` and `X-Forwarded-*`
+    /// headers are used by [ConnectionInfo](./dev/struct.ConnectionInfo.html)
+    /// only for requests from trusted proxies. By default headers
+    /// from all peers are trusted.
+    ///
+    /// Panics if network could not be parsed.
+    pub fn trusted_proxy<T: AsRef<str>>(self, net: T) -> Self {
+        let net = Cidr::parse(net.as_ref())
+            .unwrap_or_else(|| panic!("Invalid proxy network: {}", net.as_ref()));
+        self.config
+            .lock()
+            .unwrap()
+            .trusted_proxies
+            .get_or_insert_with(Vec::new)
+            .push(net);
+        self
+    }
+
     /// Stop ntex runtime when server get dropped.
     ///
     /// By default "stop runtime" is disabled.
@@ -260,12 +323,14 @@ where
             format!("ntex-web-service-{}", addr),
             lst,
             move || {
-                let c = cfg.lock().unwrap();
+                let mut c = cfg.lock().unwrap();
+                c.set_blocking_pool();
                 let cfg = AppConfig::new(
                     false,
                     addr,
                     c.host.clone().unwrap_or_else(|| format!("{}", addr)),
-                );
+                )
+                .set_trusted_proxies(c.trusted_proxies.clone());
 
                 HttpService::build()
                     .keep_alive(c.keep_alive)
@@ -305,12 +370,14 @@ where
             format!("ntex-web-service-{}", addr),
             lst,
             move || {
-                let c = cfg.lock().unwrap();
+                let mut c = cfg.lock().unwrap();
+                c.set_blocking_pool();
                 let cfg = AppConfig::new(
                     true,
                     addr,
                     c.host.clone().unwrap_or_else(|| format!("{}", addr)),
-                );
+                )
+                .set_trusted_proxies(c.trusted_proxies.clone());
                 HttpService::build()
                     .keep_alive(c.keep_alive)
                     .client_timeout(c.client_timeout)
@@ -350,12 +417,14 @@ where
             format!("ntex-web-rustls-service-{}", addr),
             lst,
             move || {
-                let c = cfg.lock().unwrap();
+                let mut c = cfg.lock().unwrap();
+                c.set_blocking_pool();
                 let cfg = AppConfig::new(
                     true,
                     addr,
                     c.host.clone().unwrap_or_else(|| format!("{}", addr)),
-                );
+                )
+                .set_trusted_proxies(c.trusted_proxies.clone());
                 HttpService::build()
                     .keep_alive(c.keep_alive)
                     .client_timeout(c.client_timeout)
@@ -471,12 +540,14 @@ where
         let addr = format!("ntex-web-service-{:?}", lst.local_addr()?);
 
         self.builder = self.builder.listen_uds(addr, lst, move || {
-            let c = cfg.lock().unwrap();
+            let mut c = cfg.lock().unwrap();
+            c.set_blocking_pool();
             let config = AppConfig::new(
                 false,
                 socket_addr,
                 c.host.clone().unwrap_or_else(|| format!("{}", socket_addr)),
-            );
+            )
+            .set_trusted_proxies(c.trusted_proxies.clone());
             pipeline_factory(|io: UnixStream| {
                 crate::util::Ready::Ok((io, Protocol::Http1, None))
             })
@@ -512,12 +583,14 @@ where
             format!("ntex-web-service-{:?}", addr.as_ref()),
             addr,
             move || {
-                let c = cfg.lock().unwrap();
+                let mut c = cfg.lock().unwrap();
+                c.set_blocking_pool();
                 let config = AppConfig::new(
                     false,
                     socket_addr,
                     c.host.clone().unwrap_or_else(|| format!("{}", socket_addr)),
-                );
+                )
+                .set_trusted_proxies(c.trusted_proxies.clone());
                 pipeline_factory(|io: UnixStream| {
                     crate::util::Ready::Ok((io, Protocol::Http1, None))
                 })
@@ -1,17 +1,19 @@
 use std::rc::Rc;
 
+#[cfg(feature = "openapi")]
+use crate::http::Method;
 use crate::router::{IntoPattern, ResourceDef};
 use crate::service::{boxed, IntoServiceFactory, ServiceFactory};
-use crate::util::Extensions;
 
 use super::config::AppConfig;
 use super::dev::insert_slesh;
 use super::error::ErrorRenderer;
-use super::guard::Guard;
+use super::guard::{Guard, Host, HostGuard};
+#[cfg(feature = "openapi")]
+use super::openapi::{OpenApi, Operation, Operations};
 use super::request::WebRequest;
 use super::response::WebResponse;
 use super::rmap::ResourceMap;
-use super::types::data::DataFactory;
 
 pub trait WebServiceFactory<Err: ErrorRenderer> {
     fn register(self, config: &mut WebServiceConfig<Err>);
@@ -53,6 +55,7 @@ type HttpServiceFactory<Err: ErrorRenderer> =
 pub struct WebServiceConfig<Err: ErrorRenderer> {
     config: AppConfig,
     root: bool,
+    method_not_allowed: bool,
     default: Rc<HttpServiceFactory<Err>>,
     services: Vec<(
         ResourceDef,
@@ -60,7 +63,10 @@ pub struct WebServiceConfig<Err: ErrorRenderer> {
         Option<Guards>,
         Option<Rc<ResourceMap>>,
     )>,
-    service_data: Rc<Vec<Box<dyn DataFactory>>>,
+    #[cfg(feature = "openapi")]
+    operations: Operations,
+    #[cfg(feature = "openapi")]
+    documents: Vec<OpenApi>,
 }
 
 impl<Err: ErrorRenderer> WebServiceConfig<Err> {
@@ -68,14 +74,18 @@ impl<Err: ErrorRenderer> WebServiceConfig<Err> {
     pub(crate) fn new(
         config: AppConfig,
         default: Rc<HttpServiceFactory<Err>>,
-        service_data: Rc<Vec<Box<dyn DataFactory>>>,
+        method_not_allowed: bool,
     ) -> Self {
         WebServiceConfig {
             config,
             default,
-            service_data,
+            method_not_allowed,
             root: true,
             services: Vec::new(),
+            #[cfg(feature = "openapi")]
+            operations: Vec::new(),
+            #[cfg(feature = "openapi")]
+            documents: Vec::new(),
         }
     }
 
@@ -104,10 +114,45 @@ impl<Err: ErrorRenderer> WebServiceConfig<Err> {
             default: self.default.clone(),
             services: Vec::new(),
             root: false,
-            service_data: self.service_data.clone(),
+            method_not_allowed: self.method_not_allowed,
+            #[cfg(feature = "openapi")]
+            operations: Vec::new(),
+            #[cfg(feature = "openapi")]
+            documents: Vec::new(),
         }
     }
 
+    #[cfg(feature = "openapi")]
+    /// Register api operation
+    pub(crate) fn add_operation(&mut self, path: String, method: Method, op: Operation) {
+        self.operations.push((path, method, op));
+    }
+
+    #[cfg(feature = "openapi")]
+    pub(crate) fn take_operations(&mut self) -> Operations {
+        std::mem::take(&mut self.operations)
+    }
+
+    #[cfg(feature = "openapi")]
+    /// Register OpenAPI document, document is generated after
+    /// all services get registered
+    pub(crate) fn add_openapi(&mut self, api: OpenApi) {
+        self.documents.push(api);
+    }
+
+    #[cfg(feature = "openapi")]
+    /// Generate registered OpenAPI documents
+    pub(crate) fn generate_openapi(&self) {
+        for api in &self.documents {
+            api.generate(&self.operations);
+        }
+    }
+
+    /// Check if automatic *405 Method Not Allowed* responses are enabled
+    pub(crate) fn method_not_allowed(&self) -> bool {
+        self.method_not_allowed
+    }
+
     /// Service configuration
     pub fn config(&self) -> &AppConfig {
         &self.config
@@ -118,12 +163,9 @@ impl<Err: ErrorRenderer> WebServiceConfig<Err> {
         self.default.clone()
     }
 
-    /// Set global route data
-    pub fn set_service_data(&self, extensions: &mut Extensions) -> bool {
-        for f in self.service_data.iter() {
-            f.create(extensions);
-        }
-        !self.service_data.is_empty()
+    /// Set default service for nested services
+    pub(crate) fn set_default_service(&mut self, default: Rc<HttpServiceFactory<Err>>) {
+        self.default = default;
     }
 
     /// Register http service
@@ -273,6 +315,39 @@ where
     }
 }
 
+/// Service tree bound to a host name
+pub(super) struct HostService<T> {
+    host: HostGuard,
+    factory: T,
+}
+
+impl<T> HostService<T> {
+    pub(super) fn new(host: &str, factory: T) -> Self {
+        HostService {
+            factory,
+            host: Host(host),
+        }
+    }
+}
+
+impl<T, Err> WebServiceFactory<Err> for HostService<T>
+where
+    T: WebServiceFactory<Err>,
+    Err: ErrorRenderer,
+{
+    fn register(self, config: &mut WebServiceConfig<Err>) {
+        let start = config.services.len();
+        self.factory.register(config);
+
+        // guard all services registered by the factory
+        for item in &mut config.services[start..] {
+            item.2
+                .get_or_insert_with(Vec::new)
+                .insert(0, Box::new(self.host.clone()));
+        }
+    }
+}
+
 /// WebServiceFactory implementation for a Vec<T>
 #[allow(unused_parens)]
 impl<Err, T> WebServiceFactory<Err> for Vec<T>
@@ -418,7 +493,7 @@ mod tests {
             .method(Method::PUT)
             .to_request();
         let resp = srv.call(req).await.unwrap();
-        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
     }
 
     #[crate::rt_test]
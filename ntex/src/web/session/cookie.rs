@@ -0,0 +1,48 @@
+use super::{SessionError, SessionState, SessionStore, StoreFuture};
+
+/// Max size of session state in cookie
+const MAX_SIZE: usize = 4064;
+
+/// Session store that keeps session state in the session cookie
+///
+/// Session state is json serialized and is used as a session key,
+/// state size is limited by 4064 bytes.
+#[derive(Clone, Debug, Default)]
+pub struct CookieStore;
+
+impl CookieStore {
+    /// Create cookie session store
+    pub fn new() -> Self {
+        CookieStore
+    }
+}
+
+impl SessionStore for CookieStore {
+    fn load(&self, key: &str) -> StoreFuture<Option<SessionState>> {
+        let result = serde_json::from_str(key)
+            .map(Some)
+            .map_err(SessionError::from);
+        Box::pin(async move { result })
+    }
+
+    fn save(&self, state: SessionState) -> StoreFuture<String> {
+        let result = serde_json::to_string(&state)
+            .map_err(SessionError::from)
+            .and_then(|key| {
+                if key.len() > MAX_SIZE {
+                    Err(SessionError::Overflow)
+                } else {
+                    Ok(key)
+                }
+            });
+        Box::pin(async move { result })
+    }
+
+    fn update(&self, _: String, state: SessionState) -> StoreFuture<String> {
+        self.save(state)
+    }
+
+    fn delete(&self, _: &str) -> StoreFuture<()> {
+        Box::pin(async { Ok(()) })
+    }
+}
@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use rand::{distributions::Alphanumeric, Rng};
+
+use super::{SessionState, SessionStore, StoreFuture};
+
+/// Session store that keeps session state in process memory
+///
+/// Store could be shared between workers, session state is lost on
+/// process restart.
+#[derive(Clone, Default)]
+pub struct MemoryStore {
+    inner: Arc<Mutex<HashMap<String, Entry>>>,
+    ttl: Option<Duration>,
+}
+
+struct Entry {
+    state: SessionState,
+    expires: Option<Instant>,
+}
+
+impl MemoryStore {
+    /// Create memory session store
+    pub fn new() -> Self {
+        MemoryStore::default()
+    }
+
+    /// Set session state time to live, by default session state never expires
+    ///
+    /// Expiration time is updated on every session state update.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Number of stored sessions
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().len()
+    }
+
+    /// Check if store is empty
+    pub fn is_empty(&self) -> bool {
+        self.inner.lock().unwrap().is_empty()
+    }
+
+    /// Store session state, new key is generated if key is not set or
+    /// it is not stored already
+    fn insert(&self, key: Option<String>, state: SessionState) -> String {
+        let now = Instant::now();
+        let mut inner = self.inner.lock().unwrap();
+        inner.retain(|_, entry| entry.expires.map(|exp| exp > now).unwrap_or(true));
+
+        let key = match key {
+            Some(key) if inner.contains_key(&key) => key,
+            _ => loop {
+                let key = generate_key();
+                if !inner.contains_key(&key) {
+                    break key;
+                }
+            },
+        };
+        inner.insert(
+            key.clone(),
+            Entry {
+                state,
+                expires: self.ttl.map(|ttl| now + ttl),
+            },
+        );
+        key
+    }
+}
+
+impl SessionStore for MemoryStore {
+    fn load(&self, key: &str) -> StoreFuture<Option<SessionState>> {
+        let state = self.inner.lock().unwrap().get(key).and_then(|entry| {
+            if entry
+                .expires
+                .map(|exp| exp > Instant::now())
+                .unwrap_or(true)
+            {
+                Some(entry.state.clone())
+            } else {
+                None
+            }
+        });
+        Box::pin(async move { Ok(state) })
+    }
+
+    fn save(&self, state: SessionState) -> StoreFuture<String> {
+        let key = self.insert(None, state);
+        Box::pin(async move { Ok(key) })
+    }
+
+    fn update(&self, key: String, state: SessionState) -> StoreFuture<String> {
+        // unknown or expired key is never reused
+        let key = self.insert(Some(key), state);
+        Box::pin(async move { Ok(key) })
+    }
+
+    fn delete(&self, key: &str) -> StoreFuture<()> {
+        self.inner.lock().unwrap().remove(key);
+        Box::pin(async { Ok(()) })
+    }
+}
+
+fn generate_key() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(64)
+        .map(char::from)
+        .collect()
+}
@@ -0,0 +1,749 @@
+//! Session management.
+//!
+//! `SessionManager` middleware loads session state from `SessionStore`
+//! before request is handled and persists modified state after response
+//! is generated. Session key is kept in signed or encrypted cookie.
+//! Handlers access session state with `Session` extractor.
+//!
+//! Cookie key could be rotated with `SessionManager::with_keys()`, cookies
+//! signed or encrypted with previous keys are still accepted.
+//!
+//! Two stores are included:
+//!
+//! * `CookieStore` keeps whole session state in the cookie
+//! * `MemoryStore` keeps session state in process memory
+//!
+//! ```rust
+//! use ntex::web::{self, App, HttpResponse, Error};
+//! use ntex::web::session::{CookieStore, Key, Session, SessionManager};
+//!
+//! async fn index(session: Session) -> Result<HttpResponse, Error> {
+//!     let counter = session.get::<u32>("counter")?.unwrap_or(0) + 1;
+//!     session.set("counter", counter)?;
+//!     Ok(HttpResponse::Ok().body(format!("Counter: {}", counter)))
+//! }
+//!
+//! fn main() {
+//!     let key = Key::generate();
+//!
+//!     let app = App::new()
+//!         .wrap(SessionManager::new(CookieStore::new(), key.clone()))
+//!         .service(web::resource("/").to(index));
+//! }
+//! ```
+use std::task::{Context, Poll};
+use std::{cell::RefCell, collections::HashMap, future::Future, pin::Pin, rc::Rc};
+use std::{convert::TryFrom, time::Duration};
+
+use coo_kie::{Cookie, CookieJar};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::http::{HttpMessage, Payload};
+use crate::service::{Service, Transform};
+use crate::util::Ready;
+use crate::web::dev::{WebRequest, WebResponse};
+use crate::web::{ErrorRenderer, FromRequest, HttpRequest};
+
+mod cookie;
+mod memory;
+
+pub use self::cookie::CookieStore;
+pub use self::memory::MemoryStore;
+pub use crate::web::cookie::{CookieKeys, Key};
+pub use coo_kie::SameSite;
+
+/// Session state, values are json serialized
+pub type SessionState = HashMap<String, String>;
+
+/// Session store future
+pub type StoreFuture<T> = Pin<Box<dyn Future<Output = Result<T, SessionError>>>>;
+
+/// Session storage backend
+///
+/// Store maps session key to session state. Session key is sent to
+/// the client in the session cookie.
+pub trait SessionStore {
+    /// Load session state for session key
+    fn load(&self, key: &str) -> StoreFuture<Option<SessionState>>;
+
+    /// Save new session state, returns new session key
+    fn save(&self, state: SessionState) -> StoreFuture<String>;
+
+    /// Update existing session state, returns session key
+    ///
+    /// If session key is unknown or expired, store must issue new session key.
+    fn update(&self, key: String, state: SessionState) -> StoreFuture<String>;
+
+    /// Delete session state
+    fn delete(&self, key: &str) -> StoreFuture<()>;
+}
+
+/// Session error
+#[derive(Debug, Display, From)]
+pub enum SessionError {
+    /// Session state serialization error
+    #[display(fmt = "Session state serialization error: {}", _0)]
+    Serialize(serde_json::Error),
+    /// Session state does not fit into cookie
+    #[display(fmt = "Session state is too large")]
+    Overflow,
+    /// Session store error
+    #[display(fmt = "Session store error: {}", _0)]
+    Store(Box<dyn std::error::Error>),
+}
+
+/// Status of session state
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SessionStatus {
+    /// Session state is not modified
+    Unchanged,
+    /// Session state is modified
+    Changed,
+    /// Session state is removed and session cookie is deleted
+    Purged,
+    /// Session key is renewed
+    Renewed,
+}
+
+struct SessionInner {
+    state: SessionState,
+    status: SessionStatus,
+}
+
+/// Request session
+///
+/// Session is available as an extractor or via `UserSession::get_session()`.
+/// If `SessionManager` middleware is not configured, session state is
+/// not persisted.
+#[derive(Clone)]
+pub struct Session(Rc<RefCell<SessionInner>>);
+
+impl Session {
+    fn new(state: SessionState) -> Self {
+        Session(Rc::new(RefCell::new(SessionInner {
+            state,
+            status: SessionStatus::Unchanged,
+        })))
+    }
+
+    /// Get value from the session
+    pub fn get<T: DeserializeOwned>(
+        &self,
+        key: &str,
+    ) -> Result<Option<T>, serde_json::Error> {
+        if let Some(val) = self.0.borrow().state.get(key) {
+            Ok(Some(serde_json::from_str(val)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Set value in the session
+    pub fn set<T: Serialize>(
+        &self,
+        key: &str,
+        value: T,
+    ) -> Result<(), serde_json::Error> {
+        let value = serde_json::to_string(&value)?;
+        let mut inner = self.0.borrow_mut();
+        if inner.status != SessionStatus::Purged {
+            if inner.status != SessionStatus::Renewed {
+                inner.status = SessionStatus::Changed;
+            }
+            inner.state.insert(key.to_owned(), value);
+        }
+        Ok(())
+    }
+
+    /// Remove value from the session
+    pub fn remove(&self, key: &str) {
+        let mut inner = self.0.borrow_mut();
+        if inner.status != SessionStatus::Purged {
+            if inner.status != SessionStatus::Renewed {
+                inner.status = SessionStatus::Changed;
+            }
+            inner.state.remove(key);
+        }
+    }
+
+    /// Remove all values from the session
+    pub fn clear(&self) {
+        let mut inner = self.0.borrow_mut();
+        if inner.status != SessionStatus::Purged {
+            if inner.status != SessionStatus::Renewed {
+                inner.status = SessionStatus::Changed;
+            }
+            inner.state.clear()
+        }
+    }
+
+    /// Remove session state and delete session cookie
+    pub fn purge(&self) {
+        let mut inner = self.0.borrow_mut();
+        inner.status = SessionStatus::Purged;
+        inner.state.clear();
+    }
+
+    /// Renew session key, session state is preserved
+    pub fn renew(&self) {
+        let mut inner = self.0.borrow_mut();
+        if inner.status != SessionStatus::Purged {
+            inner.status = SessionStatus::Renewed;
+        }
+    }
+
+    /// Session status
+    pub fn status(&self) -> SessionStatus {
+        self.0.borrow().status
+    }
+
+    fn take(&self) -> (SessionStatus, SessionState) {
+        let mut inner = self.0.borrow_mut();
+        (inner.status, std::mem::take(&mut inner.state))
+    }
+}
+
+/// Access session of the request
+pub trait UserSession {
+    /// Get request session
+    fn get_session(&self) -> Session;
+}
+
+impl UserSession for HttpRequest {
+    fn get_session(&self) -> Session {
+        if let Some(session) = self.extensions().get::<Session>() {
+            return session.clone();
+        }
+        let session = Session::new(SessionState::new());
+        self.extensions_mut().insert(session.clone());
+        session
+    }
+}
+
+impl<Err> UserSession for WebRequest<Err> {
+    fn get_session(&self) -> Session {
+        if let Some(session) = self.extensions().get::<Session>() {
+            return session.clone();
+        }
+        let session = Session::new(SessionState::new());
+        self.extensions_mut().insert(session.clone());
+        session
+    }
+}
+
+impl<Err: ErrorRenderer> FromRequest<Err> for Session {
+    type Error = Err::Container;
+    type Future = Ready<Session, Err::Container>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        Ready::Ok(req.get_session())
+    }
+}
+
+/// Session cookie content security
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CookieContentSecurity {
+    /// Cookie content is signed, client could read but not modify it
+    Signed,
+    /// Cookie content is encrypted and authenticated
+    Private,
+}
+
+/// `Middleware` for session management.
+///
+/// Session cookie is signed by default, use
+/// `SessionManager::content_security()` to encrypt cookie content.
+pub struct SessionManager<St> {
+    inner: Rc<Inner<St>>,
+}
+
+struct Inner<St> {
+    store: St,
+    keys: CookieKeys,
+    name: String,
+    path: String,
+    domain: Option<String>,
+    secure: bool,
+    http_only: bool,
+    same_site: Option<SameSite>,
+    max_age: Option<time::Duration>,
+    security: CookieContentSecurity,
+}
+
+impl<St: SessionStore> SessionManager<St> {
+    /// Create session middleware with store and cookie key
+    pub fn new(store: St, key: Key) -> Self {
+        Self::with_keys(store, CookieKeys::new(key))
+    }
+
+    /// Create session middleware with store and cookie keys
+    ///
+    /// Session cookie is signed or encrypted with current key,
+    /// previous keys are used for verification only.
+    pub fn with_keys(store: St, keys: CookieKeys) -> Self {
+        SessionManager {
+            inner: Rc::new(Inner {
+                store,
+                keys,
+                name: "ntex-session".to_string(),
+                path: "/".to_string(),
+                domain: None,
+                secure: true,
+                http_only: true,
+                same_site: Some(SameSite::Lax),
+                max_age: None,
+                security: CookieContentSecurity::Signed,
+            }),
+        }
+    }
+
+    /// Set session cookie name, default is `ntex-session`
+    pub fn cookie_name<T: Into<String>>(mut self, name: T) -> Self {
+        Rc::get_mut(&mut self.inner).unwrap().name = name.into();
+        self
+    }
+
+    /// Set session cookie path, default is `/`
+    pub fn cookie_path<T: Into<String>>(mut self, path: T) -> Self {
+        Rc::get_mut(&mut self.inner).unwrap().path = path.into();
+        self
+    }
+
+    /// Set session cookie domain
+    pub fn cookie_domain<T: Into<String>>(mut self, domain: T) -> Self {
+        Rc::get_mut(&mut self.inner).unwrap().domain = Some(domain.into());
+        self
+    }
+
+    /// Set session cookie `secure` attribute, default is `true`
+    pub fn cookie_secure(mut self, value: bool) -> Self {
+        Rc::get_mut(&mut self.inner).unwrap().secure = value;
+        self
+    }
+
+    /// Set session cookie `http_only` attribute, default is `true`
+    pub fn cookie_http_only(mut self, value: bool) -> Self {
+        Rc::get_mut(&mut self.inner).unwrap().http_only = value;
+        self
+    }
+
+    /// Set session cookie `same_site` attribute, default is `Lax`
+    pub fn cookie_same_site(mut self, value: SameSite) -> Self {
+        Rc::get_mut(&mut self.inner).unwrap().same_site = Some(value);
+        self
+    }
+
+    /// Set session cookie `max_age` attribute
+    ///
+    /// By default session cookie is a browser session cookie.
+    pub fn cookie_max_age(mut self, value: Duration) -> Self {
+        Rc::get_mut(&mut self.inner).unwrap().max_age = Some(
+            time::Duration::try_from(value)
+                .unwrap_or_else(|_| time::Duration::max_value()),
+        );
+        self
+    }
+
+    /// Set session cookie content security, default is `Signed`
+    pub fn content_security(mut self, value: CookieContentSecurity) -> Self {
+        Rc::get_mut(&mut self.inner).unwrap().security = value;
+        self
+    }
+}
+
+impl<St> Inner<St> {
+    fn session_key<Err>(&self, req: &WebRequest<Err>) -> Option<String> {
+        let cookie = req.cookie(&self.name)?;
+        let cookie = match self.security {
+            CookieContentSecurity::Signed => self.keys.verify(cookie),
+            CookieContentSecurity::Private => self.keys.decrypt(cookie),
+        };
+        if cookie.is_none() {
+            log::debug!("Session cookie verification failed");
+        }
+        cookie.map(|c| c.value().to_owned())
+    }
+
+    fn cookie(&self, value: String) -> Cookie<'static> {
+        let mut cookie = Cookie::new(self.name.clone(), value);
+        cookie.set_path(self.path.clone());
+        cookie.set_secure(self.secure);
+        cookie.set_http_only(self.http_only);
+        if let Some(ref domain) = self.domain {
+            cookie.set_domain(domain.clone());
+        }
+        if let Some(same_site) = self.same_site {
+            cookie.set_same_site(same_site);
+        }
+        if let Some(max_age) = self.max_age {
+            cookie.set_max_age(max_age);
+        }
+        cookie
+    }
+
+    fn set_cookie(&self, res: &mut WebResponse, key: String) {
+        let mut jar = CookieJar::new();
+        match self.security {
+            CookieContentSecurity::Signed => {
+                jar.signed_mut(self.keys.key()).add(self.cookie(key))
+            }
+            CookieContentSecurity::Private => {
+                jar.private_mut(self.keys.key()).add(self.cookie(key))
+            }
+        }
+        for cookie in jar.delta() {
+            if let Err(e) = res.response_mut().add_cookie(cookie) {
+                log::error!("Cannot set session cookie: {}", e);
+            }
+        }
+    }
+
+    fn remove_cookie(&self, res: &mut WebResponse) {
+        let mut cookie = self.cookie(String::new());
+        cookie.make_removal();
+        if let Err(e) = res.response_mut().add_cookie(&cookie) {
+            log::error!("Cannot remove session cookie: {}", e);
+        }
+    }
+}
+
+impl<S, St, Err> Transform<S> for SessionManager<St>
+where
+    S: Service<Request = WebRequest<Err>, Response = WebResponse> + 'static,
+    St: SessionStore + 'static,
+    Err: ErrorRenderer,
+    SessionError: Into<Err::Container>,
+{
+    type Request = WebRequest<Err>;
+    type Response = WebResponse;
+    type Error = S::Error;
+    type InitError = ();
+    type Transform = SessionManagerMiddleware<S, St>;
+    type Future = Ready<Self::Transform, Self::InitError>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        Ready::Ok(SessionManagerMiddleware {
+            service: Rc::new(service),
+            inner: self.inner.clone(),
+        })
+    }
+}
+
+/// Session middleware
+pub struct SessionManagerMiddleware<S, St> {
+    service: Rc<S>,
+    inner: Rc<Inner<St>>,
+}
+
+impl<S, St, Err> Service for SessionManagerMiddleware<S, St>
+where
+    S: Service<Request = WebRequest<Err>, Response = WebResponse> + 'static,
+    St: SessionStore + 'static,
+    Err: ErrorRenderer,
+    SessionError: Into<Err::Container>,
+{
+    type Request = WebRequest<Err>;
+    type Response = WebResponse;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    #[inline]
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(&self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&self, req: WebRequest<Err>) -> Self::Future {
+        let srv = self.service.clone();
+        let inner = self.inner.clone();
+
+        Box::pin(async move {
+            let key = inner.session_key(&req);
+            let state = if let Some(ref key) = key {
+                match inner.store.load(key).await {
+                    Ok(state) => state,
+                    Err(e) => return Ok(req.error_response(e)),
+                }
+            } else {
+                None
+            };
+            let session = Session::new(state.unwrap_or_default());
+            req.extensions_mut().insert(session.clone());
+
+            let mut res = srv.call(req).await?;
+
+            let result = match session.take() {
+                (SessionStatus::Unchanged, _) => Ok(()),
+                (SessionStatus::Changed, state) => {
+                    let result = if let Some(key) = key {
+                        inner.store.update(key, state).await
+                    } else {
+                        inner.store.save(state).await
+                    };
+                    result.map(|key| inner.set_cookie(&mut res, key))
+                }
+                (SessionStatus::Renewed, state) => {
+                    if let Some(ref key) = key {
+                        if let Err(e) = inner.store.delete(key).await {
+                            return Ok(res.error_response::<Err, _>(e));
+                        }
+                    }
+                    inner
+                        .store
+                        .save(state)
+                        .await
+                        .map(|key| inner.set_cookie(&mut res, key))
+                }
+                (SessionStatus::Purged, _) => {
+                    if let Some(ref key) = key {
+                        inner
+                            .store
+                            .delete(key)
+                            .await
+                            .map(|_| inner.remove_cookie(&mut res))
+                    } else {
+                        Ok(())
+                    }
+                }
+            };
+
+            match result {
+                Ok(_) => Ok(res),
+                Err(e) => Ok(res.error_response::<Err, _>(e)),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::web::test::{call_service, init_service, read_body, TestRequest};
+    use crate::web::{self, App, HttpResponse};
+
+    async fn counter(session: Session) -> Result<String, web::Error> {
+        let counter = session.get::<u32>("counter")?.unwrap_or(0) + 1;
+        session.set("counter", counter)?;
+        Ok(counter.to_string())
+    }
+
+    fn session_cookie(res: &WebResponse) -> Option<Cookie<'static>> {
+        res.response()
+            .cookies()
+            .find(|c| c.name() == "ntex-session")
+            .map(|c| c.into_owned())
+    }
+
+    #[crate::rt_test]
+    async fn test_session() {
+        let session = Session::new(SessionState::new());
+        assert_eq!(session.status(), SessionStatus::Unchanged);
+        assert_eq!(session.get::<u32>("key").unwrap(), None);
+
+        session.set("key", 10).unwrap();
+        assert_eq!(session.status(), SessionStatus::Changed);
+        assert_eq!(session.get::<u32>("key").unwrap(), Some(10));
+        assert!(session.get::<String>("key").is_err());
+
+        session.renew();
+        session.remove("key");
+        assert_eq!(session.status(), SessionStatus::Renewed);
+        assert_eq!(session.get::<u32>("key").unwrap(), None);
+
+        session.purge();
+        session.set("key", 10).unwrap();
+        assert_eq!(session.status(), SessionStatus::Purged);
+        assert_eq!(session.get::<u32>("key").unwrap(), None);
+
+        let req = TestRequest::default().to_http_request();
+        req.get_session().set("key", "value").unwrap();
+        assert_eq!(
+            req.get_session().get::<String>("key").unwrap(),
+            Some("value".to_string())
+        );
+    }
+
+    #[crate::rt_test]
+    async fn test_cookie_store() {
+        let key = Key::generate();
+        for security in &[
+            CookieContentSecurity::Signed,
+            CookieContentSecurity::Private,
+        ] {
+            let srv = init_service(
+                App::new()
+                    .wrap(
+                        SessionManager::new(CookieStore::new(), key.clone())
+                            .content_security(*security)
+                            .cookie_max_age(Duration::from_secs(3600)),
+                    )
+                    .service(web::resource("/").to(counter))
+                    .service(web::resource("/purge").to(
+                        |session: Session| async move {
+                            session.purge();
+                            HttpResponse::Ok()
+                        },
+                    )),
+            )
+            .await;
+
+            let res = call_service(&srv, TestRequest::default().to_request()).await;
+            let cookie = session_cookie(&res).unwrap();
+            assert!(cookie.http_only().unwrap());
+            assert_eq!(cookie.path(), Some("/"));
+            assert!(cookie.max_age().is_some());
+            assert_eq!(read_body(res).await, "1");
+
+            let req = TestRequest::default().cookie(cookie.clone()).to_request();
+            let res = call_service(&srv, req).await;
+            let cookie = session_cookie(&res).unwrap();
+            assert_eq!(read_body(res).await, "2");
+
+            // tampered cookie starts new session
+            let mut tampered = cookie.clone();
+            tampered.set_value(format!("{}0", cookie.value()));
+            let req = TestRequest::default().cookie(tampered).to_request();
+            let res = call_service(&srv, req).await;
+            assert_eq!(read_body(res).await, "1");
+
+            let req = TestRequest::with_uri("/purge").cookie(cookie).to_request();
+            let res = call_service(&srv, req).await;
+            let cookie = session_cookie(&res).unwrap();
+            assert_eq!(cookie.value(), "");
+            assert_eq!(cookie.max_age(), Some(time::Duration::seconds(0)));
+        }
+    }
+
+    #[crate::rt_test]
+    async fn test_key_rotation() {
+        let (old, new) = (Key::generate(), Key::generate());
+        for security in &[
+            CookieContentSecurity::Signed,
+            CookieContentSecurity::Private,
+        ] {
+            let srv = init_service(
+                App::new()
+                    .wrap(
+                        SessionManager::new(CookieStore::new(), old.clone())
+                            .content_security(*security),
+                    )
+                    .service(web::resource("/").to(counter)),
+            )
+            .await;
+            let res = call_service(&srv, TestRequest::default().to_request()).await;
+            let cookie = session_cookie(&res).unwrap();
+
+            let keys = CookieKeys::new(new.clone()).previous_key(old.clone());
+            let srv = init_service(
+                App::new()
+                    .wrap(
+                        SessionManager::with_keys(CookieStore::new(), keys)
+                            .content_security(*security),
+                    )
+                    .service(web::resource("/").to(counter)),
+            )
+            .await;
+
+            // cookie issued with previous key is accepted
+            let req = TestRequest::default().cookie(cookie.clone()).to_request();
+            let res = call_service(&srv, req).await;
+            let renewed = session_cookie(&res).unwrap();
+            assert_eq!(read_body(res).await, "2");
+
+            // new cookie is issued with current key
+            let srv = init_service(
+                App::new()
+                    .wrap(
+                        SessionManager::new(CookieStore::new(), new.clone())
+                            .content_security(*security),
+                    )
+                    .service(web::resource("/").to(counter)),
+            )
+            .await;
+            let req = TestRequest::default().cookie(renewed).to_request();
+            let res = call_service(&srv, req).await;
+            assert_eq!(read_body(res).await, "3");
+
+            let req = TestRequest::default().cookie(cookie).to_request();
+            let res = call_service(&srv, req).await;
+            assert_eq!(read_body(res).await, "1");
+        }
+    }
+
+    #[crate::rt_test]
+    async fn test_memory_store() {
+        let store = MemoryStore::new().ttl(Duration::from_secs(60));
+        let srv = init_service(
+            App::new()
+                .wrap(SessionManager::new(store.clone(), Key::generate()))
+                .service(web::resource("/").to(counter))
+                .service(web::resource("/renew").to(|session: Session| async move {
+                    session.renew();
+                    HttpResponse::Ok()
+                }))
+                .service(web::resource("/none").to(|| async { HttpResponse::Ok() })),
+        )
+        .await;
+
+        let res = call_service(&srv, TestRequest::with_uri("/none").to_request()).await;
+        assert!(session_cookie(&res).is_none());
+        assert!(store.is_empty());
+
+        let res = call_service(&srv, TestRequest::default().to_request()).await;
+        let cookie = session_cookie(&res).unwrap();
+        assert_eq!(read_body(res).await, "1");
+        assert_eq!(store.len(), 1);
+
+        let req = TestRequest::default().cookie(cookie.clone()).to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(read_body(res).await, "2");
+
+        let req = TestRequest::with_uri("/renew")
+            .cookie(cookie.clone())
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let renewed = session_cookie(&res).unwrap();
+        assert_ne!(renewed.value(), cookie.value());
+        assert_eq!(store.len(), 1);
+
+        // old session key is deleted
+        let req = TestRequest::default().cookie(cookie).to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(read_body(res).await, "1");
+
+        let req = TestRequest::default().cookie(renewed).to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(read_body(res).await, "3");
+
+        // unknown session key is not reused
+        let state = SessionState::default();
+        let key = store.update("unknown".to_string(), state).await.unwrap();
+        assert_ne!(key, "unknown");
+        assert!(store.load("unknown").await.unwrap().is_none());
+        assert!(store.load(&key).await.unwrap().is_some());
+    }
+
+    #[crate::rt_test]
+    async fn test_overflow() {
+        let srv = init_service(
+            App::new()
+                .wrap(SessionManager::new(CookieStore::new(), Key::generate()))
+                .service(web::resource("/").to(|session: Session| async move {
+                    session.set("key", "x".repeat(5000)).unwrap();
+                    HttpResponse::Ok()
+                })),
+        )
+        .await;
+
+        let res = call_service(&srv, TestRequest::default().to_request()).await;
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(session_cookie(&res).is_none());
+    }
+}
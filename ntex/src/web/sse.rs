@@ -0,0 +1,402 @@
+//! Server-Sent Events support.
+//!
+//! ```rust
+//! use std::time::Duration;
+//! use ntex::web::{self, sse, App};
+//!
+//! async fn events(last_id: sse::LastEventId) -> sse::SseResponder<impl ntex::Stream<Item = sse::SseEvent>> {
+//!     let start = last_id.0.and_then(|id| id.parse::<u64>().ok()).unwrap_or(0);
+//!     let (tx, rx) = ntex::channel::mpsc::channel();
+//!     for id in start + 1..start + 4 {
+//!         let _ = tx.send(sse::SseEvent::data(format!("event {}", id)).id(&id.to_string()));
+//!     }
+//!     sse::SseResponder::new(rx).keep_alive(Duration::from_secs(30))
+//! }
+//!
+//! fn main() {
+//!     let app = App::new().route("/events", web::get().to(events));
+//! }
+//! ```
+use std::task::{Context, Poll};
+use std::{error::Error, fmt, future::Future, pin::Pin, time::Duration};
+
+use crate::http::body::{Body, BodySize, MessageBody};
+use crate::http::header::{HeaderName, CACHE_CONTROL, CONTENT_TYPE};
+use crate::http::{Payload, Response};
+use crate::rt::time::{sleep, Instant, Sleep};
+use crate::util::{Bytes, BytesMut, Ready};
+use crate::web::responder::{self, Responder};
+use crate::web::{ErrorRenderer, FromRequest, HttpRequest};
+use crate::Stream;
+
+/// Max size of the body chunk, ready events are combined up to this size
+const MAX_CHUNK_SIZE: usize = 65_536;
+
+/// Server-Sent event.
+///
+/// ```rust
+/// use std::time::Duration;
+/// use ntex::web::sse::SseEvent;
+///
+/// let event = SseEvent::data("line 1\nline 2")
+///     .event("update")
+///     .id("42")
+///     .retry(Duration::from_secs(5));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SseEvent {
+    data: Option<String>,
+    event: Option<String>,
+    id: Option<String>,
+    retry: Option<Duration>,
+    comment: Option<String>,
+}
+
+impl SseEvent {
+    /// Create event with data, multi-line data is split to multiple `data` fields.
+    pub fn data<T: Into<String>>(data: T) -> Self {
+        SseEvent {
+            data: Some(data.into()),
+            ..Default::default()
+        }
+    }
+
+    /// Create comment, comments are ignored by clients.
+    pub fn comment<T: Into<String>>(comment: T) -> Self {
+        SseEvent {
+            comment: Some(comment.into()),
+            ..Default::default()
+        }
+    }
+
+    /// Set event type.
+    pub fn event(mut self, event: &str) -> Self {
+        self.event = Some(single_line(event));
+        self
+    }
+
+    /// Set event id, client sends last seen id in `Last-Event-ID` header on reconnect.
+    pub fn id(mut self, id: &str) -> Self {
+        self.id = Some(single_line(id).replace('\0', ""));
+        self
+    }
+
+    /// Set client reconnection time.
+    pub fn retry(mut self, retry: Duration) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    fn encode(&self, buf: &mut BytesMut) {
+        if let Some(ref comment) = self.comment {
+            for line in lines(comment) {
+                field(buf, "", line);
+            }
+        }
+        if let Some(ref event) = self.event {
+            field(buf, "event", event);
+        }
+        if let Some(ref id) = self.id {
+            field(buf, "id", id);
+        }
+        if let Some(retry) = self.retry {
+            field(buf, "retry", &retry.as_millis().to_string());
+        }
+        if let Some(ref data) = self.data {
+            for line in lines(data) {
+                field(buf, "data", line);
+            }
+        }
+        buf.extend_from_slice(b"\n");
+    }
+}
+
+fn single_line(s: &str) -> String {
+    s.replace(&['\r', '\n'][..], "")
+}
+
+/// Split on `\r\n`, `\r` and `\n`, all of them are line ends in event stream
+fn lines(s: &str) -> impl Iterator<Item = &str> {
+    let mut rest = Some(s);
+    std::iter::from_fn(move || {
+        let s = rest?;
+        if let Some(pos) = s.find(&['\r', '\n'][..]) {
+            let next = if s[pos..].starts_with("\r\n") {
+                pos + 2
+            } else {
+                pos + 1
+            };
+            rest = Some(&s[next..]);
+            Some(&s[..pos])
+        } else {
+            rest = None;
+            Some(s)
+        }
+    })
+}
+
+fn field(buf: &mut BytesMut, name: &str, value: &str) {
+    buf.extend_from_slice(name.as_bytes());
+    buf.extend_from_slice(b":");
+    if !value.is_empty() {
+        buf.extend_from_slice(b" ");
+        buf.extend_from_slice(value.as_bytes());
+    }
+    buf.extend_from_slice(b"\n");
+}
+
+/// Server-Sent Events responder.
+///
+/// Responder turns stream of events to `text/event-stream` response.
+/// Events are pulled from the stream only when connection is ready to send
+/// more data, so slow clients do not cause unbounded buffering. If stream
+/// does not produce events for keep-alive period, comment is sent to keep
+/// connection open. Default keep-alive period is 15 seconds.
+pub struct SseResponder<S> {
+    stream: S,
+    keep_alive: Option<Duration>,
+}
+
+impl<S> SseResponder<S>
+where
+    S: Stream<Item = SseEvent> + Unpin + 'static,
+{
+    /// Create new responder for stream of events.
+    pub fn new(stream: S) -> Self {
+        SseResponder {
+            stream,
+            keep_alive: Some(Duration::from_secs(15)),
+        }
+    }
+
+    /// Set keep-alive period, zero duration disables keep-alive comments.
+    pub fn keep_alive(mut self, period: Duration) -> Self {
+        self.keep_alive = if period == Duration::from_secs(0) {
+            None
+        } else {
+            Some(period)
+        };
+        self
+    }
+}
+
+impl<S, Err> Responder<Err> for SseResponder<S>
+where
+    S: Stream<Item = SseEvent> + Unpin + 'static,
+    Err: ErrorRenderer,
+{
+    type Error = Err::Container;
+    type Future = responder::Ready<Response>;
+
+    fn respond_to(self, _: &HttpRequest) -> Self::Future {
+        let body = SseBody {
+            stream: Some(self.stream),
+            keep_alive: self
+                .keep_alive
+                .map(|period| (period, Box::pin(sleep(period)))),
+        };
+
+        Response::Ok()
+            .header(CONTENT_TYPE, "text/event-stream")
+            .header(CACHE_CONTROL, "no-cache")
+            .body(Body::from_message(body))
+            .into()
+    }
+}
+
+/// Response body, encodes events and sends keep-alive comments
+struct SseBody<S> {
+    stream: Option<S>,
+    keep_alive: Option<(Duration, Pin<Box<Sleep>>)>,
+}
+
+impl<S> MessageBody for SseBody<S>
+where
+    S: Stream<Item = SseEvent> + Unpin,
+{
+    fn size(&self) -> BodySize {
+        BodySize::Stream
+    }
+
+    fn poll_next_chunk(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Box<dyn Error>>>> {
+        let stream = if let Some(ref mut stream) = self.stream {
+            stream
+        } else {
+            return Poll::Ready(None);
+        };
+
+        // combine all ready events
+        let mut buf = BytesMut::new();
+        let mut eof = false;
+        while buf.len() < MAX_CHUNK_SIZE {
+            match Pin::new(&mut *stream).poll_next(cx) {
+                Poll::Ready(Some(event)) => event.encode(&mut buf),
+                Poll::Ready(None) => {
+                    eof = true;
+                    break;
+                }
+                Poll::Pending => break,
+            }
+        }
+        if eof {
+            self.stream = None;
+        }
+
+        if let Some((period, ref mut delay)) = self.keep_alive {
+            if !buf.is_empty() {
+                delay.as_mut().reset(Instant::now() + period);
+            } else if self.stream.is_some() && delay.as_mut().poll(cx).is_ready() {
+                log::trace!("Send sse keep-alive comment");
+                buf.extend_from_slice(b":\n\n");
+                delay.as_mut().reset(Instant::now() + period);
+                // register timer
+                let _ = delay.as_mut().poll(cx);
+            }
+        }
+
+        if !buf.is_empty() {
+            Poll::Ready(Some(Ok(buf.freeze())))
+        } else if self.stream.is_none() {
+            Poll::Ready(None)
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+/// Value of `Last-Event-ID` request header.
+///
+/// Clients send id of the last received event on reconnect.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LastEventId(pub Option<String>);
+
+impl LastEventId {
+    /// Deconstruct to an inner value
+    pub fn into_inner(self) -> Option<String> {
+        self.0
+    }
+}
+
+impl fmt::Display for LastEventId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.0.as_deref().unwrap_or(""))
+    }
+}
+
+impl<Err: ErrorRenderer> FromRequest<Err> for LastEventId {
+    type Error = Err::Container;
+    type Future = Ready<Self, Self::Error>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let id = req
+            .headers()
+            .get(HeaderName::from_static("last-event-id"))
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string());
+        Ok(LastEventId(id)).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::channel::mpsc;
+    use crate::http::body::ResponseBody;
+    use crate::http::StatusCode;
+    use crate::util::{next, poll_fn};
+    use crate::web::test::{from_request, respond_to, TestRequest};
+
+    #[test]
+    fn test_encode() {
+        let encode = |ev: SseEvent| {
+            let mut buf = BytesMut::new();
+            ev.encode(&mut buf);
+            buf.freeze()
+        };
+
+        assert_eq!(encode(SseEvent::data("test")), "data: test\n\n");
+        assert_eq!(
+            encode(SseEvent::data("line1\r\nline2\n").event("up\ndate").id("1")),
+            "event: update\nid: 1\ndata: line1\ndata: line2\ndata:\n\n"
+        );
+        // lone cr is a line end, it could not inject fields
+        assert_eq!(
+            encode(SseEvent::data("a\rid: x\r\revent: y\r")),
+            "data: a\ndata: id: x\ndata:\ndata: event: y\ndata:\n\n"
+        );
+        assert_eq!(
+            encode(SseEvent::comment("ping").retry(Duration::from_secs(3))),
+            ": ping\nretry: 3000\n\n"
+        );
+    }
+
+    #[crate::rt_test]
+    async fn test_responder() {
+        let (tx, rx) = mpsc::channel();
+        tx.send(SseEvent::data("1")).unwrap();
+        tx.send(SseEvent::data("2")).unwrap();
+
+        let req = TestRequest::default().to_http_request();
+        let mut resp = respond_to(SseResponder::new(rx), &req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(CONTENT_TYPE).unwrap(),
+            "text/event-stream"
+        );
+        assert_eq!(resp.headers().get(CACHE_CONTROL).unwrap(), "no-cache");
+
+        // ready events are combined
+        let mut body = resp.take_body();
+        assert_eq!(body.size(), BodySize::Stream);
+        let chunk = next(&mut body).await.unwrap().unwrap();
+        assert_eq!(chunk, "data: 1\n\ndata: 2\n\n");
+
+        // nothing to send
+        let res = poll_fn(|cx| Poll::Ready(body.poll_next_chunk(cx).is_pending())).await;
+        assert!(res);
+
+        tx.send(SseEvent::data("3")).unwrap();
+        drop(tx);
+        let chunk = next(&mut body).await.unwrap().unwrap();
+        assert_eq!(chunk, "data: 3\n\n");
+        assert!(next(&mut body).await.is_none());
+    }
+
+    #[crate::rt_test]
+    async fn test_keep_alive() {
+        let (tx, rx) = mpsc::channel::<SseEvent>();
+
+        let req = TestRequest::default().to_http_request();
+        let mut resp = respond_to(
+            SseResponder::new(rx).keep_alive(Duration::from_millis(50)),
+            &req,
+        )
+        .await;
+        let mut body: ResponseBody<Body> = resp.take_body();
+
+        let chunk = next(&mut body).await.unwrap().unwrap();
+        assert_eq!(chunk, ":\n\n");
+        let chunk = next(&mut body).await.unwrap().unwrap();
+        assert_eq!(chunk, ":\n\n");
+
+        drop(tx);
+        assert!(next(&mut body).await.is_none());
+    }
+
+    #[crate::rt_test]
+    async fn test_last_event_id() {
+        let (req, mut pl) =
+            TestRequest::with_header("last-event-id", "10").to_http_parts();
+        let id = from_request::<LastEventId>(&req, &mut pl).await.unwrap();
+        assert_eq!(id.0.as_deref(), Some("10"));
+        assert_eq!(id.to_string(), "10");
+
+        let (req, mut pl) = TestRequest::default().to_http_parts();
+        let id = from_request::<LastEventId>(&req, &mut pl).await.unwrap();
+        assert_eq!(id.into_inner(), None);
+    }
+}
@@ -1,4 +1,4 @@
-use std::{ops::Deref, sync::Arc};
+use std::{fmt, future::Future, marker::PhantomData, ops::Deref, pin::Pin, sync::Arc};
 
 use crate::http::Payload;
 use crate::util::{Extensions, Ready};
@@ -11,6 +11,10 @@ pub(crate) trait DataFactory {
     fn create(&self, extensions: &mut Extensions) -> bool;
 }
 
+/// Async application data factory
+pub(crate) type FnDataFactory =
+    Box<dyn Fn() -> Pin<Box<dyn Future<Output = Result<Box<dyn DataFactory>, ()>>>>>;
+
 /// Application data.
 ///
 /// Application data is an arbitrary data attached to the app.
@@ -83,6 +87,73 @@ impl<T> Data<T> {
     }
 }
 
+impl<T: 'static> Data<T> {
+    /// Create async data initializer.
+    ///
+    /// Initializer is evaluated during application initialization, once per
+    /// worker. Initializer could be registered with `App::data_init()`
+    /// or `Scope::data_init()` methods.
+    ///
+    /// ```rust
+    /// use ntex::web::{self, App, HttpResponse, types::Data};
+    ///
+    /// struct DbPool;
+    ///
+    /// async fn index(pool: Data<DbPool>) -> HttpResponse {
+    ///     HttpResponse::Ok().into()
+    /// }
+    ///
+    /// let app = App::new().service(
+    ///     web::scope("/db")
+    ///         .data_init(Data::from_fn(|| async { Ok::<_, ()>(DbPool) }))
+    ///         .route("/index.html", web::get().to(index))
+    /// );
+    /// ```
+    pub fn from_fn<F, Out, E>(f: F) -> DataInit<T>
+    where
+        F: Fn() -> Out + 'static,
+        Out: Future<Output = Result<T, E>> + 'static,
+        E: fmt::Debug,
+    {
+        DataInit {
+            factory: Box::new(move || {
+                let fut = f();
+                Box::pin(async move {
+                    match fut.await {
+                        Err(e) => {
+                            log::error!("Cannot construct data instance: {:?}", e);
+                            Err(())
+                        }
+                        Ok(data) => {
+                            let data: Box<dyn DataFactory> = Box::new(Data::new(data));
+                            Ok(data)
+                        }
+                    }
+                })
+            }),
+            _t: PhantomData,
+        }
+    }
+}
+
+/// Async application data initializer, created by `Data::from_fn()` method.
+pub struct DataInit<T> {
+    factory: FnDataFactory,
+    _t: PhantomData<T>,
+}
+
+impl<T> DataInit<T> {
+    pub(crate) fn into_factory(self) -> FnDataFactory {
+        self.factory
+    }
+}
+
+impl<T> fmt::Debug for DataInit<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DataInit").finish()
+    }
+}
+
 impl<T> Deref for Data<T> {
     type Target = Arc<T>;
 
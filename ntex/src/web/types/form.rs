@@ -1,8 +1,10 @@
 //! Form extractor
-use std::{fmt, future::Future, ops, pin::Pin, task::Context, task::Poll};
+use std::{cell::RefCell, fmt, future::Future, ops, pin::Pin, sync::Arc};
+use std::{task::Context, task::Poll};
 
 use encoding_rs::{Encoding, UTF_8};
-use serde::{de::DeserializeOwned, Serialize};
+use serde::de::{self, value::MapDeserializer, DeserializeOwned};
+use serde::Serialize;
 
 #[cfg(feature = "compress")]
 use crate::http::encoding::Decoder;
@@ -13,6 +15,8 @@ use crate::web::error::{ErrorRenderer, UrlencodedError, WebResponseError};
 use crate::web::responder::{Ready, Responder};
 use crate::web::{FromRequest, HttpRequest};
 
+use super::urlencoded::{decode, Part};
+
 /// Form data helper (`application/x-www-form-urlencoded`)
 ///
 /// Can be use to extract url-encoded data from the request body,
@@ -105,15 +109,26 @@ where
 
     #[inline]
     fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
-        let limit = req
+        let (limit, ctype, err_handler) = req
             .app_data::<FormConfig>()
-            .map(|c| c.limit)
-            .unwrap_or(16384);
+            .map(|c| (c.limit, c.content_type.clone(), c.err_handler.clone()))
+            .unwrap_or((16384, None, None));
 
-        let fut = UrlEncoded::new(req, payload).limit(limit);
+        let req = req.clone();
+        let fut = UrlEncoded::new(&req, payload, ctype).limit(limit);
         Box::pin(async move {
             match fut.await {
-                Err(e) => Err(e),
+                Err(e) => {
+                    if let Some(err_handler) = err_handler {
+                        let response = (*err_handler)(&e, &req);
+                        Err(UrlencodedError::Response {
+                            error: Box::new(e),
+                            response: RefCell::new(Some(response)),
+                        })
+                    } else {
+                        Err(e)
+                    }
+                }
                 Ok(item) => Ok(Form(item)),
             }
         })
@@ -154,8 +169,10 @@ where
 
 /// Form extractor configuration
 ///
+/// Configuration could be set for app, scope or resource.
+///
 /// ```rust
-/// use ntex::web::{self, App, Error, FromRequest};
+/// use ntex::web::{self, App, Error, FromRequest, HttpResponse};
 ///
 /// #[derive(serde::Deserialize)]
 /// struct FormData {
@@ -173,15 +190,23 @@ where
 ///         web::resource("/index.html")
 ///             // change `Form` extractor configuration
 ///             .app_data(
-///                 web::types::FormConfig::default().limit(4097)
+///                 web::types::FormConfig::default()
+///                     .limit(4097)
+///                     .content_type(|mime| mime == mime::TEXT_PLAIN)
+///                     .error_handler(|err, _| {
+///                         HttpResponse::Conflict().body(err.to_string())
+///                     })
 ///             )
 ///             .route(web::get().to(index))
 ///     );
 /// }
 /// ```
-#[derive(Clone, Debug)]
+#[derive(Clone)]
 pub struct FormConfig {
     limit: usize,
+    content_type: Option<Arc<dyn Fn(mime::Mime) -> bool + Send + Sync>>,
+    err_handler:
+        Option<Arc<dyn Fn(&UrlencodedError, &HttpRequest) -> Response + Send + Sync>>,
 }
 
 impl FormConfig {
@@ -190,11 +215,45 @@ impl FormConfig {
         self.limit = limit;
         self
     }
+
+    /// Set predicate for allowed content types
+    ///
+    /// `application/x-www-form-urlencoded` is always allowed.
+    pub fn content_type<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(mime::Mime) -> bool + Send + Sync + 'static,
+    {
+        self.content_type = Some(Arc::new(predicate));
+        self
+    }
+
+    /// Set custom error handler
+    ///
+    /// Handler generates response for extraction errors.
+    pub fn error_handler<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&UrlencodedError, &HttpRequest) -> Response + Send + Sync + 'static,
+    {
+        self.err_handler = Some(Arc::new(f));
+        self
+    }
 }
 
 impl Default for FormConfig {
     fn default() -> Self {
-        FormConfig { limit: 16384 }
+        FormConfig {
+            limit: 16384,
+            content_type: None,
+            err_handler: None,
+        }
+    }
+}
+
+impl fmt::Debug for FormConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FormConfig")
+            .field("limit", &self.limit)
+            .finish()
     }
 }
 
@@ -204,6 +263,9 @@ impl Default for FormConfig {
 /// Return `UrlEncoded` future. Form can be deserialized to any type that
 /// implements `Deserialize` trait from *serde*.
 ///
+/// Pairs are decoded as soon as they are received, deserialization
+/// starts once the whole payload is read.
+///
 /// Returns error:
 ///
 /// * content type is not `application/x-www-form-urlencoded`
@@ -223,9 +285,20 @@ struct UrlEncoded<U> {
 
 impl<U> UrlEncoded<U> {
     /// Create a new future to URL encode a request
-    fn new(req: &HttpRequest, payload: &mut Payload) -> UrlEncoded<U> {
+    fn new(
+        req: &HttpRequest,
+        payload: &mut Payload,
+        ctype: Option<Arc<dyn Fn(mime::Mime) -> bool + Send + Sync>>,
+    ) -> UrlEncoded<U> {
         // check content type
-        if req.content_type().to_lowercase() != "application/x-www-form-urlencoded" {
+        let form = req
+            .content_type()
+            .eq_ignore_ascii_case("application/x-www-form-urlencoded")
+            || match (req.mime_type(), ctype) {
+                (Ok(Some(mime)), Some(predicate)) => predicate(mime),
+                _ => false,
+            };
+        if !form {
             return Self::err(UrlencodedError::ContentType);
         }
         let encoding = match req.encoding() {
@@ -307,36 +380,85 @@ where
         let mut stream = self.stream.take().unwrap();
 
         self.fut = Some(Box::pin(async move {
-            let mut body = BytesMut::with_capacity(8192);
+            let mut parser = PairsParser::new(encoding);
+            let mut size = 0;
 
             while let Some(item) = next(&mut stream).await {
                 let chunk = item?;
-                if (body.len() + chunk.len()) > limit {
-                    return Err(UrlencodedError::Overflow {
-                        size: body.len() + chunk.len(),
-                        limit,
-                    });
-                } else {
-                    body.extend_from_slice(&chunk);
+                size += chunk.len();
+                if size > limit {
+                    return Err(UrlencodedError::Overflow { size, limit });
                 }
+                parser.feed(&chunk)?;
             }
 
-            if encoding == UTF_8 {
-                serde_urlencoded::from_bytes::<U>(&body)
-                    .map_err(|_| UrlencodedError::Parse)
-            } else {
-                let body = encoding
-                    .decode_without_bom_handling_and_without_replacement(&body)
-                    .map(|s| s.into_owned())
-                    .ok_or(UrlencodedError::Parse)?;
-                serde_urlencoded::from_str::<U>(&body)
-                    .map_err(|_| UrlencodedError::Parse)
-            }
+            U::deserialize(MapDeserializer::new(parser.finish()?.into_iter())).map_err(
+                |e: de::value::Error| {
+                    log::debug!("Cannot deserialize urlencoded payload: {}", e);
+                    UrlencodedError::Parse
+                },
+            )
         }));
         self.poll(cx)
     }
 }
 
+/// Parser of urlencoded pairs
+struct PairsParser {
+    encoding: &'static Encoding,
+    buf: BytesMut,
+    pairs: Vec<(Part, Part)>,
+}
+
+impl PairsParser {
+    fn new(encoding: &'static Encoding) -> Self {
+        PairsParser {
+            encoding,
+            buf: BytesMut::new(),
+            pairs: Vec::new(),
+        }
+    }
+
+    /// Parse complete pairs, incomplete pair stays in the buffer
+    fn feed(&mut self, chunk: &[u8]) -> Result<(), UrlencodedError> {
+        self.buf.extend_from_slice(chunk);
+
+        while let Some(pos) = self.buf.iter().position(|b| *b == b'&') {
+            let pair = self.buf.split_to(pos + 1);
+            self.parse(&pair[..pos])?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<Vec<(Part, Part)>, UrlencodedError> {
+        let pair = self.buf.split();
+        self.parse(&pair)?;
+        Ok(self.pairs)
+    }
+
+    fn parse(&mut self, pair: &[u8]) -> Result<(), UrlencodedError> {
+        if !pair.is_empty() {
+            let (key, value) = match pair.iter().position(|b| *b == b'=') {
+                Some(pos) => (&pair[..pos], &pair[pos + 1..]),
+                None => (pair, &b""[..]),
+            };
+            let pair = (self.decode(key)?, self.decode(value)?);
+            self.pairs.push(pair);
+        }
+        Ok(())
+    }
+
+    fn decode(&self, s: &[u8]) -> Result<Part, UrlencodedError> {
+        match decode(s, self.encoding) {
+            Some(s) => Ok(Part(s)),
+            None => {
+                log::debug!("Urlencoded payload is not valid {}", self.encoding.name());
+                Err(UrlencodedError::Parse)
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use serde::{Deserialize, Serialize};
@@ -344,7 +466,10 @@ mod tests {
     use super::*;
     use crate::http::header::{HeaderValue, CONTENT_TYPE};
     use crate::util::Bytes;
-    use crate::web::test::{from_request, respond_to, TestRequest};
+    use crate::web::test::{
+        call_service, from_request, init_service, read_body, respond_to, TestRequest,
+    };
+    use crate::web::{self, App, DefaultError};
 
     #[derive(Deserialize, Serialize, Debug, PartialEq, derive_more::Display)]
     #[display(fmt = "{}", "hello")]
@@ -415,14 +540,14 @@ mod tests {
             TestRequest::with_header(CONTENT_TYPE, "application/x-www-form-urlencoded")
                 .header(CONTENT_LENGTH, "xxxx")
                 .to_http_parts();
-        let info = UrlEncoded::<Info>::new(&req, &mut pl).await;
+        let info = UrlEncoded::<Info>::new(&req, &mut pl, None).await;
         assert!(eq(info.err().unwrap(), UrlencodedError::UnknownLength));
 
         let (req, mut pl) =
             TestRequest::with_header(CONTENT_TYPE, "application/x-www-form-urlencoded")
                 .header(CONTENT_LENGTH, "1000000")
                 .to_http_parts();
-        let info = UrlEncoded::<Info>::new(&req, &mut pl).await;
+        let info = UrlEncoded::<Info>::new(&req, &mut pl, None).await;
         assert!(eq(
             info.err().unwrap(),
             UrlencodedError::Overflow { size: 0, limit: 0 }
@@ -431,7 +556,7 @@ mod tests {
         let (req, mut pl) = TestRequest::with_header(CONTENT_TYPE, "text/plain")
             .header(CONTENT_LENGTH, "10")
             .to_http_parts();
-        let info = UrlEncoded::<Info>::new(&req, &mut pl).await;
+        let info = UrlEncoded::<Info>::new(&req, &mut pl, None).await;
         assert!(eq(info.err().unwrap(), UrlencodedError::ContentType));
     }
 
@@ -443,7 +568,7 @@ mod tests {
                 .set_payload(Bytes::from_static(b"hello=world&counter=123"))
                 .to_http_parts();
 
-        let info = UrlEncoded::<Info>::new(&req, &mut pl).await.unwrap();
+        let info = UrlEncoded::<Info>::new(&req, &mut pl, None).await.unwrap();
         assert_eq!(
             info,
             Info {
@@ -460,7 +585,7 @@ mod tests {
         .set_payload(Bytes::from_static(b"hello=world&counter=123"))
         .to_http_parts();
 
-        let info = UrlEncoded::<Info>::new(&req, &mut pl).await.unwrap();
+        let info = UrlEncoded::<Info>::new(&req, &mut pl, None).await.unwrap();
         assert_eq!(
             info,
             Info {
@@ -477,7 +602,7 @@ mod tests {
         .set_payload(Bytes::from_static(b"hello=world&counter=123"))
         .to_http_parts();
 
-        let info = UrlEncoded::<Info>::new(&req, &mut pl).await.unwrap();
+        let info = UrlEncoded::<Info>::new(&req, &mut pl, None).await.unwrap();
         assert_eq!(
             info,
             Info {
@@ -487,6 +612,160 @@ mod tests {
         );
     }
 
+    #[derive(Deserialize, Debug, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    enum Kind {
+        First,
+        Second,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Params {
+        name: String,
+        kind: Kind,
+        enabled: bool,
+        ratio: f32,
+        id: Option<u32>,
+        missing: Option<u32>,
+    }
+
+    #[crate::rt_test]
+    async fn test_chunked() {
+        let body =
+            &b"name=hello+w%C3%B6rld%26&kind=second&&enabled=true&ratio=0.5&id=7"[..];
+        for size in &[1, 2, 5, body.len()] {
+            let chunks: Vec<_> = body
+                .chunks(*size)
+                .map(|c| Ok(Bytes::copy_from_slice(c)))
+                .collect();
+            let stream: crate::http::PayloadStream =
+                Box::pin(futures::stream::iter(chunks));
+            let (req, _) = TestRequest::with_header(
+                CONTENT_TYPE,
+                "application/x-www-form-urlencoded",
+            )
+            .to_http_parts();
+            let mut pl = Payload::from(stream);
+
+            let params = UrlEncoded::<Params>::new(&req, &mut pl, None)
+                .await
+                .unwrap();
+            assert_eq!(
+                params,
+                Params {
+                    name: "hello wörld&".to_string(),
+                    kind: Kind::Second,
+                    enabled: true,
+                    ratio: 0.5,
+                    id: Some(7),
+                    missing: None,
+                }
+            );
+        }
+
+        let (req, mut pl) =
+            TestRequest::with_header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+                .set_payload(Bytes::from_static(b"hello=world&counter=abc"))
+                .to_http_parts();
+        let info = UrlEncoded::<Info>::new(&req, &mut pl, None).await;
+        assert!(matches!(info, Err(UrlencodedError::Parse)));
+
+        let (req, mut pl) = TestRequest::with_header(
+            CONTENT_TYPE,
+            "application/x-www-form-urlencoded; charset=cp1251",
+        )
+        .set_payload(Bytes::from_static(b"hello=%EF%F0%E8%E2%E5%F2&counter=1"))
+        .to_http_parts();
+        let info = UrlEncoded::<Info>::new(&req, &mut pl, None).await.unwrap();
+        assert_eq!(info.hello, "привет");
+
+        // invalid utf-8 sequence
+        let (req, mut pl) =
+            TestRequest::with_header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+                .set_payload(Bytes::from_static(b"hello=%FF%FE&counter=1"))
+                .to_http_parts();
+        let info = UrlEncoded::<Info>::new(&req, &mut pl, None).await;
+        assert!(matches!(info, Err(UrlencodedError::Parse)));
+    }
+
+    #[crate::rt_test]
+    async fn test_config() {
+        let (req, mut pl) = TestRequest::with_header(CONTENT_TYPE, "text/plain")
+            .set_payload(Bytes::from_static(b"hello=world&counter=123"))
+            .data(FormConfig::default().content_type(|mime| mime == mime::TEXT_PLAIN))
+            .to_http_parts();
+        let Form(s) = from_request::<Form<Info>>(&req, &mut pl).await.unwrap();
+        assert_eq!(s.hello, "world");
+
+        let (req, mut pl) = TestRequest::with_header(CONTENT_TYPE, "text/html")
+            .set_payload(Bytes::from_static(b"hello=world&counter=123"))
+            .data(FormConfig::default().content_type(|mime| mime == mime::TEXT_PLAIN))
+            .to_http_parts();
+        let res = from_request::<Form<Info>>(&req, &mut pl).await;
+        assert!(eq(res.err().unwrap(), UrlencodedError::ContentType));
+
+        let (req, mut pl) =
+            TestRequest::with_header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+                .set_payload(Bytes::from_static(b"hello=world&counter=123"))
+                .data(FormConfig::default().limit(10).error_handler(|err, _| {
+                    Response::Conflict().body(format!("custom: {}", err))
+                }))
+                .to_http_parts();
+        let err = from_request::<Form<Info>>(&req, &mut pl)
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(
+            WebResponseError::<DefaultError>::status_code(&err),
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+        let resp = WebResponseError::<DefaultError>::error_response(&err, &req);
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+        assert!(resp
+            .body()
+            .get_ref()
+            .starts_with(b"custom: Urlencoded payload"));
+    }
+
+    #[crate::rt_test]
+    async fn test_scope_config() {
+        let srv = init_service(
+            App::new()
+                .app_data(FormConfig::default().limit(5))
+                .service(
+                    web::scope("/scope")
+                        .app_data(FormConfig::default().limit(1024))
+                        .route(
+                            "/",
+                            web::post()
+                                .to(|f: Form<Info>| async move { f.hello.clone() }),
+                        ),
+                )
+                .route(
+                    "/",
+                    web::post().to(|f: Form<Info>| async move { f.hello.clone() }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::post()
+            .uri("/scope/")
+            .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .set_payload(Bytes::from_static(b"hello=world&counter=123"))
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(read_body(resp).await, Bytes::from_static(b"world"));
+
+        let req = TestRequest::post()
+            .uri("/")
+            .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .set_payload(Bytes::from_static(b"hello=world&counter=123"))
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
     #[crate::rt_test]
     async fn test_responder() {
         let req = TestRequest::default().to_http_request();
@@ -0,0 +1,423 @@
+//! Typed header extractor
+use std::{fmt, ops, str::FromStr};
+
+use mime::Mime;
+
+use crate::http::header::{self, HeaderName, HeaderValue};
+use crate::http::Payload;
+use crate::util::Ready;
+use crate::web::error::{ErrorRenderer, HeaderError};
+use crate::web::extract::FromRequest;
+use crate::web::httprequest::HttpRequest;
+
+/// Typed http header.
+///
+/// Types that implement this trait could be extracted from request
+/// with `Header<T>` extractor.
+pub trait FromHeader: Sized {
+    /// Header name
+    fn name() -> HeaderName;
+
+    /// Parse header from all values of the header.
+    ///
+    /// Iterator contains at least one value. Returns `None` if header is malformed.
+    fn parse<'a, I>(values: I) -> Option<Self>
+    where
+        I: Iterator<Item = &'a HeaderValue>;
+}
+
+/// Extract typed header from the request.
+///
+/// Extraction fails with `HeaderError::Missing` if request does not contain
+/// the header and with `HeaderError::Invalid` if header is malformed,
+/// default error renderer responds with `400 Bad Request` in both cases.
+/// Use `Option<Header<T>>` for optional headers.
+///
+/// ```rust
+/// use ntex::web::{self, types::{Header, UserAgent}, HttpResponse};
+///
+/// async fn index(agent: Header<UserAgent>) -> HttpResponse {
+///     HttpResponse::Ok().body(format!("User agent: {}", agent.as_str()))
+/// }
+///
+/// fn main() {
+///     let app = web::App::new().service(
+///         web::resource("/").route(web::get().to(index))
+///     );
+/// }
+/// ```
+#[derive(Clone, PartialEq, Eq)]
+pub struct Header<T>(pub T);
+
+impl<T> Header<T> {
+    /// Deconstruct to an inner value
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> ops::Deref for Header<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> ops::DerefMut for Header<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Header<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<T: FromHeader, Err: ErrorRenderer> FromRequest<Err> for Header<T> {
+    type Error = HeaderError;
+    type Future = Ready<Self, Self::Error>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let name = T::name();
+        let mut values = req.headers().get_all(&name).peekable();
+        if values.peek().is_none() {
+            return Ready::Err(HeaderError::Missing(name));
+        }
+
+        if let Some(item) = T::parse(values) {
+            Ready::Ok(Header(item))
+        } else {
+            log::debug!(
+                "Failed during Header extractor parsing, header: {:?}, path: {:?}",
+                name,
+                req.path()
+            );
+            Ready::Err(HeaderError::Invalid(name))
+        }
+    }
+}
+
+/// Parse first header value as a string
+fn first_str<'a, I>(mut values: I) -> Option<&'a str>
+where
+    I: Iterator<Item = &'a HeaderValue>,
+{
+    values
+        .next()
+        .and_then(|val| val.to_str().ok())
+        .map(|s| s.trim())
+}
+
+/// `Authorization` header
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Authorization {
+    scheme: String,
+    credentials: String,
+}
+
+impl Authorization {
+    /// Authentication scheme, i.e. `Basic` or `Bearer`
+    pub fn scheme(&self) -> &str {
+        &self.scheme
+    }
+
+    /// Credentials
+    pub fn credentials(&self) -> &str {
+        &self.credentials
+    }
+
+    /// Token of `Bearer` authentication scheme
+    pub fn bearer(&self) -> Option<&str> {
+        if self.scheme.eq_ignore_ascii_case("bearer") {
+            Some(&self.credentials)
+        } else {
+            None
+        }
+    }
+
+    /// User id and password of `Basic` authentication scheme
+    pub fn basic(&self) -> Option<(String, Option<String>)> {
+        if !self.scheme.eq_ignore_ascii_case("basic") {
+            return None;
+        }
+        let decoded = base64::decode(&self.credentials).ok()?;
+        let decoded = String::from_utf8(decoded).ok()?;
+        let mut parts = decoded.splitn(2, ':');
+        let user = parts.next()?.to_string();
+        Some((user, parts.next().map(|s| s.to_string())))
+    }
+}
+
+impl FromHeader for Authorization {
+    fn name() -> HeaderName {
+        header::AUTHORIZATION
+    }
+
+    fn parse<'a, I>(values: I) -> Option<Self>
+    where
+        I: Iterator<Item = &'a HeaderValue>,
+    {
+        let mut parts = first_str(values)?.splitn(2, ' ');
+        let scheme = parts.next().filter(|s| !s.is_empty())?;
+        let credentials = parts.next().map(|s| s.trim()).unwrap_or("");
+        Some(Authorization {
+            scheme: scheme.to_string(),
+            credentials: credentials.to_string(),
+        })
+    }
+}
+
+/// `Content-Type` header
+#[derive(Clone, Debug, PartialEq)]
+pub struct ContentType(pub Mime);
+
+impl ops::Deref for ContentType {
+    type Target = Mime;
+
+    fn deref(&self) -> &Mime {
+        &self.0
+    }
+}
+
+impl FromHeader for ContentType {
+    fn name() -> HeaderName {
+        header::CONTENT_TYPE
+    }
+
+    fn parse<'a, I>(values: I) -> Option<Self>
+    where
+        I: Iterator<Item = &'a HeaderValue>,
+    {
+        first_str(values)?.parse().ok().map(ContentType)
+    }
+}
+
+/// `Content-Length` header
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ContentLength(pub u64);
+
+impl FromHeader for ContentLength {
+    fn name() -> HeaderName {
+        header::CONTENT_LENGTH
+    }
+
+    fn parse<'a, I>(values: I) -> Option<Self>
+    where
+        I: Iterator<Item = &'a HeaderValue>,
+    {
+        u64::from_str(first_str(values)?).ok().map(ContentLength)
+    }
+}
+
+/// `User-Agent` header
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UserAgent(pub String);
+
+impl UserAgent {
+    /// User agent as a string
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromHeader for UserAgent {
+    fn name() -> HeaderName {
+        header::USER_AGENT
+    }
+
+    fn parse<'a, I>(values: I) -> Option<Self>
+    where
+        I: Iterator<Item = &'a HeaderValue>,
+    {
+        first_str(values).map(|s| UserAgent(s.to_string()))
+    }
+}
+
+/// `If-None-Match` header
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum IfNoneMatch {
+    /// `*`, matches any entity tag
+    Any,
+    /// List of entity tags, including quotes and weak `W/` prefix
+    Tags(Vec<String>),
+}
+
+impl IfNoneMatch {
+    /// Check if entity tag matches the header, uses weak comparison
+    pub fn matches(&self, etag: &str) -> bool {
+        match self {
+            IfNoneMatch::Any => true,
+            IfNoneMatch::Tags(ref tags) => {
+                let etag = etag.trim_start_matches("W/");
+                tags.iter().any(|tag| tag.trim_start_matches("W/") == etag)
+            }
+        }
+    }
+}
+
+impl FromHeader for IfNoneMatch {
+    fn name() -> HeaderName {
+        header::IF_NONE_MATCH
+    }
+
+    fn parse<'a, I>(values: I) -> Option<Self>
+    where
+        I: Iterator<Item = &'a HeaderValue>,
+    {
+        let mut tags = Vec::new();
+        for val in values {
+            for tag in val.to_str().ok()?.split(',') {
+                let tag = tag.trim();
+                if tag == "*" {
+                    return Some(IfNoneMatch::Any);
+                }
+                let opaque = tag.trim_start_matches("W/");
+                if opaque.len() < 2 || !opaque.starts_with('"') || !opaque.ends_with('"')
+                {
+                    return None;
+                }
+                tags.push(tag.to_string());
+            }
+        }
+        Some(IfNoneMatch::Tags(tags))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::web::test::{call_service, init_service, TestRequest};
+    use crate::web::{self, App, HttpResponse};
+
+    async fn extract<T: FromHeader>(req: TestRequest) -> Result<Header<T>, HeaderError> {
+        let (req, mut pl) = req.to_http_parts();
+        <Header<T> as FromRequest<web::DefaultError>>::from_request(&req, &mut pl).await
+    }
+
+    #[crate::rt_test]
+    async fn test_header() {
+        let res = extract::<UserAgent>(TestRequest::default()).await;
+        assert_eq!(res, Err(HeaderError::Missing(header::USER_AGENT)));
+
+        let agent = extract::<UserAgent>(TestRequest::with_header(
+            header::USER_AGENT,
+            "ntex/0.4",
+        ))
+        .await
+        .unwrap();
+        assert_eq!(agent.as_str(), "ntex/0.4");
+
+        let ct = extract::<ContentType>(TestRequest::with_header(
+            header::CONTENT_TYPE,
+            "application/json",
+        ))
+        .await
+        .unwrap();
+        assert_eq!(ct.into_inner().0, mime::APPLICATION_JSON);
+        let res = extract::<ContentType>(TestRequest::with_header(
+            header::CONTENT_TYPE,
+            "json",
+        ))
+        .await;
+        assert_eq!(res, Err(HeaderError::Invalid(header::CONTENT_TYPE)));
+
+        let len = extract::<ContentLength>(TestRequest::with_header(
+            header::CONTENT_LENGTH,
+            "10",
+        ))
+        .await
+        .unwrap();
+        assert_eq!(len.into_inner(), ContentLength(10));
+    }
+
+    #[crate::rt_test]
+    async fn test_authorization() {
+        let auth = extract::<Authorization>(TestRequest::with_header(
+            header::AUTHORIZATION,
+            "Basic dXNlcjpwYXNz",
+        ))
+        .await
+        .unwrap();
+        assert_eq!(auth.scheme(), "Basic");
+        assert_eq!(
+            auth.basic(),
+            Some(("user".to_string(), Some("pass".to_string())))
+        );
+        assert!(auth.bearer().is_none());
+
+        let auth = extract::<Authorization>(TestRequest::with_header(
+            header::AUTHORIZATION,
+            "Bearer token",
+        ))
+        .await
+        .unwrap();
+        assert_eq!(auth.bearer(), Some("token"));
+        assert_eq!(auth.credentials(), "token");
+        assert!(auth.basic().is_none());
+
+        let res = extract::<Authorization>(TestRequest::with_header(
+            header::AUTHORIZATION,
+            "",
+        ))
+        .await;
+        assert_eq!(res, Err(HeaderError::Invalid(header::AUTHORIZATION)));
+    }
+
+    #[crate::rt_test]
+    async fn test_if_none_match() {
+        let inm = extract::<IfNoneMatch>(
+            TestRequest::default()
+                .header(header::IF_NONE_MATCH, "\"xyzzy\", W/\"r2d2xxxx\"")
+                .header(header::IF_NONE_MATCH, "\"c3piozzzz\""),
+        )
+        .await
+        .unwrap();
+        if let IfNoneMatch::Tags(ref tags) = inm.0 {
+            assert_eq!(tags.len(), 3);
+            assert!(tags.contains(&"W/\"r2d2xxxx\"".to_string()));
+        } else {
+            panic!()
+        }
+        assert!(inm.matches("\"c3piozzzz\""));
+        assert!(inm.matches("\"r2d2xxxx\""));
+        assert!(inm.matches("W/\"xyzzy\""));
+        assert!(!inm.matches("\"other\""));
+
+        let inm =
+            extract::<IfNoneMatch>(TestRequest::with_header(header::IF_NONE_MATCH, "*"))
+                .await
+                .unwrap();
+        assert_eq!(inm.0, IfNoneMatch::Any);
+        assert!(inm.matches("\"other\""));
+
+        let res = extract::<IfNoneMatch>(TestRequest::with_header(
+            header::IF_NONE_MATCH,
+            "xyzzy",
+        ))
+        .await;
+        assert_eq!(res, Err(HeaderError::Invalid(header::IF_NONE_MATCH)));
+    }
+
+    #[crate::rt_test]
+    async fn test_header_service() {
+        let srv = init_service(App::new().service(web::resource("/").to(
+            |auth: Header<Authorization>| async move {
+                HttpResponse::Ok().body(auth.credentials().to_string())
+            },
+        )))
+        .await;
+
+        let res = call_service(&srv, TestRequest::default().to_request()).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        let req =
+            TestRequest::with_header(header::AUTHORIZATION, "Bearer token").to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+}
@@ -1,5 +1,6 @@
 //! Json extractor/responder
-use std::{fmt, future::Future, ops, pin::Pin, sync::Arc, task::Context, task::Poll};
+use std::{cell::RefCell, fmt, future::Future, ops, pin::Pin, sync::Arc};
+use std::{task::Context, task::Poll};
 
 use serde::{de::DeserializeOwned, Serialize};
 
@@ -169,10 +170,10 @@ where
     #[inline]
     fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
         let req2 = req.clone();
-        let (limit, ctype) = req
+        let (limit, ctype, err_handler) = req
             .app_data::<JsonConfig>()
-            .map(|c| (c.limit, c.content_type.clone()))
-            .unwrap_or((32768, None));
+            .map(|c| (c.limit, c.content_type.clone(), c.err_handler.clone()))
+            .unwrap_or((32768, None, None));
 
         let fut = JsonBody::new(req, payload, ctype).limit(limit);
         Box::pin(async move {
@@ -183,7 +184,15 @@ where
                          Request path: {}",
                         req2.path()
                     );
-                    Err(e)
+                    if let Some(err_handler) = err_handler {
+                        let response = (*err_handler)(&e, &req2);
+                        Err(JsonPayloadError::Response {
+                            error: Box::new(e),
+                            response: RefCell::new(Some(response)),
+                        })
+                    } else {
+                        Err(e)
+                    }
                 }
                 Ok(data) => Ok(Json(data)),
             }
@@ -193,6 +202,8 @@ where
 
 /// Json extractor configuration
 ///
+/// Configuration could be set for app, scope or resource.
+///
 /// ```rust
 /// use ntex::http::error;
 /// use ntex::web::{self, App, FromRequest, HttpResponse};
@@ -217,6 +228,9 @@ where
 ///                    .content_type(|mime| {  // <- accept text/plain content type
 ///                        mime.type_() == mime::TEXT && mime.subtype() == mime::PLAIN
 ///                    })
+///                    .error_handler(|err, _| {  // <- custom error response
+///                        HttpResponse::Conflict().body(err.to_string())
+///                    })
 ///             )
 ///             .route(web::post().to(index))
 ///     );
@@ -226,6 +240,8 @@ where
 pub struct JsonConfig {
     limit: usize,
     content_type: Option<Arc<dyn Fn(mime::Mime) -> bool + Send + Sync>>,
+    err_handler:
+        Option<Arc<dyn Fn(&JsonPayloadError, &HttpRequest) -> Response + Send + Sync>>,
 }
 
 impl JsonConfig {
@@ -243,6 +259,17 @@ impl JsonConfig {
         self.content_type = Some(Arc::new(predicate));
         self
     }
+
+    /// Set custom error handler
+    ///
+    /// Handler generates response for extraction errors.
+    pub fn error_handler<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&JsonPayloadError, &HttpRequest) -> Response + Send + Sync + 'static,
+    {
+        self.err_handler = Some(Arc::new(f));
+        self
+    }
 }
 
 impl Default for JsonConfig {
@@ -250,6 +277,7 @@ impl Default for JsonConfig {
         JsonConfig {
             limit: 32768,
             content_type: None,
+            err_handler: None,
         }
     }
 }
@@ -374,7 +402,10 @@ mod tests {
     use super::*;
     use crate::http::header;
     use crate::util::Bytes;
-    use crate::web::test::{from_request, respond_to, TestRequest};
+    use crate::web::test::{
+        call_service, from_request, init_service, read_body, respond_to, TestRequest,
+    };
+    use crate::web::{self, App, DefaultError};
 
     #[derive(
         serde::Serialize, serde::Deserialize, PartialEq, Debug, derive_more::Display,
@@ -575,4 +606,83 @@ mod tests {
         let s = from_request::<Json<MyObject>>(&req, &mut pl).await;
         assert!(s.is_err())
     }
+
+    #[crate::rt_test]
+    async fn test_error_handler() {
+        let (req, mut pl) = TestRequest::with_header(
+            header::CONTENT_TYPE,
+            header::HeaderValue::from_static("application/json"),
+        )
+        .set_payload(Bytes::from_static(b"{\"name\": 1}"))
+        .data(JsonConfig::default().error_handler(|err, _| {
+            let msg = match err {
+                JsonPayloadError::Deserialize(_) => "invalid object",
+                _ => "error",
+            };
+            Response::UnprocessableEntity().body(msg)
+        }))
+        .to_http_parts();
+
+        let err = from_request::<Json<MyObject>>(&req, &mut pl)
+            .await
+            .err()
+            .unwrap();
+        assert!(format!("{}", err).contains("Json deserialize error"));
+        assert_eq!(
+            WebResponseError::<DefaultError>::status_code(&err),
+            StatusCode::BAD_REQUEST
+        );
+        let resp = WebResponseError::<DefaultError>::error_response(&err, &req);
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(resp.body().get_ref(), b"invalid object");
+    }
+
+    #[crate::rt_test]
+    async fn test_scope_config() {
+        let srv = init_service(
+            App::new()
+                .app_data(JsonConfig::default().limit(5))
+                .service(
+                    web::scope("/scope")
+                        .app_data(JsonConfig::default().error_handler(|_, _| {
+                            Response::UnprocessableEntity().finish()
+                        }))
+                        .route(
+                            "/",
+                            web::post()
+                                .to(|j: Json<MyObject>| async move { j.name.clone() }),
+                        ),
+                )
+                .route(
+                    "/",
+                    web::post().to(|j: Json<MyObject>| async move { j.name.clone() }),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::post()
+            .uri("/scope/")
+            .header(header::CONTENT_TYPE, "application/json")
+            .set_payload(Bytes::from_static(b"{\"name\": \"test\"}"))
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(read_body(resp).await, Bytes::from_static(b"test"));
+
+        let req = TestRequest::post()
+            .uri("/scope/")
+            .header(header::CONTENT_TYPE, "application/json")
+            .set_payload(Bytes::from_static(b"{\"name\": 1}"))
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let req = TestRequest::post()
+            .uri("/")
+            .header(header::CONTENT_TYPE, "application/json")
+            .set_payload(Bytes::from_static(b"{\"name\": \"test\"}"))
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
 }
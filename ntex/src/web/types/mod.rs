@@ -2,14 +2,32 @@
 
 pub(in crate::web) mod data;
 pub(in crate::web) mod form;
+mod header;
 pub(in crate::web) mod json;
+mod multipart;
+mod negotiate;
 mod path;
 pub(in crate::web) mod payload;
+mod peer_cert;
 mod query;
+mod urlencoded;
 
-pub use self::data::Data;
+pub use self::data::{Data, DataInit};
 pub use self::form::{Form, FormConfig};
+pub use self::header::{
+    Authorization, ContentLength, ContentType, FromHeader, Header, IfNoneMatch,
+    UserAgent,
+};
 pub use self::json::{Json, JsonConfig};
+pub use self::multipart::{Field, Multipart, MultipartConfig};
+#[cfg(feature = "cbor")]
+pub use self::negotiate::CborFormat;
+#[cfg(feature = "msgpack")]
+pub use self::negotiate::MsgPackFormat;
+pub use self::negotiate::{
+    DefaultFormats, Format, Formats, JsonFormat, Negotiate, NegotiateConfig,
+};
 pub use self::path::Path;
-pub use self::payload::{Payload, PayloadConfig};
-pub use self::query::Query;
+pub use self::payload::{Payload, PayloadConfig, PayloadStream};
+pub use self::peer_cert::PeerCert;
+pub use self::query::{Query, QueryConfig};
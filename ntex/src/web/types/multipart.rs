@@ -0,0 +1,742 @@
+//! Multipart payload extractor
+use std::{cell::RefCell, fmt, pin::Pin, rc::Rc, task::Context, task::Poll};
+
+use mime::Mime;
+
+#[cfg(feature = "compress")]
+use crate::http::encoding::Decoder;
+use crate::http::header::{self, HeaderMap, HeaderName, HeaderValue};
+use crate::http::{HttpMessage, Payload, PayloadStream};
+use crate::util::{Bytes, BytesMut, Ready};
+use crate::web::error::{ErrorRenderer, MultipartError};
+use crate::web::{FromRequest, HttpRequest};
+use crate::Stream;
+
+/// Max size of the field headers
+const MAX_HEADERS_SIZE: usize = 8192;
+
+/// Max number of the field headers
+const MAX_HEADERS: usize = 32;
+
+/// Multipart payload extractor (`multipart/form-data`)
+///
+/// Multipart is a stream of fields, each field is a stream of bytes.
+/// Field data is not buffered, so large file uploads could be handled
+/// chunk by chunk. Unread data of the previous field is skipped when
+/// next field is requested.
+///
+/// [**MultipartConfig**](struct.MultipartConfig.html) allows to configure
+/// size limits for the fields.
+///
+/// ## Example
+///
+/// ```rust
+/// use ntex::util::next;
+/// use ntex::web::{self, error::MultipartError, types::Multipart, App};
+///
+/// async fn upload(mut form: Multipart) -> Result<String, MultipartError> {
+///     let mut size = 0;
+///     while let Some(field) = next(&mut form).await {
+///         let mut field = field?;
+///         println!("field: {:?}, type: {}", field.name(), field.content_type());
+///
+///         while let Some(chunk) = next(&mut field).await {
+///             size += chunk?.len();
+///         }
+///     }
+///     Ok(format!("Uploaded {} bytes", size))
+/// }
+///
+/// fn main() {
+///     let app = App::new().service(
+///         web::resource("/upload")
+///             .app_data(web::types::MultipartConfig::default().limit(1_048_576))
+///             .route(web::post().to(upload))
+///     );
+/// }
+/// ```
+pub struct Multipart {
+    inner: Rc<RefCell<Inner>>,
+}
+
+impl Multipart {
+    fn new(
+        req: &HttpRequest,
+        payload: &mut Payload,
+        config: MultipartConfig,
+    ) -> Result<Multipart, MultipartError> {
+        let mt = match req.mime_type() {
+            Ok(Some(mt)) if mt.type_() == mime::MULTIPART => mt,
+            _ => return Err(MultipartError::ContentType),
+        };
+        let boundary = match mt.get_param(mime::BOUNDARY) {
+            Some(boundary) if !boundary.as_str().is_empty() => boundary.as_str(),
+            _ => return Err(MultipartError::Boundary),
+        };
+
+        #[cfg(feature = "compress")]
+        let stream: PayloadStream =
+            Box::pin(Decoder::from_headers(payload.take(), req.headers()));
+        #[cfg(not(feature = "compress"))]
+        let stream: PayloadStream = Box::pin(payload.take());
+
+        Ok(Multipart {
+            inner: Rc::new(RefCell::new(Inner {
+                stream,
+                config,
+                boundary: Bytes::from(format!("\r\n--{}", boundary)),
+                // first boundary is not required to be preceded by crlf
+                buf: BytesMut::from(&b"\r\n"[..]),
+                state: State::Preamble,
+                field: 0,
+            })),
+        })
+    }
+}
+
+impl<Err: ErrorRenderer> FromRequest<Err> for Multipart {
+    type Error = MultipartError;
+    type Future = Ready<Multipart, MultipartError>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let config = req
+            .app_data::<MultipartConfig>()
+            .cloned()
+            .unwrap_or_default();
+        Multipart::new(req, payload, config).into()
+    }
+}
+
+impl Stream for Multipart {
+    type Item = Result<Field, MultipartError>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let mut inner = self.inner.borrow_mut();
+
+        match inner.poll_field(cx) {
+            Poll::Ready(Some(Ok(headers))) => {
+                let (name, filename) = headers
+                    .get(&header::CONTENT_DISPOSITION)
+                    .and_then(|v| v.to_str().ok())
+                    .map(parse_disposition)
+                    .unwrap_or((None, None));
+                let content_type = headers
+                    .get(&header::CONTENT_TYPE)
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(mime::TEXT_PLAIN);
+                let limit = inner.config.get_limit(name.as_deref());
+
+                Poll::Ready(Some(Ok(Field {
+                    name,
+                    filename: filename.as_deref().and_then(sanitize_filename),
+                    content_type,
+                    headers,
+                    limit,
+                    id: inner.field,
+                    size: 0,
+                    inner: self.inner.clone(),
+                })))
+            }
+            Poll::Ready(Some(Err(e))) => Poll::Ready(Some(Err(e))),
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl fmt::Debug for Multipart {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Multipart")
+            .field("state", &self.inner.borrow().state)
+            .finish()
+    }
+}
+
+/// Multipart field
+///
+/// Field is a stream of data chunks. Field stream ends when next field
+/// is requested from `Multipart`.
+pub struct Field {
+    id: usize,
+    name: Option<String>,
+    filename: Option<String>,
+    content_type: Mime,
+    headers: HeaderMap,
+    limit: usize,
+    size: usize,
+    inner: Rc<RefCell<Inner>>,
+}
+
+impl Field {
+    /// Field name from `Content-Disposition` header
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// File name from `Content-Disposition` header
+    ///
+    /// File name is sanitized, directory components and control characters
+    /// are removed, names like `..` are ignored. It is still client supplied
+    /// value and must not be trusted. Original value is available via
+    /// `Content-Disposition` header.
+    pub fn filename(&self) -> Option<&str> {
+        self.filename.as_deref()
+    }
+
+    /// Field content type, `text/plain` if content type is not specified
+    pub fn content_type(&self) -> &Mime {
+        &self.content_type
+    }
+
+    /// Field headers
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+}
+
+impl Stream for Field {
+    type Item = Result<Bytes, MultipartError>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let mut inner = this.inner.borrow_mut();
+        if inner.field != this.id || inner.state != State::Body {
+            return Poll::Ready(None);
+        }
+
+        match inner.poll_chunk(cx) {
+            Poll::Ready(Some(Ok(chunk))) => {
+                this.size += chunk.len();
+                if this.size > this.limit {
+                    inner.state = State::Eof;
+                    Poll::Ready(Some(Err(MultipartError::Overflow {
+                        name: this.name.clone().unwrap_or_default(),
+                        limit: this.limit,
+                    })))
+                } else {
+                    Poll::Ready(Some(Ok(chunk)))
+                }
+            }
+            res => res,
+        }
+    }
+}
+
+impl fmt::Debug for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Field")
+            .field("name", &self.name)
+            .field("filename", &self.filename)
+            .field("content_type", &self.content_type)
+            .field("headers", &self.headers)
+            .finish()
+    }
+}
+
+/// Multipart extractor configuration
+///
+/// ```rust
+/// use ntex::web::{self, App};
+///
+/// let app = App::new().service(
+///     web::resource("/upload")
+///         .app_data(
+///             web::types::MultipartConfig::default()
+///                 .limit(4096)
+///                 .field_limit("avatar", 1_048_576)
+///                 .max_fields(16)
+///         )
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct MultipartConfig {
+    limit: usize,
+    max_fields: usize,
+    fields: Vec<(String, usize)>,
+}
+
+impl MultipartConfig {
+    /// Change max size of a field. By default max size is 256Kb
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Set max size of a field with specific name.
+    pub fn field_limit<T: Into<String>>(mut self, name: T, limit: usize) -> Self {
+        self.fields.push((name.into(), limit));
+        self
+    }
+
+    /// Set max number of fields. By default max number of fields is 64
+    pub fn max_fields(mut self, max: usize) -> Self {
+        self.max_fields = max;
+        self
+    }
+
+    fn get_limit(&self, name: Option<&str>) -> usize {
+        name.and_then(|name| {
+            self.fields
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, limit)| *limit)
+        })
+        .unwrap_or(self.limit)
+    }
+}
+
+impl Default for MultipartConfig {
+    fn default() -> Self {
+        MultipartConfig {
+            limit: 262_144,
+            max_fields: 64,
+            fields: Vec::new(),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum State {
+    /// Data before first boundary
+    Preamble,
+    /// Rest of the boundary line
+    Boundary,
+    /// Field headers
+    Headers,
+    /// Field data
+    Body,
+    /// Closing boundary is found or error occured
+    Eof,
+}
+
+struct Inner {
+    stream: PayloadStream,
+    config: MultipartConfig,
+    boundary: Bytes,
+    buf: BytesMut,
+    state: State,
+    field: usize,
+}
+
+impl Inner {
+    /// Read more data to the buffer
+    fn poll_payload(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), MultipartError>> {
+        match Pin::new(&mut self.stream).poll_next(cx) {
+            Poll::Ready(Some(Ok(chunk))) => {
+                self.buf.extend_from_slice(&chunk);
+                Poll::Ready(Ok(()))
+            }
+            Poll::Ready(Some(Err(e))) => Poll::Ready(Err(e.into())),
+            Poll::Ready(None) => Poll::Ready(Err(MultipartError::Incomplete)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+
+    /// Read field data, returns `None` at the end of the field
+    fn poll_chunk(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, MultipartError>>> {
+        loop {
+            if let Some(pos) = find(&self.buf, &self.boundary) {
+                if pos == 0 {
+                    let _ = self.buf.split_to(self.boundary.len());
+                    self.state = State::Boundary;
+                    return Poll::Ready(None);
+                }
+                return Poll::Ready(Some(Ok(self.buf.split_to(pos).freeze())));
+            } else if self.buf.len() > self.boundary.len() {
+                // tail of the buffer could contain part of the boundary
+                let len = self.buf.len() - self.boundary.len();
+                return Poll::Ready(Some(Ok(self.buf.split_to(len).freeze())));
+            }
+
+            match self.poll_payload(cx) {
+                Poll::Ready(Ok(_)) => (),
+                Poll::Ready(Err(e)) => {
+                    self.state = State::Eof;
+                    return Poll::Ready(Some(Err(e)));
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+
+    /// Skip unread data and parse headers of the next field
+    fn poll_field(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<HeaderMap, MultipartError>>> {
+        loop {
+            match self.state {
+                State::Eof => return Poll::Ready(None),
+                State::Body => match self.poll_chunk(cx) {
+                    Poll::Ready(Some(Ok(_))) | Poll::Ready(None) => continue,
+                    Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e))),
+                    Poll::Pending => return Poll::Pending,
+                },
+                State::Preamble => {
+                    if let Some(pos) = find(&self.buf, &self.boundary) {
+                        let _ = self.buf.split_to(pos + self.boundary.len());
+                        self.state = State::Boundary;
+                        continue;
+                    } else if self.buf.len() > self.boundary.len() {
+                        let len = self.buf.len() - self.boundary.len();
+                        let _ = self.buf.split_to(len);
+                    }
+                }
+                State::Boundary => {
+                    if self.buf.len() >= 2 && &self.buf[..2] == b"--" {
+                        log::trace!("Closing multipart boundary is found");
+                        self.buf.clear();
+                        self.state = State::Eof;
+                        return Poll::Ready(None);
+                    }
+                    if let Some(pos) = find(&self.buf, b"\r\n") {
+                        // transport padding
+                        if self.buf[..pos].iter().all(|c| *c == b' ' || *c == b'\t') {
+                            let _ = self.buf.split_to(pos + 2);
+                            self.state = State::Headers;
+                            continue;
+                        }
+                        return self.fail(MultipartError::Boundary);
+                    } else if self.buf.len() > MAX_HEADERS_SIZE {
+                        return self.fail(MultipartError::Boundary);
+                    }
+                }
+                State::Headers => {
+                    let end = if self.buf.starts_with(b"\r\n") {
+                        Some(2)
+                    } else {
+                        find(&self.buf, b"\r\n\r\n").map(|pos| pos + 4)
+                    };
+
+                    if let Some(end) = end {
+                        let headers = match parse_headers(&self.buf[..end]) {
+                            Some(headers) => headers,
+                            None => return self.fail(MultipartError::Headers),
+                        };
+                        let _ = self.buf.split_to(end);
+                        self.state = State::Body;
+                        self.field += 1;
+                        if self.field > self.config.max_fields {
+                            return self.fail(MultipartError::TooManyFields(
+                                self.config.max_fields,
+                            ));
+                        }
+                        return Poll::Ready(Some(Ok(headers)));
+                    } else if self.buf.len() > MAX_HEADERS_SIZE {
+                        return self.fail(MultipartError::Headers);
+                    }
+                }
+            }
+
+            match self.poll_payload(cx) {
+                Poll::Ready(Ok(_)) => (),
+                Poll::Ready(Err(e)) => return self.fail(e),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+
+    fn fail<T>(
+        &mut self,
+        err: MultipartError,
+    ) -> Poll<Option<Result<T, MultipartError>>> {
+        self.state = State::Eof;
+        Poll::Ready(Some(Err(err)))
+    }
+}
+
+fn find(buf: &[u8], pat: &[u8]) -> Option<usize> {
+    buf.windows(pat.len()).position(|w| w == pat)
+}
+
+fn parse_headers(buf: &[u8]) -> Option<HeaderMap> {
+    let mut parsed = [httparse::EMPTY_HEADER; MAX_HEADERS];
+    match httparse::parse_headers(buf, &mut parsed) {
+        Ok(httparse::Status::Complete((_, parsed))) => {
+            let mut headers = HeaderMap::new();
+            for h in parsed {
+                let name = HeaderName::from_bytes(h.name.as_bytes()).ok()?;
+                let value = HeaderValue::from_bytes(h.value).ok()?;
+                headers.append(name, value);
+            }
+            Some(headers)
+        }
+        _ => None,
+    }
+}
+
+/// Strip directory components and control characters from client file name
+fn sanitize_filename(name: &str) -> Option<String> {
+    let name = name.rsplit(&['/', '\\'][..]).next().unwrap_or(name);
+    let name: String = name.chars().filter(|c| !c.is_control()).collect();
+    let name = name.trim();
+    if name.is_empty() || name == "." || name == ".." {
+        None
+    } else {
+        Some(name.to_owned())
+    }
+}
+
+/// Parse `name` and `filename` params of the `Content-Disposition` header
+fn parse_disposition(val: &str) -> (Option<String>, Option<String>) {
+    let mut name = None;
+    let mut filename = None;
+    let mut rest = match val.find(';') {
+        Some(pos) => &val[pos + 1..],
+        None => return (name, filename),
+    };
+
+    while let Some(pos) = rest.find('=') {
+        let key = rest[..pos].trim();
+        rest = rest[pos + 1..].trim_start();
+
+        let value = if let Some(quoted) = rest.strip_prefix('"') {
+            let mut value = String::new();
+            let mut escaped = false;
+            let mut end = quoted.len();
+            for (idx, ch) in quoted.char_indices() {
+                if escaped {
+                    value.push(ch);
+                    escaped = false;
+                } else if ch == '\\' {
+                    escaped = true;
+                } else if ch == '"' {
+                    end = idx + 1;
+                    break;
+                } else {
+                    value.push(ch);
+                }
+            }
+            rest = &quoted[end..];
+            value
+        } else {
+            let end = rest.find(';').unwrap_or_else(|| rest.len());
+            let value = rest[..end].trim().to_owned();
+            rest = &rest[end..];
+            value
+        };
+
+        if key.eq_ignore_ascii_case("name") {
+            name = Some(value);
+        } else if key.eq_ignore_ascii_case("filename") {
+            filename = Some(value);
+        }
+
+        rest = match rest.find(';') {
+            Some(pos) => &rest[pos + 1..],
+            None => break,
+        };
+    }
+
+    (name, filename)
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::stream;
+
+    use super::*;
+    use crate::util::next;
+    use crate::web::test::{from_request, TestRequest};
+
+    const BODY: &[u8] = b"preamble\r\n\
+        --abbc761f78ff4d7cb7573b5a23f96ef0\r\n\
+        Content-Disposition: form-data; name=\"text\"\r\n\
+        \r\n\
+        test\r\n\
+        --abbc761f78ff4d7cb7573b5a23f96ef0\r\n\
+        Content-Disposition: form-data; name=\"file\"; filename=\"fn \\\"1\\\".txt\"\r\n\
+        Content-Type: application/octet-stream\r\n\
+        \r\n\
+        data\r\n--abbc\r\n\
+        --abbc761f78ff4d7cb7573b5a23f96ef0--\r\n\
+        epilogue";
+
+    fn request(chunk: usize) -> (HttpRequest, Payload) {
+        let chunks: Vec<_> = BODY
+            .chunks(chunk)
+            .map(|c| Ok(Bytes::copy_from_slice(c)))
+            .collect();
+        let (req, _) = TestRequest::with_header(
+            header::CONTENT_TYPE,
+            "multipart/form-data; boundary=\"abbc761f78ff4d7cb7573b5a23f96ef0\"",
+        )
+        .to_http_parts();
+        let payload: PayloadStream = Box::pin(stream::iter(chunks));
+        (req, payload.into())
+    }
+
+    #[crate::rt_test]
+    async fn test_multipart() {
+        for chunk in &[1, 3, 7, 64, BODY.len()] {
+            let (req, mut pl) = request(*chunk);
+            let mut form = from_request::<Multipart>(&req, &mut pl).await.unwrap();
+
+            let mut field = next(&mut form).await.unwrap().unwrap();
+            assert_eq!(field.name(), Some("text"));
+            assert_eq!(field.filename(), None);
+            assert_eq!(field.content_type(), &mime::TEXT_PLAIN);
+            let mut data = BytesMut::new();
+            while let Some(chunk) = next(&mut field).await {
+                data.extend_from_slice(&chunk.unwrap());
+            }
+            assert_eq!(&data[..], b"test");
+
+            let mut field = next(&mut form).await.unwrap().unwrap();
+            assert_eq!(field.name(), Some("file"));
+            assert_eq!(field.filename(), Some("fn \"1\".txt"));
+            assert_eq!(field.content_type(), &mime::APPLICATION_OCTET_STREAM);
+            assert_eq!(field.headers().len(), 2);
+            let mut data = BytesMut::new();
+            while let Some(chunk) = next(&mut field).await {
+                data.extend_from_slice(&chunk.unwrap());
+            }
+            assert_eq!(&data[..], b"data\r\n--abbc");
+
+            assert!(next(&mut form).await.is_none());
+            assert!(next(&mut field).await.is_none());
+        }
+    }
+
+    #[crate::rt_test]
+    async fn test_skip_field() {
+        let (req, mut pl) = request(5);
+        let mut form = from_request::<Multipart>(&req, &mut pl).await.unwrap();
+
+        let mut first = next(&mut form).await.unwrap().unwrap();
+        let second = next(&mut form).await.unwrap().unwrap();
+        assert_eq!(second.name(), Some("file"));
+        assert!(next(&mut first).await.is_none());
+        assert!(next(&mut form).await.is_none());
+    }
+
+    #[crate::rt_test]
+    async fn test_limits() {
+        let (req, mut pl) = request(64);
+        let config = MultipartConfig::default().limit(4).field_limit("file", 5);
+        let mut form = Multipart::new(&req, &mut pl, config).unwrap();
+
+        let mut field = next(&mut form).await.unwrap().unwrap();
+        assert_eq!(&next(&mut field).await.unwrap().unwrap()[..], b"test");
+
+        let mut field = next(&mut form).await.unwrap().unwrap();
+        let mut res = Ok(());
+        while let Some(chunk) = next(&mut field).await {
+            if let Err(e) = chunk {
+                res = Err(e);
+            }
+        }
+        match res {
+            Err(MultipartError::Overflow { name, limit }) => {
+                assert_eq!(name, "file");
+                assert_eq!(limit, 5);
+            }
+            _ => panic!("overflow is expected"),
+        }
+        assert!(next(&mut form).await.is_none());
+    }
+
+    #[crate::rt_test]
+    async fn test_max_fields() {
+        let (req, mut pl) = request(64);
+        let config = MultipartConfig::default().max_fields(1);
+        let mut form = Multipart::new(&req, &mut pl, config).unwrap();
+
+        let field = next(&mut form).await.unwrap().unwrap();
+        assert_eq!(field.name(), Some("text"));
+        match next(&mut form).await {
+            Some(Err(MultipartError::TooManyFields(1))) => (),
+            _ => panic!("too many fields error is expected"),
+        }
+        assert!(next(&mut form).await.is_none());
+    }
+
+    #[crate::rt_test]
+    async fn test_errors() {
+        let (req, mut pl) = TestRequest::default().to_http_parts();
+        match from_request::<Multipart>(&req, &mut pl).await {
+            Err(MultipartError::ContentType) => (),
+            _ => panic!("content type error is expected"),
+        }
+
+        let (req, mut pl) =
+            TestRequest::with_header(header::CONTENT_TYPE, "multipart/form-data")
+                .to_http_parts();
+        match from_request::<Multipart>(&req, &mut pl).await {
+            Err(MultipartError::Boundary) => (),
+            _ => panic!("boundary error is expected"),
+        }
+
+        let (req, mut pl) = TestRequest::with_header(
+            header::CONTENT_TYPE,
+            "multipart/form-data; boundary=b",
+        )
+        .set_payload(Bytes::from_static(
+            b"--b\r\nContent-Type: text/plain\r\n\r\ntest data",
+        ))
+        .to_http_parts();
+        let mut form = from_request::<Multipart>(&req, &mut pl).await.unwrap();
+        let mut field = next(&mut form).await.unwrap().unwrap();
+        assert_eq!(&next(&mut field).await.unwrap().unwrap()[..], b"test");
+        match next(&mut field).await {
+            Some(Err(MultipartError::Incomplete)) => (),
+            _ => panic!("incomplete error is expected"),
+        }
+        assert!(next(&mut form).await.is_none());
+
+        let (req, mut pl) = TestRequest::with_header(
+            header::CONTENT_TYPE,
+            "multipart/form-data; boundary=b",
+        )
+        .set_payload(Bytes::from_static(b"--b\r\nbad header\r\n\r\n"))
+        .to_http_parts();
+        let mut form = from_request::<Multipart>(&req, &mut pl).await.unwrap();
+        match next(&mut form).await {
+            Some(Err(MultipartError::Headers)) => (),
+            _ => panic!("headers error is expected"),
+        }
+    }
+
+    #[test]
+    fn test_parse_disposition() {
+        assert_eq!(
+            parse_disposition("form-data; name=\"field\"; filename=\"a;b.txt\""),
+            (Some("field".to_string()), Some("a;b.txt".to_string()))
+        );
+        assert_eq!(
+            parse_disposition("form-data; NAME=field ; filename=file.txt"),
+            (Some("field".to_string()), Some("file.txt".to_string()))
+        );
+        assert_eq!(parse_disposition("form-data"), (None, None));
+    }
+
+    #[test]
+    fn test_sanitize_filename() {
+        assert_eq!(sanitize_filename("file.txt"), Some("file.txt".to_string()));
+        assert_eq!(
+            sanitize_filename("../../etc/passwd"),
+            Some("passwd".to_string())
+        );
+        assert_eq!(
+            sanitize_filename("C:\\dir\\file.txt"),
+            Some("file.txt".to_string())
+        );
+        assert_eq!(sanitize_filename("a\r\nb.txt"), Some("ab.txt".to_string()));
+        assert_eq!(sanitize_filename("dir/.."), None);
+        assert_eq!(sanitize_filename(""), None);
+    }
+}
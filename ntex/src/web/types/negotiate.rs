@@ -0,0 +1,606 @@
+//! Content negotiation extractor/responder
+use std::{error::Error, fmt, future::Future, marker::PhantomData, ops, pin::Pin};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+#[cfg(feature = "compress")]
+use crate::http::encoding::Decoder;
+use crate::http::header::{HeaderValue, ACCEPT, CONTENT_LENGTH, CONTENT_TYPE, VARY};
+use crate::http::{Payload, Response, StatusCode};
+use crate::util::{next, BytesMut};
+use crate::web::error::{ErrorContainer, ErrorRenderer, NegotiateError};
+use crate::web::responder::{Ready, Responder};
+use crate::web::{FromRequest, HttpRequest};
+
+/// Serialization format.
+///
+/// Custom formats could be used with `Negotiate` by implementing this trait.
+///
+/// ```rust
+/// use ntex::web::types::Format;
+///
+/// /// `application/x-www-form-urlencoded` format
+/// struct UrlencodedFormat;
+///
+/// impl Format for UrlencodedFormat {
+///     const MEDIA_TYPE: &'static str = "application/x-www-form-urlencoded";
+///
+///     fn serialize<T: serde::Serialize>(value: &T) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+///         Ok(serde_urlencoded::to_string(value)?.into_bytes())
+///     }
+///
+///     fn deserialize<T: serde::de::DeserializeOwned>(data: &[u8]) -> Result<T, Box<dyn std::error::Error>> {
+///         Ok(serde_urlencoded::from_bytes(data)?)
+///     }
+/// }
+/// ```
+pub trait Format {
+    /// Media type of the format, i.e. `application/json`
+    const MEDIA_TYPE: &'static str;
+
+    /// Serialize value
+    fn serialize<T: Serialize>(value: &T) -> Result<Vec<u8>, Box<dyn Error>>;
+
+    /// Deserialize value
+    fn deserialize<T: DeserializeOwned>(data: &[u8]) -> Result<T, Box<dyn Error>>;
+}
+
+/// Set of serialization formats.
+///
+/// Trait is implemented for tuples of up to 6 formats, formats are
+/// listed in order of preference.
+pub trait Formats: 'static {
+    /// Supported media types in order of preference
+    fn media_types() -> Vec<&'static str>;
+
+    /// Serialize value with format at specified index
+    fn serialize<T: Serialize>(idx: usize, value: &T)
+        -> Result<Vec<u8>, Box<dyn Error>>;
+
+    /// Deserialize value with format at specified index
+    fn deserialize<T: DeserializeOwned>(
+        idx: usize,
+        data: &[u8],
+    ) -> Result<T, Box<dyn Error>>;
+}
+
+/// `application/json` format
+#[derive(Debug)]
+pub struct JsonFormat;
+
+impl Format for JsonFormat {
+    const MEDIA_TYPE: &'static str = "application/json";
+
+    fn serialize<T: Serialize>(value: &T) -> Result<Vec<u8>, Box<dyn Error>> {
+        Ok(serde_json::to_vec(value)?)
+    }
+
+    fn deserialize<T: DeserializeOwned>(data: &[u8]) -> Result<T, Box<dyn Error>> {
+        Ok(serde_json::from_slice(data)?)
+    }
+}
+
+#[cfg(feature = "cbor")]
+/// `application/cbor` format
+#[derive(Debug)]
+pub struct CborFormat;
+
+#[cfg(feature = "cbor")]
+impl Format for CborFormat {
+    const MEDIA_TYPE: &'static str = "application/cbor";
+
+    fn serialize<T: Serialize>(value: &T) -> Result<Vec<u8>, Box<dyn Error>> {
+        Ok(serde_cbor::to_vec(value)?)
+    }
+
+    fn deserialize<T: DeserializeOwned>(data: &[u8]) -> Result<T, Box<dyn Error>> {
+        Ok(serde_cbor::from_slice(data)?)
+    }
+}
+
+#[cfg(feature = "msgpack")]
+/// `application/msgpack` format
+#[derive(Debug)]
+pub struct MsgPackFormat;
+
+#[cfg(feature = "msgpack")]
+impl Format for MsgPackFormat {
+    const MEDIA_TYPE: &'static str = "application/msgpack";
+
+    fn serialize<T: Serialize>(value: &T) -> Result<Vec<u8>, Box<dyn Error>> {
+        Ok(rmp_serde::to_vec_named(value)?)
+    }
+
+    fn deserialize<T: DeserializeOwned>(data: &[u8]) -> Result<T, Box<dyn Error>> {
+        Ok(rmp_serde::from_slice(data)?)
+    }
+}
+
+#[cfg(all(feature = "cbor", feature = "msgpack"))]
+/// Default formats
+pub type DefaultFormats = (JsonFormat, CborFormat, MsgPackFormat);
+
+#[cfg(all(feature = "cbor", not(feature = "msgpack")))]
+/// Default formats
+pub type DefaultFormats = (JsonFormat, CborFormat);
+
+#[cfg(all(not(feature = "cbor"), feature = "msgpack"))]
+/// Default formats
+pub type DefaultFormats = (JsonFormat, MsgPackFormat);
+
+#[cfg(all(not(feature = "cbor"), not(feature = "msgpack")))]
+/// Default formats
+pub type DefaultFormats = (JsonFormat,);
+
+/// Content negotiation extractor/responder.
+///
+/// As a responder, `Negotiate<T>` selects best format for `Accept` request
+/// header and serializes `T` with it. If request does not contain `Accept`
+/// header, first format is used. If none of formats is acceptable,
+/// *406 Not Acceptable* response is returned.
+///
+/// As an extractor, `Negotiate<T>` deserializes request payload with format
+/// matching `Content-Type` request header, *415 Unsupported Media Type*
+/// error is returned for unknown content types. Payload size is limited
+/// by `NegotiateConfig`, default limit is 32kB.
+///
+/// By default json format is supported, `cbor` and `msgpack` features
+/// enable `application/cbor` and `application/msgpack` formats. Set of
+/// formats is defined by the second type parameter.
+///
+/// ```rust
+/// use ntex::web::{self, types::Negotiate};
+///
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct MyObj {
+///     name: String,
+/// }
+///
+/// async fn index(obj: Negotiate<MyObj>) -> Negotiate<MyObj> {
+///     Negotiate::new(MyObj { name: obj.name.to_uppercase() })
+/// }
+///
+/// fn main() {
+///     let app = web::App::new().service(
+///         web::resource("/index.html").route(web::post().to(index))
+///     );
+/// }
+/// ```
+pub struct Negotiate<T, F = DefaultFormats> {
+    value: T,
+    _t: PhantomData<F>,
+}
+
+impl<T, F> Negotiate<T, F> {
+    /// Create new `Negotiate` instance
+    pub fn new(value: T) -> Self {
+        Negotiate {
+            value,
+            _t: PhantomData,
+        }
+    }
+
+    /// Deconstruct to an inner value
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T, F> ops::Deref for Negotiate<T, F> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T, F> ops::DerefMut for Negotiate<T, F> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+impl<T: fmt::Debug, F> fmt::Debug for Negotiate<T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Negotiate").field(&self.value).finish()
+    }
+}
+
+impl<T, F, Err> Responder<Err> for Negotiate<T, F>
+where
+    T: Serialize,
+    F: Formats,
+    Err: ErrorRenderer,
+    Err::Container: From<NegotiateError>,
+{
+    type Error = NegotiateError;
+    type Future = Ready<Response>;
+
+    fn respond_to(self, req: &HttpRequest) -> Self::Future {
+        let media_types = F::media_types();
+        let accept = req.headers().get(ACCEPT).and_then(|v| v.to_str().ok());
+
+        let res = match select(&media_types, accept) {
+            Some(idx) => F::serialize(idx, &self.value)
+                .map(|body| (media_types[idx], body))
+                .map_err(NegotiateError::Serialize),
+            None => Err(NegotiateError::NotAcceptable),
+        };
+
+        let mut res = match res {
+            Ok((ctype, body)) => Response::build(StatusCode::OK)
+                .content_type(ctype)
+                .body(body),
+            Err(e) => Err::Container::from(e).error_response(req),
+        };
+        res.headers_mut()
+            .append(VARY, HeaderValue::from_static("accept"));
+        res.into()
+    }
+}
+
+/// Select best media type for `Accept` header value
+fn select(media_types: &[&str], accept: Option<&str>) -> Option<usize> {
+    let accept = if let Some(accept) = accept {
+        accept
+    } else {
+        return Some(0);
+    };
+
+    let mut best: Option<(usize, f32)> = None;
+    for (idx, media_type) in media_types.iter().enumerate() {
+        // most specific matching range defines quality
+        let mut quality: Option<(u8, f32)> = None;
+        for item in accept.split(',') {
+            let mut parts = item.split(';');
+            let range = parts.next().unwrap_or("").trim();
+            let q = parts
+                .filter_map(|p| {
+                    let p = p.trim();
+                    if p.starts_with("q=") || p.starts_with("Q=") {
+                        p[2..].trim().parse::<f32>().ok()
+                    } else {
+                        None
+                    }
+                })
+                .next()
+                .unwrap_or(1.0);
+
+            let specificity = if range.eq_ignore_ascii_case(media_type) {
+                2
+            } else if range == "*/*" {
+                0
+            } else if range.ends_with("/*")
+                && media_type
+                    .split('/')
+                    .next()
+                    .map_or(false, |t| t.eq_ignore_ascii_case(&range[..range.len() - 2]))
+            {
+                1
+            } else {
+                continue;
+            };
+            if quality.map_or(true, |(s, _)| specificity > s) {
+                quality = Some((specificity, q));
+            }
+        }
+
+        if let Some((_, q)) = quality {
+            if q > 0.0 && best.map_or(true, |(_, best_q)| q > best_q) {
+                best = Some((idx, q));
+            }
+        }
+    }
+    best.map(|(idx, _)| idx)
+}
+
+impl<T, F, Err> FromRequest<Err> for Negotiate<T, F>
+where
+    T: DeserializeOwned + 'static,
+    F: Formats,
+    Err: ErrorRenderer,
+{
+    type Error = NegotiateError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let limit = req
+            .app_data::<NegotiateConfig>()
+            .map(|c| c.limit)
+            .unwrap_or(32768);
+
+        // select format by content type
+        let ctype = req
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.split(';').next())
+            .map(|v| v.trim());
+        let idx = ctype.and_then(|ctype| {
+            F::media_types()
+                .iter()
+                .position(|m| m.eq_ignore_ascii_case(ctype))
+        });
+        let idx = match idx {
+            Some(idx) => idx,
+            None => {
+                return Box::pin(async { Err(NegotiateError::UnsupportedMediaType) })
+            }
+        };
+
+        let len = req
+            .headers()
+            .get(&CONTENT_LENGTH)
+            .and_then(|l| l.to_str().ok())
+            .and_then(|s| s.parse::<usize>().ok());
+        if len.map_or(false, |len| len > limit) {
+            return Box::pin(async { Err(NegotiateError::Overflow) });
+        }
+
+        #[cfg(feature = "compress")]
+        let mut stream = Decoder::from_headers(payload.take(), req.headers());
+        #[cfg(not(feature = "compress"))]
+        let mut stream = payload.take();
+
+        Box::pin(async move {
+            let mut body = BytesMut::with_capacity(8192);
+
+            while let Some(item) = next(&mut stream).await {
+                let chunk = item?;
+                if (body.len() + chunk.len()) > limit {
+                    return Err(NegotiateError::Overflow);
+                } else {
+                    body.extend_from_slice(&chunk);
+                }
+            }
+            F::deserialize(idx, &body)
+                .map(Negotiate::new)
+                .map_err(NegotiateError::Deserialize)
+        })
+    }
+}
+
+/// Negotiate extractor configuration
+///
+/// ```rust
+/// use ntex::web::{self, types::{Negotiate, NegotiateConfig}, App};
+///
+/// #[derive(serde::Deserialize)]
+/// struct Info {
+///     username: String,
+/// }
+///
+/// async fn index(info: Negotiate<Info>) -> String {
+///     format!("Welcome {}!", info.username)
+/// }
+///
+/// fn main() {
+///     let app = App::new().service(
+///         web::resource("/index.html")
+///             .app_data(NegotiateConfig::default().limit(4096))
+///             .route(web::post().to(index))
+///     );
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct NegotiateConfig {
+    limit: usize,
+}
+
+impl NegotiateConfig {
+    /// Change max size of payload. By default max size is 32Kb
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+}
+
+impl Default for NegotiateConfig {
+    fn default() -> Self {
+        NegotiateConfig { limit: 32768 }
+    }
+}
+
+macro_rules! formats_tuple ({ $(($n:tt, $T:ident)),+} => {
+    impl<$($T: Format + 'static,)+> Formats for ($($T,)+) {
+        fn media_types() -> Vec<&'static str> {
+            vec![$($T::MEDIA_TYPE,)+]
+        }
+
+        fn serialize<T: Serialize>(idx: usize, value: &T) -> Result<Vec<u8>, Box<dyn Error>> {
+            match idx {
+                $($n => $T::serialize(value),)+
+                _ => unreachable!(),
+            }
+        }
+
+        fn deserialize<T: DeserializeOwned>(idx: usize, data: &[u8]) -> Result<T, Box<dyn Error>> {
+            match idx {
+                $($n => $T::deserialize(data),)+
+                _ => unreachable!(),
+            }
+        }
+    }
+});
+
+#[rustfmt::skip]
+mod m {
+    use super::*;
+
+    formats_tuple!((0, A));
+    formats_tuple!((0, A), (1, B));
+    formats_tuple!((0, A), (1, B), (2, C));
+    formats_tuple!((0, A), (1, B), (2, C), (3, D));
+    formats_tuple!((0, A), (1, B), (2, C), (3, D), (4, E));
+    formats_tuple!((0, A), (1, B), (2, C), (3, D), (4, E), (5, F));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::header;
+    use crate::web::test::{from_request, respond_to, TestRequest};
+
+    #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+    struct MyObject {
+        name: String,
+    }
+
+    struct UrlencodedFormat;
+
+    impl Format for UrlencodedFormat {
+        const MEDIA_TYPE: &'static str = "application/x-www-form-urlencoded";
+
+        fn serialize<T: Serialize>(value: &T) -> Result<Vec<u8>, Box<dyn Error>> {
+            Ok(serde_urlencoded::to_string(value)?.into_bytes())
+        }
+
+        fn deserialize<T: DeserializeOwned>(data: &[u8]) -> Result<T, Box<dyn Error>> {
+            Ok(serde_urlencoded::from_bytes(data)?)
+        }
+    }
+
+    #[test]
+    fn test_select() {
+        let types = ["application/json", "application/cbor", "text/plain"];
+        assert_eq!(select(&types, None), Some(0));
+        assert_eq!(select(&types, Some("*/*")), Some(0));
+        assert_eq!(select(&types, Some("application/cbor")), Some(1));
+        assert_eq!(select(&types, Some("text/*")), Some(2));
+        assert_eq!(
+            select(&types, Some("application/json;q=0.5, application/cbor")),
+            Some(1)
+        );
+        assert_eq!(
+            select(&types, Some("application/*;q=0.8, application/json;q=0.1")),
+            Some(1)
+        );
+        assert_eq!(select(&types, Some("*/*, application/json;q=0")), Some(1));
+        assert_eq!(select(&types, Some("image/png")), None);
+    }
+
+    #[crate::rt_test]
+    async fn test_responder() {
+        let obj = || {
+            Negotiate::<_, (JsonFormat, UrlencodedFormat)>::new(MyObject {
+                name: "test".to_string(),
+            })
+        };
+
+        let req = TestRequest::default().to_http_request();
+        let resp = respond_to(obj(), &req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/json"
+        );
+        assert_eq!(resp.headers().get(header::VARY).unwrap(), "accept");
+        assert_eq!(resp.body().get_ref(), b"{\"name\":\"test\"}");
+
+        let req = TestRequest::with_header(
+            header::ACCEPT,
+            "application/json;q=0.9, application/x-www-form-urlencoded",
+        )
+        .to_http_request();
+        let resp = respond_to(obj(), &req).await;
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/x-www-form-urlencoded"
+        );
+        assert_eq!(resp.body().get_ref(), b"name=test");
+
+        let req =
+            TestRequest::with_header(header::ACCEPT, "text/html").to_http_request();
+        let resp = respond_to(obj(), &req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_ACCEPTABLE);
+    }
+
+    #[crate::rt_test]
+    async fn test_extractor() {
+        let (req, mut pl) = TestRequest::with_header(
+            header::CONTENT_TYPE,
+            "application/x-www-form-urlencoded",
+        )
+        .set_payload("name=test")
+        .to_http_parts();
+        let s = from_request::<Negotiate<MyObject, (JsonFormat, UrlencodedFormat)>>(
+            &req, &mut pl,
+        )
+        .await
+        .unwrap();
+        assert_eq!(s.name, "test");
+
+        let (req, mut pl) =
+            TestRequest::with_header(header::CONTENT_TYPE, "application/json")
+                .set_payload("{\"name\": \"test\"}")
+                .to_http_parts();
+        let s = from_request::<Negotiate<MyObject>>(&req, &mut pl)
+            .await
+            .unwrap();
+        assert_eq!(s.into_inner().name, "test");
+
+        let (req, mut pl) = TestRequest::with_header(header::CONTENT_TYPE, "text/plain")
+            .set_payload("test")
+            .to_http_parts();
+        let err = from_request::<Negotiate<MyObject>>(&req, &mut pl)
+            .await
+            .err()
+            .unwrap();
+        assert!(matches!(err, NegotiateError::UnsupportedMediaType));
+
+        let (req, mut pl) =
+            TestRequest::with_header(header::CONTENT_TYPE, "application/json")
+                .data(NegotiateConfig::default().limit(4))
+                .set_payload("{\"name\": \"test\"}")
+                .to_http_parts();
+        let err = from_request::<Negotiate<MyObject>>(&req, &mut pl)
+            .await
+            .err()
+            .unwrap();
+        assert!(matches!(err, NegotiateError::Overflow));
+    }
+
+    #[cfg(all(feature = "cbor", feature = "msgpack"))]
+    #[crate::rt_test]
+    async fn test_formats() {
+        use crate::web::test::{call_service, init_service, read_body};
+        use crate::web::{self, App};
+
+        let srv = init_service(App::new().service(web::resource("/").route(
+            web::post().to(|obj: Negotiate<MyObject>| async move {
+                Negotiate::<_>::new(MyObject {
+                    name: obj.name.to_uppercase(),
+                })
+            }),
+        )))
+        .await;
+
+        let obj = MyObject {
+            name: "test".to_string(),
+        };
+        let req = TestRequest::post()
+            .header(header::CONTENT_TYPE, "application/cbor")
+            .header(header::ACCEPT, "application/msgpack")
+            .set_payload(serde_cbor::to_vec(&obj).unwrap())
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/msgpack"
+        );
+        let body = read_body(resp).await;
+        let res: MyObject = rmp_serde::from_slice(&body).unwrap();
+        assert_eq!(res.name, "TEST");
+
+        let req = TestRequest::post()
+            .header(header::CONTENT_TYPE, "application/msgpack")
+            .header(header::ACCEPT, "application/cbor")
+            .set_payload(rmp_serde::to_vec_named(&obj).unwrap())
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        let body = read_body(resp).await;
+        let res: MyObject = serde_cbor::from_slice(&body).unwrap();
+        assert_eq!(res.name, "TEST");
+    }
+}
@@ -66,6 +66,26 @@ impl<T> Path<T> {
     }
 }
 
+impl<T: de::DeserializeOwned> Path<T> {
+    /// Extract value of named dynamic segment from the request's path.
+    ///
+    /// Route macros use it for handler arguments named after
+    /// dynamic segments.
+    pub fn param(req: &HttpRequest, name: &str) -> Result<Self, PathError> {
+        PathDeserializer::new(req.match_info())
+            .param(name)
+            .map(|inner| Path { inner })
+            .map_err(move |e| {
+                log::debug!(
+                    "Failed during Path extractor deserialization. \
+                     Request path: {:?}",
+                    req.path()
+                );
+                PathError::from(e)
+            })
+    }
+}
+
 impl<T> AsRef<T> for Path<T> {
     fn as_ref(&self) -> &T {
         &self.inner
@@ -208,6 +228,22 @@ mod tests {
         assert!(from_request::<Path<MyStruct>>(&req, &mut pl).await.is_err());
     }
 
+    #[crate::rt_test]
+    async fn test_extract_param() {
+        let mut router = Router::<usize>::build();
+        router.path("/{key}/{value}/", 10).0.set_id(0);
+        let router = router.finish();
+
+        let mut req = TestRequest::with_uri("/name/32/").to_srv_request();
+        router.recognize(req.match_info_mut());
+
+        let (req, _) = req.into_parts();
+        assert_eq!(*Path::<u32>::param(&req, "value").unwrap(), 32);
+        assert_eq!(*Path::<String>::param(&req, "key").unwrap(), "name");
+        assert!(Path::<u32>::param(&req, "key").is_err());
+        assert!(Path::<u32>::param(&req, "unknown").is_err());
+    }
+
     #[crate::rt_test]
     async fn test_tuple_extract() {
         let mut router = Router::<usize>::build();
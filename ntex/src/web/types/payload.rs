@@ -101,6 +101,112 @@ impl<Err: ErrorRenderer> FromRequest<Err> for Payload {
     }
 }
 
+/// Payload stream extractor with flow control.
+///
+/// Extractor returns request's payload stream, that could be paused and
+/// resumed. Use it for proxies and upload endpoints that need to throttle
+/// clients.
+///
+/// ## Flow control
+///
+/// * For *http/1* requests, paused stream stops reading request body from
+///   the connection, so client gets blocked by tcp flow control once
+///   socket buffers are full.
+/// * For *http/2* requests, paused stream does not release stream's flow
+///   control capacity, so client stops sending data once stream's
+///   window is exhausted. Other streams of the connection are not affected.
+///
+/// Chunks received before pause are still returned by the stream. Paused
+/// stream returns `Poll::Pending` after buffered data is consumed, so
+/// stream must be resumed before it could complete.
+///
+/// ## Example
+///
+/// ```rust
+/// use std::time::Duration;
+/// use ntex::web::{self, error, types::PayloadStream, App, HttpResponse};
+///
+/// /// consume upload with at most one chunk per 10 millis
+/// async fn upload(mut body: PayloadStream) -> Result<HttpResponse, error::PayloadError> {
+///     let mut size = 0;
+///     while let Some(item) = ntex::util::next(&mut body).await {
+///         size += item?.len();
+///
+///         body.pause();
+///         ntex::rt::time::sleep(Duration::from_millis(10)).await;
+///         body.resume();
+///     }
+///     Ok(HttpResponse::Ok().body(format!("{} bytes", size)))
+/// }
+///
+/// fn main() {
+///     let app = App::new().service(
+///         web::resource("/upload").route(web::post().to(upload))
+///     );
+/// }
+/// ```
+#[derive(Debug)]
+pub struct PayloadStream {
+    pl: crate::http::Payload,
+    paused: bool,
+}
+
+impl PayloadStream {
+    /// Stop reading payload data from the peer
+    pub fn pause(&mut self) {
+        if !self.paused {
+            self.paused = true;
+            self.pl.pause();
+        }
+    }
+
+    /// Resume reading payload data from the peer
+    pub fn resume(&mut self) {
+        if self.paused {
+            self.paused = false;
+            self.pl.resume();
+        }
+    }
+
+    /// Check if payload stream is paused
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Deconstruct to a inner value
+    pub fn into_inner(self) -> crate::http::Payload {
+        self.pl
+    }
+}
+
+impl Stream for PayloadStream {
+    type Item = Result<Bytes, error::PayloadError>;
+
+    #[inline]
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.pl).poll_next(cx)
+    }
+}
+
+impl<Err: ErrorRenderer> FromRequest<Err> for PayloadStream {
+    type Error = Err::Container;
+    type Future = Ready<PayloadStream, Self::Error>;
+
+    #[inline]
+    fn from_request(
+        _: &HttpRequest,
+        payload: &mut crate::http::Payload,
+    ) -> Self::Future {
+        Ready::Ok(PayloadStream {
+            pl: payload.take(),
+            paused: false,
+        })
+    }
+}
+
 /// Request binary data from a request's payload.
 ///
 /// Loads request's payload and construct Bytes instance.
@@ -443,6 +549,26 @@ mod tests {
         assert_eq!(b, Bytes::from_static(b"hello=world"));
     }
 
+    #[crate::rt_test]
+    async fn test_payload_stream() {
+        let (req, mut pl) = TestRequest::with_header(header::CONTENT_LENGTH, "11")
+            .set_payload(Bytes::from_static(b"hello=world"))
+            .to_http_parts();
+
+        let mut s = from_request::<PayloadStream>(&req, &mut pl).await.unwrap();
+        assert!(!s.is_paused());
+
+        // buffered data is available for paused stream
+        s.pause();
+        assert!(s.is_paused());
+        let b = next(&mut s).await.unwrap().unwrap();
+        assert_eq!(b, Bytes::from_static(b"hello=world"));
+
+        s.resume();
+        assert!(!s.is_paused());
+        assert!(next(&mut s).await.is_none());
+    }
+
     #[crate::rt_test]
     async fn test_bytes() {
         let (req, mut pl) = TestRequest::with_header(header::CONTENT_LENGTH, "11")
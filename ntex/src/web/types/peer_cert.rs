@@ -0,0 +1,73 @@
+//! Tls peer certificate extractor
+use crate::http::Payload;
+use crate::util::Ready;
+use crate::web::error::{ErrorRenderer, PeerCertError};
+use crate::web::extract::FromRequest;
+use crate::web::httprequest::HttpRequest;
+
+pub use crate::http::PeerCert;
+
+/// Extract client certificate verified by tls acceptor.
+///
+/// Extraction fails with `PeerCertError::NotPresent` error if
+/// connection does not use tls or client did not provide certificate.
+/// Use `Option<PeerCert>` for optional certificates.
+///
+/// ```rust
+/// use ntex::web::{self, types::PeerCert, HttpResponse};
+///
+/// async fn index(cert: PeerCert) -> HttpResponse {
+///     HttpResponse::Ok().body(format!("cert size: {}", cert.der().len()))
+/// }
+///
+/// fn main() {
+///     let app = web::App::new().service(
+///         web::resource("/").route(web::get().to(index))
+///     );
+/// }
+/// ```
+impl<Err: ErrorRenderer> FromRequest<Err> for PeerCert {
+    type Error = PeerCertError;
+    type Future = Ready<Self, Self::Error>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        if let Some(cert) = req.extensions().get::<PeerCert>() {
+            Ready::Ok(cert.clone())
+        } else {
+            Ready::Err(PeerCertError::NotPresent)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::StatusCode;
+    use crate::web::test::{call_service, init_service, TestRequest};
+    use crate::web::{self, App, HttpResponse};
+
+    #[crate::rt_test]
+    async fn test_peer_cert() {
+        let (req, mut pl) = TestRequest::default().to_http_parts();
+        let res =
+            <PeerCert as FromRequest<web::DefaultError>>::from_request(&req, &mut pl)
+                .await;
+        assert_eq!(res, Err(PeerCertError::NotPresent));
+
+        req.extensions_mut()
+            .insert(PeerCert::from_der(&b"cert"[..]));
+        let cert =
+            <PeerCert as FromRequest<web::DefaultError>>::from_request(&req, &mut pl)
+                .await
+                .unwrap();
+        assert_eq!(cert.der(), b"cert");
+
+        let srv = init_service(App::new().service(web::resource("/").to(
+            |cert: PeerCert| async move { HttpResponse::Ok().body(cert.der().to_vec()) },
+        )))
+        .await;
+        let res = call_service(&srv, TestRequest::default().to_request()).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+}
@@ -8,6 +8,8 @@ use crate::web::error::{ErrorRenderer, QueryPayloadError};
 use crate::web::{FromRequest, HttpRequest};
 use crate::{http::Payload, util::Ready};
 
+use super::urlencoded::from_str_nested;
+
 /// Extract typed information from the request's query.
 ///
 /// **Note**: A query string consists of unordered `key=value` pairs, therefore it cannot
@@ -133,8 +135,17 @@ where
 
     #[inline]
     fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
-        serde_urlencoded::from_str::<T>(req.query_string())
-            .map(|val| Ready::Ok(Query(val)))
+        let nested = req
+            .app_data::<QueryConfig>()
+            .map(|c| c.nested)
+            .unwrap_or(false);
+        let res = if nested {
+            from_str_nested::<T>(req.query_string())
+        } else {
+            serde_urlencoded::from_str::<T>(req.query_string())
+        };
+
+        res.map(|val| Ready::Ok(Query(val)))
             .unwrap_or_else(move |e| {
                 let e = QueryPayloadError::Deserialize(e);
 
@@ -148,6 +159,54 @@ where
     }
 }
 
+/// Query extractor configuration
+///
+/// ```rust
+/// use ntex::web;
+///
+/// #[derive(serde::Deserialize)]
+/// struct Filter {
+///     name: Option<String>,
+///     age: Option<u32>,
+/// }
+///
+/// #[derive(serde::Deserialize)]
+/// struct Params {
+///     tag: Vec<String>,
+///     filter: Filter,
+/// }
+///
+/// // The correct request for this handler would be
+/// // `/index.html?tag=a&tag=b&filter[name]=ntex&filter[age]=5`
+/// async fn index(params: web::types::Query<Params>) -> String {
+///     format!("Tags: {:?}", params.tag)
+/// }
+///
+/// fn main() {
+///     let app = web::App::new()
+///         .app_data(web::types::QueryConfig::default().nested(true))
+///         .service(web::resource("/index.html").route(web::get().to(index)));
+/// }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct QueryConfig {
+    nested: bool,
+}
+
+impl QueryConfig {
+    /// Support repeated keys and nested structures.
+    ///
+    /// Repeated keys are deserialized to sequences, `tag=a&tag=b`,
+    /// bracket syntax is supported for sequences and maps,
+    /// `tag[]=a&tag[]=b`, `pos[0]=1&pos[1]=2`, `filter[name]=ntex`.
+    /// Query string could contain up to 1024 pairs, nesting depth
+    /// is limited to 16 levels. By default nested structures are not supported.
+    pub fn nested(mut self, value: bool) -> Self {
+        self.nested = value;
+        self
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use derive_more::Display;
@@ -194,4 +253,27 @@ mod tests {
         let s = s.into_inner();
         assert_eq!(s.id, "test1");
     }
+
+    #[crate::rt_test]
+    async fn test_nested() {
+        #[derive(serde::Deserialize, Debug)]
+        struct Tags {
+            tag: Vec<String>,
+        }
+
+        let (req, mut pl) = TestRequest::with_uri("/?tag=a&tag=b")
+            .data(QueryConfig::default().nested(true))
+            .to_http_parts();
+        let s = from_request::<Query<Tags>>(&req, &mut pl).await.unwrap();
+        assert_eq!(s.tag, vec!["a".to_string(), "b".to_string()]);
+
+        let (req, mut pl) = TestRequest::with_uri("/?tag[]=a")
+            .data(QueryConfig::default().nested(true))
+            .to_http_parts();
+        let s = from_request::<Query<Tags>>(&req, &mut pl).await.unwrap();
+        assert_eq!(s.tag, vec!["a".to_string()]);
+
+        let (req, mut pl) = TestRequest::with_uri("/?tag=a&tag=b").to_http_parts();
+        assert!(from_request::<Query<Tags>>(&req, &mut pl).await.is_err());
+    }
 }
@@ -0,0 +1,486 @@
+//! Urlencoded values deserializer
+use std::{collections::HashMap, iter};
+
+use encoding_rs::{Encoding, UTF_8};
+use percent_encoding::percent_decode;
+use serde::de::value::{Error, MapDeserializer, SeqDeserializer};
+use serde::de::{self, DeserializeOwned, IntoDeserializer, Unexpected};
+
+/// Decode urlencoded key or value
+///
+/// Returns `None` if decoded bytes are not valid in the specified encoding.
+pub(super) fn decode(s: &[u8], encoding: &'static Encoding) -> Option<String> {
+    let s: Vec<u8> = s
+        .iter()
+        .map(|b| if *b == b'+' { b' ' } else { *b })
+        .collect();
+    let s: Vec<u8> = percent_decode(&s).collect();
+    encoding
+        .decode_without_bom_handling_and_without_replacement(&s)
+        .map(|s| s.into_owned())
+}
+
+/// Max number of bracket segments in a key
+const MAX_DEPTH: usize = 16;
+
+/// Max number of pairs in urlencoded string
+const MAX_PAIRS: usize = 1024;
+
+/// Deserialize urlencoded string with support of repeated keys and
+/// bracket syntax, `a=1&a=2`, `a[]=1&a[]=2`, `a[0]=1&a[1]=2`, `a[b]=1`
+pub(super) fn from_str_nested<T: DeserializeOwned>(s: &str) -> Result<T, Error> {
+    let mut root = Map::default();
+    let mut count = 0;
+
+    for pair in s.as_bytes().split(|b| *b == b'&') {
+        if pair.is_empty() {
+            continue;
+        }
+        count += 1;
+        if count > MAX_PAIRS {
+            return Err(de::Error::custom(format_args!(
+                "number of pairs exceeds limit of {}",
+                MAX_PAIRS
+            )));
+        }
+        let (key, value) = match pair.iter().position(|b| *b == b'=') {
+            Some(pos) => (&pair[..pos], &pair[pos + 1..]),
+            None => (pair, &b""[..]),
+        };
+        let (key, value) = match (decode(key, UTF_8), decode(value, UTF_8)) {
+            (Some(key), Some(value)) => (key, value),
+            _ => return Err(de::Error::custom("invalid utf-8 sequence")),
+        };
+
+        let (name, path) = split_key(&key);
+        if path.len() > MAX_DEPTH {
+            return Err(de::Error::custom(format_args!(
+                "nesting depth of `{}` key exceeds limit of {}",
+                name, MAX_DEPTH
+            )));
+        }
+        insert(&mut root, name, &path, value)?;
+    }
+
+    T::deserialize(Value::Map(root))
+}
+
+/// Split key to name and bracket segments, `a[b][]` is `a`, `["b", ""]`
+fn split_key(key: &str) -> (&str, Vec<&str>) {
+    let start = match key.find('[') {
+        Some(pos) if pos > 0 && key.ends_with(']') => pos,
+        _ => return (key, Vec::new()),
+    };
+
+    let mut path = Vec::new();
+    let mut rest = &key[start..];
+    while let Some(seg) = rest.strip_prefix('[') {
+        match seg.find(']') {
+            Some(end) => {
+                path.push(&seg[..end]);
+                rest = &seg[end + 1..];
+            }
+            None => return (key, Vec::new()),
+        }
+    }
+    if rest.is_empty() {
+        (&key[..start], path)
+    } else {
+        (key, Vec::new())
+    }
+}
+
+fn insert(map: &mut Map, name: &str, path: &[&str], value: String) -> Result<(), Error> {
+    let idx = if let Some(idx) = map.index.get(name) {
+        *idx
+    } else {
+        let item = match path.first() {
+            None => Value::Seq(Vec::new()),
+            Some(&"") => Value::Seq(Vec::new()),
+            Some(_) => Value::Map(Map::default()),
+        };
+        map.index.insert(name.to_string(), map.items.len());
+        map.items.push((name.to_string(), item));
+        map.items.len() - 1
+    };
+
+    match (&mut map.items[idx].1, path.split_first()) {
+        // repeated key, `a=1&a=2`
+        (Value::Seq(ref mut items), None) => {
+            items.push(Value::Str(value));
+            Ok(())
+        }
+        // append, `a[]=1&a[]=2`
+        (Value::Seq(ref mut items), Some((&"", rest))) => {
+            if rest.is_empty() {
+                items.push(Value::Str(value));
+            } else {
+                let mut item = Map::default();
+                insert(&mut item, rest[0], &rest[1..], value)?;
+                items.push(Value::Map(item));
+            }
+            Ok(())
+        }
+        // nested, `a[b]=1`
+        (Value::Map(ref mut items), Some((key, rest))) if !key.is_empty() => {
+            insert(items, key, rest, value)
+        }
+        _ => Err(de::Error::custom(format_args!(
+            "conflicting definitions of `{}` key",
+            name
+        ))),
+    }
+}
+
+/// Decoded key or value of the urlencoded pair
+pub(super) struct Part(pub(super) String);
+
+impl<'de> IntoDeserializer<'de, Error> for Part {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+macro_rules! parse_value {
+    ($method:ident, $visit:ident) => {
+        fn $method<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where
+            V: de::Visitor<'de>,
+        {
+            match self.0.parse() {
+                Ok(val) => visitor.$visit(val),
+                Err(e) => Err(de::Error::custom(e)),
+            }
+        }
+    };
+}
+
+impl<'de> de::Deserializer<'de> for Part {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_string(self.0)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _: &'static str,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_enum(IntoDeserializer::<Self::Error>::into_deserializer(self.0))
+    }
+
+    parse_value!(deserialize_bool, visit_bool);
+    parse_value!(deserialize_i8, visit_i8);
+    parse_value!(deserialize_i16, visit_i16);
+    parse_value!(deserialize_i32, visit_i32);
+    parse_value!(deserialize_i64, visit_i64);
+    parse_value!(deserialize_u8, visit_u8);
+    parse_value!(deserialize_u16, visit_u16);
+    parse_value!(deserialize_u32, visit_u32);
+    parse_value!(deserialize_u64, visit_u64);
+    parse_value!(deserialize_f32, visit_f32);
+    parse_value!(deserialize_f64, visit_f64);
+
+    serde::forward_to_deserialize_any! {
+        char str string bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+/// Structured value of the urlencoded string
+enum Value {
+    Str(String),
+    Seq(Vec<Value>),
+    Map(Map),
+}
+
+/// Map entries in insertion order, indexed by key
+#[derive(Default)]
+struct Map {
+    items: Vec<(String, Value)>,
+    index: HashMap<String, usize>,
+}
+
+impl Value {
+    fn unexpected(&self) -> Unexpected<'_> {
+        match self {
+            Value::Str(ref s) => Unexpected::Str(s),
+            Value::Seq(_) => Unexpected::Seq,
+            Value::Map(_) => Unexpected::Map,
+        }
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Value {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+macro_rules! forward_value {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+            where
+                V: de::Visitor<'de>,
+            {
+                match self {
+                    // single value of the repeated key
+                    Value::Seq(mut items) if items.len() == 1 => {
+                        items.pop().unwrap().$method(visitor)
+                    }
+                    Value::Str(s) => de::Deserializer::$method(Part(s), visitor),
+                    val => Err(de::Error::invalid_type(val.unexpected(), &visitor)),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for Value {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self {
+            Value::Str(s) => visitor.visit_string(s),
+            Value::Seq(items) => {
+                visitor.visit_seq(SeqDeserializer::new(items.into_iter()))
+            }
+            Value::Map(map) => {
+                visitor.visit_map(MapDeserializer::new(map.items.into_iter()))
+            }
+        }
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self {
+            Value::Str(s) => {
+                visitor.visit_seq(SeqDeserializer::new(iter::once(Value::Str(s))))
+            }
+            Value::Seq(items) => {
+                visitor.visit_seq(SeqDeserializer::new(items.into_iter()))
+            }
+            // indexed, `a[1]=2&a[0]=1`
+            Value::Map(map) => {
+                let mut indexed = Vec::with_capacity(map.items.len());
+                for (key, item) in map.items {
+                    match key.parse::<usize>() {
+                        Ok(idx) => indexed.push((idx, item)),
+                        Err(_) => {
+                            return Err(de::Error::invalid_type(
+                                Unexpected::Map,
+                                &visitor,
+                            ))
+                        }
+                    }
+                }
+                indexed.sort_by_key(|(idx, _)| *idx);
+                visitor.visit_seq(SeqDeserializer::new(
+                    indexed.into_iter().map(|(_, item)| item),
+                ))
+            }
+        }
+    }
+
+    fn deserialize_tuple<V>(self, _: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        _: &'static str,
+        _: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self {
+            Value::Seq(mut items) if items.len() == 1 => items
+                .pop()
+                .unwrap()
+                .deserialize_enum(name, variants, visitor),
+            Value::Str(s) => {
+                de::Deserializer::deserialize_enum(Part(s), name, variants, visitor)
+            }
+            val => Err(de::Error::invalid_type(val.unexpected(), &visitor)),
+        }
+    }
+
+    forward_value! {
+        deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32
+        deserialize_i64 deserialize_u8 deserialize_u16 deserialize_u32
+        deserialize_u64 deserialize_f32 deserialize_f64 deserialize_char
+        deserialize_str deserialize_string
+    }
+
+    serde::forward_to_deserialize_any! {
+        bytes byte_buf unit unit_struct map struct identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Filter {
+        name: String,
+        ids: Vec<u32>,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Params {
+        tag: Vec<String>,
+        single: Vec<String>,
+        page: u32,
+        filter: Filter,
+        pos: (u8, u8),
+        opt: Option<Vec<String>>,
+        items: Vec<HashMap<String, String>>,
+    }
+
+    #[test]
+    fn test_nested() {
+        let params: Params = from_str_nested(
+            "tag=a&tag=b%20c&single=x&page=2&filter[name]=n&filter[ids][]=1\
+             &filter[ids][]=2&pos[1]=8&pos[0]=4&items[][a]=1&items[][b]=2",
+        )
+        .unwrap();
+        assert_eq!(
+            params,
+            Params {
+                tag: vec!["a".to_string(), "b c".to_string()],
+                single: vec!["x".to_string()],
+                page: 2,
+                filter: Filter {
+                    name: "n".to_string(),
+                    ids: vec![1, 2],
+                },
+                pos: (4, 8),
+                opt: None,
+                items: vec![
+                    vec![("a".to_string(), "1".to_string())]
+                        .into_iter()
+                        .collect(),
+                    vec![("b".to_string(), "2".to_string())]
+                        .into_iter()
+                        .collect(),
+                ],
+            }
+        );
+
+        let map: HashMap<String, String> = from_str_nested("a=1&b[=2&c]=3").unwrap();
+        assert_eq!(map["a"], "1");
+        assert_eq!(map["b["], "2");
+        assert_eq!(map["c]"], "3");
+
+        assert!(from_str_nested::<HashMap<String, String>>("a=1&a=2").is_err());
+        assert!(from_str_nested::<HashMap<String, String>>("a=1&a[b]=2").is_err());
+        assert!(from_str_nested::<Params>("page=x").is_err());
+        assert!(from_str_nested::<HashMap<String, String>>("a=%FF").is_err());
+    }
+
+    #[test]
+    fn test_split_key() {
+        assert_eq!(split_key("a"), ("a", vec![]));
+        assert_eq!(split_key("a[]"), ("a", vec![""]));
+        assert_eq!(split_key("a[b][c]"), ("a", vec!["b", "c"]));
+        assert_eq!(split_key("a[b]c"), ("a[b]c", vec![]));
+        assert_eq!(split_key("[a]"), ("[a]", vec![]));
+    }
+
+    #[test]
+    fn test_limits() {
+        let key = format!("a{}", "[b]".repeat(MAX_DEPTH));
+        let res: Result<HashMap<String, serde::de::IgnoredAny>, _> =
+            from_str_nested(&format!("{}=1", key));
+        assert!(res.is_ok());
+        let res: Result<HashMap<String, serde::de::IgnoredAny>, _> =
+            from_str_nested(&format!("{}[b]=1", key));
+        assert!(res.unwrap_err().to_string().contains("nesting depth"));
+
+        let query = (0..MAX_PAIRS)
+            .map(|i| format!("a{}={}", i, i))
+            .collect::<Vec<_>>()
+            .join("&");
+        let map: HashMap<String, String> = from_str_nested(&query).unwrap();
+        assert_eq!(map.len(), MAX_PAIRS);
+        let res: Result<HashMap<String, String>, _> =
+            from_str_nested(&format!("{}&b=1", query));
+        assert!(res.unwrap_err().to_string().contains("number of pairs"));
+    }
+}
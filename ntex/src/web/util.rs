@@ -4,7 +4,7 @@ use std::fmt;
 use ntex_router::IntoPattern;
 
 use crate::http::body::MessageBody;
-use crate::http::error::{BlockingError, ResponseError};
+use crate::http::error::ResponseError;
 use crate::http::header::ContentEncoding;
 use crate::http::{Method, Request, Response};
 use crate::{IntoServiceFactory, Service, ServiceFactory};
@@ -253,20 +253,6 @@ pub fn service<T: IntoPattern>(path: T) -> WebServiceAdapter {
     WebServiceAdapter::new(path)
 }
 
-/// Execute blocking function on a thread pool, returns future that resolves
-/// to result of the function execution.
-pub async fn block<F, I, E>(f: F) -> Result<I, BlockingError<E>>
-where
-    F: FnOnce() -> Result<I, E> + Send + 'static,
-    I: Send + 'static,
-    E: Send + std::fmt::Debug + 'static,
-{
-    match ntex_rt::task::spawn_blocking(f).await {
-        Ok(res) => res.map_err(BlockingError::Error),
-        Err(_) => Err(BlockingError::Canceled),
-    }
-}
-
 /// Create new http server with application factory.
 ///
 /// ```rust,no_run
@@ -11,10 +11,50 @@ use crate::service::{IntoServiceFactory, Service, ServiceFactory};
 use crate::web::{HttpRequest, HttpResponse};
 use crate::{channel::mpsc, rt, util::Bytes, ws, Sink, Stream};
 
+/// Websockets connection sink.
+///
+/// Sink is passed to the websockets service factory, it could be cloned
+/// and used for pushing messages to the peer with `send_message()` method.
 pub type WebSocketsSink =
     ws::StreamEncoder<mpsc::Sender<Result<Bytes, Box<dyn StdError>>>>;
 
 /// Do websocket handshake and start websockets service.
+///
+/// Service factory receives connection sink as config, service handles
+/// incoming frames and optionally responds with message.
+///
+/// ```rust
+/// use ntex::service::{fn_factory_with_config, fn_service};
+/// use ntex::web::{self, ws, App, HttpRequest};
+///
+/// async fn ws_index(
+///     req: HttpRequest,
+///     pl: web::types::Payload,
+/// ) -> Result<web::HttpResponse, web::Error> {
+///     ws::start::<_, _, _, web::Error>(
+///         req,
+///         pl,
+///         fn_factory_with_config(|sink: ws::WebSocketsSink| async move {
+///             // push message to the peer
+///             let _ = sink.send_message(ws::Message::Text("welcome".into()));
+///
+///             Ok::<_, web::Error>(fn_service(|frame| async move {
+///                 let msg = match frame {
+///                     ws::Frame::Ping(msg) => Some(ws::Message::Pong(msg)),
+///                     ws::Frame::Close(reason) => Some(ws::Message::Close(reason)),
+///                     _ => None,
+///                 };
+///                 Ok::<_, std::io::Error>(msg)
+///             }))
+///         }),
+///     )
+///     .await
+/// }
+///
+/// fn main() {
+///     let app = App::new().route("/ws", web::get().to(ws_index));
+/// }
+/// ```
 pub async fn start<T, F, S, Err>(
     req: HttpRequest,
     payload: S,
@@ -4,7 +4,7 @@ use std::{
 
 use super::{Codec, Frame, Message, ProtocolError};
 use crate::util::{Bytes, BytesMut};
-use crate::{codec::Decoder, codec::Encoder, Sink, Stream};
+use crate::{channel::mpsc, codec::Decoder, codec::Encoder, Sink, Stream};
 
 /// Stream error
 #[derive(Debug, Display)]
@@ -109,6 +109,27 @@ impl<S> StreamEncoder<S> {
     }
 }
 
+impl<E> StreamEncoder<mpsc::Sender<Result<Bytes, E>>> {
+    /// Encode and send message to the peer.
+    ///
+    /// Channel is unbounded, message is queued without waiting.
+    pub fn send_message(
+        &self,
+        item: Message,
+    ) -> Result<(), StreamError<mpsc::SendError<Result<Bytes, E>>>> {
+        let mut buf = BytesMut::new();
+        self.codec.borrow_mut().encode(item, &mut buf)?;
+        self.sink
+            .send(Ok(buf.freeze()))
+            .map_err(StreamError::Stream)
+    }
+
+    /// Check if peer is disconnected
+    pub fn is_closed(&self) -> bool {
+        self.sink.is_closed()
+    }
+}
+
 impl<S, E> Sink<Result<Message, E>> for StreamEncoder<S>
 where
     S: Sink<Result<Bytes, E>>,
@@ -169,9 +190,7 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{
-        channel::mpsc, util::next, util::poll_fn, util::send, util::ByteString,
-    };
+    use crate::{util::next, util::poll_fn, util::send, util::ByteString};
 
     #[crate::rt_test]
     async fn test_decoder() {
@@ -222,4 +241,23 @@ mod tests {
         assert_eq!(data, b"\x81\x04test".as_ref());
         assert!(next(&mut rx).await.is_none());
     }
+
+    #[crate::rt_test]
+    async fn test_encoder_send_message() {
+        let (tx, mut rx) = mpsc::channel::<Result<Bytes, ()>>();
+        let encoder = StreamEncoder::new(tx);
+
+        encoder
+            .send_message(Message::Text(ByteString::from_static("test")))
+            .unwrap();
+        let data = next(&mut rx).await.unwrap().unwrap();
+        assert_eq!(data, b"\x81\x04test".as_ref());
+        assert!(!encoder.is_closed());
+
+        drop(rx);
+        assert!(encoder.is_closed());
+        assert!(encoder
+            .send_message(Message::Text(ByteString::from_static("test")))
+            .is_err());
+    }
 }
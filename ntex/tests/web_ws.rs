@@ -118,3 +118,47 @@ async fn web_ws_client() {
 
     on_disconnect.await
 }
+
+#[ntex::test]
+async fn web_ws_send_message() {
+    let srv = test::server(|| {
+        App::new().service(web::resource("/").route(web::to(
+            |req: HttpRequest, pl: web::types::Payload| async move {
+                ws::start::<_, _, _, web::Error>(
+                    req,
+                    pl,
+                    fn_factory_with_config(|sink: ws::WebSocketsSink| async move {
+                        sink.send_message(ws::Message::Text(ByteString::from_static(
+                            "welcome",
+                        )))
+                        .unwrap();
+
+                        Ok::<_, web::Error>(fn_service(move |frame| {
+                            // push message from the service
+                            if let ws::Frame::Text(ref text) = frame {
+                                let text = String::from_utf8_lossy(text).to_uppercase();
+                                sink.send_message(ws::Message::Text(text.into()))
+                                    .unwrap();
+                            }
+                            service(frame)
+                        }))
+                    }),
+                )
+                .await
+            },
+        )))
+    });
+
+    let mut framed = srv.ws().await.unwrap().into_inner().1;
+    let item = framed.next().await.unwrap().unwrap();
+    assert_eq!(item, ws::Frame::Text(Bytes::from_static(b"welcome")));
+
+    framed
+        .send(ws::Message::Text(ByteString::from_static("text")))
+        .await
+        .unwrap();
+    let item = framed.next().await.unwrap().unwrap();
+    assert_eq!(item, ws::Frame::Text(Bytes::from_static(b"TEXT")));
+    let item = framed.next().await.unwrap().unwrap();
+    assert_eq!(item, ws::Frame::Text(Bytes::from_static(b"text")));
+}